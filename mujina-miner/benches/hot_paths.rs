@@ -0,0 +1,188 @@
+//! Benchmarks for the paths that run per nonce and per job.
+//!
+//! Every nonce a chip reports is decoded off the serial link and checked
+//! by rebuilding its block header, merkle root included, and every job
+//! sent to a chip goes out with a CRC. On small SBC hosts these compete
+//! with the rest of the daemon, so a regression here shows up as nonces
+//! backing up behind the codec.
+//!
+//! Run with `just bench`, or `cargo bench --bench hot_paths -- <filter>`
+//! for one group. Measurement is kept short so a run stays quick. Pass
+//! or fail floors live in the ignored `*_throughput` tests next to the
+//! code, which `just perf` (part of `just checks`) runs in release.
+
+use std::hint::black_box;
+use std::time::Duration;
+
+use bitcoin::Target;
+use bitcoin::block::Header as BlockHeader;
+use bytes::BytesMut;
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use tokio_util::codec::Decoder;
+
+use mujina_miner::asic::bm13xx::FrameCodec;
+use mujina_miner::asic::bm13xx::crc::{crc5, crc16};
+use mujina_miner::job_source::test_blocks::block_881423;
+use mujina_miner::job_source::{Extranonce2Range, MerkleRootTemplate};
+
+/// Nonce response from a Bitaxe Gamma running esp-miner.
+const NONCE_FRAME: [u8; 11] = [
+    0xAA, 0x55, 0x4C, 0x03, 0x52, 0x75, 0x0C, 0xD2, 0x05, 0xA2, 0x9C,
+];
+
+/// Version mask write from the same board's initialization.
+const WRITE_FRAME: [u8; 11] = [
+    0x55, 0xAA, 0x51, 0x09, 0x00, 0xA4, 0x90, 0x00, 0xFF, 0xFF, 0x1C,
+];
+
+/// Job sent to the chip in the same capture.
+const JOB_FRAME: [u8; 88] = [
+    0x55, 0xAA, 0x21, 0x56, 0x68, 0x01, 0x00, 0x00, 0x00, 0x00, 0x04, 0x3A, 0x02, 0x17, 0xD7, 0x68,
+    0x54, 0x68, 0x55, 0x19, 0xA7, 0xCB, 0x04, 0x4F, 0x88, 0x72, 0x63, 0x55, 0x91, 0x9E, 0x61, 0xA9,
+    0x8B, 0xCF, 0x71, 0xA0, 0xC2, 0x87, 0x95, 0xEA, 0x54, 0xDB, 0x8C, 0x36, 0x41, 0x4B, 0x06, 0xDD,
+    0xF5, 0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x96, 0x52, 0x01, 0x00, 0x1D, 0x39,
+    0x96, 0xBC, 0xA3, 0xF4, 0x67, 0x0D, 0xFC, 0xD4, 0xF2, 0x01, 0xC1, 0x62, 0xB9, 0x6D, 0xFD, 0x55,
+    0x64, 0x6B, 0x00, 0x00, 0x00, 0x20, 0x72, 0x1C,
+];
+
+/// Nonce frames decoded per iteration, about what a busy chain reports
+/// in a second.
+const FRAMES_PER_BATCH: usize = 1_000;
+
+/// Merkle template for block 881,423, with its 11 branches and the
+/// coinbase in the legacy format pools send.
+fn block_template() -> MerkleRootTemplate {
+    MerkleRootTemplate::new(
+        block_881423::coinbase1_legacy_bytes(),
+        block_881423::extranonce1_bytes().to_vec(),
+        Extranonce2Range::new(block_881423::EXTRANONCE2.size()).unwrap(),
+        block_881423::coinbase2_legacy_bytes(),
+        block_881423::MERKLE_BRANCHES.clone(),
+    )
+}
+
+/// The same template with the coinbase as mined, witness included, which
+/// can't use the cached coinbase prefix.
+fn segwit_block_template() -> MerkleRootTemplate {
+    MerkleRootTemplate::new(
+        block_881423::coinbase1_bytes().to_vec(),
+        block_881423::extranonce1_bytes().to_vec(),
+        Extranonce2Range::new(block_881423::EXTRANONCE2.size()).unwrap(),
+        block_881423::coinbase2_bytes().to_vec(),
+        block_881423::MERKLE_BRANCHES.clone(),
+    )
+}
+
+fn frame_codec(c: &mut Criterion) {
+    let mut stream = BytesMut::new();
+    for _ in 0..FRAMES_PER_BATCH {
+        stream.extend_from_slice(&NONCE_FRAME);
+    }
+
+    let mut group = c.benchmark_group("frame_codec");
+    group.throughput(Throughput::Elements(FRAMES_PER_BATCH as u64));
+    group.bench_function("decode_nonces", |b| {
+        let mut codec = FrameCodec::default();
+        b.iter_batched_ref(
+            || stream.clone(),
+            |buf| {
+                while let Some(response) = codec.decode(buf).unwrap() {
+                    black_box(response);
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    // A frame cut at every possible point, as reads off a serial port
+    // arrive
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("decode_split_nonce", |b| {
+        let mut codec = FrameCodec::default();
+        b.iter(|| {
+            let mut buf = BytesMut::new();
+            for &byte in &NONCE_FRAME {
+                buf.extend_from_slice(&[byte]);
+                if let Some(response) = codec.decode(&mut buf).unwrap() {
+                    black_box(response);
+                }
+            }
+        })
+    });
+    group.finish();
+}
+
+fn crc(c: &mut Criterion) {
+    let mut group = c.benchmark_group("crc");
+    let command = &WRITE_FRAME[2..WRITE_FRAME.len() - 1];
+    group.throughput(Throughput::Bytes(command.len() as u64));
+    group.bench_function("crc5_command", |b| b.iter(|| crc5(black_box(command))));
+
+    let job = &JOB_FRAME[2..JOB_FRAME.len() - 2];
+    group.throughput(Throughput::Bytes(job.len() as u64));
+    group.bench_function("crc16_job", |b| b.iter(|| crc16(black_box(job))));
+    group.finish();
+}
+
+fn merkle_root(c: &mut Criterion) {
+    let extranonce2 = *block_881423::EXTRANONCE2;
+
+    let mut group = c.benchmark_group("merkle_root");
+    for (name, template) in [
+        ("block_881423", block_template()),
+        ("segwit_coinbase", segwit_block_template()),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                template
+                    .compute_merkle_root(black_box(&extranonce2))
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn share_validation(c: &mut Criterion) {
+    let template = block_template();
+    let extranonce2 = *block_881423::EXTRANONCE2;
+    let target = Target::from_compact(*block_881423::BITS);
+    let header = |merkle_root| BlockHeader {
+        version: *block_881423::VERSION,
+        prev_blockhash: *block_881423::PREV_BLOCKHASH,
+        merkle_root,
+        time: block_881423::TIME,
+        bits: *block_881423::BITS,
+        nonce: black_box(block_881423::NONCE),
+    };
+    assert!(target.is_met_by(header(*block_881423::MERKLE_ROOT).block_hash()));
+
+    let mut group = c.benchmark_group("share_validation");
+    // What the hash thread does per nonce: rebuild the merkle root for
+    // the task's extranonce2, then hash the header
+    group.bench_function("with_merkle_root", |b| {
+        b.iter(|| {
+            let merkle_root = template.compute_merkle_root(&extranonce2).unwrap();
+            target.is_met_by(header(merkle_root).block_hash())
+        })
+    });
+    // The header alone, for a merkle root already known
+    group.bench_function("header_only", |b| {
+        b.iter(|| target.is_met_by(header(*block_881423::MERKLE_ROOT).block_hash()))
+    });
+    group.finish();
+}
+
+fn config() -> Criterion {
+    Criterion::default()
+        .sample_size(30)
+        .warm_up_time(Duration::from_millis(500))
+        .measurement_time(Duration::from_secs(2))
+}
+
+criterion_group! {
+    name = hot_paths;
+    config = config();
+    targets = frame_codec, crc, merkle_root, share_validation
+}
+criterion_main!(hot_paths);
@@ -0,0 +1,69 @@
+//! Embed build information for `mujina_miner::build_info`.
+//!
+//! Sets `MUJINA_GIT_HASH` (when built from a git checkout, or when set in
+//! the environment by a packager building from a tarball),
+//! `MUJINA_BUILD_TIMESTAMP` (Unix seconds, honoring `SOURCE_DATE_EPOCH`
+//! for reproducible builds) and `MUJINA_FEATURES` (enabled Cargo
+//! features, comma separated).
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=MUJINA_GIT_HASH");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    let git_hash = env::var("MUJINA_GIT_HASH").ok().or_else(git_hash);
+    if let Some(hash) = git_hash {
+        println!("cargo:rustc-env=MUJINA_GIT_HASH={hash}");
+    }
+
+    let timestamp = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        });
+    println!("cargo:rustc-env=MUJINA_BUILD_TIMESTAMP={timestamp}");
+
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(str::to_owned))
+        .map(|name| name.to_lowercase().replace('_', "-"))
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=MUJINA_FEATURES={}", features.join(","));
+}
+
+/// Short hash of the checked-out commit, if building from git.
+fn git_hash() -> Option<String> {
+    let git_dir = run_git(&["rev-parse", "--git-dir"])?;
+    // Rebuild when HEAD moves (checkout, commit)
+    println!("cargo:rerun-if-changed={git_dir}/HEAD");
+    if let Some(head_ref) = run_git(&["symbolic-ref", "-q", "HEAD"]) {
+        // A ref that has been packed lives in packed-refs instead; a
+        // missing path would make Cargo rerun this script every build
+        let loose = Path::new(&git_dir).join(&head_ref);
+        let watched = if loose.exists() {
+            loose
+        } else {
+            Path::new(&git_dir).join("packed-refs")
+        };
+        println!("cargo:rerun-if-changed={}", watched.display());
+    }
+    run_git(&["rev-parse", "--short=12", "HEAD"])
+}
+
+fn run_git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8(output.stdout).ok()?;
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_owned())
+}
@@ -0,0 +1,37 @@
+//! Feed arbitrary bytes to the host-to-chip command decoder.
+//!
+//! Same invariants as the response decoder: no panics, no errors, always
+//! progress. Any command that decodes must also survive re-encoding.
+
+#![no_main]
+
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use mujina_miner::asic::bm13xx::{CommandDecoder, FrameCodec};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Preamble plus the longest frame (JobFull).
+const MAX_FRAME_LEN: usize = 2 + 86;
+
+fuzz_target!(|data: &[u8]| {
+    let mut decoder = CommandDecoder;
+    let mut buf = BytesMut::from(data);
+
+    loop {
+        let before = buf.len();
+        match decoder.decode(&mut buf) {
+            Ok(Some(command)) => {
+                assert!(buf.len() < before);
+                let mut encoded = BytesMut::new();
+                FrameCodec::default()
+                    .encode(command, &mut encoded)
+                    .expect("decoded command re-encodes");
+            }
+            Ok(None) if buf.len() == before => break,
+            Ok(None) => {}
+            Err(e) => panic!("decoder returned error: {e}"),
+        }
+    }
+
+    assert!(buf.len() < MAX_FRAME_LEN);
+});
@@ -0,0 +1,31 @@
+//! Feed arbitrary bytes to the chip-to-host response decoder.
+//!
+//! The decoder must never panic, never return an error (which would end the
+//! stream), and must always make progress: each call either yields a frame,
+//! consumes bytes, or leaves fewer than one frame's worth buffered.
+
+#![no_main]
+
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use mujina_miner::asic::bm13xx::FrameCodec;
+use tokio_util::codec::Decoder;
+
+const FRAME_LEN: usize = 11;
+
+fuzz_target!(|data: &[u8]| {
+    let mut codec = FrameCodec::default();
+    let mut buf = BytesMut::from(data);
+
+    loop {
+        let before = buf.len();
+        match codec.decode(&mut buf) {
+            Ok(Some(_)) => assert!(buf.len() < before),
+            Ok(None) if buf.len() == before => break,
+            Ok(None) => {}
+            Err(e) => panic!("decoder returned error: {e}"),
+        }
+    }
+
+    assert!(buf.len() < FRAME_LEN);
+});
@@ -0,0 +1,202 @@
+//! Agent mode: serve a locally attached board to a remote controller.
+//!
+//! One controller can drive boards attached to several small computers.
+//! Each of them runs `mujina-minerd --agent`, which brings its board up as
+//! the daemon would but, instead of mining, offers the board's hash
+//! threads over the network ([`protocol`]). The controller lists the agent
+//! among its network boards (`agent@host:4029`); its
+//! [`RemoteBoard`](crate::board::remote::RemoteBoard) connects, and from
+//! then on the scheduler assigns work to the remote threads, their shares
+//! come back, and board commands reach the board, as if it were local.
+//!
+//! An agent serves one board, the first it finds; run one agent per
+//! board. It serves one controller at a time, and a controller that
+//! authenticates takes over from any other. With no controller connected
+//! the threads sit idle.
+
+pub mod protocol;
+mod server;
+
+use std::time::Duration;
+
+use anyhow::Context;
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, mpsc};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+pub use server::{AgentServer, LocalBoard};
+
+use crate::tracing::prelude::*;
+use crate::{
+    api::commands::{BoardCommand, CommandBus},
+    asic::hash_thread::HashThread,
+    backplane::Backplane,
+    config::{self, Config},
+    events::{BoardEvent, Boards, EventBus},
+    miner,
+    transport::{TransportEvent, UsbTransport},
+};
+
+/// Port agents listen on unless configured otherwise.
+pub const DEFAULT_PORT: u16 = 4029;
+
+/// How long after a board's first hash thread its others may follow.
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// A running agent.
+pub struct Agent {
+    shutdown: CancellationToken,
+    tracker: TaskTracker,
+    /// Keeps the backplane's board events open while no transport is
+    /// discovering boards
+    _transport_tx: mpsc::Sender<TransportEvent>,
+}
+
+impl Agent {
+    /// Bring up the local board and serve it once it is ready.
+    ///
+    /// Needs `boards.agent_token` for controllers to authenticate with.
+    pub async fn start(config: Config) -> anyhow::Result<Agent> {
+        let Config { agent, boards, .. } = config;
+        let token = boards
+            .agent_token
+            .clone()
+            .filter(|token| !token.is_empty())
+            .context("agent mode needs a token (boards.agent_token or --agent-token)")?;
+        let bind_addr = match agent.listen {
+            Some(addr) if addr.contains(':') => addr,
+            Some(addr) => format!("{addr}:{DEFAULT_PORT}"),
+            None => format!("127.0.0.1:{DEFAULT_PORT}"),
+        };
+        let listener = TcpListener::bind(&bind_addr)
+            .await
+            .with_context(|| format!("failed to listen on {bind_addr}"))?;
+        info!(addr = %bind_addr, "Agent listening.");
+
+        let usb_discovery = boards.usb_discovery.unwrap_or(true);
+        let simulate = boards.simulate.unwrap_or(false);
+        let profile = boards.profile.unwrap_or_default();
+        let burn_in_dir = boards.burn_in_dir();
+        config::install_board_config(boards);
+
+        let shutdown = CancellationToken::new();
+        let tracker = TaskTracker::new();
+        let (transport_tx, transport_rx) = mpsc::channel::<TransportEvent>(100);
+        let (thread_tx, thread_rx) = mpsc::channel::<Box<dyn HashThread>>(10);
+
+        if usb_discovery
+            && let Err(e) = UsbTransport::new(transport_tx.clone())
+                .start_discovery(shutdown.clone())
+                .await
+        {
+            error!("Failed to start USB discovery: {}", e);
+        }
+        miner::inject_cpu_miner(&transport_tx).await;
+        if simulate {
+            let event = TransportEvent::Simulated {
+                device_id: "sim-0".into(),
+            };
+            transport_tx.send(event).await?;
+        }
+
+        let events = EventBus::new();
+        let board_events = events.subscribe::<Boards>();
+        let (board_cmd_tx, board_cmd_rx) = mpsc::channel::<BoardCommand>(16);
+        let mut backplane = Backplane::new(transport_rx, thread_tx, events, board_cmd_rx)
+            .with_profile(profile)
+            .with_burn_in_dir(burn_in_dir);
+        tracker.spawn({
+            let shutdown = shutdown.clone();
+            async move {
+                tokio::select! {
+                    result = backplane.run().instrument(info_span!("backplane")) => {
+                        if let Err(e) = result {
+                            error!("Backplane error: {}", e);
+                        }
+                    }
+                    _ = shutdown.cancelled() => {}
+                }
+
+                backplane.shutdown_all_boards().await;
+            }
+        });
+
+        // The agent has no scheduler; only board commands are sent
+        let (scheduler_cmd_tx, _) = mpsc::channel(1);
+        let commands = CommandBus::new(scheduler_cmd_tx, board_cmd_tx);
+        tracker.spawn({
+            let shutdown = shutdown.clone();
+            async move {
+                let Some(board) = local_board(board_events, thread_rx, &shutdown).await else {
+                    return;
+                };
+                info!(
+                    board = %board.name,
+                    threads = board.threads.len(),
+                    "Serving board."
+                );
+                AgentServer::new(board, token, commands)
+                    .run(listener, shutdown)
+                    .instrument(info_span!("agent"))
+                    .await;
+            }
+        });
+        tracker.close();
+
+        Ok(Agent {
+            shutdown,
+            tracker,
+            _transport_tx: transport_tx,
+        })
+    }
+
+    /// Stop serving, shut the board down, and wait for both.
+    pub async fn stop(self) {
+        self.shutdown.cancel();
+        self.tracker.wait().await;
+    }
+}
+
+/// Wait for the first board and the hash threads it brings.
+async fn local_board(
+    mut board_events: broadcast::Receiver<BoardEvent>,
+    mut thread_rx: mpsc::Receiver<Box<dyn HashThread>>,
+    shutdown: &CancellationToken,
+) -> Option<LocalBoard> {
+    let (name, state_rx) = loop {
+        let event = tokio::select! {
+            event = board_events.recv() => event,
+            _ = shutdown.cancelled() => return None,
+        };
+        match event {
+            Ok(BoardEvent::Added { name, state_rx }) => break (name, state_rx),
+            Ok(BoardEvent::Removed { .. }) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    };
+
+    let mut threads = Vec::new();
+    tokio::select! {
+        thread = thread_rx.recv() => threads.extend(thread),
+        _ = shutdown.cancelled() => return None,
+    }
+    while let Ok(Some(thread)) = tokio::time::timeout(SETTLE_TIME, thread_rx.recv()).await {
+        threads.push(thread);
+    }
+
+    // Later boards stay up but unserved
+    tokio::spawn(async move {
+        while let Ok(event) = board_events.recv().await {
+            if let BoardEvent::Added { name, .. } = event {
+                warn!(board = %name, "Agent already serves a board; run one agent per board.");
+            }
+        }
+    });
+    tokio::spawn(async move { while thread_rx.recv().await.is_some() {} });
+
+    Some(LocalBoard {
+        name,
+        state_rx,
+        threads,
+    })
+}
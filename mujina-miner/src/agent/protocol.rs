@@ -0,0 +1,834 @@
+//! Wire protocol between an agent and the controller driving its board.
+//!
+//! Messages are JSON, one per frame, each frame prefixed with its length
+//! as a 4-byte big-endian integer. The agent speaks first:
+//!
+//! ```text
+//! agent                                  controller
+//!   | -- challenge {version, nonce} -------> |
+//!   | <------------ proof {nonce, hmac} ---- |
+//!   | -- accepted {hmac} ------------------> |
+//!   | == welcome {board, threads} =========> |
+//!   | <==== assign / go_idle / commands ==== |
+//!   | ==== shares, thread events, state ===> |
+//! ```
+//!
+//! Each end sends a fresh nonce and proves it holds the shared token with
+//! HMAC-SHA256, keyed with the token, over its role and both nonces; the
+//! token itself never crosses the wire, and neither end's proof passes
+//! for the other's or for another connection's. Both then derive a
+//! session key the same way, and every later frame (`==` above) ends
+//! with an HMAC over the sender's role, the frame's sequence number and
+//! its payload. A frame that is altered, injected, replayed, reordered or
+//! dropped fails the next check and ends the session.
+//!
+//! Frames aren't encrypted: anyone on the path can still read the jobs
+//! and shares. Reach agents through a tunnel (WireGuard, SSH) where that
+//! matters.
+//!
+//! Threads are addressed by their index in the welcome, tasks by a number
+//! the controller picks when assigning them. Jobs, shares and thread status
+//! travel as the [`crate::schema`] records.
+
+use bitcoin::hashes::{Hash, HashEngine, Hmac, HmacEngine, cmp::fixed_time_eq, sha256};
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+use crate::{
+    api_client::types::{BoardState, Profile},
+    asic::hash_thread::{HashTask, HashThread, HashThreadCapabilities, Share, ShareSender},
+    job_source::{Extranonce2Range, GeneralPurposeBits, JobTemplate},
+    schema::{self, JobRecord, SchemaError, ShareRecord, ThreadStatusRecord},
+    types::{Difficulty, HashRate, Target, Work},
+};
+
+/// Version of the protocol spoken by this build; both ends must match.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Largest frame either end accepts.
+const MAX_FRAME: usize = 1024 * 1024;
+
+/// Length of each end's handshake nonce in bytes.
+const NONCE_LEN: usize = 32;
+
+/// Length of the MAC ending each frame after the handshake.
+const MAC_LEN: usize = 32;
+
+/// A framed connection to the other end.
+pub type Connection<T> = Framed<T, LengthDelimitedCodec>;
+
+/// Errors on an agent connection.
+#[derive(Debug, thiserror::Error)]
+pub enum ProtocolError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("malformed message: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("connection closed")]
+    Closed,
+
+    #[error("unexpected message, expected {0}")]
+    Unexpected(&'static str),
+
+    #[error("protocol version {0} not supported")]
+    Version(u32),
+
+    #[error("authentication failed")]
+    AuthFailed,
+
+    #[error("message failed its integrity check")]
+    Integrity,
+
+    #[error("refused by agent: {0}")]
+    Refused(String),
+}
+
+/// Messages from the agent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentMessage {
+    /// Opens every connection; answered with a proof.
+    Challenge {
+        version: u32,
+        nonce: String,
+    },
+
+    /// The controller's proof checked out; the agent's own, over the
+    /// same nonces, for the controller to check.
+    Accepted {
+        hmac: String,
+    },
+
+    /// The controller authenticated; what it now drives.
+    Welcome {
+        board: BoardState,
+        threads: Vec<ThreadInfo>,
+    },
+
+    /// The controller didn't authenticate, or another took over.
+    Refused {
+        reason: String,
+    },
+
+    /// The board's state changed.
+    State {
+        board: BoardState,
+    },
+
+    /// A share found for a task.
+    Share {
+        thread: usize,
+        task: u64,
+        #[serde(flatten)]
+        share: RemoteShare,
+    },
+
+    Status {
+        thread: usize,
+        status: ThreadStatusRecord,
+    },
+
+    WorkDepletionWarning {
+        thread: usize,
+        estimated_remaining_ms: u64,
+    },
+
+    WorkExhausted {
+        thread: usize,
+        en2_searched: u64,
+    },
+
+    /// The thread is going away; nothing more comes from it.
+    GoingOffline {
+        thread: usize,
+    },
+
+    /// Answer to a request, carrying its error if it failed.
+    Reply {
+        id: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+
+    Pong {
+        id: u64,
+    },
+}
+
+/// Messages from the controller.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControllerMessage {
+    /// Answers the challenge, with the controller's own nonce.
+    Proof {
+        nonce: String,
+        hmac: String,
+    },
+
+    /// Pass on the scheduler's assignment parameters; answered by a reply.
+    Negotiate {
+        id: u64,
+        thread: usize,
+        max_ntime_roll: u32,
+    },
+
+    Assign(Box<Assignment>),
+
+    GoIdle {
+        thread: usize,
+    },
+
+    /// Board commands; each answered by a reply.
+    SetProfile {
+        id: u64,
+        profile: Profile,
+    },
+    SetFrequency {
+        id: u64,
+        mhz: Option<f32>,
+    },
+    SetFanTarget {
+        id: u64,
+        fan: String,
+        percent: Option<u8>,
+    },
+
+    Ping {
+        id: u64,
+    },
+}
+
+/// A hash thread as the controller sees it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThreadInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+    /// Hashes per second
+    pub hashrate: u64,
+    /// Version bits the hardware can roll, as a mask over the version
+    pub version_rolling_mask: u32,
+    pub max_ntime_roll: u32,
+    pub iterates_extranonce2: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reporting_difficulty: Option<Difficulty>,
+}
+
+impl ThreadInfo {
+    pub fn new(thread: &dyn HashThread) -> Self {
+        let capabilities = thread.capabilities();
+        Self {
+            name: thread.name().to_string(),
+            device_id: thread.device_id().map(str::to_string),
+            hashrate: capabilities.hashrate_estimate.0,
+            version_rolling_mask: capabilities.version_rolling.to_version_mask(),
+            max_ntime_roll: capabilities.max_ntime_roll,
+            iterates_extranonce2: capabilities.iterates_extranonce2,
+            reporting_difficulty: capabilities.reporting_difficulty,
+        }
+    }
+
+    pub fn capabilities(&self) -> HashThreadCapabilities {
+        HashThreadCapabilities {
+            hashrate_estimate: HashRate(self.hashrate),
+            version_rolling: GeneralPurposeBits::new(
+                ((self.version_rolling_mask >> 13) as u16).to_be_bytes(),
+            ),
+            max_ntime_roll: self.max_ntime_roll,
+            iterates_extranonce2: self.iterates_extranonce2,
+            reporting_difficulty: self.reporting_difficulty,
+        }
+    }
+}
+
+/// A task assigned to one of the agent's threads.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub thread: usize,
+    pub task: u64,
+    /// Whether the thread's earlier tasks are invalid
+    pub replace: bool,
+    pub job: JobRecord,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub en2_range: Option<RangeRecord>,
+    /// Starting extranonce2 as hex, two digits per byte of its size
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub en2: Option<String>,
+    /// Big-endian hex
+    pub share_target: String,
+    pub ntime: u32,
+}
+
+/// An extranonce2 range.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RangeRecord {
+    pub min: u64,
+    pub max: u64,
+    pub size: u8,
+}
+
+impl Assignment {
+    pub fn new(thread: usize, task: u64, replace: bool, hash_task: &HashTask) -> Self {
+        Self {
+            thread,
+            task,
+            replace,
+            job: JobRecord::from(hash_task.template.as_ref()),
+            en2_range: hash_task.en2_range.as_ref().map(|range| RangeRecord {
+                min: range.min,
+                max: range.max,
+                size: range.size,
+            }),
+            en2: hash_task.en2.map(|en2| en2.to_string()),
+            share_target: hex::encode(hash_task.share_target.to_be_bytes()),
+            ntime: hash_task.ntime,
+        }
+    }
+
+    /// The task to hand the thread, sending its shares to `share_tx`.
+    pub fn to_task(&self, share_tx: ShareSender) -> Result<HashTask, SchemaError> {
+        let en2_range = self
+            .en2_range
+            .map(|range| Extranonce2Range::new_range(range.min, range.max, range.size))
+            .transpose()
+            .map_err(|e| invalid("en2_range", e.to_string()))?;
+        Ok(HashTask {
+            template: JobTemplate::try_from(&self.job)?.into(),
+            en2_range,
+            en2: self
+                .en2
+                .as_deref()
+                .map(schema::parse_extranonce2)
+                .transpose()?,
+            share_target: Target::from_be_bytes(parse_u256("share_target", &self.share_target)?),
+            ntime: self.ntime,
+            share_tx,
+        })
+    }
+}
+
+/// A share as sent to the controller.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteShare {
+    #[serde(flatten)]
+    pub record: ShareRecord,
+    /// Big-endian hex
+    pub expected_work: String,
+}
+
+impl RemoteShare {
+    pub fn new(share: &Share, job_id: &str) -> Self {
+        Self {
+            record: ShareRecord {
+                job_id: job_id.to_string(),
+                nonce: share.nonce,
+                time: share.ntime,
+                version: share.version.to_consensus() as u32,
+                extranonce2: share.extranonce2.map(|en2| en2.to_string()),
+                device_id: None,
+                hash: share.hash.to_string(),
+            },
+            expected_work: hex::encode(share.expected_work.to_be_bytes()),
+        }
+    }
+
+    pub fn to_share(&self) -> Result<Share, SchemaError> {
+        let share = crate::job_source::Share::try_from(&self.record)?;
+        Ok(Share {
+            nonce: share.nonce,
+            hash: share.hash,
+            version: share.version,
+            ntime: share.time,
+            extranonce2: share.extranonce2,
+            expected_work: Work::from_be_bytes(parse_u256("expected_work", &self.expected_work)?),
+        })
+    }
+}
+
+fn invalid(field: &'static str, reason: String) -> SchemaError {
+    SchemaError::InvalidField { field, reason }
+}
+
+fn parse_u256(field: &'static str, text: &str) -> Result<[u8; 32], SchemaError> {
+    hex::decode(text)
+        .map_err(|e| invalid(field, e.to_string()))?
+        .try_into()
+        .map_err(|_| invalid(field, "must be 32 bytes".into()))
+}
+
+/// Frame a stream for the protocol.
+pub fn framed<T: AsyncRead + AsyncWrite>(stream: T) -> Connection<T> {
+    Framed::new(
+        stream,
+        LengthDelimitedCodec::builder()
+            .max_frame_length(MAX_FRAME)
+            .new_codec(),
+    )
+}
+
+/// Send one handshake message.
+async fn send<T, M>(conn: &mut Connection<T>, message: &M) -> Result<(), ProtocolError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+    M: Serialize,
+{
+    conn.send(Bytes::from(serde_json::to_vec(message)?)).await?;
+    Ok(())
+}
+
+/// Receive one handshake message, failing if the other end hung up.
+async fn recv<T, M>(conn: &mut Connection<T>) -> Result<M, ProtocolError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+    M: DeserializeOwned,
+{
+    match conn.next().await {
+        Some(frame) => Ok(serde_json::from_slice(&frame?)?),
+        None => Err(ProtocolError::Closed),
+    }
+}
+
+/// Which end of a connection something comes from.
+///
+/// Proofs and MACs cover it, so neither end's can be reflected back as
+/// the other's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Agent,
+    Controller,
+}
+
+impl Role {
+    fn label(self) -> &'static [u8] {
+        match self {
+            Role::Agent => b"mujina agent",
+            Role::Controller => b"mujina controller",
+        }
+    }
+
+    fn peer(self) -> Self {
+        match self {
+            Role::Agent => Role::Controller,
+            Role::Controller => Role::Agent,
+        }
+    }
+}
+
+/// HMAC-SHA256 keyed with `key` over `parts` in order.
+fn hmac(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut engine = HmacEngine::<sha256::Hash>::new(key);
+    for part in parts {
+        engine.input(part);
+    }
+    Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+}
+
+/// Proof that `role` holds `token`, bound to both ends' nonces.
+fn proof(token: &str, role: Role, agent_nonce: &[u8], controller_nonce: &[u8]) -> [u8; 32] {
+    hmac(
+        token.as_bytes(),
+        &[role.label(), agent_nonce, controller_nonce],
+    )
+}
+
+/// Check `role`'s proof in constant time.
+fn verify(
+    token: &str,
+    role: Role,
+    agent_nonce: &[u8],
+    controller_nonce: &[u8],
+    hmac: &str,
+) -> bool {
+    hex::decode(hmac).is_ok_and(|hmac| {
+        hmac.len() == 32 && fixed_time_eq(&proof(token, role, agent_nonce, controller_nonce), &hmac)
+    })
+}
+
+/// Key for the session both nonces opened.
+fn session_key(token: &str, agent_nonce: &[u8], controller_nonce: &[u8]) -> [u8; 32] {
+    hmac(
+        token.as_bytes(),
+        &[b"mujina session", agent_nonce, controller_nonce],
+    )
+}
+
+/// Decode a nonce sent by the other end.
+fn parse_nonce(nonce: &str) -> Result<Vec<u8>, ProtocolError> {
+    hex::decode(nonce)
+        .ok()
+        .filter(|nonce| nonce.len() == NONCE_LEN)
+        .ok_or(ProtocolError::Unexpected("32-byte hex nonce"))
+}
+
+/// An authenticated connection.
+///
+/// Every frame ends with a MAC keyed with the session key over the
+/// sender's role, the frame's sequence number in its direction and the
+/// payload; [`Session::recv`] fails on the first frame that doesn't
+/// check out.
+pub struct Session<T> {
+    conn: Connection<T>,
+    key: [u8; 32],
+    /// This end
+    role: Role,
+    sent: u64,
+    received: u64,
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Session<T> {
+    fn new(conn: Connection<T>, key: [u8; 32], role: Role) -> Self {
+        Self {
+            conn,
+            key,
+            role,
+            sent: 0,
+            received: 0,
+        }
+    }
+
+    fn mac(&self, role: Role, sequence: u64, payload: &[u8]) -> [u8; MAC_LEN] {
+        hmac(&self.key, &[role.label(), &sequence.to_be_bytes(), payload])
+    }
+
+    /// Send one message.
+    pub async fn send<M: Serialize>(&mut self, message: &M) -> Result<(), ProtocolError> {
+        let mut frame = serde_json::to_vec(message)?;
+        let mac = self.mac(self.role, self.sent, &frame);
+        frame.extend_from_slice(&mac);
+        self.conn.send(Bytes::from(frame)).await?;
+        self.sent += 1;
+        Ok(())
+    }
+
+    /// Receive one message, failing if the other end hung up or the
+    /// frame's MAC doesn't check out.
+    pub async fn recv<M: DeserializeOwned>(&mut self) -> Result<M, ProtocolError> {
+        let frame = match self.conn.next().await {
+            Some(frame) => frame?,
+            None => return Err(ProtocolError::Closed),
+        };
+        let split = frame
+            .len()
+            .checked_sub(MAC_LEN)
+            .ok_or(ProtocolError::Integrity)?;
+        let (payload, mac) = frame.split_at(split);
+        if !fixed_time_eq(&self.mac(self.role.peer(), self.received, payload), mac) {
+            return Err(ProtocolError::Integrity);
+        }
+        self.received += 1;
+        Ok(serde_json::from_slice(payload)?)
+    }
+}
+
+/// Agent side of the handshake: challenge the controller, check its
+/// proof and prove the token in turn.
+///
+/// Refuses the controller before failing, so it knows why.
+pub async fn challenge<T>(mut conn: Connection<T>, token: &str) -> Result<Session<T>, ProtocolError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let agent_nonce = random_nonce()?;
+    let challenge = AgentMessage::Challenge {
+        version: PROTOCOL_VERSION,
+        nonce: hex::encode(agent_nonce),
+    };
+    send(&mut conn, &challenge).await?;
+
+    let ControllerMessage::Proof { nonce, hmac } = recv::<_, ControllerMessage>(&mut conn).await?
+    else {
+        return Err(ProtocolError::Unexpected("proof"));
+    };
+    let controller_nonce = parse_nonce(&nonce)?;
+    if !verify(
+        token,
+        Role::Controller,
+        &agent_nonce,
+        &controller_nonce,
+        &hmac,
+    ) {
+        let refused = AgentMessage::Refused {
+            reason: "authentication failed".into(),
+        };
+        let _ = send(&mut conn, &refused).await;
+        return Err(ProtocolError::AuthFailed);
+    }
+
+    let accepted = AgentMessage::Accepted {
+        hmac: hex::encode(proof(token, Role::Agent, &agent_nonce, &controller_nonce)),
+    };
+    send(&mut conn, &accepted).await?;
+    let key = session_key(token, &agent_nonce, &controller_nonce);
+    Ok(Session::new(conn, key, Role::Agent))
+}
+
+/// Controller side of the handshake: answer the agent's challenge, check
+/// the agent's proof and wait to be welcomed.
+pub async fn authenticate<T>(
+    mut conn: Connection<T>,
+    token: &str,
+) -> Result<(Session<T>, BoardState, Vec<ThreadInfo>), ProtocolError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let AgentMessage::Challenge { version, nonce } = recv::<_, AgentMessage>(&mut conn).await?
+    else {
+        return Err(ProtocolError::Unexpected("challenge"));
+    };
+    if version != PROTOCOL_VERSION {
+        return Err(ProtocolError::Version(version));
+    }
+    let agent_nonce = parse_nonce(&nonce)?;
+    let controller_nonce = random_nonce()?;
+    let proof = ControllerMessage::Proof {
+        nonce: hex::encode(controller_nonce),
+        hmac: hex::encode(proof(
+            token,
+            Role::Controller,
+            &agent_nonce,
+            &controller_nonce,
+        )),
+    };
+    send(&mut conn, &proof).await?;
+
+    match recv::<_, AgentMessage>(&mut conn).await? {
+        AgentMessage::Accepted { hmac }
+            if verify(token, Role::Agent, &agent_nonce, &controller_nonce, &hmac) => {}
+        AgentMessage::Accepted { .. } => return Err(ProtocolError::AuthFailed),
+        AgentMessage::Refused { reason } => return Err(ProtocolError::Refused(reason)),
+        _ => return Err(ProtocolError::Unexpected("accepted")),
+    }
+
+    let key = session_key(token, &agent_nonce, &controller_nonce);
+    let mut session = Session::new(conn, key, Role::Controller);
+    match session.recv().await? {
+        AgentMessage::Welcome { board, threads } => Ok((session, board, threads)),
+        AgentMessage::Refused { reason } => Err(ProtocolError::Refused(reason)),
+        _ => Err(ProtocolError::Unexpected("welcome")),
+    }
+}
+
+/// A fresh handshake nonce from the kernel's random source.
+fn random_nonce() -> std::io::Result<[u8; NONCE_LEN]> {
+    use std::io::Read;
+
+    let mut nonce = [0u8; NONCE_LEN];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut nonce)?;
+    Ok(nonce)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bitcoin::block::Version;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::job_source::{Extranonce2, JobTemplate, MerkleRootKind, VersionTemplate};
+
+    fn task(share_tx: ShareSender) -> HashTask {
+        let template = JobTemplate {
+            id: "7".into(),
+            prev_blockhash: bitcoin::BlockHash::all_zeros(),
+            version: VersionTemplate::new(
+                Version::from_consensus(0x2000_0000),
+                GeneralPurposeBits::full(),
+            )
+            .unwrap(),
+            bits: bitcoin::CompactTarget::from_consensus(0x1702_8c61),
+            share_target: Difficulty::from(1024).to_target(),
+            time: 0x6839_5e10,
+            merkle_root: MerkleRootKind::Fixed(bitcoin::TxMerkleNode::all_zeros()),
+        };
+        HashTask {
+            template: Arc::new(template),
+            en2_range: Some(Extranonce2Range::new_range(0, 255, 4).unwrap()),
+            en2: Some(Extranonce2::new(3, 4).unwrap()),
+            share_target: Difficulty::from(256).to_target(),
+            ntime: 0x6839_5e11,
+            share_tx,
+        }
+    }
+
+    fn share_sender() -> ShareSender {
+        let (tx, _rx) = mpsc::channel(1);
+        ShareSender::new(tx, Default::default())
+    }
+
+    #[test]
+    fn proofs_check_token_role_and_nonces() {
+        let (a, c) = ([7u8; NONCE_LEN], [9u8; NONCE_LEN]);
+        let hmac = hex::encode(proof("secret", Role::Controller, &a, &c));
+
+        assert!(verify("secret", Role::Controller, &a, &c, &hmac));
+        assert!(!verify("Secret", Role::Controller, &a, &c, &hmac));
+        assert!(!verify("secret", Role::Agent, &a, &c, &hmac));
+        assert!(!verify("secret", Role::Controller, &c, &a, &hmac));
+        assert!(!verify("secret", Role::Controller, &a, &c, &hmac[..62]));
+    }
+
+    #[test]
+    fn assignments_round_trip() {
+        let original = task(share_sender());
+        let assignment = Assignment::new(1, 42, true, &original);
+        let json = serde_json::to_string(&ControllerMessage::Assign(Box::new(assignment))).unwrap();
+
+        let ControllerMessage::Assign(assignment) = serde_json::from_str(&json).unwrap() else {
+            panic!("not an assignment");
+        };
+        assert_eq!((assignment.thread, assignment.task), (1, 42));
+        let received = assignment.to_task(share_sender()).unwrap();
+        assert_eq!(
+            JobRecord::from(received.template.as_ref()),
+            JobRecord::from(original.template.as_ref())
+        );
+        assert_eq!(received.en2_range, original.en2_range);
+        assert_eq!(received.en2, original.en2);
+        assert_eq!(received.share_target, original.share_target);
+        assert_eq!(received.ntime, original.ntime);
+    }
+
+    #[test]
+    fn shares_round_trip() {
+        let share = Share {
+            nonce: 0x1234_5678,
+            hash: bitcoin::BlockHash::all_zeros(),
+            version: Version::from_consensus(0x2000_e000),
+            ntime: 0x6839_5e11,
+            extranonce2: Some(Extranonce2::new(3, 4).unwrap()),
+            expected_work: Difficulty::from(256).to_target().to_work(),
+        };
+        let message = AgentMessage::Share {
+            thread: 0,
+            task: 42,
+            share: RemoteShare::new(&share, "7"),
+        };
+        let json = serde_json::to_string(&message).unwrap();
+
+        let AgentMessage::Share { share: remote, .. } = serde_json::from_str(&json).unwrap() else {
+            panic!("not a share");
+        };
+        assert_eq!(remote.record.job_id, "7");
+        let received = remote.to_share().unwrap();
+        assert_eq!(received.nonce, share.nonce);
+        assert_eq!(received.version, share.version);
+        assert_eq!(received.ntime, share.ntime);
+        assert_eq!(received.extranonce2, share.extranonce2);
+        assert_eq!(received.expected_work, share.expected_work);
+    }
+
+    #[tokio::test]
+    async fn handshake_admits_only_the_token_holder() {
+        for (token, admitted) in [("secret", true), ("guess", false)] {
+            let (agent_end, controller_end) = tokio::io::duplex(4096);
+
+            let agent = tokio::spawn(async move {
+                let mut session = challenge(framed(agent_end), "secret").await?;
+                let welcome = AgentMessage::Welcome {
+                    board: BoardState::default(),
+                    threads: Vec::new(),
+                };
+                session.send(&welcome).await
+            });
+            let result = authenticate(framed(controller_end), token).await;
+
+            assert_eq!(result.is_ok(), admitted, "token {token}");
+            assert_eq!(agent.await.unwrap().is_ok(), admitted);
+            if !admitted {
+                assert!(matches!(result, Err(ProtocolError::Refused(_))));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn handshake_rejects_an_agent_without_the_token() {
+        let (agent_end, controller_end) = tokio::io::duplex(4096);
+
+        // Answers on the agent's address and accepts any controller, but
+        // can only guess at the proof
+        tokio::spawn(async move {
+            let mut conn = framed(agent_end);
+            let agent_nonce = [1u8; NONCE_LEN];
+            let challenge = AgentMessage::Challenge {
+                version: PROTOCOL_VERSION,
+                nonce: hex::encode(agent_nonce),
+            };
+            send(&mut conn, &challenge).await.unwrap();
+            let ControllerMessage::Proof { nonce, .. } = recv(&mut conn).await.unwrap() else {
+                panic!("not a proof");
+            };
+            let controller_nonce = parse_nonce(&nonce).unwrap();
+            let accepted = AgentMessage::Accepted {
+                hmac: hex::encode(proof("guess", Role::Agent, &agent_nonce, &controller_nonce)),
+            };
+            send(&mut conn, &accepted).await.unwrap();
+            conn
+        });
+
+        let result = authenticate(framed(controller_end), "secret").await;
+        assert!(matches!(result, Err(ProtocolError::AuthFailed)));
+    }
+
+    #[tokio::test]
+    async fn sessions_reject_tampered_replayed_and_reflected_frames() {
+        let key = [5u8; 32];
+        let ping = |id| ControllerMessage::Ping { id };
+
+        // Capture two frames from the controller as sent on the wire
+        let (sender_end, wire_end) = tokio::io::duplex(4096);
+        let mut sender = Session::new(framed(sender_end), key, Role::Controller);
+        let mut wire = framed(wire_end);
+        sender.send(&ping(1)).await.unwrap();
+        sender.send(&ping(2)).await.unwrap();
+        let first = wire.next().await.unwrap().unwrap().freeze();
+        let second = wire.next().await.unwrap().unwrap().freeze();
+
+        // Deliver `frames` to a fresh session playing `role`; how many
+        // it takes before one fails
+        let deliver = |frames: Vec<Bytes>, role| async move {
+            let (receiver_end, wire_end) = tokio::io::duplex(4096);
+            let mut wire = framed(wire_end);
+            let count = frames.len();
+            for frame in frames {
+                wire.send(frame).await.unwrap();
+            }
+            let mut receiver = Session::new(framed(receiver_end), key, role);
+            let mut taken = 0;
+            while taken < count {
+                match receiver.recv::<ControllerMessage>().await {
+                    Ok(ControllerMessage::Ping { .. }) => taken += 1,
+                    Ok(_) => panic!("not a ping"),
+                    Err(e) => {
+                        assert!(matches!(e, ProtocolError::Integrity), "{e}");
+                        break;
+                    }
+                }
+            }
+            taken
+        };
+
+        assert_eq!(
+            deliver(vec![first.clone(), second.clone()], Role::Agent).await,
+            2
+        );
+        // Replayed
+        assert_eq!(
+            deliver(vec![first.clone(), first.clone()], Role::Agent).await,
+            1
+        );
+        // Dropped
+        assert_eq!(deliver(vec![second.clone()], Role::Agent).await, 0);
+        // Altered
+        let mut altered = first.to_vec();
+        altered[10] ^= 1;
+        assert_eq!(deliver(vec![Bytes::from(altered)], Role::Agent).await, 0);
+        // Reflected back at the controller
+        assert_eq!(deliver(vec![first, second], Role::Controller).await, 0);
+    }
+}
@@ -0,0 +1,411 @@
+//! Agent side: serve the local board's hash threads to a controller.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Semaphore, mpsc, watch};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{StreamExt, StreamMap};
+use tokio_util::sync::CancellationToken;
+
+use super::protocol::{
+    self, AgentMessage, Assignment, ControllerMessage, ProtocolError, RemoteShare, ThreadInfo,
+};
+use crate::{
+    api::commands::{BoardCommand, CommandBus},
+    api_client::types::BoardState,
+    asic::hash_thread::{
+        AssignmentParameters, ChannelPressure, HashThread, HashThreadEvent, Share, ShareSender,
+    },
+    schema::ThreadStatusRecord,
+    tracing::prelude::*,
+};
+
+/// How long a connection gets to authenticate.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connections authenticating at once; more are closed on arrival.
+const MAX_HANDSHAKES: usize = 8;
+
+type Session = protocol::Session<TcpStream>;
+
+/// The board an agent serves.
+pub struct LocalBoard {
+    /// Name the board goes by in commands
+    pub name: String,
+    pub state_rx: watch::Receiver<BoardState>,
+    pub threads: Vec<Box<dyn HashThread>>,
+}
+
+/// Serves one board to whichever controller authenticated last.
+pub struct AgentServer {
+    board_name: String,
+    state_rx: watch::Receiver<BoardState>,
+    threads: Vec<LocalThread>,
+    token: String,
+    commands: CommandBus,
+    session: Option<Session>,
+    /// Tasks assigned by the current controller, by the controller's ID
+    tasks: HashMap<u64, TaskRoute>,
+}
+
+struct LocalThread {
+    thread: Box<dyn HashThread>,
+    info: ThreadInfo,
+    share_pressure: Arc<ChannelPressure>,
+}
+
+/// Where a task's shares go back to.
+struct TaskRoute {
+    thread: usize,
+    job_id: String,
+}
+
+impl AgentServer {
+    pub fn new(board: LocalBoard, token: String, commands: CommandBus) -> Self {
+        let threads = board
+            .threads
+            .into_iter()
+            .map(|thread| LocalThread {
+                info: ThreadInfo::new(thread.as_ref()),
+                thread,
+                share_pressure: Arc::default(),
+            })
+            .collect();
+        Self {
+            board_name: board.name,
+            state_rx: board.state_rx,
+            threads,
+            token,
+            commands,
+            session: None,
+            tasks: HashMap::new(),
+        }
+    }
+
+    /// Accept controllers on `listener` until shutdown or until the
+    /// board's threads are all gone.
+    pub async fn run(mut self, listener: TcpListener, shutdown: CancellationToken) {
+        let mut thread_events = StreamMap::new();
+        for (index, local) in self.threads.iter_mut().enumerate() {
+            if let Some(event_rx) = local.thread.take_event_receiver() {
+                thread_events.insert(index, ReceiverStream::new(event_rx));
+            }
+        }
+        let mut shares: StreamMap<u64, ReceiverStream<Share>> = StreamMap::new();
+        let (authed_tx, mut authed_rx) = mpsc::channel::<Session>(1);
+        let (reply_tx, mut reply_rx) = mpsc::channel::<AgentMessage>(16);
+        let handshakes = Arc::new(Semaphore::new(MAX_HANDSHAKES));
+
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => break,
+
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, peer)) => {
+                        let Ok(permit) = handshakes.clone().try_acquire_owned() else {
+                            debug!(%peer, "Too many connections authenticating; closing.");
+                            continue;
+                        };
+                        let token = self.token.clone();
+                        let authed_tx = authed_tx.clone();
+                        tokio::spawn(async move {
+                            let result = handshake(stream, &token).await;
+                            drop(permit);
+                            match result {
+                                Ok(session) => {
+                                    info!(%peer, "Controller authenticated.");
+                                    let _ = authed_tx.send(session).await;
+                                }
+                                // Link probes connect and hang up; only
+                                // failed proofs are worth a warning
+                                Err(ProtocolError::AuthFailed) => {
+                                    warn!(%peer, "Controller failed to authenticate.");
+                                }
+                                Err(e) => {
+                                    debug!(%peer, error = %e, "Closed before authenticating.");
+                                }
+                            }
+                        });
+                    }
+                    Err(e) => warn!(error = %e, "Failed to accept connection"),
+                },
+
+                Some(session) = authed_rx.recv() => {
+                    if let Some(mut previous) = self.session.take() {
+                        info!("Another controller took over.");
+                        let refused = AgentMessage::Refused {
+                            reason: "another controller took over".into(),
+                        };
+                        let _ = previous.send(&refused).await;
+                    }
+                    self.idle_threads(&mut shares).await;
+                    self.session = Some(session);
+                    let welcome = AgentMessage::Welcome {
+                        board: self.state_rx.borrow().clone(),
+                        threads: self.threads.iter().map(|local| local.info.clone()).collect(),
+                    };
+                    self.send(welcome, &mut shares).await;
+                }
+
+                message = next_message(&mut self.session) => match message {
+                    Ok(message) => self.handle(message, &mut shares, &reply_tx).await,
+                    Err(e) => {
+                        info!(error = %e, "Controller disconnected.");
+                        self.disconnect(&mut shares).await;
+                    }
+                },
+
+                Some(reply) = reply_rx.recv() => self.send(reply, &mut shares).await,
+
+                event = thread_events.next() => {
+                    let Some((thread, event)) = event else {
+                        warn!("Board's hash threads are gone, nothing left to serve.");
+                        break;
+                    };
+                    let message = match event {
+                        HashThreadEvent::WorkDepletionWarning { estimated_remaining_ms } => {
+                            AgentMessage::WorkDepletionWarning { thread, estimated_remaining_ms }
+                        }
+                        HashThreadEvent::WorkExhausted { en2_searched } => {
+                            AgentMessage::WorkExhausted { thread, en2_searched }
+                        }
+                        HashThreadEvent::StatusUpdate(status) => AgentMessage::Status {
+                            thread,
+                            status: ThreadStatusRecord::from(status.as_ref()),
+                        },
+                        HashThreadEvent::GoingOffline => AgentMessage::GoingOffline { thread },
+                    };
+                    self.send(message, &mut shares).await;
+                }
+
+                Some((task, share)) = shares.next() => {
+                    if let Some(route) = self.tasks.get(&task) {
+                        let message = AgentMessage::Share {
+                            thread: route.thread,
+                            task,
+                            share: RemoteShare::new(&share, &route.job_id),
+                        };
+                        self.send(message, &mut shares).await;
+                    }
+                }
+
+                Ok(()) = self.state_rx.changed() => {
+                    let board = self.state_rx.borrow_and_update().clone();
+                    self.send(AgentMessage::State { board }, &mut shares).await;
+                }
+            }
+        }
+
+        if let Some(mut session) = self.session.take() {
+            let refused = AgentMessage::Refused {
+                reason: "agent shutting down".into(),
+            };
+            let _ = session.send(&refused).await;
+        }
+    }
+
+    async fn handle(
+        &mut self,
+        message: ControllerMessage,
+        shares: &mut StreamMap<u64, ReceiverStream<Share>>,
+        reply_tx: &mpsc::Sender<AgentMessage>,
+    ) {
+        match message {
+            ControllerMessage::Negotiate {
+                id,
+                thread,
+                max_ntime_roll,
+            } => {
+                let result = match self.threads.get_mut(thread) {
+                    Some(local) => local
+                        .thread
+                        .negotiate(AssignmentParameters { max_ntime_roll })
+                        .await
+                        .map_err(|e| e.to_string()),
+                    None => Err(format!("no thread {thread}")),
+                };
+                let reply = AgentMessage::Reply {
+                    id,
+                    error: result.err(),
+                };
+                self.send(reply, shares).await;
+            }
+            ControllerMessage::Assign(assignment) => self.assign(*assignment, shares).await,
+            ControllerMessage::GoIdle { thread } => {
+                if let Some(local) = self.threads.get_mut(thread) {
+                    if let Err(e) = local.thread.go_idle().await {
+                        warn!(thread = %local.thread.name(), error = %e, "Failed to idle thread");
+                    }
+                    self.drop_tasks(thread, None, shares);
+                }
+            }
+            ControllerMessage::SetProfile { id, profile } => {
+                self.board_command(id, reply_tx, move |reply| BoardCommand::SetProfile {
+                    profile,
+                    reply,
+                });
+            }
+            ControllerMessage::SetFrequency { id, mhz } => {
+                let board = self.board_name.clone();
+                self.board_command(id, reply_tx, move |reply| BoardCommand::SetFrequency {
+                    board,
+                    frequency_mhz: mhz,
+                    reply,
+                });
+            }
+            ControllerMessage::SetFanTarget { id, fan, percent } => {
+                let board = self.board_name.clone();
+                self.board_command(id, reply_tx, move |reply| BoardCommand::SetFanTarget {
+                    board,
+                    fan,
+                    percent,
+                    reply,
+                });
+            }
+            ControllerMessage::Ping { id } => self.send(AgentMessage::Pong { id }, shares).await,
+            ControllerMessage::Proof { .. } => debug!("Ignoring proof outside the handshake"),
+        }
+    }
+
+    async fn assign(
+        &mut self,
+        assignment: Assignment,
+        shares: &mut StreamMap<u64, ReceiverStream<Share>>,
+    ) {
+        let Some(local) = self.threads.get_mut(assignment.thread) else {
+            warn!(thread = assignment.thread, "Assignment for unknown thread");
+            return;
+        };
+        let (share_tx, share_rx) = mpsc::channel(32);
+        let task =
+            match assignment.to_task(ShareSender::new(share_tx, local.share_pressure.clone())) {
+                Ok(task) => task,
+                Err(e) => {
+                    warn!(error = %e, "Malformed assignment");
+                    return;
+                }
+            };
+
+        let result = if assignment.replace {
+            local.thread.replace_task(task).await
+        } else {
+            local.thread.update_task(task).await
+        };
+        if let Err(e) = result {
+            warn!(thread = %local.thread.name(), error = %e, "Failed to assign task");
+            return;
+        }
+
+        // A replaced task's shares are stale; an updated one's still
+        // count, until the task before it is updated in turn
+        let keep = (!assignment.replace)
+            .then(|| {
+                self.tasks
+                    .iter()
+                    .filter(|(_, route)| route.thread == assignment.thread)
+                    .map(|(task, _)| *task)
+                    .max()
+            })
+            .flatten();
+        self.drop_tasks(assignment.thread, keep, shares);
+
+        self.tasks.insert(
+            assignment.task,
+            TaskRoute {
+                thread: assignment.thread,
+                job_id: assignment.job.id,
+            },
+        );
+        shares.insert(assignment.task, ReceiverStream::new(share_rx));
+    }
+
+    /// Forget a thread's tasks, except `keep`.
+    fn drop_tasks(
+        &mut self,
+        thread: usize,
+        keep: Option<u64>,
+        shares: &mut StreamMap<u64, ReceiverStream<Share>>,
+    ) {
+        self.tasks.retain(|task, route| {
+            let dropped = route.thread == thread && Some(*task) != keep;
+            if dropped {
+                shares.remove(task);
+            }
+            !dropped
+        });
+    }
+
+    /// Carry out a board command in the background, replying when done.
+    fn board_command(
+        &self,
+        id: u64,
+        reply_tx: &mpsc::Sender<AgentMessage>,
+        make_cmd: impl FnOnce(tokio::sync::oneshot::Sender<anyhow::Result<()>>) -> BoardCommand
+        + Send
+        + 'static,
+    ) {
+        let commands = self.commands.clone();
+        let reply_tx = reply_tx.clone();
+        tokio::spawn(async move {
+            let error = commands
+                .request(make_cmd)
+                .await
+                .err()
+                .map(|e| e.to_string());
+            let _ = reply_tx.send(AgentMessage::Reply { id, error }).await;
+        });
+    }
+
+    /// Send to the controller, if one is connected, dropping it if the
+    /// link fails.
+    async fn send(
+        &mut self,
+        message: AgentMessage,
+        shares: &mut StreamMap<u64, ReceiverStream<Share>>,
+    ) {
+        let Some(session) = &mut self.session else {
+            return;
+        };
+        if let Err(e) = session.send(&message).await {
+            info!(error = %e, "Lost the controller.");
+            self.disconnect(shares).await;
+        }
+    }
+
+    /// The controller is gone: stop hashing until the next one assigns
+    /// work.
+    async fn disconnect(&mut self, shares: &mut StreamMap<u64, ReceiverStream<Share>>) {
+        self.session = None;
+        self.idle_threads(shares).await;
+    }
+
+    async fn idle_threads(&mut self, shares: &mut StreamMap<u64, ReceiverStream<Share>>) {
+        for local in &mut self.threads {
+            if let Err(e) = local.thread.go_idle().await {
+                warn!(thread = %local.thread.name(), error = %e, "Failed to idle thread");
+            }
+        }
+        self.tasks.clear();
+        shares.clear();
+    }
+}
+
+/// Challenge a new connection, within [`HANDSHAKE_TIMEOUT`].
+async fn handshake(stream: TcpStream, token: &str) -> Result<Session, ProtocolError> {
+    let _ = stream.set_nodelay(true);
+    let conn = protocol::framed(stream);
+    tokio::time::timeout(HANDSHAKE_TIMEOUT, protocol::challenge(conn, token))
+        .await
+        .unwrap_or(Err(ProtocolError::Closed))
+}
+
+/// Next message from the controller; pending while there is none.
+async fn next_message(session: &mut Option<Session>) -> Result<ControllerMessage, ProtocolError> {
+    match session {
+        Some(session) => session.recv().await,
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,509 @@
+//! Alerts on miner conditions, sent to webhooks and MQTT.
+//!
+//! Rules from `[alerts]` in the config (see [`rules`] for their syntax)
+//! are checked against the miner's state every [`CHECK_INTERVAL`]. A rule
+//! fires when its condition starts to hold and resolves when it stops.
+//! Both are logged, recorded in the [`AlertHistory`] served by
+//! `GET /api/v0/alerts`, and sent through the [`Notifier`].
+//!
+//! A hashrate rule written as a percentage compares against
+//! `expected_hashrate` when configured, else against the highest hashrate
+//! seen since the miner started.
+
+pub mod notify;
+pub mod rules;
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+pub use crate::api_client::types::{AlertEntry, AlertState};
+use crate::api_client::types::{BoardState, MinerState};
+use crate::tracing::prelude::*;
+use crate::types::{HashRate, now_ms};
+pub use notify::{MqttTarget, Notifier};
+pub use rules::{AlertRule, RuleError};
+use rules::{Condition, Event, Metric, Threshold};
+
+/// How often rules are checked.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Alerts kept in the history unless configured otherwise.
+pub const DEFAULT_HISTORY: usize = 100;
+
+/// The readings rules are checked against, taken from one miner state.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    /// Hottest temperature any board reports, °C
+    pub temp_c: Option<f64>,
+    /// Miner hashrate, H/s
+    pub hashrate: f64,
+    /// Power drawn by all boards, W, if any board measures it
+    pub power_w: Option<f64>,
+    /// Whether each source has work from its upstream, by name
+    pub sources: Vec<(String, bool)>,
+    /// Names of the boards present
+    pub boards: Vec<String>,
+}
+
+impl Snapshot {
+    pub fn of(state: &MinerState) -> Self {
+        let temp_c = state
+            .boards
+            .iter()
+            .flat_map(|board| &board.temperatures)
+            .filter_map(|sensor| sensor.temperature_c)
+            .map(f64::from)
+            .reduce(f64::max);
+        let powers: Vec<f64> = state
+            .boards
+            .iter()
+            .flat_map(|board| &board.powers)
+            .filter_map(|power| power.power_w)
+            .map(f64::from)
+            .collect();
+        Self {
+            temp_c,
+            hashrate: state.hashrate as f64,
+            power_w: (!powers.is_empty()).then(|| powers.iter().sum()),
+            sources: state
+                .sources
+                .iter()
+                .map(|source| (source.name.clone(), source.health.connected))
+                .collect(),
+            boards: state
+                .boards
+                .iter()
+                .map(|board| board.name.clone())
+                .collect(),
+        }
+    }
+
+    fn reading(&self, metric: Metric) -> Option<f64> {
+        match metric {
+            Metric::Temp => self.temp_c,
+            Metric::Hashrate => Some(self.hashrate),
+            Metric::Power => self.power_w,
+        }
+    }
+
+    /// Times `event` happened between `previous` and this snapshot.
+    fn count(&self, event: Event, previous: &Self) -> usize {
+        match event {
+            Event::PoolDisconnected => self
+                .sources
+                .iter()
+                .filter(|(name, connected)| {
+                    !connected
+                        && previous
+                            .sources
+                            .iter()
+                            .any(|(before, was)| before == name && *was)
+                })
+                .count(),
+            Event::BoardDisconnected => previous
+                .boards
+                .iter()
+                .filter(|name| !self.boards.contains(name))
+                .count(),
+        }
+    }
+}
+
+/// A rule and what has been seen of it so far.
+struct Watch {
+    rule: AlertRule,
+    /// When a sustained rule's condition began to hold
+    since: Option<Instant>,
+    /// When a repeated rule's events happened, within its window
+    events: VecDeque<Instant>,
+    firing: bool,
+}
+
+/// Checks rules against successive snapshots.
+pub struct AlertEngine {
+    watches: Vec<Watch>,
+    expected_hashrate: Option<HashRate>,
+    peak_hashrate: f64,
+    previous: Option<Snapshot>,
+}
+
+impl AlertEngine {
+    pub fn new(rules: Vec<AlertRule>, expected_hashrate: Option<HashRate>) -> Self {
+        Self {
+            watches: rules
+                .into_iter()
+                .map(|rule| Watch {
+                    rule,
+                    since: None,
+                    events: VecDeque::new(),
+                    firing: false,
+                })
+                .collect(),
+            expected_hashrate,
+            peak_hashrate: 0.0,
+            previous: None,
+        }
+    }
+
+    /// Check every rule against `snapshot`, taken at `now`, returning the
+    /// alerts that fired or resolved.
+    pub fn observe(&mut self, snapshot: Snapshot, now: Instant) -> Vec<AlertEntry> {
+        self.peak_hashrate = self.peak_hashrate.max(snapshot.hashrate);
+        let expected = self
+            .expected_hashrate
+            .map(|rate| rate.0 as f64)
+            .or(Some(self.peak_hashrate).filter(|&peak| peak > 0.0));
+
+        let mut alerts = Vec::new();
+        for watch in &mut self.watches {
+            let (holds, value) = match watch.rule.condition {
+                Condition::Sustained {
+                    metric,
+                    comparison,
+                    threshold,
+                    duration,
+                } => {
+                    let threshold = match threshold {
+                        Threshold::Value(value) => Some(value),
+                        Threshold::PercentOfExpected(percent) => {
+                            expected.map(|expected| expected * percent / 100.0)
+                        }
+                    };
+                    let reading = snapshot.reading(metric);
+                    let past = match (reading, threshold) {
+                        (Some(value), Some(threshold)) => comparison.holds(value, threshold),
+                        _ => false,
+                    };
+                    let since = if past {
+                        *watch.since.get_or_insert(now)
+                    } else {
+                        watch.since = None;
+                        now
+                    };
+                    (
+                        past && now.duration_since(since) >= duration,
+                        reading.unwrap_or_default(),
+                    )
+                }
+                Condition::Repeated {
+                    event,
+                    count,
+                    window,
+                } => {
+                    if let Some(previous) = &self.previous {
+                        let happened = snapshot.count(event, previous);
+                        watch.events.extend(std::iter::repeat_n(now, happened));
+                    }
+                    while watch
+                        .events
+                        .front()
+                        .is_some_and(|&at| now.duration_since(at) > window)
+                    {
+                        watch.events.pop_front();
+                    }
+                    let seen = watch.events.len();
+                    (seen >= count as usize, seen as f64)
+                }
+            };
+
+            if holds != watch.firing {
+                watch.firing = holds;
+                let state = if holds {
+                    AlertState::Firing
+                } else {
+                    AlertState::Resolved
+                };
+                alerts.push(AlertEntry {
+                    rule: watch.rule.to_string(),
+                    state,
+                    at_ms: now_ms(),
+                    value,
+                    message: message(&watch.rule, state, value),
+                });
+            }
+        }
+
+        self.previous = Some(snapshot);
+        alerts
+    }
+}
+
+fn message(rule: &AlertRule, state: AlertState, value: f64) -> String {
+    let reading = match rule.condition {
+        Condition::Sustained {
+            metric: Metric::Temp,
+            ..
+        } => format!("{value:.1} °C"),
+        Condition::Sustained {
+            metric: Metric::Hashrate,
+            ..
+        } => HashRate(value as u64).to_string(),
+        Condition::Sustained {
+            metric: Metric::Power,
+            ..
+        } => format!("{value:.0} W"),
+        Condition::Repeated { .. } => format!("{value} times"),
+    };
+    match state {
+        AlertState::Firing => format!("{rule} (now {reading})"),
+        AlertState::Resolved => format!("resolved: {rule} (now {reading})"),
+    }
+}
+
+/// Alerts that fired or resolved recently, shared by the alert task and
+/// the API.
+///
+/// Clones record into the same history. A disabled history keeps nothing.
+#[derive(Clone, Debug, Default)]
+pub struct AlertHistory {
+    inner: Option<Arc<Mutex<Entries>>>,
+}
+
+#[derive(Debug)]
+struct Entries {
+    capacity: usize,
+    /// Alerts recorded so far, numbering each entry.
+    recorded: u64,
+    /// Oldest first, with their numbers.
+    entries: VecDeque<(u64, AlertEntry)>,
+}
+
+impl AlertHistory {
+    /// Keep the last `capacity` alerts; zero disables the history.
+    pub fn new(capacity: usize) -> Self {
+        if capacity == 0 {
+            return Self::disabled();
+        }
+        Self {
+            inner: Some(Arc::new(Mutex::new(Entries {
+                capacity,
+                recorded: 0,
+                entries: VecDeque::with_capacity(capacity),
+            }))),
+        }
+    }
+
+    /// A history that keeps nothing.
+    pub fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    pub fn record(&self, alert: AlertEntry) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        let mut inner = inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.entries.len() == inner.capacity {
+            inner.entries.pop_front();
+        }
+        inner.recorded += 1;
+        let number = inner.recorded;
+        inner.entries.push_back((number, alert));
+    }
+
+    /// Recorded alerts, newest first, each with its number: one more
+    /// than the alert recorded before it, for paging through the history.
+    pub fn numbered(&self) -> Vec<(u64, AlertEntry)> {
+        let Some(inner) = &self.inner else {
+            return Vec::new();
+        };
+        let inner = inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.entries.iter().rev().cloned().collect()
+    }
+}
+
+/// Check `engine`'s rules until `shutdown`, recording and sending each
+/// alert.
+///
+/// `state_rx` is the scheduler's state; `boards_rx` the boards present.
+pub async fn task(
+    mut engine: AlertEngine,
+    notifier: Notifier,
+    history: AlertHistory,
+    state_rx: watch::Receiver<MinerState>,
+    boards_rx: watch::Receiver<Vec<watch::Receiver<BoardState>>>,
+    shutdown: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.cancelled() => break,
+        }
+
+        let mut state = state_rx.borrow().clone();
+        state.boards = boards_rx
+            .borrow()
+            .iter()
+            .filter(|rx| rx.has_changed().is_ok())
+            .map(|rx| rx.borrow().clone())
+            .collect();
+
+        for alert in engine.observe(Snapshot::of(&state), Instant::now()) {
+            match alert.state {
+                AlertState::Firing => warn!(rule = %alert.rule, "Alert: {}", alert.message),
+                AlertState::Resolved => info!(rule = %alert.rule, "Alert: {}", alert.message),
+            }
+            history.record(alert.clone());
+            // A slow webhook must not hold up the next check
+            let notifier = notifier.clone();
+            tokio::spawn(async move { notifier.send(&alert).await });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(rules: &[&str], expected: Option<HashRate>) -> AlertEngine {
+        AlertEngine::new(rules.iter().map(|r| r.parse().unwrap()).collect(), expected)
+    }
+
+    fn states(alerts: &[AlertEntry]) -> Vec<(&str, AlertState)> {
+        alerts.iter().map(|a| (a.rule.as_str(), a.state)).collect()
+    }
+
+    #[test]
+    fn sustained_rule_fires_after_its_duration_and_resolves() {
+        let mut engine = engine(&["temp > 80 for 5m"], None);
+        let start = Instant::now();
+        let hot = Snapshot {
+            temp_c: Some(83.0),
+            ..Default::default()
+        };
+
+        assert!(engine.observe(hot.clone(), start).is_empty());
+        assert!(
+            engine
+                .observe(hot.clone(), start + Duration::from_secs(299))
+                .is_empty()
+        );
+        let fired = engine.observe(hot.clone(), start + Duration::from_secs(300));
+        assert_eq!(states(&fired), [("temp > 80 for 5m", AlertState::Firing)]);
+        assert_eq!(fired[0].value, 83.0);
+        assert_eq!(fired[0].message, "temp > 80 for 5m (now 83.0 °C)");
+        assert!(
+            engine
+                .observe(hot, start + Duration::from_secs(305))
+                .is_empty()
+        );
+
+        let cool = Snapshot {
+            temp_c: Some(70.0),
+            ..Default::default()
+        };
+        let resolved = engine.observe(cool, start + Duration::from_secs(310));
+        assert_eq!(
+            states(&resolved),
+            [("temp > 80 for 5m", AlertState::Resolved)]
+        );
+    }
+
+    #[test]
+    fn brief_excursion_restarts_the_clock() {
+        let mut engine = engine(&["temp > 80 for 1m"], None);
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let temp = |c| Snapshot {
+            temp_c: Some(c),
+            ..Default::default()
+        };
+
+        engine.observe(temp(85.0), at(0));
+        engine.observe(temp(75.0), at(30));
+        engine.observe(temp(85.0), at(40));
+        assert!(engine.observe(temp(85.0), at(90)).is_empty());
+        assert_eq!(engine.observe(temp(85.0), at(100)).len(), 1);
+    }
+
+    #[test]
+    fn hashrate_percentage_uses_expected_or_peak() {
+        let rate = |h| Snapshot {
+            hashrate: h,
+            ..Default::default()
+        };
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        // Against the peak seen
+        let mut peak = engine(&["hashrate < 50% of expected for 10m"], None);
+        peak.observe(rate(1e12), at(0));
+        peak.observe(rate(0.4e12), at(1));
+        assert_eq!(peak.observe(rate(0.4e12), at(601)).len(), 1);
+
+        // Against the configured rate, which the miner never reached
+        let mut configured = engine(
+            &["hashrate < 50% of expected for 10m"],
+            Some(HashRate::from_terahashes(2.0)),
+        );
+        configured.observe(rate(0.9e12), at(0));
+        let fired = configured.observe(rate(0.9e12), at(600));
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].value, 0.9e12);
+    }
+
+    #[test]
+    fn repeated_rule_counts_disconnects_within_window() {
+        let mut engine = engine(&["pool disconnected 3x in 1h"], None);
+        let start = Instant::now();
+        let at = |mins: u64| start + Duration::from_secs(mins * 60);
+        let pool = |connected| Snapshot {
+            sources: vec![("pool".into(), connected)],
+            ..Default::default()
+        };
+
+        let mut fired = Vec::new();
+        for mins in 0..6 {
+            // Connected on even minutes, down on odd ones
+            fired.extend(engine.observe(pool(mins % 2 == 0), at(mins)));
+        }
+        assert_eq!(
+            states(&fired),
+            [("pool disconnected 3x in 1h", AlertState::Firing)]
+        );
+        assert_eq!(fired[0].value, 3.0);
+
+        let resolved = engine.observe(pool(true), at(62));
+        assert_eq!(
+            states(&resolved),
+            [("pool disconnected 3x in 1h", AlertState::Resolved)]
+        );
+    }
+
+    #[test]
+    fn board_disappearing_counts_as_disconnect() {
+        let mut engine = engine(&["board disconnected 1x in 10m"], None);
+        let now = Instant::now();
+        let boards = |names: &[&str]| Snapshot {
+            boards: names.iter().map(|n| n.to_string()).collect(),
+            ..Default::default()
+        };
+        assert!(engine.observe(boards(&["a", "b"]), now).is_empty());
+        assert_eq!(engine.observe(boards(&["a"]), now).len(), 1);
+    }
+
+    #[test]
+    fn history_keeps_newest_alerts() {
+        let history = AlertHistory::new(2);
+        for n in 0..3 {
+            history.record(AlertEntry {
+                rule: format!("rule {n}"),
+                state: AlertState::Firing,
+                at_ms: n,
+                value: 0.0,
+                message: String::new(),
+            });
+        }
+        let numbered = history.numbered();
+        assert_eq!(numbered.len(), 2);
+        assert_eq!(numbered[0].0, 3);
+        assert_eq!(numbered[0].1.rule, "rule 2");
+        assert!(AlertHistory::disabled().numbered().is_empty());
+    }
+}
@@ -0,0 +1,333 @@
+//! Alert rules and the text they are written in.
+//!
+//! A rule is one line of text in `[alerts] rules`, in one of two forms:
+//!
+//! - `<metric> <op> <threshold> for <duration>`: a reading stays past a
+//!   threshold, e.g. `temp > 80 for 5m`. Metrics are `temp` (hottest
+//!   sensor on any board, °C), `power` (all boards, W) and `hashrate`
+//!   (H/s, with units such as `500G`, or a percentage of the expected
+//!   hashrate: `hashrate < 50% of expected for 10m`). Operators are `>`,
+//!   `>=`, `<` and `<=`.
+//! - `<event> <n>x in <window>`: something happens often, e.g.
+//!   `pool disconnected 3x in 1h`. Events are `pool disconnected` and
+//!   `board disconnected`.
+//!
+//! Durations are a whole number of seconds, minutes, hours or days:
+//! `30s`, `5m`, `1h`, `1d`.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::HashRate;
+
+/// A reading a rule can watch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// Hottest temperature any board reports, °C
+    Temp,
+    /// Miner hashrate, H/s
+    Hashrate,
+    /// Power drawn by all boards, W
+    Power,
+}
+
+/// How a reading is compared with its threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Above,
+    AtLeast,
+    Below,
+    AtMost,
+}
+
+impl Comparison {
+    pub fn holds(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Self::Above => value > threshold,
+            Self::AtLeast => value >= threshold,
+            Self::Below => value < threshold,
+            Self::AtMost => value <= threshold,
+        }
+    }
+}
+
+/// What a reading is compared with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Threshold {
+    /// A value in the metric's unit
+    Value(f64),
+    /// A percentage of the expected hashrate
+    PercentOfExpected(f64),
+}
+
+/// Something a rule counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// A job source lost its upstream
+    PoolDisconnected,
+    /// A board went away
+    BoardDisconnected,
+}
+
+/// What a rule watches for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Condition {
+    /// A reading past a threshold for at least `duration`
+    Sustained {
+        metric: Metric,
+        comparison: Comparison,
+        threshold: Threshold,
+        duration: Duration,
+    },
+    /// An event happening at least `count` times within `window`
+    Repeated {
+        event: Event,
+        count: u32,
+        window: Duration,
+    },
+}
+
+/// An alert rule, kept with the text it was written as.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AlertRule {
+    text: String,
+    pub condition: Condition,
+}
+
+/// Errors from parsing a rule.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum RuleError {
+    #[error(
+        "expected \"<metric> <op> <threshold> for <duration>\" or \"<event> <n>x in <window>\""
+    )]
+    Form,
+
+    #[error("unknown metric {0:?}; expected temp, hashrate or power")]
+    Metric(String),
+
+    #[error("unknown operator {0:?}; expected >, >=, < or <=")]
+    Operator(String),
+
+    #[error("invalid threshold {0:?}")]
+    Threshold(String),
+
+    #[error("unknown event {0:?}; expected \"pool disconnected\" or \"board disconnected\"")]
+    Event(String),
+
+    #[error("invalid count {0:?}; expected e.g. 3x")]
+    Count(String),
+
+    #[error("invalid duration {0:?}; expected e.g. 30s, 5m, 1h or 1d")]
+    Duration(String),
+}
+
+impl AlertRule {
+    /// The rule as written.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl FromStr for AlertRule {
+    type Err = RuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.split_whitespace().collect::<Vec<_>>().join(" ");
+        let condition = if let Some((reading, duration)) = text.split_once(" for ") {
+            parse_sustained(reading, parse_duration(duration)?)?
+        } else if let Some((event, window)) = text.split_once(" in ") {
+            parse_repeated(event, parse_duration(window)?)?
+        } else {
+            return Err(RuleError::Form);
+        };
+        Ok(Self { text, condition })
+    }
+}
+
+impl TryFrom<String> for AlertRule {
+    type Error = RuleError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<AlertRule> for String {
+    fn from(rule: AlertRule) -> Self {
+        rule.text
+    }
+}
+
+impl fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+fn parse_sustained(reading: &str, duration: Duration) -> Result<Condition, RuleError> {
+    let words: Vec<&str> = reading.split(' ').collect();
+    let (metric, op, threshold) = match words[..] {
+        [metric, op, threshold] | [metric, op, threshold, "of", "expected"] => {
+            (metric, op, threshold)
+        }
+        _ => return Err(RuleError::Form),
+    };
+    let metric = match metric {
+        "temp" => Metric::Temp,
+        "hashrate" => Metric::Hashrate,
+        "power" => Metric::Power,
+        _ => return Err(RuleError::Metric(metric.into())),
+    };
+    let comparison = match op {
+        ">" => Comparison::Above,
+        ">=" => Comparison::AtLeast,
+        "<" => Comparison::Below,
+        "<=" => Comparison::AtMost,
+        _ => return Err(RuleError::Operator(op.into())),
+    };
+
+    let invalid = || RuleError::Threshold(threshold.into());
+    let of_expected = words.len() == 5;
+    let threshold = match threshold.strip_suffix('%') {
+        Some(percent) if metric == Metric::Hashrate => Threshold::PercentOfExpected(
+            percent
+                .parse()
+                .ok()
+                .filter(|p: &f64| p.is_finite() && *p >= 0.0)
+                .ok_or_else(invalid)?,
+        ),
+        Some(_) => return Err(invalid()),
+        None if of_expected => return Err(invalid()),
+        None if metric == Metric::Hashrate => {
+            Threshold::Value(threshold.parse::<HashRate>().map_err(|_| invalid())?.0 as f64)
+        }
+        None => Threshold::Value(
+            threshold
+                .parse()
+                .ok()
+                .filter(|v: &f64| v.is_finite())
+                .ok_or_else(invalid)?,
+        ),
+    };
+
+    Ok(Condition::Sustained {
+        metric,
+        comparison,
+        threshold,
+        duration,
+    })
+}
+
+fn parse_repeated(event: &str, window: Duration) -> Result<Condition, RuleError> {
+    let (event, count) = event.rsplit_once(' ').ok_or(RuleError::Form)?;
+    let count = count
+        .strip_suffix('x')
+        .and_then(|n| n.parse().ok())
+        .filter(|&n| n > 0)
+        .ok_or_else(|| RuleError::Count(count.into()))?;
+    let event = match event {
+        "pool disconnected" => Event::PoolDisconnected,
+        "board disconnected" => Event::BoardDisconnected,
+        _ => return Err(RuleError::Event(event.into())),
+    };
+    Ok(Condition::Repeated {
+        event,
+        count,
+        window,
+    })
+}
+
+fn parse_duration(s: &str) -> Result<Duration, RuleError> {
+    let invalid = || RuleError::Duration(s.into());
+    let split = s.len().checked_sub(1).ok_or_else(invalid)?;
+    let (number, unit) = s.split_at(split);
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .filter(|&n| n > 0)
+        .and_then(|n| n.checked_mul(scale))
+        .map(Duration::from_secs)
+        .ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn condition(rule: &str) -> Condition {
+        rule.parse::<AlertRule>().unwrap().condition
+    }
+
+    #[test]
+    fn parses_both_forms() {
+        assert_eq!(
+            condition("temp > 80 for 5m"),
+            Condition::Sustained {
+                metric: Metric::Temp,
+                comparison: Comparison::Above,
+                threshold: Threshold::Value(80.0),
+                duration: Duration::from_secs(300),
+            }
+        );
+        assert_eq!(
+            condition("hashrate  <  50% of expected for 10m"),
+            Condition::Sustained {
+                metric: Metric::Hashrate,
+                comparison: Comparison::Below,
+                threshold: Threshold::PercentOfExpected(50.0),
+                duration: Duration::from_secs(600),
+            }
+        );
+        assert!(matches!(
+            condition("hashrate <= 500G for 30s"),
+            Condition::Sustained {
+                threshold: Threshold::Value(v),
+                ..
+            } if v == 500e9
+        ));
+        assert_eq!(
+            condition("pool disconnected 3x in 1h"),
+            Condition::Repeated {
+                event: Event::PoolDisconnected,
+                count: 3,
+                window: Duration::from_secs(3600),
+            }
+        );
+
+        let rule: AlertRule = "temp >= 75.5 for 1d".parse().unwrap();
+        assert_eq!(rule.to_string(), "temp >= 75.5 for 1d");
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        let error = |rule: &str| rule.parse::<AlertRule>().unwrap_err();
+        assert_eq!(error("temp > 80"), RuleError::Form);
+        assert_eq!(error("fan > 80 for 5m"), RuleError::Metric("fan".into()));
+        assert_eq!(error("temp = 80 for 5m"), RuleError::Operator("=".into()));
+        assert_eq!(
+            error("temp > 80% for 5m"),
+            RuleError::Threshold("80%".into())
+        );
+        assert_eq!(error("temp > 80 for 5"), RuleError::Duration("5".into()));
+        assert_eq!(error("temp > 80 for 0m"), RuleError::Duration("0m".into()));
+        assert_eq!(
+            error("pool disconnected 0x in 1h"),
+            RuleError::Count("0x".into())
+        );
+        assert_eq!(
+            error("fan stopped 1x in 1h"),
+            RuleError::Event("fan stopped".into())
+        );
+    }
+}
@@ -1,18 +1,36 @@
 //! Command types sent from API handlers to backend components.
 //!
 //! Each command carries a oneshot reply channel so the handler can
-//! await the result and translate it into an HTTP response.
+//! await the result and translate it into an HTTP response. Handlers
+//! send every command through a [`CommandBus`], which routes it to the
+//! component that carries it out.
+
+use std::time::Duration;
 
 use anyhow::Result;
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::api_client::types::{
+    BurnInRequest, BurnInStatus, ChipRegisterDump, PauseLevel, Profile,
+};
 
 /// Commands from the API to the scheduler.
 pub enum SchedulerCommand {
-    /// Pause job distribution to all threads.
-    PauseMining { reply: oneshot::Sender<Result<()>> },
+    /// Pause mining at a level, or move an existing pause to it.
+    PauseMining {
+        level: PauseLevel,
+        reply: oneshot::Sender<Result<()>>,
+    },
 
     /// Resume job distribution after a pause.
     ResumeMining { reply: oneshot::Sender<Result<()>> },
+
+    /// Mine a shared source whenever it has work, whatever its health
+    /// score, or hand the choice back to failover with `None`.
+    PreferSource {
+        source: Option<String>,
+        reply: oneshot::Sender<Result<()>>,
+    },
 }
 
 /// Commands from the API to board management.
@@ -25,4 +43,242 @@ pub enum BoardCommand {
         percent: Option<u8>,
         reply: oneshot::Sender<Result<()>>,
     },
+
+    /// Set a board's core frequency, or return it to the profile's.
+    SetFrequency {
+        board: String,
+        /// Core frequency in MHz, or None for the profile's.
+        frequency_mhz: Option<f32>,
+        reply: oneshot::Sender<Result<()>>,
+    },
+
+    /// Take a board's hash threads out of service, leaving the board up.
+    Disable {
+        board: String,
+        reply: oneshot::Sender<Result<()>>,
+    },
+
+    /// Bring a disabled board back into service with fresh hash threads.
+    Enable {
+        board: String,
+        reply: oneshot::Sender<Result<()>>,
+    },
+
+    /// Read back a chip's registers on a specific board.
+    DumpRegisters {
+        board: String,
+        chip_address: u8,
+        /// Compare against the values written during initialization.
+        diff: bool,
+        reply: oneshot::Sender<Result<ChipRegisterDump>>,
+    },
+
+    /// Start replacing a board's management firmware.
+    ///
+    /// Answers once the update is under way, not when it finishes.
+    UpdateFirmware {
+        board: String,
+        image: Vec<u8>,
+        reply: oneshot::Sender<Result<()>>,
+    },
+
+    /// Start a burn-in on a specific board.
+    ///
+    /// Answers once the first step is under way, not when the run ends.
+    StartBurnIn {
+        board: String,
+        request: BurnInRequest,
+        reply: oneshot::Sender<Result<()>>,
+    },
+
+    /// Report a board's running burn-in and its last result.
+    BurnInStatus {
+        board: String,
+        reply: oneshot::Sender<Result<BurnInStatus>>,
+    },
+
+    /// Power every board's chips down for a pause, or back up after one.
+    ///
+    /// Boards disabled individually stay disabled either way.
+    SetPowered {
+        powered: bool,
+        reply: oneshot::Sender<Result<()>>,
+    },
+
+    /// Switch every board to an operating profile.
+    SetProfile {
+        profile: Profile,
+        reply: oneshot::Sender<Result<()>>,
+    },
+}
+
+/// How long a command gets to answer unless it says otherwise.
+pub const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Any command, tagged with the component it goes to.
+pub enum Command {
+    Scheduler(SchedulerCommand),
+    Board(BoardCommand),
+}
+
+impl From<SchedulerCommand> for Command {
+    fn from(cmd: SchedulerCommand) -> Self {
+        Self::Scheduler(cmd)
+    }
+}
+
+impl From<BoardCommand> for Command {
+    fn from(cmd: BoardCommand) -> Self {
+        Self::Board(cmd)
+    }
+}
+
+/// Why a command has no result.
+#[derive(Debug, thiserror::Error)]
+pub enum CommandError {
+    #[error("{0} is not running")]
+    Unavailable(&'static str),
+
+    #[error("no reply within {0:?}")]
+    Timeout(Duration),
+
+    #[error(transparent)]
+    Failed(anyhow::Error),
+}
+
+/// Single path for API commands to the components that carry them out.
+#[derive(Clone)]
+pub struct CommandBus {
+    scheduler: mpsc::Sender<SchedulerCommand>,
+    boards: mpsc::Sender<BoardCommand>,
+}
+
+impl CommandBus {
+    pub fn new(
+        scheduler: mpsc::Sender<SchedulerCommand>,
+        boards: mpsc::Sender<BoardCommand>,
+    ) -> Self {
+        Self { scheduler, boards }
+    }
+
+    /// Send the command built by `make_cmd` around a reply channel, and
+    /// wait up to [`COMMAND_TIMEOUT`] for its result.
+    pub async fn request<T, C>(
+        &self,
+        make_cmd: impl FnOnce(oneshot::Sender<Result<T>>) -> C,
+    ) -> Result<T, CommandError>
+    where
+        C: Into<Command>,
+    {
+        self.request_within(COMMAND_TIMEOUT, make_cmd).await
+    }
+
+    /// As [`request`](Self::request), for commands that take longer.
+    pub async fn request_within<T, C>(
+        &self,
+        timeout: Duration,
+        make_cmd: impl FnOnce(oneshot::Sender<Result<T>>) -> C,
+    ) -> Result<T, CommandError>
+    where
+        C: Into<Command>,
+    {
+        let (tx, rx) = oneshot::channel();
+        self.route(make_cmd(tx).into()).await?;
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(result)) => result.map_err(CommandError::Failed),
+            // The component dropped the command without answering
+            Ok(Err(_)) => Err(CommandError::Failed(anyhow::anyhow!("command dropped"))),
+            Err(_) => Err(CommandError::Timeout(timeout)),
+        }
+    }
+
+    /// Hand a command to the component that carries it out.
+    async fn route(&self, cmd: Command) -> Result<(), CommandError> {
+        match cmd {
+            Command::Scheduler(cmd) => self
+                .scheduler
+                .send(cmd)
+                .await
+                .map_err(|_| CommandError::Unavailable("scheduler")),
+            Command::Board(cmd) => self
+                .boards
+                .send(cmd)
+                .await
+                .map_err(|_| CommandError::Unavailable("backplane")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn routes_each_command_to_its_component() {
+        let (scheduler_tx, mut scheduler_rx) = mpsc::channel(1);
+        let (board_tx, mut board_rx) = mpsc::channel(1);
+        let bus = CommandBus::new(scheduler_tx, board_tx);
+
+        let request = tokio::spawn({
+            let bus = bus.clone();
+            async move {
+                bus.request(|reply| SchedulerCommand::ResumeMining { reply })
+                    .await
+            }
+        });
+        let Some(SchedulerCommand::ResumeMining { reply }) = scheduler_rx.recv().await else {
+            panic!("expected ResumeMining");
+        };
+        reply.send(Ok(())).unwrap();
+        assert!(request.await.unwrap().is_ok());
+
+        let request = tokio::spawn({
+            let bus = bus.clone();
+            async move {
+                bus.request(|reply| BoardCommand::SetPowered {
+                    powered: true,
+                    reply,
+                })
+                .await
+            }
+        });
+        let Some(BoardCommand::SetPowered { reply, .. }) = board_rx.recv().await else {
+            panic!("expected SetPowered");
+        };
+        reply.send(Err(anyhow::anyhow!("no boards"))).unwrap();
+        assert!(matches!(
+            request.await.unwrap(),
+            Err(CommandError::Failed(_))
+        ));
+
+        drop(board_rx);
+        let result = bus
+            .request(|reply| BoardCommand::SetPowered {
+                powered: true,
+                reply,
+            })
+            .await;
+        assert!(matches!(
+            result,
+            Err(CommandError::Unavailable("backplane"))
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unanswered_command_times_out() {
+        let (scheduler_tx, mut scheduler_rx) = mpsc::channel(1);
+        let (board_tx, _board_rx) = mpsc::channel(1);
+        let bus = CommandBus::new(scheduler_tx, board_tx);
+
+        let request = tokio::spawn(async move {
+            bus.request(|reply| SchedulerCommand::ResumeMining { reply })
+                .await
+        });
+        // Held, never answered
+        let _cmd = scheduler_rx.recv().await;
+        assert!(matches!(
+            request.await.unwrap(),
+            Err(CommandError::Timeout(COMMAND_TIMEOUT))
+        ));
+    }
 }
@@ -0,0 +1,221 @@
+//! Prometheus text exposition of the miner state.
+//!
+//! Rendered from the same [`MinerState`] snapshot the JSON API serves, so
+//! the two never disagree.
+
+use std::fmt::Write;
+
+use crate::api_client::types::{MinerState, SensorKind, ShareDifficultyHistogram};
+
+/// Content type of the Prometheus text format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Render `state` in the Prometheus text format.
+pub fn render(state: &MinerState) -> String {
+    let mut out = String::new();
+
+    metric_header(
+        &mut out,
+        "mujina_hashrate",
+        "gauge",
+        "Measured hashrate in hashes per second.",
+    );
+    let _ = writeln!(out, "mujina_hashrate {}", state.hashrate);
+
+    metric_header(
+        &mut out,
+        "mujina_shares_submitted_total",
+        "counter",
+        "Shares submitted to job sources.",
+    );
+    let _ = writeln!(
+        out,
+        "mujina_shares_submitted_total {}",
+        state.shares_submitted
+    );
+
+    metric_header(
+        &mut out,
+        "mujina_thread_share_difficulty",
+        "histogram",
+        "Difficulty achieved by the shares each hash thread found.",
+    );
+    for thread in &state.scheduling {
+        histogram(
+            &mut out,
+            "mujina_thread_share_difficulty",
+            ("thread", &thread.name),
+            &thread.share_difficulties,
+        );
+    }
+
+    metric_header(
+        &mut out,
+        "mujina_source_share_difficulty",
+        "histogram",
+        "Difficulty achieved by the shares found on each source's jobs.",
+    );
+    for source in &state.sources {
+        histogram(
+            &mut out,
+            "mujina_source_share_difficulty",
+            ("source", &source.name),
+            &source.share_difficulties,
+        );
+    }
+
+    for (kind, name, help) in [
+        (
+            SensorKind::AmbientTemperature,
+            "mujina_external_temperature_celsius",
+            "Room temperature pushed by an external sensor.",
+        ),
+        (
+            SensorKind::Power,
+            "mujina_external_power_watts",
+            "Power draw pushed by an external sensor.",
+        ),
+    ] {
+        metric_header(&mut out, name, "gauge", help);
+        for sensor in state.sensors.iter().filter(|s| s.kind == kind) {
+            let _ = writeln!(
+                out,
+                "{name}{{sensor=\"{}\"}} {}",
+                escape_label(&sensor.name),
+                sensor.value
+            );
+        }
+    }
+
+    out
+}
+
+fn metric_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Write one histogram's series, with cumulative buckets from `le="1"` up
+/// to the highest bucket holding a share.
+fn histogram(
+    out: &mut String,
+    name: &str,
+    (label, value): (&str, &str),
+    histogram: &ShareDifficultyHistogram,
+) {
+    let value = escape_label(value);
+    let top = histogram
+        .buckets
+        .last()
+        .map_or(1, |b| match b.min_difficulty {
+            0 => 1,
+            min => min.saturating_mul(2),
+        });
+
+    let mut le: u64 = 1;
+    let mut cumulative = 0;
+    let mut buckets = histogram.buckets.iter().peekable();
+    loop {
+        while let Some(bucket) = buckets.next_if(|b| b.min_difficulty < le) {
+            cumulative += bucket.count;
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{{label}=\"{value}\",le=\"{le}\"}} {cumulative}"
+        );
+        if le >= top {
+            break;
+        }
+        le = le.saturating_mul(2);
+    }
+    let _ = writeln!(
+        out,
+        "{name}_bucket{{{label}=\"{value}\",le=\"+Inf\"}} {}",
+        histogram.count
+    );
+    let _ = writeln!(out, "{name}_sum{{{label}=\"{value}\"}} {}", histogram.sum);
+    let _ = writeln!(
+        out,
+        "{name}_count{{{label}=\"{value}\"}} {}",
+        histogram.count
+    );
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('\n', r"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_client::types::{ExternalSensor, ShareDifficultyBucket, ThreadScheduling};
+
+    #[test]
+    fn renders_cumulative_difficulty_buckets() {
+        let state = MinerState {
+            scheduling: vec![ThreadScheduling {
+                name: "Bitaxe \"Gamma\"".into(),
+                share_difficulties: ShareDifficultyHistogram {
+                    count: 4,
+                    sum: 13.5,
+                    buckets: vec![
+                        ShareDifficultyBucket {
+                            min_difficulty: 0,
+                            count: 1,
+                        },
+                        ShareDifficultyBucket {
+                            min_difficulty: 4,
+                            count: 3,
+                        },
+                    ],
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let text = render(&state);
+        let series = |le: &str| {
+            format!(
+                "mujina_thread_share_difficulty_bucket{{thread=\"Bitaxe \\\"Gamma\\\"\",le=\"{le}\"}}"
+            )
+        };
+        for (le, count) in [("1", 1), ("2", 1), ("4", 1), ("8", 4), ("+Inf", 4)] {
+            assert!(
+                text.contains(&format!("{} {count}\n", series(le))),
+                "le={le} missing from:\n{text}"
+            );
+        }
+        assert!(!text.contains(&series("16")));
+        assert!(text.contains("# TYPE mujina_thread_share_difficulty histogram\n"));
+        assert!(
+            text.contains(
+                "mujina_thread_share_difficulty_count{thread=\"Bitaxe \\\"Gamma\\\"\"} 4\n"
+            )
+        );
+    }
+
+    #[test]
+    fn renders_external_sensors_by_kind() {
+        let sensor = |name: &str, kind, value| ExternalSensor {
+            name: name.into(),
+            kind,
+            value,
+            age_secs: 0,
+        };
+        let state = MinerState {
+            sensors: vec![
+                sensor("shed", SensorKind::AmbientTemperature, 31.5),
+                sensor("plug", SensorKind::Power, 184.0),
+            ],
+            ..Default::default()
+        };
+
+        let text = render(&state);
+        assert!(text.contains("mujina_external_temperature_celsius{sensor=\"shed\"} 31.5\n"));
+        assert!(text.contains("mujina_external_power_watts{sensor=\"plug\"} 184\n"));
+    }
+}
@@ -5,8 +5,15 @@
 //! require authentication for local access.
 
 pub mod commands;
-mod registry;
+mod metrics;
+mod paging;
 mod server;
+mod socket;
+mod store;
+mod stream;
 mod v0;
+mod v1;
+mod versions;
 
 pub use server::{ApiConfig, serve};
+pub use socket::claim_activation;
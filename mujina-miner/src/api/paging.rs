@@ -0,0 +1,189 @@
+//! Paging, time-range filtering and field selection for list endpoints.
+//!
+//! Every endpoint that lists a log or history takes the same query
+//! parameters, so a client written for one can page through any:
+//!
+//! - `limit`: entries per page (default [`DEFAULT_LIMIT`], at most
+//!   [`MAX_LIMIT`])
+//! - `cursor`: the `next_cursor` of the previous page
+//! - `since_ms`, `until_ms`: only entries from that time on, and from
+//!   before that time, in milliseconds since the Unix epoch
+//! - `fields`: comma-separated names of the fields to return, e.g.
+//!   `job_id,outcome`, for clients that only need a few
+//!
+//! Lists are newest first. A cursor names the last entry handed out
+//! rather than an offset, so entries added while a client pages don't
+//! shift the pages it has yet to fetch.
+
+use serde::{Deserialize, Serialize};
+
+use crate::api_client::types::Page;
+
+/// Entries per page when the client doesn't ask for a number.
+pub const DEFAULT_LIMIT: usize = 100;
+
+/// Most entries handed out per page.
+pub const MAX_LIMIT: usize = 1000;
+
+/// Query parameters common to list endpoints.
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct ListQuery {
+    cursor: Option<String>,
+    limit: Option<usize>,
+    since_ms: Option<u64>,
+    until_ms: Option<u64>,
+    fields: Option<String>,
+}
+
+/// An entry of a list.
+pub(crate) struct Entry<T> {
+    /// Number that grows with every entry added to the list.
+    pub number: u64,
+    /// When the entry was recorded, in milliseconds since the Unix epoch.
+    pub at_ms: u64,
+    pub value: T,
+}
+
+/// Errors from list query parameters.
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub(crate) enum ListError {
+    #[error("invalid cursor")]
+    InvalidCursor,
+    #[error("limit must be at least 1")]
+    InvalidLimit,
+}
+
+impl ListQuery {
+    /// Cut one page out of `entries`, which must be newest first.
+    pub fn page<T: Serialize>(
+        &self,
+        entries: impl IntoIterator<Item = Entry<T>>,
+    ) -> Result<Page, ListError> {
+        let limit = match self.limit {
+            Some(0) => return Err(ListError::InvalidLimit),
+            Some(limit) => limit.min(MAX_LIMIT),
+            None => DEFAULT_LIMIT,
+        };
+        let before = self
+            .cursor
+            .as_deref()
+            .map(|cursor| cursor.parse::<u64>().map_err(|_| ListError::InvalidCursor))
+            .transpose()?;
+        let fields: Option<Vec<&str>> = self
+            .fields
+            .as_deref()
+            .map(|fields| fields.split(',').map(str::trim).collect());
+
+        let mut matching = entries.into_iter().filter(|entry| {
+            before.is_none_or(|before| entry.number < before)
+                && self.since_ms.is_none_or(|since| entry.at_ms >= since)
+                && self.until_ms.is_none_or(|until| entry.at_ms < until)
+        });
+
+        let mut items = Vec::new();
+        let mut last = None;
+        for entry in matching.by_ref().take(limit) {
+            last = Some(entry.number);
+            items.push(select(&entry.value, fields.as_deref()));
+        }
+        let more = matching.next().is_some();
+        Ok(Page {
+            items,
+            next_cursor: last.filter(|_| more).map(|number| number.to_string()),
+        })
+    }
+}
+
+/// Serialize an entry, keeping only `fields` if given.
+fn select<T: Serialize>(value: &T, fields: Option<&[&str]>) -> serde_json::Value {
+    let mut value = serde_json::to_value(value).unwrap_or_default();
+    if let (Some(fields), serde_json::Value::Object(map)) = (fields, &mut value) {
+        map.retain(|key, _| fields.contains(&key.as_str()));
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Item {
+        n: u64,
+        label: &'static str,
+    }
+
+    /// Entries numbered 10 down to 1, a second apart.
+    fn entries() -> impl Iterator<Item = Entry<Item>> {
+        (1..=10).rev().map(|n| Entry {
+            number: n,
+            at_ms: n * 1000,
+            value: Item { n, label: "x" },
+        })
+    }
+
+    fn numbers(page: &Page) -> Vec<u64> {
+        page.items
+            .iter()
+            .map(|item| item["n"].as_u64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn pages_follow_the_cursor_to_the_end() {
+        let mut query = ListQuery {
+            limit: Some(4),
+            ..Default::default()
+        };
+        let first = query.page(entries()).unwrap();
+        assert_eq!(numbers(&first), [10, 9, 8, 7]);
+
+        query.cursor = first.next_cursor;
+        let second = query.page(entries()).unwrap();
+        assert_eq!(numbers(&second), [6, 5, 4, 3]);
+
+        query.cursor = second.next_cursor;
+        let last = query.page(entries()).unwrap();
+        assert_eq!(numbers(&last), [2, 1]);
+        assert_eq!(last.next_cursor, None);
+    }
+
+    #[test]
+    fn filters_by_time_range() {
+        let query = ListQuery {
+            since_ms: Some(3000),
+            until_ms: Some(6000),
+            ..Default::default()
+        };
+        let page = query.page(entries()).unwrap();
+        assert_eq!(numbers(&page), [5, 4, 3]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn selects_fields() {
+        let query = ListQuery {
+            limit: Some(1),
+            fields: Some("n".into()),
+            ..Default::default()
+        };
+        let page = query.page(entries()).unwrap();
+        assert_eq!(page.items, [json!({ "n": 10 })]);
+    }
+
+    #[test]
+    fn rejects_bad_parameters() {
+        let query = ListQuery {
+            cursor: Some("abc".into()),
+            ..Default::default()
+        };
+        assert_eq!(query.page(entries()), Err(ListError::InvalidCursor));
+        let query = ListQuery {
+            limit: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(query.page(entries()), Err(ListError::InvalidLimit));
+    }
+}
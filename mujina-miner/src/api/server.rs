@@ -1,47 +1,82 @@
 //! HTTP server lifecycle and router construction.
 
-use std::sync::{Arc, Mutex};
+use std::future::IntoFuture;
+use std::path::PathBuf;
+use std::sync::Arc;
 
 use anyhow::Result;
-use axum::{Router, response::Redirect, routing};
+use axum::{Extension, Router, middleware, response::Redirect, routing};
 use tokio::net::TcpListener;
-use tokio::sync::{mpsc, watch};
+use tokio::sync::{broadcast, watch};
 use tokio_util::sync::CancellationToken;
 use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
 use tracing::{Level, info, warn};
 use utoipa_axum::router::OpenApiRouter;
 use utoipa_swagger_ui::SwaggerUi;
 
-use super::{commands::SchedulerCommand, registry::BoardRegistry, v0};
-use crate::api_client::types::MinerState;
+use super::{
+    commands::CommandBus,
+    socket::{AdminConnection, AdminSocket},
+    store::StateStore,
+    v0, v1, versions,
+};
+use crate::alerts::AlertHistory;
+use crate::api_client::types::{BuildInfo, MinerState, Profile, ReadinessReport};
 use crate::board::BoardRegistration;
+use crate::build_info;
+use crate::config::Config;
+use crate::events::{BoardEvent, EventBus};
+use crate::self_check::{self, StartupChecks};
+use crate::share_audit::ShareAudit;
 
 /// API server configuration.
 #[derive(Debug, Clone)]
 pub struct ApiConfig {
     /// Address and port to bind the API server to.
     pub bind_addr: String,
+    /// Local admin socket to serve the API on as well, unless one is
+    /// passed by socket activation.
+    pub socket_path: Option<PathBuf>,
+    /// Operating profile the boards start with.
+    pub profile: Profile,
+    /// User agent the miner presents to pools, reported by `/version`.
+    pub user_agent: String,
+    /// Self-check results from before the server started.
+    pub startup_checks: StartupChecks,
+    /// Configuration the miner is running with, served by `/config`.
+    pub config: Config,
+    /// Recent alerts, served by `/alerts`.
+    pub alerts: AlertHistory,
 }
 
 /// Shared application state available to all handlers.
 #[derive(Clone)]
 pub(crate) struct SharedState {
-    pub miner_state_rx: watch::Receiver<MinerState>,
-    pub board_registry: Arc<Mutex<BoardRegistry>>,
-    pub scheduler_cmd_tx: mpsc::Sender<SchedulerCommand>,
+    /// Latest state published by the scheduler and boards
+    pub store: StateStore,
+    /// Where handlers send every mutation
+    pub commands: CommandBus,
+    pub build_info: Arc<BuildInfo>,
+    pub share_audit: ShareAudit,
+    pub alerts: AlertHistory,
+    pub startup_checks: Arc<StartupChecks>,
+    /// Miner events, for the event stream
+    pub events: EventBus,
+    /// Configuration the miner is running with
+    pub config: Arc<Config>,
 }
 
 impl SharedState {
     /// Build a complete MinerState by combining scheduler data with board
-    /// snapshots from the registry.
+    /// snapshots from the store.
     pub fn miner_state(&self) -> MinerState {
-        let mut state = self.miner_state_rx.borrow().clone();
-        state.boards = self
-            .board_registry
-            .lock()
-            .unwrap_or_else(|e| e.into_inner())
-            .boards();
-        state
+        self.store.miner_state()
+    }
+
+    /// Self-check report as of now.
+    pub fn readiness(&self) -> ReadinessReport {
+        let state = self.miner_state();
+        self.startup_checks.report(&state.boards, &state.sources)
     }
 }
 
@@ -51,33 +86,85 @@ impl SharedState {
 /// cancellation token is triggered. It binds to localhost only by default for
 /// security.
 ///
-/// Board registrations arrive via `board_reg_rx` as boards connect. The
-/// server manages the collection internally and cleans up when boards
-/// disconnect.
+/// Boards arrive on `board_events` as they connect; the receiver is
+/// taken from `events` by the caller before any board can connect, so
+/// none are missed. The server manages the collection internally and
+/// cleans up when boards disconnect.
 pub async fn serve(
     config: ApiConfig,
     shutdown: CancellationToken,
     miner_state_rx: watch::Receiver<MinerState>,
-    mut board_reg_rx: mpsc::Receiver<BoardRegistration>,
-    scheduler_cmd_tx: mpsc::Sender<SchedulerCommand>,
+    events: EventBus,
+    mut board_events: broadcast::Receiver<BoardEvent>,
+    commands: CommandBus,
+    share_audit: ShareAudit,
 ) -> Result<()> {
-    let board_registry = Arc::new(Mutex::new(BoardRegistry::new()));
+    let store = StateStore::new(miner_state_rx, config.profile);
 
-    // Drain board registrations into the registry as they arrive.
-    // Exits when the sender is dropped (backplane shutdown).
+    // Add boards to the store as they arrive. Removals need no handling:
+    // the store drops boards whose state channel has closed.
     tokio::spawn({
-        let registry = board_registry.clone();
+        let store = store.clone();
+        let shutdown = shutdown.clone();
         async move {
-            while let Some(reg) = board_reg_rx.recv().await {
-                registry.lock().unwrap_or_else(|e| e.into_inner()).push(reg);
+            loop {
+                let event = tokio::select! {
+                    event = board_events.recv() => event,
+                    _ = shutdown.cancelled() => break,
+                };
+                match event {
+                    Ok(BoardEvent::Added { state_rx, .. }) => {
+                        store.register_board(BoardRegistration { state_rx });
+                    }
+                    Ok(BoardEvent::Removed { .. }) => {}
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!(
+                            missed,
+                            "Missed board events; boards may be absent from the API"
+                        );
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
             }
         }
     });
 
-    let app = build_router(miner_state_rx, board_registry, scheduler_cmd_tx);
-
-    let listener = TcpListener::bind(&config.bind_addr).await?;
+    let mut startup_checks = config.startup_checks;
+    let listener = match TcpListener::bind(&config.bind_addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            startup_checks.api = self_check::check_api(Err(e.to_string()));
+            self_check::log_report(&self_check::report(vec![
+                startup_checks.config,
+                startup_checks.api,
+                startup_checks.usb,
+            ]));
+            return Err(e.into());
+        }
+    };
     let actual_addr = listener.local_addr()?;
+    let admin_socket = match AdminSocket::activated()? {
+        Some(socket) => Some(socket),
+        None => match config.socket_path {
+            Some(ref path) => Some(AdminSocket::bind(path).map_err(|e| {
+                anyhow::anyhow!("failed to bind admin socket {}: {}", path.display(), e)
+            })?),
+            None => None,
+        },
+    };
+    startup_checks.api = self_check::check_api(Ok(actual_addr.to_string()));
+
+    let state = SharedState {
+        store,
+        commands,
+        build_info: Arc::new(build_info::build_info(&config.user_agent)),
+        share_audit,
+        alerts: config.alerts,
+        startup_checks: Arc::new(startup_checks),
+        events,
+        config: Arc::new(config.config),
+    };
+    let app = build_router(state.clone());
 
     info!(url = %format!("http://{}", actual_addr), "API server listening.");
 
@@ -90,37 +177,75 @@ pub async fn serve(
         );
     }
 
+    // Log the self-check once boards and pools have had time to come up
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            tokio::select! {
+                _ = tokio::time::sleep(self_check::SETTLE_TIME) => {}
+                _ = shutdown.cancelled() => return,
+            }
+            self_check::log_report(&state.readiness());
+        }
+    });
+
     // Run server with graceful shutdown
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async move {
-            shutdown.cancelled().await;
-        })
-        .await?;
+    let tcp = axum::serve(listener, app.clone())
+        .with_graceful_shutdown(shutdown.clone().cancelled_owned())
+        .into_future();
+    match admin_socket {
+        Some(socket) => {
+            if let Some(path) = socket.path() {
+                info!(path = %path.display(), "API also listening on admin socket.");
+            }
+            let (socket, _file) = socket.into_parts();
+            let unix = axum::serve(socket, app.layer(Extension(AdminConnection)))
+                .with_graceful_shutdown(shutdown.cancelled_owned())
+                .into_future();
+            tokio::try_join!(tcp, unix)?;
+        }
+        None => tcp.await?,
+    }
 
     Ok(())
 }
 
 /// Build the application router with all API routes.
-pub(crate) fn build_router(
-    miner_state_rx: watch::Receiver<MinerState>,
-    board_registry: Arc<Mutex<BoardRegistry>>,
-    scheduler_cmd_tx: mpsc::Sender<SchedulerCommand>,
-) -> Router {
-    let state = SharedState {
-        miner_state_rx,
-        board_registry,
-        scheduler_cmd_tx,
-    };
-
-    let (router, api) = OpenApiRouter::new()
-        .nest("/api/v0", v0::routes())
+///
+/// Each version has its own OpenAPI spec. v0 responses for endpoints
+/// that v1 has frozen are marked deprecated (see [`versions`]).
+pub(crate) fn build_router(state: SharedState) -> Router {
+    let (v1_router, v1_api) = OpenApiRouter::new()
+        .nest(&versions::prefix("v1"), v1::routes())
+        .with_state(state.clone())
+        .split_for_parts();
+    let successors: versions::Successors = Arc::new(
+        v1_api
+            .paths
+            .paths
+            .keys()
+            .filter_map(|path| path.strip_prefix(&versions::prefix("v1")))
+            .map(str::to_string)
+            .collect(),
+    );
+    let (v0_router, v0_api) = OpenApiRouter::new()
+        .nest(&versions::prefix("v0"), v0::routes())
         .with_state(state)
         .split_for_parts();
+    let v0_router = v0_router.layer(middleware::from_fn_with_state(
+        successors,
+        versions::mark_superseded,
+    ));
 
-    router
+    v0_router
+        .merge(v1_router)
         .route("/", routing::get(Redirect::permanent("/swagger-ui")))
-        .route("/api", routing::get(Redirect::permanent("/swagger-ui")))
-        .merge(SwaggerUi::new("/swagger-ui").url("/api/v0/openapi.json", api))
+        .route("/api", routing::get(versions::discover))
+        .merge(
+            SwaggerUi::new("/swagger-ui")
+                .url(versions::openapi_path("v1"), v1_api)
+                .url(versions::openapi_path("v0"), v0_api),
+        )
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(DefaultMakeSpan::new().level(Level::TRACE))
@@ -135,9 +260,13 @@ mod tests {
     use tower::ServiceExt;
 
     use super::*;
-    use crate::api::commands::SchedulerCommand;
-    use crate::api_client::types::{BoardState, SourceState};
+    use crate::api::commands::{BoardCommand, SchedulerCommand};
+    use crate::api_client::types::{
+        ApiVersionStatus, ApiVersions, BoardState, ChipNonceReport, ChipRegisterDump, PauseLevel,
+        ReadinessState, SourceHealthState, SourceState, ThreadScheduling, ThreadState,
+    };
     use crate::board::BoardRegistration;
+    use tokio::sync::mpsc;
 
     /// Test fixtures returned by the router builder.
     struct TestFixtures {
@@ -145,28 +274,61 @@ mod tests {
         /// Keep alive to prevent board watch channels from closing.
         _board_senders: Vec<watch::Sender<BoardState>>,
         /// Publish updated miner state (e.g. after handling a command).
-        _miner_tx: watch::Sender<MinerState>,
+        miner_tx: watch::Sender<MinerState>,
         /// Receives commands sent by PATCH handlers.
-        _cmd_rx: mpsc::Receiver<SchedulerCommand>,
+        cmd_rx: mpsc::Receiver<SchedulerCommand>,
+        /// Receives commands sent by board handlers.
+        board_cmd_rx: mpsc::Receiver<BoardCommand>,
+        /// Share audit log served by the router.
+        share_audit: ShareAudit,
+        /// Alert history served by the router.
+        alerts: AlertHistory,
+        /// Event bus relayed by the event stream.
+        events: EventBus,
     }
 
     fn build_test_router(miner_state: MinerState, board_states: Vec<BoardState>) -> TestFixtures {
+        build_test_router_with_config(miner_state, board_states, Config::default())
+    }
+
+    fn build_test_router_with_config(
+        miner_state: MinerState,
+        board_states: Vec<BoardState>,
+        config: Config,
+    ) -> TestFixtures {
         let (miner_tx, miner_rx) = watch::channel(miner_state);
         let (cmd_tx, cmd_rx) = mpsc::channel::<SchedulerCommand>(16);
+        let (board_cmd_tx, board_cmd_rx) = mpsc::channel::<BoardCommand>(16);
 
-        let mut registry = BoardRegistry::new();
+        let share_audit = ShareAudit::new(8);
+        let alerts = AlertHistory::new(8);
+        let events = EventBus::new();
+        let store = StateStore::new(miner_rx, Profile::default());
         let mut board_senders = Vec::new();
         for state in board_states {
             let (tx, rx) = watch::channel(state);
-            registry.push(BoardRegistration { state_rx: rx });
+            store.register_board(BoardRegistration { state_rx: rx });
             board_senders.push(tx);
         }
 
         TestFixtures {
-            router: build_router(miner_rx, Arc::new(Mutex::new(registry)), cmd_tx),
+            router: build_router(SharedState {
+                store,
+                commands: CommandBus::new(cmd_tx, board_cmd_tx),
+                build_info: Arc::new(build_info::build_info("test-agent/1.0")),
+                share_audit: share_audit.clone(),
+                alerts: alerts.clone(),
+                startup_checks: Arc::new(StartupChecks::default()),
+                events: events.clone(),
+                config: Arc::new(config),
+            }),
             _board_senders: board_senders,
-            _miner_tx: miner_tx,
-            _cmd_rx: cmd_rx,
+            miner_tx,
+            cmd_rx,
+            board_cmd_rx,
+            share_audit,
+            alerts,
+            events,
         }
     }
 
@@ -189,6 +351,74 @@ mod tests {
         assert_eq!(body, "OK");
     }
 
+    #[tokio::test]
+    async fn serves_the_api_on_the_admin_socket() {
+        let fixtures = build_test_router(MinerState::default(), vec![]);
+        let path = std::env::temp_dir().join(format!("mujina-api-{}.sock", std::process::id()));
+        let (listener, _file) = AdminSocket::bind(&path).unwrap().into_parts();
+        tokio::spawn(axum::serve(listener, fixtures.router.clone()).into_future());
+
+        let client = crate::api_client::Client::with_unix_socket(&path).unwrap();
+        assert_eq!(client.get_raw("health").await.unwrap(), "OK");
+    }
+
+    #[tokio::test]
+    async fn health_detail_fails_until_boards_and_pools_are_up() {
+        let fixtures = build_test_router(MinerState::default(), vec![]);
+        let (status, body) = get(fixtures.router.clone(), "/api/v0/health/detail").await;
+        assert_eq!(status, 503);
+        let report: ReadinessReport = serde_json::from_str(&body).unwrap();
+        assert_eq!(report.state, ReadinessState::Failed);
+
+        let miner_state = MinerState {
+            sources: vec![SourceState {
+                name: "pool".into(),
+                url: Some("stratum+tcp://localhost:3333".into()),
+                health: SourceHealthState {
+                    connected: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let board = BoardState {
+            name: "test-board".into(),
+            threads: vec![ThreadState {
+                name: "test-board-0".into(),
+                hashrate: 0,
+                is_active: true,
+                nonces: 0,
+                hardware_errors: 0,
+                chip_resets: 0,
+                quarantined: false,
+                link_errors: 0,
+                link_degraded: false,
+                job_dispatch: None,
+            }],
+            ..Default::default()
+        };
+        let fixtures = build_test_router(miner_state, vec![board]);
+        let (status, body) = get(fixtures.router.clone(), "/api/v0/health/detail").await;
+        assert_eq!(status, 200);
+        let report: ReadinessReport = serde_json::from_str(&body).unwrap();
+        assert_eq!(report.state, ReadinessState::Ready);
+        let names: Vec<&str> = report.checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["config", "api", "usb", "boards", "pools"]);
+    }
+
+    #[tokio::test]
+    async fn version_reports_build_info() {
+        let fixtures = build_test_router(MinerState::default(), vec![]);
+        let (status, body) = get(fixtures.router.clone(), "/api/v0/version").await;
+        assert_eq!(status, 200);
+
+        let info: BuildInfo = serde_json::from_str(&body).unwrap();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.user_agent, "test-agent/1.0");
+        assert!(info.build_date.is_some());
+    }
+
     #[tokio::test]
     async fn miner_includes_boards_and_sources() {
         let miner_state = MinerState {
@@ -272,6 +502,578 @@ mod tests {
         assert_eq!(status, 404);
     }
 
+    #[tokio::test]
+    async fn api_lists_versions() {
+        let fixtures = build_test_router(MinerState::default(), vec![]);
+        let (status, body) = get(fixtures.router.clone(), "/api").await;
+        assert_eq!(status, 200);
+
+        let versions: ApiVersions = serde_json::from_str(&body).unwrap();
+        assert_eq!(versions.stable, "v1");
+        let v0 = &versions.versions[0];
+        assert_eq!(v0.status, ApiVersionStatus::Unstable);
+        assert_eq!(v0.openapi, "/api/v0/openapi.json");
+        assert_eq!(versions.versions[1].path, "/api/v1");
+    }
+
+    #[tokio::test]
+    async fn v1_adapts_v0_state_and_v0_links_to_it() {
+        let thread = |name: &str, hashrate| ThreadState {
+            name: name.into(),
+            hashrate,
+            is_active: true,
+            nonces: 0,
+            hardware_errors: 0,
+            chip_resets: 0,
+            quarantined: false,
+            link_errors: 0,
+            link_degraded: false,
+            job_dispatch: None,
+        };
+        let board = BoardState {
+            name: "bitaxe-abc".into(),
+            model: "Bitaxe".into(),
+            threads: vec![thread("bitaxe-abc-0", 300), thread("bitaxe-abc-1", 200)],
+            ..Default::default()
+        };
+        let fixtures = build_test_router(MinerState::default(), vec![board]);
+
+        let (status, body) = get(fixtures.router.clone(), "/api/v1/boards/bitaxe-abc").await;
+        assert_eq!(status, 200);
+        let board: v1::Board = serde_json::from_str(&body).unwrap();
+        assert_eq!(board.hashrate, 500);
+        assert!(board.threads[0].active);
+
+        let (status, _) = get(fixtures.router.clone(), "/api/v1/scheduling").await;
+        assert_eq!(status, 404);
+
+        // v0 endpoints frozen in v1 point to their successor; the rest
+        // don't
+        let request = |uri: &str| {
+            let req = Request::builder()
+                .uri(uri)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            fixtures.router.clone().oneshot(req)
+        };
+        let resp = request("/api/v0/boards/bitaxe-abc").await.unwrap();
+        assert_eq!(resp.headers()["deprecation"], "true");
+        assert_eq!(
+            resp.headers()[http::header::LINK],
+            "</api/v1/boards/bitaxe-abc>; rel=\"successor-version\""
+        );
+        let resp = request("/api/v0/scheduling").await.unwrap();
+        assert_eq!(resp.status(), 200);
+        assert!(!resp.headers().contains_key("deprecation"));
+    }
+
+    #[tokio::test]
+    async fn board_nonces_served_apart_from_board_state() {
+        let board = BoardState {
+            name: "bitaxe-abc".into(),
+            nonce_reports: vec![ChipNonceReport {
+                thread: "BM1370".into(),
+                nonces: 1600,
+                weak_regions: vec![5],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let fixtures = build_test_router(MinerState::default(), vec![board]);
+
+        let (status, body) = get(fixtures.router.clone(), "/api/v0/boards/bitaxe-abc/nonces").await;
+        assert_eq!(status, 200);
+        let reports: Vec<ChipNonceReport> = serde_json::from_str(&body).unwrap();
+        assert_eq!(reports[0].nonces, 1600);
+        assert_eq!(reports[0].weak_regions, [5]);
+
+        let (_, body) = get(fixtures.router.clone(), "/api/v0/boards/bitaxe-abc").await;
+        assert!(!body.contains("weak_regions"));
+
+        let (status, _) = get(fixtures.router.clone(), "/api/v0/boards/nonexistent/nonces").await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn config_is_exported_without_secrets_and_imported_over_the_admin_socket() {
+        let path = std::env::temp_dir().join(format!("mujina-api-{}.toml", std::process::id()));
+        std::fs::write(&path, "[pool]\npassword = \"unit-secret\"\n").unwrap();
+        let running: Config = toml::from_str(
+            "[pool]\nurl = \"stratum+tcp://pool:3333\"\npassword = \"running-secret\"",
+        )
+        .unwrap();
+        let fixtures = build_test_router_with_config(
+            MinerState::default(),
+            vec![],
+            Config {
+                path: Some(path.clone()),
+                ..running
+            },
+        );
+
+        let (status, body) = get(fixtures.router.clone(), "/api/v0/config").await;
+        assert_eq!(status, 200);
+        assert!(!body.contains("secret"), "{body}");
+        let exported: Config = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            exported.pool.url.as_deref(),
+            Some("stratum+tcp://pool:3333")
+        );
+
+        let put = |body: String| {
+            Request::builder()
+                .method("PUT")
+                .uri("/api/v0/config")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let resp = fixtures
+            .router
+            .clone()
+            .oneshot(put(body.replace("3333", "4444")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 403);
+        assert_eq!(Config::load_from(&path).unwrap().pool.url, None);
+
+        let admin = fixtures.router.clone().layer(Extension(AdminConnection));
+        let resp = admin
+            .clone()
+            .oneshot(put(body.replace("3333", "4444")))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        let written = Config::load_from(&path).unwrap();
+        assert_eq!(written.pool.url.as_deref(), Some("stratum+tcp://pool:4444"));
+        assert_eq!(written.pool.password.as_deref(), Some("unit-secret"));
+
+        let resp = admin
+            .oneshot(put(r#"{"pool":{"url":"no port"}}"#.into()))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 422);
+        assert_eq!(Config::load_from(&path).unwrap(), written);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    async fn post(app: Router, uri: &str) -> http::StatusCode {
+        let req = Request::builder()
+            .method("POST")
+            .uri(uri)
+            .body(axum::body::Body::empty())
+            .unwrap();
+        app.oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn board_disable_routes_command_to_backplane() {
+        let board = BoardState {
+            name: "bitaxe-abc".into(),
+            ..Default::default()
+        };
+        let mut fixtures = build_test_router(MinerState::default(), vec![board]);
+
+        let request = tokio::spawn(post(
+            fixtures.router.clone(),
+            "/api/v0/boards/bitaxe-abc/disable",
+        ));
+        match fixtures.board_cmd_rx.recv().await {
+            Some(BoardCommand::Disable { board, reply }) => {
+                assert_eq!(board, "bitaxe-abc");
+                reply.send(Ok(())).unwrap();
+            }
+            _ => panic!("expected Disable command"),
+        }
+
+        assert_eq!(request.await.unwrap(), 204);
+    }
+
+    async fn patch(app: Router, uri: &str, body: &'static str) -> http::StatusCode {
+        let req = Request::builder()
+            .method("PATCH")
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(body))
+            .unwrap();
+        app.oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn power_down_pause_stops_scheduler_before_boards() {
+        let mut fixtures = build_test_router(MinerState::default(), vec![]);
+
+        let request = tokio::spawn(patch(
+            fixtures.router.clone(),
+            "/api/v0/miner",
+            r#"{"pause_level":"power_down"}"#,
+        ));
+        match fixtures.cmd_rx.recv().await {
+            Some(SchedulerCommand::PauseMining { level, reply }) => {
+                assert_eq!(level, PauseLevel::PowerDown);
+                fixtures.miner_tx.send_modify(|state| {
+                    state.paused = true;
+                    state.pause_level = Some(level);
+                });
+                reply.send(Ok(())).unwrap();
+            }
+            _ => panic!("expected PauseMining command"),
+        }
+        match fixtures.board_cmd_rx.recv().await {
+            Some(BoardCommand::SetPowered { powered, reply }) => {
+                assert!(!powered);
+                reply.send(Ok(())).unwrap();
+            }
+            _ => panic!("expected SetPowered command"),
+        }
+        assert_eq!(request.await.unwrap(), 200);
+
+        // Resuming powers the boards up before dispatch restarts
+        let request = tokio::spawn(patch(
+            fixtures.router.clone(),
+            "/api/v0/miner",
+            r#"{"paused":false}"#,
+        ));
+        match fixtures.board_cmd_rx.recv().await {
+            Some(BoardCommand::SetPowered { powered, reply }) => {
+                assert!(powered);
+                reply.send(Ok(())).unwrap();
+            }
+            _ => panic!("expected SetPowered command"),
+        }
+        match fixtures.cmd_rx.recv().await {
+            Some(SchedulerCommand::ResumeMining { reply }) => reply.send(Ok(())).unwrap(),
+            _ => panic!("expected ResumeMining command"),
+        }
+        assert_eq!(request.await.unwrap(), 200);
+    }
+
+    #[tokio::test]
+    async fn fan_target_routes_command_to_backplane() {
+        let board = BoardState {
+            name: "sim-0".into(),
+            ..Default::default()
+        };
+        let mut fixtures = build_test_router(MinerState::default(), vec![board]);
+
+        let req = Request::builder()
+            .method("PUT")
+            .uri("/api/v0/boards/sim-0/fans/fan")
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(r#"{"target_percent":40}"#))
+            .unwrap();
+        let request = tokio::spawn(fixtures.router.clone().oneshot(req));
+        match fixtures.board_cmd_rx.recv().await {
+            Some(BoardCommand::SetFanTarget {
+                board,
+                fan,
+                percent,
+                reply,
+            }) => {
+                assert_eq!((board.as_str(), fan.as_str()), ("sim-0", "fan"));
+                assert_eq!(percent, Some(40));
+                reply.send(Ok(())).unwrap();
+            }
+            _ => panic!("expected SetFanTarget command"),
+        }
+        assert_eq!(request.await.unwrap().unwrap().status(), 204);
+
+        let req = Request::builder()
+            .method("PUT")
+            .uri("/api/v0/boards/sim-0/fans/fan")
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(r#"{"target_percent":150}"#))
+            .unwrap();
+        let resp = fixtures.router.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn frequency_routes_command_to_backplane() {
+        let board = BoardState {
+            name: "sim-0".into(),
+            ..Default::default()
+        };
+        let mut fixtures = build_test_router(MinerState::default(), vec![board]);
+
+        let put = |body: &'static str| {
+            Request::builder()
+                .method("PUT")
+                .uri("/api/v0/boards/sim-0/frequency")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let request = tokio::spawn(
+            fixtures
+                .router
+                .clone()
+                .oneshot(put(r#"{"frequency_mhz":480}"#)),
+        );
+        match fixtures.board_cmd_rx.recv().await {
+            Some(BoardCommand::SetFrequency {
+                board,
+                frequency_mhz,
+                reply,
+            }) => {
+                assert_eq!(board, "sim-0");
+                assert_eq!(frequency_mhz, Some(480.0));
+                reply.send(Ok(())).unwrap();
+            }
+            _ => panic!("expected SetFrequency command"),
+        }
+        assert_eq!(request.await.unwrap().unwrap().status(), 204);
+
+        let resp = fixtures
+            .router
+            .clone()
+            .oneshot(put(r#"{"frequency_mhz":-1}"#))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn firmware_image_routes_to_backplane() {
+        let board = BoardState {
+            name: "bitaxe-abc".into(),
+            ..Default::default()
+        };
+        let mut fixtures = build_test_router(MinerState::default(), vec![board]);
+
+        let post = |body: &'static [u8]| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/v0/boards/bitaxe-abc/firmware")
+                .header("content-type", "application/octet-stream")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let request = tokio::spawn(fixtures.router.clone().oneshot(post(b"\x01\x02\x03")));
+        match fixtures.board_cmd_rx.recv().await {
+            Some(BoardCommand::UpdateFirmware {
+                board,
+                image,
+                reply,
+            }) => {
+                assert_eq!(board, "bitaxe-abc");
+                assert_eq!(image, [1, 2, 3]);
+                reply.send(Ok(())).unwrap();
+            }
+            _ => panic!("expected UpdateFirmware command"),
+        }
+        assert_eq!(request.await.unwrap().unwrap().status(), 202);
+
+        let resp = fixtures.router.clone().oneshot(post(b"")).await.unwrap();
+        assert_eq!(resp.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn preferred_source_routes_command_to_scheduler() {
+        let source = |name: &str, pinned: bool| SourceState {
+            name: name.into(),
+            pinned,
+            health: SourceHealthState::default(),
+            ..Default::default()
+        };
+        let miner_state = MinerState {
+            sources: vec![source("pool", false), source("solo", true)],
+            ..Default::default()
+        };
+        let mut fixtures = build_test_router(miner_state, vec![]);
+
+        let post = |body: &'static str| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/v0/miner/source")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(body))
+                .unwrap()
+        };
+        let request = tokio::spawn(
+            fixtures
+                .router
+                .clone()
+                .oneshot(post(r#"{"source":"pool"}"#)),
+        );
+        match fixtures.cmd_rx.recv().await {
+            Some(SchedulerCommand::PreferSource { source, reply }) => {
+                assert_eq!(source.as_deref(), Some("pool"));
+                reply.send(Ok(())).unwrap();
+            }
+            _ => panic!("expected PreferSource command"),
+        }
+        assert_eq!(request.await.unwrap().unwrap().status(), 200);
+
+        // Unknown and pinned sources are refused before reaching the scheduler
+        for (body, status) in [(r#"{"source":"nope"}"#, 404), (r#"{"source":"solo"}"#, 400)] {
+            let resp = fixtures.router.clone().oneshot(post(body)).await.unwrap();
+            assert_eq!(resp.status(), status);
+        }
+        assert!(fixtures.cmd_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn chip_registers_route_dump_request_to_backplane() {
+        let board = BoardState {
+            name: "bitaxe-abc".into(),
+            ..Default::default()
+        };
+        let mut fixtures = build_test_router(MinerState::default(), vec![board]);
+
+        let request = tokio::spawn(get(
+            fixtures.router.clone(),
+            "/api/v0/boards/bitaxe-abc/chips/2/registers?diff=true",
+        ));
+        match fixtures.board_cmd_rx.recv().await {
+            Some(BoardCommand::DumpRegisters {
+                board,
+                chip_address,
+                diff,
+                reply,
+            }) => {
+                assert_eq!(
+                    (board.as_str(), chip_address, diff),
+                    ("bitaxe-abc", 2, true)
+                );
+                reply
+                    .send(Ok(ChipRegisterDump {
+                        chip_address,
+                        mismatches: vec!["AnalogMux".into()],
+                        ..Default::default()
+                    }))
+                    .unwrap();
+            }
+            _ => panic!("expected DumpRegisters command"),
+        }
+
+        let (status, body) = request.await.unwrap();
+        assert_eq!(status, 200);
+        let dump: ChipRegisterDump = serde_json::from_str(&body).unwrap();
+        assert_eq!(dump.chip_address, 2);
+        assert_eq!(dump.mismatches, ["AnalogMux"]);
+
+        let (status, _) = get(
+            fixtures.router.clone(),
+            "/api/v0/boards/missing/chips/0/registers",
+        )
+        .await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn profile_switch_routes_command_and_reports_profile() {
+        let mut fixtures = build_test_router(MinerState::default(), vec![]);
+
+        let req = Request::builder()
+            .method("POST")
+            .uri("/api/v0/miner/profile")
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(r#"{"profile":"quiet"}"#))
+            .unwrap();
+        let request = tokio::spawn(fixtures.router.clone().oneshot(req));
+        match fixtures.board_cmd_rx.recv().await {
+            Some(BoardCommand::SetProfile { profile, reply }) => {
+                assert_eq!(profile, Profile::Quiet);
+                reply.send(Ok(())).unwrap();
+            }
+            _ => panic!("expected SetProfile command"),
+        }
+
+        let resp = request.await.unwrap().unwrap();
+        assert_eq!(resp.status(), 200);
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let state: MinerState = serde_json::from_slice(&body).unwrap();
+        assert_eq!(state.profile, Profile::Quiet);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stream_sends_state_then_material_deltas() {
+        let fixtures = build_test_router(MinerState::default(), vec![]);
+        let req = Request::builder()
+            .uri("/api/v0/stream")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let resp = fixtures.router.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), 200);
+        let mut body = resp.into_body();
+
+        let first = next_event(&mut body).await;
+        assert!(first.starts_with("event: state\ndata: {"), "{first}");
+
+        // Uptime ticking alone is not material
+        fixtures.miner_tx.send_modify(|s| s.uptime_secs = 1);
+        let quiet = tokio::time::timeout(std::time::Duration::from_secs(3), body.frame()).await;
+        assert!(quiet.is_err(), "unexpected event");
+
+        // A hashrate change is, and carries the new uptime along
+        fixtures.miner_tx.send_modify(|s| s.hashrate = 500);
+        let delta = next_event(&mut body).await;
+        let data = delta.strip_prefix("event: delta\ndata: ").unwrap();
+        let patch: serde_json::Value = serde_json::from_str(data.trim_end()).unwrap();
+        assert_eq!(
+            patch,
+            serde_json::json!({"hashrate": 500, "uptime_secs": 1})
+        );
+    }
+
+    #[tokio::test]
+    async fn event_stream_relays_chosen_topics() {
+        use crate::events::{PoolEvent, PoolEventKind, Pools, Thermal, ThermalEvent};
+
+        let fixtures = build_test_router(MinerState::default(), vec![]);
+        let req = Request::builder()
+            .uri("/api/v0/events?topics=pools")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let resp = fixtures.router.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), 200);
+        let mut body = resp.into_body();
+
+        // Not followed, so never seen
+        fixtures.events.publish::<Thermal>(ThermalEvent {
+            board: "bitaxe-1".into(),
+            temperatures: vec![],
+            fans: vec![],
+            powers: vec![],
+        });
+        fixtures.events.publish::<Pools>(PoolEvent {
+            source: "pool".into(),
+            kind: PoolEventKind::ShareAccepted,
+        });
+
+        let event = next_event(&mut body).await;
+        let data = event.strip_prefix("event: pools\ndata: ").unwrap();
+        let event: serde_json::Value = serde_json::from_str(data.trim_end()).unwrap();
+        assert_eq!(
+            event,
+            serde_json::json!({"source": "pool", "kind": "share_accepted"})
+        );
+    }
+
+    #[tokio::test]
+    async fn event_stream_rejects_unknown_topics() {
+        let fixtures = build_test_router(MinerState::default(), vec![]);
+        let req = Request::builder()
+            .uri("/api/v0/events?topics=pools,weather")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let resp = fixtures.router.clone().oneshot(req).await.unwrap();
+        assert_eq!(resp.status(), 400);
+    }
+
+    async fn next_event(body: &mut axum::body::Body) -> String {
+        let frame = body.frame().await.unwrap().unwrap();
+        String::from_utf8(frame.into_data().unwrap().to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn board_enable_returns_404_when_missing() {
+        let fixtures = build_test_router(MinerState::default(), vec![]);
+        let status = post(fixtures.router.clone(), "/api/v0/boards/nonexistent/enable").await;
+        assert_eq!(status, 404);
+    }
+
     #[tokio::test]
     async fn sources_returns_list() {
         let miner_state = MinerState {
@@ -329,6 +1131,128 @@ mod tests {
         assert_eq!(status, 404);
     }
 
+    #[tokio::test]
+    async fn scheduling_returns_thread_telemetry() {
+        let miner_state = MinerState {
+            scheduling: vec![ThreadScheduling {
+                name: "bitaxe-0".into(),
+                tasks_assigned: 3,
+                idle_percent: 10,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let fixtures = build_test_router(miner_state, vec![]);
+
+        let (status, body) = get(fixtures.router.clone(), "/api/v0/scheduling").await;
+        assert_eq!(status, 200);
+
+        let threads: Vec<ThreadScheduling> = serde_json::from_str(&body).unwrap();
+        assert_eq!(threads.len(), 1);
+        assert_eq!(threads[0].name, "bitaxe-0");
+        assert_eq!(threads[0].tasks_assigned, 3);
+        assert_eq!(threads[0].idle_percent, 10);
+    }
+
+    #[tokio::test]
+    async fn recent_shares_lists_audit_newest_first() {
+        use crate::share_audit::{ShareAuditEntry, ShareOutcome};
+        use bitcoin::{BlockHash, hashes::Hash};
+
+        let fixtures = build_test_router(MinerState::default(), vec![]);
+        let audit = &fixtures.share_audit;
+        for n in 1..=2u8 {
+            let hash = BlockHash::from_byte_array([n; 32]);
+            audit.received(hash, &format!("job-{n}"), "bitaxe-0", "pool");
+            audit.validated(hash, n == 2);
+        }
+
+        let (status, body) = get(fixtures.router.clone(), "/api/v0/shares/recent").await;
+        assert_eq!(status, 200);
+
+        let shares: Vec<ShareAuditEntry> = serde_json::from_str(&body).unwrap();
+        assert_eq!(shares.len(), 2);
+        assert_eq!(shares[0].job_id, "job-2");
+        assert_eq!(shares[0].outcome, None);
+        assert_eq!(shares[1].outcome, Some(ShareOutcome::BelowTarget));
+    }
+
+    #[tokio::test]
+    async fn shares_page_through_the_audit() {
+        use crate::api_client::types::Page;
+        use bitcoin::{BlockHash, hashes::Hash};
+
+        let fixtures = build_test_router(MinerState::default(), vec![]);
+        for n in 1..=3u8 {
+            let hash = BlockHash::from_byte_array([n; 32]);
+            fixtures
+                .share_audit
+                .received(hash, &format!("job-{n}"), "bitaxe-0", "pool");
+        }
+
+        let (status, body) = get(
+            fixtures.router.clone(),
+            "/api/v0/shares?limit=2&fields=job_id",
+        )
+        .await;
+        assert_eq!(status, 200);
+        let page: Page = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            page.items,
+            [
+                serde_json::json!({ "job_id": "job-3" }),
+                serde_json::json!({ "job_id": "job-2" })
+            ]
+        );
+
+        let cursor = page.next_cursor.unwrap();
+        let (_, body) = get(
+            fixtures.router.clone(),
+            &format!("/api/v0/shares?limit=2&cursor={cursor}"),
+        )
+        .await;
+        let page: Page = serde_json::from_str(&body).unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0]["job_id"], "job-1");
+        assert_eq!(page.next_cursor, None);
+
+        let (status, _) = get(fixtures.router.clone(), "/api/v0/shares?cursor=x").await;
+        assert_eq!(status, 400);
+    }
+
+    #[tokio::test]
+    async fn alerts_page_through_the_history() {
+        use crate::alerts::{AlertEntry, AlertState};
+        use crate::api_client::types::Page;
+
+        let fixtures = build_test_router(MinerState::default(), vec![]);
+        for (at_ms, state) in [(1_000, AlertState::Firing), (2_000, AlertState::Resolved)] {
+            fixtures.alerts.record(AlertEntry {
+                rule: "temp > 80 for 5m".into(),
+                state,
+                at_ms,
+                value: 81.0,
+                message: String::new(),
+            });
+        }
+
+        let (status, body) = get(fixtures.router.clone(), "/api/v0/alerts").await;
+        assert_eq!(status, 200);
+        let page: Page = serde_json::from_str(&body).unwrap();
+        let alerts: Vec<AlertEntry> = page
+            .items
+            .into_iter()
+            .map(|item| serde_json::from_value(item).unwrap())
+            .collect();
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].state, AlertState::Resolved);
+
+        let (_, body) = get(fixtures.router.clone(), "/api/v0/alerts?until_ms=1500").await;
+        let page: Page = serde_json::from_str(&body).unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0]["state"], "firing");
+    }
+
     #[tokio::test]
     async fn unknown_route_returns_404() {
         let fixtures = build_test_router(MinerState::default(), vec![]);
@@ -0,0 +1,272 @@
+//! Local admin socket.
+//!
+//! The API can also be served on a UNIX domain socket, for the CLI on
+//! machines where the HTTP port is firewalled or not bound at all. Access
+//! is governed by the socket file's permissions instead of the network:
+//! the socket is created owner- and group-accessible only, so adding an
+//! operator to the daemon's group is what grants them the API.
+//!
+//! Under systemd the socket can instead come from socket activation
+//! (`ListenStream=/run/mujina/api.sock` in a `.socket` unit). The unit
+//! then decides the path, owner and mode, and the daemon serves whatever
+//! it was handed, once it has checked the descriptor really is a
+//! listening UNIX stream socket.
+
+use std::fs;
+use std::io;
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use nix::sys::socket::{
+    AddressFamily, SockType, SockaddrLike, SockaddrStorage, getsockname, getsockopt, sockopt,
+};
+use tokio::net::UnixListener;
+use tracing::debug;
+
+/// Permissions the socket is created with: read-write for owner and group.
+pub const SOCKET_MODE: u32 = 0o660;
+
+/// First file descriptor passed by socket activation (`SD_LISTEN_FDS_START`).
+const LISTEN_FDS_START: i32 = 3;
+
+/// Number of descriptors socket activation passed to this process.
+static LISTEN_FDS: OnceLock<u32> = OnceLock::new();
+
+/// Claim the descriptors passed by socket activation, if any.
+///
+/// Records how many were passed to this process and removes
+/// `LISTEN_PID`, `LISTEN_FDS` and `LISTEN_FDNAMES` from the environment,
+/// so nothing the daemon starts mistakes them for its own.
+///
+/// # Safety
+///
+/// Modifies the environment, so it must run before the daemon starts
+/// any other thread.
+pub unsafe fn claim_activation() {
+    let for_us = std::env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        == Some(std::process::id());
+    let count = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|n| n.parse::<u32>().ok())
+        .filter(|_| for_us)
+        .unwrap_or(0);
+    let _ = LISTEN_FDS.set(count);
+
+    // SAFETY: the caller guarantees no other thread is running.
+    unsafe {
+        std::env::remove_var("LISTEN_PID");
+        std::env::remove_var("LISTEN_FDS");
+        std::env::remove_var("LISTEN_FDNAMES");
+    }
+}
+
+/// Marks requests that came in over the admin socket.
+///
+/// Added to the socket's router only, so handlers that change the unit
+/// itself, like `PUT /config`, can refuse requests from the network.
+#[derive(Debug, Clone, Copy)]
+pub struct AdminConnection;
+
+/// Listening admin socket.
+///
+/// A socket this process created is removed again when dropped, so a clean
+/// shutdown leaves nothing behind. An activated socket belongs to systemd
+/// and is left alone.
+#[derive(Debug)]
+pub struct AdminSocket {
+    listener: UnixListener,
+    created: Option<PathBuf>,
+}
+
+impl AdminSocket {
+    /// Take the socket passed by socket activation, if there is one.
+    ///
+    /// Only sees descriptors recorded by [`claim_activation`].
+    pub fn activated() -> io::Result<Option<Self>> {
+        if LISTEN_FDS.get().copied().unwrap_or(0) == 0 {
+            return Ok(None);
+        }
+
+        // SAFETY: systemd passes the listening sockets as descriptors
+        // starting at 3 and `LISTEN_PID` showed they were meant for this
+        // process, so the descriptor is open. Nothing else in the daemon
+        // claims it.
+        let fd = unsafe { BorrowedFd::borrow_raw(LISTEN_FDS_START) };
+        check_listening_unix_stream(fd)?;
+        // SAFETY: as above; it is a listening UNIX stream socket.
+        let std_listener =
+            unsafe { std::os::unix::net::UnixListener::from_raw_fd(LISTEN_FDS_START) };
+        std_listener.set_nonblocking(true)?;
+        Ok(Some(Self {
+            listener: UnixListener::from_std(std_listener)?,
+            created: None,
+        }))
+    }
+
+    /// Create the socket at `path` with [`SOCKET_MODE`].
+    ///
+    /// A socket left behind by a daemon that didn't shut down cleanly is
+    /// replaced; any other file at `path` is an error rather than deleted.
+    ///
+    /// The socket is bound in a private directory next to `path` and
+    /// only moved into place once its mode is set, so it is never
+    /// reachable with the looser permissions the umask would give it.
+    pub fn bind(path: &Path) -> io::Result<Self> {
+        match fs::symlink_metadata(path) {
+            Ok(meta) if meta.file_type().is_socket() => {
+                debug!(path = %path.display(), "Removing stale admin socket.");
+                fs::remove_file(path)?;
+            }
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                ));
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        let file_name = path.file_name().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a file path", path.display()),
+            )
+        })?;
+        let staging = path.with_file_name(format!(
+            ".{}.{}",
+            file_name.to_string_lossy(),
+            std::process::id()
+        ));
+        fs::DirBuilder::new().mode(0o700).create(&staging)?;
+        let staged = staging.join("sock");
+        let listener = UnixListener::bind(&staged).and_then(|listener| {
+            fs::set_permissions(&staged, fs::Permissions::from_mode(SOCKET_MODE))?;
+            fs::rename(&staged, path)?;
+            Ok(listener)
+        });
+        let _ = fs::remove_dir_all(&staging);
+
+        Ok(Self {
+            listener: listener?,
+            created: Some(path.to_path_buf()),
+        })
+    }
+
+    /// Where the socket lives, for logging.
+    pub fn path(&self) -> Option<PathBuf> {
+        self.created.clone().or_else(|| {
+            self.listener
+                .local_addr()
+                .ok()
+                .and_then(|addr| addr.as_pathname().map(Path::to_path_buf))
+        })
+    }
+
+    /// Hand the listener to the server.
+    ///
+    /// The returned guard removes a created socket file when dropped; keep
+    /// it until the server has stopped.
+    pub fn into_parts(self) -> (UnixListener, SocketFile) {
+        (self.listener, SocketFile(self.created))
+    }
+}
+
+/// Fail unless `fd` is a listening UNIX stream socket.
+///
+/// Guards against taking over whatever happens to be open as descriptor
+/// 3 when the activation variables are stale or the unit passes a
+/// different kind of socket.
+fn check_listening_unix_stream(fd: BorrowedFd<'_>) -> io::Result<()> {
+    let family = getsockname::<SockaddrStorage>(fd.as_raw_fd())?.family();
+    let sock_type = getsockopt(&fd, sockopt::SockType)?;
+    let listening = getsockopt(&fd, sockopt::AcceptConn)?;
+    if family != Some(AddressFamily::Unix) || sock_type != SockType::Stream || !listening {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "descriptor {} from socket activation is not a listening UNIX stream socket",
+                fd.as_raw_fd()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Removes a created socket file when dropped.
+#[derive(Debug)]
+pub struct SocketFile(Option<PathBuf>);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if let Some(path) = self.0.take() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_socket_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("mujina-{}-{}.sock", name, std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn bind_restricts_permissions_and_cleans_up() {
+        let path = temp_socket_path("perm");
+        let socket = AdminSocket::bind(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, SOCKET_MODE);
+        assert_eq!(socket.path().as_deref(), Some(path.as_path()));
+
+        let (_listener, file) = socket.into_parts();
+        drop(file);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn bind_replaces_stale_socket_but_not_other_files() {
+        let path = temp_socket_path("stale");
+        let stale = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        drop(stale);
+        let socket = AdminSocket::bind(&path).unwrap();
+        drop(socket.into_parts());
+
+        let path = temp_socket_path("regular");
+        fs::write(&path, b"keep me").unwrap();
+        let err = AdminSocket::bind(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read(&path).unwrap(), b"keep me");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn activation_accepts_only_listening_unix_stream_sockets() {
+        use std::os::fd::AsFd;
+        use std::os::unix::net;
+
+        let path = temp_socket_path("activation");
+        let listener = net::UnixListener::bind(&path).unwrap();
+        assert!(check_listening_unix_stream(listener.as_fd()).is_ok());
+        drop(listener);
+        fs::remove_file(&path).unwrap();
+
+        let (datagram, _) = net::UnixDatagram::pair().unwrap();
+        assert!(check_listening_unix_stream(datagram.as_fd()).is_err());
+
+        let (stream, _) = net::UnixStream::pair().unwrap();
+        assert!(check_listening_unix_stream(stream.as_fd()).is_err());
+
+        let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        assert!(check_listening_unix_stream(tcp.as_fd()).is_err());
+
+        let file = fs::File::open("/dev/null").unwrap();
+        assert!(check_listening_unix_stream(file.as_fd()).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! Lock-free snapshots the API assembles its state from.
+//!
+//! Each subsystem publishes its latest state on a watch channel: the
+//! scheduler its [`MinerState`], every board its [`BoardState`], and the
+//! profile handler the profile last applied. Handlers borrow the current
+//! values and never wait on another task, so a slow board or a busy
+//! scheduler can't stall a request.
+
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use crate::api_client::types::{BoardState, MinerState, Profile};
+use crate::board::BoardRegistration;
+use crate::sensors;
+
+/// Latest published state of every subsystem.
+///
+/// Cheap to clone; clones share the same channels.
+#[derive(Clone)]
+pub(crate) struct StateStore {
+    miner_rx: watch::Receiver<MinerState>,
+    /// State channels of the boards registered so far, in arrival order
+    boards_tx: watch::Sender<Vec<watch::Receiver<BoardState>>>,
+    profile_tx: watch::Sender<Profile>,
+}
+
+impl StateStore {
+    /// Create a store over the scheduler's snapshots, starting with no
+    /// boards and `profile`.
+    pub fn new(miner_rx: watch::Receiver<MinerState>, profile: Profile) -> Self {
+        Self {
+            miner_rx,
+            boards_tx: watch::Sender::new(Vec::new()),
+            profile_tx: watch::Sender::new(profile),
+        }
+    }
+
+    /// Add a newly connected board.
+    ///
+    /// Boards that have since disconnected are dropped from the list here
+    /// rather than by readers, which only skip them.
+    pub fn register_board(&self, reg: BoardRegistration) {
+        self.boards_tx.send_modify(|boards| {
+            boards.retain(is_connected);
+            boards.push(reg.state_rx);
+        });
+    }
+
+    /// Snapshot every connected board.
+    pub fn boards(&self) -> Vec<BoardState> {
+        self.boards_tx
+            .borrow()
+            .iter()
+            .filter(|rx| is_connected(rx))
+            .map(|rx| rx.borrow().clone())
+            .collect()
+    }
+
+    /// Snapshot one connected board by name.
+    pub fn board(&self, name: &str) -> Option<BoardState> {
+        self.boards_tx
+            .borrow()
+            .iter()
+            .filter(|rx| is_connected(rx))
+            .map(|rx| rx.borrow())
+            .find(|state| state.name == name)
+            .map(|state| state.clone())
+    }
+
+    /// Record the profile last applied to the boards.
+    pub fn set_profile(&self, profile: Profile) {
+        self.profile_tx.send_replace(profile);
+    }
+
+    /// Assemble a complete [`MinerState`] from the scheduler's snapshot,
+    /// the boards', the profile and the external sensors' readings.
+    pub fn miner_state(&self) -> MinerState {
+        let mut state = self.miner_rx.borrow().clone();
+        state.boards = self.boards();
+        state.profile = *self.profile_tx.borrow();
+        state.sensors = sensors::shared().readings(Instant::now());
+        state
+    }
+}
+
+/// Whether the board behind a state channel is still connected, i.e. its
+/// sender hasn't been dropped.
+fn is_connected(rx: &watch::Receiver<BoardState>) -> bool {
+    rx.has_changed().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a board registration with the given name, returning the
+    /// state sender so the test can update or drop it.
+    fn make_board(name: &str) -> (watch::Sender<BoardState>, BoardRegistration) {
+        let state = BoardState {
+            name: name.into(),
+            model: "Test".into(),
+            ..Default::default()
+        };
+        let (tx, rx) = watch::channel(state);
+        (tx, BoardRegistration { state_rx: rx })
+    }
+
+    fn store() -> StateStore {
+        let (_, miner_rx) = watch::channel(MinerState::default());
+        StateStore::new(miner_rx, Profile::default())
+    }
+
+    #[test]
+    fn tracks_registered_boards() {
+        let store = store();
+
+        let (_keep_a, reg_a) = make_board("board-a");
+        let (_keep_b, reg_b) = make_board("board-b");
+        store.register_board(reg_a);
+        store.register_board(reg_b);
+
+        let boards = store.boards();
+        assert_eq!(boards.len(), 2);
+        assert_eq!(boards[0].name, "board-a");
+        assert_eq!(boards[1].name, "board-b");
+        assert_eq!(store.board("board-b").unwrap().name, "board-b");
+        assert!(store.board("board-c").is_none());
+    }
+
+    #[test]
+    fn skips_disconnected_boards() {
+        let store = store();
+
+        let (keep, reg_a) = make_board("stays");
+        let (drop_me, reg_b) = make_board("goes-away");
+        store.register_board(reg_a);
+        store.register_board(reg_b);
+
+        // Both present initially
+        assert_eq!(store.boards().len(), 2);
+
+        // Drop the sender for board B -- simulates board disconnect
+        drop(drop_me);
+        let boards = store.boards();
+        assert_eq!(boards.len(), 1);
+        assert_eq!(boards[0].name, "stays");
+        assert!(store.board("goes-away").is_none());
+
+        // The next registration prunes it for good
+        let (_keep_c, reg_c) = make_board("arrives");
+        store.register_board(reg_c);
+        assert_eq!(store.boards_tx.borrow().len(), 2);
+
+        drop(keep);
+    }
+
+    #[test]
+    fn reflects_published_state() {
+        let (miner_tx, miner_rx) = watch::channel(MinerState::default());
+        let store = StateStore::new(miner_rx, Profile::Balanced);
+
+        let (tx, reg) = make_board("board-a");
+        store.register_board(reg);
+        assert_eq!(store.boards()[0].model, "Test");
+
+        tx.send_modify(|s| s.model = "Updated".into());
+        miner_tx.send_modify(|s| s.shares_submitted = 3);
+        store.set_profile(Profile::Quiet);
+
+        let state = store.miner_state();
+        assert_eq!(state.boards[0].model, "Updated");
+        assert_eq!(state.shares_submitted, 3);
+        assert_eq!(state.profile, Profile::Quiet);
+    }
+}
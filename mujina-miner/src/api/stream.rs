@@ -0,0 +1,210 @@
+//! Server-sent event streams of miner state changes and miner events.
+//!
+//! On the state stream a client first receives a `state` event carrying
+//! the full [`MinerState`], then a `delta` event each time the state
+//! changes materially. Deltas are JSON merge patches (RFC 7396) against
+//! the previous event, so a client keeps an up-to-date copy by merging
+//! each one into what it has.
+//!
+//! Durations and ages (fields ending in `_secs`) advance on their own
+//! every second. A change to those alone doesn't produce an event; the
+//! new values ride along with the next material delta.
+//!
+//! The event stream relays topics of the [`EventBus`] as they are
+//! published, one SSE event per bus event, named after its topic.
+
+use std::convert::Infallible;
+use std::time::Duration;
+
+use axum::response::sse::Event;
+use futures::{Stream, StreamExt, stream::BoxStream};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::MissedTickBehavior;
+
+use super::server::SharedState;
+use crate::api_client::types::MinerState;
+use crate::events::{Boards, EventBus, Pools, Shares, Thermal, Topic};
+
+/// Topics the event stream relays.
+pub(crate) const TOPICS: [&str; 4] = [Shares::NAME, Boards::NAME, Thermal::NAME, Pools::NAME];
+
+/// How often the state is checked for changes.
+///
+/// Board and scheduler state are published by different tasks at their
+/// own pace, so polling the combined snapshot is simpler than waiting on
+/// every source.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Stream `state` and `delta` events for as long as the client listens.
+pub(crate) fn state_events(state: SharedState) -> impl Stream<Item = Result<Event, Infallible>> {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    futures::stream::unfold(
+        (state, interval, None::<Value>),
+        |(state, mut interval, last)| async move {
+            loop {
+                interval.tick().await;
+                let current = snapshot(&state.miner_state());
+                let event = match &last {
+                    None => Event::default().event("state").data(current.to_string()),
+                    Some(last) => {
+                        let Some(delta) = material_delta(last, &current) else {
+                            continue;
+                        };
+                        Event::default().event("delta").data(delta.to_string())
+                    }
+                };
+                return Some((Ok(event), (state, interval, Some(current))));
+            }
+        },
+    )
+}
+
+/// Stream events from `topics`, all of them if empty, for as long as the
+/// client listens.
+///
+/// Returns the first unknown topic name as the error. A client that falls
+/// behind gets a `lagged` event saying how many events of a topic it
+/// missed.
+pub(crate) fn bus_events(
+    events: &EventBus,
+    topics: &[&str],
+) -> Result<BoxStream<'static, Result<Event, Infallible>>, String> {
+    let topics = if topics.is_empty() {
+        &TOPICS[..]
+    } else {
+        topics
+    };
+    let mut streams = Vec::new();
+    for &topic in topics {
+        streams.push(match topic {
+            t if t == Shares::NAME => topic_events::<Shares>(events),
+            t if t == Boards::NAME => topic_events::<Boards>(events),
+            t if t == Thermal::NAME => topic_events::<Thermal>(events),
+            t if t == Pools::NAME => topic_events::<Pools>(events),
+            unknown => return Err(unknown.to_string()),
+        });
+    }
+    Ok(futures::stream::select_all(streams).boxed())
+}
+
+fn topic_events<T: Topic>(events: &EventBus) -> BoxStream<'static, Result<Event, Infallible>>
+where
+    T::Event: Serialize,
+{
+    futures::stream::unfold(events.subscribe::<T>(), |mut rx| async move {
+        let event = match rx.recv().await {
+            Ok(event) => Event::default()
+                .event(T::NAME)
+                .data(serde_json::to_string(&event).unwrap_or_default()),
+            Err(RecvError::Lagged(missed)) => Event::default()
+                .event("lagged")
+                .data(json!({"topic": T::NAME, "missed": missed}).to_string()),
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok(event), rx))
+    })
+    .boxed()
+}
+
+fn snapshot(state: &MinerState) -> Value {
+    serde_json::to_value(state).unwrap_or(Value::Null)
+}
+
+/// Merge patch from `last` to `current`, if anything other than a
+/// clock-driven field changed.
+fn material_delta(last: &Value, current: &Value) -> Option<Value> {
+    merge_patch(&without_clock_fields(last), &without_clock_fields(current))?;
+    merge_patch(last, current)
+}
+
+/// RFC 7396 merge patch that turns `from` into `to`, or `None` if they
+/// are equal.
+///
+/// Objects are diffed key by key; anything else, arrays included, is
+/// replaced whole.
+fn merge_patch(from: &Value, to: &Value) -> Option<Value> {
+    match (from, to) {
+        (Value::Object(from), Value::Object(to)) => {
+            let mut patch = Map::new();
+            for (key, new) in to {
+                let change = match from.get(key) {
+                    Some(old) => merge_patch(old, new),
+                    None => Some(new.clone()),
+                };
+                if let Some(change) = change {
+                    patch.insert(key.clone(), change);
+                }
+            }
+            for key in from.keys().filter(|key| !to.contains_key(*key)) {
+                patch.insert(key.clone(), Value::Null);
+            }
+            (!patch.is_empty()).then_some(Value::Object(patch))
+        }
+        _ => (from != to).then(|| to.clone()),
+    }
+}
+
+/// Copy of `value` with every `*_secs` field removed, at any depth.
+fn without_clock_fields(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .filter(|(key, _)| !key.ends_with("_secs"))
+                .map(|(key, v)| (key.clone(), without_clock_fields(v)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(without_clock_fields).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn merge_patch_describes_changes_only() {
+        let from = json!({"hashrate": 10, "paused": false, "solo": {"blocks_found": 0, "luck_percent": null}});
+        let to = json!({"hashrate": 12, "paused": false, "solo": {"blocks_found": 1, "luck_percent": null}});
+        assert_eq!(
+            merge_patch(&from, &to),
+            Some(json!({"hashrate": 12, "solo": {"blocks_found": 1}}))
+        );
+        assert_eq!(merge_patch(&to, &to), None);
+    }
+
+    #[test]
+    fn merge_patch_replaces_arrays_and_nulls_removed_keys() {
+        let from = json!({"boards": [{"name": "a"}], "url": "x"});
+        let to = json!({"boards": [{"name": "a"}, {"name": "b"}]});
+        assert_eq!(
+            merge_patch(&from, &to),
+            Some(json!({"boards": [{"name": "a"}, {"name": "b"}], "url": null}))
+        );
+    }
+
+    #[test]
+    fn clock_fields_alone_are_not_material() {
+        let from =
+            json!({"uptime_secs": 10, "scheduling": [{"registered_secs": 5, "tasks_assigned": 3}]});
+        let ticked =
+            json!({"uptime_secs": 11, "scheduling": [{"registered_secs": 6, "tasks_assigned": 3}]});
+        assert_eq!(material_delta(&from, &ticked), None);
+
+        // Once something real changes, the clock fields come along
+        let assigned =
+            json!({"uptime_secs": 12, "scheduling": [{"registered_secs": 7, "tasks_assigned": 4}]});
+        assert_eq!(
+            material_delta(&from, &assigned),
+            Some(
+                json!({"uptime_secs": 12, "scheduling": [{"registered_secs": 7, "tasks_assigned": 4}]})
+            )
+        );
+    }
+}
@@ -1,31 +1,75 @@
 //! API v0 endpoints.
 //!
 //! Version 0 signals an unstable API -- breaking changes are expected
-//! until the miner reaches 1.0.
+//! until the miner reaches 1.0. Endpoints that have settled are frozen
+//! in [`v1`](super::v1), which shares these handlers.
 
 use axum::{
-    Json,
-    extract::{Path, State},
-    http::StatusCode,
+    Extension, Json,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{
+        IntoResponse,
+        sse::{Event, KeepAlive, Sse},
+    },
 };
+use futures::Stream;
+use serde::Deserialize;
+use std::convert::Infallible;
 use std::time::Duration;
 
 use tokio::sync::oneshot;
 use utoipa_axum::{router::OpenApiRouter, routes};
 
-use super::commands::SchedulerCommand;
+use super::commands::{BoardCommand, CommandError, SchedulerCommand};
+use super::metrics;
+use super::paging::{self, ListQuery};
 use super::server::SharedState;
-use crate::api_client::types::{BoardState, MinerPatchRequest, MinerState, SourceState};
+use super::socket::AdminConnection;
+use super::stream;
+use crate::api_client::types::{
+    BoardState, BuildInfo, BurnInRequest, BurnInStatus, ChipNonceReport, ChipRegisterDump,
+    ExternalSensor, JobAccounting, MinerPatchRequest, MinerState, Page, PauseLevel,
+    PreferSourceRequest, ProfileRequest, ReadinessReport, ReadinessState, SensorReadingRequest,
+    SetFanTargetRequest, SetFrequencyRequest, ShareAuditEntry, SourceState, ThreadScheduling,
+};
+use crate::config::{Config, ConfigError, DEFAULT_CONFIG_PATH};
+use crate::self_check;
+use crate::sensors;
 
 /// Build the v0 API routes with OpenAPI metadata.
 pub fn routes() -> OpenApiRouter<SharedState> {
     OpenApiRouter::new()
         .routes(routes!(health))
+        .routes(routes!(health_detail))
+        .routes(routes!(get_version))
+        .routes(routes!(get_config, put_config))
         .routes(routes!(get_miner, patch_miner))
+        .routes(routes!(set_profile))
+        .routes(routes!(prefer_source))
+        .routes(routes!(stream_state))
+        .routes(routes!(stream_events))
         .routes(routes!(get_boards))
         .routes(routes!(get_board))
+        .routes(routes!(get_board_nonces))
+        .routes(routes!(disable_board))
+        .routes(routes!(enable_board))
+        .routes(routes!(set_fan_target))
+        .routes(routes!(set_board_frequency))
+        .routes(routes!(update_board_firmware))
+        .routes(routes!(get_chip_registers))
+        .routes(routes!(start_burn_in, get_burn_in))
         .routes(routes!(get_sources))
         .routes(routes!(get_source))
+        .routes(routes!(get_scheduling))
+        .routes(routes!(get_recent_jobs))
+        .routes(routes!(get_recent_shares))
+        .routes(routes!(list_shares))
+        .routes(routes!(list_alerts))
+        .routes(routes!(get_metrics))
+        .routes(routes!(get_sensors))
+        .routes(routes!(push_sensor_reading))
 }
 
 /// Health check endpoint.
@@ -37,10 +81,123 @@ pub fn routes() -> OpenApiRouter<SharedState> {
         (status = OK, description = "Server is running", body = String),
     ),
 )]
-async fn health() -> &'static str {
+pub(super) async fn health() -> &'static str {
     "OK"
 }
 
+/// Report the self-check: configuration, API listener, USB discovery,
+/// boards and pools, each ready, degraded or failed.
+#[utoipa::path(
+    get,
+    path = "/health/detail",
+    tag = "health",
+    responses(
+        (status = OK, description = "Ready or degraded", body = ReadinessReport),
+        (status = SERVICE_UNAVAILABLE, description = "A check failed", body = ReadinessReport),
+    ),
+)]
+pub(super) async fn health_detail(
+    State(state): State<SharedState>,
+) -> (StatusCode, Json<ReadinessReport>) {
+    let report = state.readiness();
+    let status = match report.state {
+        ReadinessState::Failed => StatusCode::SERVICE_UNAVAILABLE,
+        ReadinessState::Ready | ReadinessState::Degraded => StatusCode::OK,
+    };
+    (status, Json(report))
+}
+
+/// Return version and build details, and the user agent sent to pools.
+#[utoipa::path(
+    get,
+    path = "/version",
+    tag = "health",
+    responses(
+        (status = OK, description = "Build information", body = BuildInfo),
+    ),
+)]
+pub(super) async fn get_version(State(state): State<SharedState>) -> Json<BuildInfo> {
+    Json((*state.build_info).clone())
+}
+
+/// Return the configuration the miner is running with, file, environment
+/// and flags combined, with passwords and tokens left out.
+///
+/// The result can be given to another unit's `PUT /config` to clone this
+/// one's setup.
+#[utoipa::path(
+    get,
+    path = "/config",
+    tag = "config",
+    responses(
+        (status = OK, description = "Effective configuration, without secrets", body = serde_json::Value),
+    ),
+)]
+async fn get_config(State(state): State<SharedState>) -> Json<Config> {
+    Json(state.config.redacted())
+}
+
+/// Replace the configuration file.
+///
+/// Only served on the admin socket: the file holds the pool passwords
+/// and agent token, and nothing on the HTTP port is authenticated.
+/// Secrets the new configuration leaves out, as `GET /config` does, keep
+/// their values from the current file; an empty one clears them (see
+/// [`Config::keep_secrets`]). The file is checked and written whole, and
+/// takes effect when the miner restarts; environment variables and flags
+/// still override it.
+#[utoipa::path(
+    put,
+    path = "/config",
+    tag = "config",
+    request_body = serde_json::Value,
+    responses(
+        (status = OK, description = "Configuration as written, without secrets", body = serde_json::Value),
+        (status = FORBIDDEN, description = "Not on the admin socket", body = String),
+        (status = UNPROCESSABLE_ENTITY, description = "Invalid configuration", body = String),
+        (status = INTERNAL_SERVER_ERROR, description = "Configuration file could not be read or written", body = String),
+    ),
+)]
+async fn put_config(
+    State(state): State<SharedState>,
+    admin: Option<Extension<AdminConnection>>,
+    Json(mut config): Json<Config>,
+) -> Result<Json<Config>, (StatusCode, String)> {
+    if admin.is_none() {
+        return Err((
+            StatusCode::FORBIDDEN,
+            "the configuration can only be replaced over the admin socket".into(),
+        ));
+    }
+
+    let check = self_check::check_config(&config);
+    if check.state == ReadinessState::Failed {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, check.detail));
+    }
+
+    let path = state
+        .config
+        .path
+        .clone()
+        .unwrap_or_else(|| DEFAULT_CONFIG_PATH.into());
+    let current = match Config::load_from(&path) {
+        Ok(current) => current,
+        Err(ConfigError::Read { source, .. }) if source.kind() == std::io::ErrorKind::NotFound => {
+            Config::default()
+        }
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    };
+    config.keep_secrets(&current);
+
+    match config.save_to(&path) {
+        Ok(()) => Ok(Json(config.redacted())),
+        Err(e @ ConfigError::InvalidValue { .. }) => {
+            Err((StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))
+        }
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
 /// Return the current miner state snapshot.
 #[utoipa::path(
     get,
@@ -50,11 +207,15 @@ async fn health() -> &'static str {
         (status = OK, description = "Current miner state", body = MinerState),
     ),
 )]
-async fn get_miner(State(state): State<SharedState>) -> Json<MinerState> {
+pub(super) async fn get_miner(State(state): State<SharedState>) -> Json<MinerState> {
     Json(state.miner_state())
 }
 
 /// Apply partial updates to the miner configuration.
+///
+/// Pausing at `power_down`, or resuming from it, also powers the boards
+/// down or back up: down after the scheduler has stopped, up before it
+/// dispatches again.
 #[utoipa::path(
     patch,
     path = "/miner",
@@ -69,27 +230,194 @@ async fn patch_miner(
     State(state): State<SharedState>,
     Json(req): Json<MinerPatchRequest>,
 ) -> Result<Json<MinerState>, StatusCode> {
-    if let Some(paused) = req.paused {
-        let (tx, rx) = oneshot::channel();
-        let cmd = if paused {
-            SchedulerCommand::PauseMining { reply: tx }
-        } else {
-            SchedulerCommand::ResumeMining { reply: tx }
-        };
+    let current = state.miner_state().pause_level;
+    let target = match (req.paused, req.pause_level) {
+        (Some(false), _) => Some(None),
+        (_, Some(level)) => Some(Some(level)),
+        // Already paused: stay at the current level
+        (Some(true), None) => Some(Some(current.unwrap_or_default())),
+        (None, None) => None,
+    };
+
+    if let Some(target) = target {
+        let was_powered = current != Some(PauseLevel::PowerDown);
+        let powered = target != Some(PauseLevel::PowerDown);
+        if powered && !was_powered {
+            set_boards_powered(&state, true).await?;
+        }
+
         state
-            .scheduler_cmd_tx
-            .send(cmd)
+            .commands
+            .request(|reply| match target {
+                Some(level) => SchedulerCommand::PauseMining { level, reply },
+                None => SchedulerCommand::ResumeMining { reply },
+            })
             .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        // Result layers: timeout / channel-closed / command-error.
-        let Ok(Ok(Ok(()))) = tokio::time::timeout(Duration::from_secs(5), rx).await else {
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        };
+            .map_err(command_failed)?;
+
+        if was_powered && !powered {
+            set_boards_powered(&state, false).await?;
+        }
+    }
+
+    Ok(Json(state.miner_state()))
+}
+
+/// Power every board down for a pause, or back up after one.
+async fn set_boards_powered(state: &SharedState, powered: bool) -> Result<(), StatusCode> {
+    /// Each board gets a couple of seconds to stop its threads.
+    const POWER_TIMEOUT: Duration = Duration::from_secs(30);
+
+    state
+        .commands
+        .request_within(POWER_TIMEOUT, |reply| BoardCommand::SetPowered {
+            powered,
+            reply,
+        })
+        .await
+        .map_err(command_failed)
+}
+
+/// Switch all boards to a named operating profile.
+#[utoipa::path(
+    post,
+    path = "/miner/profile",
+    tag = "miner",
+    request_body = ProfileRequest,
+    responses(
+        (status = OK, description = "Updated miner state", body = MinerState),
+        (status = INTERNAL_SERVER_ERROR, description = "Profile could not be applied"),
+    ),
+)]
+async fn set_profile(
+    State(state): State<SharedState>,
+    Json(req): Json<ProfileRequest>,
+) -> Result<Json<MinerState>, StatusCode> {
+    state
+        .commands
+        .request(|reply| BoardCommand::SetProfile {
+            profile: req.profile,
+            reply,
+        })
+        .await
+        .map_err(command_failed)?;
+
+    state.store.set_profile(req.profile);
+    Ok(Json(state.miner_state()))
+}
+
+/// Mine a shared source whenever it has work, or return the choice to
+/// failover.
+///
+/// A preferred source that loses its work hands over to the healthiest
+/// standby, and takes over again once it has work.
+#[utoipa::path(
+    post,
+    path = "/miner/source",
+    tag = "miner",
+    request_body = PreferSourceRequest,
+    responses(
+        (status = OK, description = "Updated miner state", body = MinerState),
+        (status = BAD_REQUEST, description = "Source mines threads of its own or only backfills"),
+        (status = NOT_FOUND, description = "Source not found"),
+        (status = INTERNAL_SERVER_ERROR, description = "Command channel error"),
+    ),
+)]
+async fn prefer_source(
+    State(state): State<SharedState>,
+    Json(req): Json<PreferSourceRequest>,
+) -> Result<Json<MinerState>, StatusCode> {
+    if let Some(name) = &req.source {
+        let sources = state.miner_state().sources;
+        let source = sources
+            .iter()
+            .find(|s| s.name == *name)
+            .ok_or(StatusCode::NOT_FOUND)?;
+        if source.pinned || source.backfill {
+            return Err(StatusCode::BAD_REQUEST);
+        }
     }
 
+    state
+        .commands
+        .request(|reply| SchedulerCommand::PreferSource {
+            source: req.source,
+            reply,
+        })
+        .await
+        .map_err(command_failed)?;
     Ok(Json(state.miner_state()))
 }
 
+/// Stream miner state changes as server-sent events.
+///
+/// Sends the full state as a `state` event, then a `delta` event (a JSON
+/// merge patch) whenever it changes materially.
+#[utoipa::path(
+    get,
+    path = "/stream",
+    tag = "miner",
+    responses(
+        (status = OK, description = "Event stream of miner state changes",
+         content_type = "text/event-stream", body = String),
+    ),
+)]
+async fn stream_state(
+    State(state): State<SharedState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    Sse::new(stream::state_events(state)).keep_alive(KeepAlive::default())
+}
+
+/// Topics to follow on the event stream.
+#[derive(Deserialize)]
+struct EventsQuery {
+    /// Comma-separated topic names; all of them if absent
+    topics: Option<String>,
+}
+
+/// Stream miner events as server-sent events.
+///
+/// Each event is named after its topic: `shares` for shares found,
+/// `boards` for boards connecting and disconnecting, `thermal` for
+/// periodic board readings, and `pools` for job source work and share
+/// verdicts.
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "miner",
+    params(
+        ("topics" = Option<String>, Query, description = "Comma-separated topics to follow (default all): shares, boards, thermal, pools"),
+    ),
+    responses(
+        (status = OK, description = "Event stream of the chosen topics",
+         content_type = "text/event-stream", body = String),
+        (status = BAD_REQUEST, description = "Unknown topic"),
+    ),
+)]
+async fn stream_events(
+    State(state): State<SharedState>,
+    Query(query): Query<EventsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    let topics: Vec<&str> = query
+        .topics
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect();
+    let events = stream::bus_events(&state.events, &topics).map_err(|topic| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "unknown topic {topic:?}; expected one of {}",
+                stream::TOPICS.join(", ")
+            ),
+        )
+    })?;
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
 /// Return all connected boards.
 #[utoipa::path(
     get,
@@ -99,14 +427,8 @@ async fn patch_miner(
         (status = OK, description = "List of connected boards", body = Vec<BoardState>),
     ),
 )]
-async fn get_boards(State(state): State<SharedState>) -> Json<Vec<BoardState>> {
-    Json(
-        state
-            .board_registry
-            .lock()
-            .unwrap_or_else(|e| e.into_inner())
-            .boards(),
-    )
+pub(super) async fn get_boards(State(state): State<SharedState>) -> Json<Vec<BoardState>> {
+    Json(state.store.boards())
 }
 
 /// Return a single board by name, or 404 if not found.
@@ -122,21 +444,346 @@ async fn get_boards(State(state): State<SharedState>) -> Json<Vec<BoardState>> {
         (status = NOT_FOUND, description = "Board not found"),
     ),
 )]
-async fn get_board(
+pub(super) async fn get_board(
     State(state): State<SharedState>,
     Path(name): Path<String>,
 ) -> Result<Json<BoardState>, StatusCode> {
     state
-        .board_registry
-        .lock()
-        .unwrap_or_else(|e| e.into_inner())
-        .boards()
-        .into_iter()
-        .find(|b| b.name == name)
+        .store
+        .board(&name)
         .map(Json)
         .ok_or(StatusCode::NOT_FOUND)
 }
 
+/// Return where each of a board's chips has been finding nonces.
+///
+/// Flags regions of the nonce range and small cores that find
+/// significantly fewer nonces than their share, a sign of a partially
+/// defective die. Empty for boards whose chips don't report nonces.
+#[utoipa::path(
+    get,
+    path = "/boards/{name}/nonces",
+    tag = "boards",
+    params(
+        ("name" = String, Path, description = "Board name"),
+    ),
+    responses(
+        (status = OK, description = "Per-chip nonce distribution", body = Vec<ChipNonceReport>),
+        (status = NOT_FOUND, description = "Board not found"),
+    ),
+)]
+async fn get_board_nonces(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<Vec<ChipNonceReport>>, StatusCode> {
+    state
+        .store
+        .board(&name)
+        .map(|b| Json(b.nonce_reports))
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Take a board's hash threads out of service without unplugging it.
+#[utoipa::path(
+    post,
+    path = "/boards/{name}/disable",
+    tag = "boards",
+    params(
+        ("name" = String, Path, description = "Board name"),
+    ),
+    responses(
+        (status = NO_CONTENT, description = "Board disabled"),
+        (status = NOT_FOUND, description = "Board not found"),
+        (status = INTERNAL_SERVER_ERROR, description = "Board could not be disabled"),
+    ),
+)]
+async fn disable_board(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    send_board_command(&state, &name, |board, reply| BoardCommand::Disable {
+        board,
+        reply,
+    })
+    .await
+}
+
+/// Bring a disabled board back into service.
+#[utoipa::path(
+    post,
+    path = "/boards/{name}/enable",
+    tag = "boards",
+    params(
+        ("name" = String, Path, description = "Board name"),
+    ),
+    responses(
+        (status = NO_CONTENT, description = "Board enabled"),
+        (status = NOT_FOUND, description = "Board not found"),
+        (status = INTERNAL_SERVER_ERROR, description = "Board could not be enabled"),
+    ),
+)]
+async fn enable_board(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, StatusCode> {
+    send_board_command(&state, &name, |board, reply| BoardCommand::Enable {
+        board,
+        reply,
+    })
+    .await
+}
+
+/// Set a fan's target duty cycle, or return it to automatic control.
+#[utoipa::path(
+    put,
+    path = "/boards/{name}/fans/{fan}",
+    tag = "boards",
+    params(
+        ("name" = String, Path, description = "Board name"),
+        ("fan" = String, Path, description = "Fan name"),
+    ),
+    request_body = SetFanTargetRequest,
+    responses(
+        (status = NO_CONTENT, description = "Fan target set"),
+        (status = BAD_REQUEST, description = "Target above 100%"),
+        (status = NOT_FOUND, description = "Board not found"),
+        (status = INTERNAL_SERVER_ERROR, description = "Fan target could not be set"),
+    ),
+)]
+async fn set_fan_target(
+    State(state): State<SharedState>,
+    Path((name, fan)): Path<(String, String)>,
+    Json(req): Json<SetFanTargetRequest>,
+) -> Result<StatusCode, StatusCode> {
+    if req.target_percent.is_some_and(|p| p > 100) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    send_board_command(&state, &name, |board, reply| BoardCommand::SetFanTarget {
+        board,
+        fan,
+        percent: req.target_percent,
+        reply,
+    })
+    .await
+}
+
+/// Set a board's core frequency, or return it to the profile's.
+///
+/// Thermal derating still applies on top. Boards reject frequencies
+/// outside what their chips are safe to run at their profile's voltage.
+#[utoipa::path(
+    put,
+    path = "/boards/{name}/frequency",
+    tag = "boards",
+    params(
+        ("name" = String, Path, description = "Board name"),
+    ),
+    request_body = SetFrequencyRequest,
+    responses(
+        (status = NO_CONTENT, description = "Frequency set"),
+        (status = BAD_REQUEST, description = "Frequency not a positive number"),
+        (status = NOT_FOUND, description = "Board not found"),
+        (status = INTERNAL_SERVER_ERROR, description = "Frequency could not be set"),
+    ),
+)]
+async fn set_board_frequency(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(req): Json<SetFrequencyRequest>,
+) -> Result<StatusCode, StatusCode> {
+    if req
+        .frequency_mhz
+        .is_some_and(|mhz| !mhz.is_finite() || mhz <= 0.0)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    send_board_command(&state, &name, |board, reply| BoardCommand::SetFrequency {
+        board,
+        frequency_mhz: req.frequency_mhz,
+        reply,
+    })
+    .await
+}
+
+/// Flash a new image to a board's management firmware.
+///
+/// The board must be disabled first. The update runs in the background
+/// and reports its progress in the board's `firmware_update`; once the
+/// image is written and verified, the board reboots into it and
+/// reconnects in service.
+#[utoipa::path(
+    post,
+    path = "/boards/{name}/firmware",
+    tag = "boards",
+    params(
+        ("name" = String, Path, description = "Board name"),
+    ),
+    request_body(
+        content = Vec<u8>,
+        content_type = "application/octet-stream",
+        description = "Firmware image",
+    ),
+    responses(
+        (status = ACCEPTED, description = "Update started"),
+        (status = BAD_REQUEST, description = "Empty image"),
+        (status = NOT_FOUND, description = "Board not found"),
+        (status = INTERNAL_SERVER_ERROR, description = "Update could not be started"),
+    ),
+)]
+async fn update_board_firmware(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    image: Bytes,
+) -> Result<StatusCode, StatusCode> {
+    if image.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    board_request(&state, &name, |board, reply| BoardCommand::UpdateFirmware {
+        board,
+        image: image.to_vec(),
+        reply,
+    })
+    .await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Start a burn-in: step the board through core frequencies, checking
+/// temperature, power and hardware errors at each.
+///
+/// Runs in the background on whatever work the board is given; start the
+/// miner without a pool for dummy work. The board returns to its profile
+/// frequency when the run ends, and the report is kept under its serial.
+#[utoipa::path(
+    post,
+    path = "/boards/{name}/burn-in",
+    tag = "boards",
+    params(
+        ("name" = String, Path, description = "Board name"),
+    ),
+    request_body = BurnInRequest,
+    responses(
+        (status = ACCEPTED, description = "Burn-in started"),
+        (status = NOT_FOUND, description = "Board not found"),
+        (status = INTERNAL_SERVER_ERROR, description = "Burn-in could not be started"),
+    ),
+)]
+async fn start_burn_in(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+    Json(request): Json<BurnInRequest>,
+) -> Result<StatusCode, StatusCode> {
+    board_request(&state, &name, |board, reply| BoardCommand::StartBurnIn {
+        board,
+        request,
+        reply,
+    })
+    .await?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Return a board's running burn-in, if any, and its last report.
+#[utoipa::path(
+    get,
+    path = "/boards/{name}/burn-in",
+    tag = "boards",
+    params(
+        ("name" = String, Path, description = "Board name"),
+    ),
+    responses(
+        (status = OK, description = "Burn-in progress and last report", body = BurnInStatus),
+        (status = NOT_FOUND, description = "Board not found"),
+    ),
+)]
+async fn get_burn_in(
+    State(state): State<SharedState>,
+    Path(name): Path<String>,
+) -> Result<Json<BurnInStatus>, StatusCode> {
+    board_request(&state, &name, |board, reply| BoardCommand::BurnInStatus {
+        board,
+        reply,
+    })
+    .await
+    .map(Json)
+}
+
+/// Query parameters for [`get_chip_registers`].
+#[derive(Debug, Default, Deserialize)]
+struct RegisterDumpQuery {
+    #[serde(default)]
+    diff: bool,
+}
+
+/// Read back every known register of one chip.
+///
+/// For debugging chip initialization. With `diff=true`, registers that
+/// initialization sets also carry the value written, and those that
+/// differ are listed in `mismatches`.
+#[utoipa::path(
+    get,
+    path = "/boards/{name}/chips/{address}/registers",
+    tag = "boards",
+    params(
+        ("name" = String, Path, description = "Board name"),
+        ("address" = u8, Path, description = "Chip address on the chain"),
+        ("diff" = Option<bool>, Query, description = "Compare against the values written during initialization"),
+    ),
+    responses(
+        (status = OK, description = "Register values", body = ChipRegisterDump),
+        (status = NOT_FOUND, description = "Board not found"),
+        (status = INTERNAL_SERVER_ERROR, description = "Registers could not be read"),
+    ),
+)]
+async fn get_chip_registers(
+    State(state): State<SharedState>,
+    Path((name, chip_address)): Path<(String, u8)>,
+    Query(query): Query<RegisterDumpQuery>,
+) -> Result<Json<ChipRegisterDump>, StatusCode> {
+    board_request(&state, &name, |board, reply| BoardCommand::DumpRegisters {
+        board,
+        chip_address,
+        diff: query.diff,
+        reply,
+    })
+    .await
+    .map(Json)
+}
+
+/// Send a command for a named board to the backplane and await its reply.
+async fn send_board_command(
+    state: &SharedState,
+    name: &str,
+    make_cmd: impl FnOnce(String, oneshot::Sender<anyhow::Result<()>>) -> BoardCommand,
+) -> Result<StatusCode, StatusCode> {
+    board_request(state, name, make_cmd).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Send a request for a named board to the backplane and return its
+/// answer.
+async fn board_request<T>(
+    state: &SharedState,
+    name: &str,
+    make_cmd: impl FnOnce(String, oneshot::Sender<anyhow::Result<T>>) -> BoardCommand,
+) -> Result<T, StatusCode> {
+    if state.store.board(name).is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    state
+        .commands
+        .request(|reply| make_cmd(name.to_string(), reply))
+        .await
+        .map_err(command_failed)
+}
+
+/// Status for a command that has no result.
+///
+/// The handlers check what they can (such as whether a board exists)
+/// before sending, so whatever fails past that is the miner's fault.
+fn command_failed(_: CommandError) -> StatusCode {
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
 /// Return all registered job sources.
 #[utoipa::path(
     get,
@@ -146,7 +793,7 @@ async fn get_board(
         (status = OK, description = "List of job sources", body = Vec<SourceState>),
     ),
 )]
-async fn get_sources(State(state): State<SharedState>) -> Json<Vec<SourceState>> {
+pub(super) async fn get_sources(State(state): State<SharedState>) -> Json<Vec<SourceState>> {
     Json(state.miner_state().sources)
 }
 
@@ -163,7 +810,7 @@ async fn get_sources(State(state): State<SharedState>) -> Json<Vec<SourceState>>
         (status = NOT_FOUND, description = "Source not found"),
     ),
 )]
-async fn get_source(
+pub(super) async fn get_source(
     State(state): State<SharedState>,
     Path(name): Path<String>,
 ) -> Result<Json<SourceState>, StatusCode> {
@@ -175,3 +822,183 @@ async fn get_source(
         .map(Json)
         .ok_or(StatusCode::NOT_FOUND)
 }
+
+/// Return per-thread work distribution telemetry.
+#[utoipa::path(
+    get,
+    path = "/scheduling",
+    tag = "scheduling",
+    responses(
+        (status = OK, description = "Work distribution per hash thread", body = Vec<ThreadScheduling>),
+    ),
+)]
+async fn get_scheduling(State(state): State<SharedState>) -> Json<Vec<ThreadScheduling>> {
+    Json(state.miner_state().scheduling)
+}
+
+/// Return share accounting for recent jobs, newest first.
+#[utoipa::path(
+    get,
+    path = "/jobs/recent",
+    tag = "scheduling",
+    responses(
+        (status = OK, description = "Shares found, expected and judged per recent job", body = Vec<JobAccounting>),
+    ),
+)]
+async fn get_recent_jobs(State(state): State<SharedState>) -> Json<Vec<JobAccounting>> {
+    Json(state.miner_state().recent_jobs)
+}
+
+/// Return the share audit trail, newest first.
+///
+/// Empty unless the share audit log is enabled.
+#[utoipa::path(
+    get,
+    path = "/shares/recent",
+    tag = "shares",
+    responses(
+        (status = OK, description = "Recent shares and when each reached every stage", body = Vec<ShareAuditEntry>),
+    ),
+)]
+async fn get_recent_shares(State(state): State<SharedState>) -> Json<Vec<ShareAuditEntry>> {
+    Json(state.share_audit.recent())
+}
+
+/// Page through the share audit trail, newest first.
+///
+/// Takes the list parameters every list endpoint does; see
+/// `docs/api.md`.
+#[utoipa::path(
+    get,
+    path = "/shares",
+    tag = "shares",
+    params(
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("limit" = Option<usize>, Query, description = "Entries per page (default 100, at most 1000)"),
+        ("since_ms" = Option<u64>, Query, description = "Only shares received at or after this Unix time, in ms"),
+        ("until_ms" = Option<u64>, Query, description = "Only shares received before this Unix time, in ms"),
+        ("fields" = Option<String>, Query, description = "Comma-separated fields to return"),
+    ),
+    responses(
+        (status = OK, description = "A page of audited shares", body = Page),
+        (status = BAD_REQUEST, description = "Invalid cursor or limit"),
+    ),
+)]
+async fn list_shares(
+    State(state): State<SharedState>,
+    Query(query): Query<ListQuery>,
+) -> Result<Json<Page>, StatusCode> {
+    let entries = state
+        .share_audit
+        .numbered()
+        .into_iter()
+        .map(|(number, entry)| paging::Entry {
+            number,
+            at_ms: entry.received_at_ms,
+            value: entry,
+        });
+    query
+        .page(entries)
+        .map(Json)
+        .map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+/// Page through the alerts that fired or resolved, newest first.
+///
+/// Empty unless alert rules are configured. Takes the list parameters
+/// every list endpoint does; see `docs/api.md`.
+#[utoipa::path(
+    get,
+    path = "/alerts",
+    tag = "alerts",
+    params(
+        ("cursor" = Option<String>, Query, description = "next_cursor of the previous page"),
+        ("limit" = Option<usize>, Query, description = "Entries per page (default 100, at most 1000)"),
+        ("since_ms" = Option<u64>, Query, description = "Only alerts at or after this Unix time, in ms"),
+        ("until_ms" = Option<u64>, Query, description = "Only alerts before this Unix time, in ms"),
+        ("fields" = Option<String>, Query, description = "Comma-separated fields to return"),
+    ),
+    responses(
+        (status = OK, description = "A page of alerts (AlertEntry items)", body = Page),
+        (status = BAD_REQUEST, description = "Invalid cursor or limit"),
+    ),
+)]
+async fn list_alerts(
+    State(state): State<SharedState>,
+    Query(query): Query<ListQuery>,
+) -> Result<Json<Page>, StatusCode> {
+    let entries = state
+        .alerts
+        .numbered()
+        .into_iter()
+        .map(|(number, entry)| paging::Entry {
+            number,
+            at_ms: entry.at_ms,
+            value: entry,
+        });
+    query
+        .page(entries)
+        .map(Json)
+        .map_err(|_| StatusCode::BAD_REQUEST)
+}
+
+/// Return metrics in the Prometheus text format.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "metrics",
+    responses(
+        (status = OK, description = "Prometheus text exposition", body = String, content_type = "text/plain"),
+    ),
+)]
+async fn get_metrics(State(state): State<SharedState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, metrics::CONTENT_TYPE)],
+        metrics::render(&state.miner_state()),
+    )
+}
+
+/// Return the readings external systems have pushed, by sensor name.
+///
+/// Readings older than ten minutes are dropped.
+#[utoipa::path(
+    get,
+    path = "/sensors",
+    tag = "sensors",
+    responses(
+        (status = OK, description = "Fresh external sensor readings", body = Vec<ExternalSensor>),
+    ),
+)]
+async fn get_sensors() -> Json<Vec<ExternalSensor>> {
+    Json(sensors::shared().readings(tokio::time::Instant::now()))
+}
+
+/// Push a reading from a sensor the boards lack, such as a room
+/// thermometer or a smart plug.
+///
+/// The reading replaces the sensor's last and is served as a metric; a
+/// room temperature also brings thermal derating forward when
+/// `boards.ambient_reference` is set. Push at least every few minutes:
+/// readings older than ten minutes are dropped.
+#[utoipa::path(
+    put,
+    path = "/sensors/{name}",
+    tag = "sensors",
+    params(
+        ("name" = String, Path, description = "Sensor name"),
+    ),
+    request_body = SensorReadingRequest,
+    responses(
+        (status = NO_CONTENT, description = "Reading recorded"),
+        (status = BAD_REQUEST, description = "Invalid name or implausible reading", body = String),
+    ),
+)]
+async fn push_sensor_reading(
+    Path(name): Path<String>,
+    Json(req): Json<SensorReadingRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sensors::shared()
+        .push(&name, req.kind, req.value, tokio::time::Instant::now())
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
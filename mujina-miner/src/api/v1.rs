@@ -0,0 +1,224 @@
+//! API v1 endpoints.
+//!
+//! Version 1 is the stable contract: a frozen subset of v0 whose shapes
+//! won't change incompatibly. Fields may be added; none are renamed,
+//! retyped or removed. Features land in v0 first and are frozen here
+//! once they have settled.
+//!
+//! Handlers are v0's, shared. Where v1's shape differs, the v0 result
+//! goes through an adapter into the v1 types below, so v0's types can
+//! keep evolving without breaking v1 clients.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use super::server::SharedState;
+use super::v0;
+use crate::api_client::types::{BoardState, MinerState, SourceState, ThreadState};
+
+/// Build the v1 API routes with OpenAPI metadata.
+pub fn routes() -> OpenApiRouter<SharedState> {
+    OpenApiRouter::new()
+        .routes(routes!(v0::health))
+        .routes(routes!(v0::health_detail))
+        .routes(routes!(v0::get_version))
+        .routes(routes!(get_miner))
+        .routes(routes!(get_boards))
+        .routes(routes!(get_board))
+        .routes(routes!(get_sources))
+        .routes(routes!(get_source))
+}
+
+/// Miner summary with its boards and sources.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct Miner {
+    pub uptime_secs: u64,
+    /// Aggregate hashrate in hashes per second.
+    pub hashrate: u64,
+    pub shares_submitted: u64,
+    pub paused: bool,
+    pub boards: Vec<Board>,
+    pub sources: Vec<Source>,
+}
+
+/// A connected board and its hash threads.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct Board {
+    pub name: String,
+    pub model: String,
+    pub serial: Option<String>,
+    /// Sum of the threads' hashrates, in hashes per second.
+    pub hashrate: u64,
+    pub threads: Vec<Thread>,
+}
+
+/// A hash thread: one chain of chips.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct Thread {
+    pub name: String,
+    /// Hashrate in hashes per second.
+    pub hashrate: u64,
+    /// Whether the thread is hashing.
+    pub active: bool,
+    /// Nonces the chips have reported, valid or not.
+    pub nonces: u64,
+    /// Reported nonces whose hash fails the chips' own reporting
+    /// difficulty.
+    pub hardware_errors: u64,
+}
+
+/// A job source, such as a pool.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct Source {
+    pub name: String,
+    /// Connection URL, or null for sources without one.
+    pub url: Option<String>,
+    /// Current share difficulty set by the source, if any.
+    pub difficulty: Option<u64>,
+    /// Whether the scheduler is mining this source's jobs.
+    pub active: bool,
+    /// Whether the source has work from its upstream right now.
+    pub connected: bool,
+    /// Hashrate of the threads mining this source's jobs, in H/s.
+    pub hashrate: u64,
+}
+
+impl From<MinerState> for Miner {
+    fn from(state: MinerState) -> Self {
+        Self {
+            uptime_secs: state.uptime_secs,
+            hashrate: state.hashrate,
+            shares_submitted: state.shares_submitted,
+            paused: state.paused,
+            boards: state.boards.into_iter().map(Board::from).collect(),
+            sources: state.sources.into_iter().map(Source::from).collect(),
+        }
+    }
+}
+
+impl From<BoardState> for Board {
+    fn from(board: BoardState) -> Self {
+        Self {
+            name: board.name,
+            model: board.model,
+            serial: board.serial,
+            hashrate: board.threads.iter().map(|t| t.hashrate).sum(),
+            threads: board.threads.into_iter().map(Thread::from).collect(),
+        }
+    }
+}
+
+impl From<ThreadState> for Thread {
+    fn from(thread: ThreadState) -> Self {
+        Self {
+            name: thread.name,
+            hashrate: thread.hashrate,
+            active: thread.is_active,
+            nonces: thread.nonces,
+            hardware_errors: thread.hardware_errors,
+        }
+    }
+}
+
+impl From<SourceState> for Source {
+    fn from(source: SourceState) -> Self {
+        Self {
+            name: source.name,
+            url: source.url,
+            difficulty: source.difficulty,
+            active: source.active,
+            connected: source.health.connected,
+            hashrate: source.hashrate,
+        }
+    }
+}
+
+/// Return the miner summary.
+#[utoipa::path(
+    get,
+    path = "/miner",
+    tag = "miner",
+    responses(
+        (status = OK, description = "Miner summary", body = Miner),
+    ),
+)]
+async fn get_miner(state: State<SharedState>) -> Json<Miner> {
+    let Json(miner) = v0::get_miner(state).await;
+    Json(miner.into())
+}
+
+/// Return all connected boards.
+#[utoipa::path(
+    get,
+    path = "/boards",
+    tag = "boards",
+    responses(
+        (status = OK, description = "List of connected boards", body = Vec<Board>),
+    ),
+)]
+async fn get_boards(state: State<SharedState>) -> Json<Vec<Board>> {
+    let Json(boards) = v0::get_boards(state).await;
+    Json(boards.into_iter().map(Board::from).collect())
+}
+
+/// Return a single board by name, or 404 if not found.
+#[utoipa::path(
+    get,
+    path = "/boards/{name}",
+    tag = "boards",
+    params(
+        ("name" = String, Path, description = "Board name"),
+    ),
+    responses(
+        (status = OK, description = "Board details", body = Board),
+        (status = NOT_FOUND, description = "Board not found"),
+    ),
+)]
+async fn get_board(
+    state: State<SharedState>,
+    name: Path<String>,
+) -> Result<Json<Board>, StatusCode> {
+    let Json(board) = v0::get_board(state, name).await?;
+    Ok(Json(board.into()))
+}
+
+/// Return all registered job sources.
+#[utoipa::path(
+    get,
+    path = "/sources",
+    tag = "sources",
+    responses(
+        (status = OK, description = "List of job sources", body = Vec<Source>),
+    ),
+)]
+async fn get_sources(state: State<SharedState>) -> Json<Vec<Source>> {
+    let Json(sources) = v0::get_sources(state).await;
+    Json(sources.into_iter().map(Source::from).collect())
+}
+
+/// Return a single source by name, or 404 if not found.
+#[utoipa::path(
+    get,
+    path = "/sources/{name}",
+    tag = "sources",
+    params(
+        ("name" = String, Path, description = "Source name"),
+    ),
+    responses(
+        (status = OK, description = "Source details", body = Source),
+        (status = NOT_FOUND, description = "Source not found"),
+    ),
+)]
+async fn get_source(
+    state: State<SharedState>,
+    name: Path<String>,
+) -> Result<Json<Source>, StatusCode> {
+    let Json(source) = v0::get_source(state, name).await?;
+    Ok(Json(source.into()))
+}
@@ -0,0 +1,86 @@
+//! API version discovery and deprecation notices.
+//!
+//! `GET /api` lists the versions served and the compatibility promise of
+//! each. v0 responses for endpoints that v1 has frozen carry a
+//! `Deprecation` header and a `Link` to their v1 successor, so clients
+//! find out they can move to the stable contract.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{MatchedPath, Request, State},
+    http::{HeaderValue, header},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::api_client::types::{ApiVersion, ApiVersionStatus, ApiVersions};
+
+/// Every version served, oldest first.
+const VERSIONS: &[(&str, ApiVersionStatus)] = &[
+    ("v0", ApiVersionStatus::Unstable),
+    ("v1", ApiVersionStatus::Stable),
+];
+
+/// Prefix of a version's endpoints.
+pub(super) fn prefix(version: &str) -> String {
+    format!("/api/{version}")
+}
+
+/// Where a version's OpenAPI spec is served.
+pub(super) fn openapi_path(version: &str) -> String {
+    format!("/api/{version}/openapi.json")
+}
+
+/// List the API versions served.
+pub(super) async fn discover() -> Json<ApiVersions> {
+    let stable = VERSIONS
+        .iter()
+        .rev()
+        .find(|(_, status)| *status == ApiVersionStatus::Stable)
+        .map(|(version, _)| version.to_string())
+        .unwrap_or_default();
+    Json(ApiVersions {
+        stable,
+        versions: VERSIONS
+            .iter()
+            .map(|&(version, status)| ApiVersion {
+                version: version.into(),
+                status,
+                path: prefix(version),
+                openapi: openapi_path(version),
+            })
+            .collect(),
+    })
+}
+
+/// Endpoint templates of v1, relative to its prefix (e.g.
+/// "/boards/{name}").
+pub(super) type Successors = Arc<BTreeSet<String>>;
+
+/// Mark a v0 response as deprecated when v1 has frozen its endpoint,
+/// linking to the v1 endpoint.
+pub(super) async fn mark_superseded(
+    State(successors): State<Successors>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let v0 = prefix("v0");
+    let successor = request
+        .extensions()
+        .get::<MatchedPath>()
+        .and_then(|matched| matched.as_str().strip_prefix(&v0))
+        .filter(|template| successors.contains(*template))
+        .and_then(|_| request.uri().path().strip_prefix(&v0))
+        .map(|rest| format!("<{}{rest}>; rel=\"successor-version\"", prefix("v1")));
+
+    let mut response = next.run(request).await;
+    if let Some(link) = successor.and_then(|link| HeaderValue::try_from(link).ok()) {
+        let headers = response.headers_mut();
+        headers.insert("deprecation", HeaderValue::from_static("true"));
+        headers.insert(header::LINK, link);
+    }
+    response
+}
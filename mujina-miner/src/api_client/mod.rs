@@ -5,6 +5,8 @@
 
 pub mod types;
 
+use std::path::Path;
+
 use anyhow::{Context, Result};
 use reqwest::Client as HttpClient;
 
@@ -38,6 +40,19 @@ impl Client {
         }
     }
 
+    /// Create a client connecting through the daemon's local admin socket.
+    pub fn with_unix_socket(path: &Path) -> Result<Self> {
+        let http = HttpClient::builder()
+            .unix_socket(path)
+            .build()
+            .context("failed to build HTTP client")?;
+        Ok(Self {
+            http,
+            // Only the path matters; the host is never resolved
+            base_url: "http://localhost".to_string(),
+        })
+    }
+
     /// Fetch the current miner state snapshot.
     pub async fn get_miner(&self) -> Result<MinerState> {
         self.get_json("miner").await
@@ -62,6 +77,40 @@ impl Client {
             .context("failed to parse API response")
     }
 
+    /// POST a JSON body to a v0 API endpoint, ignoring any response body.
+    pub async fn post_json<B: serde::Serialize>(&self, path: &str, body: &B) -> Result<()> {
+        let url = format!("{}/api/v0/{}", self.base_url, path);
+        let response = self
+            .http
+            .post(&url)
+            .json(body)
+            .send()
+            .await
+            .context("failed to connect to miner API")?;
+        let status = response.status();
+        if !status.is_success() {
+            anyhow::bail!("API request failed: {}", status);
+        }
+        Ok(())
+    }
+
+    /// PUT a JSON body to a v0 API endpoint, ignoring any response body.
+    pub async fn put_json<B: serde::Serialize>(&self, path: &str, body: &B) -> Result<()> {
+        let url = format!("{}/api/v0/{}", self.base_url, path);
+        let response = self
+            .http
+            .put(&url)
+            .json(body)
+            .send()
+            .await
+            .context("failed to connect to miner API")?;
+        let status = response.status();
+        if !status.is_success() {
+            anyhow::bail!("API request failed: {}", status);
+        }
+        Ok(())
+    }
+
     /// GET a v0 API endpoint and return the raw response body.
     pub async fn get_raw(&self, path: &str) -> Result<String> {
         let url = format!("{}/api/v0/{}", self.base_url, path);
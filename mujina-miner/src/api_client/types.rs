@@ -16,8 +16,187 @@ pub struct MinerState {
     pub hashrate: u64,
     pub shares_submitted: u64,
     pub paused: bool,
+    /// How deeply mining is paused, or null while mining.
+    pub pause_level: Option<PauseLevel>,
+    /// Whether the shared threads are mining backfill because no source
+    /// has work; hashrate meanwhile earns nothing.
+    #[serde(default)]
+    pub backfilling: bool,
+    /// Operating profile applied to the boards.
+    pub profile: Profile,
     pub boards: Vec<BoardState>,
     pub sources: Vec<SourceState>,
+    /// Block-finding effort and luck, for solo mining.
+    pub solo: SoloStats,
+    /// Per-thread work distribution, from the scheduler's point of view.
+    pub scheduling: Vec<ThreadScheduling>,
+    /// Share accounting for the most recent jobs, newest first.
+    #[serde(default)]
+    pub recent_jobs: Vec<JobAccounting>,
+    /// Readings pushed by external systems, by sensor name.
+    #[serde(default)]
+    pub sensors: Vec<ExternalSensor>,
+}
+
+/// Version and build details of the running miner.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct BuildInfo {
+    /// Crate version, e.g. "0.1.0".
+    pub version: String,
+    /// Short hash of the commit built from, or null if unknown.
+    pub git_hash: Option<String>,
+    /// Build time as RFC 3339, or null if unknown.
+    pub build_date: Option<String>,
+    /// Cargo features the binary was built with.
+    pub features: Vec<String>,
+    /// User agent sent to pools in `mining.subscribe`.
+    pub user_agent: String,
+}
+
+/// API versions the server offers, from `GET /api`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ApiVersions {
+    /// Newest version whose contract is frozen, e.g. "v1".
+    pub stable: String,
+    pub versions: Vec<ApiVersion>,
+}
+
+/// One API version and where to find it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ApiVersion {
+    /// Version name, e.g. "v0".
+    pub version: String,
+    pub status: ApiVersionStatus,
+    /// Prefix of the version's endpoints, e.g. "/api/v0".
+    pub path: String,
+    /// Where the version's OpenAPI spec is served.
+    pub openapi: String,
+}
+
+/// Compatibility promise of an API version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiVersionStatus {
+    /// Breaking changes may come with any release.
+    Unstable,
+    /// Only backwards-compatible changes, such as new fields.
+    Stable,
+}
+
+/// Outcome of the startup self-check, re-evaluated on each request.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ReadinessReport {
+    /// Worst state among the checks.
+    pub state: ReadinessState,
+    pub checks: Vec<ReadinessCheck>,
+}
+
+/// One self-check, such as whether the pool is reachable.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ReadinessCheck {
+    /// Check name: "config", "api", "usb", "boards" or "pools".
+    pub name: String,
+    pub state: ReadinessState,
+    /// What was found, for a human reading the report.
+    pub detail: String,
+}
+
+/// How ready the miner, or one part of it, is to hash.
+///
+/// Ordered from best to worst.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadinessState {
+    /// Working as configured.
+    Ready,
+    /// Hashing, but short of what was configured.
+    Degraded,
+    /// Not hashing, or unable to report shares.
+    Failed,
+}
+
+/// Work done against network difficulty.
+///
+/// Only blocks found by this miner count, so these figures matter when
+/// mining solo. Effort resets each time a block is found; luck covers the
+/// whole run.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct SoloStats {
+    /// Network difficulty of the current job, or null before any work.
+    pub network_difficulty: Option<u64>,
+    /// Work since the last block as a percentage of one block's expected
+    /// work (may exceed 100).
+    pub effort_percent: f64,
+    /// Blocks the work done would find on average.
+    pub expected_blocks: f64,
+    pub blocks_found: u64,
+    /// Blocks found relative to blocks expected, as a percentage (above
+    /// 100 is lucky), or null before any work.
+    pub luck_percent: Option<f64>,
+    /// Seconds since the last block was found, or null if none has been.
+    pub last_block_secs: Option<u64>,
+}
+
+/// How far a pause winds the miner down.
+///
+/// Deeper levels save more power and take longer to resume from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseLevel {
+    /// No new jobs are dispatched; chips finish the work they have.
+    /// Resuming is instant.
+    #[default]
+    StopDispatch,
+    /// Chips are sent idle and draw little power. Resuming takes seconds.
+    IdleChips,
+    /// Hash threads are released and chips held in reset. Resuming
+    /// re-initializes every chip, the slowest to come back.
+    PowerDown,
+}
+
+/// Named operating profile.
+///
+/// Each board maps a profile to its own frequency, core voltage, fan
+/// duty and power budget.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Lower frequency and voltage, fans held back.
+    Quiet,
+    /// Stock settings.
+    #[default]
+    Balanced,
+    /// Higher frequency and voltage, fans at full speed.
+    Turbo,
+}
+
+impl Profile {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Quiet => "quiet",
+            Self::Balanced => "balanced",
+            Self::Turbo => "turbo",
+        }
+    }
+}
+
+impl std::fmt::Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "quiet" => Ok(Self::Quiet),
+            "balanced" => Ok(Self::Balanced),
+            "turbo" => Ok(Self::Turbo),
+            _ => Err("expected quiet, balanced or turbo".into()),
+        }
+    }
 }
 
 /// Board status.
@@ -30,7 +209,47 @@ pub struct BoardState {
     pub fans: Vec<Fan>,
     pub temperatures: Vec<TemperatureSensor>,
     pub powers: Vec<PowerMeasurement>,
+    pub idle_power: IdlePower,
     pub threads: Vec<ThreadState>,
+    /// Whether the board's sensors have gone unread for too long. Their
+    /// values are then null rather than the last ones read.
+    #[serde(default)]
+    pub sensors_stale: bool,
+    /// Management firmware update in progress or just failed, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firmware_update: Option<FirmwareUpdateState>,
+    /// Nonce distribution per chip; served on its own at
+    /// `/boards/{name}/nonces` rather than with the rest of the state.
+    #[serde(skip)]
+    pub nonce_reports: Vec<ChipNonceReport>,
+}
+
+/// Progress of an update to a board's management firmware.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub struct FirmwareUpdateState {
+    pub phase: FirmwareUpdatePhase,
+    /// Image bytes written to the board so far.
+    pub written_bytes: u32,
+    /// Size of the image being written.
+    pub total_bytes: u32,
+    /// Why the update failed, once it has.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Step a firmware update is at.
+///
+/// An update that fails before `rebooting` leaves the running firmware
+/// in place. After it, the board drops off and reconnects running the
+/// new image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FirmwareUpdatePhase {
+    Erasing,
+    Writing,
+    Verifying,
+    Rebooting,
+    Failed,
 }
 
 /// Fan status.
@@ -61,6 +280,135 @@ pub struct PowerMeasurement {
     pub power_w: Option<f32>,
 }
 
+/// Average core power while hashing and while idling in low power.
+///
+/// Each is null until the board has been measured in that state.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct IdlePower {
+    pub hashing_w: Option<f32>,
+    pub low_power_w: Option<f32>,
+    /// How much less the chips draw when idle.
+    pub saving_w: Option<f32>,
+}
+
+/// Where a chip's recent nonces fell, to spot weak areas of its die.
+///
+/// Counts are halved as they grow, so they cover the chip's recent
+/// history rather than its whole run.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ChipNonceReport {
+    /// Hash thread driving the chip.
+    pub thread: String,
+    /// Chip index in chain order.
+    pub chip: usize,
+    /// Nonces counted.
+    pub nonces: u64,
+    /// Nonces per equal slice of the chip's nonce range, lowest first.
+    pub regions: Vec<u64>,
+    /// Nonces per small core ID reported with each nonce.
+    pub small_cores: Vec<u64>,
+    /// Regions finding significantly fewer nonces than an even share.
+    pub weak_regions: Vec<usize>,
+    /// Small cores finding significantly fewer nonces than an even share.
+    pub weak_small_cores: Vec<usize>,
+    /// Time from each job dispatch to the chip's first nonce for it.
+    #[serde(default)]
+    pub latency: NonceLatencyHistogram,
+}
+
+/// How long a chip took to answer new jobs with a first nonce.
+///
+/// At the chips' reporting difficulty this is mostly a wait of about a
+/// second. Growth against the chip's own baseline points at serial
+/// congestion or a chip that hashes less than it should. Counts are
+/// halved as they grow, like those of the nonce map.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct NonceLatencyHistogram {
+    /// Dispatches counted.
+    pub count: u64,
+    /// Fast moving average of the latency, in milliseconds.
+    pub recent_ms: Option<u64>,
+    /// Slow moving average, in milliseconds; null until enough
+    /// dispatches have been answered to judge.
+    pub baseline_ms: Option<u64>,
+    /// Whether the recent average is well above the baseline.
+    pub rising: bool,
+    /// Buckets holding any dispatches, lowest first.
+    pub buckets: Vec<LatencyBucket>,
+}
+
+/// Dispatches answered within `min_ms` up to twice that (or under 1 ms,
+/// for the bucket starting at 0).
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct LatencyBucket {
+    pub min_ms: u64,
+    pub count: u64,
+}
+
+/// Job dispatch cost for a chain: wire times worked out from the frame
+/// size and baud rate, and the measured time to hand each job to the
+/// link.
+///
+/// A job goes out as one frame that every chip on the chain takes, so
+/// the whole chain costs an estimated `est_broadcast_wire_us` of link
+/// time per job; `est_per_chip_wire_us` is the estimate for a frame per
+/// chip instead, which BM13xx chips can't be sent, so it is never
+/// measured. The handoff times end when the serial writer accepts
+/// the frame, which may be before it is on the wire, so they are not a
+/// wire measurement; a handoff average well above the estimate still
+/// points at a backed-up link.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct JobDispatchStats {
+    /// Jobs dispatched.
+    pub dispatches: u64,
+    /// Chips each dispatch reaches.
+    pub chips: usize,
+    /// Bytes in one job frame.
+    pub frame_bytes: usize,
+    /// Link rate in bits per second; null for boards that don't set it.
+    pub baud_rate: Option<u32>,
+    /// Estimated time to clock one job frame out at that rate, in
+    /// microseconds.
+    pub est_broadcast_wire_us: Option<u64>,
+    /// Estimated time to send the same job chip by chip, one frame each,
+    /// in microseconds.
+    pub est_per_chip_wire_us: Option<u64>,
+    /// Moving average of the time to hand a job to the link, in
+    /// microseconds.
+    pub handoff_avg_us: Option<u64>,
+    /// Longest time taken to hand a job to the link, in microseconds.
+    pub handoff_max_us: u64,
+}
+
+/// Registers read back from one chip, for debugging its configuration.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct ChipRegisterDump {
+    /// Hash thread driving the chip.
+    pub thread: String,
+    /// Chip address on the chain.
+    pub chip_address: u8,
+    /// Every known register, by address.
+    pub registers: Vec<RegisterReading>,
+    /// Registers whose value differs from what initialization wrote.
+    /// Empty unless a diff was requested.
+    pub mismatches: Vec<String>,
+}
+
+/// One register in a [`ChipRegisterDump`].
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct RegisterReading {
+    /// Register name, as in the driver's register map.
+    pub name: String,
+    pub address: u8,
+    /// Raw value, or null if the chip didn't answer.
+    pub value: Option<u32>,
+    /// Value broken down into its fields.
+    pub decoded: Option<String>,
+    /// Value written during initialization, if a diff was requested and
+    /// initialization sets this register.
+    pub expected: Option<u32>,
+}
+
 /// Per-thread runtime status.
 #[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 pub struct ThreadState {
@@ -68,6 +416,32 @@ pub struct ThreadState {
     /// Hashrate in hashes per second.
     pub hashrate: u64,
     pub is_active: bool,
+    /// Nonces the chips have reported, valid or not.
+    #[serde(default)]
+    pub nonces: u64,
+    /// Reported nonces whose hash fails the chips' own reporting
+    /// difficulty.
+    #[serde(default)]
+    pub hardware_errors: u64,
+    /// Times the chips were re-initialized or reset themselves.
+    #[serde(default)]
+    pub chip_resets: u64,
+    /// Whether the chips reset so often that they are held at reduced
+    /// frequency instead of being re-initialized again. Lifted after an
+    /// hour without a reset.
+    #[serde(default)]
+    pub quarantined: bool,
+    /// Frames from the chips lost to CRC or decode errors.
+    #[serde(default)]
+    pub link_errors: u64,
+    /// Whether link errors spiked and the chips run at reduced baud rate
+    /// and frequency. Full speed is probed periodically and kept once it
+    /// stays clean.
+    #[serde(default)]
+    pub link_degraded: bool,
+    /// Job dispatch timing; null for threads that don't measure it.
+    #[serde(default)]
+    pub job_dispatch: Option<JobDispatchStats>,
 }
 
 /// Writable fields for `PATCH /api/v0/miner`.
@@ -79,6 +453,143 @@ pub struct ThreadState {
 pub struct MinerPatchRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub paused: Option<bool>,
+    /// Pause at this level; `paused: true` alone means `stop_dispatch`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pause_level: Option<PauseLevel>,
+}
+
+/// Request body for `POST /api/v0/miner/profile`.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct ProfileRequest {
+    pub profile: Profile,
+}
+
+/// What an external sensor measures.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorKind {
+    /// Room temperature, °C.
+    AmbientTemperature,
+    /// Power drawn at the wall, e.g. by a smart plug, W.
+    Power,
+}
+
+/// Request body for `PUT /api/v0/sensors/{name}`.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct SensorReadingRequest {
+    pub kind: SensorKind,
+    /// Reading in the kind's unit.
+    pub value: f64,
+}
+
+/// Latest reading pushed for an external sensor.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ExternalSensor {
+    pub name: String,
+    pub kind: SensorKind,
+    /// Reading in the kind's unit.
+    pub value: f64,
+    /// Seconds since the reading was pushed.
+    pub age_secs: u64,
+}
+
+/// Request body for `POST /api/v0/miner/source`.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct PreferSourceRequest {
+    /// Shared source to mine whenever it has work, or null to let
+    /// failover choose by health score.
+    pub source: Option<String>,
+}
+
+/// Request body for setting a board's core frequency.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct SetFrequencyRequest {
+    /// Core frequency in MHz, or null for the profile's.
+    pub frequency_mhz: Option<f32>,
+}
+
+/// Request body for `POST /api/v0/boards/{name}/burn-in`.
+///
+/// Every field is optional; unset ones take the burn-in defaults.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct BurnInRequest {
+    /// Core frequencies to step through, in MHz, lowest first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequencies_mhz: Option<Vec<f32>>,
+    /// How long to hold each frequency, in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step_secs: Option<u64>,
+    /// Hottest reading a passing step may reach.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_temp_c: Option<f32>,
+    /// Largest share of nonces that may be hardware errors, 0--1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_error_rate: Option<f64>,
+    /// Highest power a passing step may draw, in watts; unlimited if null.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_power_w: Option<f32>,
+}
+
+/// A board's burn-in, running or last finished.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct BurnInStatus {
+    /// Progress of a running burn-in, or null if none is running.
+    pub running: Option<BurnInProgress>,
+    /// Report of the last burn-in of this board, if there has been one.
+    pub report: Option<BurnInReport>,
+}
+
+/// Where a running burn-in has got to.
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+pub struct BurnInProgress {
+    /// Step being run, counting from 1.
+    pub step: usize,
+    pub steps: usize,
+    /// Core frequency of the current step, in MHz.
+    pub frequency_mhz: f32,
+    /// Seconds until the current step ends.
+    pub step_remaining_secs: u64,
+}
+
+/// Outcome of a burn-in, kept per board serial.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct BurnInReport {
+    /// Board name at the time of the burn-in.
+    pub board: String,
+    pub serial: Option<String>,
+    /// When the burn-in started, as Unix time.
+    pub started_at: u64,
+    /// Whether every planned step passed.
+    pub passed: bool,
+    /// Highest frequency that passed along with every step below it, in
+    /// MHz, or null if the first step failed.
+    pub safe_frequency_mhz: Option<f32>,
+    /// Limits the steps were held to.
+    pub max_temp_c: f32,
+    pub max_error_rate: f64,
+    pub max_power_w: Option<f32>,
+    /// Steps run, lowest frequency first. A failed step ends the run.
+    pub steps: Vec<BurnInStepResult>,
+}
+
+/// Measurements from one burn-in step.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct BurnInStepResult {
+    pub frequency_mhz: f32,
+    /// How long the step ran, in seconds.
+    pub duration_secs: u64,
+    /// Hottest temperature read, or null if none was.
+    pub max_temp_c: Option<f32>,
+    /// Average power drawn, in watts, or null if not measured.
+    pub mean_power_w: Option<f32>,
+    /// Average hashrate, in hashes per second.
+    pub mean_hashrate: u64,
+    pub nonces: u64,
+    pub hardware_errors: u64,
+    /// Hardware errors per nonce.
+    pub error_rate: f64,
+    /// Why the step failed, or null if it passed.
+    pub failure: Option<String>,
 }
 
 /// Request body for setting a fan's target duty cycle.
@@ -98,4 +609,397 @@ pub struct SourceState {
     /// Current share difficulty set by the source.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub difficulty: Option<u64>,
+    /// Whether the scheduler is currently mining this source's jobs.
+    pub active: bool,
+    /// Whether the source mines on threads of its own rather than
+    /// competing with the other sources for all of them.
+    #[serde(default)]
+    pub pinned: bool,
+    /// Whether the operator picked this source to mine whenever it has
+    /// work, overriding failover.
+    #[serde(default)]
+    pub preferred: bool,
+    /// Whether the source is dummy work that only fills in for idle
+    /// threads; its shares are neither submitted nor counted.
+    #[serde(default)]
+    pub backfill: bool,
+    /// Measured hashrate of the threads mining this source's jobs, in H/s.
+    #[serde(default)]
+    pub hashrate: u64,
+    /// Number of threads mining this source's jobs.
+    #[serde(default)]
+    pub threads: u32,
+    pub health: SourceHealthState,
+    /// Automatic response to a high share reject rate.
+    #[serde(default)]
+    pub remediation: RemediationState,
+    /// Jobs and shares kept inside the pool's ntime window.
+    #[serde(default)]
+    pub ntime_guard: NtimeGuardState,
+    /// Jobs replaced by a later one from the same burst before being sent
+    /// to the hash threads.
+    #[serde(default)]
+    pub coalesced_jobs: u64,
+    /// Shares held while the pool was unreachable, if the source holds
+    /// them.
+    #[serde(default)]
+    pub offline_queue: OfflineQueueState,
+    /// Difficulties achieved by the shares found on this source's jobs.
+    #[serde(default)]
+    pub share_difficulties: ShareDifficultyHistogram,
+    /// Extranonce2 of the current job left over for threads that join
+    /// while it runs.
+    #[serde(default)]
+    pub en2_spare: Extranonce2SpareState,
+}
+
+/// What a source is doing about a high share reject rate.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct RemediationState {
+    /// Step whose effect is being watched, or null if none is.
+    pub step: Option<RemediationStep>,
+    /// Seconds until remediation may start again, if every step failed
+    /// last time; null otherwise.
+    pub retry_in_secs: Option<u64>,
+    /// Number of times remediation has started.
+    pub sequences: u64,
+}
+
+/// What a source has done to keep share ntime where the pool accepts it.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct NtimeGuardState {
+    /// Jobs started earlier than asked so rolled ntime stays in range.
+    pub clamped_jobs: u64,
+    /// Shares dropped because their ntime was out of range.
+    pub dropped_shares: u64,
+}
+
+/// Use of the extranonce2 a job's threads left over.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct Extranonce2SpareState {
+    /// Values reserved in chunks by threads that joined the job.
+    pub reserved: u64,
+    /// Values still free.
+    pub remaining: u64,
+}
+
+/// Shares a source held while its pool was unreachable, to submit on
+/// reconnect.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct OfflineQueueState {
+    /// Shares found while the pool was unreachable and held for it.
+    pub held: u64,
+    /// Held shares submitted after reconnecting.
+    pub recovered: u64,
+    /// Recovered shares the pool accepted.
+    pub recovered_accepted: u64,
+    /// Held shares discarded unsubmitted: too old, or the pool started a
+    /// new session or block in the meantime.
+    pub discarded: u64,
+}
+
+/// A remediation step, in the order they are tried.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RemediationStep {
+    /// Re-estimate the pool's clock and restart work at its ntime.
+    ResyncNtime,
+    /// Drop queued shares and restart work on the current job.
+    RefreshJob,
+    /// Suggest a difficulty afresh, so the share target matches the pool's.
+    ResyncDifficulty,
+    /// Drop the connection and start a new session.
+    Reconnect,
+}
+
+impl RemediationStep {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ResyncNtime => "resync_ntime",
+            Self::RefreshJob => "refresh_job",
+            Self::ResyncDifficulty => "resync_difficulty",
+            Self::Reconnect => "reconnect",
+        }
+    }
+}
+
+impl std::fmt::Display for RemediationStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Observed health of a job source.
+///
+/// The scheduler mines the healthiest source that has work; a source
+/// whose score falls well below another's is demoted.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct SourceHealthState {
+    /// Overall score (0--100); higher is healthier.
+    pub score: u8,
+    /// Whether the source has work from its upstream right now.
+    #[serde(default)]
+    pub connected: bool,
+    /// Time connected since the source registered (0--100).
+    pub uptime_percent: u8,
+    /// Disconnects within the last ten minutes.
+    pub recent_disconnects: u32,
+    /// Rejected fraction of recent share verdicts (0--100).
+    pub reject_percent: u8,
+    /// Variation in the interval between jobs relative to its mean
+    /// (0--100, capped); lower means a steadier notify cadence.
+    pub notify_jitter_percent: u8,
+    /// Smoothed share submission round-trip time in milliseconds, or
+    /// null before any share has been answered.
+    pub latency_ms: Option<u64>,
+}
+
+/// How the scheduler has been distributing work to one hash thread.
+///
+/// Useful on multi-board rigs to spot a starved board or a thread that
+/// dominates the extranonce2 space.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct ThreadScheduling {
+    /// Thread name, as reported in `BoardState::threads`.
+    pub name: String,
+    /// Seconds since the thread registered with the scheduler.
+    pub registered_secs: u64,
+    /// Total number of tasks assigned since registration.
+    pub tasks_assigned: u64,
+    /// Share of time since registration spent without work (0--100).
+    pub idle_percent: u8,
+    /// Shares found by this thread (at the scheduler's target).
+    pub shares_found: u64,
+    /// Shares from this thread forwarded to a source.
+    pub shares_submitted: u64,
+    /// This thread's fraction of all submitted shares (0--100).
+    pub share_percent: u8,
+    /// Hashrate of each chip, estimated from the nonces it reports at
+    /// chip difficulty. Updates within seconds, unlike share-based
+    /// figures; empty until the thread has reported.
+    pub chip_hashrates: Vec<u64>,
+    /// Shares that found the thread's share channel full and waited for
+    /// the scheduler to catch up. Steady growth means the chip reports
+    /// more shares than the scheduler can take.
+    #[serde(default)]
+    pub shares_delayed: u64,
+    /// Total time shares spent waiting, in milliseconds.
+    #[serde(default)]
+    pub share_wait_ms: u64,
+    /// Status updates dropped because the thread's event channel was full;
+    /// each was superseded by a newer one.
+    #[serde(default)]
+    pub status_updates_coalesced: u64,
+    /// Nonces the chips reported twice, after running out of header space
+    /// before fresh work reached them. Not credited.
+    #[serde(default)]
+    pub duplicate_nonces: u64,
+    /// Times a chip's PLL read back at a frequency other than the one
+    /// written, as when a chip resets itself. Each episode counts once.
+    #[serde(default)]
+    pub frequency_mismatches: u64,
+    /// Difficulties this thread's shares achieved.
+    #[serde(default)]
+    pub share_difficulties: ShareDifficultyHistogram,
+    /// How the thread's shares are filtered on their way to the pool.
+    #[serde(default)]
+    pub filtering: ShareFiltering,
+    /// Most recent task assignments, newest first.
+    pub recent_assignments: Vec<TaskAssignment>,
+}
+
+/// The stages a thread's shares pass on their way to the pool, for
+/// checking they are set up sanely.
+///
+/// The chips report nonces at `chip_difficulty`; the thread forwards
+/// those meeting the task's share target to the scheduler; the
+/// scheduler submits those meeting the pool's. Each stage should be no
+/// harder than the next, and pass on fewer shares than the one before.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ShareFiltering {
+    /// Difficulty the chips' ticket mask is set to, or null for hardware
+    /// without one or chips not yet initialized.
+    pub chip_difficulty: Option<u64>,
+    /// Difficulty of the current task's share target, or null while idle.
+    pub share_difficulty: Option<u64>,
+    /// Pool difficulty of the current task, or null while idle.
+    pub pool_difficulty: Option<u64>,
+    /// Nonces the chips reported.
+    pub chip_shares_found: u64,
+    /// Of those, shares meeting the share target.
+    pub shares_forwarded: u64,
+    /// Of those, shares meeting the pool's target.
+    pub pool_shares_submitted: u64,
+    /// What looks wrong with the stages; empty when all is well.
+    pub issues: Vec<FilteringIssue>,
+}
+
+/// Something wrong with how a thread filters its shares.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FilteringIssue {
+    /// The chips filter harder than the pool, dropping shares the pool
+    /// would take.
+    ChipHarderThanPool,
+    /// The chips filter harder than the share target, so fewer shares
+    /// arrive than the target promises and hashrate estimates run low.
+    ChipHarderThanShareTarget,
+    /// The share target is harder than the pool's, dropping shares the
+    /// pool would take. Expected when the pool's difficulty is so low the
+    /// scheduler caps the share rate.
+    ShareTargetHarderThanPool,
+    /// A stage passed on more shares than the one before it.
+    CountsOutOfOrder,
+}
+
+/// Shares counted by the difficulty their hash achieved, in log-2
+/// buckets.
+///
+/// Above the share target each bucket should hold about half the shares
+/// of the one below it. Shares below the target point at a chip
+/// reporting bogus nonces.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ShareDifficultyHistogram {
+    /// Shares counted.
+    pub count: u64,
+    /// Sum of the shares' difficulties.
+    pub sum: f64,
+    /// Buckets holding any shares, lowest first.
+    pub buckets: Vec<ShareDifficultyBucket>,
+}
+
+/// Shares with a difficulty from `min_difficulty` up to twice that (or up
+/// to 1, for the bucket starting at 0).
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ShareDifficultyBucket {
+    pub min_difficulty: u64,
+    pub count: u64,
+}
+
+/// One job's shares and extranonce2, as the scheduler accounted for them.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct JobAccounting {
+    /// Name of the source that provided the job.
+    pub source: String,
+    pub job_id: String,
+    /// Seconds since the job was first handed out.
+    pub age_secs: u64,
+    /// Tasks the job went out in, across threads and reissues.
+    pub tasks: u64,
+    /// Whether any thread is still mining the job.
+    pub active: bool,
+    /// Shares the threads found on the job, at their own share targets.
+    pub shares_found: u64,
+    /// Shares the threads should have found, from their hashrate and the
+    /// time they spent on the job.
+    pub shares_expected: f64,
+    /// Shares that met the job's target and went to the source.
+    pub shares_submitted: u64,
+    pub shares_accepted: u64,
+    pub shares_rejected: u64,
+    /// Extranonce2 values mined: each task's first, and those its shares
+    /// show it moved on to.
+    pub en2_consumed: u64,
+    /// Whether the job found implausibly few shares for the time spent on
+    /// it, as when its work reached the chips corrupted. Judged once no
+    /// thread is left on the job.
+    pub anomalous: bool,
+}
+
+/// A single task handed to a thread.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct TaskAssignment {
+    /// Name of the source that provided the job.
+    pub source: String,
+    pub job_id: String,
+    /// Seconds since the task was assigned.
+    pub age_secs: u64,
+    /// First extranonce2 value in the assigned range, if rolling.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub en2_start: Option<u64>,
+    /// Number of extranonce2 values in the assigned range, if rolling.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub en2_len: Option<u64>,
+    /// Version bits the thread rolls on this task: those both the pool
+    /// and the hardware allow.
+    #[serde(default)]
+    pub version_bits_rolled: u32,
+}
+
+/// One share's trip from the hash thread that found it to the pool, as
+/// recorded by the share audit log.
+///
+/// Each stage's time is null until the share reaches it, so the last
+/// non-null stage shows where a share that never got a verdict stopped.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct ShareAuditEntry {
+    /// Hash of the solved header, which identifies the share.
+    pub hash: String,
+    pub job_id: String,
+    /// Name of the thread that found the share.
+    pub thread: String,
+    /// Name of the source the share is for.
+    pub source: String,
+    /// When the scheduler received the nonce from the thread.
+    pub received_at_ms: u64,
+    /// When the share was checked against the source's target.
+    pub validated_at_ms: Option<u64>,
+    /// When the source queued the share for submission.
+    pub enqueued_at_ms: Option<u64>,
+    /// When the share was sent to the pool.
+    pub submitted_at_ms: Option<u64>,
+    /// When the pool's verdict arrived.
+    pub acked_at_ms: Option<u64>,
+    /// How the trip ended, or null while the share is still under way
+    /// (or was lost).
+    pub outcome: Option<ShareOutcome>,
+}
+
+/// One page of a list endpoint, newest entries first.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct Page {
+    /// Entries on this page, with only the requested `fields` if any
+    /// were.
+    #[schema(value_type = Vec<Object>)]
+    pub items: Vec<serde_json::Value>,
+    /// Cursor for the next, older page, or null if this is the last.
+    pub next_cursor: Option<String>,
+}
+
+/// How a share's trip ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareOutcome {
+    /// Didn't meet the source's target, so wasn't submitted.
+    BelowTarget,
+    /// The pool accepted it.
+    Accepted,
+    /// The pool rejected it.
+    Rejected,
+}
+
+/// An alert rule starting or ceasing to hold, as recorded in the alert
+/// history and sent to webhooks and MQTT.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, ToSchema)]
+pub struct AlertEntry {
+    /// The rule, as configured (e.g. "temp > 80 for 5m").
+    pub rule: String,
+    pub state: AlertState,
+    /// When the rule fired or resolved.
+    pub at_ms: u64,
+    /// The reading at that moment: the metric's value in its unit, or
+    /// the number of events within the rule's window.
+    pub value: f64,
+    /// Human-readable summary.
+    pub message: String,
+}
+
+/// Whether an alert started or stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertState {
+    /// The rule's condition began to hold.
+    Firing,
+    /// The rule's condition no longer holds.
+    Resolved,
 }
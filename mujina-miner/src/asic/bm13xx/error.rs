@@ -22,3 +22,27 @@ pub enum ProtocolError {
     #[error("Invalid frequency: {mhz} MHz (must be between 50-800 MHz)")]
     InvalidFrequency { mhz: u32 },
 }
+
+/// Failure of a [`WriteBatch`](super::write_batch::WriteBatch).
+#[derive(Error, Debug)]
+pub enum WriteBatchError {
+    #[error("Failed to send {0}")]
+    Send(String),
+
+    #[error(
+        "Registers did not take after {attempts} attempts: {}",
+        join(mismatches)
+    )]
+    Mismatch {
+        attempts: usize,
+        mismatches: Vec<super::write_batch::Mismatch>,
+    },
+}
+
+fn join(mismatches: &[super::write_batch::Mismatch]) -> String {
+    mismatches
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
@@ -0,0 +1,149 @@
+//! Holding back chips that keep needing resets.
+//!
+//! A chip is re-initialized when the [job watchdog](super::job_watchdog)
+//! gives up on it, and resets itself when it browns out, which the
+//! [PLL readback](super::pll_readback) notices. Once in a while either is
+//! routine. A chip that needs it every few minutes has a marginal supply,
+//! a failing solder joint or a frequency it can't hold, and resetting it
+//! again and again only hides that while costing the rest of the chain
+//! its work each time.
+//!
+//! [`FlapDamper`] counts the resets of the last hour. Past the limit the
+//! chip is quarantined: it runs at a reduced frequency, the watchdog
+//! resends jobs but no longer re-initializes it, and its thread reports
+//! the quarantine so the self-check can flag the board. The quarantine is
+//! lifted an hour after the last reset.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Period resets are counted over.
+pub const WINDOW: Duration = Duration::from_secs(3600);
+
+/// Resets within [`WINDOW`] tolerated before a chip is quarantined.
+pub const DEFAULT_MAX_RESETS: usize = 4;
+
+/// Fraction of its usual frequency a quarantined chip runs at.
+pub const QUARANTINE_FREQUENCY_FACTOR: f32 = 0.75;
+
+/// Reset history of one chip chain.
+#[derive(Debug)]
+pub struct FlapDamper {
+    max_resets: usize,
+    /// Resets within the window, oldest first
+    recent: VecDeque<Instant>,
+    total: u64,
+    quarantined: bool,
+}
+
+impl FlapDamper {
+    pub fn new(max_resets: usize) -> Self {
+        Self {
+            max_resets,
+            recent: VecDeque::new(),
+            total: 0,
+            quarantined: false,
+        }
+    }
+
+    /// A reset happened at `now`.
+    ///
+    /// Returns true if it tipped the chip into quarantine.
+    pub fn record(&mut self, now: Instant) -> bool {
+        self.expire(now);
+        self.recent.push_back(now);
+        self.total += 1;
+        if !self.quarantined && self.recent.len() > self.max_resets {
+            self.quarantined = true;
+            return true;
+        }
+        false
+    }
+
+    /// Lift the quarantine if a whole window has passed since the last
+    /// reset.
+    ///
+    /// Returns true if it was lifted.
+    pub fn release(&mut self, now: Instant) -> bool {
+        self.expire(now);
+        if self.quarantined && self.recent.is_empty() {
+            self.quarantined = false;
+            return true;
+        }
+        false
+    }
+
+    pub fn is_quarantined(&self) -> bool {
+        self.quarantined
+    }
+
+    /// The frequency to run at instead of `mhz`, reduced in quarantine.
+    pub fn cap(&self, mhz: f32) -> f32 {
+        if self.quarantined {
+            mhz * QUARANTINE_FREQUENCY_FACTOR
+        } else {
+            mhz
+        }
+    }
+
+    /// Resets within the last window.
+    pub fn recent(&self) -> usize {
+        self.recent.len()
+    }
+
+    /// Resets since the thread started.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while self
+            .recent
+            .front()
+            .is_some_and(|&at| now.duration_since(at) >= WINDOW)
+        {
+            self.recent.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn occasional_resets_are_tolerated() {
+        let mut damper = FlapDamper::new(2);
+        let now = Instant::now();
+        for i in 0..10 {
+            assert!(!damper.record(now + WINDOW / 2 * i));
+        }
+        assert!(!damper.is_quarantined());
+        assert_eq!(damper.total(), 10);
+        assert_eq!(damper.cap(500.0), 500.0);
+    }
+
+    #[test]
+    fn flapping_quarantines_until_a_quiet_hour() {
+        let mut damper = FlapDamper::new(2);
+        let now = Instant::now();
+        assert!(!damper.record(now));
+        assert!(!damper.record(now + MINUTE));
+        assert!(damper.record(now + MINUTE * 2));
+        assert!(damper.is_quarantined());
+        assert_eq!(damper.cap(500.0), 375.0);
+
+        // A self-reset during quarantine extends it
+        assert!(!damper.record(now + MINUTE * 30));
+        assert!(!damper.release(now + WINDOW + MINUTE * 2));
+        assert!(damper.is_quarantined());
+
+        assert!(damper.release(now + WINDOW + MINUTE * 30));
+        assert!(!damper.is_quarantined());
+        assert_eq!((damper.recent(), damper.total()), (0, 4));
+    }
+}
@@ -0,0 +1,165 @@
+//! Recording of the command sequence that initializes a chip.
+//!
+//! Chip initialization is a long run of register writes whose values and
+//! order were worked out by watching esp-miner boot real boards. A changed
+//! constant or a reordered batch leaves the chip hashing poorly, or not at
+//! all, without anything failing. An [`InitRecorder`] sits between
+//! initialization and the command sink and keeps every frame sent, so the
+//! sequence can be logged and compared against golden sequences.
+
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::Sink;
+
+use super::protocol::Command;
+
+/// A frame sent during initialization.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Sent {
+    frame: Vec<u8>,
+    /// Whether the frame reads a register back rather than configuring
+    /// the chip
+    read: bool,
+}
+
+/// Frames sent to the chips during initialization, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InitSequence {
+    sent: Vec<Sent>,
+}
+
+impl InitSequence {
+    /// Every frame sent, preamble and CRC included.
+    pub fn frames(&self) -> impl Iterator<Item = &[u8]> {
+        self.sent.iter().map(|sent| sent.frame.as_slice())
+    }
+
+    /// Frames that configure the chips, leaving out read-backs.
+    ///
+    /// Write batches read registers back to confirm them; esp-miner never
+    /// does, so these are the frames to compare against its captures.
+    pub fn writes(&self) -> impl Iterator<Item = &[u8]> {
+        self.sent
+            .iter()
+            .filter(|sent| !sent.read)
+            .map(|sent| sent.frame.as_slice())
+    }
+
+    pub fn len(&self) -> usize {
+        self.sent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sent.is_empty()
+    }
+}
+
+/// One frame per line, as space-separated hex.
+impl fmt::Display for InitSequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, frame) in self.frames().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            for (j, byte) in frame.iter().enumerate() {
+                if j > 0 {
+                    write!(f, " ")?;
+                }
+                write!(f, "{byte:02x}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Command sink that records what passes through it.
+pub struct InitRecorder<W> {
+    inner: W,
+    sequence: InitSequence,
+}
+
+impl<W> InitRecorder<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            sequence: InitSequence::default(),
+        }
+    }
+
+    /// Stop recording and return what was sent.
+    pub fn into_sequence(self) -> InitSequence {
+        self.sequence
+    }
+}
+
+impl<W> Sink<Command> for InitRecorder<W>
+where
+    W: Sink<Command> + Unpin,
+{
+    type Error = W::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, command: Command) -> Result<(), Self::Error> {
+        let sent = Sent {
+            frame: command.to_frame().to_vec(),
+            read: matches!(command, Command::ReadRegister { .. }),
+        };
+        Pin::new(&mut self.inner).start_send(command)?;
+        self.sequence.sent.push(sent);
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::SinkExt;
+
+    use super::*;
+    use crate::asic::bm13xx::protocol::RegisterAddress;
+
+    #[tokio::test]
+    async fn records_frames_and_tells_reads_apart() {
+        let (tx, _rx) = futures::channel::mpsc::unbounded::<Command>();
+        let mut recorder = InitRecorder::new(tx);
+        recorder.send(Command::ChainInactive).await.unwrap();
+        recorder
+            .send(Command::ReadRegister {
+                broadcast: false,
+                chip_address: 0x00,
+                register_address: RegisterAddress::ChipId,
+            })
+            .await
+            .unwrap();
+        recorder
+            .send(Command::SetChipAddress { chip_address: 0x04 })
+            .await
+            .unwrap();
+
+        let sequence = recorder.into_sequence();
+        assert_eq!(sequence.len(), 3);
+        assert_eq!(
+            sequence.writes().collect::<Vec<_>>(),
+            [
+                &[0x55, 0xaa, 0x53, 0x05, 0x00, 0x00, 0x03][..],
+                &[0x55, 0xaa, 0x40, 0x05, 0x04, 0x00, 0x03],
+            ]
+        );
+        assert_eq!(
+            sequence.to_string().lines().next(),
+            Some("55 aa 53 05 00 00 03")
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Timing of job dispatches to the chain.
+//!
+//! A job frame carries no chip address. Every chip on the chain takes
+//! the same frame as it passes down the daisy chain, so one frame
+//! reaches them all in the time it takes to clock it out once; each chip
+//! searches its own slice of the nonce space. Protocols that address
+//! jobs to one chip at a time need a frame per chip instead, and a long
+//! chain spends most of its link on them.
+//!
+//! BM13xx chips have no addressed job frame, so per-chip dispatch can't
+//! be sent to them, let alone timed: the comparison with it is an
+//! estimate. [`DispatchTiming`] measures how long each broadcast takes
+//! to hand to the link and sets that against wire times worked out from
+//! the frame size and baud rate, for the broadcast frame and for the
+//! frame per chip an addressed protocol would need. The handoff ends
+//! once the writer accepts the frame, not once it has left the UART, so
+//! only a handoff well above the estimate says much: the link is backed
+//! up.
+
+use std::time::Duration;
+
+use crate::api_client::types::JobDispatchStats;
+
+/// Bytes in a full-format job frame: preamble, header, length, 82 bytes
+/// of job and a CRC16.
+pub const JOB_FRAME_BYTES: usize = 2 + 1 + 1 + 82 + 2;
+
+/// Bits on the wire per byte: start bit, eight data bits, stop bit.
+const BITS_PER_BYTE: u64 = 10;
+
+/// Weight of each dispatch in the moving average.
+const RECENT_WEIGHT: f64 = 1.0 / 8.0;
+
+/// Measured job dispatch times for one chain.
+#[derive(Debug, Clone, Default)]
+pub struct DispatchTiming {
+    dispatches: u64,
+    recent_us: f64,
+    max_us: u64,
+}
+
+impl DispatchTiming {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a dispatch that took `elapsed` to send.
+    pub fn record(&mut self, elapsed: Duration) {
+        let us = elapsed.as_secs_f64() * 1_000_000.0;
+        if self.dispatches == 0 {
+            self.recent_us = us;
+        } else {
+            self.recent_us += (us - self.recent_us) * RECENT_WEIGHT;
+        }
+        self.max_us = self.max_us.max(elapsed.as_micros() as u64);
+        self.dispatches += 1;
+    }
+
+    pub fn dispatches(&self) -> u64 {
+        self.dispatches
+    }
+
+    /// Stats for a chain of `chips` on a link at `baud_rate`, if known.
+    pub fn report(&self, chips: usize, baud_rate: Option<u32>) -> JobDispatchStats {
+        let est_broadcast_wire_us = baud_rate.map(|baud| frame_wire_time(baud).as_micros() as u64);
+        JobDispatchStats {
+            dispatches: self.dispatches,
+            chips,
+            frame_bytes: JOB_FRAME_BYTES,
+            baud_rate,
+            est_broadcast_wire_us,
+            est_per_chip_wire_us: est_broadcast_wire_us.map(|us| us * chips as u64),
+            handoff_avg_us: (self.dispatches > 0).then(|| self.recent_us.round() as u64),
+            handoff_max_us: self.max_us,
+        }
+    }
+}
+
+/// Time to clock one job frame out at `baud`.
+pub fn frame_wire_time(baud: u32) -> Duration {
+    let bits = JOB_FRAME_BYTES as u64 * BITS_PER_BYTE;
+    Duration::from_nanos(bits * 1_000_000_000 / u64::from(baud.max(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asic::bm13xx::protocol::{Command, JobFullFormat};
+    use crate::job_source::test_blocks::block_881423;
+
+    #[test]
+    fn frame_length_matches_encoded_job() {
+        let job_data = JobFullFormat {
+            job_id: 0,
+            num_midstates: 1,
+            starting_nonce: 0,
+            nbits: *block_881423::BITS,
+            ntime: block_881423::TIME,
+            merkle_root: *block_881423::MERKLE_ROOT,
+            prev_block_hash: *block_881423::PREV_BLOCKHASH,
+            version: *block_881423::VERSION,
+        };
+        assert_eq!(
+            Command::JobFull { job_data }.to_frame().len(),
+            JOB_FRAME_BYTES
+        );
+    }
+
+    #[test]
+    fn broadcast_costs_one_frame_for_the_chain() {
+        let mut timing = DispatchTiming::new();
+        assert_eq!(timing.report(4, Some(115_200)).handoff_avg_us, None);
+
+        timing.record(Duration::from_micros(8_000));
+        timing.record(Duration::from_micros(16_000));
+        let stats = timing.report(4, Some(115_200));
+
+        // 880 bits at 115,200 baud
+        assert_eq!(stats.est_broadcast_wire_us, Some(7_638));
+        assert_eq!(stats.est_per_chip_wire_us, Some(4 * 7_638));
+        assert_eq!(stats.dispatches, 2);
+        assert_eq!(stats.handoff_avg_us, Some(9_000));
+        assert_eq!(stats.handoff_max_us, 16_000);
+
+        // Boards without baud control leave the wire time unknown
+        let stats = timing.report(4, None);
+        assert_eq!(stats.est_broadcast_wire_us, None);
+        assert_eq!(stats.est_per_chip_wire_us, None);
+    }
+}
@@ -0,0 +1,183 @@
+//! Chip job ID allocation.
+//!
+//! BM13xx chips identify jobs by a 4-bit ID, so the host has to hand out
+//! IDs round-robin and reuse them. Each ID maps to a slot holding the job
+//! the chip was given under it, so nonce responses can be matched back to
+//! the right host-side job.
+//!
+//! Reusing an ID is the dangerous moment: nonces for the slot's previous
+//! job may still be in flight on the serial link. Looking them up against
+//! the new job would silently drop (or, with a bad header, mis-credit) the
+//! work. Each slot therefore keeps the job it displaced for
+//! [`LATE_NONCE_WINDOW`], and [`JobSlots::candidates`] offers both. The
+//! caller tells them apart by checking which header the nonce actually
+//! solves.
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Number of distinct job IDs the chip understands.
+pub const JOB_ID_COUNT: usize = 16;
+
+/// How long a displaced job still accepts nonces after its ID is reused.
+pub const LATE_NONCE_WINDOW: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+struct Slot<T> {
+    current: Option<T>,
+    /// When `current` was sent.
+    sent: Option<Instant>,
+    /// Job displaced by `current`, and when.
+    retired: Option<(T, Instant)>,
+}
+
+impl<T> Default for Slot<T> {
+    fn default() -> Self {
+        Self {
+            current: None,
+            sent: None,
+            retired: None,
+        }
+    }
+}
+
+/// Round-robin job ID allocator with wraparound correlation.
+#[derive(Debug)]
+pub struct JobSlots<T> {
+    slots: [Slot<T>; JOB_ID_COUNT],
+    next_id: u8,
+}
+
+impl<T> Default for JobSlots<T> {
+    fn default() -> Self {
+        Self {
+            slots: Default::default(),
+            next_id: 0,
+        }
+    }
+}
+
+impl<T> JobSlots<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assign the next job ID to `job`.
+    ///
+    /// The slot's previous job, if any, stays reachable for late nonces
+    /// until [`LATE_NONCE_WINDOW`] after `now`.
+    pub fn insert(&mut self, job: T, now: Instant) -> u8 {
+        let id = self.next_id;
+        let slot = &mut self.slots[id as usize];
+        slot.retired = slot.current.replace(job).map(|old| (old, now));
+        slot.sent = Some(now);
+        self.next_id = (self.next_id + 1) % JOB_ID_COUNT as u8;
+        id
+    }
+
+    /// Jobs a nonce reported under `id` may belong to.
+    ///
+    /// Yields the current job first, then the displaced one if it is still
+    /// within the late nonce window. The flag is true for the displaced job.
+    pub fn candidates(&self, id: u8, now: Instant) -> impl Iterator<Item = (&T, bool)> {
+        let slot = self.slots.get(id as usize);
+        let current = slot
+            .and_then(|s| s.current.as_ref())
+            .map(|job| (job, false));
+        let retired = slot
+            .and_then(|s| s.retired.as_ref())
+            .filter(|(_, at)| now.duration_since(*at) < LATE_NONCE_WINDOW)
+            .map(|(job, _)| (job, true));
+        current.into_iter().chain(retired)
+    }
+
+    /// When the job under `id` was sent.
+    ///
+    /// None while the displaced job is still within the late nonce
+    /// window, since a nonce under the ID may belong to either.
+    pub fn sent_at(&self, id: u8, now: Instant) -> Option<Instant> {
+        let slot = self.slots.get(id as usize)?;
+        let ambiguous = slot
+            .retired
+            .as_ref()
+            .is_some_and(|(_, at)| now.duration_since(*at) < LATE_NONCE_WINDOW);
+        if ambiguous { None } else { slot.sent }
+    }
+
+    /// Forget all jobs, including displaced ones.
+    ///
+    /// Used when old work becomes invalid; ID assignment carries on from
+    /// where it was so nonces still in flight don't land on new jobs.
+    pub fn clear(&mut self) {
+        self.slots = Default::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(slots: &JobSlots<u32>, id: u8, now: Instant) -> Vec<(u32, bool)> {
+        slots
+            .candidates(id, now)
+            .map(|(job, late)| (*job, late))
+            .collect()
+    }
+
+    #[test]
+    fn ids_wrap_around() {
+        let now = Instant::now();
+        let mut slots = JobSlots::new();
+        let assigned: Vec<u8> = (0..20).map(|job| slots.insert(job, now)).collect();
+        assert_eq!(assigned[..16], (0..16).collect::<Vec<u8>>()[..]);
+        assert_eq!(assigned[16..], [0, 1, 2, 3]);
+        assert_eq!(ids(&slots, 15, now), [(15, false)]);
+        assert!(ids(&slots, 16, now).is_empty());
+    }
+
+    #[test]
+    fn late_nonce_finds_displaced_job_after_rapid_replacement() {
+        let now = Instant::now();
+        let mut slots = JobSlots::new();
+
+        // Seventeen jobs in quick succession: job 16 reuses job 0's ID
+        for job in 0..17 {
+            slots.insert(job, now);
+        }
+        assert_eq!(ids(&slots, 0, now), [(16, false), (0, true)]);
+
+        // Once the window has passed only the new job matches
+        let later = now + LATE_NONCE_WINDOW;
+        assert_eq!(ids(&slots, 0, later), [(16, false)]);
+        assert_eq!(slots.sent_at(0, now), None);
+        assert_eq!(slots.sent_at(0, later), Some(now));
+        assert_eq!(slots.sent_at(1, now), Some(now));
+    }
+
+    #[test]
+    fn only_one_generation_is_kept() {
+        let now = Instant::now();
+        let mut slots = JobSlots::new();
+        for job in 0..33 {
+            slots.insert(job, now);
+        }
+
+        // Job 0 was displaced twice over; a nonce for it can't be matched
+        assert_eq!(ids(&slots, 0, now), [(32, false), (16, true)]);
+    }
+
+    #[test]
+    fn clear_drops_displaced_jobs_and_keeps_counting() {
+        let now = Instant::now();
+        let mut slots = JobSlots::new();
+        for job in 0..17 {
+            slots.insert(job, now);
+        }
+        slots.clear();
+
+        assert!(ids(&slots, 0, now).is_empty());
+        assert_eq!(slots.insert(100, now), 1);
+        assert_eq!(ids(&slots, 1, now), [(100, false)]);
+    }
+}
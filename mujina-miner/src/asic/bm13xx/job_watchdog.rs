@@ -0,0 +1,180 @@
+//! Detecting jobs a chip never started on.
+//!
+//! A job frame corrupted or dropped on the serial link is discarded by the
+//! chip without a word. The host goes on believing the chip is hashing it,
+//! and nothing corrects that until the next job arrives, which on a quiet
+//! pool can be a minute or more of lost work.
+//!
+//! At the reporting difficulty the thread configures, a hashing chip
+//! answers within milliseconds, so a long silence after a dispatch means
+//! the job never arrived. [`JobWatchdog`] is armed when a task is sent and
+//! disarmed by the first nonce. If it expires, the task is sent again; if
+//! that goes unanswered too, the link or the chip is in a worse state than
+//! one lost frame and the chip is re-initialized.
+//!
+//! Register replies don't count as an answer: the temperature poll is
+//! answered whether or not the chip has work.
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Default time a chip gets to report its first nonce for a new task.
+pub const DEFAULT_NONCE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What to do about a task the chip has not answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogAction {
+    /// Send the task again, in case the frame was lost
+    Resend,
+    /// Re-initialize the chip, then send the task again
+    Reset,
+}
+
+/// Deadline for the first nonce after a dispatch.
+#[derive(Debug)]
+pub struct JobWatchdog {
+    timeout: Duration,
+    deadline: Option<Instant>,
+    /// Whether the current task has already been sent again
+    resent: bool,
+    resends: u64,
+    resets: u64,
+}
+
+impl JobWatchdog {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            deadline: None,
+            resent: false,
+            resends: 0,
+            resets: 0,
+        }
+    }
+
+    /// Change the timeout, taking effect from the next dispatch.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// A new task was sent to the chip at `now`.
+    pub fn arm(&mut self, now: Instant) {
+        self.deadline = Some(now + self.timeout);
+        self.resent = false;
+    }
+
+    /// The task was sent again at `now`, after [`expire`](Self::expire).
+    ///
+    /// Unlike [`arm`](Self::arm) this remembers what was already tried.
+    pub fn rearm(&mut self, now: Instant) {
+        self.deadline = Some(now + self.timeout);
+    }
+
+    /// The chip reported a nonce; it has work.
+    pub fn heard(&mut self) {
+        self.deadline = None;
+    }
+
+    /// The chip was sent idle; there is nothing to answer.
+    pub fn disarm(&mut self) {
+        self.deadline = None;
+    }
+
+    /// When the chip must have answered by, if a dispatch is outstanding.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Decide what to do once the deadline has passed at `now`.
+    ///
+    /// The watchdog stays disarmed until [`rearm`](Self::rearm)ed once
+    /// the action is done, since a reset takes a while. A reset starts the
+    /// count again, so a chip that stays silent is reset every second
+    /// timeout rather than given up on.
+    pub fn expire(&mut self, now: Instant) -> Option<WatchdogAction> {
+        if self.deadline.is_none_or(|deadline| now < deadline) {
+            return None;
+        }
+        self.deadline = None;
+        if self.resent {
+            self.resent = false;
+            self.resets += 1;
+            Some(WatchdogAction::Reset)
+        } else {
+            self.resent = true;
+            self.resends += 1;
+            Some(WatchdogAction::Resend)
+        }
+    }
+
+    /// Tasks sent again because the chip was silent, so far.
+    pub fn resends(&self) -> u64 {
+        self.resends
+    }
+
+    /// Chip re-initializations after a resend went unanswered, so far.
+    pub fn resets(&self) -> u64 {
+        self.resets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn a_nonce_disarms() {
+        let mut watchdog = JobWatchdog::new(TIMEOUT);
+        let now = Instant::now();
+        assert_eq!(watchdog.expire(now + TIMEOUT), None, "not armed");
+
+        watchdog.arm(now);
+        assert_eq!(watchdog.deadline(), Some(now + TIMEOUT));
+        assert_eq!(watchdog.expire(now + TIMEOUT / 2), None);
+
+        watchdog.heard();
+        assert_eq!(watchdog.deadline(), None);
+        assert_eq!(watchdog.expire(now + TIMEOUT), None);
+    }
+
+    #[test]
+    fn silence_resends_then_resets() {
+        let mut watchdog = JobWatchdog::new(TIMEOUT);
+        let now = Instant::now();
+        watchdog.arm(now);
+
+        let first = now + TIMEOUT;
+        assert_eq!(watchdog.expire(first), Some(WatchdogAction::Resend));
+        assert_eq!(watchdog.deadline(), None);
+        watchdog.rearm(first);
+        assert_eq!(watchdog.deadline(), Some(first + TIMEOUT));
+
+        let second = first + TIMEOUT;
+        assert_eq!(watchdog.expire(second), Some(WatchdogAction::Reset));
+        watchdog.rearm(second);
+        assert_eq!(
+            watchdog.expire(second + TIMEOUT),
+            Some(WatchdogAction::Resend)
+        );
+        assert_eq!((watchdog.resends(), watchdog.resets()), (2, 1));
+    }
+
+    #[test]
+    fn a_new_task_starts_afresh() {
+        let mut watchdog = JobWatchdog::new(TIMEOUT);
+        let now = Instant::now();
+        watchdog.arm(now);
+        assert_eq!(watchdog.expire(now + TIMEOUT), Some(WatchdogAction::Resend));
+
+        // The next task gets its own resend before any reset
+        let later = now + TIMEOUT * 3 / 2;
+        watchdog.arm(later);
+        assert_eq!(
+            watchdog.expire(later + TIMEOUT),
+            Some(WatchdogAction::Resend)
+        );
+    }
+}
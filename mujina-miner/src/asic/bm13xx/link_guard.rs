@@ -0,0 +1,229 @@
+//! Falling back to a slower link when the serial line gets noisy.
+//!
+//! EMI from a nearby motor or a long, poorly shielded cable corrupts bytes
+//! on the chip link. The codec throws corrupted frames away (see
+//! [`DecodeStats`]), so noise costs nonces and, past a point, commands the
+//! chips never see. A lower baud rate tolerates more noise, and chips at a
+//! lower frequency report fewer nonces to lose.
+//!
+//! [`LinkGuard`] watches the share of bad frames. When it passes
+//! [`DEGRADE_ERROR_RATIO`], the link is degraded: the thread drops to the
+//! chips' power-up baud rate and [`DEGRADED_FREQUENCY_FACTOR`] of its
+//! frequency, and reports it. After [`PROBE_INTERVAL`] it probes by
+//! restoring full speed. If the errors come back within [`PROBE_WINDOW`]
+//! the link is degraded again and the next probe waits twice as long, up
+//! to [`MAX_PROBE_INTERVAL`]; otherwise the link stays at full speed.
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use super::protocol::DecodeStats;
+
+/// Share of bad frames at which the link is degraded.
+pub const DEGRADE_ERROR_RATIO: f64 = 0.1;
+
+/// Frames, good or bad, a verdict on the link is based on.
+const MIN_FRAMES: u64 = 50;
+
+/// Fraction of its usual frequency a chain on a degraded link runs at.
+pub const DEGRADED_FREQUENCY_FACTOR: f32 = 0.8;
+
+/// Time on a degraded link before the first probe at full speed.
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Longest wait between probes of a link that stays noisy.
+pub const MAX_PROBE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Time a probe has to stay clean for full speed to be kept.
+pub const PROBE_WINDOW: Duration = Duration::from_secs(60);
+
+/// What the thread should do about its link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkAction {
+    /// Drop to the reduced baud rate and frequency
+    Degrade,
+    /// Try full speed again
+    Probe,
+    /// The probe stayed clean; full speed is kept
+    Restored,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Normal,
+    Degraded { probe_at: Instant },
+    Probing { until: Instant },
+}
+
+/// Error rate tracking and speed decisions for one chip link.
+#[derive(Debug)]
+pub struct LinkGuard {
+    /// Counts at the start of the frames not yet judged
+    base: Option<DecodeStats>,
+    state: State,
+    probe_interval: Duration,
+    degradations: u64,
+}
+
+impl Default for LinkGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinkGuard {
+    pub fn new() -> Self {
+        Self {
+            base: None,
+            state: State::Normal,
+            probe_interval: PROBE_INTERVAL,
+            degradations: 0,
+        }
+    }
+
+    /// Look at the link's decode counts at `now`.
+    ///
+    /// Frames are judged once at least [`MIN_FRAMES`] have arrived since
+    /// the last verdict. Counts right after a speed change aren't judged,
+    /// since the switch itself garbles a frame or two.
+    pub fn check(&mut self, stats: DecodeStats, now: Instant) -> Option<LinkAction> {
+        let noisy = match self.base {
+            None => {
+                self.base = Some(stats);
+                false
+            }
+            Some(base) => {
+                let bad = (stats.crc_errors + stats.decode_errors)
+                    .saturating_sub(base.crc_errors + base.decode_errors);
+                let seen = bad + stats.frames.saturating_sub(base.frames);
+                if seen >= MIN_FRAMES {
+                    self.base = Some(stats);
+                    bad as f64 / seen as f64 >= DEGRADE_ERROR_RATIO
+                } else {
+                    false
+                }
+            }
+        };
+
+        match self.state {
+            State::Normal if noisy => Some(self.degrade(now)),
+            State::Normal => None,
+            State::Degraded { probe_at } if now >= probe_at => {
+                self.state = State::Probing {
+                    until: now + PROBE_WINDOW,
+                };
+                self.base = None;
+                Some(LinkAction::Probe)
+            }
+            State::Degraded { .. } => None,
+            State::Probing { .. } if noisy => {
+                self.probe_interval = (self.probe_interval * 2).min(MAX_PROBE_INTERVAL);
+                Some(self.degrade(now))
+            }
+            State::Probing { until } if now >= until => {
+                self.state = State::Normal;
+                self.probe_interval = PROBE_INTERVAL;
+                Some(LinkAction::Restored)
+            }
+            State::Probing { .. } => None,
+        }
+    }
+
+    fn degrade(&mut self, now: Instant) -> LinkAction {
+        self.state = State::Degraded {
+            probe_at: now + self.probe_interval,
+        };
+        self.base = None;
+        self.degradations += 1;
+        LinkAction::Degrade
+    }
+
+    /// Whether the link runs at reduced speed.
+    pub fn is_degraded(&self) -> bool {
+        matches!(self.state, State::Degraded { .. })
+    }
+
+    /// The frequency to run at instead of `mhz`, reduced while degraded.
+    pub fn cap(&self, mhz: f32) -> f32 {
+        if self.is_degraded() {
+            mhz * DEGRADED_FREQUENCY_FACTOR
+        } else {
+            mhz
+        }
+    }
+
+    /// Times the link has been degraded since the thread started.
+    pub fn degradations(&self) -> u64 {
+        self.degradations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn stats(frames: u64, crc_errors: u64) -> DecodeStats {
+        DecodeStats {
+            frames,
+            crc_errors,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn occasional_errors_are_tolerated() {
+        let mut guard = LinkGuard::new();
+        let now = Instant::now();
+        assert_eq!(guard.check(stats(0, 0), now), None);
+        assert_eq!(guard.check(stats(95, 5), now + SECOND), None);
+        // Too few frames to judge
+        assert_eq!(guard.check(stats(100, 15), now + SECOND * 2), None);
+        assert!(!guard.is_degraded());
+        assert_eq!(guard.cap(500.0), 500.0);
+    }
+
+    #[test]
+    fn noisy_link_degrades_and_probes_with_backoff() {
+        let mut guard = LinkGuard::new();
+        let now = Instant::now();
+        guard.check(stats(0, 0), now);
+        assert_eq!(
+            guard.check(stats(40, 20), now + SECOND),
+            Some(LinkAction::Degrade)
+        );
+        assert!(guard.is_degraded());
+        assert_eq!(guard.cap(500.0), 400.0);
+
+        // Still noisy when the first probe comes: degraded again, and
+        // the next probe waits twice as long. The check right after a
+        // speed change only takes a baseline.
+        let probe = now + SECOND + PROBE_INTERVAL;
+        assert_eq!(guard.check(stats(40, 20), probe), Some(LinkAction::Probe));
+        assert!(!guard.is_degraded());
+        assert_eq!(guard.check(stats(80, 40), probe + SECOND), None);
+        let noisy_again = probe + SECOND * 2;
+        assert_eq!(
+            guard.check(stats(120, 60), noisy_again),
+            Some(LinkAction::Degrade)
+        );
+        assert_eq!(
+            guard.check(stats(120, 60), noisy_again + PROBE_INTERVAL),
+            None
+        );
+        let probe = noisy_again + PROBE_INTERVAL * 2;
+        assert_eq!(guard.check(stats(120, 60), probe), Some(LinkAction::Probe));
+
+        // The line has calmed down: full speed is kept
+        assert_eq!(guard.check(stats(120, 60), probe + SECOND), None);
+        assert_eq!(guard.check(stats(220, 61), probe + SECOND * 2), None);
+        assert_eq!(
+            guard.check(stats(320, 61), probe + PROBE_WINDOW),
+            Some(LinkAction::Restored)
+        );
+        assert!(!guard.is_degraded());
+        assert_eq!(guard.degradations(), 2);
+    }
+}
@@ -5,14 +5,30 @@
 
 pub mod crc;
 pub mod error;
+pub mod flap_damper;
+pub mod init_sequence;
+pub mod job_dispatch;
+pub mod job_slots;
+pub mod job_watchdog;
+pub mod link_guard;
+pub mod model;
+pub mod nonce_latency;
+pub mod nonce_map;
+pub mod nonce_rate;
+pub mod nonce_space;
+pub mod pll_readback;
 pub mod protocol;
+pub mod register_dump;
 pub mod thread;
+pub mod write_batch;
 
+#[cfg(test)]
+pub mod sim;
 #[cfg(test)]
 pub mod test_data;
 
 // Re-export commonly used types
-pub use protocol::{FrameCodec, Register, Response};
+pub use protocol::{CommandDecoder, DecodeStats, FrameCodec, Register, Response};
 
 // Re-export the protocol handler
 pub use protocol::BM13xxProtocol;
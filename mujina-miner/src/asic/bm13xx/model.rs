@@ -0,0 +1,163 @@
+//! What is known about each BM13xx chip model.
+//!
+//! Figures are from esp-miner, which has run these chips on Bitaxe
+//! boards for years, and from captures of stock firmware. Discovery looks
+//! each chip's ID up here to fill in its [`ChipInfo`], and hash threads
+//! take their defaults (starting frequency, frequency ceiling, hashrate
+//! estimate, version rolling, nonce reporting) from the model rather than
+//! from numbers of their own.
+//!
+//! Models missing from [`MODELS`], such as the BM1362, are known by ID
+//! only; callers fall back to conservative behavior for them.
+
+use std::time::Duration;
+
+use super::protocol::{ChipType, Hashrate, ReportingInterval, ReportingRate, TicketMask};
+use crate::asic::ChipInfo;
+use crate::job_source::GeneralPurposeBits;
+use crate::types::HashRate;
+
+/// Defaults and limits for one chip model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChipModel {
+    pub chip_type: ChipType,
+    /// Hash engines on the die, if known
+    pub core_count: Option<u32>,
+    /// Hashes completed per core clock cycle across the chip; theoretical
+    /// hashrate is this times the core frequency. These are the small
+    /// core counts esp-miner uses for its expected hashrate.
+    pub hashes_per_clock: u32,
+    /// Core frequency the chip runs at unless told otherwise, MHz
+    pub default_frequency_mhz: f32,
+    /// Fastest core frequency the chip is driven at, MHz
+    pub max_frequency_mhz: f32,
+    /// Core voltage at the default frequency, V
+    pub nominal_voltage_v: f32,
+    /// General purpose version bits the chip can roll (bits 13-28 of the
+    /// block version, as a 16-bit mask)
+    pub version_rolling_bits: u16,
+    /// Time between nonces the chip reports at its default frequency,
+    /// which sets its ticket mask
+    pub nonce_interval: Duration,
+}
+
+pub const BM1366: ChipModel = ChipModel {
+    chip_type: ChipType::BM1366,
+    core_count: None,
+    hashes_per_clock: 894,
+    default_frequency_mhz: 485.0,
+    max_frequency_mhz: 575.0,
+    nominal_voltage_v: 1.2,
+    version_rolling_bits: 0xffff,
+    nonce_interval: Duration::from_secs(1),
+};
+
+pub const BM1370: ChipModel = ChipModel {
+    chip_type: ChipType::BM1370,
+    // 80 domains of 16 engines
+    core_count: Some(1280),
+    hashes_per_clock: 2040,
+    default_frequency_mhz: 525.0,
+    max_frequency_mhz: 625.0,
+    nominal_voltage_v: 1.15,
+    version_rolling_bits: 0xffff,
+    nonce_interval: Duration::from_secs(1),
+};
+
+pub const BM1397: ChipModel = ChipModel {
+    chip_type: ChipType::BM1397,
+    core_count: None,
+    hashes_per_clock: 672,
+    default_frequency_mhz: 425.0,
+    max_frequency_mhz: 500.0,
+    nominal_voltage_v: 1.4,
+    version_rolling_bits: 0xffff,
+    nonce_interval: Duration::from_secs(1),
+};
+
+/// Every model with known figures.
+pub const MODELS: &[ChipModel] = &[BM1366, BM1370, BM1397];
+
+impl ChipModel {
+    /// The model of `chip_type`, if its figures are known.
+    pub fn lookup(chip_type: ChipType) -> Option<&'static ChipModel> {
+        MODELS.iter().find(|m| m.chip_type == chip_type)
+    }
+
+    /// Hashrate the chip should deliver at `frequency_mhz`.
+    pub fn hashrate_at(&self, frequency_mhz: f32) -> HashRate {
+        HashRate::from_megahashes(frequency_mhz as f64 * self.hashes_per_clock as f64)
+    }
+
+    /// Version bits the chip can roll.
+    pub fn version_rolling(&self) -> GeneralPurposeBits {
+        GeneralPurposeBits::new(self.version_rolling_bits.to_be_bytes())
+    }
+
+    /// Ticket mask that has the chip report a nonce about every
+    /// [`nonce_interval`](Self::nonce_interval) at its default frequency.
+    pub fn ticket_mask(&self) -> TicketMask {
+        let hashrate = self.hashrate_at(self.default_frequency_mhz);
+        TicketMask::new(ReportingInterval::from_rate(
+            Hashrate::gibihashes_per_sec(u64::from(hashrate) as f64 / 2f64.powi(30)),
+            ReportingRate::nonces_per_sec(1.0 / self.nonce_interval.as_secs_f64()),
+        ))
+    }
+}
+
+/// Describe a chip found during discovery from what it reported.
+///
+/// The chip's own core count is kept when it reports one; version
+/// rolling is assumed only for models known to support it.
+pub fn chip_info(chip_type: ChipType, reported_cores: u8, address: u8) -> ChipInfo {
+    let model = ChipModel::lookup(chip_type);
+    let core_count = match reported_cores {
+        0 => model.and_then(|m| m.core_count).unwrap_or(0),
+        cores => cores.into(),
+    };
+    ChipInfo {
+        chip_id: chip_type.id_bytes(),
+        core_count,
+        address,
+        supports_version_rolling: model.is_some_and(|m| m.version_rolling_bits != 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn models_are_found_by_chip_type() {
+        assert_eq!(ChipModel::lookup(ChipType::BM1370), Some(&BM1370));
+        assert_eq!(ChipModel::lookup(ChipType::BM1362), None);
+        for model in MODELS {
+            assert!(model.default_frequency_mhz <= model.max_frequency_mhz);
+            assert_eq!(ChipType::from(model.chip_type.id_bytes()), model.chip_type);
+        }
+    }
+
+    #[test]
+    fn defaults_follow_from_the_model() {
+        // About 1.07 TH/s at 525 MHz
+        let hashrate = BM1370.hashrate_at(BM1370.default_frequency_mhz);
+        assert_eq!(u64::from(hashrate), 1_071_000_000_000);
+        // One nonce a second at ~2^40 hashes a second
+        assert_eq!(BM1370.ticket_mask().difficulty(), 256);
+        assert_eq!(BM1370.version_rolling(), GeneralPurposeBits::full());
+    }
+
+    #[test]
+    fn discovery_fills_in_what_the_chip_does_not_report() {
+        let info = chip_info(ChipType::BM1370, 0, 0x08);
+        assert_eq!(info.core_count, 1280);
+        assert!(info.supports_version_rolling);
+
+        let info = chip_info(ChipType::BM1370, 128, 0);
+        assert_eq!(info.core_count, 128);
+
+        let unknown = chip_info(ChipType::Unknown([0x13, 0x99]), 0, 0);
+        assert_eq!(unknown.core_count, 0);
+        assert!(!unknown.supports_version_rolling);
+    }
+}
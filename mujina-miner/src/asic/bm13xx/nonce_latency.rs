@@ -0,0 +1,259 @@
+//! How long each chip takes to answer a new job.
+//!
+//! Every job dispatch goes to the whole chain at once, and each chip
+//! answers with its first nonce at chip difficulty. How long that takes
+//! is mostly chance, a wait of about a second at the reporting
+//! difficulty the thread sets, but serial congestion delays the job on
+//! its way out and the nonce on its way back, and a chip losing cores
+//! or dropping frames takes longer to find anything. Either shows as the
+//! chip's latency creeping up against its own history.
+//!
+//! [`NonceLatency`] histograms the time from dispatch to each chip's
+//! first nonce, in power-of-two millisecond buckets, and keeps a fast
+//! and a slow moving average. A chip whose fast average runs
+//! [`RISING_FACTOR`] times its slow one is flagged. Counts are halved
+//! once a chip's total reaches [`MAX_SAMPLES`], as in the
+//! [nonce map](super::nonce_map).
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use super::job_slots::JOB_ID_COUNT;
+use super::nonce_rate::chip_for_nonce;
+use crate::api_client::types::{LatencyBucket, NonceLatencyHistogram};
+
+/// Buckets: under 1 ms, then 1-2 ms, 2-4 ms and so on; the last holds
+/// everything from about 33 s.
+pub const BUCKETS: usize = 17;
+
+/// Weight of each latency in the fast moving average.
+const RECENT_WEIGHT: f64 = 1.0 / 8.0;
+
+/// Weight of each latency in the slow moving average.
+const BASELINE_WEIGHT: f64 = 1.0 / 256.0;
+
+/// Latencies per chip before it is judged.
+const MIN_SAMPLES: u64 = 64;
+
+/// Fast average over slow average at which latency counts as rising.
+pub const RISING_FACTOR: f64 = 2.0;
+
+/// Latencies per chip at which counts are halved.
+const MAX_SAMPLES: u64 = 1 << 12;
+
+/// A change in a chip's latency worth logging.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LatencyAlert {
+    /// Well above the chip's usual
+    Rising {
+        chip: usize,
+        recent_ms: f64,
+        baseline_ms: f64,
+    },
+    /// Back to the chip's usual
+    Settled { chip: usize },
+}
+
+/// Dispatch-to-first-nonce latency for each chip on a chain.
+#[derive(Debug, Clone)]
+pub struct NonceLatency {
+    chips: Vec<ChipLatency>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ChipLatency {
+    /// Dispatch the chip last answered under each job ID
+    answered: [Option<Instant>; JOB_ID_COUNT],
+    buckets: [u64; BUCKETS],
+    total: u64,
+    samples: u64,
+    recent_ms: f64,
+    baseline_ms: f64,
+    rising: bool,
+}
+
+impl NonceLatency {
+    pub fn new(chip_count: usize) -> Self {
+        Self {
+            chips: vec![ChipLatency::default(); chip_count.max(1)],
+        }
+    }
+
+    /// Note a nonce under `job_id`, for the dispatch sent at `sent_at`.
+    ///
+    /// Only each chip's first nonce per dispatch counts.
+    pub fn record(
+        &mut self,
+        nonce: u32,
+        job_id: u8,
+        sent_at: Instant,
+        now: Instant,
+    ) -> Option<LatencyAlert> {
+        let chip_count = self.chips.len();
+        let index = chip_for_nonce(nonce, chip_count);
+        let chip = &mut self.chips[index];
+        let answered = chip.answered.get_mut(job_id as usize)?;
+        if *answered == Some(sent_at) {
+            return None;
+        }
+        *answered = Some(sent_at);
+        chip.record(now.duration_since(sent_at), index)
+    }
+
+    /// Histogram for each chip, in chain order.
+    pub fn reports(&self) -> Vec<NonceLatencyHistogram> {
+        self.chips.iter().map(ChipLatency::report).collect()
+    }
+}
+
+impl ChipLatency {
+    fn record(&mut self, latency: Duration, index: usize) -> Option<LatencyAlert> {
+        let ms = latency.as_secs_f64() * 1000.0;
+        self.buckets[bucket(latency)] += 1;
+        self.total += 1;
+        if self.total >= MAX_SAMPLES {
+            for count in &mut self.buckets {
+                *count /= 2;
+            }
+            self.total = self.buckets.iter().sum();
+        }
+
+        if self.samples == 0 {
+            self.recent_ms = ms;
+            self.baseline_ms = ms;
+        } else {
+            self.recent_ms += (ms - self.recent_ms) * RECENT_WEIGHT;
+            self.baseline_ms += (ms - self.baseline_ms) * BASELINE_WEIGHT;
+        }
+        self.samples += 1;
+        if self.samples < MIN_SAMPLES {
+            return None;
+        }
+
+        let rising = self.recent_ms > self.baseline_ms * RISING_FACTOR;
+        if rising == self.rising {
+            return None;
+        }
+        self.rising = rising;
+        Some(if rising {
+            LatencyAlert::Rising {
+                chip: index,
+                recent_ms: self.recent_ms,
+                baseline_ms: self.baseline_ms,
+            }
+        } else {
+            LatencyAlert::Settled { chip: index }
+        })
+    }
+
+    fn report(&self) -> NonceLatencyHistogram {
+        let judged = self.samples >= MIN_SAMPLES;
+        NonceLatencyHistogram {
+            count: self.total,
+            recent_ms: (self.samples > 0).then(|| self.recent_ms.round() as u64),
+            baseline_ms: judged.then(|| self.baseline_ms.round() as u64),
+            rising: self.rising,
+            buckets: self
+                .buckets
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0)
+                .map(|(i, &count)| LatencyBucket {
+                    min_ms: if i == 0 { 0 } else { 1 << (i - 1) },
+                    count,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Bucket a latency falls in.
+fn bucket(latency: Duration) -> usize {
+    let ms = latency.as_millis();
+    if ms == 0 {
+        return 0;
+    }
+    let bits = (u128::BITS - ms.leading_zeros()) as usize;
+    bits.min(BUCKETS - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    /// Dispatch at `sent_at` answered by chip 0 after `latency`.
+    fn answer(
+        latency: &mut NonceLatency,
+        sent_at: Instant,
+        after: Duration,
+    ) -> Option<LatencyAlert> {
+        latency.record(0, 0, sent_at, sent_at + after)
+    }
+
+    #[test]
+    fn latencies_fall_in_power_of_two_buckets() {
+        assert_eq!(bucket(Duration::ZERO), 0);
+        assert_eq!(bucket(MS), 1);
+        assert_eq!(bucket(MS * 3), 2);
+        assert_eq!(bucket(MS * 1000), 10);
+        assert_eq!(bucket(Duration::from_secs(3600)), BUCKETS - 1);
+    }
+
+    #[test]
+    fn only_the_first_nonce_per_dispatch_counts() {
+        let mut latency = NonceLatency::new(2);
+        let sent = Instant::now();
+        latency.record(0, 3, sent, sent + MS * 500);
+        latency.record(1, 3, sent, sent + MS * 600);
+        // Chip 1's half of the nonce space
+        latency.record(0x8000_0000, 3, sent, sent + MS * 700);
+        latency.record(0x8000_0001, 3, sent, sent + MS * 900);
+
+        let reports = latency.reports();
+        assert_eq!(reports[0].count, 1);
+        assert_eq!(reports[0].recent_ms, Some(500));
+        assert_eq!(
+            reports[0].buckets,
+            [LatencyBucket {
+                min_ms: 256,
+                count: 1
+            }]
+        );
+        assert_eq!(reports[1].recent_ms, Some(700));
+        assert_eq!(reports[1].baseline_ms, None, "too few to judge");
+
+        // The next dispatch under the same ID counts again
+        let resent = sent + Duration::from_secs(5);
+        latency.record(0, 3, resent, resent + MS * 100);
+        assert_eq!(latency.reports()[0].count, 2);
+    }
+
+    #[test]
+    fn rising_latency_is_flagged_and_cleared() {
+        let mut latency = NonceLatency::new(1);
+        let mut sent = Instant::now();
+        for _ in 0..MIN_SAMPLES {
+            assert_eq!(answer(&mut latency, sent, MS * 800), None);
+            sent += Duration::from_secs(1);
+        }
+
+        let mut alert = None;
+        for _ in 0..20 {
+            alert = alert.or(answer(&mut latency, sent, MS * 4000));
+            sent += Duration::from_secs(5);
+        }
+        assert!(matches!(alert, Some(LatencyAlert::Rising { chip: 0, .. })));
+        assert!(latency.reports()[0].rising);
+
+        let mut alert = None;
+        for _ in 0..40 {
+            alert = alert.or(answer(&mut latency, sent, MS * 800));
+            sent += Duration::from_secs(1);
+        }
+        assert_eq!(alert, Some(LatencyAlert::Settled { chip: 0 }));
+        assert!(!latency.reports()[0].rising);
+    }
+}
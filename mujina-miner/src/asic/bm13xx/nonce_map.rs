@@ -0,0 +1,184 @@
+//! Where each chip's nonces fall, for spotting partially defective dies.
+//!
+//! A healthy chip finds nonces evenly across its slice of the nonce
+//! space and across its cores. A die with dead or slow cores still
+//! hashes, just less, and the loss only shows as a hashrate a little
+//! under what the frequency promises. Binning nonces makes the gap
+//! visible: the top nonce bits carry the main core that found it, so a
+//! slice of the chip's range that comes up short points at a group of
+//! cores, and the small core ID in each result points at the rest.
+//!
+//! Nonces at chip difficulty arrive at random, so each bin's count is
+//! Poisson around an even share of the chip's total. A bin is flagged
+//! weak once the chip has enough samples and the bin falls more than
+//! [`WEAK_Z_SCORE`] standard deviations short. Counts are halved once a
+//! chip's total reaches [`MAX_SAMPLES`], so the map follows a chip that
+//! degrades over time rather than averaging over its whole life.
+
+use crate::api_client::types::ChipNonceReport;
+
+use super::nonce_rate::chip_for_nonce;
+
+/// Equal slices each chip's nonce range is split into.
+pub const REGIONS: usize = 16;
+
+/// Small cores per main core, as numbered in nonce results.
+pub const SMALL_CORES: usize = 16;
+
+/// Expected nonces per bin before any bin is judged.
+const MIN_EXPECTED: f64 = 32.0;
+
+/// Standard deviations below its share at which a bin is weak.
+const WEAK_Z_SCORE: f64 = 4.0;
+
+/// Total nonces per chip at which counts are halved.
+const MAX_SAMPLES: u64 = 1 << 16;
+
+/// Nonce counts by region and small core for each chip on a chain.
+#[derive(Debug, Clone)]
+pub struct NonceMap {
+    chips: Vec<ChipBins>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ChipBins {
+    total: u64,
+    regions: [u64; REGIONS],
+    small_cores: [u64; SMALL_CORES],
+}
+
+impl NonceMap {
+    pub fn new(chip_count: usize) -> Self {
+        Self {
+            chips: vec![ChipBins::default(); chip_count.max(1)],
+        }
+    }
+
+    /// Count a nonce and the small core that reported it.
+    pub fn record(&mut self, nonce: u32, small_core: u8) {
+        let chip_count = self.chips.len();
+        let chip = &mut self.chips[chip_for_nonce(nonce, chip_count)];
+
+        // Drop the chip's own slice bits to get the position within it
+        let chip_bits = chip_count.next_power_of_two().trailing_zeros();
+        let within = (u64::from(nonce) << chip_bits) & 0xffff_ffff;
+        let region = (within * REGIONS as u64) >> 32;
+
+        chip.regions[region as usize] += 1;
+        chip.small_cores[usize::from(small_core) % SMALL_CORES] += 1;
+        chip.total += 1;
+        if chip.total >= MAX_SAMPLES {
+            chip.age();
+        }
+    }
+
+    /// Report for each chip, in chain order.
+    pub fn reports(&self) -> Vec<ChipNonceReport> {
+        self.chips
+            .iter()
+            .enumerate()
+            .map(|(chip, bins)| ChipNonceReport {
+                chip,
+                nonces: bins.total,
+                regions: bins.regions.to_vec(),
+                small_cores: bins.small_cores.to_vec(),
+                weak_regions: weak_bins(&bins.regions, bins.total),
+                weak_small_cores: weak_bins(&bins.small_cores, bins.total),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+impl ChipBins {
+    /// Halve every count, keeping the proportions.
+    fn age(&mut self) {
+        for count in self.regions.iter_mut().chain(&mut self.small_cores) {
+            *count /= 2;
+        }
+        self.total = self.regions.iter().sum();
+    }
+}
+
+/// Indices of bins well short of an even share of `total`.
+fn weak_bins(bins: &[u64], total: u64) -> Vec<usize> {
+    let expected = total as f64 / bins.len() as f64;
+    if expected < MIN_EXPECTED {
+        return Vec::new();
+    }
+    bins.iter()
+        .enumerate()
+        .filter(|(_, count)| (expected - **count as f64) / expected.sqrt() > WEAK_Z_SCORE)
+        .map(|(index, _)| index)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Nonces spread evenly over a chip's slice, with a given number of
+    /// chips on the chain.
+    fn spread(chip: u32, chip_count: usize, n: u32) -> impl Iterator<Item = u32> {
+        let chip_bits = chip_count.next_power_of_two().trailing_zeros();
+        let slice = 1u64 << (32 - chip_bits);
+        (0..n).map(move |i| {
+            let offset = u64::from(i) * slice / u64::from(n);
+            (u64::from(chip) * slice + offset) as u32
+        })
+    }
+
+    #[test]
+    fn healthy_chips_have_no_weak_bins() {
+        let mut map = NonceMap::new(2);
+        for chip in 0..2 {
+            for (i, nonce) in spread(chip, 2, 1600).enumerate() {
+                map.record(nonce, i as u8);
+            }
+        }
+
+        let reports = map.reports();
+        assert_eq!(reports.len(), 2);
+        for report in &reports {
+            assert_eq!(report.nonces, 1600);
+            assert!(report.regions.iter().all(|&n| n == 100));
+            assert!(report.small_cores.iter().all(|&n| n == 100));
+            assert!(report.weak_regions.is_empty());
+            assert!(report.weak_small_cores.is_empty());
+        }
+    }
+
+    #[test]
+    fn flags_a_dead_region_and_small_core() {
+        let mut map = NonceMap::new(1);
+        for (i, nonce) in spread(0, 1, 3200).enumerate() {
+            // Region 5 and small core 3 never find anything
+            if (nonce >> 28) == 5 || i % SMALL_CORES == 3 {
+                continue;
+            }
+            map.record(nonce, (i % SMALL_CORES) as u8);
+        }
+
+        let report = &map.reports()[0];
+        assert_eq!(report.weak_regions, [5]);
+        assert_eq!(report.weak_small_cores, [3]);
+    }
+
+    #[test]
+    fn too_few_samples_are_not_judged() {
+        let mut map = NonceMap::new(1);
+        map.record(0, 0);
+        assert!(map.reports()[0].weak_regions.is_empty());
+    }
+
+    #[test]
+    fn counts_age_once_the_sample_is_full() {
+        let mut map = NonceMap::new(1);
+        for nonce in spread(0, 1, MAX_SAMPLES as u32) {
+            map.record(nonce, 0);
+        }
+        let report = &map.reports()[0];
+        assert_eq!(report.nonces, MAX_SAMPLES / 2);
+        assert_eq!(report.small_cores[0], MAX_SAMPLES / 2);
+    }
+}
@@ -0,0 +1,146 @@
+//! Per-chip hashrate from the rate of nonces at chip difficulty.
+//!
+//! Chips report every nonce that meets their ticket mask, which is set
+//! for about one nonce per second per terahash. That is far more often
+//! than pool shares arrive, so counting them gives a hashrate within
+//! seconds of a frequency change instead of minutes, and does so per
+//! chip rather than per thread.
+//!
+//! Nonces are attributed to chips by their top bits. Each chip on a
+//! chain searches its own slice of the nonce space, and the slices are
+//! spread evenly by chip address, so the top `log2(chips)` bits of a
+//! nonce identify the chip that found it (chain lengths that aren't a
+//! power of two are rounded up).
+
+use std::time::{Duration, Instant};
+
+use bitcoin::pow::Work;
+
+use crate::asic::ChipStats;
+use crate::types::{Difficulty, HashRate, HashrateEstimator};
+
+/// Window over which nonce rates are averaged.
+///
+/// At one nonce per second per terahash this holds about 60 samples for
+/// a 1 TH/s chip, enough to settle within ~15%.
+pub const NONCE_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Index of the chip on a chain of `chip_count` that found `nonce`.
+pub fn chip_for_nonce(nonce: u32, chip_count: usize) -> usize {
+    if chip_count <= 1 {
+        return 0;
+    }
+    let bits = chip_count.next_power_of_two().trailing_zeros();
+    let index = (nonce >> (32 - bits)) as usize;
+    // With a chain length rounded up, the top slices belong to no chip
+    index.min(chip_count - 1)
+}
+
+/// Nonce counts and hashrate estimates for each chip on a chain.
+pub struct ChipNonceRates {
+    work_per_nonce: Work,
+    chips: Vec<ChipRate>,
+}
+
+struct ChipRate {
+    nonces: u64,
+    estimator: HashrateEstimator,
+}
+
+impl ChipNonceRates {
+    /// Track `chip_count` chips reporting at `reporting_difficulty`.
+    pub fn new(chip_count: usize, reporting_difficulty: Difficulty) -> Self {
+        Self {
+            work_per_nonce: reporting_difficulty.to_target().to_work(),
+            chips: (0..chip_count.max(1))
+                .map(|_| ChipRate {
+                    nonces: 0,
+                    estimator: HashrateEstimator::new(NONCE_RATE_WINDOW),
+                })
+                .collect(),
+        }
+    }
+
+    /// Count a nonce reported at `at`.
+    pub fn record_at(&mut self, at: Instant, nonce: u32) {
+        let index = chip_for_nonce(nonce, self.chips.len());
+        let chip = &mut self.chips[index];
+        chip.nonces += 1;
+        chip.estimator.record_at(at, self.work_per_nonce);
+    }
+
+    /// Hashrate of each chip as of `now`.
+    pub fn chip_hashrates_at(&mut self, now: Instant) -> Vec<HashRate> {
+        self.chips
+            .iter_mut()
+            .map(|chip| chip.estimator.hashrate_at(now))
+            .collect()
+    }
+
+    /// Stats for each chip as of `now`.
+    ///
+    /// The hashrate is left out until a chip has reported enough nonces
+    /// in the window to be meaningful.
+    pub fn chip_stats_at(&mut self, now: Instant) -> Vec<ChipStats> {
+        self.chips
+            .iter_mut()
+            .map(|chip| {
+                let hashrate = chip.estimator.hashrate_at(now);
+                ChipStats {
+                    nonces_found: chip.nonces,
+                    hashrate: chip.estimator.is_settled().then_some(hashrate),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nonces_map_to_evenly_spaced_chips() {
+        assert_eq!(chip_for_nonce(0xffff_ffff, 1), 0);
+
+        assert_eq!(chip_for_nonce(0x0000_0001, 4), 0);
+        assert_eq!(chip_for_nonce(0x4000_0000, 4), 1);
+        assert_eq!(chip_for_nonce(0xbfff_ffff, 4), 2);
+        assert_eq!(chip_for_nonce(0xc000_0000, 4), 3);
+
+        // Three chips share a four-way split; the unused top slice folds
+        // into the last chip
+        assert_eq!(chip_for_nonce(0x8000_0000, 3), 2);
+        assert_eq!(chip_for_nonce(0xffff_ffff, 3), 2);
+    }
+
+    #[test]
+    fn hashrate_follows_nonce_rate_per_chip() {
+        let start = Instant::now();
+        let mut rates = ChipNonceRates::new(2, Difficulty::from(256));
+
+        // Chip 0 reports one nonce per second, chip 1 one every four
+        for second in 0..40u64 {
+            let at = start + Duration::from_secs(second);
+            rates.record_at(at, 0x0000_1000);
+            if second % 4 == 0 {
+                rates.record_at(at, 0x8000_1000);
+            }
+        }
+
+        let now = start + Duration::from_secs(40);
+        let hashrates = rates.chip_hashrates_at(now);
+        // Difficulty 256 is 256 * 2^32 hashes per nonce
+        let per_nonce = 256.0 * 4_294_967_296.0;
+        let chip0 = u64::from(hashrates[0]) as f64;
+        let chip1 = u64::from(hashrates[1]) as f64;
+        assert!((chip0 / per_nonce - 1.0).abs() < 0.05, "{chip0}");
+        assert!((chip1 / per_nonce - 0.25).abs() < 0.02, "{chip1}");
+
+        let stats = rates.chip_stats_at(now);
+        assert_eq!(stats[0].nonces_found, 40);
+        assert_eq!(stats[1].nonces_found, 10);
+        assert!(stats[1].hashrate.is_some());
+    }
+}
@@ -0,0 +1,209 @@
+//! Header space a chain works through for one job.
+//!
+//! The chips on a chain split the 32-bit nonce range between them by
+//! address, and each rolls every version bit it is allowed. A chip thus
+//! has `2^32 / chips × 2^version_bits` headers to try per job. Once it has
+//! tried them all its nonce counter wraps and it starts over, reporting
+//! the same nonces a second time. With the full 16 version bits a fast
+//! chain gets there in seconds; with a pool that allows few bits, in
+//! milliseconds. The ntime roll gives the chips a fresh header space each
+//! second, but only until it reaches its limit.
+//!
+//! The thread uses this model to dispatch fresh work (the next
+//! extranonce2 of its range) before any chip wraps, and drops the
+//! duplicates that slip through anyway.
+
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+use crate::job_source::{Extranonce2, Extranonce2Range, GeneralPurposeBits};
+use crate::types::HashRate;
+
+/// Nonces remembered for spotting duplicates.
+///
+/// A wrapped chip repeats its nonces one exhaustion period later, and fresh
+/// work is sent well within that period, so the window only has to span a
+/// few seconds of reports. At the reporting difficulty a chip reports about
+/// one nonce per second per terahash, which leaves room for a chain of a few
+/// hundred terahashes.
+pub const DUPLICATE_WINDOW: usize = 4096;
+
+/// Share of the predicted exhaustion time after which fresh work is sent.
+const REFRESH_MARGIN: f64 = 0.8;
+
+/// Shortest interval between header space refreshes.
+///
+/// Each one sends a full job, and faster than this the serial link spends
+/// more time on jobs than on nonces. Chains that wrap sooner still repeat
+/// work; the duplicate window keeps it from being credited.
+pub const MIN_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time until the first chip runs out of headers for a job.
+///
+/// `chip_rates` holds each chip's hashrate in chain order; chips without
+/// one are ignored. None if no chip is hashing.
+pub fn exhaustion_time(version_bits: u32, chip_rates: &[HashRate]) -> Option<Duration> {
+    let chips = chip_rates.len() as f64;
+    let headers_per_chip = 2f64.powi(32 + version_bits as i32) / chips;
+    chip_rates
+        .iter()
+        .filter(|rate| rate.0 > 0)
+        .map(|rate| headers_per_chip / rate.0 as f64)
+        .min_by(f64::total_cmp)
+        .map(Duration::from_secs_f64)
+}
+
+/// How long to keep a job before refreshing its header space.
+pub fn refresh_after(exhaustion: Duration) -> Duration {
+    exhaustion.mul_f64(REFRESH_MARGIN).max(MIN_REFRESH_INTERVAL)
+}
+
+/// Extranonce2 values a task moves on to as its header spaces run out.
+///
+/// The scheduler starts each round of a job one value further into the
+/// thread's range, so the walk runs down from the top of the range,
+/// away from the values later rounds start at, and stops short of the
+/// value the task started with.
+#[derive(Debug)]
+pub struct Extranonce2Walk {
+    start: u64,
+    max: u64,
+    next: u64,
+    size: u8,
+}
+
+impl Extranonce2Walk {
+    /// Walk the rest of `range` after `start`.
+    pub fn new(range: &Extranonce2Range, start: &Extranonce2) -> Self {
+        Self {
+            start: start.value(),
+            max: range.max,
+            next: range.max,
+            size: range.size,
+        }
+    }
+
+    /// Values mined so far, including the starting one.
+    pub fn searched(&self) -> u64 {
+        1 + (self.max - self.next.max(self.start))
+    }
+}
+
+impl Iterator for Extranonce2Walk {
+    type Item = Extranonce2;
+
+    /// Next value to mine, or None once the range is used up.
+    fn next(&mut self) -> Option<Extranonce2> {
+        if self.next <= self.start {
+            return None;
+        }
+        let value = self.next;
+        self.next -= 1;
+        Extranonce2::new(value, self.size).ok()
+    }
+}
+
+/// Recently reported nonces, for dropping repeats.
+#[derive(Debug)]
+pub struct DuplicateWindow {
+    seen: HashSet<(u8, u32, [u8; 2])>,
+    order: VecDeque<(u8, u32, [u8; 2])>,
+    capacity: usize,
+}
+
+impl DuplicateWindow {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            seen: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Note a nonce, returning true if it was already reported.
+    ///
+    /// A nonce is the same if it came for the same chip job ID with the
+    /// same version bits. A reused job ID carries a different header, on
+    /// which the same nonce and version meeting the reporting target is
+    /// vanishingly unlikely.
+    pub fn is_repeat(&mut self, job_id: u8, nonce: u32, version: GeneralPurposeBits) -> bool {
+        let key = (job_id, nonce, *version.as_bytes());
+        if !self.seen.insert(key) {
+            return true;
+        }
+        if self.order.len() == self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.seen.remove(&oldest);
+        }
+        self.order.push_back(key);
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slowest_space_per_chip_sets_the_pace() {
+        // 2^32 nonces split across two chips, 2 version bits: 2^33 headers each
+        let per_chip = 2f64.powi(33);
+        let rates = [
+            HashRate(per_chip as u64),
+            HashRate((per_chip * 4.0) as u64),
+            HashRate(0),
+        ];
+        // The idle third chip still takes its share of the nonce range
+        let headers = 2f64.powi(34) / 3.0;
+        let expected = Duration::from_secs_f64(headers / (per_chip * 4.0));
+        let time = exhaustion_time(2, &rates).unwrap();
+        assert!(
+            time.abs_diff(expected) < Duration::from_micros(1),
+            "{time:?}"
+        );
+
+        assert_eq!(exhaustion_time(16, &[HashRate(0)]), None);
+        assert_eq!(exhaustion_time(16, &[]), None);
+    }
+
+    #[test]
+    fn refresh_leaves_margin_but_not_too_little() {
+        assert_eq!(
+            refresh_after(Duration::from_secs(10)),
+            Duration::from_secs(8)
+        );
+        assert_eq!(
+            refresh_after(Duration::from_millis(10)),
+            MIN_REFRESH_INTERVAL
+        );
+    }
+
+    #[test]
+    fn walk_runs_down_to_the_starting_value() {
+        let range = Extranonce2Range::new_range(10, 13, 1).unwrap();
+        let start = Extranonce2::new(11, 1).unwrap();
+        let mut walk = Extranonce2Walk::new(&range, &start);
+        assert_eq!(walk.searched(), 1);
+
+        let values: Vec<u64> = walk.by_ref().map(|en2| en2.value()).collect();
+        assert_eq!(values, [13, 12]);
+        assert_eq!(walk.searched(), 3);
+        assert!(walk.next().is_none());
+    }
+
+    #[test]
+    fn repeats_are_caught_within_the_window() {
+        let version = GeneralPurposeBits::from([0x12, 0x34]);
+        let mut window = DuplicateWindow::new(2);
+        assert!(!window.is_repeat(1, 0xdead, version));
+        assert!(window.is_repeat(1, 0xdead, version));
+
+        // Same nonce under another job ID or version is new work
+        assert!(!window.is_repeat(2, 0xdead, version));
+        assert!(!window.is_repeat(1, 0xdead, GeneralPurposeBits::from([0, 0])));
+
+        // The window only remembers the most recent nonces
+        assert!(!window.is_repeat(1, 0xdead, version));
+    }
+}
@@ -0,0 +1,201 @@
+//! Core frequency read back from the chips' PLL registers.
+//!
+//! The thread knows what frequency it last wrote, but not whether every
+//! chip took it. A chip that browns out or resets itself comes back at
+//! its power-up PLL setting, and a write lost on the serial link leaves
+//! a chip where it was. Either way the chip hashes slower than the host
+//! believes, and the only symptom is a hashrate a little short.
+//!
+//! The thread reads the PLL register of every chip on each temperature
+//! poll. Chips answer a broadcast read in chain order, so the n-th answer
+//! since the poll belongs to the n-th chip. [`PllReadback`] keeps the
+//! frequency each chip reports, which is what the thread publishes, and
+//! compares it with the frequency the thread last wrote. A chip that
+//! disagrees raises a [`PllAlert`] once, and another once it agrees
+//! again.
+//!
+//! The thread may retune between sending a read and hearing the answers.
+//! Answers are only judged against the frequency written when the read
+//! was sent, and dropped if that has changed since.
+
+use super::protocol::PllConfig;
+
+/// How far a chip's PLL may be from the written frequency before it
+/// counts as a mismatch.
+///
+/// PLL settings are chosen to land within 1 MHz of the requested
+/// frequency, so a chip at its written setting is always within this.
+pub const MISMATCH_TOLERANCE_MHZ: f32 = 2.0;
+
+/// A change in whether a chip runs at the frequency it was given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PllAlert {
+    /// The chip reports a frequency other than the one written
+    Mismatch {
+        chip: usize,
+        read_mhz: f32,
+        written_mhz: f32,
+    },
+    /// The chip is back at the written frequency
+    Recovered { chip: usize, mhz: f32 },
+}
+
+/// The frequency each chip on a chain reports running at.
+#[derive(Debug, Clone)]
+pub struct PllReadback {
+    chips: Vec<ChipPll>,
+    /// Frequency written when the current read was sent, if one is
+    /// awaiting answers
+    written_mhz: Option<f32>,
+    /// Chip the next answer belongs to
+    next: usize,
+    mismatches: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ChipPll {
+    mhz: Option<f32>,
+    mismatched: bool,
+}
+
+impl PllReadback {
+    pub fn new(chip_count: usize) -> Self {
+        Self {
+            chips: vec![ChipPll::default(); chip_count.max(1)],
+            written_mhz: None,
+            next: 0,
+            mismatches: 0,
+        }
+    }
+
+    /// A read of every chip's PLL was sent while the thread had written
+    /// `written_mhz`.
+    pub fn requested(&mut self, written_mhz: f32) {
+        self.written_mhz = Some(written_mhz);
+        self.next = 0;
+    }
+
+    /// Record the next chip's answer, now that the thread has written
+    /// `written_mhz`.
+    ///
+    /// Returns an alert when the chip starts or stops disagreeing with
+    /// the written frequency.
+    pub fn record(&mut self, config: PllConfig, written_mhz: f32) -> Option<PllAlert> {
+        let requested_mhz = self.written_mhz?;
+        if requested_mhz != written_mhz {
+            // Retuned since the read went out; the answer may be either
+            self.written_mhz = None;
+            return None;
+        }
+        let chip = self.next;
+        let state = self.chips.get_mut(chip)?;
+        self.next += 1;
+
+        let read_mhz = config.frequency_mhz()?;
+        state.mhz = Some(read_mhz);
+        let mismatched = (read_mhz - written_mhz).abs() > MISMATCH_TOLERANCE_MHZ;
+        if mismatched == state.mismatched {
+            return None;
+        }
+        state.mismatched = mismatched;
+        if mismatched {
+            self.mismatches += 1;
+            Some(PllAlert::Mismatch {
+                chip,
+                read_mhz,
+                written_mhz,
+            })
+        } else {
+            Some(PllAlert::Recovered {
+                chip,
+                mhz: read_mhz,
+            })
+        }
+    }
+
+    /// Frequency `chip` last reported, in MHz.
+    pub fn frequency_mhz(&self, chip: usize) -> Option<f32> {
+        self.chips.get(chip).and_then(|state| state.mhz)
+    }
+
+    /// Times a chip was found away from the written frequency, so far.
+    pub fn mismatches(&self) -> u64 {
+        self.mismatches
+    }
+
+    /// Forget what the chips reported, after they were re-initialized.
+    pub fn reset(&mut self) {
+        let mismatches = self.mismatches;
+        *self = Self::new(self.chips.len());
+        self.mismatches = mismatches;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asic::bm13xx::protocol::Frequency;
+
+    fn pll(mhz: f32) -> PllConfig {
+        Frequency::from_mhz(mhz).unwrap().calculate_pll()
+    }
+
+    #[test]
+    fn answers_are_attributed_in_chain_order() {
+        let mut readback = PllReadback::new(2);
+        assert_eq!(readback.frequency_mhz(0), None);
+
+        readback.requested(525.0);
+        assert_eq!(readback.record(pll(525.0), 525.0), None);
+        assert_eq!(readback.record(pll(525.0), 525.0), None);
+        // A third answer has no chip to belong to
+        assert_eq!(readback.record(pll(100.0), 525.0), None);
+
+        assert!((readback.frequency_mhz(0).unwrap() - 525.0).abs() < 1.0);
+        assert!((readback.frequency_mhz(1).unwrap() - 525.0).abs() < 1.0);
+        assert_eq!(readback.mismatches(), 0);
+    }
+
+    #[test]
+    fn a_chip_that_reset_itself_alerts_once() {
+        let mut readback = PllReadback::new(2);
+
+        readback.requested(525.0);
+        assert_eq!(readback.record(pll(525.0), 525.0), None);
+        assert!(matches!(
+            readback.record(pll(56.25), 525.0),
+            Some(PllAlert::Mismatch {
+                chip: 1,
+                written_mhz: 525.0,
+                ..
+            })
+        ));
+
+        // Still at its power-up setting on the next poll
+        readback.requested(525.0);
+        readback.record(pll(525.0), 525.0);
+        assert_eq!(readback.record(pll(56.25), 525.0), None);
+        assert_eq!(readback.mismatches(), 1);
+        assert!(readback.frequency_mhz(1).unwrap() < 60.0);
+
+        readback.requested(525.0);
+        readback.record(pll(525.0), 525.0);
+        assert!(matches!(
+            readback.record(pll(525.0), 525.0),
+            Some(PllAlert::Recovered { chip: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn answers_after_a_retune_are_dropped() {
+        let mut readback = PllReadback::new(1);
+
+        readback.requested(525.0);
+        assert_eq!(readback.record(pll(525.0), 400.0), None);
+        assert_eq!(readback.frequency_mhz(0), None);
+
+        // Nor judged against the new frequency until the next read
+        assert_eq!(readback.record(pll(525.0), 400.0), None);
+        assert_eq!(readback.mismatches(), 0);
+    }
+}
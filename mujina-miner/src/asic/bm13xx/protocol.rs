@@ -12,8 +12,14 @@
 use bitcoin::hashes::Hash;
 use bitvec::prelude::*;
 use bytes::{Buf, BufMut, BytesMut};
-use std::{fmt, io};
-use strum::FromRepr;
+use std::{
+    fmt, io,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+use strum::{EnumIter, FromRepr};
 use tokio_util::codec::{Decoder, Encoder};
 
 use super::crc::{crc5, crc5_is_valid, crc16};
@@ -146,6 +152,24 @@ impl PllConfig {
     }
 }
 
+impl PllConfig {
+    /// Core frequency this configuration produces, in MHz.
+    ///
+    /// `None` for a configuration no chip would run at, such as a zero
+    /// divider read from a chip that hasn't been configured.
+    pub fn frequency_mhz(&self) -> Option<f32> {
+        if self.fb_div == 0 || self.ref_div == 0 {
+            return None;
+        }
+        let post_div1 = ((self.post_div >> 4) & 0xf) as u32 + 1;
+        let post_div2 = (self.post_div & 0xf) as u32 + 1;
+        Some(
+            Frequency::CRYSTAL_MHZ * self.fb_div as f32
+                / (self.ref_div as u32 * post_div1 * post_div2) as f32,
+        )
+    }
+}
+
 impl From<u32> for PllConfig {
     fn from(raw: u32) -> Self {
         Self {
@@ -192,12 +216,21 @@ impl ChipType {
         }
     }
 
+    /// Known figures for this chip type; see [`super::model`].
+    pub fn model(&self) -> Option<&'static super::model::ChipModel> {
+        super::model::ChipModel::lookup(*self)
+    }
+
     /// Get expected hash engine count for this chip type, if known
     pub fn core_count(&self) -> Option<u32> {
-        match self {
-            Self::BM1370 => Some(1280), // 80 domains x 16 engines
-            _ => None,
-        }
+        self.model().and_then(|m| m.core_count)
+    }
+
+    /// Hashes completed per core clock cycle across the chip, if known.
+    ///
+    /// Theoretical hashrate is this times the core frequency.
+    pub fn hashes_per_clock(&self) -> Option<u32> {
+        self.model().map(|m| m.hashes_per_clock)
     }
 }
 
@@ -413,6 +446,11 @@ impl TicketMask {
         }
     }
 
+    /// Share difficulty a nonce must reach to be reported.
+    pub const fn difficulty(&self) -> u64 {
+        1 << self.zero_bits
+    }
+
     /// Encode ticket mask to wire format bytes
     pub fn to_wire_bytes(&self) -> [u8; 4] {
         if self.zero_bits == 0 {
@@ -474,6 +512,31 @@ pub enum BaudRate {
     Custom(u32),
 }
 
+impl BaudRate {
+    /// Host-side serial rate in bits per second, if known.
+    ///
+    /// Returns `None` for `Custom` values since the relationship between
+    /// the raw register value and the line rate depends on chip clocking.
+    pub fn bits_per_sec(&self) -> Option<u32> {
+        match self {
+            BaudRate::Baud115200 => Some(115_200),
+            BaudRate::Baud1M => Some(1_000_000),
+            BaudRate::Baud3M => Some(3_000_000),
+            BaudRate::Custom(_) => None,
+        }
+    }
+
+    /// Look up the register setting for a host-side serial rate.
+    pub fn from_bits_per_sec(rate: u32) -> Option<Self> {
+        match rate {
+            115_200 => Some(BaudRate::Baud115200),
+            1_000_000 => Some(BaudRate::Baud1M),
+            3_000_000 => Some(BaudRate::Baud3M),
+            _ => None,
+        }
+    }
+}
+
 impl From<BaudRate> for [u8; 4] {
     fn from(baud: BaudRate) -> Self {
         let value = match baud {
@@ -552,11 +615,24 @@ impl VersionMask {
 
     /// Create version mask with all lower 16 bits enabled
     pub fn full_rolling() -> Self {
+        Self::rolling(Self::FULL_MASK)
+    }
+
+    /// Create version mask allowing the chip to roll only the given bits.
+    ///
+    /// `mask` covers the 16 general purpose version bits (13-28), i.e. a
+    /// Stratum version mask shifted right by 13.
+    pub fn rolling(mask: u16) -> Self {
         Self {
-            mask: Self::FULL_MASK,
+            mask,
             control: Self::ENABLE_ROLLING,
         }
     }
+
+    /// Bits the chip is allowed to roll.
+    pub fn mask(&self) -> u16 {
+        self.mask
+    }
 }
 
 impl fmt::Debug for VersionMask {
@@ -577,27 +653,98 @@ impl From<VersionMask> for [u8; 4] {
     fn from(mask: VersionMask) -> Self {
         let mut bytes = [0u8; 4];
         bytes[0..2].copy_from_slice(&mask.control.to_le_bytes());
-        bytes[2..4].copy_from_slice(&mask.mask.to_le_bytes());
+        // Mask goes out high byte first (esp-miner BM1370_set_version_mask)
+        bytes[2..4].copy_from_slice(&mask.mask.to_be_bytes());
         bytes
     }
 }
 
-#[derive(FromRepr, Copy, Clone, Debug)]
+/// Known register addresses.
+///
+/// Names follow the BM1397 documentation where one exists; later chips keep
+/// the same layout for most of the map.
+#[derive(FromRepr, EnumIter, Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(u8)]
 pub enum RegisterAddress {
+    /// Chip type, core count and assigned address (read-only).
     ChipId = 0x00,
+    /// Hash counter, advanced as the cores work through nonce space.
+    HashCounter = 0x04,
+    /// PLL0 configuration, which clocks the hashing cores.
     PllDivider = 0x08,
+    /// Offset of this chip's slice of the nonce space; bit 31 enables it.
+    ChipNonceOffset = 0x0C,
+    /// Nonce space partitioning across cores.
     NonceRange = 0x10,
+    /// Difficulty mask for reported nonces.
     TicketMask = 0x14,
+    /// Miscellaneous control, including the UART clock source.
     MiscControl = 0x18,
+    /// I2C master used to reach sensors hanging off the chip.
+    I2cControl = 0x1C,
+    /// Clock enable bitmap for the core groups.
+    OrderedClockEnable = 0x20,
+    /// Fast UART configuration (baud rate).
     UartBaud = 0x28,
+    /// UART relay configuration for voltage domain boundaries.
     UartRelay = 0x2C,
+    /// Secondary ticket mask (BM1366 and later).
+    TicketMask2 = 0x38,
+    /// Indirect access to per-core registers.
     Core = 0x3C,
+    /// Result of a core register read issued through `Core`.
+    CoreRegisterValue = 0x40,
+    /// Result of the last temperature sensor read over the chip's I2C.
+    ExternalTempSensor = 0x44,
+    /// Sticky error flags.
+    ErrorFlag = 0x48,
+    /// Nonces that failed the chip's internal check.
+    NonceErrorCounter = 0x4C,
+    /// Nonces dropped because the return FIFO was full.
+    NonceOverflowCounter = 0x50,
+    /// Analog mux selection, e.g. the temperature diode.
     AnalogMux = 0x54,
+    /// Output driver strength for the IO pins.
     IoDriverStrength = 0x58,
+    /// Nonce space timeout.
+    Timeout = 0x5C,
+    /// PLL1 configuration, same layout as `PllDivider`.
+    Pll1Parameter = 0x60,
+    /// PLL2 configuration, same layout as `PllDivider`.
+    Pll2Parameter = 0x64,
+    /// PLL3 configuration.
     Pll3Parameter = 0x68,
+    /// Clock monitor status for the core groups.
+    OrderedClockMonitor = 0x6C,
+    /// Output divider for PLL0.
+    Pll0Divider = 0x70,
+    /// Output divider for PLL1.
+    Pll1Divider = 0x74,
+    /// Output divider for PLL2.
+    Pll2Divider = 0x78,
+    /// Output divider for PLL3.
+    Pll3Divider = 0x7C,
+    /// Clock ordering for core groups 0-7.
+    ClockOrderControl0 = 0x80,
+    /// Clock ordering for core groups 8-15.
+    ClockOrderControl1 = 0x84,
+    /// Clock ordering status.
+    ClockOrderStatus = 0x8C,
+    /// Frequency sweep (self-test) control.
+    FrequencySweepControl = 0x90,
+    /// Expected nonce for the frequency sweep self-test.
+    GoldenNonceForSweepReturn = 0x94,
+    /// Per-group pass/fail status from the frequency sweep.
+    ReturnedGroupPatternStatus = 0x98,
+    /// Timeout before an incomplete nonce return is abandoned.
+    NonceReturnedTimeout = 0x9C,
+    /// Single-pattern status from the frequency sweep.
+    ReturnedSinglePatternStatus = 0xA0,
+    /// Version rolling enable and mask.
     VersionMask = 0xA4,
+    /// Initialization control.
     InitControl = 0xA8,
+    /// Miscellaneous settings written during BM1370 init.
     MiscSettings = 0xB9,
 }
 
@@ -635,6 +782,84 @@ pub enum Register {
     MiscSettings {
         raw_value: u32,
     },
+    HashCounter {
+        count: u32,
+    },
+    ChipNonceOffset {
+        raw_value: u32,
+    },
+    I2cControl {
+        raw_value: u32,
+    },
+    OrderedClockEnable {
+        raw_value: u32,
+    },
+    TicketMask2 {
+        raw_value: u32,
+    },
+    CoreRegisterValue {
+        raw_value: u32,
+    },
+    ExternalTempSensor {
+        raw_value: u32,
+    },
+    ErrorFlag {
+        raw_value: u32,
+    },
+    NonceErrorCounter {
+        count: u32,
+    },
+    NonceOverflowCounter {
+        count: u32,
+    },
+    Timeout {
+        raw_value: u32,
+    },
+    Pll1Parameter {
+        raw_value: u32,
+    },
+    Pll2Parameter {
+        raw_value: u32,
+    },
+    OrderedClockMonitor {
+        raw_value: u32,
+    },
+    Pll0Divider {
+        raw_value: u32,
+    },
+    Pll1Divider {
+        raw_value: u32,
+    },
+    Pll2Divider {
+        raw_value: u32,
+    },
+    Pll3Divider {
+        raw_value: u32,
+    },
+    ClockOrderControl0 {
+        raw_value: u32,
+    },
+    ClockOrderControl1 {
+        raw_value: u32,
+    },
+    ClockOrderStatus {
+        raw_value: u32,
+    },
+    FrequencySweepControl {
+        raw_value: u32,
+    },
+    GoldenNonceForSweepReturn {
+        raw_value: u32,
+    },
+    ReturnedGroupPatternStatus {
+        raw_value: u32,
+    },
+    NonceReturnedTimeout {
+        raw_value: u32,
+    },
+    ReturnedSinglePatternStatus {
+        raw_value: u32,
+    },
 }
 
 impl Register {
@@ -660,14 +885,17 @@ impl Register {
                 // Decode known baud rates
                 let baud = match raw_value {
                     0x00000271 => BaudRate::Baud115200,
-                    0x00000130 => BaudRate::Baud1M,
+                    0x00023011 => BaudRate::Baud1M,
                     0x00003001 => BaudRate::Baud3M,
                     other => BaudRate::Custom(other),
                 };
                 Register::UartBaud(baud)
             }
             RegisterAddress::UartRelay => Register::UartRelay { raw_value },
-            RegisterAddress::Core => Register::Core { raw_value },
+            // Core register accesses are big-endian on the wire
+            RegisterAddress::Core => Register::Core {
+                raw_value: u32::from_be_bytes(*bytes),
+            },
             RegisterAddress::AnalogMux => Register::AnalogMux { raw_value },
             RegisterAddress::IoDriverStrength => {
                 // Parse driver strength from raw value
@@ -679,17 +907,64 @@ impl Register {
             }
             RegisterAddress::Pll3Parameter => Register::Pll3Parameter { raw_value },
             RegisterAddress::VersionMask => {
-                let mask = (raw_value >> 16) as u16;
+                let mask = ((raw_value >> 16) as u16).swap_bytes();
                 let control = (raw_value & 0xffff) as u16;
                 Register::VersionMask(VersionMask { mask, control })
             }
             RegisterAddress::InitControl => Register::InitControl { raw_value },
             RegisterAddress::MiscSettings => Register::MiscSettings { raw_value },
+            RegisterAddress::HashCounter => Register::HashCounter { count: raw_value },
+            RegisterAddress::ChipNonceOffset => Register::ChipNonceOffset { raw_value },
+            RegisterAddress::I2cControl => Register::I2cControl { raw_value },
+            RegisterAddress::OrderedClockEnable => Register::OrderedClockEnable { raw_value },
+            RegisterAddress::TicketMask2 => Register::TicketMask2 { raw_value },
+            RegisterAddress::CoreRegisterValue => Register::CoreRegisterValue { raw_value },
+            RegisterAddress::ExternalTempSensor => Register::ExternalTempSensor { raw_value },
+            RegisterAddress::ErrorFlag => Register::ErrorFlag { raw_value },
+            RegisterAddress::NonceErrorCounter => Register::NonceErrorCounter { count: raw_value },
+            RegisterAddress::NonceOverflowCounter => {
+                Register::NonceOverflowCounter { count: raw_value }
+            }
+            RegisterAddress::Timeout => Register::Timeout { raw_value },
+            RegisterAddress::Pll1Parameter => Register::Pll1Parameter { raw_value },
+            RegisterAddress::Pll2Parameter => Register::Pll2Parameter { raw_value },
+            RegisterAddress::OrderedClockMonitor => Register::OrderedClockMonitor { raw_value },
+            RegisterAddress::Pll0Divider => Register::Pll0Divider { raw_value },
+            RegisterAddress::Pll1Divider => Register::Pll1Divider { raw_value },
+            RegisterAddress::Pll2Divider => Register::Pll2Divider { raw_value },
+            RegisterAddress::Pll3Divider => Register::Pll3Divider { raw_value },
+            RegisterAddress::ClockOrderControl0 => Register::ClockOrderControl0 { raw_value },
+            RegisterAddress::ClockOrderControl1 => Register::ClockOrderControl1 { raw_value },
+            RegisterAddress::ClockOrderStatus => Register::ClockOrderStatus { raw_value },
+            RegisterAddress::FrequencySweepControl => Register::FrequencySweepControl { raw_value },
+            RegisterAddress::GoldenNonceForSweepReturn => {
+                Register::GoldenNonceForSweepReturn { raw_value }
+            }
+            RegisterAddress::ReturnedGroupPatternStatus => {
+                Register::ReturnedGroupPatternStatus { raw_value }
+            }
+            RegisterAddress::NonceReturnedTimeout => Register::NonceReturnedTimeout { raw_value },
+            RegisterAddress::ReturnedSinglePatternStatus => {
+                Register::ReturnedSinglePatternStatus { raw_value }
+            }
+        }
+    }
+
+    /// Raw 32-bit value, as accepted by [`BM13xxProtocol::write_register`].
+    ///
+    /// Useful for logging and diffing registers whose typed form doesn't
+    /// expose every bit.
+    pub fn raw_value(&self) -> u32 {
+        let mut bytes = BytesMut::with_capacity(4);
+        self.encode_data(&mut bytes);
+        match self {
+            Register::Core { .. } => bytes.get_u32(),
+            _ => bytes.get_u32_le(),
         }
     }
 
     /// Get the register address for this register
-    fn address(&self) -> RegisterAddress {
+    pub fn address(&self) -> RegisterAddress {
         match self {
             Register::ChipId { .. } => RegisterAddress::ChipId,
             Register::PllDivider(_) => RegisterAddress::PllDivider,
@@ -705,6 +980,38 @@ impl Register {
             Register::VersionMask(_) => RegisterAddress::VersionMask,
             Register::InitControl { .. } => RegisterAddress::InitControl,
             Register::MiscSettings { .. } => RegisterAddress::MiscSettings,
+            Register::HashCounter { .. } => RegisterAddress::HashCounter,
+            Register::ChipNonceOffset { .. } => RegisterAddress::ChipNonceOffset,
+            Register::I2cControl { .. } => RegisterAddress::I2cControl,
+            Register::OrderedClockEnable { .. } => RegisterAddress::OrderedClockEnable,
+            Register::TicketMask2 { .. } => RegisterAddress::TicketMask2,
+            Register::CoreRegisterValue { .. } => RegisterAddress::CoreRegisterValue,
+            Register::ExternalTempSensor { .. } => RegisterAddress::ExternalTempSensor,
+            Register::ErrorFlag { .. } => RegisterAddress::ErrorFlag,
+            Register::NonceErrorCounter { .. } => RegisterAddress::NonceErrorCounter,
+            Register::NonceOverflowCounter { .. } => RegisterAddress::NonceOverflowCounter,
+            Register::Timeout { .. } => RegisterAddress::Timeout,
+            Register::Pll1Parameter { .. } => RegisterAddress::Pll1Parameter,
+            Register::Pll2Parameter { .. } => RegisterAddress::Pll2Parameter,
+            Register::OrderedClockMonitor { .. } => RegisterAddress::OrderedClockMonitor,
+            Register::Pll0Divider { .. } => RegisterAddress::Pll0Divider,
+            Register::Pll1Divider { .. } => RegisterAddress::Pll1Divider,
+            Register::Pll2Divider { .. } => RegisterAddress::Pll2Divider,
+            Register::Pll3Divider { .. } => RegisterAddress::Pll3Divider,
+            Register::ClockOrderControl0 { .. } => RegisterAddress::ClockOrderControl0,
+            Register::ClockOrderControl1 { .. } => RegisterAddress::ClockOrderControl1,
+            Register::ClockOrderStatus { .. } => RegisterAddress::ClockOrderStatus,
+            Register::FrequencySweepControl { .. } => RegisterAddress::FrequencySweepControl,
+            Register::GoldenNonceForSweepReturn { .. } => {
+                RegisterAddress::GoldenNonceForSweepReturn
+            }
+            Register::ReturnedGroupPatternStatus { .. } => {
+                RegisterAddress::ReturnedGroupPatternStatus
+            }
+            Register::NonceReturnedTimeout { .. } => RegisterAddress::NonceReturnedTimeout,
+            Register::ReturnedSinglePatternStatus { .. } => {
+                RegisterAddress::ReturnedSinglePatternStatus
+            }
         }
     }
 
@@ -745,7 +1052,33 @@ impl Register {
             | Register::AnalogMux { raw_value }
             | Register::Pll3Parameter { raw_value }
             | Register::InitControl { raw_value }
-            | Register::MiscSettings { raw_value } => {
+            | Register::MiscSettings { raw_value }
+            | Register::ChipNonceOffset { raw_value }
+            | Register::I2cControl { raw_value }
+            | Register::OrderedClockEnable { raw_value }
+            | Register::TicketMask2 { raw_value }
+            | Register::CoreRegisterValue { raw_value }
+            | Register::ExternalTempSensor { raw_value }
+            | Register::ErrorFlag { raw_value }
+            | Register::Timeout { raw_value }
+            | Register::Pll1Parameter { raw_value }
+            | Register::Pll2Parameter { raw_value }
+            | Register::OrderedClockMonitor { raw_value }
+            | Register::Pll0Divider { raw_value }
+            | Register::Pll1Divider { raw_value }
+            | Register::Pll2Divider { raw_value }
+            | Register::Pll3Divider { raw_value }
+            | Register::ClockOrderControl0 { raw_value }
+            | Register::ClockOrderControl1 { raw_value }
+            | Register::ClockOrderStatus { raw_value }
+            | Register::FrequencySweepControl { raw_value }
+            | Register::GoldenNonceForSweepReturn { raw_value }
+            | Register::ReturnedGroupPatternStatus { raw_value }
+            | Register::NonceReturnedTimeout { raw_value }
+            | Register::ReturnedSinglePatternStatus { raw_value }
+            | Register::HashCounter { count: raw_value }
+            | Register::NonceErrorCounter { count: raw_value }
+            | Register::NonceOverflowCounter { count: raw_value } => {
                 dst.put_u32_le(*raw_value);
             }
             Register::IoDriverStrength(strength) => {
@@ -787,31 +1120,56 @@ impl std::fmt::Debug for Register {
             | Register::Pll3Parameter { raw_value }
             | Register::InitControl { raw_value }
             | Register::Core { raw_value }
-            | Register::MiscSettings { raw_value } => {
-                let register_name = match self {
-                    Register::MiscControl { .. } => "MiscControl",
-                    Register::UartRelay { .. } => "UartRelay",
-                    Register::AnalogMux { .. } => "AnalogMux",
-                    Register::Pll3Parameter { .. } => "Pll3Parameter",
-                    Register::InitControl { .. } => "InitControl",
-                    Register::Core { .. } => "Core",
-                    Register::MiscSettings { .. } => "MiscSettings",
-                    _ => unreachable!(),
-                };
-                f.debug_struct(register_name)
-                    .field("raw_value", &format_args!("0x{:08x}", raw_value))
-                    .finish()
+            | Register::MiscSettings { raw_value }
+            | Register::ChipNonceOffset { raw_value }
+            | Register::I2cControl { raw_value }
+            | Register::OrderedClockEnable { raw_value }
+            | Register::TicketMask2 { raw_value }
+            | Register::CoreRegisterValue { raw_value }
+            | Register::ExternalTempSensor { raw_value }
+            | Register::ErrorFlag { raw_value }
+            | Register::Timeout { raw_value }
+            | Register::Pll1Parameter { raw_value }
+            | Register::Pll2Parameter { raw_value }
+            | Register::OrderedClockMonitor { raw_value }
+            | Register::Pll0Divider { raw_value }
+            | Register::Pll1Divider { raw_value }
+            | Register::Pll2Divider { raw_value }
+            | Register::Pll3Divider { raw_value }
+            | Register::ClockOrderControl0 { raw_value }
+            | Register::ClockOrderControl1 { raw_value }
+            | Register::ClockOrderStatus { raw_value }
+            | Register::FrequencySweepControl { raw_value }
+            | Register::GoldenNonceForSweepReturn { raw_value }
+            | Register::ReturnedGroupPatternStatus { raw_value }
+            | Register::NonceReturnedTimeout { raw_value }
+            | Register::ReturnedSinglePatternStatus { raw_value } => f
+                .debug_struct(&format!("{:?}", self.address()))
+                .field("raw_value", &format_args!("0x{:08x}", raw_value))
+                .finish(),
+            Register::HashCounter { count } => {
+                f.debug_struct("HashCounter").field("count", count).finish()
             }
+            Register::NonceErrorCounter { count } => f
+                .debug_struct("NonceErrorCounter")
+                .field("count", count)
+                .finish(),
+            Register::NonceOverflowCounter { count } => f
+                .debug_struct("NonceOverflowCounter")
+                .field("count", count)
+                .finish(),
         }
     }
 }
 
+#[derive(FromRepr)]
 #[repr(u8)]
 enum CommandFlagsType {
     Job = 1,
     Command = 2,
 }
 
+#[derive(FromRepr)]
 #[repr(u8)]
 enum CommandFlagsCmd {
     SetChipAddress = 0,
@@ -856,6 +1214,20 @@ pub fn hash_from_wire_bytes(wire_bytes: &[u8; 32]) -> [u8; 32] {
     hash
 }
 
+/// Temperature in degrees Celsius from a sensor register read.
+///
+/// The low half of the register holds a signed reading in 1/256 degree
+/// steps. A register of all zeros means no conversion has completed yet.
+/// Returns `None` for that case and for other registers.
+pub fn chip_temperature(register: &Register) -> Option<f32> {
+    match register {
+        Register::ExternalTempSensor { raw_value } if *raw_value != 0 => {
+            Some((*raw_value as u16 as i16) as f32 / 256.0)
+        }
+        _ => None,
+    }
+}
+
 #[derive(Debug)]
 pub enum Command {
     /// Assign an address to the first unaddressed chip via daisy-chain forwarding
@@ -1105,6 +1477,143 @@ impl Command {
     }
 }
 
+impl Command {
+    /// The command as it goes out on the wire, preamble and CRC included.
+    pub fn to_frame(&self) -> BytesMut {
+        const PREAMBLE: [u8; 2] = [0x55, 0xaa];
+        let mut frame = BytesMut::from(&PREAMBLE[..]);
+        self.encode(&mut frame);
+
+        // Jobs use CRC16, other commands use CRC5
+        match self {
+            Command::JobFull { .. } | Command::JobMidstate { .. } => {
+                // Calculate CRC16 over flags + length + data
+                let crc = crc16(&frame[PREAMBLE.len()..]);
+                // Wire format: CRC transmitted big-endian (high byte, low byte)
+                frame.put_slice(&crc.to_be_bytes());
+            }
+            _ => {
+                // Calculate CRC5 over everything after preamble
+                let crc = crc5(&frame[PREAMBLE.len()..]);
+                frame.put_u8(crc);
+            }
+        }
+        frame
+    }
+
+    /// Decode a command frame as sent by the host, excluding the preamble.
+    ///
+    /// `frame` starts at the flags byte and must be exactly as long as the
+    /// frame's length field says. This is the inverse of the encoder and is
+    /// used to inspect host-to-chip traffic (captures, simulated chips). It
+    /// must not panic on any input, since the bytes come off noisy links.
+    pub fn decode(frame: &[u8]) -> Result<Command, ProtocolError> {
+        const SHORT_LEN: usize = 5; // flags, length, chip, register, crc5
+        const WRITE_LEN: usize = 9; // flags, length, chip, register, data(4), crc5
+        const JOB_FULL_LEN: usize = 86; // flags, length, data(82), crc16
+
+        let (&flags, rest) = frame.split_first().ok_or(ProtocolError::BufferTooSmall {
+            need: SHORT_LEN,
+            have: frame.len(),
+        })?;
+        let &length = rest.first().ok_or(ProtocolError::BufferTooSmall {
+            need: SHORT_LEN,
+            have: frame.len(),
+        })?;
+        if length as usize != frame.len() {
+            return Err(ProtocolError::InvalidFrame);
+        }
+
+        let field = flags.view_bits::<Lsb0>();
+        let typ = CommandFlagsType::from_repr(field[5..7].load::<u8>())
+            .ok_or(ProtocolError::InvalidFrame)?;
+        let broadcast = field[4];
+        let cmd = CommandFlagsCmd::from_repr(field[0..4].load::<u8>())
+            .ok_or(ProtocolError::InvalidFrame)?;
+
+        match typ {
+            CommandFlagsType::Job => {
+                if frame.len() != JOB_FULL_LEN
+                    || !matches!(cmd, CommandFlagsCmd::WriteRegisterOrJob)
+                {
+                    return Err(ProtocolError::InvalidFrame);
+                }
+                let (body, crc) = frame.split_at(frame.len() - 2);
+                if crc16(body) != u16::from_be_bytes([crc[0], crc[1]]) {
+                    return Err(ProtocolError::InvalidFrame);
+                }
+
+                let mut data = &body[2..];
+                let job_id = (data.get_u8() >> 3) & 0x0f;
+                let num_midstates = data.get_u8();
+                let starting_nonce = data.get_u32_le();
+                let nbits = bitcoin::CompactTarget::from_consensus(data.get_u32_le());
+                let ntime = data.get_u32_le();
+
+                let mut wire = [0u8; 32];
+                data.copy_to_slice(&mut wire);
+                let merkle_root =
+                    bitcoin::hash_types::TxMerkleNode::from_byte_array(hash_from_wire_bytes(&wire));
+                data.copy_to_slice(&mut wire);
+                let prev_block_hash =
+                    bitcoin::BlockHash::from_byte_array(hash_from_wire_bytes(&wire));
+                let version = bitcoin::block::Version::from_consensus(data.get_u32_le() as i32);
+
+                Ok(Command::JobFull {
+                    job_data: JobFullFormat {
+                        job_id,
+                        num_midstates,
+                        starting_nonce,
+                        nbits,
+                        ntime,
+                        merkle_root,
+                        prev_block_hash,
+                        version,
+                    },
+                })
+            }
+            CommandFlagsType::Command => {
+                let expected_len = match cmd {
+                    CommandFlagsCmd::WriteRegisterOrJob => WRITE_LEN,
+                    _ => SHORT_LEN,
+                };
+                if frame.len() != expected_len {
+                    return Err(ProtocolError::InvalidFrame);
+                }
+                let (body, crc) = frame.split_at(frame.len() - 1);
+                if crc5(body) != crc[0] {
+                    return Err(ProtocolError::InvalidFrame);
+                }
+
+                let chip_address = body[2];
+                let register_repr = body[3];
+                let register_address = || {
+                    RegisterAddress::from_repr(register_repr)
+                        .ok_or(ProtocolError::InvalidRegisterAddress(register_repr))
+                };
+
+                match cmd {
+                    CommandFlagsCmd::SetChipAddress => Ok(Command::SetChipAddress { chip_address }),
+                    CommandFlagsCmd::ChainInactive => Ok(Command::ChainInactive),
+                    CommandFlagsCmd::ReadRegister => Ok(Command::ReadRegister {
+                        broadcast,
+                        chip_address,
+                        register_address: register_address()?,
+                    }),
+                    CommandFlagsCmd::WriteRegisterOrJob => {
+                        let value = [body[4], body[5], body[6], body[7]];
+                        Ok(Command::WriteRegister {
+                            broadcast,
+                            chip_address,
+                            register: Register::decode(register_address()?, &value),
+                        })
+                    }
+                }
+            }
+        }
+    }
+}
+
 #[derive(FromRepr)]
 #[repr(u8)]
 enum ResponseType {
@@ -1188,42 +1697,68 @@ impl Response {
     }
 }
 
+/// Codec for the BM13xx serial link: encodes commands, decodes responses.
+///
+/// Clones share decode statistics, so a caller can keep a clone to watch
+/// link quality after handing the codec to a `FramedRead`.
+#[derive(Clone, Default)]
+pub struct FrameCodec {
+    counters: Arc<DecodeCounters>,
+}
+
 #[derive(Default)]
-pub struct FrameCodec;
+struct DecodeCounters {
+    frames: AtomicU64,
+    crc_errors: AtomicU64,
+    decode_errors: AtomicU64,
+    bytes_skipped: AtomicU64,
+}
+
+/// Response decoding statistics for a link.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeStats {
+    /// Responses decoded successfully.
+    pub frames: u64,
+    /// Candidate frames with a valid preamble rejected for a bad CRC.
+    pub crc_errors: u64,
+    /// Frames with a valid CRC that didn't decode as a known response.
+    pub decode_errors: u64,
+    /// Bytes discarded while searching for the next preamble.
+    pub bytes_skipped: u64,
+}
+
+impl FrameCodec {
+    /// Decode statistics accumulated by this codec and its clones.
+    pub fn stats(&self) -> DecodeStats {
+        let c = &self.counters;
+        DecodeStats {
+            frames: c.frames.load(Ordering::Relaxed),
+            crc_errors: c.crc_errors.load(Ordering::Relaxed),
+            decode_errors: c.decode_errors.load(Ordering::Relaxed),
+            bytes_skipped: c.bytes_skipped.load(Ordering::Relaxed),
+        }
+    }
+
+    fn count(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
 
 impl Encoder<Command> for FrameCodec {
     type Error = io::Error;
 
     fn encode(&mut self, command: Command, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        const PREAMBLE: [u8; 2] = [0x55, 0xaa];
-        dst.put_slice(&PREAMBLE);
-
-        let start_pos = dst.len();
-        command.encode(dst);
-
-        // Jobs use CRC16, other commands use CRC5
-        match &command {
-            Command::JobFull { .. } | Command::JobMidstate { .. } => {
-                // Calculate CRC16 over flags + length + data
-                let crc = crc16(&dst[start_pos..]);
-                // Wire format: CRC transmitted big-endian (high byte, low byte)
-                dst.put_slice(&crc.to_be_bytes());
-            }
-            _ => {
-                // Calculate CRC5 over everything after preamble
-                let crc = crc5(&dst[2..]);
-                dst.put_u8(crc);
-            }
-        }
+        let frame = command.to_frame();
 
         // Log the encoded frame for debugging
         trace!(
             cmd = ?command,
-            bytes = dst.len(),
-            frame = %HexBytes(dst.as_ref()),
+            bytes = frame.len(),
+            frame = %HexBytes(&frame),
             "TX BM13xx"
         );
 
+        dst.extend_from_slice(&frame);
         Ok(())
     }
 }
@@ -1256,11 +1791,13 @@ impl Decoder for FrameCodec {
 
         // Check preamble without consuming the buffer
         if src[0] != PREAMBLE[0] {
+            Self::count(&self.counters.bytes_skipped);
             src.advance(1);
             return CALL_AGAIN;
         }
 
         if src[1] != PREAMBLE[1] {
+            Self::count(&self.counters.bytes_skipped);
             src.advance(1);
             return CALL_AGAIN;
         }
@@ -1271,6 +1808,7 @@ impl Decoder for FrameCodec {
             trace!(
                 "Frame sync lost: CRC5 failed for potential frame at position 0. Searching for next frame..."
             );
+            Self::count(&self.counters.crc_errors);
             src.advance(1);
             return CALL_AGAIN;
         }
@@ -1287,6 +1825,7 @@ impl Decoder for FrameCodec {
             Ok(response) => {
                 // Only advance if decode was successful
                 src.advance(FRAME_LEN);
+                Self::count(&self.counters.frames);
 
                 // Log the received frame for debugging
                 trace!(
@@ -1299,6 +1838,7 @@ impl Decoder for FrameCodec {
             }
             Err(err) => {
                 warn!("Failed to decode response: {}", err);
+                Self::count(&self.counters.decode_errors);
                 // Advance by 1 to try to find next valid frame
                 src.advance(1);
                 CALL_AGAIN
@@ -1307,6 +1847,64 @@ impl Decoder for FrameCodec {
     }
 }
 
+/// Decoder for the host-to-chip direction of the link.
+///
+/// The counterpart of [`FrameCodec`]'s decoder for traffic the host sends:
+/// splits a byte stream into [`Command`]s, resynchronizing on the preamble
+/// after noise or truncated frames. Used to inspect captured or simulated
+/// command streams; the miner itself only ever encodes commands.
+#[derive(Default)]
+pub struct CommandDecoder;
+
+impl Decoder for CommandDecoder {
+    type Item = Command;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        // As in FrameCodec, never return an error: that would end the stream.
+        // Invalid data is skipped a byte at a time until a frame checks out.
+        const PREAMBLE: [u8; 2] = [0x55, 0xaa];
+        const VALID_LENGTHS: [usize; 3] = [5, 9, 86];
+
+        loop {
+            if src.len() < PREAMBLE.len() + 2 {
+                return Ok(None);
+            }
+
+            if src[..2] != PREAMBLE {
+                src.advance(1);
+                continue;
+            }
+
+            let length = src[3] as usize;
+            if !VALID_LENGTHS.contains(&length) {
+                src.advance(1);
+                continue;
+            }
+
+            if src.len() < PREAMBLE.len() + length {
+                return Ok(None);
+            }
+
+            match Command::decode(&src[2..2 + length]) {
+                Ok(command) => {
+                    trace!(
+                        cmd = ?command,
+                        bytes = 2 + length,
+                        frame = %HexBytes(&src[..2 + length]),
+                        "Decoded BM13xx command"
+                    );
+                    src.advance(2 + length);
+                    return Ok(Some(command));
+                }
+                Err(_) => {
+                    src.advance(1);
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod init_tests {
     use super::*;
@@ -1584,6 +2182,53 @@ mod command_tests {
         );
     }
 
+    #[test]
+    fn write_partial_version_mask() {
+        // Stratum mask 0x01ffe000 >> 13 = 0x0fff, high byte first on the wire
+        assert_frame_eq(
+            Command::WriteRegister {
+                broadcast: true,
+                chip_address: 0x00,
+                register: Register::VersionMask(VersionMask::rolling(0x0fff)),
+            },
+            &[
+                0x55, 0xaa, 0x51, 0x09, 0x00, 0xa4, 0x90, 0x00, 0x0f, 0xff, 0x06,
+            ],
+        );
+
+        let bytes: [u8; 4] = VersionMask::rolling(0x0fff).into();
+        assert!(matches!(
+            Register::decode(RegisterAddress::VersionMask, &bytes),
+            Register::VersionMask(mask) if mask == VersionMask::rolling(0x0fff)
+        ));
+    }
+
+    #[test]
+    fn write_uart_baud_1m() {
+        // From esp-miner BM1370_set_max_baud: 55 AA 51 09 00 28 11 30 02 00 03
+        assert_frame_eq(
+            BM13xxProtocol::new().set_baudrate(BaudRate::Baud1M),
+            &[
+                0x55, 0xaa, 0x51, 0x09, 0x00, 0x28, 0x11, 0x30, 0x02, 0x00, 0x03,
+            ],
+        );
+    }
+
+    #[test]
+    fn uart_baud_register_round_trips() {
+        for baud in [BaudRate::Baud115200, BaudRate::Baud1M, BaudRate::Baud3M] {
+            let bytes: [u8; 4] = baud.into();
+            assert!(matches!(
+                Register::decode(RegisterAddress::UartBaud, &bytes),
+                Register::UartBaud(decoded) if decoded == baud
+            ));
+            assert_eq!(
+                BaudRate::from_bits_per_sec(baud.bits_per_sec().unwrap()),
+                Some(baud)
+            );
+        }
+    }
+
     #[test]
     fn write_init_control_from_capture() {
         // From Bitaxe capture: TX: 55 AA 51 09 00 A8 00 07 00 00 03
@@ -1655,6 +2300,102 @@ mod command_tests {
         );
     }
 
+    #[test]
+    fn pll_register_reads_back_as_frequency() {
+        for mhz in [56.25, 400.0, 525.0, 600.0] {
+            let written: [u8; 4] = Frequency::from_mhz(mhz).unwrap().calculate_pll().into();
+            let Register::PllDivider(config) =
+                Register::decode(RegisterAddress::PllDivider, &written)
+            else {
+                panic!("PLL register decoded as another register");
+            };
+            let read = config.frequency_mhz().unwrap();
+            assert!((read - mhz).abs() < 1.0, "{mhz} MHz read back as {read}");
+        }
+
+        // Reset value of a chip that was never configured
+        assert_eq!(PllConfig::from(0).frequency_mhz(), None);
+    }
+
+    #[test]
+    fn chip_temperature_from_sensor_register() {
+        let reading = Register::decode(RegisterAddress::ExternalTempSensor, &[0x80, 0x3e, 0, 0]);
+        assert_eq!(chip_temperature(&reading), Some(62.5));
+
+        let pending = Register::decode(RegisterAddress::ExternalTempSensor, &[0, 0, 0, 0]);
+        assert_eq!(chip_temperature(&pending), None);
+
+        let below_zero = Register::decode(RegisterAddress::ExternalTempSensor, &[0x00, 0xff, 0, 0]);
+        assert_eq!(chip_temperature(&below_zero), Some(-1.0));
+
+        let other = Register::decode(RegisterAddress::MiscControl, &[0x80, 0x3e, 0, 0]);
+        assert_eq!(chip_temperature(&other), None);
+    }
+
+    #[test]
+    fn core_register_decodes_as_written() {
+        let register = Register::decode(RegisterAddress::Core, &[0x80, 0x00, 0x8b, 0x00]);
+        assert!(matches!(
+            register,
+            Register::Core {
+                raw_value: 0x80008b00
+            }
+        ));
+
+        let command = BM13xxProtocol::new()
+            .write_register(0x00, RegisterAddress::Core, 0x80008b00)
+            .unwrap();
+        let mut frame = BytesMut::new();
+        FrameCodec::default().encode(command, &mut frame).unwrap();
+        assert_eq!(&frame[5..10], &[0x3c, 0x80, 0x00, 0x8b, 0x00]);
+    }
+
+    #[test]
+    fn register_map_round_trips_raw_values() {
+        // Every register without a lossy typed form must read back exactly
+        // what was written.
+        let lossy = [
+            RegisterAddress::ChipId,
+            RegisterAddress::PllDivider,
+            RegisterAddress::TicketMask,
+        ];
+        let protocol = BM13xxProtocol::new();
+
+        for repr in 0..=u8::MAX {
+            let Some(address) = RegisterAddress::from_repr(repr) else {
+                continue;
+            };
+            if lossy.contains(&address) {
+                continue;
+            }
+
+            let value = 0x1234_5678;
+            match protocol.write_register(0x00, address, value).unwrap() {
+                Command::WriteRegister { register, .. } => {
+                    assert_eq!(register.address(), address);
+                    assert_eq!(register.raw_value(), value, "{address:?}");
+                }
+                other => panic!("expected WriteRegister, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn nonce_counters_decode() {
+        let register = Register::decode(
+            RegisterAddress::NonceOverflowCounter,
+            &[0x10, 0x01, 0x00, 0x00],
+        );
+        assert!(matches!(
+            register,
+            Register::NonceOverflowCounter { count: 0x110 }
+        ));
+        assert_eq!(
+            format!("{register:?}"),
+            "NonceOverflowCounter { count: 272 }"
+        );
+    }
+
     #[test]
     fn write_ticket_mask_from_capture() {
         // From S21 Pro capture: TX: 55 AA 51 09 00 14 00 00 00 FF 08
@@ -1730,7 +2471,7 @@ mod command_tests {
             version: bitcoin::block::Version::from_consensus(0x20000000),
         };
 
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
         let mut frame = BytesMut::new();
         codec
             .encode(
@@ -1808,7 +2549,7 @@ mod command_tests {
             version: *esp_miner_job::wire_tx::VERSION,
         };
 
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
         let mut frame = BytesMut::new();
         codec
             .encode(
@@ -1827,8 +2568,115 @@ mod command_tests {
         );
     }
 
+    #[test]
+    fn command_decoder_reads_captured_job() {
+        use crate::asic::bm13xx::test_data::esp_miner_job;
+
+        let mut buf = BytesMut::from(&esp_miner_job::wire_tx::FRAME[..]);
+        let command = CommandDecoder.decode(&mut buf).unwrap();
+
+        match command {
+            Some(Command::JobFull { job_data }) => {
+                assert_eq!(job_data.ntime, *esp_miner_job::wire_tx::NTIME);
+                assert_eq!(job_data.nbits, *esp_miner_job::wire_tx::NBITS);
+                assert_eq!(job_data.merkle_root, *esp_miner_job::wire_tx::MERKLE_ROOT);
+                assert_eq!(
+                    job_data.prev_block_hash,
+                    *esp_miner_job::wire_tx::PREV_BLOCKHASH
+                );
+                assert_eq!(job_data.version, *esp_miner_job::wire_tx::VERSION);
+            }
+            other => panic!("expected JobFull, got {other:?}"),
+        }
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn command_decoder_resyncs_after_noise() {
+        let mut codec = FrameCodec::default();
+        let mut buf = BytesMut::new();
+        // Garbage including a false preamble with an implausible length
+        buf.put_slice(&[0x00, 0x55, 0xaa, 0x42, 0x55]);
+        codec.encode(Command::ChainInactive, &mut buf).unwrap();
+        // Truncated register write
+        buf.put_slice(&[0x55, 0xaa, 0x51, 0x09, 0x01]);
+        codec
+            .encode(Command::SetChipAddress { chip_address: 0x08 }, &mut buf)
+            .unwrap();
+
+        let mut decoder = CommandDecoder;
+        let mut commands = Vec::new();
+        while let Some(command) = decoder.decode(&mut buf).unwrap() {
+            commands.push(command);
+        }
+
+        assert!(matches!(commands[0], Command::ChainInactive));
+        assert!(matches!(
+            commands[1],
+            Command::SetChipAddress { chip_address: 0x08 }
+        ));
+        assert_eq!(commands.len(), 2);
+    }
+
+    #[test]
+    fn command_decode_rejects_length_mismatch() {
+        // Valid ChainInactive with the length field claiming a write
+        let frame = [0x53, 0x09, 0x00, 0x00, 0x03];
+        assert!(Command::decode(&frame).is_err());
+        assert!(Command::decode(&[]).is_err());
+        assert!(Command::decode(&[0x53]).is_err());
+    }
+
+    /// Arbitrary bytes must never panic or stall either decoder.
+    ///
+    /// A cheap stand-in for the fuzz targets (see `fuzz/`) that runs with the
+    /// normal test suite.
+    #[test]
+    fn decoders_survive_arbitrary_bytes() {
+        fn drain<D: Decoder>(decoder: &mut D, buf: &mut BytesMut) {
+            loop {
+                let before = buf.len();
+                match decoder.decode(buf) {
+                    Ok(Some(_)) => continue,
+                    Ok(None) if buf.len() == before => break,
+                    Ok(None) => continue,
+                    Err(_) => panic!("decoder returned an error"),
+                }
+            }
+        }
+
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        };
+
+        for _ in 0..200 {
+            let mut data = BytesMut::new();
+            for _ in 0..512 {
+                // Bias toward preamble bytes so the framing paths get exercised
+                let byte = match next() % 4 {
+                    0 => 0x55,
+                    1 => 0xaa,
+                    _ => next(),
+                };
+                data.put_u8(byte);
+            }
+
+            let mut responses = data.clone();
+            drain(&mut FrameCodec::default(), &mut responses);
+            assert!(responses.len() < 11);
+
+            let mut commands = data;
+            drain(&mut CommandDecoder, &mut commands);
+            assert!(commands.len() < 2 + 86);
+        }
+    }
+
     fn assert_frame_eq(cmd: Command, expect: &[u8]) {
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
         let mut frame = BytesMut::new();
         codec
             .encode(cmd, &mut frame)
@@ -1871,7 +2719,7 @@ mod command_tests {
             version: *esp_miner_job::wire_tx::VERSION,
         };
 
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
         let mut frame = BytesMut::new();
         codec
             .encode(Command::JobFull { job_data: job }, &mut frame)
@@ -1903,7 +2751,7 @@ mod response_tests {
 
     #[test]
     fn decoder_with_exact_frame_size() {
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
 
         // Exactly 11 bytes - a complete frame
         let mut buf = BytesMut::new();
@@ -1918,6 +2766,41 @@ mod response_tests {
         );
     }
 
+    #[test]
+    fn decoder_counts_rejected_frames() {
+        const FRAME: [u8; 11] = [
+            0xaa, 0x55, 0x13, 0x70, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+        ];
+        let mut corrupted = FRAME;
+        corrupted[4] ^= 0x01;
+
+        let codec = FrameCodec::default();
+        let mut decoder = codec.clone();
+        let mut buf = BytesMut::new();
+        buf.put_slice(&[0x00, 0xaa]);
+        buf.put_slice(&corrupted);
+        buf.put_slice(&FRAME);
+
+        let mut decoded = 0;
+        loop {
+            let before = buf.len();
+            match decoder.decode(&mut buf).unwrap() {
+                Some(_) => decoded += 1,
+                None if buf.len() == before => break,
+                None => {}
+            }
+        }
+
+        assert_eq!(decoded, 1);
+        assert!(buf.is_empty());
+        // Clones share counters, so the original sees the decoder's work
+        let stats = codec.stats();
+        assert_eq!(stats.frames, 1);
+        assert_eq!(stats.crc_errors, 1);
+        // Two leading bytes, then the rest of the corrupted frame
+        assert_eq!(stats.bytes_skipped, 2 + 10);
+    }
+
     #[test]
     fn read_register() {
         // 11-byte register read response from captures
@@ -1952,7 +2835,7 @@ mod response_tests {
 
     fn decode_frame(frame: &[u8]) -> Option<Response> {
         let mut buf = BytesMut::from(frame);
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
         codec.decode(&mut buf).expect("Failed to decode frame")
     }
 
@@ -2048,7 +2931,7 @@ mod response_tests {
 
     #[test]
     fn decoder_handles_partial_frames() {
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
 
         // Test with incomplete frame (less than 11 bytes)
         let mut buf = BytesMut::new();
@@ -2068,7 +2951,7 @@ mod response_tests {
 
     #[test]
     fn decoder_handles_corrupted_crc() {
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
 
         // Valid frame with corrupted CRC (last byte)
         let mut buf = BytesMut::new();
@@ -2087,7 +2970,7 @@ mod response_tests {
 
     #[test]
     fn decoder_finds_frame_after_garbage() {
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
 
         // Garbage bytes followed by valid frame
         let mut buf = BytesMut::new();
@@ -2109,7 +2992,7 @@ mod response_tests {
 
     #[test]
     fn decoder_handles_false_start() {
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
 
         // Frame that starts with 0xAA but not followed by 0x55
         let mut buf = BytesMut::new();
@@ -2149,7 +3032,7 @@ mod response_tests {
 
     #[test]
     fn decoder_handles_back_to_back_frames() {
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
 
         // Two valid frames back-to-back
         let mut buf = BytesMut::new();
@@ -2175,7 +3058,7 @@ mod response_tests {
 
     #[test]
     fn decoder_handles_real_s21_pro_frames() {
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
 
         // Real frames from S21 Pro capture
         let frames = vec![
@@ -2205,7 +3088,7 @@ mod response_tests {
 
     #[test]
     fn decoder_handles_stream_with_lost_bytes() {
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
 
         // Simulate a stream where some bytes in the middle are lost
         let mut buf = BytesMut::new();
@@ -2233,7 +3116,7 @@ mod response_tests {
 
     #[test]
     fn decoder_handles_mid_frame_start() {
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
 
         // Start reading in the middle of a frame
         let mut buf = BytesMut::new();
@@ -2272,7 +3155,7 @@ mod response_tests {
     #[test]
     fn decoder_validates_real_register_responses() {
         // Test all register read responses are handled correctly
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
 
         // Standard chip detection response
         let mut buf = BytesMut::new();
@@ -2293,6 +3176,34 @@ mod response_tests {
         }
     }
 
+    /// Floor on nonce frame decoding, which runs for every nonce a chip
+    /// reports. Ignored by `cargo test`, since a loaded machine would fail
+    /// it spuriously; run it with `just perf`.
+    #[test]
+    #[ignore = "benchmark"]
+    fn decode_throughput() {
+        use crate::asic::bm13xx::test_data::esp_miner_job;
+        const FRAMES: usize = 50_000;
+
+        let frame = &esp_miner_job::wire_rx::FRAME;
+        let mut buf = BytesMut::with_capacity(FRAMES * frame.len());
+        for _ in 0..FRAMES {
+            buf.extend_from_slice(frame);
+        }
+        let mut codec = FrameCodec::default();
+
+        let start = std::time::Instant::now();
+        let mut decoded = 0;
+        while let Some(response) = codec.decode(&mut buf).unwrap() {
+            assert!(matches!(response, Response::Nonce { .. }));
+            decoded += 1;
+        }
+        let rate = decoded as f64 / start.elapsed().as_secs_f64();
+
+        assert_eq!(decoded, FRAMES);
+        assert!(rate >= 50_000.0, "only {rate:.0} frames/s");
+    }
+
     #[test]
     fn decode_nonce_response_from_esp_miner_capture() {
         use crate::asic::bm13xx::test_data::esp_miner_job;
@@ -2354,7 +3265,7 @@ mod response_tests {
             version: *esp_miner_job::notify::VERSION,
         };
 
-        let mut codec = FrameCodec;
+        let mut codec = FrameCodec::default();
         let mut tx_frame = BytesMut::new();
         codec
             .encode(
@@ -2783,50 +3694,22 @@ impl BM13xxProtocol {
 
     /// Create a command to write a register.
     ///
-    /// Note: This is a placeholder - actual register encoding depends on the register type
+    /// `value` is the register's raw value, interpreted the same way as when
+    /// the register is read back.
     pub fn write_register(
         &self,
         chip_address: u8,
         register: RegisterAddress,
         value: u32,
     ) -> Result<Command, ProtocolError> {
-        // TODO: Properly encode register based on type
-        // For now, just handle RegA8 as an example
         let register_value = match register {
             RegisterAddress::ChipId => {
                 // Can't write chip ID register directly
                 return Err(ProtocolError::ReadOnlyRegister(register));
             }
-            RegisterAddress::PllDivider => Register::PllDivider(value.into()),
-            RegisterAddress::NonceRange => Register::NonceRange(NonceRangeConfig {
-                bytes: value.to_le_bytes(),
-            }),
-            RegisterAddress::TicketMask => {
-                let bytes = value.to_le_bytes();
-                let mask_value = decode_ticket_mask_bytes(&bytes);
-                let zero_bits = mask_value.count_ones() as u8;
-                Register::TicketMask(TicketMask { zero_bits })
-            }
-            RegisterAddress::MiscControl => Register::MiscControl { raw_value: value },
-            RegisterAddress::UartBaud => Register::UartBaud(BaudRate::Custom(value)),
-            RegisterAddress::UartRelay => Register::UartRelay { raw_value: value },
+            // Core words are given high byte first, as sent on the wire
             RegisterAddress::Core => Register::Core { raw_value: value },
-            RegisterAddress::AnalogMux => Register::AnalogMux { raw_value: value },
-            RegisterAddress::IoDriverStrength => {
-                let mut strengths = [0u8; 8];
-                for (i, strength) in strengths.iter_mut().enumerate() {
-                    *strength = ((value >> (i * 4)) & 0xf) as u8;
-                }
-                Register::IoDriverStrength(IoDriverStrength { strengths })
-            }
-            RegisterAddress::Pll3Parameter => Register::Pll3Parameter { raw_value: value },
-            RegisterAddress::VersionMask => {
-                let mask = (value >> 16) as u16;
-                let control = (value & 0xffff) as u16;
-                Register::VersionMask(VersionMask { mask, control })
-            }
-            RegisterAddress::InitControl => Register::InitControl { raw_value: value },
-            RegisterAddress::MiscSettings => Register::MiscSettings { raw_value: value },
+            other => Register::decode(other, &value.to_le_bytes()),
         };
 
         Ok(Command::WriteRegister {
@@ -2836,6 +3719,31 @@ impl BM13xxProtocol {
         })
     }
 
+    /// Create a command to read a chip's temperature sensor result.
+    ///
+    /// The reading reflects the junction diode only while the analog mux
+    /// routes it to the sensor, as configured during chip initialization.
+    /// Decode the response with [`chip_temperature`].
+    pub fn read_temperature(chip_address: u8) -> Command {
+        Command::ReadRegister {
+            broadcast: false,
+            chip_address,
+            register_address: RegisterAddress::ExternalTempSensor,
+        }
+    }
+
+    /// Create a broadcast command reading every chip's core clock PLL.
+    ///
+    /// Chips answer in chain order. Decode the frequency with
+    /// [`PllConfig::frequency_mhz`].
+    pub fn read_pll() -> Command {
+        Command::ReadRegister {
+            broadcast: true,
+            chip_address: 0,
+            register_address: RegisterAddress::PllDivider,
+        }
+    }
+
     /// Create a broadcast command to discover all chips.
     pub fn discover_chips() -> Command {
         Command::ReadRegister {
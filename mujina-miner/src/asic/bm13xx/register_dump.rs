@@ -0,0 +1,164 @@
+//! Reading back a chip's registers, for debugging its configuration.
+//!
+//! A board that hashes poorly after a change to chip initialization is
+//! hard to diagnose from the outside: the writes went out, but whether the
+//! chip took them doesn't show. A [`RegisterDump`] gathers the answers to
+//! a read of every known register and, given the values initialization
+//! wrote, names the registers that now hold something else.
+
+use strum::IntoEnumIterator;
+
+use crate::api_client::types::{ChipRegisterDump, RegisterReading};
+
+use super::protocol::{Command, Register, RegisterAddress};
+
+/// Register reads for one chip, filled in as the answers arrive.
+#[derive(Debug)]
+pub struct RegisterDump {
+    chip_address: u8,
+    readings: Vec<(RegisterAddress, Option<Register>)>,
+    /// Values written during initialization, if a diff was asked for
+    expected: Option<Vec<Register>>,
+}
+
+impl RegisterDump {
+    pub fn new(chip_address: u8, expected: Option<Vec<Register>>) -> Self {
+        Self {
+            chip_address,
+            readings: RegisterAddress::iter().map(|a| (a, None)).collect(),
+            expected,
+        }
+    }
+
+    /// Read commands for every known register.
+    pub fn commands(&self) -> Vec<Command> {
+        self.readings
+            .iter()
+            .map(|&(register_address, _)| Command::ReadRegister {
+                broadcast: false,
+                chip_address: self.chip_address,
+                register_address,
+            })
+            .collect()
+    }
+
+    /// Take a read response if it answers one of this dump's reads.
+    pub fn record(&mut self, chip_address: u8, register: &Register) {
+        if chip_address != self.chip_address {
+            return;
+        }
+        let address = register.address();
+        if let Some((_, reading)) = self.readings.iter_mut().find(|(a, _)| *a == address) {
+            *reading = Some(register.clone());
+        }
+    }
+
+    /// Whether every register has answered.
+    pub fn is_complete(&self) -> bool {
+        self.readings.iter().all(|(_, reading)| reading.is_some())
+    }
+
+    /// The dump as served by the API. Registers that never answered have
+    /// no value.
+    pub fn report(&self, thread: &str) -> ChipRegisterDump {
+        let expected_for = |address: RegisterAddress| {
+            self.expected
+                .as_ref()?
+                .iter()
+                .find(|r| r.address() == address)
+                .map(Register::raw_value)
+        };
+
+        let registers: Vec<RegisterReading> = self
+            .readings
+            .iter()
+            .map(|(address, reading)| RegisterReading {
+                name: format!("{address:?}"),
+                address: *address as u8,
+                value: reading.as_ref().map(Register::raw_value),
+                decoded: reading.as_ref().map(|r| format!("{r:?}")),
+                expected: expected_for(*address),
+            })
+            .collect();
+        let mismatches = registers
+            .iter()
+            .filter(|r| r.value.zip(r.expected).is_some_and(|(v, e)| v != e))
+            .map(|r| r.name.clone())
+            .collect();
+
+        ChipRegisterDump {
+            thread: thread.to_string(),
+            chip_address: self.chip_address,
+            registers,
+            mismatches,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_every_known_register_of_one_chip() {
+        let dump = RegisterDump::new(0x04, None);
+        let commands = dump.commands();
+        assert_eq!(commands.len(), RegisterAddress::iter().count());
+        assert!(commands.iter().all(|c| matches!(
+            c,
+            Command::ReadRegister {
+                broadcast: false,
+                chip_address: 0x04,
+                ..
+            }
+        )));
+    }
+
+    #[test]
+    fn diff_names_registers_that_changed() {
+        let expected = vec![
+            Register::MiscControl {
+                raw_value: 0x00C1_00F0,
+            },
+            Register::AnalogMux {
+                raw_value: 0x0200_0000,
+            },
+            Register::InitControl {
+                raw_value: 0xF001_0700,
+            },
+        ];
+        let mut dump = RegisterDump::new(0x00, Some(expected));
+        dump.record(
+            0x00,
+            &Register::MiscControl {
+                raw_value: 0x00C1_00F0,
+            },
+        );
+        dump.record(0x00, &Register::AnalogMux { raw_value: 0 });
+        // Another chip's answer doesn't count
+        dump.record(
+            0x02,
+            &Register::InitControl {
+                raw_value: 0xF001_0700,
+            },
+        );
+
+        assert!(!dump.is_complete());
+        let report = dump.report("thread-0");
+        assert_eq!(report.mismatches, ["AnalogMux"]);
+
+        let init = report
+            .registers
+            .iter()
+            .find(|r| r.name == "InitControl")
+            .unwrap();
+        assert_eq!(init.value, None);
+        assert_eq!(init.expected, Some(0xF001_0700));
+        let chip_id = report
+            .registers
+            .iter()
+            .find(|r| r.name == "ChipId")
+            .unwrap();
+        assert_eq!(chip_id.expected, None);
+    }
+}
@@ -0,0 +1,395 @@
+//! Simulated BM13xx chip for testing without hardware.
+//!
+//! [`SimChip`] speaks the BM13xx wire protocol over an in-memory byte
+//! stream. It decodes command frames the way a real chip would, keeps the
+//! register file and job slots, and "hashes" the current job by computing
+//! real block header hashes over its nonce space. Nonces whose hash meets
+//! the configured report target are sent back as nonce response frames.
+//!
+//! Because the chip works from the bytes on the wire, tests driving a
+//! hash thread against it exercise the full path: task to job encoding,
+//! frame codec, nonce decoding and share validation. An encoding bug shows
+//! up as shares that fail validation on the host side.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bitcoin::{
+    BlockHash, CompactTarget, Target, TxMerkleNode,
+    block::{Header as BlockHeader, Version},
+    hashes::Hash,
+};
+use bytes::{Buf, BytesMut};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt, DuplexStream, ReadHalf, WriteHalf},
+    task::JoinHandle,
+};
+
+use super::{
+    crc::{crc5, crc5_is_valid, crc16},
+    protocol::hash_from_wire_bytes,
+};
+
+/// Size of the in-memory pipe between host and chip.
+const PIPE_CAPACITY: usize = 64 * 1024;
+
+/// Simulated chip behavior.
+#[derive(Debug, Clone)]
+pub struct SimChipConfig {
+    /// Chip ID reported in ChipId register reads.
+    pub chip_id: [u8; 2],
+
+    /// Nonces hashed per tick.
+    pub hashes_per_tick: u32,
+
+    /// Simulated time between hashing batches.
+    pub tick: Duration,
+
+    /// Hashes meeting this target are reported to the host.
+    ///
+    /// Plays the role of the ticket mask. Use an easy target (well above
+    /// difficulty 1) to get nonces at a useful rate.
+    pub report_target: Target,
+}
+
+impl Default for SimChipConfig {
+    fn default() -> Self {
+        Self {
+            chip_id: [0x13, 0x70],
+            hashes_per_tick: 1024,
+            tick: Duration::from_millis(10),
+            report_target: Target::MAX,
+        }
+    }
+}
+
+/// A job as the chip sees it, decoded from a JobFull frame.
+#[derive(Debug, Clone, Copy)]
+struct SimJob {
+    job_id: u8,
+    version: Version,
+    prev_blockhash: BlockHash,
+    merkle_root: TxMerkleNode,
+    time: u32,
+    bits: CompactTarget,
+}
+
+/// Observable chip state, shared with the test.
+#[derive(Debug, Default)]
+pub struct SimChipState {
+    /// Last value written to each register address.
+    pub registers: HashMap<u8, [u8; 4]>,
+
+    /// Number of job frames received.
+    pub jobs_received: usize,
+
+    /// Number of nonces reported to the host.
+    pub nonces_reported: usize,
+
+    /// Frames discarded due to bad CRC or unknown type.
+    pub bad_frames: usize,
+
+    /// Job frames still to be lost on the way in, as on a bad link.
+    pub drop_jobs: usize,
+}
+
+/// Handle to a running simulated chip.
+pub struct SimChip {
+    state: Arc<Mutex<SimChipState>>,
+    task: JoinHandle<()>,
+}
+
+/// Host side of the link to a simulated chip.
+pub struct SimChipLink {
+    /// Bytes from the chip (responses).
+    pub reader: ReadHalf<DuplexStream>,
+
+    /// Bytes to the chip (commands).
+    pub writer: WriteHalf<DuplexStream>,
+}
+
+impl SimChip {
+    /// Start a simulated chip, returning it with the host end of the link.
+    pub fn spawn(config: SimChipConfig) -> (Self, SimChipLink) {
+        let (host, chip) = tokio::io::duplex(PIPE_CAPACITY);
+        let (reader, writer) = tokio::io::split(host);
+
+        let state = Arc::new(Mutex::new(SimChipState::default()));
+        let task = tokio::spawn(run(config, chip, Arc::clone(&state)));
+
+        (Self { state, task }, SimChipLink { reader, writer })
+    }
+
+    /// Inspect the chip's observable state.
+    pub fn state(&self) -> std::sync::MutexGuard<'_, SimChipState> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl Drop for SimChip {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Chip main loop: decode incoming frames and hash the current job.
+async fn run(config: SimChipConfig, io: DuplexStream, state: Arc<Mutex<SimChipState>>) {
+    let (mut rx, mut tx) = tokio::io::split(io);
+    let mut buf = BytesMut::with_capacity(1024);
+    let mut current: Option<SimJob> = None;
+    let mut next_nonce: u64 = 0;
+    let mut ticker = tokio::time::interval(config.tick);
+
+    loop {
+        tokio::select! {
+            read = rx.read_buf(&mut buf) => {
+                match read {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {}
+                }
+
+                while let Some(frame) = next_frame(&mut buf) {
+                    let mut out = Vec::new();
+                    if let Some(job) = handle_frame(&config, &frame, &state, &mut out) {
+                        current = Some(job);
+                        next_nonce = 0;
+                    }
+                    if !out.is_empty() && tx.write_all(&out).await.is_err() {
+                        return;
+                    }
+                }
+            }
+
+            _ = ticker.tick(), if current.is_some() => {
+                let job = current.as_ref().unwrap();
+                let mut out = Vec::new();
+
+                for _ in 0..config.hashes_per_tick {
+                    if next_nonce > u32::MAX as u64 {
+                        break;
+                    }
+                    let nonce = next_nonce as u32;
+                    next_nonce += 1;
+
+                    let header = BlockHeader {
+                        version: job.version,
+                        prev_blockhash: job.prev_blockhash,
+                        merkle_root: job.merkle_root,
+                        time: job.time,
+                        bits: job.bits,
+                        nonce,
+                    };
+                    if config.report_target.is_met_by(header.block_hash()) {
+                        out.extend_from_slice(&nonce_response(nonce, job.job_id));
+                        state.lock().unwrap().nonces_reported += 1;
+                    }
+                }
+
+                if !out.is_empty() && tx.write_all(&out).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Split the next complete command frame (without preamble) off `buf`.
+///
+/// Skips bytes until a preamble is found. Returns `None` when more data is
+/// needed.
+fn next_frame(buf: &mut BytesMut) -> Option<BytesMut> {
+    loop {
+        let start = buf.windows(2).position(|w| w == [0x55, 0xaa]);
+        match start {
+            Some(pos) => buf.advance(pos),
+            None => {
+                // Keep a trailing 0x55 that may begin a preamble
+                let keep = usize::from(buf.last() == Some(&0x55));
+                buf.advance(buf.len() - keep);
+                return None;
+            }
+        }
+
+        // Preamble + flags + length
+        if buf.len() < 4 {
+            return None;
+        }
+        let len = buf[3] as usize;
+        if len < 3 {
+            buf.advance(2);
+            continue;
+        }
+        if buf.len() < 2 + len {
+            return None;
+        }
+
+        buf.advance(2);
+        return Some(buf.split_to(len));
+    }
+}
+
+/// Act on one command frame. Returns a job if the frame carried one.
+fn handle_frame(
+    config: &SimChipConfig,
+    frame: &[u8],
+    state: &Mutex<SimChipState>,
+    out: &mut Vec<u8>,
+) -> Option<SimJob> {
+    let flags = frame[0];
+    let typ = (flags >> 5) & 0x3;
+    let cmd = flags & 0xf;
+
+    const TYPE_JOB: u8 = 1;
+    const CMD_WRITE: u8 = 1;
+    const CMD_READ: u8 = 2;
+
+    if typ == TYPE_JOB {
+        let (body, crc) = frame.split_at(frame.len() - 2);
+        if crc16(body) != u16::from_be_bytes([crc[0], crc[1]]) || body.len() != 84 {
+            state.lock().unwrap().bad_frames += 1;
+            return None;
+        }
+        let mut state = state.lock().unwrap();
+        if state.drop_jobs > 0 {
+            state.drop_jobs -= 1;
+            return None;
+        }
+        state.jobs_received += 1;
+        return Some(decode_job(&body[2..]));
+    }
+
+    let (body, crc) = frame.split_at(frame.len() - 1);
+    if crc5(body) != crc[0] {
+        state.lock().unwrap().bad_frames += 1;
+        return None;
+    }
+
+    match cmd {
+        CMD_WRITE if body.len() == 8 => {
+            let value = [body[4], body[5], body[6], body[7]];
+            state.lock().unwrap().registers.insert(body[3], value);
+        }
+        CMD_READ if body.len() == 4 => {
+            let register = body[3];
+            let value = if register == 0x00 {
+                [config.chip_id[0], config.chip_id[1], 0x00, 0x00]
+            } else {
+                state
+                    .lock()
+                    .unwrap()
+                    .registers
+                    .get(&register)
+                    .copied()
+                    .unwrap_or_default()
+            };
+            out.extend_from_slice(&register_response(value, 0x00, register));
+        }
+        _ => {
+            // SetChipAddress, ChainInactive: nothing to model
+        }
+    }
+
+    None
+}
+
+/// Decode the 82-byte JobFull payload.
+fn decode_job(data: &[u8]) -> SimJob {
+    let mut data = data;
+    let job_id = (data.get_u8() >> 3) & 0x0f;
+    let _num_midstates = data.get_u8();
+    let _starting_nonce = data.get_u32_le();
+    let bits = CompactTarget::from_consensus(data.get_u32_le());
+    let time = data.get_u32_le();
+
+    let mut wire = [0u8; 32];
+    data.copy_to_slice(&mut wire);
+    let merkle_root = TxMerkleNode::from_byte_array(hash_from_wire_bytes(&wire));
+    data.copy_to_slice(&mut wire);
+    let prev_blockhash = BlockHash::from_byte_array(hash_from_wire_bytes(&wire));
+
+    let version = Version::from_consensus(data.get_u32_le() as i32);
+
+    SimJob {
+        job_id,
+        version,
+        prev_blockhash,
+        merkle_root,
+        time,
+        bits,
+    }
+}
+
+/// Build an 11-byte response frame with type bits and CRC5 in the last byte.
+fn response_frame(data: [u8; 8], response_type: u8) -> [u8; 11] {
+    let mut frame = [0u8; 11];
+    frame[0] = 0xaa;
+    frame[1] = 0x55;
+    frame[2..10].copy_from_slice(&data);
+
+    // The CRC shares its byte with the type field; find the value that
+    // makes the frame check out the way the host decoder validates it.
+    for crc in 0..32u8 {
+        frame[10] = (response_type << 5) | crc;
+        if crc5_is_valid(&frame[2..]) {
+            break;
+        }
+    }
+    frame
+}
+
+fn nonce_response(nonce: u32, job_id: u8) -> [u8; 11] {
+    const NONCE: u8 = 4;
+    let mut data = [0u8; 8];
+    data[0..4].copy_from_slice(&nonce.to_le_bytes());
+    data[4] = 0x00; // midstate_num
+    data[5] = job_id << 4; // subcore 0
+    // Version bits left at zero: the simulated chip does not roll version
+    response_frame(data, NONCE)
+}
+
+fn register_response(value: [u8; 4], chip_address: u8, register: u8) -> [u8; 11] {
+    const READ_REGISTER: u8 = 0;
+    let mut data = [0u8; 8];
+    data[0..4].copy_from_slice(&value);
+    data[4] = chip_address;
+    data[5] = register;
+    response_frame(data, READ_REGISTER)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::SinkExt;
+    use tokio_stream::StreamExt;
+    use tokio_util::codec::{FramedRead, FramedWrite};
+
+    use super::*;
+    use crate::asic::bm13xx::{BM13xxProtocol, FrameCodec, Register, Response};
+
+    #[tokio::test]
+    async fn answers_chip_discovery() {
+        let (_chip, link) = SimChip::spawn(SimChipConfig::default());
+        let mut reader = FramedRead::new(link.reader, FrameCodec::default());
+        let mut writer = FramedWrite::new(link.writer, FrameCodec::default());
+
+        writer.send(BM13xxProtocol::discover_chips()).await.unwrap();
+
+        match reader.next().await {
+            Some(Ok(Response::ReadRegister {
+                register: Register::ChipId { chip_type, .. },
+                ..
+            })) => assert_eq!(chip_type.id_bytes(), [0x13, 0x70]),
+            other => panic!("expected ChipId response, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn next_frame_resyncs_after_garbage() {
+        let mut buf = BytesMut::from(&[0x00, 0x12, 0x55, 0xaa, 0x53, 0x05, 0x00, 0x00, 0x03][..]);
+        let frame = next_frame(&mut buf).expect("frame after garbage");
+        assert_eq!(&frame[..], &[0x53, 0x05, 0x00, 0x00, 0x03]);
+        assert!(buf.is_empty());
+    }
+}
@@ -1,7 +1,8 @@
 //! Test data from real mining hardware captures.
 //!
 //! This module provides known-good test data extracted from actual chip
-//! communication on Bitaxe Gamma (single BM1370).
+//! communication on Bitaxe Gamma (single BM1370): a mining round-trip and
+//! the frames that initialize the chip.
 //!
 //! This module serves as a rosetta stone between Stratum v1, Rust Bitcoin's
 //! internal format, and the BM13xx wire protocol. It demonstrates the correct
@@ -18,6 +19,7 @@
 //! - Stratum parsing tests → `stratum_v1::messages::tests`
 //! - Job conversion tests → `job_source::stratum_v1::tests`
 //! - Wire protocol tests → `asic::bm13xx::protocol::tests`
+//! - Init sequence tests → `asic::bm13xx::thread::tests`
 //!
 //! This separation ensures test_data remains a reference dataset that other
 //! modules can depend on without circular dependencies.
@@ -702,3 +704,118 @@ pub mod esp_miner_job {
         }
     }
 }
+
+/// BM1370 initialization as esp-miner sends it to a Bitaxe Gamma.
+///
+/// Golden sequence for the chip's register configuration, frame by frame,
+/// following `BM1370_init` in esp-miner. The frames shared with other
+/// captures are pinned individually in the protocol tests.
+///
+/// Two parts of esp-miner's boot are left out:
+///
+/// - Chip discovery: after the version mask writes, esp-miner reads the
+///   chip ID register (`55 AA 52 05 00 00 0A`) to count chips and writes
+///   the version mask a fourth time. Boards discover their chips before
+///   the hash thread initializes them.
+/// - The frequency ramp between [`CONFIGURATION`] and [`FINAL`]: one PLL
+///   write per 6.25 MHz step, whose dividers differ from esp-miner's
+///   (see `pll_calculation_produces_valid_frequencies`).
+pub mod esp_miner_init {
+    /// Frames from power-up to the frequency ramp
+    pub const CONFIGURATION: &[&[u8]] = &[
+        // Version mask, full rolling, three times
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0xA4, 0x90, 0x00, 0xFF, 0xFF, 0x1C,
+        ],
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0xA4, 0x90, 0x00, 0xFF, 0xFF, 0x1C,
+        ],
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0xA4, 0x90, 0x00, 0xFF, 0xFF, 0x1C,
+        ],
+        // Init control, misc control
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0xA8, 0x00, 0x07, 0x00, 0x00, 0x03,
+        ],
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0x18, 0xF0, 0x00, 0xC1, 0x00, 0x04,
+        ],
+        // Chain inactive, then address the chip
+        &[0x55, 0xAA, 0x53, 0x05, 0x00, 0x00, 0x03],
+        &[0x55, 0xAA, 0x40, 0x05, 0x00, 0x00, 0x1C],
+        // Core registers, ticket mask (difficulty 256), IO driver strength
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0x3C, 0x80, 0x00, 0x8B, 0x00, 0x12,
+        ],
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0x3C, 0x80, 0x00, 0x80, 0x0C, 0x11,
+        ],
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0x14, 0x00, 0x00, 0x00, 0xFF, 0x08,
+        ],
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0x58, 0x00, 0x01, 0x11, 0x11, 0x0D,
+        ],
+        // Per-chip init control, misc control and core registers
+        &[
+            0x55, 0xAA, 0x41, 0x09, 0x00, 0xA8, 0x00, 0x07, 0x01, 0xF0, 0x15,
+        ],
+        &[
+            0x55, 0xAA, 0x41, 0x09, 0x00, 0x18, 0xF0, 0x00, 0xC1, 0x00, 0x0C,
+        ],
+        &[
+            0x55, 0xAA, 0x41, 0x09, 0x00, 0x3C, 0x80, 0x00, 0x8B, 0x00, 0x1A,
+        ],
+        &[
+            0x55, 0xAA, 0x41, 0x09, 0x00, 0x3C, 0x80, 0x00, 0x80, 0x0C, 0x19,
+        ],
+        &[
+            0x55, 0xAA, 0x41, 0x09, 0x00, 0x3C, 0x80, 0x00, 0x82, 0xAA, 0x05,
+        ],
+        // Misc settings, analog mux, misc settings, core register
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0xB9, 0x00, 0x00, 0x44, 0x80, 0x0D,
+        ],
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0x54, 0x00, 0x00, 0x00, 0x02, 0x18,
+        ],
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0xB9, 0x00, 0x00, 0x44, 0x80, 0x0D,
+        ],
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0x3C, 0x80, 0x00, 0x8D, 0xEE, 0x1B,
+        ],
+    ];
+
+    /// Frames after the frequency ramp: nonce range and version mask
+    pub const FINAL: &[&[u8]] = &[
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0x10, 0x00, 0x00, 0x1E, 0xB5, 0x0F,
+        ],
+        &[
+            0x55, 0xAA, 0x51, 0x09, 0x00, 0xA4, 0x90, 0x00, 0xFF, 0xFF, 0x1C,
+        ],
+    ];
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::asic::bm13xx::crc::crc5;
+        use crate::asic::bm13xx::protocol::Command;
+
+        /// Every golden frame is a well-formed command with a valid CRC.
+        #[test]
+        fn golden_frames_decode() {
+            for frame in CONFIGURATION.iter().chain(FINAL) {
+                assert_eq!(&frame[..2], [0x55, 0xAA]);
+                let (crc, body) = frame[2..].split_last().unwrap();
+                assert_eq!(crc5(body), *crc, "bad CRC in {frame:02x?}");
+                let command = Command::decode(&frame[2..]).expect("decodes");
+                assert!(
+                    !matches!(command, Command::ReadRegister { .. }),
+                    "{command:?}"
+                );
+            }
+        }
+    }
+}
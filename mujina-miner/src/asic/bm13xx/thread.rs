@@ -7,7 +7,10 @@
 //! The thread is implemented as an actor task that monitors the serial bus for
 //! chip responses, filters shares, and manages work assignment.
 
-use std::sync::{Arc, RwLock};
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
 use async_trait::async_trait;
 use bitcoin::block::Header as BlockHeader;
@@ -15,51 +18,97 @@ use futures::{SinkExt, sink::Sink, stream::Stream};
 use tokio::sync::{mpsc, oneshot, watch};
 use tokio_stream::StreamExt;
 
-use super::protocol;
+use super::{
+    error::WriteBatchError,
+    flap_damper::{DEFAULT_MAX_RESETS, FlapDamper},
+    init_sequence::{InitRecorder, InitSequence},
+    job_dispatch::DispatchTiming,
+    job_slots::JobSlots,
+    job_watchdog::{DEFAULT_NONCE_TIMEOUT, JobWatchdog, WatchdogAction},
+    link_guard::{LinkAction, LinkGuard},
+    model,
+    nonce_latency::{LatencyAlert, NonceLatency},
+    nonce_map::NonceMap,
+    nonce_rate::ChipNonceRates,
+    nonce_space::{self, DUPLICATE_WINDOW, DuplicateWindow, Extranonce2Walk},
+    pll_readback::{PllAlert, PllReadback},
+    protocol,
+    register_dump::RegisterDump,
+    write_batch::WriteBatch,
+};
 use crate::{
+    api_client::types::ChipRegisterDump,
+    asic::ChipStats,
+    asic::derating::{DeratingCurve, FrequencyLimiter},
     asic::hash_thread::{
-        BoardPeripherals, HashTask, HashThread, HashThreadCapabilities, HashThreadError,
-        HashThreadEvent, HashThreadStatus, Share, ThreadRemovalSignal,
+        AssignmentParameters, BaudRateControl, BoardPeripherals, ChipPowerState, HashTask,
+        HashThread, HashThreadCapabilities, HashThreadError, HashThreadEvent, HashThreadStatus,
+        Share, ThreadRemovalSignal,
     },
+    asic::slew::ThermalSlew,
+    asic::thermal::{STALE_TICKS, ThermalConfig},
+    asic::verify_pool,
+    asic::warmup::{Warmup, WarmupConfig, WarmupStep},
+    sensors,
     tracing::prelude::*,
     types::{Difficulty, HashRate},
 };
 
-/// Tracks tasks sent to chip hardware, indexed by chip_job_id.
+/// Default interval between ntime-rolled job dispatches.
 ///
-/// BM13xx chips use 4-bit job IDs. This tracker maintains snapshots of
-/// HashTasks sent to the chip so we can match nonce responses back to the
-/// correct task context (EN2, ntime, etc.).
-struct ChipJobTracker {
-    tasks: [Option<HashTask>; 16],
-    next_id: u8,
-}
+/// Each dispatch sends a full job to the chip, so this trades serial
+/// bandwidth against timestamp freshness.
+pub const DEFAULT_NTIME_ROLL_INTERVAL: Duration = Duration::from_secs(1);
 
-impl ChipJobTracker {
-    fn new() -> Self {
-        Self {
-            tasks: Default::default(),
-            next_id: 0,
-        }
-    }
+/// Maximum number of seconds ntime may advance past the task's base.
+///
+/// Pools reject shares whose ntime runs too far ahead of the job (and
+/// Bitcoin rejects blocks more than two hours in the future). Long-lived
+/// jobs stop rolling at this point and wait for fresh work. The scheduler
+/// may negotiate a shorter limit.
+const MAX_NTIME_ROLL: u32 = 600;
 
-    fn insert(&mut self, task: HashTask) -> u8 {
-        let chip_job_id = self.next_id;
-        self.tasks[chip_job_id as usize] = Some(task);
-        self.next_id = (self.next_id + 1) % (self.tasks.len() as u8);
-        chip_job_id
-    }
+/// Model of the chips this thread drives.
+const CHIP_MODEL: model::ChipModel = model::BM1370;
 
-    fn get(&self, chip_job_id: u8) -> Option<&HashTask> {
-        self.tasks
-            .get(chip_job_id as usize)
-            .and_then(|t| t.as_ref())
-    }
+/// Default core frequency the chip is ramped to during initialization.
+const TARGET_FREQUENCY_MHZ: f32 = CHIP_MODEL.default_frequency_mhz;
 
-    fn clear(&mut self) {
-        self.tasks = Default::default();
-    }
-}
+/// Core frequency the chip comes out of reset at.
+///
+/// Initialization ramps up from here, and idle chips are ramped back down
+/// to it.
+const POWER_UP_FREQUENCY_MHZ: f32 = 56.25;
+
+/// Address the chip is given during initialization.
+const CHIP_ADDRESS: u8 = 0x00;
+
+/// `OrderedClockEnable` value with every core group clocked.
+///
+/// One bit per core group; bits beyond the chip's group count are
+/// ignored.
+const ALL_CORE_CLOCKS: u32 = 0xFFFF_FFFF;
+
+/// Frequency change per PLL write when retuning a running chip.
+const FREQUENCY_STEP_MHZ: f32 = 6.25;
+
+/// Settling time after each PLL write.
+const FREQUENCY_STEP_DELAY: Duration = Duration::from_millis(100);
+
+/// Longest a register dump waits for the chip to answer every read.
+const REGISTER_DUMP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Upper bound on time spent forwarding in-flight shares on removal.
+const SHARE_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Silence on the link after which draining stops early.
+const SHARE_DRAIN_QUIET: Duration = Duration::from_millis(100);
+
+/// Tasks sent to the chip, by chip job ID.
+///
+/// Snapshots of each HashTask so nonce responses can be matched back to
+/// the task context (EN2, ntime, etc.) they were found for.
+type ChipJobTracker = JobSlots<HashTask>;
 
 /// Command messages sent from scheduler to thread
 #[derive(Debug)]
@@ -76,16 +125,119 @@ enum ThreadCommand {
         response_tx: oneshot::Sender<std::result::Result<Option<HashTask>, HashThreadError>>,
     },
 
+    /// Apply the scheduler's assignment parameters
+    Negotiate { params: AssignmentParameters },
+
     /// Go idle (stop hashing, low power)
     GoIdle {
         response_tx: oneshot::Sender<std::result::Result<Option<HashTask>, HashThreadError>>,
     },
 
+    /// Read back every known register of one chip
+    DumpRegisters {
+        chip_address: u8,
+        /// Compare against the values written during initialization
+        diff: bool,
+        response_tx: oneshot::Sender<std::result::Result<RegisterDump, HashThreadError>>,
+    },
+
     /// Shutdown the thread
     #[expect(unused)]
     Shutdown,
 }
 
+/// How the actor chooses the chip's core frequency.
+#[derive(Debug, Clone)]
+struct FrequencyPlan {
+    /// Frequency to run at when not warming up or derated
+    target_mhz: f32,
+
+    /// Ceiling by die temperature
+    derating: DeratingCurve,
+
+    /// Staged ramp after initialization, if enabled
+    warmup: Option<WarmupConfig>,
+
+    /// Limit on how fast frequency changes may heat or cool the chip
+    slew: Option<ThermalSlew>,
+
+    /// Temperature poll and slew step timing
+    thermal: ThermalConfig,
+}
+
+impl Default for FrequencyPlan {
+    fn default() -> Self {
+        Self {
+            target_mhz: TARGET_FREQUENCY_MHZ,
+            derating: DeratingCurve::default(),
+            warmup: None,
+            slew: None,
+            thermal: ThermalConfig::default(),
+        }
+    }
+}
+
+/// Handle for retuning a running thread's chips.
+///
+/// Kept by the board after the thread itself is handed to the scheduler.
+#[derive(Debug, Clone)]
+pub struct FrequencyControl {
+    plan_tx: watch::Sender<FrequencyPlan>,
+}
+
+impl FrequencyControl {
+    /// Move the chips to a new target frequency.
+    ///
+    /// The thread steps the PLL towards it gradually, at the thermal slew
+    /// limit if one is set, and still applies thermal derating on top. An
+    /// ongoing warm-up is cut short. Targets above the chip model's
+    /// maximum are capped to it.
+    pub fn set_target(&self, mhz: f32) {
+        let mhz = mhz.min(CHIP_MODEL.max_frequency_mhz);
+        self.plan_tx.send_if_modified(|plan| {
+            let changed = plan.target_mhz != mhz;
+            plan.target_mhz = mhz;
+            changed
+        });
+    }
+}
+
+/// Handle for reading back a running thread's chip registers.
+///
+/// Kept by the board after the thread itself is handed to the scheduler.
+#[derive(Debug, Clone)]
+pub struct RegisterAccess {
+    name: String,
+    command_tx: mpsc::Sender<ThreadCommand>,
+}
+
+impl RegisterAccess {
+    /// Read every known register of the chip at `chip_address`.
+    ///
+    /// With `diff`, registers that initialization sets carry the value it
+    /// wrote, and those now holding something else are listed. Fails if
+    /// the chip hasn't been initialized yet.
+    pub async fn dump(
+        &self,
+        chip_address: u8,
+        diff: bool,
+    ) -> std::result::Result<ChipRegisterDump, HashThreadError> {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.command_tx
+            .send(ThreadCommand::DumpRegisters {
+                chip_address,
+                diff,
+                response_tx,
+            })
+            .await
+            .map_err(|_| HashThreadError::ThreadOffline)?;
+        let dump = response_rx
+            .await
+            .map_err(|_| HashThreadError::ThreadOffline)??;
+        Ok(dump.report(&self.name))
+    }
+}
+
 /// BM13xx HashThread implementation.
 ///
 /// Represents a chain of BM13xx chips as a schedulable worker. The thread
@@ -95,6 +247,9 @@ pub struct BM13xxThread {
     /// Human-readable name for logging
     name: String,
 
+    /// Stable hardware ID for share attribution
+    device_id: Option<String>,
+
     /// Channel for sending commands to the actor
     command_tx: mpsc::Sender<ThreadCommand>,
 
@@ -106,6 +261,19 @@ pub struct BM13xxThread {
 
     /// Shared status (updated by actor task)
     status: Arc<RwLock<HashThreadStatus>>,
+
+    /// Frequency plan (read by actor task)
+    frequency_tx: watch::Sender<FrequencyPlan>,
+
+    /// Time the chip gets to answer a new task (read by actor task)
+    nonce_timeout_tx: watch::Sender<Duration>,
+
+    /// Interval between ntime-rolled dispatches (watched by actor task)
+    ntime_roll_tx: watch::Sender<Duration>,
+
+    /// Decode counts of the chip link, for falling back to a slower link
+    /// when it gets noisy (read by actor task)
+    link_stats_tx: watch::Sender<Option<protocol::FrameCodec>>,
 }
 
 impl BM13xxThread {
@@ -135,31 +303,147 @@ impl BM13xxThread {
         let (cmd_tx, cmd_rx) = mpsc::channel(10);
         let (evt_tx, evt_rx) = mpsc::channel(100);
 
-        let status = Arc::new(RwLock::new(HashThreadStatus::default()));
+        // One chip at address 0 unless told otherwise
+        let status = Arc::new(RwLock::new(HashThreadStatus {
+            chips: vec![ChipStats::default()],
+            ..Default::default()
+        }));
         let status_clone = Arc::clone(&status);
+        let phase_seed = phase_seed(&name);
+        let (frequency_tx, frequency_rx) = watch::channel(FrequencyPlan::default());
+        let (nonce_timeout_tx, nonce_timeout_rx) = watch::channel(DEFAULT_NONCE_TIMEOUT);
+        let (ntime_roll_tx, ntime_roll_rx) = watch::channel(DEFAULT_NTIME_ROLL_INTERVAL);
+        let (link_stats_tx, link_stats_rx) = watch::channel(None);
 
         // Spawn the actor task
-        tokio::spawn(async move {
-            bm13xx_thread_actor(
-                cmd_rx,
-                evt_tx,
-                removal_rx,
-                status_clone,
-                chip_responses,
-                chip_commands,
-                peripherals,
-            )
-            .await;
-        });
+        let span = info_span!("hash_thread", thread = %name);
+        tokio::spawn(
+            async move {
+                bm13xx_thread_actor(
+                    cmd_rx,
+                    evt_tx,
+                    removal_rx,
+                    status_clone,
+                    chip_responses,
+                    chip_commands,
+                    peripherals,
+                    phase_seed,
+                    frequency_rx,
+                    nonce_timeout_rx,
+                    ntime_roll_rx,
+                    link_stats_rx,
+                )
+                .await;
+            }
+            .instrument(span),
+        );
 
         Self {
             name,
+            device_id: None,
             command_tx: cmd_tx,
             event_rx: Some(evt_rx),
             capabilities: HashThreadCapabilities {
-                hashrate_estimate: HashRate::from_terahashes(1.0), // Stub
+                hashrate_estimate: CHIP_MODEL.hashrate_at(TARGET_FREQUENCY_MHZ),
+                version_rolling: CHIP_MODEL.version_rolling(),
+                max_ntime_roll: MAX_NTIME_ROLL,
+                iterates_extranonce2: true,
+                reporting_difficulty: Some(Difficulty::from(reporting_ticket_mask().difficulty())),
             },
             status,
+            frequency_tx,
+            nonce_timeout_tx,
+            ntime_roll_tx,
+            link_stats_tx,
+        }
+    }
+
+    /// Identify the hardware behind this thread (e.g., board serial).
+    pub fn with_device_id(mut self, device_id: String) -> Self {
+        self.device_id = Some(device_id);
+        self
+    }
+
+    /// Limit core frequency by die temperature.
+    ///
+    /// Without a curve the chip always runs at its target frequency.
+    pub fn with_derating(self, curve: DeratingCurve) -> Self {
+        self.frequency_tx.send_modify(|plan| plan.derating = curve);
+        self
+    }
+
+    /// Warm up in stages after initialization. Without a config the chip
+    /// goes straight to its target frequency.
+    pub fn with_warmup(self, config: Option<WarmupConfig>) -> Self {
+        self.frequency_tx.send_modify(|plan| plan.warmup = config);
+        self
+    }
+
+    /// Move the core frequency slowly enough that the die temperature
+    /// changes by at most the slew limit, except when derating. Without a
+    /// limit the chip goes to each new frequency straight away.
+    pub fn with_thermal_slew(self, slew: Option<ThermalSlew>) -> Self {
+        self.frequency_tx.send_modify(|plan| plan.slew = slew);
+        self
+    }
+
+    /// Poll the chip's temperature and step slewed frequency changes on
+    /// `config`'s timing instead of the defaults.
+    pub fn with_thermal_config(self, config: ThermalConfig) -> Self {
+        self.frequency_tx.send_modify(|plan| plan.thermal = config);
+        self
+    }
+
+    /// Give the chip `timeout` to report its first nonce for a new task
+    /// before the task is sent again, and then the chip re-initialized.
+    pub fn with_nonce_timeout(self, timeout: Duration) -> Self {
+        self.nonce_timeout_tx.send_replace(timeout);
+        self
+    }
+
+    /// Re-send the current task with a fresh ntime every `interval`
+    /// instead of every second.
+    pub fn with_ntime_roll_interval(self, interval: Duration) -> Self {
+        self.ntime_roll_tx.send_replace(interval);
+        self
+    }
+
+    /// Watch the decode counts of `codec`, a clone of the codec decoding
+    /// the chips' responses, and drop to a slower link while they show
+    /// too many errors (see [`link_guard`](super::link_guard)).
+    pub fn with_link_stats(self, codec: protocol::FrameCodec) -> Self {
+        self.link_stats_tx.send_replace(Some(codec));
+        self
+    }
+
+    /// Run the chips at `mhz` instead of the default target frequency,
+    /// capped to the chip model's maximum.
+    pub fn with_target_frequency(self, mhz: f32) -> Self {
+        let mhz = mhz.min(CHIP_MODEL.max_frequency_mhz);
+        self.frequency_tx.send_modify(|plan| plan.target_mhz = mhz);
+        self
+    }
+
+    /// Number of chips on the chain, for attributing nonces to chips.
+    pub fn with_chip_count(self, count: usize) -> Self {
+        self.status.write().unwrap().chips = vec![ChipStats::default(); count.max(1)];
+        self
+    }
+
+    /// Handle for changing the target frequency after the thread has been
+    /// handed off.
+    pub fn frequency_control(&self) -> FrequencyControl {
+        FrequencyControl {
+            plan_tx: self.frequency_tx.clone(),
+        }
+    }
+
+    /// Handle for reading chip registers after the thread has been handed
+    /// off.
+    pub fn register_access(&self) -> RegisterAccess {
+        RegisterAccess {
+            name: self.name.clone(),
+            command_tx: self.command_tx.clone(),
         }
     }
 }
@@ -174,6 +458,20 @@ impl HashThread for BM13xxThread {
         &self.capabilities
     }
 
+    async fn negotiate(
+        &mut self,
+        params: AssignmentParameters,
+    ) -> std::result::Result<(), HashThreadError> {
+        self.command_tx
+            .send(ThreadCommand::Negotiate { params })
+            .await
+            .map_err(|_| HashThreadError::ChannelClosed("command channel closed".into()))
+    }
+
+    fn device_id(&self) -> Option<&str> {
+        self.device_id.as_deref()
+    }
+
     async fn update_task(
         &mut self,
         new_task: HashTask,
@@ -236,17 +534,38 @@ impl HashThread for BM13xxThread {
 
 /// Initialize BM13xx chip for mining.
 ///
-/// Enables chip, configures all registers, and ramps frequency to target.
-async fn initialize_chip<W>(
+/// Enables chip, configures all registers, and ramps frequency to
+/// `frequency_mhz`. Register writes are read back, and initialization
+/// fails if the chip doesn't take them. Returns the frames sent, which
+/// are also logged at trace level.
+async fn initialize_chip<R, W>(
     chip_commands: &mut W,
+    chip_responses: &mut R,
     peripherals: &mut BoardPeripherals,
-) -> Result<(), HashThreadError>
+    frequency_mhz: f32,
+) -> Result<InitSequence, HashThreadError>
 where
+    R: Stream<Item = Result<protocol::Response, std::io::Error>> + Unpin,
     W: Sink<protocol::Command> + Unpin,
     W::Error: std::fmt::Debug,
 {
     use protocol::{Command, Register};
 
+    let mut recorder = InitRecorder::new(chip_commands);
+    let chip_commands = &mut recorder;
+
+    // Chips come out of reset at their power-up rate; make sure the host
+    // matches in case a previous initialization switched it.
+    if let Some(ref mut baud_control) = peripherals.baud_control {
+        let initial = baud_control.initial_baud_rate();
+        baud_control.set_baud_rate(initial).await.map_err(|e| {
+            HashThreadError::InitializationFailed(format!(
+                "Failed to restore initial baud rate: {}",
+                e
+            ))
+        })?;
+    }
+
     // Enable the ASIC
     if let Some(ref mut asic_enable) = peripherals.asic_enable {
         debug!("Enabling ASIC");
@@ -278,30 +597,19 @@ where
 
     tokio::time::sleep(std::time::Duration::from_millis(10)).await;
 
-    // Pre-configuration registers
+    // Pre-configuration registers. The chip has no address yet to read
+    // them back from, and initialization overwrites them below.
     debug!("Sending pre-configuration registers");
-
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: true,
-            chip_address: 0x00,
-            register: Register::InitControl {
-                raw_value: 0x00000700,
-            },
+    WriteBatch::broadcast(&[])
+        .write(Register::InitControl {
+            raw_value: 0x00000700,
         })
-        .await
-        .map_err(|e| HashThreadError::InitializationFailed(format!("Init send failed: {:?}", e)))?;
-
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: true,
-            chip_address: 0x00,
-            register: Register::MiscControl {
-                raw_value: 0x00C100F0,
-            },
+        .write(Register::MiscControl {
+            raw_value: 0x00C100F0,
         })
+        .apply(chip_commands, chip_responses)
         .await
-        .map_err(|e| HashThreadError::InitializationFailed(format!("Misc send failed: {:?}", e)))?;
+        .map_err(init_failed("pre-configuration"))?;
 
     chip_commands
         .send(Command::ChainInactive)
@@ -311,196 +619,75 @@ where
         })?;
 
     chip_commands
-        .send(Command::SetChipAddress { chip_address: 0x00 })
+        .send(Command::SetChipAddress {
+            chip_address: CHIP_ADDRESS,
+        })
         .await
         .map_err(|e| {
             HashThreadError::InitializationFailed(format!("SetChipAddress failed: {:?}", e))
         })?;
 
-    // Core configuration (broadcast)
+    // Core configuration, ticket mask, IO strength
     debug!("Sending broadcast core configuration");
-
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: true,
-            chip_address: 0x00,
-            register: Register::Core {
-                raw_value: 0x8000_8B00,
-            },
+    WriteBatch::broadcast(&[CHIP_ADDRESS])
+        .write(Register::Core {
+            raw_value: 0x8000_8B00,
         })
-        .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("Core1 send failed: {:?}", e))
-        })?;
-
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: true,
-            chip_address: 0x00,
-            register: Register::Core {
-                raw_value: 0x8000_800C,
-            },
-        })
-        .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("Core2 send failed: {:?}", e))
-        })?;
-
-    // Ticket mask, IO strength
-    // Target: ~1 nonce per second at 1 TH/s (1000 GiH/s = 1.074 TH/s)
-    use protocol::{Hashrate, ReportingInterval, ReportingRate, TicketMask};
-    let reporting_interval = ReportingInterval::from_rate(
-        Hashrate::gibihashes_per_sec(1000.0),
-        ReportingRate::nonces_per_sec(1.0),
-    );
-    let ticket_mask = TicketMask::new(reporting_interval);
-
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: true,
-            chip_address: 0x00,
-            register: Register::TicketMask(ticket_mask),
-        })
-        .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("TicketMask send failed: {:?}", e))
-        })?;
-
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: true,
-            chip_address: 0x00,
-            register: Register::IoDriverStrength(protocol::IoDriverStrength::normal()),
+        .write(Register::Core {
+            raw_value: 0x8000_800C,
         })
+        .write(Register::TicketMask(reporting_ticket_mask()))
+        .write(Register::IoDriverStrength(
+            protocol::IoDriverStrength::normal(),
+        ))
+        .apply(chip_commands, chip_responses)
         .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("IoDriver send failed: {:?}", e))
-        })?;
+        .map_err(init_failed("core configuration"))?;
 
-    // Chip-specific configuration
     debug!("Sending chip-specific configuration");
-
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: false,
-            chip_address: 0x00,
-            register: Register::InitControl {
-                raw_value: 0xF0010700,
-            },
+    WriteBatch::to_chip(CHIP_ADDRESS)
+        .write(Register::InitControl {
+            raw_value: 0xF0010700,
         })
-        .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("InitControl chip send failed: {:?}", e))
-        })?;
-
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: false,
-            chip_address: 0x00,
-            register: Register::MiscControl {
-                raw_value: 0x00C100F0,
-            },
+        .write(Register::MiscControl {
+            raw_value: 0x00C100F0,
         })
-        .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("MiscControl chip send failed: {:?}", e))
-        })?;
-
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: false,
-            chip_address: 0x00,
-            register: Register::Core {
-                raw_value: 0x8000_8B00,
-            },
+        .write(Register::Core {
+            raw_value: 0x8000_8B00,
         })
-        .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("Core chip1 send failed: {:?}", e))
-        })?;
-
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: false,
-            chip_address: 0x00,
-            register: Register::Core {
-                raw_value: 0x8000_800C,
-            },
+        .write(Register::Core {
+            raw_value: 0x8000_800C,
         })
-        .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("Core chip2 send failed: {:?}", e))
-        })?;
-
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: false,
-            chip_address: 0x00,
-            register: Register::Core {
-                raw_value: 0x8000_82AA,
-            },
+        .write(Register::Core {
+            raw_value: 0x8000_82AA,
         })
+        .apply(chip_commands, chip_responses)
         .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("Core chip3 send failed: {:?}", e))
-        })?;
+        .map_err(init_failed("chip-specific configuration"))?;
 
     // Additional settings
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: true,
-            chip_address: 0x00,
-            register: Register::MiscSettings {
-                raw_value: 0x80440000,
-            },
+    WriteBatch::broadcast(&[CHIP_ADDRESS])
+        .write(Register::MiscSettings {
+            raw_value: 0x80440000,
         })
-        .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("MiscSettings send failed: {:?}", e))
-        })?;
-
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: true,
-            chip_address: 0x00,
-            register: Register::AnalogMux {
-                raw_value: 0x02000000,
-            },
+        .write(Register::AnalogMux {
+            raw_value: 0x02000000,
         })
-        .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("AnalogMux send failed: {:?}", e))
-        })?;
-
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: true,
-            chip_address: 0x00,
-            register: Register::MiscSettings {
-                raw_value: 0x80440000,
-            },
+        .write(Register::MiscSettings {
+            raw_value: 0x80440000,
         })
-        .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("MiscSettings2 send failed: {:?}", e))
-        })?;
-
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: true,
-            chip_address: 0x00,
-            register: Register::Core {
-                raw_value: 0x8000_8DEE,
-            },
+        .write(Register::Core {
+            raw_value: 0x8000_8DEE,
         })
+        .apply(chip_commands, chip_responses)
         .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("Core final send failed: {:?}", e))
-        })?;
+        .map_err(init_failed("additional settings"))?;
 
-    // Frequency ramping (56.25 MHz -> 525 MHz)
-    debug!("Ramping frequency from 56.25 MHz to 525 MHz");
-    let frequency_steps = generate_frequency_ramp_steps(56.25, 525.0, 6.25);
+    // Frequency ramping (56.25 MHz -> 525 MHz). Steps go out unchecked to
+    // keep their timing; the final one is confirmed below.
+    debug!("Ramping frequency from {POWER_UP_FREQUENCY_MHZ} MHz to {frequency_mhz} MHz");
+    let frequency_steps =
+        generate_frequency_ramp_steps(POWER_UP_FREQUENCY_MHZ, frequency_mhz, FREQUENCY_STEP_MHZ);
 
     for (i, pll_config) in frequency_steps.iter().enumerate() {
         chip_commands
@@ -514,7 +701,7 @@ where
                 HashThreadError::InitializationFailed(format!("PLL ramp failed: {:?}", e))
             })?;
 
-        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        tokio::time::sleep(FREQUENCY_STEP_DELAY).await;
 
         if i % 10 == 0 || i == frequency_steps.len() - 1 {
             trace!("Frequency ramp step {}/{}", i + 1, frequency_steps.len());
@@ -523,57 +710,367 @@ where
 
     debug!("Frequency ramping complete");
 
-    // Final configuration
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: true,
-            chip_address: 0x00,
-            register: Register::NonceRange(protocol::NonceRangeConfig::from_raw(0xB51E0000)),
-        })
-        .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("NonceRange send failed: {:?}", e))
-        })?;
-
-    // Final version mask
-    chip_commands
-        .send(Command::WriteRegister {
-            broadcast: true,
-            chip_address: 0x00,
-            register: Register::VersionMask(protocol::VersionMask::full_rolling()),
-        })
+    // Final configuration and version mask
+    let mut final_batch = WriteBatch::broadcast(&[CHIP_ADDRESS]);
+    if let Some(pll_config) = frequency_steps.last() {
+        final_batch = final_batch.write(Register::PllDivider(*pll_config));
+    }
+    final_batch
+        .write(Register::NonceRange(protocol::NonceRangeConfig::from_raw(
+            0xB51E0000,
+        )))
+        .write(Register::VersionMask(protocol::VersionMask::full_rolling()))
+        .apply(chip_commands, chip_responses)
         .await
-        .map_err(|e| {
-            HashThreadError::InitializationFailed(format!("Final version mask failed: {:?}", e))
-        })?;
+        .map_err(init_failed("final configuration"))?;
 
     tokio::time::sleep(std::time::Duration::from_millis(150)).await;
 
-    Ok(())
+    if let Some(ref mut baud_control) = peripherals.baud_control {
+        switch_baud_rate(chip_commands, baud_control.as_mut()).await?;
+    }
+
+    let sequence = recorder.into_sequence();
+    trace!(
+        frames = sequence.len(),
+        "Initialization sequence:\n{sequence}"
+    );
+    Ok(sequence)
 }
 
-/// Generate frequency ramp steps for smooth PLL transitions
-fn generate_frequency_ramp_steps(
-    start_mhz: f32,
-    target_mhz: f32,
-    step_mhz: f32,
-) -> Vec<protocol::PllConfig> {
-    let mut configs = Vec::new();
-    let mut current = start_mhz;
+/// Map a failed write batch to an initialization error naming the step.
+fn init_failed(step: &'static str) -> impl Fn(WriteBatchError) -> HashThreadError {
+    move |e| HashThreadError::InitializationFailed(format!("{step}: {e}"))
+}
 
-    while current <= target_mhz {
-        if let Some(config) = calculate_pll_for_frequency(current) {
-            configs.push(config);
-        }
-        current += step_mhz;
-        if current > target_mhz && (current - step_mhz) < target_mhz {
-            current = target_mhz;
-        }
+/// Move the chain from its power-up rate to the board's target baud rate.
+///
+/// The UART register write goes out at the current rate; the host side is
+/// reconfigured only after that write has drained, then both ends talk at
+/// the new rate.
+async fn switch_baud_rate<W>(
+    chip_commands: &mut W,
+    baud_control: &mut dyn BaudRateControl,
+) -> Result<(), HashThreadError>
+where
+    W: Sink<protocol::Command> + Unpin,
+    W::Error: std::fmt::Debug,
+{
+    let initial = baud_control.initial_baud_rate();
+    let target = baud_control.target_baud_rate();
+    if target == initial {
+        return Ok(());
+    }
+
+    debug!(from = initial, to = target, "Switching chip baud rate");
+    change_baud_rate(chip_commands, baud_control, target)
+        .await
+        .map_err(HashThreadError::InitializationFailed)
+}
+
+/// Move both ends of the link to `baud`, chips first.
+async fn change_baud_rate<W>(
+    chip_commands: &mut W,
+    baud_control: &mut dyn BaudRateControl,
+    baud: u32,
+) -> Result<(), String>
+where
+    W: Sink<protocol::Command> + Unpin,
+    W::Error: std::fmt::Debug,
+{
+    let Some(chip_baud) = protocol::BaudRate::from_bits_per_sec(baud) else {
+        warn!(
+            baud,
+            "No chip register setting for requested baud rate, staying at current rate"
+        );
+        return Ok(());
+    };
+
+    chip_commands
+        .send(protocol::BM13xxProtocol::new().set_baudrate(chip_baud))
+        .await
+        .map_err(|e| format!("UartBaud send failed: {:?}", e))?;
+
+    baud_control
+        .set_baud_rate(baud)
+        .await
+        .map_err(|e| format!("Failed to set host baud rate: {}", e))?;
+
+    // Give the chip UART a moment to settle at the new rate
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+    Ok(())
+}
+
+/// Baud rate the link runs at when `reduced` or not, for boards that
+/// set it.
+fn link_baud_rate(peripherals: &BoardPeripherals, reduced: bool) -> Option<u32> {
+    peripherals.baud_control.as_ref().map(|baud_control| {
+        if reduced {
+            baud_control.initial_baud_rate()
+        } else {
+            baud_control.target_baud_rate()
+        }
+    })
+}
+
+/// Run the link at the chips' power-up baud rate when `reduced`, or at
+/// the board's target rate.
+async fn set_link_speed<W>(
+    chip_commands: &mut W,
+    peripherals: &mut BoardPeripherals,
+    reduced: bool,
+) -> Result<(), String>
+where
+    W: Sink<protocol::Command> + Unpin,
+    W::Error: std::fmt::Debug,
+{
+    let Some(ref mut baud_control) = peripherals.baud_control else {
+        return Ok(());
+    };
+    let baud = if reduced {
+        baud_control.initial_baud_rate()
+    } else {
+        baud_control.target_baud_rate()
+    };
+    change_baud_rate(chip_commands, baud_control.as_mut(), baud).await
+}
+
+/// Generate frequency ramp steps for smooth PLL transitions
+fn generate_frequency_ramp_steps(
+    start_mhz: f32,
+    target_mhz: f32,
+    step_mhz: f32,
+) -> Vec<protocol::PllConfig> {
+    let mut configs = Vec::new();
+    let mut current = start_mhz;
+
+    while current <= target_mhz {
+        if let Some(config) = calculate_pll_for_frequency(current) {
+            configs.push(config);
+        }
+        current += step_mhz;
+        if current > target_mhz && (current - step_mhz) < target_mhz {
+            current = target_mhz;
+        }
     }
 
     configs
 }
 
+/// Frequencies to pass through when retuning a running chip, ending at
+/// `to_mhz`. Steps up or down as needed.
+fn frequency_path(from_mhz: f32, to_mhz: f32, step_mhz: f32) -> Vec<f32> {
+    let mut path = Vec::new();
+    let mut current = from_mhz;
+    while (to_mhz - current).abs() > step_mhz {
+        current += step_mhz.copysign(to_mhz - current);
+        path.push(current);
+    }
+    path.push(to_mhz);
+    path
+}
+
+/// Move a running chip to a new core frequency, one small step at a time
+/// so the PLL and core voltage can follow.
+///
+/// `frequency_mhz` tracks the chip's frequency as each step is written, so
+/// it stays accurate if a write fails part way.
+async fn retune_frequency<W>(
+    chip_commands: &mut W,
+    frequency_mhz: &mut f32,
+    to_mhz: f32,
+) -> Result<(), W::Error>
+where
+    W: Sink<protocol::Command> + Unpin,
+{
+    if *frequency_mhz == to_mhz {
+        return Ok(());
+    }
+    for freq in frequency_path(*frequency_mhz, to_mhz, FREQUENCY_STEP_MHZ) {
+        let Some(pll_config) = calculate_pll_for_frequency(freq) else {
+            continue;
+        };
+        chip_commands
+            .send(protocol::Command::WriteRegister {
+                broadcast: true,
+                chip_address: 0x00,
+                register: protocol::Register::PllDivider(pll_config),
+            })
+            .await?;
+        *frequency_mhz = freq;
+        tokio::time::sleep(FREQUENCY_STEP_DELAY).await;
+    }
+    Ok(())
+}
+
+/// Register values [`initialize_chip`] leaves behind, with the core
+/// clock PLL at `frequency_mhz` and version rolling at `version_mask` if
+/// it has been narrowed since.
+///
+/// Core registers are written indirectly and can't be read back, so
+/// they're left out.
+fn expected_registers(
+    frequency_mhz: f32,
+    version_mask: Option<protocol::VersionMask>,
+) -> Vec<protocol::Register> {
+    use protocol::Register;
+
+    let mut registers = vec![
+        Register::VersionMask(version_mask.unwrap_or_else(protocol::VersionMask::full_rolling)),
+        Register::InitControl {
+            raw_value: 0xF0010700,
+        },
+        Register::MiscControl {
+            raw_value: 0x00C100F0,
+        },
+        Register::TicketMask(reporting_ticket_mask()),
+        Register::IoDriverStrength(protocol::IoDriverStrength::normal()),
+        Register::MiscSettings {
+            raw_value: 0x80440000,
+        },
+        Register::AnalogMux {
+            raw_value: 0x02000000,
+        },
+        Register::NonceRange(protocol::NonceRangeConfig::from_raw(0xB51E0000)),
+    ];
+    if let Some(pll_config) = calculate_pll_for_frequency(frequency_mhz) {
+        registers.push(Register::PllDivider(pll_config));
+    }
+    registers
+}
+
+/// Ticket mask the chips are configured with.
+///
+/// Target: the model's nonce interval at its default frequency, ~1 nonce
+/// per second at ~1.07 TH/s for the BM1370.
+fn reporting_ticket_mask() -> protocol::TicketMask {
+    CHIP_MODEL.ticket_mask()
+}
+
+/// Chip version mask matching a task's allowed general purpose bits.
+fn version_mask_for(task: &HashTask) -> protocol::VersionMask {
+    let gp_bits = task.template.version.gp_bits_mask();
+    protocol::VersionMask::rolling(u16::from_be_bytes(*gp_bits.as_bytes()))
+}
+
+/// Reconfigure chip version rolling if the task's mask differs from the
+/// one currently programmed.
+///
+/// Pools may narrow the version mask mid-session; chips rolling bits
+/// outside it produce shares the pool rejects.
+async fn sync_version_mask<W>(
+    chip_commands: &mut W,
+    chip_version_mask: &mut Option<protocol::VersionMask>,
+    task: &HashTask,
+) -> Result<(), HashThreadError>
+where
+    W: Sink<protocol::Command> + Unpin,
+    W::Error: std::fmt::Debug,
+{
+    let wanted = version_mask_for(task);
+    if *chip_version_mask == Some(wanted) {
+        return Ok(());
+    }
+
+    debug!(
+        mask = format!("{:#06x}", wanted.mask()),
+        "Reconfiguring chip version mask"
+    );
+    chip_commands
+        .send(protocol::Command::WriteRegister {
+            broadcast: true,
+            chip_address: 0x00,
+            register: protocol::Register::VersionMask(wanted),
+        })
+        .await
+        .map_err(|e| {
+            HashThreadError::WorkAssignmentFailed(format!("Failed to send version mask: {:?}", e))
+        })?;
+    *chip_version_mask = Some(wanted);
+
+    Ok(())
+}
+
+/// Hashrate a chip should deliver at the given core frequency.
+fn theoretical_hashrate(chip: protocol::ChipType, frequency_mhz: f32) -> Option<HashRate> {
+    let hashes_per_clock = chip.hashes_per_clock()?;
+    Some(HashRate::from_megahashes(
+        frequency_mhz as f64 * hashes_per_clock as f64,
+    ))
+}
+
+/// When to send a task fresh header space, counting from a dispatch now.
+///
+/// Each chip is taken to hash at its measured rate or its rate at
+/// `frequency_mhz`, whichever is higher, so a chip that hasn't reported
+/// yet doesn't delay the refresh.
+fn header_space_refresh(
+    task: &HashTask,
+    chip_count: usize,
+    nonce_rates: Option<&mut ChipNonceRates>,
+    frequency_mhz: f32,
+    now: tokio::time::Instant,
+) -> Option<tokio::time::Instant> {
+    let theoretical =
+        theoretical_hashrate(CHIP_MODEL.chip_type, frequency_mhz).unwrap_or(HashRate(0));
+    let mut chip_rates = vec![theoretical; chip_count];
+    if let Some(rates) = nonce_rates {
+        for (rate, measured) in chip_rates
+            .iter_mut()
+            .zip(rates.chip_hashrates_at(now.into_std()))
+        {
+            if measured.0 > rate.0 {
+                *rate = measured;
+            }
+        }
+    }
+
+    let version_bits = version_mask_for(task).mask().count_ones();
+    nonce_space::exhaustion_time(version_bits, &chip_rates)
+        .map(|exhaustion| now + nonce_space::refresh_after(exhaustion))
+}
+
+/// Seed for a thread's dispatch phase: FNV-1a of its name.
+///
+/// Spelled out rather than taken from `std::hash`, whose output may
+/// change between Rust releases and, for `DefaultHasher`, runs.
+fn phase_seed(name: &str) -> u64 {
+    name.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Offset of a thread's ntime dispatches within the roll interval.
+///
+/// Derived from the thread name so that threads sharing a host don't all
+/// re-dispatch in the same instant, spreading serial bandwidth spikes
+/// across the interval. Stable across restarts for a given thread.
+fn dispatch_phase(seed: u64, interval: Duration) -> Duration {
+    let interval_ms = interval.as_millis().max(1) as u64;
+    Duration::from_millis(seed % interval_ms)
+}
+
+/// Ticker for ntime-rolled dispatches every `interval`, offset by the
+/// thread's phase.
+fn ntime_roll_ticker(interval: Duration, phase_seed: u64) -> tokio::time::Interval {
+    let mut ticker = tokio::time::interval_at(
+        tokio::time::Instant::now() + interval + dispatch_phase(phase_seed, interval),
+        interval,
+    );
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    ticker
+}
+
+/// ntime for a task whose base ntime was `base`, assigned `elapsed` ago.
+///
+/// Follows wall-clock time so chip timestamps stay fresh, but never
+/// advances more than `max_roll` seconds past the base.
+fn rolled_ntime(base: u32, elapsed: Duration, max_roll: u32) -> u32 {
+    let roll = elapsed.as_secs().min(max_roll as u64) as u32;
+    base.saturating_add(roll)
+}
+
 /// Convert HashTask to JobFullFormat for chip hardware.
 ///
 /// Extracts or computes the merkle root, then builds a JobFullFormat with all
@@ -620,6 +1117,26 @@ fn task_to_job_full(
     })
 }
 
+/// Send a job to the chain, timing how long the send takes.
+///
+/// The frame is broadcast: every chip on the chain takes it, so one send
+/// covers them all.
+async fn dispatch_job<W>(
+    chip_commands: &mut W,
+    job_data: protocol::JobFullFormat,
+    timing: &mut DispatchTiming,
+) -> Result<(), W::Error>
+where
+    W: Sink<protocol::Command> + Unpin,
+{
+    let start = tokio::time::Instant::now();
+    chip_commands
+        .send(protocol::Command::JobFull { job_data })
+        .await?;
+    timing.record(start.elapsed());
+    Ok(())
+}
+
 /// Calculate PLL configuration for a specific frequency
 fn calculate_pll_for_frequency(target_freq: f32) -> Option<protocol::PllConfig> {
     const CRYSTAL_FREQ: f32 = 25.0;
@@ -678,25 +1195,192 @@ fn calculate_pll_for_frequency(target_freq: f32) -> Option<protocol::PllConfig>
     ))
 }
 
+/// How far a nonce from the chip got through share filtering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NonceOutcome {
+    /// The hash fails the chip's own ticket difficulty under every job
+    /// the nonce could be for
+    HardwareError,
+    /// Below the task's share target, or for a job no longer known
+    Filtered,
+    /// Sent to the scheduler as a share; `pool_worthy` if it also meets
+    /// the pool's target
+    Forwarded { pool_worthy: bool },
+}
+
+/// Header hash of a nonce for one candidate task.
+struct CandidateHash {
+    task: HashTask,
+    late: bool,
+    /// Full block version and header hash; None if the merkle root could
+    /// not be computed
+    hashed: Option<(bitcoin::block::Version, bitcoin::BlockHash)>,
+}
+
+/// Hash `nonce` against each candidate task's header.
+///
+/// The CPU-bound part of nonce validation, run on the
+/// [`verify_pool`](crate::asic::verify_pool).
+fn hash_candidates(
+    candidates: Vec<(HashTask, bool)>,
+    nonce: u32,
+    version: crate::job_source::GeneralPurposeBits,
+) -> Vec<CandidateHash> {
+    candidates
+        .into_iter()
+        .map(|(task, late)| {
+            let template = task.template.as_ref();
+
+            // Reconstruct full version from rolling field
+            let full_version = version.apply_to_version(template.version.base());
+
+            // Compute merkle root for this task's EN2 and hash the header
+            let hashed = task
+                .en2
+                .as_ref()
+                .and_then(|en2| template.compute_merkle_root(en2).ok())
+                .map(|merkle_root| {
+                    let header = BlockHeader {
+                        version: full_version,
+                        prev_blockhash: template.prev_blockhash,
+                        merkle_root,
+                        time: task.ntime,
+                        bits: template.bits,
+                        nonce,
+                    };
+                    (full_version, header.block_hash())
+                });
+            CandidateHash { task, late, hashed }
+        })
+        .collect()
+}
+
+/// Validate a nonce from the chip and forward it as a share.
+///
+/// Rebuilds the block header from the task the chip was working on and
+/// sends a share on the task's channel if the hash meets its target.
+/// Nonces for unknown jobs can't be checked and count as valid. Hashing
+/// runs on the shared verify pool so it doesn't hold up the runtime.
+async fn process_nonce(
+    chip_jobs: &ChipJobTracker,
+    nonce: u32,
+    job_id: u8,
+    version: crate::job_source::GeneralPurposeBits,
+) -> NonceOutcome {
+    let candidates: Vec<_> = chip_jobs
+        .candidates(job_id, tokio::time::Instant::now())
+        .map(|(task, late)| (task.clone(), late))
+        .collect();
+    if candidates.is_empty() {
+        trace!(
+            chip_job_id = job_id,
+            nonce = format!("{:#x}", nonce),
+            "Nonce for unknown job_id (possibly stale)"
+        );
+        return NonceOutcome::Filtered;
+    }
+    let ticket_target = Difficulty::from(reporting_ticket_mask().difficulty()).to_target();
+    let mut valid = false;
+
+    let hashed = verify_pool::shared()
+        .run(move || hash_candidates(candidates, nonce, version))
+        .await;
+
+    // After a job ID is reused the nonce may be for either job in the
+    // slot; only the right header yields a hash that meets the target.
+    for CandidateHash { task, late, hashed } in hashed {
+        let Some((full_version, hash)) = hashed else {
+            error!(
+                chip_job_id = job_id,
+                "Failed to compute merkle root for nonce"
+            );
+            continue;
+        };
+        valid |= ticket_target.is_met_by(hash);
+
+        // Validate against task share target
+        if !task.share_target.is_met_by(hash) {
+            trace!(
+                chip_job_id = job_id,
+                late,
+                nonce = format!("{:#x}", nonce),
+                hash = %hash,
+                hash_diff = %Difficulty::from_hash(&hash),
+                target_diff = %Difficulty::from_target(task.share_target),
+                "Nonce does not meet target (filtered)"
+            );
+            continue;
+        }
+
+        let share = Share {
+            nonce,
+            hash,
+            version: full_version,
+            ntime: task.ntime,
+            extranonce2: task.en2,
+            expected_work: task.share_target.to_work(),
+        };
+
+        // Send via task's dedicated channel
+        if task.share_tx.send(share).await.is_err() {
+            // Channel closed = task replaced, share is stale
+            debug!("Share channel closed (task replaced)");
+        } else {
+            debug!(
+                chip_job_id = job_id,
+                late,
+                nonce = format!("{:#x}", nonce),
+                hash = %hash,
+                hash_diff = %Difficulty::from_hash(&hash),
+                target_diff = %Difficulty::from_target(task.share_target),
+                "Share found and sent"
+            );
+        }
+        return NonceOutcome::Forwarded {
+            pool_worthy: task.template.share_target.is_met_by(hash),
+        };
+    }
+
+    if !valid {
+        trace!(
+            chip_job_id = job_id,
+            nonce = format!("{:#x}", nonce),
+            "Nonce fails ticket difficulty (hardware error)"
+        );
+        return NonceOutcome::HardwareError;
+    }
+    NonceOutcome::Filtered
+}
+
 /// Internal actor task for BM13xxThread.
 ///
 /// This runs as an independent Tokio task and handles:
 /// - Commands from scheduler (update/replace work, go idle, shutdown)
-/// - Removal signal from board (USB unplug, fault, etc.)
+/// - Removal signal from board (USB unplug, fault, etc.), with cleanup
+///   depending on the reason
 /// - Chip initialization (lazy, on first work assignment)
 /// - Serial communication with chips
 /// - Share filtering and event emission (TODO)
 ///
 /// Chip is disabled on startup to establish known state. Chip is enabled and
 /// configured when scheduler assigns first work.
+#[expect(
+    clippy::too_many_arguments,
+    reason = "actor owns each of its channels and handles directly"
+)]
 async fn bm13xx_thread_actor<R, W>(
     mut cmd_rx: mpsc::Receiver<ThreadCommand>,
-    _evt_tx: mpsc::Sender<HashThreadEvent>,
+    evt_tx: mpsc::Sender<HashThreadEvent>,
     mut removal_rx: watch::Receiver<ThreadRemovalSignal>,
     status: Arc<RwLock<HashThreadStatus>>,
     mut chip_responses: R,
     mut chip_commands: W,
     mut peripherals: BoardPeripherals,
+    phase_seed: u64,
+    mut frequency_rx: watch::Receiver<FrequencyPlan>,
+    nonce_timeout_rx: watch::Receiver<Duration>,
+    mut ntime_roll_rx: watch::Receiver<Duration>,
+    link_stats_rx: watch::Receiver<Option<protocol::FrameCodec>>,
 ) where
     R: Stream<Item = Result<protocol::Response, std::io::Error>> + Unpin,
     W: Sink<protocol::Command> + Unpin,
@@ -710,12 +1394,62 @@ async fn bm13xx_thread_actor<R, W>(
     }
 
     let mut chip_initialized = false;
+    // Core clocks gated while idle; cleared by the next assignment
+    let mut low_power = false;
+    // Core frequency the chip is running at, once initialized, and the
+    // most it may run at (below target while warming up)
+    let mut target_mhz = frequency_rx.borrow().target_mhz;
+    let mut frequency_mhz = target_mhz;
+    let mut operating_mhz = target_mhz;
+    let mut thermal = frequency_rx.borrow().thermal;
+    let mut frequency_limiter =
+        FrequencyLimiter::new(frequency_rx.borrow_and_update().derating.clone())
+            .with_overshoot(thermal.overshoot_c);
+    // Temperature polls since slew last stepped the frequency
+    let mut ticks_since_adjust = 0u32;
+    // Polls since the chip last reported its temperature
+    let mut temperature_polls_unanswered = 0u32;
+    // With a slew limit, changes other than derating are left to the
+    // temperature poll to step towards
+    let mut slew = frequency_rx.borrow().slew;
+    let mut frequency_plan_open = true;
+    let mut warmup: Option<Warmup> = None;
+    let mut chip_version_mask: Option<protocol::VersionMask> = None;
     let mut current_task: Option<HashTask> = None;
+    // Base ntime and assignment time of the current task, for rolling
+    let mut ntime_base = (0u32, tokio::time::Instant::now());
+    let mut max_ntime_roll = MAX_NTIME_ROLL;
     let mut chip_jobs = ChipJobTracker::new();
-    let mut ntime_ticker = tokio::time::interval(tokio::time::Duration::from_secs(1));
-    ntime_ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    // Fresh extranonce2 values for the current task, and when its chips
+    // would next run out of header space
+    let mut en2_walk: Option<Extranonce2Walk> = None;
+    let mut en2_exhausted = false;
+    let mut space_refresh: Option<tokio::time::Instant> = None;
+    let mut duplicates = DuplicateWindow::new(DUPLICATE_WINDOW);
+    let mut job_watchdog = JobWatchdog::new(*nonce_timeout_rx.borrow());
+    let mut flap_damper = FlapDamper::new(DEFAULT_MAX_RESETS);
+    let mut link_guard = LinkGuard::new();
+    let mut job_dispatch = DispatchTiming::new();
+    // Created with the first nonce, once the chain length is settled
+    let mut nonce_rates: Option<ChipNonceRates> = None;
+    let mut nonce_map: Option<NonceMap> = None;
+    let mut nonce_latency: Option<NonceLatency> = None;
+    // Created with the first temperature poll, likewise
+    let mut pll_readback: Option<PllReadback> = None;
+    let mut ntime_ticker = ntime_roll_ticker(*ntime_roll_rx.borrow_and_update(), phase_seed);
+    let mut ntime_roll_open = true;
+    let mut temperature_ticker = tokio::time::interval(thermal.tick);
+    temperature_ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut removal: Option<ThreadRemovalSignal> = None;
+    // Register dump awaiting answers, and when to stop waiting for them
+    let mut register_dump: Option<(
+        RegisterDump,
+        oneshot::Sender<std::result::Result<RegisterDump, HashThreadError>>,
+    )> = None;
+    let mut register_dump_deadline = tokio::time::Instant::now();
 
     loop {
+        let watchdog_deadline = job_watchdog.deadline();
         tokio::select! {
             // Removal signal (highest priority)
             _ = removal_rx.changed() => {
@@ -724,7 +1458,7 @@ async fn bm13xx_thread_actor<R, W>(
                     ThreadRemovalSignal::Running => {
                         // False alarm - still running
                     }
-                    _reason => {
+                    reason => {
                         // Update status
                         {
                             let mut s = status.write().unwrap();
@@ -732,6 +1466,7 @@ async fn bm13xx_thread_actor<R, W>(
                         }
 
                         // Exit actor loop (channel closure signals removal to scheduler)
+                        removal = Some(reason);
                         break;
                     }
                 }
@@ -753,20 +1488,50 @@ async fn bm13xx_thread_actor<R, W>(
 
                         if !chip_initialized {
                             trace!("Initializing chip on first assignment.");
-                            if let Err(e) = initialize_chip(&mut chip_commands, &mut peripherals).await {
+                            warmup = frequency_rx.borrow().warmup.clone().map(|config| {
+                                Warmup::new(config, target_mhz, tokio::time::Instant::now())
+                            });
+                            operating_mhz = warmup.as_ref().map_or(target_mhz, Warmup::frequency);
+                            if let Err(e) = initialize_chip(&mut chip_commands, &mut chip_responses, &mut peripherals, operating_mhz).await {
                                 error!(error = %e, "Chip initialization failed");
                                 response_tx.send(Err(e)).ok();
                                 continue;
                             }
                             chip_initialized = true;
+                            frequency_mhz = operating_mhz;
+                            chip_version_mask = Some(protocol::VersionMask::full_rolling());
+                            status.write().unwrap().chip_difficulty = Some(Difficulty::from(reporting_ticket_mask().difficulty()));
+                            publish_power_state(&peripherals, ChipPowerState::Hashing);
+                        }
+
+                        if low_power {
+                            let to_mhz = frequency_limiter.ceiling().map_or(operating_mhz, |max| max.min(operating_mhz));
+                            if let Err(e) = leave_low_power(&mut chip_commands, &mut frequency_mhz, to_mhz).await {
+                                error!(error = ?e, "Failed to wake chip from low power");
+                                response_tx.send(Err(HashThreadError::WorkAssignmentFailed(
+                                    format!("Failed to wake chip: {:?}", e)
+                                ))).ok();
+                                continue;
+                            }
+                            low_power = false;
+                            publish_power_state(&peripherals, ChipPowerState::Hashing);
+                        }
+
+                        if let Err(e) = sync_version_mask(&mut chip_commands, &mut chip_version_mask, &new_task).await {
+                            error!(error = %e, "Failed to update chip version mask");
+                            response_tx.send(Err(e)).ok();
+                            continue;
                         }
 
                         // Send initial job to chip
-                        let chip_job_id = chip_jobs.insert(new_task.clone());
+                        let chip_job_id = chip_jobs.insert(new_task.clone(), tokio::time::Instant::now());
+                        ntime_base = (new_task.ntime, tokio::time::Instant::now());
+                        en2_walk = new_task.en2_range.as_ref().zip(new_task.en2.as_ref()).map(|(range, en2)| Extranonce2Walk::new(range, en2));
+                        en2_exhausted = false;
                         let old_task = current_task.replace(new_task.clone());
                         match task_to_job_full(&new_task, chip_job_id) {
                             Ok(job_data) => {
-                                if let Err(e) = chip_commands.send(protocol::Command::JobFull { job_data }).await {
+                                if let Err(e) = dispatch_job(&mut chip_commands, job_data, &mut job_dispatch).await {
                                     error!(error = ?e, "Failed to send initial JobFull to chip");
                                     response_tx.send(Err(HashThreadError::WorkAssignmentFailed(
                                         format!("Failed to send job to chip: {:?}", e)
@@ -774,6 +1539,10 @@ async fn bm13xx_thread_actor<R, W>(
                                     continue;
                                 } else {
                                     debug!("Sent initial job to chip");
+                                    job_watchdog.set_timeout(*nonce_timeout_rx.borrow());
+                                    job_watchdog.arm(tokio::time::Instant::now());
+                                    let chip_count = status.read().unwrap().chips.len();
+                                    space_refresh = header_space_refresh(&new_task, chip_count, nonce_rates.as_mut(), frequency_mhz, tokio::time::Instant::now());
                                 }
                             }
                             Err(e) => {
@@ -786,6 +1555,8 @@ async fn bm13xx_thread_actor<R, W>(
                         {
                             let mut s = status.write().unwrap();
                             s.is_active = true;
+                            s.share_target = Some(new_task.share_target);
+                            s.pool_target = Some(new_task.template.share_target);
                         }
 
                         response_tx.send(Ok(old_task)).ok();
@@ -804,23 +1575,53 @@ async fn bm13xx_thread_actor<R, W>(
 
                         if !chip_initialized {
                             trace!("Initializing chip on first assignment.");
-                            if let Err(e) = initialize_chip(&mut chip_commands, &mut peripherals).await {
+                            warmup = frequency_rx.borrow().warmup.clone().map(|config| {
+                                Warmup::new(config, target_mhz, tokio::time::Instant::now())
+                            });
+                            operating_mhz = warmup.as_ref().map_or(target_mhz, Warmup::frequency);
+                            if let Err(e) = initialize_chip(&mut chip_commands, &mut chip_responses, &mut peripherals, operating_mhz).await {
                                 error!(error = %e, "Chip initialization failed");
                                 response_tx.send(Err(e)).ok();
                                 continue;
                             }
                             chip_initialized = true;
+                            frequency_mhz = operating_mhz;
+                            chip_version_mask = Some(protocol::VersionMask::full_rolling());
+                            status.write().unwrap().chip_difficulty = Some(Difficulty::from(reporting_ticket_mask().difficulty()));
+                            publish_power_state(&peripherals, ChipPowerState::Hashing);
+                        }
+
+                        if low_power {
+                            let to_mhz = frequency_limiter.ceiling().map_or(operating_mhz, |max| max.min(operating_mhz));
+                            if let Err(e) = leave_low_power(&mut chip_commands, &mut frequency_mhz, to_mhz).await {
+                                error!(error = ?e, "Failed to wake chip from low power");
+                                response_tx.send(Err(HashThreadError::WorkAssignmentFailed(
+                                    format!("Failed to wake chip: {:?}", e)
+                                ))).ok();
+                                continue;
+                            }
+                            low_power = false;
+                            publish_power_state(&peripherals, ChipPowerState::Hashing);
+                        }
+
+                        if let Err(e) = sync_version_mask(&mut chip_commands, &mut chip_version_mask, &new_task).await {
+                            error!(error = %e, "Failed to update chip version mask");
+                            response_tx.send(Err(e)).ok();
+                            continue;
                         }
 
                         // Clear old jobs (old shares invalid)
                         chip_jobs.clear();
 
                         // Send initial job to chip
-                        let chip_job_id = chip_jobs.insert(new_task.clone());
+                        let chip_job_id = chip_jobs.insert(new_task.clone(), tokio::time::Instant::now());
+                        ntime_base = (new_task.ntime, tokio::time::Instant::now());
+                        en2_walk = new_task.en2_range.as_ref().zip(new_task.en2.as_ref()).map(|(range, en2)| Extranonce2Walk::new(range, en2));
+                        en2_exhausted = false;
                         let old_task = current_task.replace(new_task.clone());
                         match task_to_job_full(&new_task, chip_job_id) {
                             Ok(job_data) => {
-                                if let Err(e) = chip_commands.send(protocol::Command::JobFull { job_data }).await {
+                                if let Err(e) = dispatch_job(&mut chip_commands, job_data, &mut job_dispatch).await {
                                     error!(error = ?e, "Failed to send initial JobFull to chip");
                                     response_tx.send(Err(HashThreadError::WorkAssignmentFailed(
                                         format!("Failed to send job to chip: {:?}", e)
@@ -828,6 +1629,10 @@ async fn bm13xx_thread_actor<R, W>(
                                     continue;
                                 } else {
                                     debug!("Sent initial job to chip (old work invalidated)");
+                                    job_watchdog.set_timeout(*nonce_timeout_rx.borrow());
+                                    job_watchdog.arm(tokio::time::Instant::now());
+                                    let chip_count = status.read().unwrap().chips.len();
+                                    space_refresh = header_space_refresh(&new_task, chip_count, nonce_rates.as_mut(), frequency_mhz, tokio::time::Instant::now());
                                 }
                             }
                             Err(e) => {
@@ -840,24 +1645,75 @@ async fn bm13xx_thread_actor<R, W>(
                         {
                             let mut s = status.write().unwrap();
                             s.is_active = true;
+                            s.share_target = Some(new_task.share_target);
+                            s.pool_target = Some(new_task.template.share_target);
                         }
 
                         response_tx.send(Ok(old_task)).ok();
                     }
 
+                    ThreadCommand::Negotiate { params } => {
+                        max_ntime_roll = params.max_ntime_roll.min(MAX_NTIME_ROLL);
+                        debug!(max_ntime_roll, "Assignment parameters accepted");
+                    }
+
                     ThreadCommand::GoIdle { response_tx } => {
                         debug!("Going idle");
 
                         let old_task = current_task.take();
+                        job_watchdog.disarm();
+                        space_refresh = None;
+
+                        if chip_initialized && !low_power {
+                            match enter_low_power(&mut chip_commands, &mut frequency_mhz).await {
+                                Ok(()) => {
+                                    low_power = true;
+                                    publish_power_state(&peripherals, ChipPowerState::LowPower);
+                                    debug!(frequency_mhz, "Core clocks gated");
+                                }
+                                Err(e) => error!(error = ?e, "Failed to put chip in low power"),
+                            }
+                        }
 
                         {
                             let mut s = status.write().unwrap();
                             s.is_active = false;
+                            s.share_target = None;
+                            s.pool_target = None;
                         }
 
                         response_tx.send(Ok(old_task)).ok();
                     }
 
+                    ThreadCommand::DumpRegisters { chip_address, diff, response_tx } => {
+                        if !chip_initialized {
+                            response_tx.send(Err(HashThreadError::RegisterAccess("chip not initialized".into()))).ok();
+                            continue;
+                        }
+                        if register_dump.is_some() {
+                            response_tx.send(Err(HashThreadError::RegisterAccess("register dump already in progress".into()))).ok();
+                            continue;
+                        }
+
+                        let expected = diff.then(|| expected_registers(frequency_mhz, chip_version_mask));
+                        let dump = RegisterDump::new(chip_address, expected);
+                        let mut failed = None;
+                        for command in dump.commands() {
+                            if let Err(e) = chip_commands.send(command).await {
+                                failed = Some(format!("{e:?}"));
+                                break;
+                            }
+                        }
+                        if let Some(e) = failed {
+                            response_tx.send(Err(HashThreadError::RegisterAccess(format!("read failed: {e}")))).ok();
+                            continue;
+                        }
+
+                        debug!(chip_address = %format!("0x{:02x}", chip_address), "Dumping registers");
+                        register_dump_deadline = tokio::time::Instant::now() + REGISTER_DUMP_TIMEOUT;
+                        register_dump = Some((dump, response_tx));
+                    }
+
                     ThreadCommand::Shutdown => {
                         info!("Shutdown command received");
                         // Exit actor loop (channel closure signals shutdown to scheduler)
@@ -872,85 +1728,124 @@ async fn bm13xx_thread_actor<R, W>(
                     Ok(response) => {
                         match response {
                             protocol::Response::Nonce { nonce, job_id, version, midstate_num, subcore_id } => {
-                                // Look up the task for this job_id
-                                if let Some(task) = chip_jobs.get(job_id) {
-                                    let template = task.template.as_ref();
-
-                                    // Reconstruct full version from rolling field
-                                    let full_version = version.apply_to_version(template.version.base());
-
-                                    // Compute merkle root for this task's EN2
-                                    match task.en2.as_ref().and_then(|en2| template.compute_merkle_root(en2).ok()) {
-                                        Some(merkle_root) => {
-                                            // Build block header
-                                            let header = BlockHeader {
-                                                version: full_version,
-                                                prev_blockhash: template.prev_blockhash,
-                                                merkle_root,
-                                                time: task.ntime,
-                                                bits: template.bits,
-                                                nonce,
-                                            };
-
-                                            // Compute hash
-                                            let hash = header.block_hash();
-
-                                            // Validate against task share target
-                                            if task.share_target.is_met_by(hash) {
-                                                let share = Share {
-                                                    nonce,
-                                                    hash,
-                                                    version: full_version,
-                                                    ntime: task.ntime,
-                                                    extranonce2: task.en2,
-                                                    expected_work: task.share_target.to_work(),
-                                                };
-
-                                                // Send via task's dedicated channel
-                                                if task.share_tx.send(share).await.is_err() {
-                                                    // Channel closed = task replaced, share is stale
-                                                    debug!("Share channel closed (task replaced)");
-                                                } else {
-                                                    debug!(
-                                                        chip_job_id = job_id,
-                                                        nonce = format!("{:#x}", nonce),
-                                                        hash = %hash,
-                                                        hash_diff = %Difficulty::from_hash(&hash),
-                                                        target_diff = %Difficulty::from_target(task.share_target),
-                                                        "Share found and sent"
-                                                    );
-                                                }
-                                            } else {
-                                                trace!(
-                                                    chip_job_id = job_id,
-                                                    nonce = format!("{:#x}", nonce),
-                                                    hash = %hash,
-                                                    hash_diff = %Difficulty::from_hash(&hash),
-                                                    target_diff = %Difficulty::from_target(task.share_target),
-                                                    "Nonce does not meet target (filtered)"
-                                                );
-                                            }
+                                job_watchdog.heard();
+                                if duplicates.is_repeat(job_id, nonce, version) {
+                                    // The chip wrapped its nonce counter
+                                    // before fresh work reached it
+                                    trace!(chip_job_id = job_id, nonce = format!("{:#x}", nonce), "Duplicate nonce");
+                                    status.write().unwrap().duplicate_nonces += 1;
+                                    continue;
+                                }
+                                if let Some(ref mut warmup) = warmup {
+                                    warmup.record_nonce();
+                                }
+                                nonce_rates
+                                    .get_or_insert_with(|| {
+                                        let chip_count = status.read().unwrap().chips.len();
+                                        ChipNonceRates::new(chip_count, Difficulty::from(reporting_ticket_mask().difficulty()))
+                                    })
+                                    .record_at(tokio::time::Instant::now().into_std(), nonce);
+                                nonce_map
+                                    .get_or_insert_with(|| NonceMap::new(status.read().unwrap().chips.len()))
+                                    .record(nonce, subcore_id);
+                                let now = tokio::time::Instant::now();
+                                if let Some(sent_at) = chip_jobs.sent_at(job_id, now) {
+                                    let alert = nonce_latency
+                                        .get_or_insert_with(|| NonceLatency::new(status.read().unwrap().chips.len()))
+                                        .record(nonce, job_id, sent_at, now);
+                                    match alert {
+                                        Some(LatencyAlert::Rising { chip, recent_ms, baseline_ms }) => {
+                                            warn!(chip, recent_ms = recent_ms.round(), baseline_ms = baseline_ms.round(), "Chip slow to answer new jobs, link congested or chip degrading");
                                         }
-                                        None => {
-                                            error!(
-                                                chip_job_id = job_id,
-                                                "Failed to compute merkle root for nonce"
-                                            );
+                                        Some(LatencyAlert::Settled { chip }) => {
+                                            info!(chip, "Chip answering new jobs as usual again");
                                         }
+                                        None => {}
                                     }
-                                } else {
-                                    trace!(
-                                        chip_job_id = job_id,
-                                        nonce = format!("{:#x}", nonce),
-                                        "Nonce for unknown job_id (possibly stale)"
-                                    );
                                 }
-
-                                let _ = (midstate_num, subcore_id); // Unused for now
+                                status.write().unwrap().chip_shares_found += 1;
+                                match process_nonce(&chip_jobs, nonce, job_id, version).await {
+                                    NonceOutcome::HardwareError => status.write().unwrap().hardware_errors += 1,
+                                    NonceOutcome::Filtered => {}
+                                    NonceOutcome::Forwarded { pool_worthy } => {
+                                        let mut s = status.write().unwrap();
+                                        s.shares_forwarded += 1;
+                                        if pool_worthy {
+                                            s.pool_shares_submitted += 1;
+                                        }
+                                    }
+                                }
+                                let _ = midstate_num; // Unused for now
                             }
 
                             protocol::Response::ReadRegister { chip_address, register } => {
                                 trace!(chip_address = %format!("0x{:02x}", chip_address), register = ?register, "Register read response");
+
+                                // Answers to a register dump would be taken for the poll's
+                                if let protocol::Register::PllDivider(config) = register
+                                    && register_dump.is_none()
+                                    && let Some(ref mut readback) = pll_readback
+                                {
+                                    match readback.record(config, frequency_mhz) {
+                                        Some(PllAlert::Mismatch { chip, read_mhz, written_mhz }) => {
+                                            warn!(chip, read_mhz, written_mhz, "Chip not running at the frequency it was given, it may have reset");
+                                            status.write().unwrap().frequency_mismatches = readback.mismatches();
+                                            if record_reset(&mut flap_damper, &status) {
+                                                operating_mhz = flap_damper.cap(operating_mhz);
+                                                if !low_power {
+                                                    let to_mhz = frequency_limiter.ceiling().map_or(operating_mhz, |max| max.min(operating_mhz));
+                                                    if let Err(e) = retune_frequency(&mut chip_commands, &mut frequency_mhz, to_mhz).await {
+                                                        error!(error = ?e, "Failed to retune core frequency");
+                                                    }
+                                                }
+                                            }
+                                        }
+                                        Some(PllAlert::Recovered { chip, mhz }) => {
+                                            info!(chip, mhz, "Chip back at the frequency it was given");
+                                        }
+                                        None => {}
+                                    }
+                                }
+
+                                if let Some((ref mut dump, _)) = register_dump {
+                                    dump.record(chip_address, &register);
+                                    if dump.is_complete()
+                                        && let Some((dump, response_tx)) = register_dump.take()
+                                    {
+                                        response_tx.send(Ok(dump)).ok();
+                                    }
+                                }
+
+                                if let Some(temperature_c) = protocol::chip_temperature(&register) {
+                                    if temperature_polls_unanswered >= STALE_TICKS {
+                                        info!(temperature_c, "Chip temperature readings resumed");
+                                    }
+                                    temperature_polls_unanswered = 0;
+                                    status.write().unwrap().temperature_c = Some(temperature_c);
+                                    if let Some(ref tx) = peripherals.chip_temperature {
+                                        tx.publish(temperature_c);
+                                    }
+
+                                    if let Some(ref mut warmup) = warmup {
+                                        warmup.record_temperature(temperature_c);
+                                    }
+
+                                    let ambient_c = sensors::shared().ambient_c(tokio::time::Instant::now());
+                                    let ceiling = frequency_limiter
+                                        .update(temperature_c + thermal.ambient_offset_c(ambient_c))
+                                        .map_or(operating_mhz, |max| max.min(operating_mhz));
+                                    let slewed = slew.is_some() && ceiling > frequency_mhz;
+                                    if chip_initialized && !low_power && ceiling != frequency_mhz && !slewed {
+                                        if ceiling < frequency_mhz {
+                                            warn!(temperature_c, from_mhz = frequency_mhz, to_mhz = ceiling, "Derating core frequency");
+                                        } else {
+                                            info!(temperature_c, from_mhz = frequency_mhz, to_mhz = ceiling, "Restoring core frequency");
+                                        }
+                                        if let Err(e) = retune_frequency(&mut chip_commands, &mut frequency_mhz, ceiling).await {
+                                            error!(error = ?e, "Failed to retune core frequency");
+                                        }
+                                    }
+                                }
                             }
                         }
                     }
@@ -962,36 +1857,1725 @@ async fn bm13xx_thread_actor<R, W>(
                 }
             }
 
-            // ntime rolling timer (roll forward every second)
-            _ = ntime_ticker.tick(), if current_task.is_some() => {
-                let task = current_task.as_mut().unwrap();
+            // Frequency plan changes from the builder or the board
+            result = frequency_rx.changed(), if frequency_plan_open => {
+                if result.is_err() {
+                    frequency_plan_open = false;
+                    continue;
+                }
 
-                // Increment ntime
-                task.ntime += 1;
+                let plan = frequency_rx.borrow_and_update().clone();
+                if plan.derating != *frequency_limiter.curve() || plan.thermal.overshoot_c != frequency_limiter.overshoot_c() {
+                    frequency_limiter = FrequencyLimiter::new(plan.derating).with_overshoot(plan.thermal.overshoot_c);
+                }
+                if plan.thermal.tick != thermal.tick {
+                    temperature_ticker = tokio::time::interval(plan.thermal.tick);
+                    temperature_ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                }
+                thermal = plan.thermal;
+                slew = plan.slew;
+                if plan.target_mhz == target_mhz {
+                    continue;
+                }
 
-                // Convert to chip format and send
-                match task_to_job_full(task, chip_jobs.insert(task.clone())) {
+                info!(from_mhz = target_mhz, to_mhz = plan.target_mhz, "Target frequency changed");
+                target_mhz = plan.target_mhz;
+                if warmup.take().is_some() {
+                    info!("Warm-up ended by target change");
+                }
+                operating_mhz = link_guard.cap(flap_damper.cap(target_mhz));
+
+                if chip_initialized && !low_power && slew.is_none() {
+                    let to_mhz = frequency_limiter.ceiling().map_or(operating_mhz, |max| max.min(operating_mhz));
+                    if let Err(e) = retune_frequency(&mut chip_commands, &mut frequency_mhz, to_mhz).await {
+                        error!(error = ?e, "Failed to retune core frequency");
+                    }
+                }
+            }
+
+            // Registers that never answered a dump are left empty
+            _ = tokio::time::sleep_until(register_dump_deadline), if register_dump.is_some() => {
+                if let Some((dump, response_tx)) = register_dump.take() {
+                    debug!("Register dump incomplete, returning what was read");
+                    response_tx.send(Ok(dump)).ok();
+                }
+            }
+
+            // No nonce since the task was sent: the job frame may have been
+            // lost, or the chip may have stopped
+            _ = tokio::time::sleep_until(watchdog_deadline.unwrap_or_else(tokio::time::Instant::now)), if watchdog_deadline.is_some() => {
+                let Some(action) = job_watchdog.expire(tokio::time::Instant::now()) else {
+                    continue;
+                };
+                let Some(ref task) = current_task else {
+                    job_watchdog.disarm();
+                    continue;
+                };
+
+                if action == WatchdogAction::Reset && flap_damper.is_quarantined() {
+                    warn!(job = %task.template.id, "No nonces after resending job, chip quarantined, resending again");
+                } else if action == WatchdogAction::Reset {
+                    warn!(job = %task.template.id, resets = job_watchdog.resets(), "No nonces after resending job, re-initializing chip");
+                    if record_reset(&mut flap_damper, &status) {
+                        operating_mhz = flap_damper.cap(operating_mhz);
+                    }
+                    if let Err(e) = initialize_chip(&mut chip_commands, &mut chip_responses, &mut peripherals, operating_mhz).await {
+                        error!(error = %e, "Chip re-initialization failed");
+                        job_watchdog.rearm(tokio::time::Instant::now());
+                        continue;
+                    }
+                    frequency_mhz = operating_mhz;
+                    // Initialization brings the link up to full speed
+                    if link_guard.is_degraded()
+                        && let Err(e) = set_link_speed(&mut chip_commands, &mut peripherals, true).await
+                    {
+                        error!(error = %e, "Failed to lower chip link baud rate");
+                    }
+                    if let Some(ref mut readback) = pll_readback {
+                        readback.reset();
+                    }
+                    chip_version_mask = Some(protocol::VersionMask::full_rolling());
+                    if let Err(e) = sync_version_mask(&mut chip_commands, &mut chip_version_mask, task).await {
+                        error!(error = %e, "Failed to update chip version mask");
+                        continue;
+                    }
+                } else {
+                    warn!(job = %task.template.id, resends = job_watchdog.resends(), "No nonces since job was sent, resending");
+                }
+
+                match task_to_job_full(task, chip_jobs.insert(task.clone(), tokio::time::Instant::now())) {
                     Ok(job_data) => {
-                        if let Err(e) = chip_commands.send(protocol::Command::JobFull { job_data }).await {
-                            error!(error = ?e, "Failed to send JobFull to chip");
-                        } else {
-                            trace!(ntime = task.ntime, "Sent ntime-rolled job to chip");
+                        if let Err(e) = dispatch_job(&mut chip_commands, job_data, &mut job_dispatch).await {
+                            error!(error = ?e, "Failed to resend JobFull to chip");
                         }
+                        job_watchdog.rearm(tokio::time::Instant::now());
+                        let chip_count = status.read().unwrap().chips.len();
+                        space_refresh = header_space_refresh(task, chip_count, nonce_rates.as_mut(), frequency_mhz, tokio::time::Instant::now());
                     }
                     Err(e) => {
                         error!(error = %e, "Failed to convert task to JobFull");
                     }
                 }
             }
-        }
-    }
 
-    debug!("BM13xx thread actor exiting");
-}
-
-#[cfg(test)]
+            // On-die temperature sensor poll (response handled above)
+            _ = temperature_ticker.tick(), if chip_initialized => {
+                if let Err(e) = chip_commands.send(protocol::BM13xxProtocol::read_temperature(0x00)).await {
+                    warn!(error = ?e, "Failed to request chip temperature");
+                }
+                temperature_polls_unanswered += 1;
+                if temperature_polls_unanswered == STALE_TICKS {
+                    // Acting on the last reading forever is worse than
+                    // assuming the chip is as hot as the curve allows for
+                    status.write().unwrap().temperature_c = None;
+                    warn!(polls = temperature_polls_unanswered, "Chip temperature readings stopped");
+                    if let Some(max) = frequency_limiter.blind()
+                        && !low_power
+                        && max.min(operating_mhz) < frequency_mhz
+                    {
+                        let to_mhz = max.min(operating_mhz);
+                        warn!(from_mhz = frequency_mhz, to_mhz, "Derating core frequency");
+                        if let Err(e) = retune_frequency(&mut chip_commands, &mut frequency_mhz, to_mhz).await {
+                            error!(error = ?e, "Failed to retune core frequency");
+                        }
+                    }
+                }
+                let readback = pll_readback.get_or_insert_with(|| PllReadback::new(status.read().unwrap().chips.len()));
+                match chip_commands.send(protocol::BM13xxProtocol::read_pll()).await {
+                    Ok(()) => readback.requested(frequency_mhz),
+                    Err(e) => warn!(error = ?e, "Failed to request chip PLL readback"),
+                }
+
+                // Nonce rates are refreshed on the same cadence
+                if let Some(ref map) = nonce_map {
+                    let mut reports = map.reports();
+                    if let Some(ref latency) = nonce_latency {
+                        for (report, latency) in reports.iter_mut().zip(latency.reports()) {
+                            report.latency = latency;
+                        }
+                    }
+                    status.write().unwrap().nonce_map = reports;
+                }
+                if job_dispatch.dispatches() > 0
+                    && let Some(ref tx) = peripherals.job_dispatch
+                {
+                    let chip_count = status.read().unwrap().chips.len();
+                    let baud_rate = link_baud_rate(&peripherals, link_guard.is_degraded());
+                    tx.send_replace(Some(job_dispatch.report(chip_count, baud_rate)));
+                }
+                if let Some(ref mut rates) = nonce_rates {
+                    publish_chip_stats(rates, &status, &evt_tx, &peripherals, frequency_mhz, pll_readback.as_ref());
+                }
+
+                if flap_damper.release(tokio::time::Instant::now()) {
+                    info!("No chip resets for an hour, quarantine lifted");
+                    status.write().unwrap().quarantined = false;
+                    if warmup.is_none() {
+                        operating_mhz = link_guard.cap(target_mhz);
+                    }
+                    if !low_power && slew.is_none() {
+                        let to_mhz = frequency_limiter.ceiling().map_or(operating_mhz, |max| max.min(operating_mhz));
+                        if let Err(e) = retune_frequency(&mut chip_commands, &mut frequency_mhz, to_mhz).await {
+                            error!(error = ?e, "Failed to retune core frequency");
+                        }
+                    }
+                }
+
+                // Link errors likewise, once the chips are up
+                let link_stats = link_stats_rx.borrow().as_ref().map(protocol::FrameCodec::stats);
+                if let Some(stats) = link_stats
+                    && chip_initialized
+                {
+                    status.write().unwrap().link_errors = stats.crc_errors + stats.decode_errors;
+                    let action = link_guard.check(stats, tokio::time::Instant::now());
+                    match action {
+                        Some(LinkAction::Degrade) => {
+                            warn!(degradations = link_guard.degradations(), "Chip link errors spiking, dropping to reduced baud rate and frequency");
+                            operating_mhz = link_guard.cap(operating_mhz);
+                        }
+                        Some(LinkAction::Probe) => {
+                            info!("Probing chip link at full speed");
+                            if warmup.is_none() {
+                                operating_mhz = flap_damper.cap(target_mhz);
+                            }
+                        }
+                        Some(LinkAction::Restored) => info!("Chip link clean at full speed, leaving degraded mode"),
+                        None => {}
+                    }
+                    if matches!(action, Some(LinkAction::Degrade | LinkAction::Probe)) {
+                        status.write().unwrap().link_degraded = link_guard.is_degraded();
+                        if let Err(e) = set_link_speed(&mut chip_commands, &mut peripherals, link_guard.is_degraded()).await {
+                            error!(error = %e, "Failed to change chip link baud rate");
+                        }
+                        if !low_power && slew.is_none() {
+                            let to_mhz = frequency_limiter.ceiling().map_or(operating_mhz, |max| max.min(operating_mhz));
+                            if let Err(e) = retune_frequency(&mut chip_commands, &mut frequency_mhz, to_mhz).await {
+                                error!(error = ?e, "Failed to retune core frequency");
+                            }
+                        }
+                    }
+                }
+
+                // Warm-up stages are checked on the same cadence, and
+                // wait while the chip idles
+                if low_power {
+                    continue;
+                }
+                ticks_since_adjust += 1;
+                if let Some(ref slew) = slew
+                    && ticks_since_adjust >= thermal.ticks_per_adjustment()
+                {
+                    ticks_since_adjust = 0;
+                    let to_mhz = frequency_limiter.ceiling().map_or(operating_mhz, |max| max.min(operating_mhz));
+                    let next_mhz = slew.frequency_step(frequency_mhz, to_mhz, thermal.adjustment_period());
+                    if next_mhz != frequency_mhz {
+                        debug!(from_mhz = frequency_mhz, to_mhz = next_mhz, target_mhz = to_mhz, "Slewing core frequency");
+                        if let Err(e) = retune_frequency(&mut chip_commands, &mut frequency_mhz, next_mhz).await {
+                            error!(error = ?e, "Failed to retune core frequency");
+                        }
+                    }
+                }
+                if let Some(step) = warmup.as_mut().map(|w| w.poll(tokio::time::Instant::now())) {
+                    match step {
+                        WarmupStep::Hold => continue,
+                        WarmupStep::Advance(next_mhz) => {
+                            info!(frequency_mhz = next_mhz, "Warm-up stage passed");
+                            operating_mhz = next_mhz;
+                        }
+                        WarmupStep::Done => {
+                            info!(frequency_mhz = operating_mhz, "Warm-up complete");
+                            warmup = None;
+                        }
+                        WarmupStep::Abort { frequency_mhz: stable_mhz, reason } => {
+                            warn!(reason, frequency_mhz = stable_mhz, "Warm-up aborted, holding frequency");
+                            operating_mhz = stable_mhz;
+                            warmup = None;
+                        }
+                    }
+                    if slew.is_some() {
+                        continue;
+                    }
+
+                    let to_mhz = frequency_limiter.ceiling().map_or(operating_mhz, |max| max.min(operating_mhz));
+                    if let Err(e) = retune_frequency(&mut chip_commands, &mut frequency_mhz, to_mhz).await {
+                        error!(error = ?e, "Failed to retune core frequency");
+                    }
+                }
+            }
+
+            result = ntime_roll_rx.changed(), if ntime_roll_open => {
+                if result.is_err() {
+                    ntime_roll_open = false;
+                    continue;
+                }
+                let interval = *ntime_roll_rx.borrow_and_update();
+                debug!(interval = ?interval, "ntime roll interval changed");
+                ntime_ticker = ntime_roll_ticker(interval, phase_seed);
+            }
+
+            // ntime rolling timer (staggered per thread)
+            _ = ntime_ticker.tick(), if current_task.is_some() => {
+                let task = current_task.as_mut().unwrap();
+
+                let (base, assigned_at) = ntime_base;
+                let ntime = rolled_ntime(base, assigned_at.elapsed(), max_ntime_roll);
+                if ntime == task.ntime {
+                    // Roll limit reached; keep hashing the last dispatch
+                    // rather than re-sending identical work.
+                    continue;
+                }
+                task.ntime = ntime;
+
+                // Convert to chip format and send
+                match task_to_job_full(task, chip_jobs.insert(task.clone(), tokio::time::Instant::now())) {
+                    Ok(job_data) => {
+                        if let Err(e) = dispatch_job(&mut chip_commands, job_data, &mut job_dispatch).await {
+                            error!(error = ?e, "Failed to send JobFull to chip");
+                        } else {
+                            trace!(ntime = task.ntime, "Sent ntime-rolled job to chip");
+                            let chip_count = status.read().unwrap().chips.len();
+                            space_refresh = header_space_refresh(task, chip_count, nonce_rates.as_mut(), frequency_mhz, tokio::time::Instant::now());
+                        }
+                    }
+                    Err(e) => {
+                        error!(error = %e, "Failed to convert task to JobFull");
+                    }
+                }
+            }
+
+            // Chips about to run out of header space before the next ntime
+            // roll refreshes it: move on to the next extranonce2
+            _ = tokio::time::sleep_until(space_refresh.unwrap_or_else(tokio::time::Instant::now)), if space_refresh.is_some() && current_task.is_some() => {
+                space_refresh = None;
+                let task = current_task.as_mut().unwrap();
+
+                let Some(en2) = en2_walk.as_mut().and_then(Iterator::next) else {
+                    // Nothing left to move on to; the chips wrap and
+                    // repeat until new work arrives
+                    if !en2_exhausted {
+                        en2_exhausted = true;
+                        let en2_searched = en2_walk.as_ref().map_or(1, Extranonce2Walk::searched);
+                        debug!(job = %task.template.id, en2_searched, "Extranonce2 range exhausted");
+                        evt_tx.send(HashThreadEvent::WorkExhausted { en2_searched }).await.ok();
+                    }
+                    continue;
+                };
+                task.en2 = Some(en2);
+
+                match task_to_job_full(task, chip_jobs.insert(task.clone(), tokio::time::Instant::now())) {
+                    Ok(job_data) => {
+                        if let Err(e) = dispatch_job(&mut chip_commands, job_data, &mut job_dispatch).await {
+                            error!(error = ?e, "Failed to send JobFull to chip");
+                        } else {
+                            trace!(en2 = en2.value(), "Sent job with fresh extranonce2 to chip");
+                            let chip_count = status.read().unwrap().chips.len();
+                            space_refresh = header_space_refresh(task, chip_count, nonce_rates.as_mut(), frequency_mhz, tokio::time::Instant::now());
+                        }
+                    }
+                    Err(e) => {
+                        error!(error = %e, "Failed to convert task to JobFull");
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(reason) = removal {
+        match reason {
+            ThreadRemovalSignal::Running => unreachable!("loop only exits on removal"),
+
+            ThreadRemovalSignal::BoardDisconnected => {
+                // Nothing left on the other end of the link to talk to
+                info!("Board disconnected, thread going offline");
+            }
+
+            ThreadRemovalSignal::HardwareFault { description } => {
+                error!(fault = %description, "Hardware fault, powering down chip");
+                power_down_chip(
+                    &mut chip_commands,
+                    &mut peripherals,
+                    chip_initialized,
+                    &mut frequency_mhz,
+                )
+                .await;
+            }
+
+            ThreadRemovalSignal::UserRequested => {
+                let drained = drain_shares(&mut chip_responses, &chip_jobs).await;
+                info!(drained, "Thread disabled by user");
+                power_down_chip(
+                    &mut chip_commands,
+                    &mut peripherals,
+                    chip_initialized,
+                    &mut frequency_mhz,
+                )
+                .await;
+            }
+
+            ThreadRemovalSignal::Shutdown => {
+                if let Err(e) = chip_commands.flush().await {
+                    warn!(error = ?e, "Failed to flush chip commands on shutdown");
+                }
+                evt_tx.send(HashThreadEvent::GoingOffline).await.ok();
+            }
+        }
+    }
+
+    debug!("BM13xx thread actor exiting");
+}
+
+/// Put the chip in a safe, non-hashing state.
+///
+/// Holds the chip in reset where the board allows it. Otherwise, or if
+/// that fails, a running chip at least has its core clocks gated.
+async fn power_down_chip<W>(
+    chip_commands: &mut W,
+    peripherals: &mut BoardPeripherals,
+    chip_initialized: bool,
+    frequency_mhz: &mut f32,
+) where
+    W: Sink<protocol::Command> + Unpin,
+    W::Error: std::fmt::Debug,
+{
+    match peripherals.asic_enable {
+        Some(ref mut asic_enable) => match asic_enable.disable().await {
+            Ok(()) => {
+                publish_power_state(peripherals, ChipPowerState::Off);
+                return;
+            }
+            Err(e) => error!(error = %e, "Failed to disable ASIC"),
+        },
+        None => debug!("No ASIC enable control"),
+    }
+
+    if !chip_initialized {
+        return;
+    }
+    match enter_low_power(chip_commands, frequency_mhz).await {
+        Ok(()) => {
+            warn!("Chip left powered with core clocks gated");
+            publish_power_state(peripherals, ChipPowerState::LowPower);
+        }
+        Err(e) => error!(error = ?e, "Failed to gate core clocks, chip left running"),
+    }
+}
+
+/// Stop the cores without losing the chip's configuration.
+///
+/// Ramps the PLL down to its power-up frequency, then gates the clocks to
+/// every core group. Far cheaper to undo than a reset, which needs the
+/// whole initialization sequence again.
+async fn enter_low_power<W>(chip_commands: &mut W, frequency_mhz: &mut f32) -> Result<(), W::Error>
+where
+    W: Sink<protocol::Command> + Unpin,
+{
+    retune_frequency(chip_commands, frequency_mhz, POWER_UP_FREQUENCY_MHZ).await?;
+    chip_commands
+        .send(protocol::Command::WriteRegister {
+            broadcast: true,
+            chip_address: 0x00,
+            register: protocol::Register::OrderedClockEnable { raw_value: 0 },
+        })
+        .await
+}
+
+/// Count a chip reset, quarantining the chain if it has flapped.
+///
+/// Returns true if the chain has just been quarantined, leaving the
+/// caller to lower its frequency.
+fn record_reset(flap_damper: &mut FlapDamper, status: &RwLock<HashThreadStatus>) -> bool {
+    let quarantined = flap_damper.record(tokio::time::Instant::now());
+    let mut s = status.write().unwrap();
+    s.chip_resets = flap_damper.total();
+    if quarantined {
+        s.quarantined = true;
+        warn!(
+            resets = flap_damper.recent(),
+            "Chip keeps resetting, quarantining at reduced frequency"
+        );
+    }
+    quarantined
+}
+
+/// Undo [`enter_low_power`], ramping back up to `to_mhz`.
+async fn leave_low_power<W>(
+    chip_commands: &mut W,
+    frequency_mhz: &mut f32,
+    to_mhz: f32,
+) -> Result<(), W::Error>
+where
+    W: Sink<protocol::Command> + Unpin,
+{
+    chip_commands
+        .send(protocol::Command::WriteRegister {
+            broadcast: true,
+            chip_address: 0x00,
+            register: protocol::Register::OrderedClockEnable {
+                raw_value: ALL_CORE_CLOCKS,
+            },
+        })
+        .await?;
+    retune_frequency(chip_commands, frequency_mhz, to_mhz).await
+}
+
+/// Refresh the per-chip and thread hashrate from recent nonces and tell
+/// the scheduler and the board.
+fn publish_chip_stats(
+    rates: &mut ChipNonceRates,
+    status: &RwLock<HashThreadStatus>,
+    evt_tx: &mpsc::Sender<HashThreadEvent>,
+    peripherals: &BoardPeripherals,
+    frequency_mhz: f32,
+    pll_readback: Option<&PllReadback>,
+) {
+    let now = tokio::time::Instant::now().into_std();
+    let hashrate = rates
+        .chip_hashrates_at(now)
+        .into_iter()
+        .fold(0u64, |sum, rate| sum.saturating_add(rate.into()));
+    let snapshot = {
+        let mut s = status.write().unwrap();
+        let temperature_c = s.temperature_c;
+        s.hashrate = HashRate::from(hashrate);
+        s.chips = rates.chip_stats_at(now);
+        // What each chip's PLL reads back, or else what was written
+        for (i, chip) in s.chips.iter_mut().enumerate() {
+            let mhz = pll_readback
+                .and_then(|readback| readback.frequency_mhz(i))
+                .unwrap_or(frequency_mhz);
+            chip.frequency_mhz = Some(mhz.round() as u32);
+        }
+        // Only the first chip's sensor is read
+        if let Some(first) = s.chips.first_mut() {
+            first.temperature_c = temperature_c;
+        }
+        s.clone()
+    };
+    if let Some(ref tx) = peripherals.thread_status {
+        tx.send_replace(snapshot.clone());
+    }
+    // The scheduler only needs the newest status; if it has fallen
+    // behind, this update gives way to the next one
+    if evt_tx
+        .try_send(HashThreadEvent::StatusUpdate(Box::new(snapshot)))
+        .is_err()
+    {
+        trace!("Event channel full, skipping status update");
+        status.write().unwrap().status_updates_coalesced += 1;
+    }
+}
+
+fn publish_power_state(peripherals: &BoardPeripherals, state: ChipPowerState) {
+    if let Some(ref tx) = peripherals.power_state {
+        tx.send_replace(state);
+    }
+}
+
+/// Forward shares for nonces the chip has already sent.
+///
+/// Reads responses until the link goes quiet or the drain deadline passes,
+/// so work already done is not thrown away when the thread is removed.
+/// Returns the number of nonces processed.
+async fn drain_shares<R>(chip_responses: &mut R, chip_jobs: &ChipJobTracker) -> usize
+where
+    R: Stream<Item = Result<protocol::Response, std::io::Error>> + Unpin,
+{
+    let deadline = tokio::time::Instant::now() + SHARE_DRAIN_TIMEOUT;
+    let mut drained = 0;
+
+    loop {
+        let wait_until = deadline.min(tokio::time::Instant::now() + SHARE_DRAIN_QUIET);
+        match tokio::time::timeout_at(wait_until, chip_responses.next()).await {
+            Ok(Some(Ok(protocol::Response::Nonce {
+                nonce,
+                job_id,
+                version,
+                ..
+            }))) => {
+                process_nonce(chip_jobs, nonce, job_id, version).await;
+                drained += 1;
+            }
+            Ok(Some(Ok(_))) => {}
+            Ok(Some(Err(e))) => {
+                debug!(error = ?e, "Serial decode error while draining");
+            }
+            Ok(None) | Err(_) => break,
+        }
+    }
+
+    drained
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
+    use crate::job_source::GeneralPurposeBits;
+
+    /// Build a task with a computed merkle root and the given share target.
+    fn sim_task(share_target: bitcoin::Target) -> (HashTask, mpsc::Receiver<Share>) {
+        use crate::job_source::{
+            Extranonce2Range, GeneralPurposeBits, JobTemplate, MerkleRootKind, MerkleRootTemplate,
+            VersionTemplate, test_blocks::block_881423,
+        };
+        use bitcoin::{block::Version, hashes::Hash};
+
+        let en2_range = Extranonce2Range::new(4).unwrap();
+        let template = Arc::new(JobTemplate {
+            id: "sim".into(),
+            prev_blockhash: bitcoin::BlockHash::all_zeros(),
+            version: VersionTemplate::new(
+                Version::from_consensus(0x2000_0000),
+                GeneralPurposeBits::full(),
+            )
+            .unwrap(),
+            bits: bitcoin::CompactTarget::from_consensus(0x1d00ffff),
+            share_target,
+            time: 0x6500_0000,
+            merkle_root: MerkleRootKind::Computed(Box::new(MerkleRootTemplate::new(
+                block_881423::coinbase1_bytes().to_vec(),
+                block_881423::extranonce1_bytes().to_vec(),
+                en2_range.clone(),
+                block_881423::coinbase2_bytes().to_vec(),
+                Vec::new(),
+            ))),
+        });
+        let (share_tx, share_rx) = mpsc::channel(32);
+        let task = HashTask {
+            template,
+            en2: en2_range.iter().next(),
+            en2_range: Some(en2_range),
+            share_target,
+            ntime: 0x6500_0000,
+            share_tx: share_tx.into(),
+        };
+        (task, share_rx)
+    }
+
+    /// Drive the actor through the codec against a simulated chip: the chip
+    /// hashes the job it decodes off the wire, and every nonce it reports
+    /// must validate on the host as a share for the same task.
+    #[tokio::test(start_paused = true)]
+    async fn finds_shares_against_simulated_chip() {
+        use crate::asic::bm13xx::{
+            FrameCodec,
+            sim::{SimChip, SimChipConfig},
+        };
+        use tokio_util::codec::{FramedRead, FramedWrite};
+
+        // About one hash in 256 qualifies
+        let easy = Difficulty::from_f64(1.0 / (1u64 << 24) as f64).to_target();
+
+        let (chip, link) = SimChip::spawn(SimChipConfig {
+            report_target: easy,
+            ..Default::default()
+        });
+        let (_removal_tx, removal_rx) = watch::channel(ThreadRemovalSignal::Running);
+        let mut thread = BM13xxThread::new(
+            "sim".into(),
+            FramedRead::new(link.reader, FrameCodec::default()),
+            FramedWrite::new(link.writer, FrameCodec::default()),
+            BoardPeripherals {
+                asic_enable: None,
+                voltage_regulator: None,
+                baud_control: None,
+                chip_temperature: None,
+                power_state: None,
+                thread_status: None,
+                job_dispatch: None,
+            },
+            removal_rx,
+        );
+
+        let (task, mut share_rx) = sim_task(easy);
+        thread.update_task(task).await.unwrap();
+
+        for _ in 0..4 {
+            let share = tokio::time::timeout(Duration::from_secs(60), share_rx.recv())
+                .await
+                .expect("share within timeout")
+                .expect("share channel open");
+            assert!(easy.is_met_by(share.hash));
+        }
+
+        let state = chip.state();
+        assert!(state.jobs_received >= 1);
+        assert_eq!(state.bad_frames, 0);
+        assert!(state.registers.contains_key(&0xa4), "version mask written");
+    }
+
+    /// Lose job frames on the way to a simulated chip that won't get
+    /// another until the task changes: the thread sends the job again, and
+    /// when that is lost too re-initializes the chip, until shares flow.
+    #[tokio::test(start_paused = true)]
+    async fn recovers_from_lost_job_frames() {
+        use crate::asic::bm13xx::{
+            FrameCodec,
+            sim::{SimChip, SimChipConfig},
+        };
+        use tokio_util::codec::{FramedRead, FramedWrite};
+
+        let easy = Difficulty::from_f64(1.0 / (1u64 << 24) as f64).to_target();
+        let (chip, link) = SimChip::spawn(SimChipConfig {
+            report_target: easy,
+            ..Default::default()
+        });
+        chip.state().drop_jobs = 2;
+        let (_removal_tx, removal_rx) = watch::channel(ThreadRemovalSignal::Running);
+        let timeout = Duration::from_secs(5);
+        let mut thread = BM13xxThread::new(
+            "sim".into(),
+            FramedRead::new(link.reader, FrameCodec::default()),
+            FramedWrite::new(link.writer, FrameCodec::default()),
+            BoardPeripherals {
+                asic_enable: None,
+                voltage_regulator: None,
+                baud_control: None,
+                chip_temperature: None,
+                power_state: None,
+                thread_status: None,
+                job_dispatch: None,
+            },
+            removal_rx,
+        )
+        .with_nonce_timeout(timeout);
+
+        // No ntime rolling, so no fresh job frames behind the lost one
+        thread
+            .negotiate(AssignmentParameters { max_ntime_roll: 0 })
+            .await
+            .unwrap();
+        let (task, mut share_rx) = sim_task(easy);
+        let sent_at = tokio::time::Instant::now();
+        thread.update_task(task).await.unwrap();
+
+        let share = tokio::time::timeout(timeout * 4, share_rx.recv())
+            .await
+            .expect("share after recovery")
+            .expect("share channel open");
+        assert!(easy.is_met_by(share.hash));
+        assert!(sent_at.elapsed() >= timeout * 2, "resent, then reset");
+
+        let state = chip.state();
+        assert_eq!(state.drop_jobs, 0);
+        assert_eq!(state.jobs_received, 1);
+    }
+
+    /// Read back a simulated chip's registers after initialization: they
+    /// match what was written until one is changed behind the thread's
+    /// back.
+    #[tokio::test(start_paused = true)]
+    async fn register_dump_diffs_against_initialization() {
+        use crate::asic::bm13xx::{
+            FrameCodec,
+            sim::{SimChip, SimChipConfig},
+        };
+        use tokio_util::codec::{FramedRead, FramedWrite};
+
+        let (chip, link) = SimChip::spawn(SimChipConfig::default());
+        let (_removal_tx, removal_rx) = watch::channel(ThreadRemovalSignal::Running);
+        let mut thread = BM13xxThread::new(
+            "sim".into(),
+            FramedRead::new(link.reader, FrameCodec::default()),
+            FramedWrite::new(link.writer, FrameCodec::default()),
+            BoardPeripherals {
+                asic_enable: None,
+                voltage_regulator: None,
+                baud_control: None,
+                chip_temperature: None,
+                power_state: None,
+                thread_status: None,
+                job_dispatch: None,
+            },
+            removal_rx,
+        );
+        let registers = thread.register_access();
+
+        let err = registers.dump(0x00, true).await.unwrap_err();
+        assert!(matches!(err, HashThreadError::RegisterAccess(_)));
+
+        let (task, _share_rx) = sim_task(bitcoin::Target::MAX);
+        thread.update_task(task).await.unwrap();
+
+        let dump = registers.dump(0x00, true).await.unwrap();
+        assert_eq!(dump.thread, "sim");
+        assert!(dump.registers.iter().all(|r| r.value.is_some()));
+        assert!(dump.mismatches.is_empty(), "{:?}", dump.mismatches);
+        let pll = dump
+            .registers
+            .iter()
+            .find(|r| r.name == "PllDivider")
+            .unwrap();
+        assert!(pll.expected.is_some());
+
+        chip.state().registers.insert(0x54, [0; 4]);
+        let dump = registers.dump(0x00, true).await.unwrap();
+        assert_eq!(dump.mismatches, ["AnalogMux"]);
+
+        // Without a diff nothing is compared
+        let dump = registers.dump(0x00, false).await.unwrap();
+        assert!(dump.mismatches.is_empty());
+        assert!(dump.registers.iter().all(|r| r.expected.is_none()));
+    }
+
+    /// Run against a simulated chip over a noisy link: corrupted frames are
+    /// rejected by the codec or filtered as invalid nonces, never passed on
+    /// as shares, and the thread keeps finding shares through and after the
+    /// noise.
+    #[tokio::test(start_paused = true)]
+    async fn survives_noisy_link_to_simulated_chip() {
+        use crate::asic::bm13xx::{
+            FrameCodec,
+            sim::{SimChip, SimChipConfig},
+        };
+        use crate::transport::fault::{FaultConfig, FaultyReader};
+        use tokio_util::codec::{FramedRead, FramedWrite};
+
+        let easy = Difficulty::from_f64(1.0 / (1u64 << 24) as f64).to_target();
+        let (_chip, link) = SimChip::spawn(SimChipConfig {
+            report_target: easy,
+            ..Default::default()
+        });
+        let reader = FaultyReader::new(
+            link.reader,
+            FaultConfig {
+                bit_error_rate: 0.004,
+                drop_rate: 0.004,
+                latency_spike_rate: 0.1,
+                latency_spike: Duration::from_millis(100),
+                ..Default::default()
+            },
+        );
+        let faults = reader.control();
+        let codec = FrameCodec::default();
+        let (_removal_tx, removal_rx) = watch::channel(ThreadRemovalSignal::Running);
+        let mut thread = BM13xxThread::new(
+            "sim".into(),
+            FramedRead::new(reader, codec.clone()),
+            FramedWrite::new(link.writer, FrameCodec::default()),
+            BoardPeripherals {
+                asic_enable: None,
+                voltage_regulator: None,
+                baud_control: None,
+                chip_temperature: None,
+                power_state: None,
+                thread_status: None,
+                job_dispatch: None,
+            },
+            removal_rx,
+        );
+
+        // Initialization reads back its writes and gives up on a link this
+        // bad, so the noise starts once the chip is running
+        faults.set_enabled(false);
+        let (task, mut share_rx) = sim_task(easy);
+        thread.update_task(task).await.unwrap();
+        faults.set_enabled(true);
+
+        async fn next_share(share_rx: &mut mpsc::Receiver<Share>) -> Share {
+            tokio::time::timeout(Duration::from_secs(60), share_rx.recv())
+                .await
+                .expect("share within timeout")
+                .expect("share channel open")
+        }
+        // Mine until every kind of fault has hit the line at least once
+        for _ in 0..256 {
+            assert!(easy.is_met_by(next_share(&mut share_rx).await.hash));
+            let injected = faults.stats();
+            if injected.bits_flipped > 0
+                && injected.bytes_dropped > 0
+                && injected.latency_spikes > 0
+            {
+                break;
+            }
+        }
+        let injected = faults.stats();
+        assert!(injected.bits_flipped > 0 && injected.bytes_dropped > 0);
+        assert!(injected.latency_spikes > 0);
+        let noisy = codec.stats();
+        assert!(noisy.crc_errors > 0, "{noisy:?}");
+        assert!(noisy.bytes_skipped > 0, "{noisy:?}");
+
+        // Shares keep coming once the line is clean. Shares already
+        // queued were found before the switch, so drain them first.
+        faults.set_enabled(false);
+        while share_rx.try_recv().is_ok() {}
+        for _ in 0..8 {
+            assert!(easy.is_met_by(next_share(&mut share_rx).await.hash));
+        }
+        assert!(codec.stats().frames > noisy.frames);
+    }
+
+    /// ASIC enable control that counts disable calls.
+    struct MockAsicEnable {
+        disables: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl crate::asic::hash_thread::AsicEnable for MockAsicEnable {
+        async fn enable(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        async fn disable(&mut self) -> anyhow::Result<()> {
+            self.disables
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// A thread wired to in-memory channels in place of a serial port.
+    struct MockLink {
+        thread: BM13xxThread,
+        events: mpsc::Receiver<HashThreadEvent>,
+        responses: mpsc::UnboundedSender<Result<protocol::Response, std::io::Error>>,
+        commands: futures::channel::mpsc::UnboundedReceiver<protocol::Command>,
+        removal_tx: watch::Sender<ThreadRemovalSignal>,
+        disables: Arc<std::sync::atomic::AtomicUsize>,
+        power_state: watch::Receiver<ChipPowerState>,
+    }
+
+    /// Pass commands through to `commands`, answering register reads with
+    /// the last value written, as a chip would.
+    fn read_back_writes(
+        commands: futures::channel::mpsc::UnboundedSender<protocol::Command>,
+        responses: mpsc::UnboundedSender<Result<protocol::Response, std::io::Error>>,
+    ) -> futures::channel::mpsc::UnboundedSender<protocol::Command> {
+        let (tap_tx, mut tap_rx) = futures::channel::mpsc::unbounded::<protocol::Command>();
+        tokio::spawn(async move {
+            let mut registers = std::collections::HashMap::new();
+            while let Some(command) = tap_rx.next().await {
+                match &command {
+                    protocol::Command::WriteRegister { register, .. } => {
+                        registers.insert(register.address() as u8, register.clone());
+                    }
+                    protocol::Command::ReadRegister {
+                        chip_address,
+                        register_address,
+                        ..
+                    } => {
+                        if let Some(register) = registers.get(&(*register_address as u8)) {
+                            let _ = responses.send(Ok(protocol::Response::ReadRegister {
+                                chip_address: *chip_address,
+                                register: register.clone(),
+                            }));
+                        }
+                    }
+                    _ => {}
+                }
+                if commands.unbounded_send(command).is_err() {
+                    break;
+                }
+            }
+        });
+        tap_tx
+    }
+
+    impl MockLink {
+        fn new() -> Self {
+            let (responses, responses_rx) = mpsc::unbounded_channel();
+            let (commands_tx, commands) = futures::channel::mpsc::unbounded();
+            let commands_tx = read_back_writes(commands_tx, responses.clone());
+            let (removal_tx, removal_rx) = watch::channel(ThreadRemovalSignal::Running);
+            let disables = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+            let (power_state, power_state_rx) = watch::channel(ChipPowerState::Off);
+
+            let mut thread = BM13xxThread::new(
+                "mock".into(),
+                tokio_stream::wrappers::UnboundedReceiverStream::new(responses_rx),
+                commands_tx,
+                BoardPeripherals {
+                    asic_enable: Some(Box::new(MockAsicEnable {
+                        disables: Arc::clone(&disables),
+                    })),
+                    voltage_regulator: None,
+                    baud_control: None,
+                    chip_temperature: None,
+                    power_state: Some(power_state),
+                    thread_status: None,
+                    job_dispatch: None,
+                },
+                removal_rx,
+            );
+            let events = thread.take_event_receiver().unwrap();
+
+            Self {
+                thread,
+                events,
+                responses,
+                commands,
+                removal_tx,
+                disables,
+                power_state: power_state_rx,
+            }
+        }
+
+        fn disables(&self) -> usize {
+            self.disables.load(std::sync::atomic::Ordering::SeqCst)
+        }
+
+        /// Wait for the actor to close its event channel, returning any
+        /// events sent on the way out.
+        async fn wait_offline(&mut self) -> Vec<HashThreadEvent> {
+            let mut events = Vec::new();
+            while let Some(event) = tokio::time::timeout(Duration::from_secs(5), self.events.recv())
+                .await
+                .expect("thread exits within timeout")
+            {
+                events.push(event);
+            }
+            events
+        }
+    }
+
+    /// Initialize a chip that reads back what it's written and compare the
+    /// frames sent with esp-miner's boot of the same chip: the register
+    /// configuration byte for byte, a PLL ramp from power-up to the target
+    /// frequency, then the final frames.
+    #[tokio::test(start_paused = true)]
+    async fn initialization_matches_esp_miner_sequence() {
+        use crate::asic::bm13xx::test_data::esp_miner_init::{CONFIGURATION, FINAL};
+
+        let (responses, responses_rx) = mpsc::unbounded_channel();
+        let (commands_tx, _commands) = futures::channel::mpsc::unbounded();
+        let mut chip_commands = read_back_writes(commands_tx, responses);
+        let mut chip_responses = tokio_stream::wrappers::UnboundedReceiverStream::new(responses_rx);
+        let mut peripherals = BoardPeripherals {
+            asic_enable: None,
+            voltage_regulator: None,
+            baud_control: None,
+            chip_temperature: None,
+            power_state: None,
+            thread_status: None,
+            job_dispatch: None,
+        };
+
+        let sequence = initialize_chip(
+            &mut chip_commands,
+            &mut chip_responses,
+            &mut peripherals,
+            TARGET_FREQUENCY_MHZ,
+        )
+        .await
+        .unwrap();
+        let writes: Vec<&[u8]> = sequence.writes().collect();
+        assert!(sequence.len() > writes.len(), "read-backs recorded");
+
+        let (configuration, rest) = writes.split_at(CONFIGURATION.len());
+        assert_eq!(configuration, CONFIGURATION);
+        let (rest, final_frames) = rest.split_at(rest.len() - FINAL.len());
+        assert_eq!(final_frames, FINAL);
+
+        // The final batch repeats the last ramp step so it gets read back
+        let (repeat, ramp) = rest.split_last().unwrap();
+        assert_eq!(Some(repeat), ramp.last());
+        let ramp_mhz: Vec<f32> = ramp
+            .iter()
+            .map(|frame| match protocol::Command::decode(&frame[2..]) {
+                Ok(protocol::Command::WriteRegister {
+                    broadcast: true,
+                    register: protocol::Register::PllDivider(config),
+                    ..
+                }) => config.frequency_mhz().unwrap(),
+                other => panic!("expected a PLL write, got {other:?}"),
+            })
+            .collect();
+        assert!((ramp_mhz[0] - POWER_UP_FREQUENCY_MHZ).abs() < 1.0);
+        assert!((ramp_mhz[ramp_mhz.len() - 1] - TARGET_FREQUENCY_MHZ).abs() < 1.0);
+        assert!(
+            ramp_mhz
+                .windows(2)
+                .all(|step| step[1] > step[0] && step[1] - step[0] < 2.0 * FREQUENCY_STEP_MHZ),
+            "{ramp_mhz:?}"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn hardware_fault_powers_down_chip() {
+        let mut link = MockLink::new();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(link.disables(), 1, "disabled once on startup");
+
+        link.removal_tx
+            .send(ThreadRemovalSignal::HardwareFault {
+                description: "overtemperature".into(),
+            })
+            .unwrap();
+        link.wait_offline().await;
+
+        assert_eq!(link.disables(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn board_disconnect_exits_without_touching_chip() {
+        let mut link = MockLink::new();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        link.removal_tx
+            .send(ThreadRemovalSignal::BoardDisconnected)
+            .unwrap();
+        let events = link.wait_offline().await;
+
+        assert!(events.is_empty());
+        assert_eq!(link.disables(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_announces_going_offline() {
+        let mut link = MockLink::new();
+
+        link.removal_tx.send(ThreadRemovalSignal::Shutdown).unwrap();
+        let events = link.wait_offline().await;
+
+        assert!(matches!(events.as_slice(), [HashThreadEvent::GoingOffline]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn user_request_drains_pending_shares() {
+        let mut link = MockLink::new();
+        // Every hash meets an all-ones target
+        let (task, mut share_rx) = sim_task(bitcoin::Target::from_be_bytes([0xff; 32]));
+        link.thread.update_task(task).await.unwrap();
+
+        // The nonce arrives after the removal signal: only the drain
+        // can forward it.
+        link.removal_tx
+            .send(ThreadRemovalSignal::UserRequested)
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        link.responses
+            .send(Ok(protocol::Response::Nonce {
+                nonce: 0x1234,
+                job_id: 0,
+                midstate_num: 0,
+                version: crate::job_source::GeneralPurposeBits::new([0, 0]),
+                subcore_id: 0,
+            }))
+            .unwrap();
+
+        let share = tokio::time::timeout(Duration::from_secs(5), share_rx.recv())
+            .await
+            .expect("share within timeout")
+            .expect("share forwarded");
+        assert_eq!(share.nonce, 0x1234);
+
+        link.wait_offline().await;
+        assert_eq!(link.disables(), 2, "chip powered down after draining");
+    }
+
+    /// Sixteen jobs later the ID of the first is reused; a nonce for it
+    /// still in flight must be credited to it, not the job now in its slot.
+    #[tokio::test(start_paused = true)]
+    async fn late_nonce_after_job_id_reuse_credits_displaced_task() {
+        let version = crate::job_source::GeneralPurposeBits::new([0, 0]);
+        let solves = |task: &HashTask, nonce: u32| {
+            let template = task.template.as_ref();
+            let header = BlockHeader {
+                version: version.apply_to_version(template.version.base()),
+                prev_blockhash: template.prev_blockhash,
+                merkle_root: template
+                    .compute_merkle_root(task.en2.as_ref().unwrap())
+                    .unwrap(),
+                time: task.ntime,
+                bits: template.bits,
+                nonce,
+            };
+            task.share_target.is_met_by(header.block_hash())
+        };
+
+        // About one hash in 256 meets this target
+        let mut target = [0xff; 32];
+        target[0] = 0;
+        let target = bitcoin::Target::from_be_bytes(target);
+        let (old_task, mut old_rx) = sim_task(target);
+        let (mut new_task, mut new_rx) = sim_task(target);
+        new_task.ntime += 1;
+        let nonce = (0..)
+            .find(|&n| solves(&old_task, n) && !solves(&new_task, n))
+            .unwrap();
+
+        let mut chip_jobs = ChipJobTracker::new();
+        let now = tokio::time::Instant::now();
+        assert_eq!(chip_jobs.insert(old_task, now), 0);
+        for _ in 0..16 {
+            chip_jobs.insert(new_task.clone(), now);
+        }
+
+        process_nonce(&chip_jobs, nonce, 0, version).await;
+        assert_eq!(old_rx.try_recv().unwrap().nonce, nonce);
+        assert!(new_rx.try_recv().is_err());
+    }
+
+    /// A single actor checks nonces one after another; it has to keep up
+    /// with a long chain at high nonce rates. Timing-dependent, so only
+    /// run on request, by `just perf`.
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "benchmark"]
+    async fn nonce_verification_throughput() {
+        const NONCES: u32 = 20_000;
+        let (task, mut share_rx) = sim_task(bitcoin::Target::MAX);
+        let mut chip_jobs = ChipJobTracker::new();
+        chip_jobs.insert(task, tokio::time::Instant::now());
+        let version = crate::job_source::GeneralPurposeBits::new([0, 0]);
+        let drain = tokio::spawn(async move { while share_rx.recv().await.is_some() {} });
+
+        let start = std::time::Instant::now();
+        for nonce in 0..NONCES {
+            process_nonce(&chip_jobs, nonce, 0, version).await;
+        }
+        let rate = NONCES as f64 / start.elapsed().as_secs_f64();
+        drop(chip_jobs);
+        drain.await.unwrap();
+
+        assert!(
+            rate >= 10_000.0,
+            "only {rate:.0} nonces/s on {} pool threads",
+            verify_pool::shared().threads()
+        );
+    }
+
+    #[test]
+    fn frequency_path_steps_both_ways() {
+        assert_eq!(
+            frequency_path(525.0, 500.0, 6.25),
+            [518.75, 512.5, 506.25, 500.0]
+        );
+        assert_eq!(frequency_path(500.0, 510.0, 6.25), [506.25, 510.0]);
+        assert_eq!(frequency_path(500.0, 500.0, 6.25), [500.0]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn temperature_derates_and_restores_frequency() {
+        let mut link = MockLink::new();
+        link.thread
+            .frequency_tx
+            .send_modify(|plan| plan.derating = "70:500".parse().unwrap());
+        let (task, _share_rx) = sim_task(bitcoin::Target::MAX);
+        link.thread.update_task(task).await.unwrap();
+        while link.commands.try_recv().is_ok() {}
+
+        let pll_writes = |link: &mut MockLink| {
+            let mut writes = Vec::new();
+            while let Ok(command) = link.commands.try_recv() {
+                if let protocol::Command::WriteRegister {
+                    register: protocol::Register::PllDivider(config),
+                    ..
+                } = command
+                {
+                    writes.push(config);
+                }
+            }
+            writes
+        };
+        let report_temperature = |link: &MockLink, temperature_c: u32| {
+            link.responses
+                .send(Ok(protocol::Response::ReadRegister {
+                    chip_address: 0,
+                    register: protocol::Register::ExternalTempSensor {
+                        raw_value: temperature_c * 256,
+                    },
+                }))
+                .unwrap();
+        };
+
+        report_temperature(&link, 75);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let writes = pll_writes(&mut link);
+        assert_eq!(writes.len(), 4);
+        assert_eq!(writes.last(), calculate_pll_for_frequency(500.0).as_ref());
+
+        // Inside the hysteresis band: no change
+        report_temperature(&link, 69);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(pll_writes(&mut link).is_empty());
+
+        report_temperature(&link, 60);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let writes = pll_writes(&mut link);
+        assert_eq!(
+            writes.last(),
+            calculate_pll_for_frequency(TARGET_FREQUENCY_MHZ).as_ref()
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn silent_temperature_sensor_derates_to_hottest_band() {
+        let mut link = MockLink::new();
+        link.thread
+            .frequency_tx
+            .send_modify(|plan| plan.derating = "70:500, 90:400".parse().unwrap());
+        let (task, _share_rx) = sim_task(bitcoin::Target::MAX);
+        link.thread.update_task(task).await.unwrap();
+        while link.commands.try_recv().is_ok() {}
+
+        let last_pll_write = |link: &mut MockLink| {
+            let mut last = None;
+            while let Ok(command) = link.commands.try_recv() {
+                if let protocol::Command::WriteRegister {
+                    register: protocol::Register::PllDivider(config),
+                    ..
+                } = command
+                {
+                    last = Some(config);
+                }
+            }
+            last
+        };
+
+        // The chip never answers a temperature read
+        let tick = ThermalConfig::default().tick;
+        tokio::time::sleep(tick * (STALE_TICKS - 2)).await;
+        assert_eq!(last_pll_write(&mut link), None);
+        tokio::time::sleep(tick * 3).await;
+        assert_eq!(
+            last_pll_write(&mut link),
+            calculate_pll_for_frequency(400.0)
+        );
+
+        // A reading brings it back to the band the temperature calls for
+        link.responses
+            .send(Ok(protocol::Response::ReadRegister {
+                chip_address: 0,
+                register: protocol::Register::ExternalTempSensor {
+                    raw_value: 60 * 256,
+                },
+            }))
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        assert_eq!(
+            last_pll_write(&mut link),
+            calculate_pll_for_frequency(TARGET_FREQUENCY_MHZ)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_gates_core_clocks_until_next_task() {
+        let mut link = MockLink::new();
+        let (task, _share_rx) = sim_task(bitcoin::Target::MAX);
+        link.thread.update_task(task).await.unwrap();
+        assert_eq!(*link.power_state.borrow(), ChipPowerState::Hashing);
+
+        // Clock and PLL writes since the last call
+        let clock_writes = |link: &mut MockLink| {
+            let (mut gates, mut last_pll) = (Vec::new(), None);
+            while let Ok(command) = link.commands.try_recv() {
+                match command {
+                    protocol::Command::WriteRegister {
+                        register: protocol::Register::OrderedClockEnable { raw_value },
+                        ..
+                    } => gates.push(raw_value),
+                    protocol::Command::WriteRegister {
+                        register: protocol::Register::PllDivider(config),
+                        ..
+                    } => last_pll = Some(config),
+                    _ => {}
+                }
+            }
+            (gates, last_pll)
+        };
+        clock_writes(&mut link);
+
+        link.thread.go_idle().await.unwrap();
+        let (gates, last_pll) = clock_writes(&mut link);
+        assert_eq!(gates, [0]);
+        assert_eq!(
+            last_pll,
+            calculate_pll_for_frequency(POWER_UP_FREQUENCY_MHZ)
+        );
+        assert_eq!(*link.power_state.borrow(), ChipPowerState::LowPower);
+        assert_eq!(link.disables(), 1, "idle keeps the chip out of reset");
+
+        // New work wakes the chip without reinitializing it
+        let (task, _share_rx) = sim_task(bitcoin::Target::MAX);
+        link.thread.update_task(task).await.unwrap();
+        let (gates, last_pll) = clock_writes(&mut link);
+        assert_eq!(gates, [ALL_CORE_CLOCKS]);
+        assert_eq!(last_pll, calculate_pll_for_frequency(TARGET_FREQUENCY_MHZ));
+        assert_eq!(*link.power_state.borrow(), ChipPowerState::Hashing);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn nonce_rate_reports_per_chip_hashrate() {
+        let mut link = MockLink::new();
+        link.thread = link.thread.with_chip_count(2);
+        let (task, _share_rx) = sim_task(bitcoin::Target::MAX);
+        link.thread.update_task(task).await.unwrap();
+
+        // The first chip finds a nonce every second, the second none
+        for n in 0..30 {
+            link.responses
+                .send(Ok(protocol::Response::Nonce {
+                    nonce: 0x1234_5678 + n,
+                    job_id: 0,
+                    version: GeneralPurposeBits::new([0, 0]),
+                    midstate_num: 0,
+                    subcore_id: 0,
+                }))
+                .unwrap();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+
+        // Latest of the periodic updates
+        let mut latest = None;
+        while let Ok(event) = link.events.try_recv() {
+            if let HashThreadEvent::StatusUpdate(status) = event {
+                latest = Some(status);
+            }
+        }
+        let status = latest.expect("status updates while hashing");
+        assert!(status.chip_shares_found >= 25);
+        assert_eq!(status.chips.len(), 2);
+        assert_eq!(status.chips[0].nonces_found, status.chip_shares_found);
+        assert_eq!(status.chips[1].nonces_found, 0);
+        assert_eq!(status.chips[1].hashrate, None);
+
+        // One nonce per second at the reporting difficulty
+        let expected = Difficulty::from(reporting_ticket_mask().difficulty())
+            .to_target()
+            .to_work();
+        let expected = crate::u256::U256::from(expected).to_f64_approx();
+        let measured = u64::from(status.chips[0].hashrate.unwrap()) as f64;
+        assert!(
+            (measured / expected - 1.0).abs() < 0.1,
+            "{measured} vs {expected}"
+        );
+        assert_eq!(status.hashrate, status.chips[0].hashrate.unwrap());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pll_readback_reports_a_chip_that_reset() {
+        let mut link = MockLink::new();
+        link.thread = link.thread.with_chip_count(2);
+        let (task, _share_rx) = sim_task(bitcoin::Target::MAX);
+        link.thread.update_task(task).await.unwrap();
+
+        // The mock answers the first poll for the first chip; the second
+        // answers at its power-up setting
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        link.responses
+            .send(Ok(protocol::Response::ReadRegister {
+                chip_address: 0,
+                register: protocol::Register::PllDivider(
+                    calculate_pll_for_frequency(POWER_UP_FREQUENCY_MHZ).unwrap(),
+                ),
+            }))
+            .unwrap();
+        link.responses
+            .send(Ok(protocol::Response::Nonce {
+                nonce: 0x1234_5678,
+                job_id: 0,
+                version: GeneralPurposeBits::new([0, 0]),
+                midstate_num: 0,
+                subcore_id: 0,
+            }))
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(link.thread.status().frequency_mismatches, 1);
+
+        // Chip stats carry what each chip read back
+        tokio::time::sleep(ThermalConfig::default().tick).await;
+        let chips = link.thread.status().chips;
+        let read_mhz = |chip: usize| chips[chip].frequency_mhz.unwrap() as f32;
+        assert!((read_mhz(0) - TARGET_FREQUENCY_MHZ).abs() <= 1.0);
+        assert!((read_mhz(1) - POWER_UP_FREQUENCY_MHZ).abs() <= 1.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn narrow_version_mask_walks_extranonce2_and_drops_repeats() {
+        use crate::job_source::{Extranonce2, Extranonce2Range, VersionTemplate};
+
+        // No version rolling: a chip runs through its nonces in milliseconds
+        let mut link = MockLink::new();
+        let (mut task, _share_rx) = sim_task(bitcoin::Target::MAX);
+        let mut template = (*task.template).clone();
+        template.version = VersionTemplate::new(
+            bitcoin::block::Version::from_consensus(0x2000_0000),
+            GeneralPurposeBits::new([0, 0]),
+        )
+        .unwrap();
+        task.template = Arc::new(template);
+        task.en2_range = Some(Extranonce2Range::new_range(5, 7, 4).unwrap());
+        task.en2 = Some(Extranonce2::new(5, 4).unwrap());
+        link.thread.update_task(task).await.unwrap();
+
+        // Fresh extranonce2 values until the range runs out, well before
+        // the first ntime roll
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        let mut merkle_roots = Vec::new();
+        while let Ok(command) = link.commands.try_recv() {
+            if let protocol::Command::JobFull { job_data } = command {
+                merkle_roots.push(job_data.merkle_root);
+            }
+        }
+        assert_eq!(merkle_roots.len(), 3);
+        merkle_roots.dedup();
+        assert_eq!(merkle_roots.len(), 3, "each dispatch is fresh work");
+
+        let mut exhausted = Vec::new();
+        while let Ok(event) = link.events.try_recv() {
+            if let HashThreadEvent::WorkExhausted { en2_searched } = event {
+                exhausted.push(en2_searched);
+            }
+        }
+        assert_eq!(exhausted, [3], "exhaustion is reported once");
+
+        // A wrapped chip reports the same nonce again
+        for _ in 0..2 {
+            link.responses
+                .send(Ok(protocol::Response::Nonce {
+                    nonce: 0x1234_5678,
+                    job_id: 2,
+                    version: GeneralPurposeBits::new([0, 0]),
+                    midstate_num: 0,
+                    subcore_id: 0,
+                }))
+                .unwrap();
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let status = link.thread.status();
+        assert_eq!(status.chip_shares_found, 1);
+        assert_eq!(status.duplicate_nonces, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn frequency_control_retunes_running_chip() {
+        let mut link = MockLink::new();
+        let control = link.thread.frequency_control();
+        let (task, _share_rx) = sim_task(bitcoin::Target::MAX);
+        link.thread.update_task(task).await.unwrap();
+        while link.commands.try_recv().is_ok() {}
+
+        control.set_target(500.0);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        let mut writes = Vec::new();
+        while let Ok(command) = link.commands.try_recv() {
+            if let protocol::Command::WriteRegister {
+                register: protocol::Register::PllDivider(config),
+                ..
+            } = command
+            {
+                writes.push(config);
+            }
+        }
+        assert_eq!(writes.len(), 4);
+        assert_eq!(writes.last(), calculate_pll_for_frequency(500.0).as_ref());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn thermal_slew_spreads_target_change_over_polls() {
+        let mut link = MockLink::new();
+        // 50 MHz a minute, about 4 MHz per temperature poll
+        link.thread = link
+            .thread
+            .with_thermal_slew(Some(ThermalSlew::new(5.0).unwrap()));
+        let control = link.thread.frequency_control();
+        let (task, _share_rx) = sim_task(bitcoin::Target::MAX);
+        link.thread.update_task(task).await.unwrap();
+        while link.commands.try_recv().is_ok() {}
+
+        let pll_writes = |link: &mut MockLink| {
+            let mut writes = Vec::new();
+            while let Ok(command) = link.commands.try_recv() {
+                if let protocol::Command::WriteRegister {
+                    register: protocol::Register::PllDivider(config),
+                    ..
+                } = command
+                {
+                    writes.push(config);
+                }
+            }
+            writes
+        };
+
+        control.set_target(500.0);
+        tokio::time::sleep(Duration::from_secs(12)).await;
+        let writes = pll_writes(&mut link);
+        assert!(!writes.is_empty() && writes.len() <= 3);
+        assert_ne!(writes.last(), calculate_pll_for_frequency(500.0).as_ref());
+
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        let writes = pll_writes(&mut link);
+        assert!(writes.len() >= 3);
+        assert_eq!(writes.last(), calculate_pll_for_frequency(500.0).as_ref());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slew_steps_wait_for_the_adjustment_interval() {
+        let mut link = MockLink::new();
+        // 50 MHz a minute, taken 25 MHz at a time
+        link.thread = link
+            .thread
+            .with_thermal_slew(Some(ThermalSlew::new(5.0).unwrap()))
+            .with_thermal_config(ThermalConfig {
+                tick: Duration::from_secs(1),
+                adjust_interval: Duration::from_secs(30),
+                ..Default::default()
+            });
+        let control = link.thread.frequency_control();
+        let (task, _share_rx) = sim_task(bitcoin::Target::MAX);
+        link.thread.update_task(task).await.unwrap();
+        while link.commands.try_recv().is_ok() {}
+
+        let last_pll_write = |link: &mut MockLink| {
+            let mut last = None;
+            while let Ok(command) = link.commands.try_recv() {
+                if let protocol::Command::WriteRegister {
+                    register: protocol::Register::PllDivider(config),
+                    ..
+                } = command
+                {
+                    last = Some(config);
+                }
+            }
+            last
+        };
+
+        control.set_target(500.0);
+        tokio::time::sleep(Duration::from_secs(20)).await;
+        assert_eq!(last_pll_write(&mut link), None);
+        tokio::time::sleep(Duration::from_secs(15)).await;
+        assert_eq!(
+            last_pll_write(&mut link),
+            calculate_pll_for_frequency(500.0)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn warmup_starts_low_and_ramps_on_nonces() {
+        let mut link = MockLink::new();
+        link.thread = link.thread.with_warmup(Some(WarmupConfig {
+            start_mhz: 500.0,
+            stage_mhz: 25.0,
+            stage_duration: Duration::from_secs(10),
+            max_temp_rise_c: 5.0,
+        }));
+        let (task, _share_rx) = sim_task(bitcoin::Target::MAX);
+        link.thread.update_task(task).await.unwrap();
+
+        let last_pll_write = |link: &mut MockLink| {
+            let mut last = None;
+            while let Ok(command) = link.commands.try_recv() {
+                if let protocol::Command::WriteRegister {
+                    register: protocol::Register::PllDivider(config),
+                    ..
+                } = command
+                {
+                    last = Some(config);
+                }
+            }
+            last
+        };
+        assert_eq!(
+            last_pll_write(&mut link),
+            calculate_pll_for_frequency(500.0)
+        );
+
+        link.responses
+            .send(Ok(protocol::Response::Nonce {
+                nonce: 0x1234,
+                job_id: 0,
+                midstate_num: 0,
+                version: crate::job_source::GeneralPurposeBits::new([0, 0]),
+                subcore_id: 0,
+            }))
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(12)).await;
+        assert_eq!(
+            last_pll_write(&mut link),
+            calculate_pll_for_frequency(TARGET_FREQUENCY_MHZ)
+        );
+    }
+
+    #[test]
+    fn theoretical_hashrate_from_frequency() {
+        // Bitaxe Gamma at stock frequency: ~1.07 TH/s
+        let hashrate = theoretical_hashrate(protocol::ChipType::BM1370, 525.0).unwrap();
+        assert_eq!(hashrate, HashRate::from_megahashes(1_071_000.0));
+
+        assert!(theoretical_hashrate(protocol::ChipType::BM1362, 525.0).is_none());
+    }
+
+    #[test]
+    fn version_mask_follows_template_gp_bits() {
+        use crate::job_source::{GeneralPurposeBits, JobTemplate, MerkleRootKind, VersionTemplate};
+        use bitcoin::{block::Version, hashes::Hash};
+
+        let template = Arc::new(JobTemplate {
+            id: "test".into(),
+            prev_blockhash: bitcoin::BlockHash::all_zeros(),
+            version: VersionTemplate::new(
+                Version::from_consensus(0x2000_0000),
+                GeneralPurposeBits::from(&0x01ffe000u32.to_be_bytes()),
+            )
+            .unwrap(),
+            bits: bitcoin::CompactTarget::from_consensus(0x1d00ffff),
+            share_target: crate::types::Difficulty::from(1_u64).to_target(),
+            time: 0,
+            merkle_root: MerkleRootKind::Fixed(bitcoin::TxMerkleNode::all_zeros()),
+        });
+        let (share_tx, _share_rx) = mpsc::channel(1);
+        let task = HashTask {
+            template,
+            en2_range: None,
+            en2: None,
+            share_target: crate::types::Difficulty::from(1_u64).to_target(),
+            ntime: 0,
+            share_tx: share_tx.into(),
+        };
+
+        assert_eq!(
+            version_mask_for(&task),
+            protocol::VersionMask::rolling(0x0fff)
+        );
+    }
+
+    #[test]
+    fn rolled_ntime_follows_elapsed_time() {
+        assert_eq!(rolled_ntime(1000, Duration::ZERO, MAX_NTIME_ROLL), 1000);
+        assert_eq!(
+            rolled_ntime(1000, Duration::from_millis(2500), MAX_NTIME_ROLL),
+            1002
+        );
+    }
+
+    #[test]
+    fn rolled_ntime_stops_at_roll_limit() {
+        let limit = 1000 + MAX_NTIME_ROLL;
+        assert_eq!(
+            rolled_ntime(
+                1000,
+                Duration::from_secs(MAX_NTIME_ROLL as u64),
+                MAX_NTIME_ROLL
+            ),
+            limit
+        );
+        assert_eq!(
+            rolled_ntime(1000, Duration::from_secs(86_400), MAX_NTIME_ROLL),
+            limit
+        );
+        assert_eq!(
+            rolled_ntime(u32::MAX - 1, Duration::from_secs(10), MAX_NTIME_ROLL),
+            u32::MAX
+        );
+
+        // A negotiated limit tighter than the thread's own applies
+        assert_eq!(rolled_ntime(1000, Duration::from_secs(90), 60), 1060);
+    }
+
+    #[test]
+    fn dispatch_phase_is_stable_and_within_interval() {
+        // FNV-1a test vectors; a change here moves every thread's phase
+        assert_eq!(phase_seed(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(phase_seed("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(phase_seed("foobar"), 0x8594_4171_f739_67e8);
+
+        let interval = Duration::from_secs(1);
+        let a = dispatch_phase(phase_seed("Bitaxe-Gamma-e2f56f9b"), interval);
+        assert!(a < interval);
+        assert!(dispatch_phase(phase_seed("Bitaxe-Gamma-0badc0de"), interval) < interval);
+        let slow = Duration::from_secs(5);
+        assert!(dispatch_phase(phase_seed("Bitaxe-Gamma-e2f56f9b"), slow) < slow);
+    }
 
     #[test]
     fn test_task_to_job_full_converts_high_level_types() {
@@ -1028,7 +3612,7 @@ mod tests {
             en2: Some(dummy_en2),
             share_target: crate::types::Difficulty::from(100_u64).to_target(),
             ntime: *esp_miner_job::wire_tx::NTIME,
-            share_tx,
+            share_tx: share_tx.into(),
         };
 
         // Convert to JobFullFormat
@@ -0,0 +1,361 @@
+//! Register writes that are read back to confirm the chip took them.
+//!
+//! Writes to a chip get no acknowledgement, so a write lost to a glitch
+//! on the serial link or a chip that wasn't listening yet goes unnoticed
+//! until the board hashes poorly. A [`WriteBatch`] sends a sequence of
+//! writes, reads each readable register back, and rewrites the ones that
+//! don't match, giving up after [`WRITE_ATTEMPTS`] rounds.
+//!
+//! Core registers are written indirectly through a window that can't be
+//! read back, and a baud rate change takes effect before any read could
+//! come back at the old rate, so neither is verified.
+
+use std::time::Duration;
+
+use futures::{Sink, SinkExt, Stream};
+use tokio_stream::StreamExt;
+
+use super::error::WriteBatchError;
+use super::protocol::{Command, Register, RegisterAddress, Response};
+use crate::tracing::prelude::*;
+
+/// Rounds of writing and reading back before a batch fails.
+pub const WRITE_ATTEMPTS: usize = 3;
+
+/// How long to wait for read-back answers after the last read goes out.
+const READBACK_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// A register that read back differently from what was written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    pub chip_address: u8,
+    pub register: RegisterAddress,
+    pub wrote: u32,
+    /// Value read back, or `None` if the chip didn't answer
+    pub read: Option<u32>,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} on chip 0x{:02x}: wrote {:#010x}, ",
+            self.register, self.chip_address, self.wrote
+        )?;
+        match self.read {
+            Some(read) => write!(f, "read {read:#010x}"),
+            None => write!(f, "no answer"),
+        }
+    }
+}
+
+/// A sequence of register writes to one chip or all of them.
+#[derive(Debug, Clone)]
+pub struct WriteBatch {
+    broadcast: bool,
+    chip_address: u8,
+    /// Chips read back after the writes
+    verify_at: Vec<u8>,
+    /// Registers in write order, and whether each is read back
+    writes: Vec<(Register, bool)>,
+}
+
+impl WriteBatch {
+    /// Writes broadcast to every chip, read back from the chips at
+    /// `chip_addresses`.
+    pub fn broadcast(chip_addresses: &[u8]) -> Self {
+        Self {
+            broadcast: true,
+            chip_address: 0x00,
+            verify_at: chip_addresses.to_vec(),
+            writes: Vec::new(),
+        }
+    }
+
+    /// Writes addressed to the chip at `chip_address` alone.
+    pub fn to_chip(chip_address: u8) -> Self {
+        Self {
+            broadcast: false,
+            chip_address,
+            verify_at: vec![chip_address],
+            writes: Vec::new(),
+        }
+    }
+
+    /// Add a write, read back afterwards if the register allows it.
+    pub fn write(mut self, register: Register) -> Self {
+        let verify = is_verifiable(register.address());
+        self.writes.push((register, verify));
+        self
+    }
+
+    /// Send the writes and confirm the chips took them.
+    ///
+    /// Mismatched registers are written again, up to [`WRITE_ATTEMPTS`]
+    /// rounds in all. Responses other than the read-backs are dropped, so
+    /// this is meant for initialization, before the chips have work.
+    pub async fn apply<R, W>(
+        &self,
+        chip_commands: &mut W,
+        chip_responses: &mut R,
+    ) -> Result<(), WriteBatchError>
+    where
+        R: Stream<Item = Result<Response, std::io::Error>> + Unpin,
+        W: Sink<Command> + Unpin,
+        W::Error: std::fmt::Debug,
+    {
+        let mut to_write: Vec<&Register> = self.writes.iter().map(|(r, _)| r).collect();
+        let mut to_check = self.expected();
+        let mut mismatches = Vec::new();
+
+        for attempt in 1..=WRITE_ATTEMPTS {
+            for register in to_write {
+                let command = Command::WriteRegister {
+                    broadcast: self.broadcast,
+                    chip_address: self.chip_address,
+                    register: register.clone(),
+                };
+                chip_commands.send(command).await.map_err(|e| {
+                    WriteBatchError::Send(format!("{:?} write: {e:?}", register.address()))
+                })?;
+            }
+
+            mismatches = self
+                .read_back(&to_check, chip_commands, chip_responses)
+                .await?;
+            if mismatches.is_empty() {
+                return Ok(());
+            }
+            for mismatch in &mismatches {
+                warn!(attempt, %mismatch, "Register did not take");
+            }
+
+            // Rewrite and recheck only what didn't take
+            to_check.retain(|r| mismatches.iter().any(|m| m.register == r.address()));
+            to_write = to_check.clone();
+        }
+
+        Err(WriteBatchError::Mismatch {
+            attempts: WRITE_ATTEMPTS,
+            mismatches,
+        })
+    }
+
+    /// Last value written to each verified register.
+    fn expected(&self) -> Vec<&Register> {
+        let mut expected: Vec<&Register> = Vec::new();
+        for (register, _) in self.writes.iter().filter(|(_, verify)| *verify) {
+            expected.retain(|r| r.address() != register.address());
+            expected.push(register);
+        }
+        expected
+    }
+
+    /// Read `registers` back from each chip and list those that differ.
+    async fn read_back<R, W>(
+        &self,
+        registers: &[&Register],
+        chip_commands: &mut W,
+        chip_responses: &mut R,
+    ) -> Result<Vec<Mismatch>, WriteBatchError>
+    where
+        R: Stream<Item = Result<Response, std::io::Error>> + Unpin,
+        W: Sink<Command> + Unpin,
+        W::Error: std::fmt::Debug,
+    {
+        let mut pending: Vec<Mismatch> = Vec::new();
+        for &chip_address in &self.verify_at {
+            for register in registers {
+                chip_commands
+                    .send(Command::ReadRegister {
+                        broadcast: false,
+                        chip_address,
+                        register_address: register.address(),
+                    })
+                    .await
+                    .map_err(|e| {
+                        WriteBatchError::Send(format!("{:?} read: {e:?}", register.address()))
+                    })?;
+                pending.push(Mismatch {
+                    chip_address,
+                    register: register.address(),
+                    wrote: register.raw_value(),
+                    read: None,
+                });
+            }
+        }
+
+        let deadline = tokio::time::Instant::now() + READBACK_TIMEOUT;
+        let mut answered = 0;
+        while answered < pending.len() {
+            let response = match tokio::time::timeout_at(deadline, chip_responses.next()).await {
+                Ok(Some(response)) => response,
+                Ok(None) | Err(_) => break,
+            };
+            let Ok(Response::ReadRegister {
+                chip_address,
+                register,
+            }) = response
+            else {
+                continue;
+            };
+            if let Some(entry) = pending.iter_mut().find(|m| {
+                m.chip_address == chip_address
+                    && m.register == register.address()
+                    && m.read.is_none()
+            }) {
+                entry.read = Some(register.raw_value());
+                answered += 1;
+            }
+        }
+
+        pending.retain(|m| m.read != Some(m.wrote));
+        Ok(pending)
+    }
+}
+
+/// Whether a register reads back what was written to it.
+fn is_verifiable(address: RegisterAddress) -> bool {
+    !matches!(
+        address,
+        RegisterAddress::ChipId | RegisterAddress::Core | RegisterAddress::UartBaud
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use futures::channel::mpsc;
+
+    use super::*;
+
+    /// Host end of a link to a fake chip, and every command it was sent.
+    type FakeLink = (
+        mpsc::UnboundedSender<Command>,
+        mpsc::UnboundedReceiver<Result<Response, std::io::Error>>,
+        Arc<Mutex<Vec<Command>>>,
+    );
+
+    /// Chip link that answers reads from its register file. `drop_writes`
+    /// writes to a register are lost before one takes.
+    fn fake_chip(drop_writes: HashMap<u8, usize>) -> FakeLink {
+        let (command_tx, mut command_rx) = mpsc::unbounded::<Command>();
+        let (response_tx, response_rx) = mpsc::unbounded();
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&sent);
+        tokio::spawn(async move {
+            let mut registers: HashMap<u8, Register> = HashMap::new();
+            let mut drop_writes = drop_writes;
+            while let Some(command) = command_rx.next().await {
+                match &command {
+                    Command::WriteRegister { register, .. } => {
+                        let address = register.address() as u8;
+                        match drop_writes.get_mut(&address) {
+                            Some(n) if *n > 0 => *n -= 1,
+                            _ => {
+                                registers.insert(address, register.clone());
+                            }
+                        }
+                    }
+                    Command::ReadRegister {
+                        chip_address,
+                        register_address,
+                        ..
+                    } => {
+                        if let Some(register) = registers.get(&(*register_address as u8)) {
+                            let _ = response_tx.unbounded_send(Ok(Response::ReadRegister {
+                                chip_address: *chip_address,
+                                register: register.clone(),
+                            }));
+                        }
+                    }
+                    _ => {}
+                }
+                log.lock().unwrap().push(command);
+            }
+        });
+        (command_tx, response_rx, sent)
+    }
+
+    fn batch() -> WriteBatch {
+        WriteBatch::broadcast(&[0x00])
+            .write(Register::MiscControl {
+                raw_value: 0x00C1_00F0,
+            })
+            .write(Register::Core {
+                raw_value: 0x8000_8B00,
+            })
+            .write(Register::AnalogMux {
+                raw_value: 0x0200_0000,
+            })
+    }
+
+    fn writes_to(sent: &Mutex<Vec<Command>>, address: RegisterAddress) -> usize {
+        sent.lock()
+            .unwrap()
+            .iter()
+            .filter(|c| {
+                matches!(c, Command::WriteRegister { register, .. } if register.address() == address)
+            })
+            .count()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn writes_and_reads_back_once_when_registers_take() {
+        let (mut commands, mut responses, sent) = fake_chip(HashMap::new());
+        batch().apply(&mut commands, &mut responses).await.unwrap();
+
+        let sent = sent.lock().unwrap();
+        let reads: Vec<_> = sent
+            .iter()
+            .filter_map(|c| match c {
+                Command::ReadRegister {
+                    register_address, ..
+                } => Some(*register_address),
+                _ => None,
+            })
+            .collect();
+        // Core can't be read back
+        assert_eq!(
+            reads,
+            [RegisterAddress::MiscControl, RegisterAddress::AnalogMux]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rewrites_a_register_that_did_not_take() {
+        let (mut commands, mut responses, sent) =
+            fake_chip(HashMap::from([(RegisterAddress::AnalogMux as u8, 1)]));
+        batch().apply(&mut commands, &mut responses).await.unwrap();
+
+        assert_eq!(writes_to(&sent, RegisterAddress::AnalogMux), 2);
+        assert_eq!(writes_to(&sent, RegisterAddress::MiscControl), 1);
+        assert_eq!(writes_to(&sent, RegisterAddress::Core), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_after_repeated_mismatches() {
+        let (mut commands, mut responses, _sent) = fake_chip(HashMap::from([(
+            RegisterAddress::AnalogMux as u8,
+            usize::MAX,
+        )]));
+        let err = batch()
+            .apply(&mut commands, &mut responses)
+            .await
+            .unwrap_err();
+
+        let WriteBatchError::Mismatch {
+            attempts,
+            mismatches,
+        } = err
+        else {
+            panic!("expected mismatch, got {err:?}");
+        };
+        assert_eq!(attempts, WRITE_ATTEMPTS);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].register, RegisterAddress::AnalogMux);
+        assert_eq!(mismatches[0].read, None);
+    }
+}
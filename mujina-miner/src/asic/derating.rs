@@ -0,0 +1,257 @@
+//! Thermal derating of chip core frequency.
+//!
+//! A [`DeratingCurve`] maps temperature bands to the highest core frequency
+//! allowed in each band. Hash threads feed die temperature readings into a
+//! [`FrequencyLimiter`], which picks the band with a little hysteresis so
+//! the ceiling doesn't toggle when the temperature sits on a boundary. As
+//! the chip heats up the frequency steps down band by band, and climbs back
+//! once it has cooled.
+//!
+//! Curves are written as comma-separated `temp_c:max_mhz` pairs, e.g.
+//! `70:450,80:350,90:200`: at 70 °C and above the core runs at most
+//! 450 MHz, from 80 °C at most 350 MHz, and so on. Below the first band
+//! the frequency is not limited. See [`crate::config`] for where the table
+//! is set.
+
+use std::str::FromStr;
+
+use super::thermal::DEFAULT_OVERSHOOT_C;
+
+/// Errors from building a derating curve.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum DeratingError {
+    #[error("invalid band {0:?}, expected temp_c:max_mhz")]
+    InvalidBand(String),
+
+    #[error("band values must be finite and the frequency positive")]
+    InvalidValue,
+
+    #[error("frequency must not increase with temperature ({0} °C band)")]
+    NotDecreasing(f32),
+}
+
+/// One row of the derating table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeratingBand {
+    /// Temperature at which this band starts to apply.
+    pub min_temp_c: f32,
+    /// Highest core frequency allowed in this band.
+    pub max_freq_mhz: f32,
+}
+
+/// Maximum core frequency by temperature.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeratingCurve {
+    /// Bands sorted by ascending temperature.
+    bands: Vec<DeratingBand>,
+}
+
+impl DeratingCurve {
+    /// Build a curve from bands in any order.
+    ///
+    /// Hotter bands must not allow a higher frequency than cooler ones.
+    pub fn new(mut bands: Vec<DeratingBand>) -> Result<Self, DeratingError> {
+        if bands.iter().any(|b| {
+            !b.min_temp_c.is_finite() || !b.max_freq_mhz.is_finite() || b.max_freq_mhz <= 0.0
+        }) {
+            return Err(DeratingError::InvalidValue);
+        }
+
+        bands.sort_by(|a, b| a.min_temp_c.total_cmp(&b.min_temp_c));
+        if let Some(pair) = bands
+            .windows(2)
+            .find(|pair| pair[1].max_freq_mhz > pair[0].max_freq_mhz)
+        {
+            return Err(DeratingError::NotDecreasing(pair[1].min_temp_c));
+        }
+
+        Ok(Self { bands })
+    }
+
+    /// Whether the curve has no bands (never limits frequency).
+    pub fn is_empty(&self) -> bool {
+        self.bands.is_empty()
+    }
+
+    /// Frequency ceiling at `temp_c`, or `None` below the first band.
+    pub fn max_frequency(&self, temp_c: f32) -> Option<f32> {
+        self.band_at(temp_c).map(|i| self.bands[i].max_freq_mhz)
+    }
+
+    /// Index of the hottest band whose threshold `temp_c` has reached.
+    fn band_at(&self, temp_c: f32) -> Option<usize> {
+        self.bands.iter().rposition(|b| temp_c >= b.min_temp_c)
+    }
+}
+
+impl FromStr for DeratingCurve {
+    type Err = DeratingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bands = s
+            .split(',')
+            .map(str::trim)
+            .filter(|band| !band.is_empty())
+            .map(|band| {
+                let invalid = || DeratingError::InvalidBand(band.to_string());
+                let (temp, freq) = band.split_once(':').ok_or_else(invalid)?;
+                Ok(DeratingBand {
+                    min_temp_c: temp.trim().parse().map_err(|_| invalid())?,
+                    max_freq_mhz: freq.trim().parse().map_err(|_| invalid())?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(bands)
+    }
+}
+
+/// Tracks the active derating band for one chip or chain.
+#[derive(Debug, Clone)]
+pub struct FrequencyLimiter {
+    curve: DeratingCurve,
+    band: Option<usize>,
+    /// How far below a band's threshold the temperature must fall before
+    /// the limiter leaves the band
+    overshoot_c: f32,
+}
+
+impl FrequencyLimiter {
+    pub fn new(curve: DeratingCurve) -> Self {
+        Self {
+            curve,
+            band: None,
+            overshoot_c: DEFAULT_OVERSHOOT_C,
+        }
+    }
+
+    /// Leave a band only once the temperature is `overshoot_c` below its
+    /// threshold, instead of the default margin.
+    pub fn with_overshoot(mut self, overshoot_c: f32) -> Self {
+        self.overshoot_c = overshoot_c;
+        self
+    }
+
+    /// Feed a temperature reading and return the current ceiling.
+    ///
+    /// Moves to a hotter band as soon as its threshold is reached, but only
+    /// back to a cooler one once the temperature is the overshoot margin
+    /// below the current band's threshold.
+    pub fn update(&mut self, temp_c: f32) -> Option<f32> {
+        let reached = self.curve.band_at(temp_c);
+        self.band = if reached > self.band {
+            reached
+        } else {
+            self.band.min(self.curve.band_at(temp_c + self.overshoot_c))
+        };
+        self.ceiling()
+    }
+
+    /// Assume the worst while there is no temperature to go by.
+    ///
+    /// Moves to the hottest band and returns its ceiling. The next reading
+    /// brings the limiter back down to the band it calls for.
+    pub fn blind(&mut self) -> Option<f32> {
+        self.band = self.curve.bands.len().checked_sub(1);
+        self.ceiling()
+    }
+
+    /// Curve the limiter applies.
+    pub fn curve(&self) -> &DeratingCurve {
+        &self.curve
+    }
+
+    /// Overshoot margin the limiter applies, °C.
+    pub fn overshoot_c(&self) -> f32 {
+        self.overshoot_c
+    }
+
+    /// Current ceiling, or `None` when not derating.
+    pub fn ceiling(&self) -> Option<f32> {
+        self.band.map(|i| self.curve.bands[i].max_freq_mhz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve() -> DeratingCurve {
+        "70:450, 80:350, 90:200".parse().unwrap()
+    }
+
+    #[test]
+    fn parses_and_looks_up_bands() {
+        let curve = curve();
+        assert_eq!(curve.max_frequency(65.0), None);
+        assert_eq!(curve.max_frequency(70.0), Some(450.0));
+        assert_eq!(curve.max_frequency(85.5), Some(350.0));
+        assert_eq!(curve.max_frequency(120.0), Some(200.0));
+    }
+
+    #[test]
+    fn rejects_invalid_tables() {
+        assert!(matches!(
+            "70-450".parse::<DeratingCurve>(),
+            Err(DeratingError::InvalidBand(_))
+        ));
+        assert_eq!(
+            "70:300,80:400".parse::<DeratingCurve>(),
+            Err(DeratingError::NotDecreasing(80.0))
+        );
+        assert_eq!(
+            "70:0".parse::<DeratingCurve>(),
+            Err(DeratingError::InvalidValue)
+        );
+        assert!("".parse::<DeratingCurve>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn limiter_steps_down_immediately() {
+        let mut limiter = FrequencyLimiter::new(curve());
+        assert_eq!(limiter.update(60.0), None);
+        assert_eq!(limiter.update(71.0), Some(450.0));
+        assert_eq!(limiter.update(92.0), Some(200.0));
+    }
+
+    #[test]
+    fn limiter_holds_band_within_hysteresis() {
+        let mut limiter = FrequencyLimiter::new(curve());
+        assert_eq!(limiter.update(80.0), Some(350.0));
+
+        // Hovering just under the threshold keeps the lower ceiling
+        assert_eq!(limiter.update(79.5), Some(350.0));
+        assert_eq!(limiter.update(78.0), Some(350.0));
+
+        // Clearly cooler: back to the previous band
+        assert_eq!(limiter.update(76.0), Some(450.0));
+        assert_eq!(limiter.update(60.0), None);
+    }
+
+    #[test]
+    fn wider_overshoot_holds_the_band_longer() {
+        let mut limiter = FrequencyLimiter::new(curve()).with_overshoot(8.0);
+        assert_eq!(limiter.update(80.0), Some(350.0));
+        assert_eq!(limiter.update(74.0), Some(350.0));
+        assert_eq!(limiter.update(71.0), Some(450.0));
+    }
+
+    #[test]
+    fn limiter_drops_several_bands_when_cooled() {
+        let mut limiter = FrequencyLimiter::new(curve());
+        assert_eq!(limiter.update(95.0), Some(200.0));
+        assert_eq!(limiter.update(72.0), Some(450.0));
+    }
+
+    #[test]
+    fn blind_limiter_takes_the_hottest_band_until_the_next_reading() {
+        let mut limiter = FrequencyLimiter::new(curve());
+        assert_eq!(limiter.update(60.0), None);
+        assert_eq!(limiter.blind(), Some(200.0));
+        assert_eq!(limiter.update(60.0), None);
+
+        assert_eq!(
+            FrequencyLimiter::new(DeratingCurve::default()).blind(),
+            None
+        );
+    }
+}
@@ -28,28 +28,59 @@
 
 use std::fmt;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use bitcoin::BlockHash;
 use bitcoin::block::Version;
 use bitcoin::pow::Target;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
 
-use crate::job_source::{Extranonce2, Extranonce2Range, JobTemplate};
-use crate::types::HashRate;
+use super::ChipStats;
+use crate::api_client::types::{ChipNonceReport, JobDispatchStats};
+use crate::job_source::{Extranonce2, Extranonce2Range, GeneralPurposeBits, JobTemplate};
+use crate::types::{Difficulty, HashRate, ReadingSender};
 use bitcoin::pow::Work;
 
-/// HashThread capabilities reported to scheduler for work assignment decisions.
+/// What a HashThread can do, reported to the scheduler at registration.
+///
+/// The scheduler answers with [`AssignmentParameters`] through
+/// [`HashThread::negotiate`] before assigning any work.
 #[derive(Debug, Clone)]
 pub struct HashThreadCapabilities {
     /// Estimated hashrate
     pub hashrate_estimate: HashRate,
-    // Future capabilities:
-    // pub can_roll_version: bool,
-    // pub version_roll_bits: u32,
-    // pub can_roll_ntime: bool,
-    // pub ntime_range: Option<std::ops::Range<u32>>,
-    // pub can_iterate_extranonce2: bool,
+
+    /// Version bits the hardware can roll (BIP320 general purpose bits).
+    ///
+    /// Tasks narrow this further to the bits their source allows.
+    pub version_rolling: GeneralPurposeBits,
+
+    /// How far the thread can roll ntime past a task's value, in seconds
+    /// (0 if it doesn't roll ntime).
+    pub max_ntime_roll: u32,
+
+    /// Whether the thread walks its assigned extranonce2 range itself.
+    ///
+    /// Threads that don't only ever mine the starting value of a task.
+    pub iterates_extranonce2: bool,
+
+    /// Share difficulty at which the hardware reports nonces, if it
+    /// filters them itself.
+    ///
+    /// Nonces arrive no more often than this difficulty allows, so a share
+    /// target easier than it would undercount the work behind each share.
+    pub reporting_difficulty: Option<Difficulty>,
+}
+
+/// Terms the scheduler assigns work under, returned at registration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignmentParameters {
+    /// How far the thread may roll ntime past a task's value, in seconds.
+    ///
+    /// Never more than the thread's own `max_ntime_roll`.
+    pub max_ntime_roll: u32,
 }
 
 /// Current runtime status of a HashThread.
@@ -61,9 +92,24 @@ pub struct HashThreadStatus {
     /// Number of shares found (at chip target level, before pool filtering)
     pub chip_shares_found: u64,
 
-    /// Number of shares submitted to pool (after filtering)
+    /// Shares forwarded to the scheduler: chip shares meeting the task's
+    /// share target
+    pub shares_forwarded: u64,
+
+    /// Number of shares submitted to pool (after filtering): forwarded
+    /// shares also meeting the pool's target
     pub pool_shares_submitted: u64,
 
+    /// Difficulty the chips are programmed to report nonces at (their
+    /// ticket mask), or None for hardware without one
+    pub chip_difficulty: Option<Difficulty>,
+
+    /// Share target of the task being worked on
+    pub share_target: Option<Target>,
+
+    /// Pool's share target for the task being worked on
+    pub pool_target: Option<Target>,
+
     /// Number of hardware errors detected
     pub hardware_errors: u64,
 
@@ -72,13 +118,48 @@ pub struct HashThreadStatus {
 
     /// Whether thread is actively working
     pub is_active: bool,
+
+    /// Per-chip statistics, in chain order
+    pub chips: Vec<ChipStats>,
+
+    /// Per-chip nonce distribution, in chain order; empty for threads
+    /// that don't track it
+    pub nonce_map: Vec<ChipNonceReport>,
+
+    /// Status updates dropped because the event channel was full; the
+    /// next update supersedes them
+    pub status_updates_coalesced: u64,
+
+    /// Nonces reported a second time and not credited, from chips that
+    /// ran out of header space before fresh work reached them
+    pub duplicate_nonces: u64,
+
+    /// Times a chip's PLL read back other than the frequency written,
+    /// as after a chip resets itself
+    pub frequency_mismatches: u64,
+
+    /// Chip resets, whether the thread re-initialized the chips or they
+    /// reset themselves
+    pub chip_resets: u64,
+
+    /// Whether the chips reset so often they are held at reduced
+    /// frequency rather than re-initialized again
+    pub quarantined: bool,
+
+    /// Frames from the chips lost to CRC or decode errors
+    pub link_errors: u64,
+
+    /// Whether link errors spiked and the chips run at reduced baud rate
+    /// and frequency until a probe at full speed stays clean
+    pub link_degraded: bool,
 }
 
 /// Events emitted by HashThreads back to the scheduler.
 ///
 /// When a thread shuts down (USB unplug, fault, user request, etc.), it closes
-/// its event channel instead of sending an event. The scheduler detects channel
-/// closure and handles thread removal.
+/// its event channel. The scheduler detects channel closure and handles thread
+/// removal. On a graceful shutdown the thread sends
+/// [`GoingOffline`](HashThreadEvent::GoingOffline) first.
 ///
 /// Note: Shares are sent via the task's dedicated `share_tx` channel, not
 /// through this event channel. This separates share routing (task-specific)
@@ -98,7 +179,10 @@ pub enum HashThreadEvent {
     },
 
     /// Periodic status update
-    StatusUpdate(HashThreadStatus),
+    StatusUpdate(Box<HashThreadStatus>),
+
+    /// Thread is shutting down gracefully; the channel closes next
+    GoingOffline,
 }
 
 /// Error types for HashThread operations.
@@ -121,6 +205,9 @@ pub enum HashThreadError {
 
     #[error("Chip initialization failed: {0}")]
     InitializationFailed(String),
+
+    #[error("Register access failed: {0}")]
+    RegisterAccess(String),
 }
 
 // ---------------------------------------------------------------------------
@@ -150,6 +237,26 @@ pub trait VoltageRegulator: Send + Sync {
     async fn set_voltage(&mut self, volts: f32) -> anyhow::Result<()>;
 }
 
+/// Host-side baud rate control for the chip data link.
+///
+/// Chips typically power up at a conservative line rate. Hash threads that
+/// negotiate a faster rate with the chips use this to follow on the host
+/// side, and to fall back to the power-up rate when the chips are reset.
+#[async_trait]
+pub trait BaudRateControl: Send + Sync {
+    /// Line rate the chips use after reset, in bits per second.
+    fn initial_baud_rate(&self) -> u32;
+
+    /// Line rate to switch to once the chips are configured.
+    fn target_baud_rate(&self) -> u32;
+
+    /// Reconfigure the host side of the link.
+    ///
+    /// Implementations must let pending output drain at the old rate
+    /// before switching.
+    async fn set_baud_rate(&mut self, baud: u32) -> anyhow::Result<()>;
+}
+
 /// Hardware interfaces provided by the board to the hash thread.
 ///
 /// Bundles optional hardware capabilities. Not all boards provide all
@@ -160,6 +267,46 @@ pub struct BoardPeripherals {
 
     /// Voltage regulator control
     pub voltage_regulator: Option<Box<dyn VoltageRegulator>>,
+
+    /// Data link baud rate control
+    pub baud_control: Option<Box<dyn BaudRateControl>>,
+
+    /// Where the thread publishes temperature read from the chip itself.
+    ///
+    /// Lets the board report the on-die sensor alongside its own, which
+    /// typically read well below junction temperature. Each reading is
+    /// stamped, so the board notices when the thread stops polling.
+    pub chip_temperature: Option<ReadingSender<f32>>,
+
+    /// Where the thread publishes the power state it has put the chips in.
+    ///
+    /// Lets the board attribute its power readings to hashing or idling.
+    pub power_state: Option<watch::Sender<ChipPowerState>>,
+
+    /// Where the thread publishes its status alongside the scheduler's copy.
+    ///
+    /// Lets the board report per-thread hashrate with its own telemetry.
+    pub thread_status: Option<watch::Sender<HashThreadStatus>>,
+
+    /// Where the thread publishes its job dispatch timing.
+    ///
+    /// Kept out of the status: the figures are estimates for the board's
+    /// API, not state the scheduler or remote agents need.
+    pub job_dispatch: Option<watch::Sender<Option<JobDispatchStats>>>,
+}
+
+/// Power state a hash thread has put its chips in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChipPowerState {
+    /// Held in reset or not yet initialized
+    #[default]
+    Off,
+
+    /// Clocked up and hashing
+    Hashing,
+
+    /// Configured but with core clocks gated, waiting for work
+    LowPower,
 }
 
 /// Signal from board to hash thread for shutdown coordination.
@@ -167,8 +314,10 @@ pub struct BoardPeripherals {
 /// Board sends this via watch channel to signal thread shutdown. Thread checks
 /// in its select loop and exits when signal changes from Running.
 ///
-/// The reason is useful for logging but thread behavior is identical for all
-/// non-Running variants: clean up and exit.
+/// Every non-Running variant makes the thread exit. The reason decides what
+/// cleanup happens on the way out: a faulted chip is powered down, a user
+/// disable forwards in-flight shares first, and a graceful shutdown tells
+/// the scheduler before the event channel closes.
 #[derive(Clone, Debug, PartialEq)]
 pub enum ThreadRemovalSignal {
     /// Thread should continue running normally
@@ -204,6 +353,33 @@ pub trait HashThread: Send {
     /// Get thread capabilities for scheduling decisions
     fn capabilities(&self) -> &HashThreadCapabilities;
 
+    /// Accept the parameters the scheduler will assign work under.
+    ///
+    /// Called once when the thread registers, before any task is assigned.
+    /// Returning an error refuses the terms; the scheduler then drops the
+    /// thread.
+    async fn negotiate(
+        &mut self,
+        params: AssignmentParameters,
+    ) -> std::result::Result<(), HashThreadError>;
+
+    /// Stable ID of the hardware behind this thread (e.g., board serial)
+    ///
+    /// Shares are attributed to this ID so sources can submit them under
+    /// per-device worker names. Threads without an identity return None.
+    fn device_id(&self) -> Option<&str> {
+        None
+    }
+
+    /// Round trip to the hardware, for threads reached over a network
+    ///
+    /// A hint to the scheduler, which hands fresh work to the threads
+    /// furthest away first so it reaches every chip at about the same
+    /// time. Locally attached threads return None.
+    fn link_latency(&self) -> Option<Duration> {
+        None
+    }
+
     /// Update current task (shares from old task still valid)
     ///
     /// Thread continues hashing old task until new task is ready. Late-arriving
@@ -295,7 +471,7 @@ pub struct HashTask {
     ///
     /// Scheduler creates this channel and keeps the receiver. Thread sends
     /// valid shares here; channel ownership implicitly routes to correct source.
-    pub share_tx: mpsc::Sender<Share>,
+    pub share_tx: ShareSender,
 }
 
 impl fmt::Debug for HashTask {
@@ -311,6 +487,86 @@ impl fmt::Debug for HashTask {
     }
 }
 
+/// Sending half of a task's share channel.
+///
+/// Shares are never dropped: when the scheduler falls behind, sending
+/// waits for room. Each wait is counted in a [`ChannelPressure`] shared by
+/// the tasks of one thread, so a chip target set too low shows up as
+/// saturation rather than as silent latency.
+#[derive(Clone)]
+pub struct ShareSender {
+    tx: mpsc::Sender<Share>,
+    pressure: Arc<ChannelPressure>,
+}
+
+impl ShareSender {
+    pub fn new(tx: mpsc::Sender<Share>, pressure: Arc<ChannelPressure>) -> Self {
+        Self { tx, pressure }
+    }
+
+    /// Send a share, waiting for room if the channel is full.
+    ///
+    /// Fails only if the task was replaced and the channel closed.
+    pub async fn send(&self, share: Share) -> Result<(), mpsc::error::SendError<Share>> {
+        match self.tx.try_send(share) {
+            Ok(()) => Ok(()),
+            Err(mpsc::error::TrySendError::Closed(share)) => Err(mpsc::error::SendError(share)),
+            Err(mpsc::error::TrySendError::Full(share)) => {
+                let started = Instant::now();
+                let result = self.tx.send(share).await;
+                self.pressure.record_wait(started.elapsed());
+                result
+            }
+        }
+    }
+
+    /// Send a share from outside the async runtime, blocking for room.
+    pub fn blocking_send(&self, share: Share) -> Result<(), mpsc::error::SendError<Share>> {
+        match self.tx.try_send(share) {
+            Ok(()) => Ok(()),
+            Err(mpsc::error::TrySendError::Closed(share)) => Err(mpsc::error::SendError(share)),
+            Err(mpsc::error::TrySendError::Full(share)) => {
+                let started = Instant::now();
+                let result = self.tx.blocking_send(share);
+                self.pressure.record_wait(started.elapsed());
+                result
+            }
+        }
+    }
+}
+
+impl From<mpsc::Sender<Share>> for ShareSender {
+    /// A sender whose waits aren't counted anywhere else.
+    fn from(tx: mpsc::Sender<Share>) -> Self {
+        Self::new(tx, Arc::default())
+    }
+}
+
+/// How often, and for how long, shares waited for a full share channel.
+#[derive(Debug, Default)]
+pub struct ChannelPressure {
+    waits: AtomicU64,
+    wait_micros: AtomicU64,
+}
+
+impl ChannelPressure {
+    fn record_wait(&self, waited: Duration) {
+        self.waits.fetch_add(1, Ordering::Relaxed);
+        self.wait_micros
+            .fetch_add(waited.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Number of shares that had to wait.
+    pub fn waits(&self) -> u64 {
+        self.waits.load(Ordering::Relaxed)
+    }
+
+    /// Total time shares spent waiting.
+    pub fn wait_time(&self) -> Duration {
+        Duration::from_micros(self.wait_micros.load(Ordering::Relaxed))
+    }
+}
+
 /// Valid share found by a HashThread.
 ///
 /// Contains the nonce and computed hash, plus header fields needed for pool
@@ -352,6 +608,51 @@ impl From<(Share, String)> for crate::job_source::Share {
             time: share.ntime,
             version: share.version,
             extranonce2: share.extranonce2,
+            device_id: None,
+            hash: share.hash,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+
+    fn share(nonce: u32) -> Share {
+        Share {
+            nonce,
+            hash: BlockHash::all_zeros(),
+            version: Version::from_consensus(0x20000000),
+            ntime: 0,
+            extranonce2: None,
+            expected_work: Target::MAX.to_work(),
         }
     }
+
+    #[tokio::test]
+    async fn full_share_channel_waits_instead_of_dropping() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let pressure = Arc::new(ChannelPressure::default());
+        let sender = ShareSender::new(tx, pressure.clone());
+
+        sender.send(share(1)).await.unwrap();
+        assert_eq!(pressure.waits(), 0);
+
+        // The channel is full; the second share waits for the receiver
+        let blocked = tokio::spawn({
+            let sender = sender.clone();
+            async move { sender.send(share(2)).await }
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(rx.recv().await.unwrap().nonce, 1);
+        blocked.await.unwrap().unwrap();
+        assert_eq!(rx.recv().await.unwrap().nonce, 2);
+        assert_eq!(pressure.waits(), 1);
+
+        // A closed channel means the task was replaced
+        drop(rx);
+        assert!(sender.send(share(3)).await.is_err());
+        assert_eq!(pressure.waits(), 1);
+    }
 }
@@ -1,10 +1,18 @@
 pub mod bm13xx;
+pub mod derating;
 pub mod hash_thread;
+pub mod slew;
+pub mod thermal;
+pub mod thermal_tune;
+pub mod verify_pool;
+pub mod warmup;
 
 use async_trait::async_trait;
 use std::error::Error;
 use std::fmt;
 
+use crate::types::HashRate;
+
 /// Represents a mining ASIC chip.
 ///
 /// A chip receives mining jobs and returns nonces when it finds valid shares.
@@ -58,6 +66,8 @@ pub struct ChipStats {
     pub frequency_mhz: Option<u32>,
     /// Current temperature in Celsius
     pub temperature_c: Option<f32>,
+    /// Hashrate estimated from the chip's own nonce rate, once settled
+    pub hashrate: Option<HashRate>,
 }
 
 /// A mining job to be processed by a chip
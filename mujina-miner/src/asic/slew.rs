@@ -0,0 +1,128 @@
+//! Rate limiting of frequency and fan changes to limit thermal cycling.
+//!
+//! Every heat-up and cool-down strains the solder joints under a chip, and
+//! the strain grows with how fast the temperature moves. A [`ThermalSlew`]
+//! caps that rate in °C per minute. Chip temperature isn't an output the
+//! miner sets directly, so the cap is turned into a largest change per
+//! minute of the two actuators that move it, the core frequency and the
+//! fan duty, using rough sensitivities of die temperature to each. Hash
+//! threads and boards then step towards new settings by at most that much
+//! at a time instead of jumping.
+//!
+//! Thermal derating is exempt when it lowers the frequency: a chip that is
+//! too hot slows down at once. The cap is set through [`crate::config`].
+
+use std::time::Duration;
+
+/// Steady-state die temperature change per MHz of core frequency.
+///
+/// On the high side for a single-chip board with a small heatsink, so the
+/// frequency moves slowly enough on bigger ones too.
+const C_PER_MHZ: f32 = 0.1;
+
+/// Steady-state die temperature change per percent of fan duty.
+///
+/// Airflow matters most at low duty; this is the steep end.
+const C_PER_FAN_PERCENT: f32 = 0.5;
+
+/// Errors from setting a thermal slew limit.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum SlewError {
+    #[error("slew limit must be a positive number of °C per minute")]
+    InvalidRate,
+}
+
+/// Largest rate of die temperature change the actuators may cause.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThermalSlew {
+    max_c_per_min: f32,
+}
+
+impl ThermalSlew {
+    /// Limit temperature swings to `max_c_per_min` °C per minute.
+    pub fn new(max_c_per_min: f32) -> Result<Self, SlewError> {
+        if !max_c_per_min.is_finite() || max_c_per_min <= 0.0 {
+            return Err(SlewError::InvalidRate);
+        }
+        Ok(Self { max_c_per_min })
+    }
+
+    pub fn max_c_per_min(&self) -> f32 {
+        self.max_c_per_min
+    }
+
+    /// Core frequency change allowed over `elapsed`, in MHz.
+    pub fn max_frequency_change(&self, elapsed: Duration) -> f32 {
+        self.per_minute(elapsed) / C_PER_MHZ
+    }
+
+    /// Fan duty change allowed over `elapsed`, in percent.
+    pub fn max_fan_change(&self, elapsed: Duration) -> f32 {
+        self.per_minute(elapsed) / C_PER_FAN_PERCENT
+    }
+
+    /// Next core frequency on the way from `from_mhz` to `to_mhz`, `elapsed`
+    /// after the last step.
+    pub fn frequency_step(&self, from_mhz: f32, to_mhz: f32, elapsed: Duration) -> f32 {
+        towards(from_mhz, to_mhz, self.max_frequency_change(elapsed))
+    }
+
+    /// Next fan duty on the way from `from` to `to`, `elapsed` after the
+    /// last step.
+    ///
+    /// Always moves by at least one percent, so a short step interval
+    /// can't stall the fan short of its target.
+    pub fn fan_step(&self, from: u8, to: u8, elapsed: Duration) -> u8 {
+        let max_change = self.max_fan_change(elapsed).max(1.0);
+        towards(f32::from(from), f32::from(to), max_change).round() as u8
+    }
+
+    /// Degrees allowed over `elapsed`.
+    fn per_minute(&self, elapsed: Duration) -> f32 {
+        self.max_c_per_min * elapsed.as_secs_f32() / 60.0
+    }
+}
+
+/// Move from `from` towards `to` by at most `max_change`.
+fn towards(from: f32, to: f32, max_change: f32) -> f32 {
+    from + (to - from).clamp(-max_change, max_change)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn rejects_non_positive_rates() {
+        assert!(ThermalSlew::new(0.0).is_err());
+        assert!(ThermalSlew::new(-1.0).is_err());
+        assert!(ThermalSlew::new(f32::NAN).is_err());
+        assert!(ThermalSlew::new(5.0).is_ok());
+    }
+
+    #[test]
+    fn frequency_moves_by_at_most_the_allowed_change() {
+        // 5 °C/min at 0.1 °C/MHz is 50 MHz a minute
+        let slew = ThermalSlew::new(5.0).unwrap();
+        assert_eq!(slew.frequency_step(300.0, 525.0, MINUTE), 350.0);
+        assert_eq!(slew.frequency_step(525.0, 300.0, MINUTE), 475.0);
+        assert_eq!(slew.frequency_step(500.0, 525.0, MINUTE), 525.0);
+        assert_eq!(
+            slew.frequency_step(300.0, 525.0, Duration::from_secs(6)),
+            305.0
+        );
+    }
+
+    #[test]
+    fn fan_always_makes_progress() {
+        // 5 °C/min at 0.5 °C/% is 10% a minute
+        let slew = ThermalSlew::new(5.0).unwrap();
+        assert_eq!(slew.fan_step(30, 100, MINUTE), 40);
+        assert_eq!(slew.fan_step(100, 30, MINUTE), 90);
+        assert_eq!(slew.fan_step(30, 100, Duration::from_secs(1)), 31);
+        assert_eq!(slew.fan_step(99, 100, MINUTE), 100);
+        assert_eq!(slew.fan_step(60, 60, MINUTE), 60);
+    }
+}
@@ -0,0 +1,122 @@
+//! Timing of the thermal control loop.
+//!
+//! A hash thread reads its chip's temperature once per tick, and derating
+//! acts on each reading as it arrives. Slewed frequency changes (and the
+//! simulated board's fan) move once per adjustment interval, by as much as
+//! the slew limit allows over that interval. A derating band is left only
+//! once the temperature has fallen the overshoot margin below its
+//! threshold. With a reference room temperature configured, a warmer room
+//! reported by an [external sensor](crate::sensors) brings derating
+//! forward by the difference. The defaults suit the chips' sensors and the
+//! boards' thermal mass; they are set through [`crate::config`].
+//!
+//! Everything here runs on [`tokio::time`], so tests with a paused clock
+//! can run a controller through hours of operation in moments.
+
+use std::time::Duration;
+
+/// Tick unless configured otherwise.
+pub const DEFAULT_TICK: Duration = Duration::from_secs(5);
+
+/// How far below a derating band's threshold the temperature must fall
+/// before the band is left, unless configured otherwise.
+pub const DEFAULT_OVERSHOOT_C: f32 = 3.0;
+
+/// Ticks without a temperature reading before the controller stops
+/// trusting the last one and assumes the worst.
+pub const STALE_TICKS: u32 = 6;
+
+/// Thermal control loop timing.
+///
+/// The tick must be positive. An adjustment interval shorter than the
+/// tick adjusts on every tick.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThermalConfig {
+    /// How often the temperature is read and derating applied.
+    pub tick: Duration,
+    /// How often slewed frequency and fan changes take a step.
+    pub adjust_interval: Duration,
+    /// Margin below a derating band's threshold before it is left, °C.
+    pub overshoot_c: f32,
+    /// Room temperature the derating curve was written for, °C; a warmer
+    /// room reported by an external sensor shifts the curve down by the
+    /// difference. `None` ignores the room temperature.
+    pub ambient_reference_c: Option<f32>,
+}
+
+impl Default for ThermalConfig {
+    fn default() -> Self {
+        Self {
+            tick: DEFAULT_TICK,
+            adjust_interval: DEFAULT_TICK,
+            overshoot_c: DEFAULT_OVERSHOOT_C,
+            ambient_reference_c: None,
+        }
+    }
+}
+
+impl ThermalConfig {
+    /// Ticks between adjustments, at least one.
+    pub fn ticks_per_adjustment(&self) -> u32 {
+        let ticks = self.adjust_interval.as_secs_f64() / self.tick.as_secs_f64();
+        (ticks.round() as u32).max(1)
+    }
+
+    /// Age past which a temperature reading is no longer trusted.
+    pub fn stale_after(&self) -> Duration {
+        self.tick * STALE_TICKS
+    }
+
+    /// Time between adjustments, a whole number of ticks.
+    pub fn adjustment_period(&self) -> Duration {
+        self.tick * self.ticks_per_adjustment()
+    }
+
+    /// Degrees to add to a die temperature before it is derated on, for a
+    /// room at `ambient_c`.
+    ///
+    /// A room warmer than the reference leaves the cooling less headroom,
+    /// so derating starts that much earlier. A cooler one never delays it.
+    pub fn ambient_offset_c(&self, ambient_c: Option<f32>) -> f32 {
+        match (self.ambient_reference_c, ambient_c) {
+            (Some(reference), Some(ambient)) => (ambient - reference).max(0.0),
+            _ => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adjusts_every_so_many_ticks() {
+        assert_eq!(ThermalConfig::default().ticks_per_adjustment(), 1);
+
+        let slow = ThermalConfig {
+            tick: Duration::from_secs(2),
+            adjust_interval: Duration::from_secs(30),
+            ..Default::default()
+        };
+        assert_eq!(slow.ticks_per_adjustment(), 15);
+
+        let hurried = ThermalConfig {
+            adjust_interval: Duration::from_secs(1),
+            ..Default::default()
+        };
+        assert_eq!(hurried.ticks_per_adjustment(), 1);
+        assert_eq!(hurried.adjustment_period(), DEFAULT_TICK);
+    }
+
+    #[test]
+    fn only_a_warmer_room_brings_derating_forward() {
+        let config = ThermalConfig {
+            ambient_reference_c: Some(25.0),
+            ..Default::default()
+        };
+        assert_eq!(config.ambient_offset_c(Some(32.0)), 7.0);
+        assert_eq!(config.ambient_offset_c(Some(18.0)), 0.0);
+        assert_eq!(config.ambient_offset_c(None), 0.0);
+        assert_eq!(ThermalConfig::default().ambient_offset_c(Some(40.0)), 0.0);
+    }
+}
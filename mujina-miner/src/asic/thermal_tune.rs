@@ -0,0 +1,499 @@
+//! Thermal tuning from a board's measured response.
+//!
+//! `mujina-cli thermal-tune` holds a board at a grid of fan speeds and
+//! core frequencies, waiting at each until the chip temperature settles,
+//! and hands the readings to this module. From the settled temperatures,
+//! [`ThermalModel`] fits how many °C each MHz and each percent of fan duty are worth; from
+//! how fast each step got there, the board's time constant; and from how
+//! much the settled readings wander, the sensor noise.
+//!
+//! The miner's thermal control is not a PID loop but derating bands with
+//! an overshoot margin, stepped once per adjustment interval
+//! ([`super::thermal`]). Its gains are those settings, so that is what
+//! [`ThermalModel::suggest`] proposes: a target temperature at which
+//! derating starts, far enough under the limit that the margin keeps it
+//! there, a band frequency that cools the chips back through the margin,
+//! and an adjustment interval matched to the time constant.
+
+use std::time::Duration;
+
+use super::thermal::DEFAULT_OVERSHOOT_C;
+use crate::config::Config;
+
+/// Hottest temperature a tuned board should reach unless told otherwise.
+pub const DEFAULT_LIMIT_C: f32 = 75.0;
+
+/// Fraction of a step's temperature change after one time constant.
+const TIME_CONSTANT_FRACTION: f32 = 0.632;
+
+/// Steps whose temperature moved less than this say nothing about the
+/// time constant.
+const MIN_STEP_CHANGE_C: f32 = 1.0;
+
+/// Band frequencies are rounded down to a multiple of this, in MHz.
+const FREQUENCY_ROUNDING_MHZ: f32 = 5.0;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum TuneError {
+    #[error("need settled temperatures at two or more frequencies")]
+    TooFewFrequencies,
+
+    #[error("temperature did not rise with frequency; is the sensor on the chips?")]
+    NoHeating,
+
+    #[error("config file: {0}")]
+    Config(String),
+}
+
+/// One operating point of a tuning run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TuneStep {
+    pub fan_percent: u8,
+    pub frequency_mhz: f32,
+}
+
+/// Every combination of `fans` and `frequencies`, coolest fan setting
+/// first and frequencies rising within each.
+pub fn plan(fans: &[u8], frequencies: &[f32]) -> Vec<TuneStep> {
+    let mut fans = fans.to_vec();
+    fans.sort_unstable_by(|a, b| b.cmp(a));
+    let mut frequencies = frequencies.to_vec();
+    frequencies.sort_by(f32::total_cmp);
+    fans.iter()
+        .flat_map(|&fan_percent| {
+            frequencies.iter().map(move |&frequency_mhz| TuneStep {
+                fan_percent,
+                frequency_mhz,
+            })
+        })
+        .collect()
+}
+
+/// A temperature read some time into a step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub elapsed: Duration,
+    pub temp_c: f32,
+}
+
+/// When a step's temperature counts as settled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settling {
+    /// Span of readings that must agree
+    pub window: Duration,
+    /// How far apart readings within the window may be, °C
+    pub tolerance_c: f32,
+    /// Longest a step waits before moving on unsettled
+    pub timeout: Duration,
+}
+
+impl Default for Settling {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60),
+            tolerance_c: 0.5,
+            timeout: Duration::from_secs(600),
+        }
+    }
+}
+
+impl Settling {
+    /// Whether the readings so far have settled.
+    pub fn settled(&self, samples: &[Sample]) -> bool {
+        let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+            return false;
+        };
+        if last.elapsed < first.elapsed + self.window {
+            return false;
+        }
+        let (min, max) = self
+            .in_window(samples)
+            .fold((f32::MAX, f32::MIN), |(min, max), t| {
+                (min.min(t), max.max(t))
+            });
+        max - min <= self.tolerance_c
+    }
+
+    /// Temperatures within the window ending at the last reading.
+    fn in_window<'a>(&self, samples: &'a [Sample]) -> impl Iterator<Item = f32> + 'a {
+        let from = samples
+            .last()
+            .map_or(Duration::ZERO, |s| s.elapsed.saturating_sub(self.window));
+        samples
+            .iter()
+            .filter(move |s| s.elapsed >= from)
+            .map(|s| s.temp_c)
+    }
+}
+
+/// What one step of a tuning run measured.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepRecord {
+    pub step: TuneStep,
+    /// Mean temperature over the final window, °C
+    pub steady_c: f32,
+    /// Standard deviation over the final window, °C
+    pub noise_c: f32,
+    /// Time to cover 63% of the change from the previous step, if the
+    /// temperature moved enough to tell
+    pub time_constant: Option<Duration>,
+    pub settled: bool,
+}
+
+impl StepRecord {
+    /// Summarize a step's readings, taken after moving to it from a
+    /// temperature of `start_c`.
+    pub fn new(
+        step: TuneStep,
+        start_c: f32,
+        samples: &[Sample],
+        settling: &Settling,
+    ) -> Option<Self> {
+        let window: Vec<f32> = settling.in_window(samples).collect();
+        if window.is_empty() {
+            return None;
+        }
+        let n = window.len() as f32;
+        let steady_c = window.iter().sum::<f32>() / n;
+        let noise_c = (window.iter().map(|t| (t - steady_c).powi(2)).sum::<f32>() / n).sqrt();
+
+        let change = steady_c - start_c;
+        let time_constant = (change.abs() >= MIN_STEP_CHANGE_C)
+            .then(|| {
+                samples
+                    .iter()
+                    .find(|s| (s.temp_c - start_c) / change >= TIME_CONSTANT_FRACTION)
+                    .map(|s| s.elapsed)
+            })
+            .flatten();
+
+        Some(Self {
+            step,
+            steady_c,
+            noise_c,
+            time_constant,
+            settled: settling.settled(samples),
+        })
+    }
+}
+
+/// Settled chip temperature as a linear function of frequency and fan
+/// duty, with the board's time constant and sensor noise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThermalModel {
+    /// Temperature extrapolated to 0 MHz and 0% fan, °C
+    pub base_c: f32,
+    /// °C per MHz of core frequency
+    pub c_per_mhz: f32,
+    /// °C per percent of fan duty (negative: the fan cools); zero if
+    /// only one fan speed was measured
+    pub c_per_fan_percent: f32,
+    /// Median of the steps' time constants
+    pub time_constant: Option<Duration>,
+    /// Largest wander of a settled temperature, °C
+    pub noise_c: f32,
+}
+
+impl ThermalModel {
+    /// Fit the model to a run's records by least squares.
+    pub fn fit(records: &[StepRecord]) -> Result<Self, TuneError> {
+        let distinct = |mut values: Vec<f32>| {
+            values.sort_by(f32::total_cmp);
+            values.dedup();
+            values.len()
+        };
+        let frequencies = distinct(records.iter().map(|r| r.step.frequency_mhz).collect());
+        if frequencies < 2 {
+            return Err(TuneError::TooFewFrequencies);
+        }
+        let with_fan = distinct(records.iter().map(|r| r.step.fan_percent as f32).collect()) > 1;
+
+        let rows: Vec<Vec<f64>> = records
+            .iter()
+            .map(|r| {
+                let mut row = vec![1.0, r.step.frequency_mhz as f64];
+                if with_fan {
+                    row.push(r.step.fan_percent as f64);
+                }
+                row
+            })
+            .collect();
+        let temps: Vec<f64> = records.iter().map(|r| r.steady_c as f64).collect();
+        let coefficients = least_squares(&rows, &temps).ok_or(TuneError::TooFewFrequencies)?;
+
+        let c_per_mhz = coefficients[1] as f32;
+        if c_per_mhz <= 0.0 {
+            return Err(TuneError::NoHeating);
+        }
+
+        let mut time_constants: Vec<Duration> =
+            records.iter().filter_map(|r| r.time_constant).collect();
+        time_constants.sort();
+
+        Ok(Self {
+            base_c: coefficients[0] as f32,
+            c_per_mhz,
+            c_per_fan_percent: coefficients.get(2).map_or(0.0, |&c| c as f32),
+            time_constant: time_constants.get(time_constants.len() / 2).copied(),
+            noise_c: records.iter().map(|r| r.noise_c).fold(0.0, f32::max),
+        })
+    }
+
+    /// Settled temperature expected at a frequency and fan duty.
+    pub fn predict(&self, frequency_mhz: f32, fan_percent: u8) -> f32 {
+        self.base_c + self.c_per_mhz * frequency_mhz + self.c_per_fan_percent * fan_percent as f32
+    }
+
+    /// Frequency that settles at `temp_c` with the fan at `fan_percent`.
+    pub fn frequency_for(&self, temp_c: f32, fan_percent: u8) -> f32 {
+        (temp_c - self.base_c - self.c_per_fan_percent * fan_percent as f32) / self.c_per_mhz
+    }
+
+    /// Settings that keep the chips under `limit_c` with the fan at
+    /// `fan_percent`, derating no lower than `min_mhz` and no higher than
+    /// `max_mhz`, the range the run measured. Adjustment intervals are
+    /// whole multiples of `tick`.
+    pub fn suggest(
+        &self,
+        limit_c: f32,
+        fan_percent: u8,
+        min_mhz: f32,
+        max_mhz: f32,
+        tick: Duration,
+    ) -> Suggestion {
+        // Readings wander by the noise; leaving a band on noise alone
+        // would cycle, so the margin covers three standard deviations
+        let overshoot_c = ((3.0 * self.noise_c).max(DEFAULT_OVERSHOOT_C) * 2.0).ceil() / 2.0;
+        let target_c = limit_c - overshoot_c;
+
+        // The band's frequency must cool the chips through the margin,
+        // or they would sit at the threshold in the band for good
+        let band_mhz = self.frequency_for(target_c - overshoot_c, fan_percent);
+        let band_mhz = ((band_mhz / FREQUENCY_ROUNDING_MHZ).floor() * FREQUENCY_ROUNDING_MHZ)
+            .clamp(min_mhz, max_mhz);
+        let derating = if band_mhz > min_mhz {
+            format!("{target_c}:{band_mhz},{limit_c}:{min_mhz}")
+        } else {
+            format!("{target_c}:{min_mhz}")
+        };
+
+        // A quarter of the time constant per step lets each one take
+        // effect before the next
+        let adjust_secs = self.time_constant.map(|tau| {
+            let tick_secs = tick.as_secs().max(1);
+            let ticks = (tau.as_secs_f64() / 4.0 / tick_secs as f64).round() as u64;
+            ticks.max(1) * tick_secs
+        });
+
+        Suggestion {
+            target_c,
+            overshoot_c,
+            derating,
+            adjust_secs,
+        }
+    }
+}
+
+/// Thermal settings proposed by a tuning run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// Temperature derating starts at, °C
+    pub target_c: f32,
+    /// `boards.thermal_overshoot`
+    pub overshoot_c: f32,
+    /// `boards.derating`
+    pub derating: String,
+    /// `boards.thermal_adjust_secs`, if the time constant was measured
+    pub adjust_secs: Option<u64>,
+}
+
+impl Suggestion {
+    /// Config file `text` with the suggested settings written into its
+    /// `[boards]` table.
+    ///
+    /// The file is rewritten from its parsed form, so comments and
+    /// layout are not kept.
+    pub fn apply_to(&self, text: &str) -> Result<String, TuneError> {
+        let config_error = |e: &dyn std::fmt::Display| TuneError::Config(e.to_string());
+        let mut table: toml::Table = text.parse().map_err(|e| config_error(&e))?;
+        let boards = table
+            .entry("boards")
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| TuneError::Config("boards is not a table".into()))?;
+        boards.insert("derating".into(), self.derating.clone().into());
+        boards.insert("thermal_overshoot".into(), (self.overshoot_c as f64).into());
+        if let Some(secs) = self.adjust_secs {
+            boards.insert("thermal_adjust_secs".into(), (secs as i64).into());
+        }
+
+        let text = toml::to_string(&table).map_err(|e| config_error(&e))?;
+        toml::from_str::<Config>(&text).map_err(|e| config_error(&e))?;
+        Ok(text)
+    }
+}
+
+/// Solve for the coefficients minimizing the squared error of
+/// `rows · x = values`, or `None` if they are not determined.
+fn least_squares(rows: &[Vec<f64>], values: &[f64]) -> Option<Vec<f64>> {
+    let n = rows.first()?.len();
+    // Normal equations, augmented with the right-hand side
+    let mut m: Vec<Vec<f64>> = (0..n)
+        .map(|i| {
+            let mut eq: Vec<f64> = (0..n)
+                .map(|j| rows.iter().map(|r| r[i] * r[j]).sum())
+                .collect();
+            eq.push(rows.iter().zip(values).map(|(r, v)| r[i] * v).sum());
+            eq
+        })
+        .collect();
+
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))?;
+        if m[pivot][col].abs() < 1e-9 {
+            return None;
+        }
+        m.swap(col, pivot);
+        let pivot_eq = m[col].clone();
+        for (row, eq) in m.iter_mut().enumerate() {
+            if row != col {
+                let factor = eq[col] / pivot_eq[col];
+                for (x, p) in eq[col..].iter_mut().zip(&pivot_eq[col..]) {
+                    *x -= factor * p;
+                }
+            }
+        }
+    }
+    Some((0..n).map(|i| m[i][n] / m[i][i]).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asic::derating::DeratingCurve;
+
+    /// A board that settles at 20 °C + 0.1 °C/MHz − 0.2 °C per % fan.
+    fn board(step: TuneStep) -> f32 {
+        20.0 + 0.1 * step.frequency_mhz - 0.2 * step.fan_percent as f32
+    }
+
+    /// Readings every 5 s for 5 min of a first-order approach from
+    /// `start_c` with a 40 s time constant.
+    fn approach(start_c: f32, steady_c: f32) -> Vec<Sample> {
+        (0..=60)
+            .map(|i| {
+                let t = i as f32 * 5.0;
+                Sample {
+                    elapsed: Duration::from_secs_f32(t),
+                    temp_c: steady_c + (start_c - steady_c) * (-t / 40.0).exp(),
+                }
+            })
+            .collect()
+    }
+
+    fn run(fans: &[u8], frequencies: &[f32]) -> Vec<StepRecord> {
+        let settling = Settling::default();
+        let mut temp_c = 30.0;
+        plan(fans, frequencies)
+            .into_iter()
+            .map(|step| {
+                let record =
+                    StepRecord::new(step, temp_c, &approach(temp_c, board(step)), &settling)
+                        .unwrap();
+                temp_c = record.steady_c;
+                record
+            })
+            .collect()
+    }
+
+    #[test]
+    fn plan_starts_cool() {
+        let steps = plan(&[60, 100], &[500.0, 400.0]);
+        assert_eq!(steps.len(), 4);
+        assert_eq!(
+            steps[0],
+            TuneStep {
+                fan_percent: 100,
+                frequency_mhz: 400.0
+            }
+        );
+        assert_eq!(steps[3].fan_percent, 60);
+        assert_eq!(steps[3].frequency_mhz, 500.0);
+    }
+
+    #[test]
+    fn settles_once_the_window_agrees() {
+        let settling = Settling::default();
+        let samples = approach(30.0, 60.0);
+        assert!(!settling.settled(&samples[..12]));
+        assert!(settling.settled(&samples));
+
+        let record = StepRecord::new(
+            TuneStep {
+                fan_percent: 100,
+                frequency_mhz: 500.0,
+            },
+            30.0,
+            &samples,
+            &settling,
+        )
+        .unwrap();
+        assert!(record.settled);
+        assert!((record.steady_c - 60.0).abs() < 0.1);
+        assert_eq!(record.time_constant, Some(Duration::from_secs(40)));
+    }
+
+    #[test]
+    fn fits_a_linear_board() {
+        let model = ThermalModel::fit(&run(&[100, 60], &[400.0, 500.0])).unwrap();
+        assert!((model.c_per_mhz - 0.1).abs() < 0.001);
+        assert!((model.c_per_fan_percent + 0.2).abs() < 0.001);
+        assert!((model.predict(450.0, 80) - 49.0).abs() < 0.1);
+        assert!(model.time_constant.unwrap() <= Duration::from_secs(45));
+
+        // One fan speed still fits, without a fan term
+        let model = ThermalModel::fit(&run(&[100], &[400.0, 500.0])).unwrap();
+        assert_eq!(model.c_per_fan_percent, 0.0);
+        assert_eq!(
+            ThermalModel::fit(&run(&[100, 60], &[500.0])),
+            Err(TuneError::TooFewFrequencies)
+        );
+    }
+
+    #[test]
+    fn suggestion_keeps_under_the_limit() {
+        let model = ThermalModel::fit(&run(&[100, 60], &[400.0, 600.0])).unwrap();
+        let suggestion = model.suggest(75.0, 60, 400.0, 600.0, Duration::from_secs(5));
+
+        assert_eq!(suggestion.overshoot_c, DEFAULT_OVERSHOOT_C);
+        assert_eq!(suggestion.target_c, 72.0);
+        // 69 °C at 60% fan is 610 MHz, more than was measured
+        assert_eq!(suggestion.derating, "72:600,75:400");
+        assert_eq!(suggestion.adjust_secs, Some(10));
+
+        let curve: DeratingCurve = suggestion.derating.parse().unwrap();
+        assert!(model.predict(curve.max_frequency(72.0).unwrap(), 60) <= 72.0 - 3.0 + 1.0);
+    }
+
+    #[test]
+    fn writes_into_the_boards_table() {
+        let suggestion = Suggestion {
+            target_c: 72.0,
+            overshoot_c: 3.5,
+            derating: "72:450,75:400".into(),
+            adjust_secs: Some(15),
+        };
+        let text = suggestion
+            .apply_to(
+                "[pool]\nurl = \"stratum+tcp://pool:3333\"\n\n[boards]\nderating = \"80:300\"\n",
+            )
+            .unwrap();
+        let config: Config = toml::from_str(&text).unwrap();
+        assert_eq!(config.boards.derating.as_deref(), Some("72:450,75:400"));
+        assert_eq!(config.boards.thermal_overshoot, Some(3.5));
+        assert_eq!(config.boards.thermal_adjust_secs, Some(15));
+        assert!(config.pool.url.is_some());
+
+        assert!(suggestion.apply_to("boards = 1").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! Checking nonces off the async runtime.
+//!
+//! Checking a nonce means rebuilding the job's merkle root and hashing the
+//! block header with double SHA-256. That is a few microseconds of CPU per
+//! nonce; at the nonce rates of a long chain it adds up to enough to hold
+//! up the runtime worker a hash thread actor runs on, and with it every
+//! other task queued on that worker. Actors hand the hashing to a
+//! [`VerifyPool`] instead and await the result.
+//!
+//! Work runs on tokio's blocking threads, at most a few jobs at once, so a
+//! burst of nonces can't take over every core.
+
+use std::sync::{Arc, OnceLock};
+
+use tokio::sync::Semaphore;
+
+/// Most jobs the shared pool runs at once.
+const MAX_THREADS: usize = 4;
+
+/// Runs CPU-bound checks on a bounded number of blocking threads.
+///
+/// Cheap to clone; clones share the same limit.
+#[derive(Debug, Clone)]
+pub struct VerifyPool {
+    permits: Arc<Semaphore>,
+    threads: usize,
+}
+
+impl VerifyPool {
+    /// Pool running at most `threads` jobs at once (at least one).
+    pub fn new(threads: usize) -> Self {
+        let threads = threads.max(1);
+        Self {
+            permits: Arc::new(Semaphore::new(threads)),
+            threads,
+        }
+    }
+
+    /// Most jobs run at once.
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Run `work` on a blocking thread once one of the pool's slots is
+    /// free, and return its result.
+    ///
+    /// A panic in `work` is resumed in the caller.
+    pub async fn run<F, T>(&self, work: F) -> T
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("pool semaphore is never closed");
+        match tokio::task::spawn_blocking(work).await {
+            Ok(result) => result,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+static SHARED: OnceLock<VerifyPool> = OnceLock::new();
+
+/// The pool every hash thread checks its nonces on.
+///
+/// Sized to leave a core for the runtime, up to [`MAX_THREADS`].
+pub fn shared() -> &'static VerifyPool {
+    SHARED.get_or_init(|| {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        VerifyPool::new(cores.saturating_sub(1).min(MAX_THREADS))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn runs_no_more_than_its_threads_at_once() {
+        let pool = VerifyPool::new(2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+
+        let jobs: Vec<_> = (0..8)
+            .map(|i| {
+                let pool = pool.clone();
+                let running = running.clone();
+                let most = most.clone();
+                tokio::spawn(async move {
+                    pool.run(move || {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        most.fetch_max(now, Ordering::SeqCst);
+                        std::thread::sleep(std::time::Duration::from_millis(10));
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        i * 2
+                    })
+                    .await
+                })
+            })
+            .collect();
+
+        let mut results = Vec::new();
+        for job in jobs {
+            results.push(job.await.unwrap());
+        }
+        assert_eq!(results, (0..8).map(|i| i * 2).collect::<Vec<_>>());
+        assert!(most.load(Ordering::SeqCst) <= 2);
+        assert_eq!(VerifyPool::new(0).threads(), 1);
+    }
+}
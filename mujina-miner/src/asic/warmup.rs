@@ -0,0 +1,210 @@
+//! Staged frequency warm-up after chip initialization.
+//!
+//! Marginal boards can misbehave when a cold chain goes straight to full
+//! frequency. With warm-up enabled, hash threads start at a conservative
+//! frequency and climb to their operating point in stages. A stage passes
+//! when the chips produced nonces and the temperature settled during it;
+//! otherwise the ramp stops at the last stage that passed. Warm-up is
+//! enabled through [`crate::config`].
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Warm-up parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarmupConfig {
+    /// Frequency for the first stage.
+    pub start_mhz: f32,
+    /// Frequency increase per stage.
+    pub stage_mhz: f32,
+    /// How long each stage is observed before moving on.
+    pub stage_duration: Duration,
+    /// Largest temperature rise over a stage still considered settled.
+    pub max_temp_rise_c: f32,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            start_mhz: 400.0,
+            stage_mhz: 25.0,
+            stage_duration: Duration::from_secs(60),
+            max_temp_rise_c: 5.0,
+        }
+    }
+}
+
+/// What the hash thread should do after a warm-up check.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WarmupStep {
+    /// Current stage still running.
+    Hold,
+    /// Stage passed; retune to the next stage's frequency.
+    Advance(f32),
+    /// Final stage passed at the target frequency.
+    Done,
+    /// Stage failed; retune to the last good frequency and stop ramping.
+    Abort {
+        frequency_mhz: f32,
+        reason: &'static str,
+    },
+}
+
+/// Progress through the warm-up stages.
+#[derive(Debug)]
+pub struct Warmup {
+    config: WarmupConfig,
+    target_mhz: f32,
+    /// Frequency of the current stage.
+    frequency_mhz: f32,
+    /// Frequency of the last stage that passed, if any.
+    stable_mhz: Option<f32>,
+    stage_started: Instant,
+    stage_nonces: u32,
+    stage_start_temp: Option<f32>,
+    last_temp: Option<f32>,
+}
+
+impl Warmup {
+    /// Begin warm-up towards `target_mhz`.
+    pub fn new(config: WarmupConfig, target_mhz: f32, now: Instant) -> Self {
+        let frequency_mhz = config.start_mhz.min(target_mhz);
+        Self {
+            config,
+            target_mhz,
+            frequency_mhz,
+            stable_mhz: None,
+            stage_started: now,
+            stage_nonces: 0,
+            stage_start_temp: None,
+            last_temp: None,
+        }
+    }
+
+    /// Frequency the chip should run at for the current stage.
+    pub fn frequency(&self) -> f32 {
+        self.frequency_mhz
+    }
+
+    /// Count a nonce reported by the chip.
+    pub fn record_nonce(&mut self) {
+        self.stage_nonces += 1;
+    }
+
+    /// Record a die temperature reading.
+    pub fn record_temperature(&mut self, temp_c: f32) {
+        if self.stage_start_temp.is_none() {
+            self.stage_start_temp = Some(temp_c);
+        }
+        self.last_temp = Some(temp_c);
+    }
+
+    /// Check the current stage.
+    pub fn poll(&mut self, now: Instant) -> WarmupStep {
+        if now.duration_since(self.stage_started) < self.config.stage_duration {
+            return WarmupStep::Hold;
+        }
+
+        let failure = if self.stage_nonces == 0 {
+            Some("no nonces during stage")
+        } else if let (Some(start), Some(last)) = (self.stage_start_temp, self.last_temp)
+            && last - start > self.config.max_temp_rise_c
+        {
+            Some("temperature still rising")
+        } else {
+            None
+        };
+        if let Some(reason) = failure {
+            return WarmupStep::Abort {
+                frequency_mhz: self
+                    .stable_mhz
+                    .unwrap_or(self.config.start_mhz.min(self.target_mhz)),
+                reason,
+            };
+        }
+
+        if self.frequency_mhz >= self.target_mhz {
+            return WarmupStep::Done;
+        }
+
+        self.stable_mhz = Some(self.frequency_mhz);
+        self.frequency_mhz = (self.frequency_mhz + self.config.stage_mhz).min(self.target_mhz);
+        self.stage_started = now;
+        self.stage_nonces = 0;
+        self.stage_start_temp = self.last_temp;
+        WarmupStep::Advance(self.frequency_mhz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> WarmupConfig {
+        WarmupConfig {
+            start_mhz: 450.0,
+            stage_mhz: 50.0,
+            stage_duration: Duration::from_secs(30),
+            max_temp_rise_c: 5.0,
+        }
+    }
+
+    fn run_stage(warmup: &mut Warmup, now: &mut Instant, temps: [f32; 2]) -> WarmupStep {
+        warmup.record_temperature(temps[0]);
+        warmup.record_nonce();
+        warmup.record_temperature(temps[1]);
+        *now += Duration::from_secs(30);
+        warmup.poll(*now)
+    }
+
+    #[test]
+    fn ramps_through_stages_to_target() {
+        let mut now = Instant::now();
+        let mut warmup = Warmup::new(config(), 525.0, now);
+        assert_eq!(warmup.frequency(), 450.0);
+        assert_eq!(warmup.poll(now + Duration::from_secs(10)), WarmupStep::Hold);
+
+        assert_eq!(
+            run_stage(&mut warmup, &mut now, [40.0, 44.0]),
+            WarmupStep::Advance(500.0)
+        );
+        assert_eq!(
+            run_stage(&mut warmup, &mut now, [44.0, 46.0]),
+            WarmupStep::Advance(525.0)
+        );
+        assert_eq!(
+            run_stage(&mut warmup, &mut now, [46.0, 47.0]),
+            WarmupStep::Done
+        );
+    }
+
+    #[test]
+    fn aborts_to_last_good_stage_on_instability() {
+        let mut now = Instant::now();
+        let mut warmup = Warmup::new(config(), 525.0, now);
+        run_stage(&mut warmup, &mut now, [40.0, 42.0]);
+
+        assert_eq!(
+            run_stage(&mut warmup, &mut now, [42.0, 55.0]),
+            WarmupStep::Abort {
+                frequency_mhz: 450.0,
+                reason: "temperature still rising"
+            }
+        );
+    }
+
+    #[test]
+    fn aborts_without_nonces() {
+        let now = Instant::now();
+        let mut warmup = Warmup::new(config(), 525.0, now);
+
+        assert!(matches!(
+            warmup.poll(now + Duration::from_secs(30)),
+            WarmupStep::Abort {
+                frequency_mhz: 450.0,
+                ..
+            }
+        ));
+    }
+}
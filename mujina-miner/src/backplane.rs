@@ -6,17 +6,33 @@
 //! lifecycle (hotplug, emergency shutdown, etc.).
 
 use crate::{
+    api::commands::BoardCommand,
+    api_client::types::{BoardState, BurnInRequest, BurnInStatus, ChipRegisterDump, Profile},
     asic::hash_thread::HashThread,
-    board::{Board, BoardDescriptor, BoardRegistration, VirtualBoardRegistry},
+    board::{
+        Board, BoardDescriptor, BoardRegistration, NetworkBoardRegistry, VirtualBoardRegistry,
+        burn_in::{self, BurnIn, BurnInAction, BurnInPlan, Sample},
+    },
     error::Result,
+    events::{BoardEvent, Boards, EventBus, Thermal, ThermalEvent},
     tracing::prelude::*,
     transport::{
-        TransportEvent, UsbDeviceInfo, cpu::TransportEvent as CpuTransportEvent,
+        TransportEvent, UsbDeviceInfo,
+        cpu::TransportEvent as CpuTransportEvent,
+        network::{self, TransportEvent as NetworkTransportEvent},
         usb::TransportEvent as UsbTransportEvent,
     },
 };
-use std::collections::HashMap;
-use tokio::sync::mpsc;
+use anyhow::anyhow;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+
+/// How often boards' thermal readings are published.
+const THERMAL_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Board registry that uses inventory to find registered boards.
 pub struct BoardRegistry;
@@ -34,6 +50,50 @@ impl BoardRegistry {
             .filter(|desc| desc.pattern.matches(device))
             .max_by_key(|desc| desc.pattern.specificity())
     }
+
+    /// Describe which of `devices` are hash boards this build can drive.
+    pub fn detect(&self, devices: &[UsbDeviceInfo]) -> Vec<DetectedBoard> {
+        devices
+            .iter()
+            .filter_map(|device| {
+                let descriptor = self.find_descriptor(device)?;
+                Some(DetectedBoard {
+                    board: descriptor.name,
+                    id: device.board_id(),
+                    vid: format!("{:04x}", device.vid),
+                    pid: format!("{:04x}", device.pid),
+                    serial_number: device.serial_number.clone(),
+                    manufacturer: device.manufacturer.clone(),
+                    product: device.product.clone(),
+                    device_path: device.device_path.clone(),
+                    serial_ports: device.serial_ports().unwrap_or_default().to_vec(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A connected USB device recognized as a hash board.
+///
+/// Printed by `mujina-minerd --list-devices` to show which device nodes
+/// a container needs mapped in.
+#[derive(Debug, Serialize)]
+pub struct DetectedBoard {
+    /// Board type that would handle the device
+    pub board: &'static str,
+    /// Board ID as used in the API
+    pub id: String,
+    /// USB vendor ID, hex
+    pub vid: String,
+    /// USB product ID, hex
+    pub pid: String,
+    pub serial_number: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+    /// sysfs path of the USB device
+    pub device_path: String,
+    /// Serial port device nodes, e.g. /dev/ttyACM0
+    pub serial_ports: Vec<String>,
 }
 
 /// Backplane that connects boards to the scheduler.
@@ -44,13 +104,36 @@ impl BoardRegistry {
 pub struct Backplane {
     registry: BoardRegistry,
     virtual_registry: VirtualBoardRegistry,
+    network_registry: NetworkBoardRegistry,
     /// Active boards managed by the backplane
     boards: HashMap<String, Box<dyn Board + Send>>,
+    /// API board names to backplane board IDs
+    board_names: HashMap<String, String>,
+    /// Boards whose hash threads were taken out of service via the API
+    disabled: HashSet<String>,
+    /// Boards powered down for a pause, to bring back on resume
+    powered_down: HashSet<String>,
+    /// USB device paths to board IDs, for routing disconnect events
+    device_paths: HashMap<String, String>,
+    /// Network boards whose link is down, with when to give up on them
+    lost: HashMap<String, Instant>,
+    /// How long a lost network board is kept for its link to come back
+    reconnect_grace: Duration,
+    /// Operating profile applied to every board
+    profile: Profile,
+    /// Published state of each board, by board ID
+    states: HashMap<String, watch::Receiver<BoardState>>,
+    /// Burn-ins under way, by board ID
+    burn_ins: HashMap<String, BurnIn>,
+    /// Directory burn-in reports are written to
+    burn_in_dir: PathBuf,
     event_rx: mpsc::Receiver<TransportEvent>,
+    /// Commands from the API server
+    cmd_rx: mpsc::Receiver<BoardCommand>,
     /// Channel to send hash threads to the scheduler
     scheduler_tx: mpsc::Sender<Box<dyn HashThread>>,
-    /// Channel to forward board registrations to the API server
-    board_reg_tx: mpsc::Sender<BoardRegistration>,
+    /// Where board lifecycle and thermal events are published
+    events: EventBus,
 }
 
 impl Backplane {
@@ -58,34 +141,500 @@ impl Backplane {
     pub fn new(
         event_rx: mpsc::Receiver<TransportEvent>,
         scheduler_tx: mpsc::Sender<Box<dyn HashThread>>,
-        board_reg_tx: mpsc::Sender<BoardRegistration>,
+        events: EventBus,
+        cmd_rx: mpsc::Receiver<BoardCommand>,
     ) -> Self {
         Self {
             registry: BoardRegistry,
             virtual_registry: VirtualBoardRegistry,
+            network_registry: NetworkBoardRegistry,
             boards: HashMap::new(),
+            board_names: HashMap::new(),
+            disabled: HashSet::new(),
+            powered_down: HashSet::new(),
+            device_paths: HashMap::new(),
+            lost: HashMap::new(),
+            reconnect_grace: network::DEFAULT_RECONNECT_GRACE,
+            profile: Profile::default(),
+            states: HashMap::new(),
+            burn_ins: HashMap::new(),
+            burn_in_dir: PathBuf::from(crate::config::DEFAULT_BURN_IN_DIR),
             event_rx,
+            cmd_rx,
             scheduler_tx,
-            board_reg_tx,
+            events,
         }
     }
 
+    /// Apply `profile` to boards as they connect.
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Keep network boards whose link drops for `grace` before removing
+    /// them.
+    pub fn with_reconnect_grace(mut self, grace: Duration) -> Self {
+        self.reconnect_grace = grace;
+        self
+    }
+
+    /// Keep burn-in reports in `dir`.
+    pub fn with_burn_in_dir(mut self, dir: PathBuf) -> Self {
+        self.burn_in_dir = dir;
+        self
+    }
+
     /// Run the backplane event loop.
     pub async fn run(&mut self) -> Result<()> {
-        while let Some(event) = self.event_rx.recv().await {
-            match event {
-                TransportEvent::Usb(usb_event) => {
-                    self.handle_usb_event(usb_event).await?;
+        let mut burn_in_ticker = tokio::time::interval(burn_in::SAMPLE_INTERVAL);
+        burn_in_ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        let mut thermal_ticker = tokio::time::interval(THERMAL_INTERVAL);
+        thermal_ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            let lost_deadline = self.next_lost_deadline();
+            tokio::select! {
+                event = self.event_rx.recv() => {
+                    let Some(event) = event else {
+                        break;
+                    };
+                    match event {
+                        TransportEvent::Usb(usb_event) => {
+                            self.handle_usb_event(usb_event).await?;
+                        }
+                        TransportEvent::Cpu(cpu_event) => {
+                            self.handle_cpu_event(cpu_event).await?;
+                        }
+                        TransportEvent::Network(network_event) => {
+                            self.handle_network_event(network_event).await;
+                        }
+                        TransportEvent::Simulated { device_id } => {
+                            self.add_simulated_board(device_id).await;
+                        }
+                    }
+                }
+
+                Some(cmd) = self.cmd_rx.recv() => {
+                    self.handle_command(cmd).await;
+                }
+
+                _ = burn_in_ticker.tick(), if !self.burn_ins.is_empty() => {
+                    self.step_burn_ins().await;
+                }
+
+                _ = thermal_ticker.tick(), if self.events.has_subscribers::<Thermal>() => {
+                    self.publish_thermal();
+                }
+
+                _ = tokio::time::sleep_until(lost_deadline), if !self.lost.is_empty() => {
+                    self.remove_lost_boards().await;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Publish each board's latest thermal readings.
+    fn publish_thermal(&self) {
+        for state_rx in self.states.values() {
+            let state = state_rx.borrow();
+            self.events.publish::<Thermal>(ThermalEvent {
+                board: state.name.clone(),
+                temperatures: state.temperatures.clone(),
+                fans: state.fans.clone(),
+                powers: state.powers.clone(),
+            });
+        }
+    }
+
+    /// Handle a command from the API, replying with the result.
+    async fn handle_command(&mut self, cmd: BoardCommand) {
+        match cmd {
+            BoardCommand::SetFanTarget {
+                board,
+                fan,
+                percent,
+                reply,
+            } => {
+                let _ = reply.send(self.set_fan_target(&board, &fan, percent).await);
+            }
+            BoardCommand::SetFrequency {
+                board,
+                frequency_mhz,
+                reply,
+            } => {
+                let _ = reply.send(self.set_frequency(&board, frequency_mhz).await);
+            }
+            BoardCommand::Disable { board, reply } => {
+                let _ = reply.send(self.disable_board(&board).await);
+            }
+            BoardCommand::Enable { board, reply } => {
+                let _ = reply.send(self.enable_board(&board).await);
+            }
+            BoardCommand::DumpRegisters {
+                board,
+                chip_address,
+                diff,
+                reply,
+            } => {
+                let _ = reply.send(self.dump_registers(&board, chip_address, diff).await);
+            }
+            BoardCommand::UpdateFirmware {
+                board,
+                image,
+                reply,
+            } => {
+                let _ = reply.send(self.update_firmware(&board, image).await);
+            }
+            BoardCommand::StartBurnIn {
+                board,
+                request,
+                reply,
+            } => {
+                let _ = reply.send(self.start_burn_in(&board, request).await);
+            }
+            BoardCommand::BurnInStatus { board, reply } => {
+                let _ = reply.send(self.burn_in_status(&board));
+            }
+            BoardCommand::SetPowered { powered, reply } => {
+                let result = if powered {
+                    self.power_up_boards().await
+                } else {
+                    self.power_down_boards().await
+                };
+                let _ = reply.send(result);
+            }
+            BoardCommand::SetProfile { profile, reply } => {
+                let _ = reply.send(self.set_profile(profile).await);
+            }
+        }
+    }
+
+    /// Switch all boards, and boards connected later, to `profile`.
+    async fn set_profile(&mut self, profile: Profile) -> anyhow::Result<()> {
+        self.profile = profile;
+
+        let mut failed = Vec::new();
+        for (board_id, board) in &mut self.boards {
+            if let Err(e) = board.apply_profile(profile).await {
+                error!(id = %board_id, error = %e, "Failed to apply profile");
+                failed.push(board_id.clone());
+            }
+        }
+
+        if !failed.is_empty() {
+            return Err(anyhow!(
+                "profile {profile} not applied to {}",
+                failed.join(", ")
+            ));
+        }
+        info!(%profile, "Profile applied.");
+        Ok(())
+    }
+
+    /// Set a fan's target duty cycle on a named board.
+    async fn set_fan_target(
+        &mut self,
+        name: &str,
+        fan: &str,
+        percent: Option<u8>,
+    ) -> anyhow::Result<()> {
+        let board_id = self.board_id(name)?;
+        let board = self
+            .boards
+            .get_mut(&board_id)
+            .ok_or_else(|| anyhow!("board {name} is not running"))?;
+        board.set_fan_target(fan, percent).await?;
+
+        info!(board = %name, %fan, target_percent = ?percent, "Fan target set.");
+        Ok(())
+    }
+
+    /// Set the core frequency of a named board.
+    async fn set_frequency(&mut self, name: &str, mhz: Option<f32>) -> anyhow::Result<()> {
+        let board_id = self.board_id(name)?;
+        let board = self
+            .boards
+            .get_mut(&board_id)
+            .ok_or_else(|| anyhow!("board {name} is not running"))?;
+        board.set_frequency(mhz).await?;
+
+        info!(board = %name, frequency_mhz = ?mhz, "Frequency set.");
+        Ok(())
+    }
+
+    /// Read back a chip's registers on a named board.
+    async fn dump_registers(
+        &mut self,
+        name: &str,
+        chip_address: u8,
+        diff: bool,
+    ) -> anyhow::Result<ChipRegisterDump> {
+        let board_id = self.board_id(name)?;
+        let board = self
+            .boards
+            .get_mut(&board_id)
+            .ok_or_else(|| anyhow!("board {name} is not running"))?;
+        Ok(board.dump_registers(chip_address, diff).await?)
+    }
+
+    /// Start a firmware update on a named board.
+    ///
+    /// The board must have been disabled through the API, so nothing puts
+    /// it back in service while the update runs. It reconnects as a new
+    /// board, hashing again.
+    async fn update_firmware(&mut self, name: &str, image: Vec<u8>) -> anyhow::Result<()> {
+        let board_id = self.board_id(name)?;
+        if !self.disabled.contains(&board_id) {
+            return Err(anyhow!("board {name} must be disabled first"));
+        }
+        let board = self
+            .boards
+            .get_mut(&board_id)
+            .ok_or_else(|| anyhow!("board {name} is not running"))?;
+        let bytes = image.len();
+        board.update_firmware(image).await?;
+
+        info!(board = %name, bytes, "Firmware update started.");
+        Ok(())
+    }
+
+    /// Start a burn-in on a named board, moving it to the first step's
+    /// frequency.
+    ///
+    /// The board must be in service, hashing whatever work the scheduler
+    /// gives it; run without a pool for dummy work.
+    async fn start_burn_in(&mut self, name: &str, request: BurnInRequest) -> anyhow::Result<()> {
+        let board_id = self.board_id(name)?;
+        if self.burn_ins.contains_key(&board_id) {
+            return Err(anyhow!("board {name} is already burning in"));
+        }
+        if self.disabled.contains(&board_id) || self.powered_down.contains(&board_id) {
+            return Err(anyhow!("board {name} is not hashing"));
+        }
+        let plan = BurnInPlan::try_from(request).map_err(|e| anyhow!(e))?;
+        let serial = self
+            .states
+            .get(&board_id)
+            .and_then(|rx| rx.borrow().serial.clone());
+        let board = self
+            .boards
+            .get_mut(&board_id)
+            .ok_or_else(|| anyhow!("board {name} is not running"))?;
+
+        let steps = plan.frequencies_mhz.len();
+        let (burn_in, first_mhz) =
+            BurnIn::start(name.to_string(), serial, plan, tokio::time::Instant::now());
+        board.set_frequency(Some(first_mhz)).await?;
+        self.burn_ins.insert(board_id, burn_in);
+
+        info!(board = %name, steps, frequency_mhz = first_mhz, "Burn-in started.");
+        Ok(())
+    }
+
+    /// A named board's running burn-in and its last report.
+    fn burn_in_status(&self, name: &str) -> anyhow::Result<BurnInStatus> {
+        let board_id = self.board_id(name)?;
+        let serial = self
+            .states
+            .get(&board_id)
+            .and_then(|rx| rx.borrow().serial.clone());
+        Ok(BurnInStatus {
+            running: self
+                .burn_ins
+                .get(&board_id)
+                .map(|b| b.progress(tokio::time::Instant::now())),
+            report: burn_in::load_report(&self.burn_in_dir, serial.as_deref().unwrap_or(name)),
+        })
+    }
+
+    /// Sample every board burning in, and move each on as its run decides.
+    async fn step_burn_ins(&mut self) {
+        let now = tokio::time::Instant::now();
+        let board_ids: Vec<String> = self.burn_ins.keys().cloned().collect();
+
+        for board_id in board_ids {
+            let (Some(state), Some(burn_in), Some(board)) = (
+                self.states.get(&board_id),
+                self.burn_ins.get_mut(&board_id),
+                self.boards.get_mut(&board_id),
+            ) else {
+                continue;
+            };
+            let sample = Sample::from_state(&state.borrow());
+            let name = state.borrow().name.clone();
+
+            let report = match burn_in.record(sample, now) {
+                BurnInAction::Hold => continue,
+                BurnInAction::SetFrequency(mhz) => match board.set_frequency(Some(mhz)).await {
+                    Ok(()) => {
+                        info!(board = %name, frequency_mhz = mhz, "Burn-in step passed.");
+                        continue;
+                    }
+                    Err(e) => burn_in.abort(now, format!("failed to set frequency: {e}")),
+                },
+                BurnInAction::Finish(report) => report,
+            };
+
+            self.burn_ins.remove(&board_id);
+            if let Err(e) = board.set_frequency(None).await {
+                error!(board = %name, error = %e, "Failed to restore frequency after burn-in");
+            }
+            if report.passed {
+                info!(board = %name, safe_frequency_mhz = ?report.safe_frequency_mhz, "Burn-in passed.");
+            } else {
+                let failure = report.steps.last().and_then(|s| s.failure.clone());
+                warn!(
+                    board = %name,
+                    safe_frequency_mhz = ?report.safe_frequency_mhz,
+                    failure = ?failure,
+                    "Burn-in failed."
+                );
+            }
+            match burn_in::save_report(&self.burn_in_dir, &report) {
+                Ok(path) => info!(board = %name, path = %path.display(), "Burn-in report saved."),
+                Err(e) => error!(board = %name, error = %e, "Failed to save burn-in report"),
+            }
+        }
+    }
+
+    /// Look up the backplane ID for a board's API name.
+    fn board_id(&self, name: &str) -> anyhow::Result<String> {
+        self.board_names
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("no board named {name}"))
+    }
+
+    /// Take a board's hash threads out of service. No-op if already disabled.
+    ///
+    /// The scheduler sees the threads' event channels close and drops them
+    /// as it would for an unplugged board.
+    async fn disable_board(&mut self, name: &str) -> anyhow::Result<()> {
+        let board_id = self.board_id(name)?;
+        if self.disabled.contains(&board_id) {
+            return Ok(());
+        }
+
+        let board = self
+            .boards
+            .get_mut(&board_id)
+            .ok_or_else(|| anyhow!("board {name} is not running"))?;
+        board.disable_hash_threads().await?;
+
+        info!(board = %name, id = %board_id, "Board disabled.");
+        self.disabled.insert(board_id);
+        Ok(())
+    }
+
+    /// Bring a disabled board back with fresh hash threads. No-op if the
+    /// board is not disabled.
+    async fn enable_board(&mut self, name: &str) -> anyhow::Result<()> {
+        let board_id = self.board_id(name)?;
+        if !self.disabled.contains(&board_id) {
+            return Ok(());
+        }
+
+        let board = self
+            .boards
+            .get_mut(&board_id)
+            .ok_or_else(|| anyhow!("board {name} is not running"))?;
+        let model = board.board_info().model;
+        let threads = board.create_hash_threads().await?;
+
+        self.disabled.remove(&board_id);
+        send_threads(&self.scheduler_tx, &model, threads).await;
+        info!(board = %name, id = %board_id, "Board enabled.");
+        Ok(())
+    }
+
+    /// Take every running board's hash threads out of service for a
+    /// power-down pause, holding the chips in reset.
+    ///
+    /// Boards already disabled through the API are left to that.
+    async fn power_down_boards(&mut self) -> anyhow::Result<()> {
+        let mut failed = Vec::new();
+        for (board_id, board) in &mut self.boards {
+            if self.disabled.contains(board_id) || self.powered_down.contains(board_id) {
+                continue;
+            }
+            match board.disable_hash_threads().await {
+                Ok(()) => {
+                    self.powered_down.insert(board_id.clone());
+                }
+                Err(e) => {
+                    error!(id = %board_id, error = %e, "Failed to power down board");
+                    failed.push(board_id.clone());
                 }
-                TransportEvent::Cpu(cpu_event) => {
-                    self.handle_cpu_event(cpu_event).await?;
+            }
+        }
+
+        if !failed.is_empty() {
+            return Err(anyhow!("boards not powered down: {}", failed.join(", ")));
+        }
+        info!(boards = self.powered_down.len(), "Boards powered down.");
+        Ok(())
+    }
+
+    /// Bring boards powered down for a pause back with fresh hash threads.
+    async fn power_up_boards(&mut self) -> anyhow::Result<()> {
+        let mut failed = Vec::new();
+        for board_id in std::mem::take(&mut self.powered_down) {
+            // Disabled through the API while powered down
+            if self.disabled.contains(&board_id) {
+                continue;
+            }
+            let Some(board) = self.boards.get_mut(&board_id) else {
+                continue;
+            };
+            let model = board.board_info().model;
+            match board.create_hash_threads().await {
+                Ok(threads) => send_threads(&self.scheduler_tx, &model, threads).await,
+                Err(e) => {
+                    error!(id = %board_id, error = %e, "Failed to power up board");
+                    self.powered_down.insert(board_id.clone());
+                    failed.push(board_id);
                 }
             }
         }
 
+        if !failed.is_empty() {
+            return Err(anyhow!("boards not powered up: {}", failed.join(", ")));
+        }
+        info!("Boards powered up.");
         Ok(())
     }
 
+    /// Publish a newly created board on the bus.
+    fn announce_board(&self, name: &str, registration: BoardRegistration) {
+        self.events.publish::<Boards>(BoardEvent::Added {
+            name: name.to_string(),
+            state_rx: registration.state_rx,
+        });
+    }
+
+    /// Forget a removed board's name, device path and disabled state.
+    fn forget_board(&mut self, board_id: &str) {
+        self.board_names.retain(|name, id| {
+            if id != board_id {
+                return true;
+            }
+            self.events
+                .publish::<Boards>(BoardEvent::Removed { name: name.clone() });
+            false
+        });
+        self.device_paths.retain(|_, id| id != board_id);
+        self.disabled.remove(board_id);
+        self.powered_down.remove(board_id);
+        self.lost.remove(board_id);
+        self.states.remove(board_id);
+        if self.burn_ins.remove(board_id).is_some() {
+            warn!(id = %board_id, "Board removed during burn-in, run abandoned.");
+        }
+    }
+
     /// Shutdown all boards managed by this backplane.
     pub async fn shutdown_all_boards(&mut self) {
         let board_ids: Vec<String> = self.boards.keys().cloned().collect();
@@ -93,16 +642,16 @@ impl Backplane {
         for board_id in board_ids {
             if let Some(mut board) = self.boards.remove(&board_id) {
                 let model = board.board_info().model;
-                debug!(board = %model, serial = %board_id, "Shutting down board");
+                debug!(board = %model, id = %board_id, "Shutting down board");
 
                 match board.shutdown().await {
                     Ok(()) => {
-                        debug!(board = %model, serial = %board_id, "Board shutdown complete");
+                        debug!(board = %model, id = %board_id, "Board shutdown complete");
                     }
                     Err(e) => {
                         error!(
                             board = %model,
-                            serial = %board_id,
+                            id = %board_id,
                             error = %e,
                             "Failed to shutdown board"
                         );
@@ -122,9 +671,13 @@ impl Backplane {
                     return Ok(());
                 };
 
+                let board_id = device_info.board_id();
+                let device_path = device_info.device_path.clone();
+
                 // Pattern matched - log the match
                 info!(
                     board = descriptor.name,
+                    id = %board_id,
                     vid = %format!("{:04x}", device_info.vid),
                     pid = %format!("{:04x}", device_info.pid),
                     manufacturer = ?device_info.manufacturer,
@@ -133,12 +686,31 @@ impl Backplane {
                     "Hash board connected via USB."
                 );
 
+                // Two boards with the same ID would collide in every map
+                // keyed by it, and in the API. Keep the first.
+                if self.boards.contains_key(&board_id) {
+                    warn!(
+                        board = descriptor.name,
+                        id = %board_id,
+                        device = %device_path,
+                        "Board ID already in use, ignoring duplicate board."
+                    );
+                    return Ok(());
+                }
+
+                // Tasks the board spawns while starting up run in its span
+                let span = info_span!("board", id = %board_id, model = descriptor.name);
+
                 // Create the board using the descriptor's factory function
-                let (mut board, registration) = match (descriptor.create_fn)(device_info).await {
+                let created = (descriptor.create_fn)(device_info)
+                    .instrument(span.clone())
+                    .await;
+                let (board, registration) = match created {
                     Ok(result) => result,
                     Err(e) => {
                         error!(
                             board = descriptor.name,
+                            id = %board_id,
                             error = %e,
                             "Failed to create board"
                         );
@@ -146,85 +718,133 @@ impl Backplane {
                     }
                 };
 
-                let board_info = board.board_info();
-                let board_id = board_info
-                    .serial_number
-                    .clone()
-                    .unwrap_or_else(|| "unknown".to_string());
-
-                // Forward board registration to the API server
-                if let Err(e) = self.board_reg_tx.send(registration).await {
-                    error!(
-                        board = %board_info.model,
-                        error = %e,
-                        "Failed to register board with API server"
-                    );
+                if self
+                    .bring_up_board(board_id.clone(), board, registration, span)
+                    .await
+                    .is_some()
+                {
+                    self.device_paths.insert(device_path, board_id);
                 }
+            }
+            UsbTransportEvent::UsbDeviceDisconnected { device_path } => {
+                // Most disconnects are for devices that were never boards
+                let Some(board_id) = self.device_paths.get(&device_path).cloned() else {
+                    return Ok(());
+                };
 
-                // Create hash threads from the board
-                match board.create_hash_threads().await {
-                    Ok(threads) => {
-                        // Store board for lifecycle management
-                        self.boards.insert(board_id.clone(), board);
-
-                        // Send threads to scheduler individually
-                        for thread in threads {
-                            if let Err(e) = self.scheduler_tx.send(thread).await {
-                                tracing::error!(
-                                    board = %board_info.model,
-                                    error = %e,
-                                    "Failed to send thread to scheduler"
-                                );
-                                break;
-                            }
+                if let Some(mut board) = self.boards.remove(&board_id) {
+                    self.forget_board(&board_id);
+                    let model = board.board_info().model;
+                    debug!(board = %model, id = %board_id, "Shutting down board");
+
+                    match board.shutdown().await {
+                        Ok(()) => {
+                            info!(board = %model, id = %board_id, "Board disconnected");
+                        }
+                        Err(e) => {
+                            tracing::error!(
+                                board = %model,
+                                id = %board_id,
+                                error = %e,
+                                "Failed to shutdown board"
+                            );
                         }
-                    }
-                    Err(e) => {
-                        tracing::error!(
-                            board = %board_info.model,
-                            serial = %board_id,
-                            error = %e,
-                            "Hash board failed to start."
-                        );
                     }
                 }
             }
-            UsbTransportEvent::UsbDeviceDisconnected { device_path: _ } => {
-                // Find and shutdown the board
-                // Note: Current design uses serial number as key, but we get device_path
-                // in disconnect event. For single-board setups this works fine.
-                // TODO: Maintain device_path -> board_id mapping for multi-board support
-                let board_ids: Vec<String> = self.boards.keys().cloned().collect();
-                for board_id in board_ids {
-                    if let Some(mut board) = self.boards.remove(&board_id) {
-                        let model = board.board_info().model;
-                        debug!(board = %model, serial = %board_id, "Shutting down board");
-
-                        match board.shutdown().await {
-                            Ok(()) => {
-                                info!(
-                                    board = %model,
-                                    serial = %board_id,
-                                    "Board disconnected"
-                                );
-                            }
-                            Err(e) => {
-                                tracing::error!(
-                                    board = %model,
-                                    serial = %board_id,
-                                    error = %e,
-                                    "Failed to shutdown board"
-                                );
-                            }
-                        }
-                        // Don't re-insert - board is removed
-                        break; // For now, assume one board per device
+        }
+
+        Ok(())
+    }
+
+    /// Handle network transport events.
+    ///
+    /// A board whose link drops stays in place through the reconnect
+    /// grace period; if the link comes back in time it carries on as it
+    /// was, otherwise it is removed like an unplugged USB board.
+    async fn handle_network_event(&mut self, event: NetworkTransportEvent) {
+        match event {
+            NetworkTransportEvent::NetworkDeviceConnected(device_info) => {
+                let board_id = device_info.device_id.clone();
+                if self.lost.remove(&board_id).is_some() {
+                    info!(id = %board_id, latency = ?device_info.latency.get(), "Network board reconnected.");
+                    return;
+                }
+                if self.boards.contains_key(&board_id) {
+                    return;
+                }
+
+                let kind = device_info.endpoint.kind.clone();
+                let Some(descriptor) = self.network_registry.find(&kind) else {
+                    error!(kind = %kind, id = %board_id, "No network board descriptor for this kind");
+                    return;
+                };
+                info!(
+                    board = descriptor.name,
+                    endpoint = %device_info.endpoint,
+                    latency = ?device_info.latency.get(),
+                    "Network board connected."
+                );
+
+                let span = info_span!("board", id = %board_id, model = descriptor.name);
+                let (board, registration) = match (descriptor.create_fn)(device_info)
+                    .instrument(span.clone())
+                    .await
+                {
+                    Ok(result) => result,
+                    Err(e) => {
+                        error!(board = descriptor.name, error = %e, "Failed to create board");
+                        return;
                     }
+                };
+                self.bring_up_board(board_id, board, registration, span)
+                    .await;
+            }
+            NetworkTransportEvent::NetworkDeviceLost { device_id } => {
+                if self.boards.contains_key(&device_id) {
+                    warn!(
+                        id = %device_id,
+                        grace_secs = self.reconnect_grace.as_secs(),
+                        "Network board unreachable, waiting for it to reconnect."
+                    );
+                    self.lost
+                        .insert(device_id, Instant::now() + self.reconnect_grace);
                 }
             }
         }
+    }
 
-        Ok(())
+    /// Earliest time a lost network board is given up on.
+    fn next_lost_deadline(&self) -> Instant {
+        self.lost
+            .values()
+            .min()
+            .copied()
+            .unwrap_or_else(|| Instant::now() + self.reconnect_grace)
+    }
+
+    /// Remove lost network boards whose grace period is over.
+    async fn remove_lost_boards(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<String> = self
+            .lost
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(board_id, _)| board_id.clone())
+            .collect();
+
+        for board_id in expired {
+            let Some(mut board) = self.boards.remove(&board_id) else {
+                self.lost.remove(&board_id);
+                continue;
+            };
+            self.forget_board(&board_id);
+            let model = board.board_info().model;
+            if let Err(e) = board.shutdown().await {
+                error!(board = %model, id = %board_id, error = %e, "Failed to shutdown board");
+            }
+            warn!(board = %model, id = %board_id, "Network board did not reconnect, removed.");
+        }
     }
 
     /// Handle CPU miner transport events.
@@ -244,68 +864,34 @@ impl Backplane {
                     "CPU miner board connected."
                 );
 
-                // Create the board using the descriptor's factory function
-                let (mut board, registration) = match (descriptor.create_fn)().await {
-                    Ok(result) => result,
-                    Err(e) => {
-                        error!(
-                            board = descriptor.name,
-                            error = %e,
-                            "Failed to create CPU miner board"
-                        );
-                        return Ok(());
-                    }
-                };
-
-                let board_info = board.board_info();
-                let board_id = device_info.device_id.clone();
+                let span =
+                    info_span!("board", id = %device_info.device_id, model = descriptor.name);
 
-                // Forward board registration to the API server
-                if let Err(e) = self.board_reg_tx.send(registration).await {
-                    error!(
-                        board = %board_info.model,
-                        error = %e,
-                        "Failed to register board with API server"
-                    );
-                }
-
-                // Create hash threads from the board
-                match board.create_hash_threads().await {
-                    Ok(threads) => {
-                        let thread_count = threads.len();
-
-                        // Store board for lifecycle management
-                        self.boards.insert(board_id.clone(), board);
-
-                        // Send threads to scheduler individually
-                        for thread in threads {
-                            if let Err(e) = self.scheduler_tx.send(thread).await {
-                                tracing::error!(
-                                    board = %board_info.model,
-                                    error = %e,
-                                    "Failed to send thread to scheduler"
-                                );
-                                break;
-                            }
+                // Create the board using the descriptor's factory function
+                let (board, registration) =
+                    match (descriptor.create_fn)().instrument(span.clone()).await {
+                        Ok(result) => result,
+                        Err(e) => {
+                            error!(
+                                board = descriptor.name,
+                                error = %e,
+                                "Failed to create CPU miner board"
+                            );
+                            return Ok(());
                         }
+                    };
 
-                        info!(
-                            board = %board_info.model,
-                            threads = thread_count,
-                            "CPU miner started."
-                        );
-                    }
-                    Err(e) => {
-                        tracing::error!(
-                            board = %board_info.model,
-                            error = %e,
-                            "CPU miner failed to start."
-                        );
-                    }
+                let board_id = device_info.device_id.clone();
+                if let Some(threads) = self
+                    .bring_up_board(board_id, board, registration, span)
+                    .await
+                {
+                    info!(board = descriptor.name, threads, "CPU miner started.");
                 }
             }
             CpuTransportEvent::CpuDeviceDisconnected { device_id } => {
                 if let Some(mut board) = self.boards.remove(&device_id) {
+                    self.forget_board(&device_id);
                     let model = board.board_info().model;
                     debug!(board = %model, id = %device_id, "Shutting down CPU miner");
 
@@ -328,4 +914,107 @@ impl Backplane {
 
         Ok(())
     }
+
+    /// Bring up a simulated board.
+    async fn add_simulated_board(&mut self, board_id: String) {
+        let Some(descriptor) = self.virtual_registry.find("sim_board") else {
+            error!("No virtual board descriptor found for sim_board");
+            return;
+        };
+
+        let span = info_span!("board", id = %board_id, model = descriptor.name);
+        let (board, registration) = match (descriptor.create_fn)().instrument(span.clone()).await {
+            Ok(result) => result,
+            Err(e) => {
+                error!(board = descriptor.name, error = %e, "Failed to create board");
+                return;
+            }
+        };
+
+        // No chips, so no threads; this just puts the board in service
+        if self
+            .bring_up_board(board_id, board, registration, span)
+            .await
+            .is_some()
+        {
+            info!(board = descriptor.name, "Simulated board started.");
+        }
+    }
+
+    /// Put a newly created board in service: apply the profile, announce
+    /// the board, start its hash threads and hand them to the scheduler.
+    ///
+    /// Returns the number of threads started, or None if the board
+    /// failed to start and was dropped.
+    async fn bring_up_board(
+        &mut self,
+        board_id: String,
+        mut board: Box<dyn Board + Send>,
+        registration: BoardRegistration,
+        span: tracing::Span,
+    ) -> Option<usize> {
+        let board_info = board.board_info();
+        let board_name = registration.state_rx.borrow().name.clone();
+        let state_rx = registration.state_rx.clone();
+
+        // Before threads exist, so they start at the profile's settings
+        if let Err(e) = board
+            .apply_profile(self.profile)
+            .instrument(span.clone())
+            .await
+        {
+            error!(
+                board = %board_info.model,
+                id = %board_id,
+                error = %e,
+                "Failed to apply profile"
+            );
+        }
+
+        // Announce the board to the API and anyone else listening
+        self.announce_board(&board_name, registration);
+
+        let threads = match board.create_hash_threads().instrument(span).await {
+            Ok(threads) => threads,
+            Err(e) => {
+                error!(
+                    board = %board_info.model,
+                    id = %board_id,
+                    error = %e,
+                    "Hash board failed to start."
+                );
+                return None;
+            }
+        };
+        let thread_count = threads.len();
+
+        // Store board for lifecycle management
+        self.boards.insert(board_id.clone(), board);
+        self.board_names.insert(board_name, board_id.clone());
+        self.states.insert(board_id, state_rx);
+
+        send_threads(&self.scheduler_tx, &board_info.model, threads).await;
+        Some(thread_count)
+    }
+}
+
+/// Hand a board's hash threads to the scheduler.
+///
+/// Takes the sender rather than `&Backplane` so the future stays `Send`
+/// (boards are `Send` but not `Sync`).
+async fn send_threads(
+    scheduler_tx: &mpsc::Sender<Box<dyn HashThread>>,
+    model: &str,
+    threads: Vec<Box<dyn HashThread>>,
+) {
+    for thread in threads {
+        if let Err(e) = scheduler_tx.send(thread).await {
+            error!(
+                board = %model,
+                error = %e,
+                "Failed to send thread to scheduler"
+            );
+            break;
+        }
+    }
 }
@@ -1,38 +1,165 @@
 //! Command-line interface for mujina-miner.
 //!
 //! This binary provides a CLI for controlling and monitoring the miner
-//! daemon via the HTTP API.
+//! daemon via the HTTP API, plus offline tools that need no daemon.
 
 use std::env;
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Serialize;
 
-use mujina_miner::api_client;
+use mujina_miner::api_client::{
+    self,
+    types::{
+        BoardState, BurnInReport, BurnInRequest, BurnInStatus, SetFanTargetRequest,
+        SetFrequencyRequest,
+    },
+};
+use mujina_miner::asic::thermal::ThermalConfig;
+use mujina_miner::asic::thermal_tune::{
+    self, Sample, Settling, StepRecord, Suggestion, ThermalModel, TuneStep,
+};
+use mujina_miner::config::{Config, DEFAULT_CONFIG_PATH};
+use mujina_miner::scheduler::decision_log;
+use mujina_miner::share_proof;
+use mujina_miner::stratum_v1::{self, PROBE_DIFFICULTY, PoolConfig};
+use mujina_miner::types::HashRate;
+
+#[path = "cli/completions.rs"]
+mod completions;
+#[path = "cli/output.rs"]
+mod output;
+
+use completions::Shell;
+use output::{Output, Render, Table, or_dash};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let output = Output::from_args(&mut args);
 
     if args.len() < 2 {
-        eprintln!("Usage: mujina-cli <command> [args]");
-        eprintln!();
-        eprintln!("Commands:");
-        eprintln!("  status          Show miner status");
-        eprintln!("  api <endpoint>  Raw API call (e.g. \"api miner\")");
-        eprintln!();
-        eprintln!("Environment:");
-        eprintln!("  MUJINA_API_URL    API base URL (default: http://127.0.0.1:7785)");
+        eprint!("{}", completions::usage());
         std::process::exit(1);
     }
 
     let command = &args[1];
 
     match command.as_str() {
-        "status" => cmd_status().await?,
+        "status" => cmd_status(output).await?,
         "api" => {
             let endpoint = args.get(2).map_or("", String::as_str);
             cmd_api(endpoint).await?;
         }
+        "replay" => {
+            let Some(path) = args.get(2) else {
+                bail!("Usage: mujina-cli replay <decision-log>");
+            };
+            cmd_replay(path, output)?;
+        }
+        "verify-shares" => {
+            let Some(path) = args.get(2) else {
+                bail!("Usage: mujina-cli verify-shares <file>");
+            };
+            cmd_verify_shares(path, output)?;
+        }
+        "pool-test" => {
+            let (Some(url), Some(user)) = (args.get(2), args.get(3)) else {
+                bail!("Usage: mujina-cli pool-test <url> <user> [pass]");
+            };
+            let pass = args.get(4).map_or("x", String::as_str);
+            cmd_pool_test(url, user, pass, output).await?;
+        }
+        "registers" => {
+            let diff = args[2..].iter().any(|a| a == "--diff");
+            let mut positional = args[2..].iter().filter(|a| *a != "--diff");
+            let Some(board) = positional.next() else {
+                bail!("Usage: mujina-cli registers <board> [address] [--diff]");
+            };
+            let address = match positional.next() {
+                Some(address) => parse_chip_address(address)?,
+                None => 0,
+            };
+            cmd_registers(board, address, diff, output).await?;
+        }
+        "burn-in" => {
+            let usage = "Usage: mujina-cli burn-in <board> [--freqs <mhz,...>] [--step-secs <n>] [--status]";
+            let Some(board) = args.get(2) else {
+                bail!(usage);
+            };
+            let mut request = BurnInRequest::default();
+            let mut status_only = false;
+            let mut rest = args[3..].iter();
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--status" => status_only = true,
+                    "--freqs" => {
+                        let list = rest.next().context(usage)?;
+                        request.frequencies_mhz = Some(
+                            list.split(',')
+                                .map(|f| f.trim().parse())
+                                .collect::<Result<_, _>>()
+                                .with_context(|| format!("invalid frequencies {list}"))?,
+                        );
+                    }
+                    "--step-secs" => {
+                        let secs = rest.next().context(usage)?;
+                        request.step_secs = Some(
+                            secs.parse()
+                                .with_context(|| format!("invalid seconds {secs}"))?,
+                        );
+                    }
+                    _ => bail!(usage),
+                }
+            }
+            cmd_burn_in(board, request, status_only, output).await?;
+        }
+        "thermal-tune" => {
+            let usage = "Usage: mujina-cli thermal-tune <board> [--fans <pct,...>] [--freqs <mhz,...>] [--limit <c>] [--settle-secs <n>] [--config <path>] [--yes]";
+            let Some(board) = args.get(2) else {
+                bail!(usage);
+            };
+            let mut options = ThermalTuneOptions::default();
+            let mut rest = args[3..].iter();
+            while let Some(arg) = rest.next() {
+                match arg.as_str() {
+                    "--yes" => options.yes = true,
+                    "--fans" => options.fans = parse_list(rest.next().context(usage)?)?,
+                    "--freqs" => options.frequencies = parse_list(rest.next().context(usage)?)?,
+                    "--limit" => {
+                        let limit = rest.next().context(usage)?;
+                        options.limit_c = limit
+                            .parse()
+                            .with_context(|| format!("invalid temperature {limit}"))?;
+                    }
+                    "--settle-secs" => {
+                        let secs = rest.next().context(usage)?;
+                        options.settling.timeout = Duration::from_secs(
+                            secs.parse()
+                                .with_context(|| format!("invalid seconds {secs}"))?,
+                        );
+                    }
+                    "--config" => options.config = Some(rest.next().context(usage)?.into()),
+                    _ => bail!(usage),
+                }
+            }
+            cmd_thermal_tune(board, options, output).await?;
+        }
+        "config" => match (args.get(2).map(String::as_str), args.get(3)) {
+            (Some("export"), path) => cmd_config_export(path.map(Path::new), output).await?,
+            (Some("import"), Some(path)) => cmd_config_import(Path::new(path), output).await?,
+            _ => bail!("Usage: mujina-cli config export [file] | config import <file>"),
+        },
+        "completions" => {
+            let Some(shell) = args.get(2) else {
+                bail!("Usage: mujina-cli completions <bash|zsh|fish>");
+            };
+            print!("{}", completions::script(shell.parse::<Shell>()?));
+        }
         _ => {
             eprintln!("Unknown command: {}", command);
             eprintln!("Run without arguments to see usage.");
@@ -43,17 +170,22 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-/// Build an API client, honoring MUJINA_API_URL if set.
-fn make_client() -> api_client::Client {
-    match env::var("MUJINA_API_URL") {
+/// Build an API client, honoring MUJINA_API_SOCKET or MUJINA_API_URL if set.
+fn make_client() -> Result<api_client::Client> {
+    if let Some(path) = env::var_os("MUJINA_API_SOCKET") {
+        return api_client::Client::with_unix_socket(Path::new(&path));
+    }
+    Ok(match env::var("MUJINA_API_URL") {
         Ok(url) => api_client::Client::with_base_url(url),
         Err(_) => api_client::Client::new(),
-    }
+    })
 }
 
 /// Make a raw API call and pretty-print the JSON response.
+///
+/// The response is printed as is, `--json` or not.
 async fn cmd_api(endpoint: &str) -> Result<()> {
-    let client = make_client();
+    let client = make_client()?;
     let body = client.get_raw(endpoint).await?;
 
     // Try to pretty-print as JSON; fall back to raw text
@@ -65,30 +197,947 @@ async fn cmd_api(endpoint: &str) -> Result<()> {
     Ok(())
 }
 
+/// Outcome of `config export` to a file, or `config import`.
+#[derive(Serialize)]
+struct ConfigWritten {
+    path: PathBuf,
+    /// Whether the miner must restart to apply the file
+    restart_needed: bool,
+}
+
+impl Render for ConfigWritten {
+    fn render(&self, out: &mut dyn Write) -> io::Result<()> {
+        if self.restart_needed {
+            writeln!(out, "Configuration written; restart the miner to apply it.")
+        } else {
+            writeln!(out, "Configuration saved to {}", self.path.display())
+        }
+    }
+}
+
+/// Write the miner's configuration, without secrets, to `path` or
+/// stdout, for `config import` on another unit.
+///
+/// The file is TOML. On stdout it is TOML too, or JSON with `--json`.
+async fn cmd_config_export(path: Option<&Path>, output: Output) -> Result<()> {
+    let client = make_client()?;
+    let config: Config = client.get_json("config").await?;
+    let text = toml::to_string(&config).context("failed to format configuration")?;
+    match path {
+        Some(path) => {
+            std::fs::write(path, text)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            output.emit(&ConfigWritten {
+                path: path.into(),
+                restart_needed: false,
+            })?;
+        }
+        None if output.is_json() => println!("{}", serde_json::to_string_pretty(&config)?),
+        None => print!("{text}"),
+    }
+    Ok(())
+}
+
+/// Replace the miner's configuration file with the one at `path`.
+///
+/// Secrets the file leaves out keep the miner's current values. The
+/// miner only accepts this over its admin socket (`MUJINA_API_SOCKET`).
+async fn cmd_config_import(path: &Path, output: Output) -> Result<()> {
+    let config = Config::load_from(path)?;
+    let client = make_client()?;
+    client.put_json("config", &config).await?;
+    output.emit(&ConfigWritten {
+        path: path.into(),
+        restart_needed: true,
+    })
+}
+
+/// Summary printed by `status`.
+#[derive(Serialize)]
+struct Status {
+    uptime_secs: u64,
+    /// Hashes per second
+    hashrate: u64,
+    shares_submitted: u64,
+    sources: Vec<SourceStatus>,
+    boards: Vec<BoardStatus>,
+}
+
+#[derive(Serialize)]
+struct SourceStatus {
+    name: String,
+    health: u8,
+    active: bool,
+    /// Threads of its own, if the source is pinned to them
+    pinned_threads: Option<u32>,
+    backfill: bool,
+    /// Remediation step being watched, if any
+    remediating: Option<String>,
+}
+
+#[derive(Serialize)]
+struct BoardStatus {
+    name: String,
+    model: String,
+    /// Hashes per second
+    hashrate: u64,
+}
+
+impl Render for Status {
+    fn render(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "Uptime:   {} s", self.uptime_secs)?;
+        writeln!(out, "Hashrate: {}", HashRate::from(self.hashrate))?;
+        writeln!(out, "Shares:   {}", self.shares_submitted)?;
+
+        if self.sources.is_empty() {
+            writeln!(out, "Sources:  (none)")?;
+        } else {
+            writeln!(out, "Sources:")?;
+            let mut table = Table::new(&["NAME", "HEALTH", "STATE"]);
+            for source in &self.sources {
+                let mut state = Vec::new();
+                if source.active {
+                    state.push("active".to_string());
+                }
+                if let Some(threads) = source.pinned_threads {
+                    state.push(format!("pinned to {threads} threads"));
+                } else if source.backfill {
+                    state.push("backfill (not earning)".to_string());
+                }
+                if let Some(step) = &source.remediating {
+                    state.push(format!("remediating: {step}"));
+                }
+                table.row(vec![
+                    source.name.clone(),
+                    source.health.to_string(),
+                    state.join(", "),
+                ]);
+            }
+            table.write(out, 2)?;
+        }
+
+        if !self.boards.is_empty() {
+            writeln!(out, "Boards:")?;
+            let mut table = Table::new(&["NAME", "MODEL", "HASHRATE"]);
+            for board in &self.boards {
+                table.row(vec![
+                    board.name.clone(),
+                    board.model.clone(),
+                    HashRate::from(board.hashrate).to_string(),
+                ]);
+            }
+            table.write(out, 2)?;
+        }
+        Ok(())
+    }
+}
+
 /// Print a summary of the current miner state.
-async fn cmd_status() -> Result<()> {
-    let client = make_client();
+async fn cmd_status(output: Output) -> Result<()> {
+    let client = make_client()?;
     let state = client.get_miner().await?;
 
-    println!("Uptime:  {} s", state.uptime_secs);
-    println!("Hashrate: {} H/s", state.hashrate);
-    println!("Shares:  {}", state.shares_submitted);
+    output.emit(&Status {
+        uptime_secs: state.uptime_secs,
+        hashrate: state.hashrate,
+        shares_submitted: state.shares_submitted,
+        sources: state
+            .sources
+            .iter()
+            .map(|source| SourceStatus {
+                name: source.name.clone(),
+                health: source.health.score,
+                active: source.active,
+                pinned_threads: source.pinned.then_some(source.threads),
+                backfill: source.backfill,
+                remediating: source.remediation.step.map(|step| step.to_string()),
+            })
+            .collect(),
+        boards: state
+            .boards
+            .iter()
+            .map(|board| BoardStatus {
+                name: board.name.clone(),
+                model: board.model.clone(),
+                hashrate: board.threads.iter().map(|t| t.hashrate).sum(),
+            })
+            .collect(),
+    })
+}
 
-    if state.sources.is_empty() {
-        println!("Sources: (none)");
-    } else {
-        println!("Sources:");
-        for source in &state.sources {
-            println!("  - {}", source.name);
+/// Outcome of `replay`.
+#[derive(Serialize)]
+struct Replay {
+    decisions: usize,
+    divergences: Vec<String>,
+    /// State at the end of the log
+    active_source: Option<String>,
+    threads: usize,
+    live_tasks: usize,
+    paused: bool,
+}
+
+impl Render for Replay {
+    fn render(&self, out: &mut dyn Write) -> io::Result<()> {
+        for divergence in &self.divergences {
+            writeln!(out, "{divergence}")?;
         }
+        writeln!(
+            out,
+            "{} decisions, {} divergent",
+            self.decisions,
+            self.divergences.len()
+        )?;
+        writeln!(
+            out,
+            "Final state: source {}, {} threads, {} tasks{}",
+            self.active_source.as_deref().unwrap_or("(none)"),
+            self.threads,
+            self.live_tasks,
+            if self.paused { ", paused" } else { "" }
+        )
     }
+}
+
+/// Replay a scheduler decision log and report where it diverges.
+///
+/// Exits non-zero if any recorded decision differs from what the current
+/// scheduling code would do.
+fn cmd_replay(path: &str, output: Output) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("failed to open {path}"))?;
+    let records = decision_log::read_log(BufReader::new(file))?;
+    let report = decision_log::replay(&records);
+
+    output.emit(&Replay {
+        decisions: report.steps,
+        divergences: report.divergences.iter().map(ToString::to_string).collect(),
+        active_source: report.active_source.clone(),
+        threads: report.threads.len(),
+        live_tasks: report.live_tasks,
+        paused: report.paused,
+    })?;
 
-    if !state.boards.is_empty() {
-        println!("Boards:");
-        for board in &state.boards {
-            println!("  - {}", board.model);
+    if !report.is_clean() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Outcome of `verify-shares`.
+#[derive(Serialize)]
+struct VerifyShares {
+    shares: usize,
+    verified: usize,
+    /// Work the verified shares prove, in difficulty-1 shares
+    difficulty_sum: f64,
+    /// First and last acceptance among the verified shares, in ms since
+    /// the Unix epoch
+    first_at_ms: Option<u64>,
+    last_at_ms: Option<u64>,
+    failures: Vec<ShareFailure>,
+}
+
+/// A proof that didn't verify.
+#[derive(Serialize)]
+struct ShareFailure {
+    /// Position of the proof in the file, from 1
+    proof: usize,
+    reason: String,
+}
+
+impl Render for VerifyShares {
+    fn render(&self, out: &mut dyn Write) -> io::Result<()> {
+        for failure in &self.failures {
+            writeln!(out, "proof {}: {}", failure.proof, failure.reason)?;
+        }
+        writeln!(
+            out,
+            "{} shares, {} verified, {} failed",
+            self.shares,
+            self.verified,
+            self.failures.len()
+        )?;
+        writeln!(
+            out,
+            "Proven work: {:.0} difficulty-1 shares",
+            self.difficulty_sum
+        )?;
+        if let (Some(first), Some(last)) = (self.first_at_ms, self.last_at_ms) {
+            let hours = last.saturating_sub(first) as f64 / 3_600_000.0;
+            writeln!(out, "Accepted over {hours:.1} h")?;
         }
+        Ok(())
     }
+}
+
+/// Check every share proof in a file by hashing its header again.
+///
+/// Exits non-zero if any proof fails: a header that doesn't hash to the
+/// claimed hash, a hash short of the claimed difficulty, or a share
+/// claimed twice.
+fn cmd_verify_shares(path: &str, output: Output) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("failed to open {path}"))?;
+    let proofs = share_proof::read(BufReader::new(file))?;
+    let audit = share_proof::audit(&proofs);
+
+    output.emit(&VerifyShares {
+        shares: audit.shares,
+        verified: audit.verified,
+        difficulty_sum: audit.difficulty_sum,
+        first_at_ms: audit.span_ms.map(|(first, _)| first),
+        last_at_ms: audit.span_ms.map(|(_, last)| last),
+        failures: audit
+            .failures
+            .iter()
+            .map(|(proof, reason)| ShareFailure {
+                proof: *proof,
+                reason: reason.clone(),
+            })
+            .collect(),
+    })?;
 
+    if !audit.is_clean() {
+        std::process::exit(1);
+    }
     Ok(())
 }
+
+/// Outcome of `pool-test`.
+#[derive(Serialize)]
+struct PoolTest {
+    url: String,
+    worker: String,
+    /// Version rolling mask, or null without version rolling
+    version_mask: Option<u32>,
+    /// Hex
+    extranonce1: String,
+    extranonce2_size: usize,
+    /// Time to the first job, or null if none arrived
+    first_job_ms: Option<u64>,
+    difficulty: Option<u64>,
+    suggested_difficulty: u64,
+    suggestion_accepted: bool,
+}
+
+impl Render for PoolTest {
+    fn render(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "Pool:            {}", self.url)?;
+        writeln!(out, "Worker:          {} (authorized)", self.worker)?;
+        match self.version_mask {
+            Some(mask) => writeln!(out, "Version rolling: mask {mask:#010x}")?,
+            None => writeln!(out, "Version rolling: not supported")?,
+        }
+        writeln!(
+            out,
+            "Extranonce:      extranonce1 {}, extranonce2 {} bytes",
+            self.extranonce1, self.extranonce2_size
+        )?;
+        match self.first_job_ms {
+            Some(ms) => writeln!(out, "First job:       {ms} ms")?,
+            None => writeln!(out, "First job:       none received")?,
+        }
+        let difficulty = self
+            .difficulty
+            .map_or("not set".to_string(), |d| d.to_string());
+        writeln!(out, "Difficulty:      {difficulty}")?;
+        writeln!(
+            out,
+            "Suggested {}:  {}",
+            self.suggested_difficulty,
+            if self.suggestion_accepted {
+                "accepted"
+            } else {
+                "ignored"
+            }
+        )
+    }
+}
+
+/// Connect to a pool, run the handshake, and report what it offers.
+///
+/// Nothing is hashed or submitted. Fails if the pool can't be reached or
+/// refuses the worker.
+async fn cmd_pool_test(url: &str, user: &str, pass: &str, output: Output) -> Result<()> {
+    let config = PoolConfig {
+        url: url.to_string(),
+        username: user.to_string(),
+        password: pass.to_string(),
+        ..Default::default()
+    };
+    let probe = stratum_v1::probe(config)
+        .await
+        .with_context(|| format!("pool test against {url} failed"))?;
+
+    output.emit(&PoolTest {
+        url: url.to_string(),
+        worker: probe.worker,
+        version_mask: probe.version_mask,
+        extranonce1: hex::encode(&probe.extranonce1),
+        extranonce2_size: probe.extranonce2_size,
+        first_job_ms: probe.first_job_latency.map(|l| l.as_millis() as u64),
+        difficulty: probe.difficulty,
+        suggested_difficulty: PROBE_DIFFICULTY,
+        suggestion_accepted: probe.suggestion_accepted,
+    })
+}
+
+/// Parse a chip address given in decimal or as 0x-prefixed hex.
+fn parse_chip_address(s: &str) -> Result<u8> {
+    let parsed = match s.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    parsed.with_context(|| format!("invalid chip address {s}"))
+}
+
+/// Outcome of `registers`.
+#[derive(Serialize)]
+struct Registers {
+    thread: String,
+    chip_address: u8,
+    registers: Vec<Register>,
+    /// Registers that differ from initialization, if a diff was asked for
+    mismatches: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct Register {
+    address: u8,
+    name: String,
+    /// Null if the chip didn't answer
+    value: Option<u32>,
+    /// Value initialization wrote, if a diff was asked for
+    expected: Option<u32>,
+}
+
+impl Render for Registers {
+    fn render(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "{} chip {:#04x}", self.thread, self.chip_address)?;
+        let mut table = Table::new(&["ADDR", "NAME", "VALUE", "EXPECTED"]);
+        for register in &self.registers {
+            let expected = match register.expected {
+                Some(e) if register.value != Some(e) => format!("{e:#010x}"),
+                _ => String::new(),
+            };
+            table.row(vec![
+                format!("{:#04x}", register.address),
+                register.name.clone(),
+                register
+                    .value
+                    .map_or("no answer".to_string(), |v| format!("{v:#010x}")),
+                expected,
+            ]);
+        }
+        table.write(out, 2)?;
+
+        match &self.mismatches {
+            Some(mismatches) if mismatches.is_empty() => {
+                writeln!(out, "All registers match initialization")
+            }
+            Some(mismatches) => {
+                writeln!(out, "Differ from initialization: {}", mismatches.join(", "))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Print a chip's registers, and with `diff` how they compare to what
+/// initialization wrote.
+///
+/// Exits non-zero if any register differs.
+async fn cmd_registers(board: &str, address: u8, diff: bool, output: Output) -> Result<()> {
+    let client = make_client()?;
+    let dump: api_client::types::ChipRegisterDump = client
+        .get_json(&format!(
+            "boards/{board}/chips/{address}/registers?diff={diff}"
+        ))
+        .await?;
+
+    let differs = !dump.mismatches.is_empty();
+    output.emit(&Registers {
+        thread: dump.thread,
+        chip_address: dump.chip_address,
+        registers: dump
+            .registers
+            .into_iter()
+            .map(|r| Register {
+                address: r.address,
+                name: r.name,
+                value: r.value,
+                expected: r.expected,
+            })
+            .collect(),
+        mismatches: diff.then_some(dump.mismatches),
+    })?;
+
+    if diff && differs {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Outcome of `burn-in`.
+#[derive(Serialize)]
+struct BurnIn {
+    board: String,
+    /// Where a burn-in still running has got to
+    running: Option<BurnInRunning>,
+    /// The board's last burn-in report, if there is one
+    report: Option<BurnInOutcome>,
+}
+
+#[derive(Serialize)]
+struct BurnInRunning {
+    step: usize,
+    steps: usize,
+    frequency_mhz: f32,
+    step_remaining_secs: u64,
+}
+
+#[derive(Serialize)]
+struct BurnInOutcome {
+    /// Board name at the time of the burn-in
+    board: String,
+    serial: Option<String>,
+    passed: bool,
+    safe_frequency_mhz: Option<f32>,
+    steps: Vec<BurnInStep>,
+}
+
+#[derive(Serialize)]
+struct BurnInStep {
+    frequency_mhz: f32,
+    duration_secs: u64,
+    max_temp_c: Option<f32>,
+    mean_power_w: Option<f32>,
+    /// Hashes per second
+    mean_hashrate: u64,
+    /// Hardware errors per nonce
+    error_rate: f64,
+    /// Why the step failed, or null if it passed
+    failure: Option<String>,
+}
+
+impl From<BurnInReport> for BurnInOutcome {
+    fn from(report: BurnInReport) -> Self {
+        Self {
+            board: report.board,
+            serial: report.serial,
+            passed: report.passed,
+            safe_frequency_mhz: report.safe_frequency_mhz,
+            steps: report
+                .steps
+                .into_iter()
+                .map(|step| BurnInStep {
+                    frequency_mhz: step.frequency_mhz,
+                    duration_secs: step.duration_secs,
+                    max_temp_c: step.max_temp_c,
+                    mean_power_w: step.mean_power_w,
+                    mean_hashrate: step.mean_hashrate,
+                    error_rate: step.error_rate,
+                    failure: step.failure,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Render for BurnIn {
+    fn render(&self, out: &mut dyn Write) -> io::Result<()> {
+        if let Some(progress) = &self.running {
+            writeln!(
+                out,
+                "Running: step {}/{} at {} MHz, {} s left in step",
+                progress.step, progress.steps, progress.frequency_mhz, progress.step_remaining_secs
+            )?;
+        }
+        let Some(report) = &self.report else {
+            return writeln!(out, "No burn-in report for {}", self.board);
+        };
+
+        writeln!(
+            out,
+            "Burn-in of {} ({}): {}",
+            report.board,
+            report.serial.as_deref().unwrap_or("no serial"),
+            if report.passed { "passed" } else { "failed" }
+        )?;
+        let mut table = Table::new(&["MHZ", "SECS", "MAX TEMP", "POWER", "HASHRATE", "ERRORS", ""]);
+        for step in &report.steps {
+            table.row(vec![
+                format!("{:.1}", step.frequency_mhz),
+                step.duration_secs.to_string(),
+                or_dash(step.max_temp_c.map(|t| format!("{t:.1} °C"))),
+                or_dash(step.mean_power_w.map(|p| format!("{p:.1} W"))),
+                HashRate::from(step.mean_hashrate).to_string(),
+                format!("{:.2}%", step.error_rate * 100.0),
+                step.failure.clone().unwrap_or_else(|| "ok".into()),
+            ]);
+        }
+        table.write(out, 2)?;
+        match report.safe_frequency_mhz {
+            Some(mhz) => writeln!(out, "Safe operating point: {mhz} MHz"),
+            None => writeln!(out, "Safe operating point: none found"),
+        }
+    }
+}
+
+/// How often a followed burn-in is polled.
+const BURN_IN_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Start a burn-in on `board` and follow it to its report, or with
+/// `status_only` print where it stands.
+///
+/// Exits non-zero if the reported burn-in failed.
+async fn cmd_burn_in(
+    board: &str,
+    request: BurnInRequest,
+    status_only: bool,
+    output: Output,
+) -> Result<()> {
+    let client = make_client()?;
+    let path = format!("boards/{board}/burn-in");
+
+    if !status_only {
+        client.post_json(&path, &request).await?;
+        output.progress(format_args!("Burn-in started on {board}"));
+    }
+    let mut last_step = 0;
+    let status = loop {
+        let status: BurnInStatus = client.get_json(&path).await?;
+        match status.running {
+            Some(ref progress) if !status_only => {
+                if progress.step != last_step {
+                    last_step = progress.step;
+                    output.progress(format_args!(
+                        "Step {}/{}: {} MHz",
+                        progress.step, progress.steps, progress.frequency_mhz
+                    ));
+                }
+                tokio::time::sleep(BURN_IN_POLL_INTERVAL).await;
+            }
+            _ => break status,
+        }
+    };
+
+    let failed = status.running.is_none() && status.report.as_ref().is_some_and(|r| !r.passed);
+    output.emit(&BurnIn {
+        board: board.to_string(),
+        running: status.running.map(|progress| BurnInRunning {
+            step: progress.step,
+            steps: progress.steps,
+            frequency_mhz: progress.frequency_mhz,
+            step_remaining_secs: progress.step_remaining_secs,
+        }),
+        report: status.report.map(BurnInOutcome::from),
+    })?;
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// How often a thermal tuning run reads the board's temperature.
+const THERMAL_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Options for `thermal-tune`.
+struct ThermalTuneOptions {
+    fans: Vec<u8>,
+    frequencies: Vec<f32>,
+    limit_c: f32,
+    settling: Settling,
+    /// Config file to write the suggestion into; the daemon's by default
+    config: Option<PathBuf>,
+    /// Write without asking
+    yes: bool,
+}
+
+impl Default for ThermalTuneOptions {
+    fn default() -> Self {
+        Self {
+            fans: vec![100, 60],
+            frequencies: vec![400.0, 500.0],
+            limit_c: thermal_tune::DEFAULT_LIMIT_C,
+            settling: Settling::default(),
+            config: None,
+            yes: false,
+        }
+    }
+}
+
+/// Parse a comma-separated list of numbers.
+fn parse_list<T: std::str::FromStr>(list: &str) -> Result<Vec<T>> {
+    list.split(',')
+        .map(|v| v.trim().parse())
+        .collect::<Result<Vec<T>, _>>()
+        .map_err(|_| anyhow!("invalid list {list}"))
+}
+
+/// Outcome of `thermal-tune`: the fitted model and the settings it
+/// suggests.
+#[derive(Serialize)]
+struct ThermalTune {
+    board: String,
+    /// °C per MHz of core frequency
+    c_per_mhz: f32,
+    /// °C per percent of fan duty
+    c_per_fan_percent: f32,
+    /// Largest wander of a settled temperature, °C
+    noise_c: f32,
+    /// Null if the steps were too small to measure it
+    time_constant_secs: Option<u64>,
+    limit_c: f32,
+    /// Slowest fan speed the suggestion holds for
+    min_fan_percent: u8,
+    /// Temperature derating starts at, °C
+    derate_from_c: f32,
+    derating: String,
+    thermal_overshoot: f32,
+    /// Null to leave the setting unchanged
+    thermal_adjust_secs: Option<u64>,
+    /// Config file the suggestion is for
+    config_path: PathBuf,
+    /// Whether the suggestion was written to it
+    written: bool,
+}
+
+impl Render for ThermalTune {
+    fn render(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "Model: {:.3} °C/MHz, {:.3} °C per % fan, noise {:.2} °C",
+            self.c_per_mhz, self.c_per_fan_percent, self.noise_c
+        )?;
+        match self.time_constant_secs {
+            Some(tau) => writeln!(out, "Time constant: {tau} s")?,
+            None => writeln!(out, "Time constant: not measured (steps too small)")?,
+        }
+        writeln!(
+            out,
+            "Suggested for a {} °C limit with the fan at {}% or more:",
+            self.limit_c, self.min_fan_percent
+        )?;
+        writeln!(out, "  Derating from:       {} °C", self.derate_from_c)?;
+        writeln!(out, "  derating =           \"{}\"", self.derating)?;
+        writeln!(out, "  thermal_overshoot =  {}", self.thermal_overshoot)?;
+        match self.thermal_adjust_secs {
+            Some(secs) => writeln!(out, "  thermal_adjust_secs = {secs}"),
+            None => writeln!(out, "  thermal_adjust_secs: unchanged"),
+        }
+    }
+}
+
+/// Step `board` through fan speeds and frequencies, fit its thermal
+/// response, and offer to write the suggested settings to the config file.
+///
+/// The fan and frequency go back to automatic and the profile's when the
+/// run ends, however it ends.
+async fn cmd_thermal_tune(board: &str, options: ThermalTuneOptions, output: Output) -> Result<()> {
+    let client = make_client()?;
+    let state: BoardState = client.get_json(&format!("boards/{board}")).await?;
+    let fan = state
+        .fans
+        .first()
+        .context("board reports no fan to step")?
+        .name
+        .clone();
+
+    let config_path = options
+        .config
+        .clone()
+        .or_else(|| env::var_os("MUJINA_CONFIG").map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
+    let tick = if config_path.exists() {
+        Config::load_from(&config_path)?.boards.thermal().tick
+    } else {
+        ThermalConfig::default().tick
+    };
+
+    let steps = thermal_tune::plan(&options.fans, &options.frequencies);
+    output.progress(format_args!(
+        "Tuning {board}: {} steps of up to {} s",
+        steps.len(),
+        options.settling.timeout.as_secs()
+    ));
+    let run = tune_steps(&client, board, &fan, &steps, &options.settling, output);
+    let records = tokio::select! {
+        records = run => records,
+        _ = tokio::signal::ctrl_c() => Err(anyhow!("interrupted")),
+    };
+
+    let restore_fan = client
+        .put_json(
+            &format!("boards/{board}/fans/{fan}"),
+            &SetFanTargetRequest {
+                target_percent: None,
+            },
+        )
+        .await;
+    let restore_frequency = client
+        .put_json(
+            &format!("boards/{board}/frequency"),
+            &SetFrequencyRequest {
+                frequency_mhz: None,
+            },
+        )
+        .await;
+    let records = records?;
+    restore_fan.context("failed to return the fan to automatic control")?;
+    restore_frequency.context("failed to return the frequency to the profile's")?;
+
+    let model = ThermalModel::fit(&records)?;
+    let min_fan = options.fans.iter().copied().min().unwrap_or(100);
+    let min_mhz = options.frequencies.iter().copied().fold(f32::MAX, f32::min);
+    let max_mhz = options.frequencies.iter().copied().fold(f32::MIN, f32::max);
+    let suggestion = model.suggest(options.limit_c, min_fan, min_mhz, max_mhz, tick);
+    let mut report = ThermalTune {
+        board: board.to_string(),
+        c_per_mhz: model.c_per_mhz,
+        c_per_fan_percent: model.c_per_fan_percent,
+        noise_c: model.noise_c,
+        time_constant_secs: model.time_constant.map(|tau| tau.as_secs()),
+        limit_c: options.limit_c,
+        min_fan_percent: min_fan,
+        derate_from_c: suggestion.target_c,
+        derating: suggestion.derating.clone(),
+        thermal_overshoot: suggestion.overshoot_c,
+        thermal_adjust_secs: suggestion.adjust_secs,
+        config_path: config_path.clone(),
+        written: false,
+    };
+
+    // A script can't answer the prompt; only --yes writes
+    if output.is_json() {
+        if options.yes {
+            write_suggestion(&suggestion, &config_path)?;
+            report.written = true;
+        }
+        return output.emit(&report);
+    }
+
+    output.emit(&report)?;
+    if !options.yes && !confirm(&format!("Write to {}?", config_path.display()))? {
+        return Ok(());
+    }
+    write_suggestion(&suggestion, &config_path)?;
+    println!(
+        "Wrote {}; restart the daemon to apply",
+        config_path.display()
+    );
+    Ok(())
+}
+
+/// Hold the board at each step until its temperature settles.
+async fn tune_steps(
+    client: &api_client::Client,
+    board: &str,
+    fan: &str,
+    steps: &[TuneStep],
+    settling: &Settling,
+    output: Output,
+) -> Result<Vec<StepRecord>> {
+    let mut start_c = read_chip_temperature(client, board)
+        .await?
+        .context("board reports no chip temperature")?;
+
+    let mut records = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        output.progress(format_args!(
+            "Step {}/{}: fan {}%, {} MHz",
+            i + 1,
+            steps.len(),
+            step.fan_percent,
+            step.frequency_mhz
+        ));
+        client
+            .put_json(
+                &format!("boards/{board}/fans/{fan}"),
+                &SetFanTargetRequest {
+                    target_percent: Some(step.fan_percent),
+                },
+            )
+            .await?;
+        client
+            .put_json(
+                &format!("boards/{board}/frequency"),
+                &SetFrequencyRequest {
+                    frequency_mhz: Some(step.frequency_mhz),
+                },
+            )
+            .await?;
+
+        let began = tokio::time::Instant::now();
+        let mut samples = Vec::new();
+        while !settling.settled(&samples) && began.elapsed() < settling.timeout {
+            tokio::time::sleep(THERMAL_SAMPLE_INTERVAL).await;
+            if let Some(temp_c) = read_chip_temperature(client, board).await? {
+                samples.push(Sample {
+                    elapsed: began.elapsed(),
+                    temp_c,
+                });
+            }
+        }
+
+        let record = StepRecord::new(*step, start_c, &samples, settling)
+            .context("no temperature readings during step")?;
+        if record.settled {
+            output.progress(format_args!("  settled at {:.1} °C", record.steady_c));
+        } else {
+            output.progress(format_args!(
+                "  still moving after {} s, around {:.1} °C",
+                settling.timeout.as_secs(),
+                record.steady_c
+            ));
+        }
+        start_c = record.steady_c;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Read the hottest chip temperature `board` reports.
+async fn read_chip_temperature(client: &api_client::Client, board: &str) -> Result<Option<f32>> {
+    let state: BoardState = client.get_json(&format!("boards/{board}")).await?;
+    Ok(chip_temperature(&state))
+}
+
+/// The hottest chip temperature a board reports.
+fn chip_temperature(state: &BoardState) -> Option<f32> {
+    let hottest = |chips_only: bool| {
+        state
+            .temperatures
+            .iter()
+            .filter(|t| !chips_only || t.name.starts_with("asic"))
+            .filter_map(|t| t.temperature_c)
+            .reduce(f32::max)
+    };
+    hottest(true).or_else(|| hottest(false))
+}
+
+/// Ask a yes/no question on the terminal; anything but yes is no.
+fn confirm(question: &str) -> Result<bool> {
+    print!("{question} [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Write suggested settings into the config file at `path`, keeping the
+/// previous file as `<path>.bak`.
+fn write_suggestion(suggestion: &Suggestion, path: &Path) -> Result<()> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => {
+            let backup = path.with_extension("toml.bak");
+            std::fs::write(&backup, &text)
+                .with_context(|| format!("failed to write {}", backup.display()))?;
+            text
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    };
+    let text = suggestion.apply_to(&text)?;
+    std::fs::write(path, text).with_context(|| format!("failed to write {}", path.display()))
+}
@@ -0,0 +1,276 @@
+//! The command table, and shell completions generated from it.
+//!
+//! Usage text and completion scripts come from [`COMMANDS`], so a new
+//! command or flag shows up in both once it is listed there.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use anyhow::{Result, bail};
+
+use super::output::JSON_FLAG;
+
+/// A CLI command, as usage and completions describe it.
+pub struct Command {
+    pub name: &'static str,
+    /// Positional arguments and flags, as shown in usage
+    pub args: &'static str,
+    pub summary: &'static str,
+    /// Words completed after the command name: subcommands or flags
+    pub words: &'static [&'static str],
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "status",
+        args: "",
+        summary: "Show miner status",
+        words: &[],
+    },
+    Command {
+        name: "api",
+        args: "<endpoint>",
+        summary: "Raw API call (e.g. \"api miner\")",
+        words: &[],
+    },
+    Command {
+        name: "replay",
+        args: "<path>",
+        summary: "Replay a scheduler decision log",
+        words: &[],
+    },
+    Command {
+        name: "verify-shares",
+        args: "<file>",
+        summary: "Check share proofs by hashing each header again",
+        words: &[],
+    },
+    Command {
+        name: "pool-test",
+        args: "<url> <user> [pass]",
+        summary: "Check a pool's handshake without mining",
+        words: &[],
+    },
+    Command {
+        name: "registers",
+        args: "<board> [address] [--diff]",
+        summary: "Read back a chip's registers",
+        words: &["--diff"],
+    },
+    Command {
+        name: "burn-in",
+        args: "<board> [--freqs <mhz,...>] [--step-secs <n>] [--status]",
+        summary: "Burn a board in and report a safe frequency",
+        words: &["--freqs", "--step-secs", "--status"],
+    },
+    Command {
+        name: "thermal-tune",
+        args: "<board> [--fans <pct,...>] [--freqs <mhz,...>] [--limit <c>] [--settle-secs <n>] [--config <path>] [--yes]",
+        summary: "Measure a board's thermal response and suggest settings",
+        words: &[
+            "--fans",
+            "--freqs",
+            "--limit",
+            "--settle-secs",
+            "--config",
+            "--yes",
+        ],
+    },
+    Command {
+        name: "config",
+        args: "export [file] | import <file>",
+        summary: "Save the miner's configuration without secrets, or replace it keeping them",
+        words: &["export", "import"],
+    },
+    Command {
+        name: "completions",
+        args: "<bash|zsh|fish>",
+        summary: "Print a shell completion script",
+        words: &["bash", "zsh", "fish"],
+    },
+];
+
+/// Usage text listing every command.
+pub fn usage() -> String {
+    let mut text = format!("Usage: mujina-cli <command> [args] [{JSON_FLAG}]\n\nCommands:\n");
+    for command in COMMANDS {
+        let synopsis = format!("{} {}", command.name, command.args);
+        let synopsis = synopsis.trim_end();
+        if synopsis.len() < 16 {
+            let _ = writeln!(text, "  {synopsis:<16}{}", command.summary);
+        } else {
+            let _ = writeln!(text, "  {synopsis}");
+            let _ = writeln!(text, "{:18}{}", "", command.summary);
+        }
+    }
+    text.push_str(&format!(
+        "\nOptions:\n  {JSON_FLAG:<16}Print results as JSON instead of text\n"
+    ));
+    text.push_str("\nEnvironment:\n");
+    text.push_str("  MUJINA_API_URL    API base URL (default: http://127.0.0.1:7785)\n");
+    text.push_str("  MUJINA_API_SOCKET Connect through the daemon's admin socket instead\n");
+    text
+}
+
+/// Shells completions are generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl FromStr for Shell {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "bash" => Self::Bash,
+            "zsh" => Self::Zsh,
+            "fish" => Self::Fish,
+            _ => bail!("unknown shell {s}; expected bash, zsh or fish"),
+        })
+    }
+}
+
+/// Completion script for `shell`.
+///
+/// Completes command names, then each command's subcommands and flags.
+/// Positional arguments (boards, paths) are left to the shell's default.
+pub fn script(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => bash(),
+        Shell::Zsh => zsh(),
+        Shell::Fish => fish(),
+    }
+}
+
+fn names() -> String {
+    COMMANDS
+        .iter()
+        .map(|c| c.name)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn bash() -> String {
+    let mut cases = String::new();
+    for command in COMMANDS.iter().filter(|c| !c.words.is_empty()) {
+        let _ = writeln!(
+            cases,
+            "        {}) words=\"{} {JSON_FLAG}\" ;;",
+            command.name,
+            command.words.join(" ")
+        );
+    }
+    format!(
+        r#"# bash completion for mujina-cli
+_mujina_cli() {{
+    local cur=${{COMP_WORDS[COMP_CWORD]}}
+    if [[ $COMP_CWORD -eq 1 ]]; then
+        COMPREPLY=($(compgen -W "{names} {JSON_FLAG}" -- "$cur"))
+        return
+    fi
+    local words="{JSON_FLAG}"
+    case ${{COMP_WORDS[1]}} in
+{cases}    esac
+    COMPREPLY=($(compgen -W "$words" -- "$cur"))
+    [[ ${{#COMPREPLY[@]}} -eq 0 ]] && COMPREPLY=($(compgen -f -- "$cur"))
+}}
+complete -F _mujina_cli mujina-cli
+"#,
+        names = names()
+    )
+}
+
+fn zsh() -> String {
+    let mut commands = String::new();
+    let mut cases = String::new();
+    for command in COMMANDS {
+        let _ = writeln!(
+            commands,
+            "        '{}:{}'",
+            command.name,
+            command.summary.replace('\'', "'\\''")
+        );
+        if !command.words.is_empty() {
+            let _ = writeln!(
+                cases,
+                "        {}) compadd -- {} {JSON_FLAG} ;;",
+                command.name,
+                command.words.join(" ")
+            );
+        }
+    }
+    format!(
+        r#"#compdef mujina-cli
+# zsh completion for mujina-cli
+_mujina_cli() {{
+    local -a commands
+    commands=(
+{commands}    )
+    if (( CURRENT == 2 )); then
+        _describe 'command' commands
+        return
+    fi
+    case $words[2] in
+{cases}        *) _files ;;
+    esac
+}}
+_mujina_cli "$@"
+"#
+    )
+}
+
+fn fish() -> String {
+    let mut text = String::from("# fish completion for mujina-cli\ncomplete -c mujina-cli -f\n");
+    let _ = writeln!(
+        text,
+        "complete -c mujina-cli -l {} -d 'Print results as JSON instead of text'",
+        JSON_FLAG.trim_start_matches('-')
+    );
+    for command in COMMANDS {
+        let _ = writeln!(
+            text,
+            "complete -c mujina-cli -n '__fish_use_subcommand' -a {} -d '{}'",
+            command.name,
+            command.summary.replace('\'', "\\'")
+        );
+        for word in command.words {
+            let _ = match word.strip_prefix("--") {
+                Some(flag) => writeln!(
+                    text,
+                    "complete -c mujina-cli -n '__fish_seen_subcommand_from {}' -l {flag}",
+                    command.name
+                ),
+                None => writeln!(
+                    text,
+                    "complete -c mujina-cli -n '__fish_seen_subcommand_from {}' -a {word}",
+                    command.name
+                ),
+            };
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_offer_every_command_and_flag() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = script(shell);
+            for command in COMMANDS {
+                assert!(script.contains(command.name), "{shell:?}: {}", command.name);
+                for word in command.words {
+                    let word = word.trim_start_matches('-');
+                    assert!(script.contains(word), "{shell:?}: {word}");
+                }
+            }
+        }
+        assert!(usage().contains("  status          Show miner status\n"));
+        assert!("powershell".parse::<Shell>().is_err());
+    }
+}
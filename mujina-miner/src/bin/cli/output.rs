@@ -0,0 +1,146 @@
+//! Rendering command results for people or for scripts.
+//!
+//! Every command builds a report and hands it to [`Output::emit`]. By
+//! default the report is rendered as text and tables; with `--json` it is
+//! printed as JSON instead. Report fields are the JSON contract: they are
+//! only ever added to, so scripts keep working across releases.
+//!
+//! Progress lines go to stdout for people and to stderr with `--json`, so
+//! stdout holds nothing but the report.
+
+use std::fmt;
+use std::io::{self, Write};
+
+use anyhow::Result;
+use serde::Serialize;
+
+/// The flag selecting JSON output, accepted anywhere on the command line.
+pub const JSON_FLAG: &str = "--json";
+
+/// A command's result, printable either way.
+pub trait Render: Serialize {
+    /// Write the result for a person to read.
+    fn render(&self, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// How results are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    Human,
+    Json,
+}
+
+impl Output {
+    /// Pick the format from `args`, removing the flag that selected it.
+    pub fn from_args(args: &mut Vec<String>) -> Self {
+        let before = args.len();
+        args.retain(|a| a != JSON_FLAG);
+        if args.len() < before {
+            Self::Json
+        } else {
+            Self::Human
+        }
+    }
+
+    pub fn is_json(self) -> bool {
+        self == Self::Json
+    }
+
+    /// Print a command's result.
+    pub fn emit(self, report: &impl Render) -> Result<()> {
+        let mut stdout = io::stdout().lock();
+        match self {
+            Self::Human => report.render(&mut stdout)?,
+            Self::Json => {
+                serde_json::to_writer_pretty(&mut stdout, report)?;
+                writeln!(stdout)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Print a line about a command still running.
+    pub fn progress(self, line: fmt::Arguments<'_>) {
+        match self {
+            Self::Human => println!("{line}"),
+            Self::Json => eprintln!("{line}"),
+        }
+    }
+}
+
+/// Rows of text printed in aligned columns.
+#[derive(Debug, Default)]
+pub struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(header: &[&str]) -> Self {
+        Self {
+            header: header.iter().map(|h| h.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn row(&mut self, cells: Vec<String>) {
+        self.rows.push(cells);
+    }
+
+    /// Write the table, each line indented by `indent` spaces.
+    ///
+    /// Columns are left-aligned and two spaces apart; the last isn't
+    /// padded.
+    pub fn write(&self, out: &mut dyn Write, indent: usize) -> io::Result<()> {
+        let columns = self.header.len();
+        let mut widths = vec![0; columns];
+        for row in std::iter::once(&self.header).chain(&self.rows) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        for row in std::iter::once(&self.header).chain(&self.rows) {
+            let mut line = " ".repeat(indent);
+            for (i, cell) in row.iter().enumerate().take(columns) {
+                if i + 1 < columns {
+                    line.push_str(&format!("{cell:<width$}  ", width = widths[i]));
+                } else {
+                    line.push_str(cell);
+                }
+            }
+            writeln!(out, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// A value, or "-" for none.
+pub fn or_dash<T: fmt::Display>(value: Option<T>) -> String {
+    value.map_or("-".to_string(), |v| v.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_flag_is_taken_from_anywhere() {
+        let mut args: Vec<String> = ["mujina-cli", "status", "--json"].map(String::from).into();
+        assert_eq!(Output::from_args(&mut args), Output::Json);
+        assert_eq!(args, ["mujina-cli", "status"]);
+        assert_eq!(Output::from_args(&mut args), Output::Human);
+    }
+
+    #[test]
+    fn table_aligns_columns() {
+        let mut table = Table::new(&["NAME", "HEALTH", "NOTE"]);
+        table.row(vec!["pool-a".into(), "97".into(), "active".into()]);
+        table.row(vec!["b".into(), "100".into(), String::new()]);
+        let mut out = Vec::new();
+        table.write(&mut out, 2).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "  NAME    HEALTH  NOTE\n  pool-a  97      active\n  b       100\n"
+        );
+    }
+}
@@ -1,11 +1,48 @@
 //! Main entry point for the mujina-miner daemon.
 
-use mujina_miner::{daemon::Daemon, tracing};
+use mujina_miner::{
+    api,
+    backplane::BoardRegistry,
+    config::{self, Config, ConfigError},
+    daemon::Daemon,
+    tracing,
+    transport::UsbTransport,
+};
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    tracing::init_journald_or_stdout();
+fn main() -> anyhow::Result<()> {
+    // SAFETY: the runtime and its worker threads don't exist yet.
+    unsafe { api::claim_activation() };
+    tokio::runtime::Runtime::new()?.block_on(run())
+}
+
+async fn run() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--list-devices") {
+        return list_devices();
+    }
+
+    let config = match Config::load(args) {
+        Ok(config) => config,
+        Err(ConfigError::Help) => {
+            print!("{}", config::USAGE);
+            return Ok(());
+        }
+        Err(e) => {
+            eprintln!("mujina-minerd: {e}\n\n{}", config::USAGE);
+            std::process::exit(2);
+        }
+    };
 
-    let daemon = Daemon::new();
+    tracing::init_journald_or_stdout(config.daemon.log_level.as_deref());
+
+    let daemon = Daemon::new(config);
     daemon.run().await
 }
+
+/// Print detected hash boards as JSON and exit.
+fn list_devices() -> anyhow::Result<()> {
+    let devices = UsbTransport::enumerate()?;
+    let boards = BoardRegistry.detect(&devices);
+    println!("{}", serde_json::to_string_pretty(&boards)?);
+    Ok(())
+}
@@ -1,10 +1,11 @@
 use async_trait::async_trait;
 use futures::sink::SinkExt;
 use std::{
+    ops::RangeInclusive,
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tokio::{
     io::{AsyncRead, ReadBuf},
@@ -15,21 +16,32 @@ use tokio_stream::StreamExt;
 use tokio_util::codec::{FramedRead, FramedWrite};
 
 use crate::{
-    api_client::types::{BoardState, Fan, PowerMeasurement, TemperatureSensor},
+    api_client::types::{
+        BoardState, ChipRegisterDump, Fan, FirmwareUpdatePhase, FirmwareUpdateState,
+        PowerMeasurement, Profile, TemperatureSensor,
+    },
     asic::{
         ChipInfo,
-        bm13xx::{self, BM13xxProtocol, protocol::Command, thread::BM13xxThread},
-        hash_thread::{BoardPeripherals, HashThread, ThreadRemovalSignal},
+        bm13xx::{
+            self, BM13xxProtocol,
+            protocol::Command,
+            thread::{BM13xxThread, FrequencyControl, RegisterAccess},
+        },
+        hash_thread::{BoardPeripherals, ChipPowerState, HashThread, ThreadRemovalSignal},
     },
+    config,
     hw_trait::{
+        self, HwError,
         gpio::{Gpio, GpioPin, PinValue},
         i2c::I2c,
     },
     mgmt_protocol::{
         ControlChannel,
         bitaxe_raw::{
+            firmware::{BitaxeRawFirmware, FlashProgress},
             gpio::{BitaxeRawGpioController, BitaxeRawGpioPin},
             i2c::BitaxeRawI2c,
+            telemetry::BitaxeRawTelemetry,
         },
     },
     peripheral::{
@@ -37,14 +49,62 @@ use crate::{
         tps546::{Tps546, Tps546Config},
     },
     tracing::prelude::*,
-    transport::serial::{SerialControl, SerialReader, SerialStream, SerialWriter},
+    transport::{
+        CaptureTap,
+        serial::{SerialControl, SerialReader, SerialStream, SerialWriter},
+    },
+    types::{ReadingReceiver, ReadingSender, reading_channel},
 };
 
 use super::{
     Board, BoardError, BoardInfo,
+    idle_power::IdlePowerMeter,
     pattern::{Match, StringMatch},
+    stats::{self, BoardStatsHandle, SensorReadings},
 };
 
+/// Operating point of a Bitaxe Gamma under a [`Profile`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ProfileSettings {
+    frequency_mhz: f32,
+    core_voltage_v: f32,
+    fan_percent: u8,
+    /// Input power above which the stats monitor warns
+    power_budget_w: f32,
+}
+
+impl ProfileSettings {
+    fn for_profile(profile: Profile) -> Self {
+        match profile {
+            Profile::Quiet => Self {
+                frequency_mhz: 490.0,
+                core_voltage_v: 1.10,
+                fan_percent: 60,
+                power_budget_w: 15.0,
+            },
+            // Stock BM1370 settings from esp-miner
+            Profile::Balanced => Self {
+                frequency_mhz: 525.0,
+                core_voltage_v: 1.15,
+                fan_percent: 100,
+                power_budget_w: 20.0,
+            },
+            Profile::Turbo => Self {
+                frequency_mhz: 575.0,
+                core_voltage_v: 1.20,
+                fan_percent: 100,
+                power_budget_w: 25.0,
+            },
+        }
+    }
+}
+
+/// Core frequencies that can be set in place of the profile's, in MHz.
+///
+/// The top is the turbo profile's; faster needs more core voltage than
+/// any profile gives the chip.
+const FREQUENCY_RANGE_MHZ: RangeInclusive<f32> = 50.0..=575.0;
+
 /// Adapter implementing `AsicEnable` for Bitaxe's GPIO-based reset control.
 struct BitaxeAsicEnable {
     /// Reset pin (directly controls nRST on the BM1370)
@@ -70,6 +130,36 @@ impl crate::asic::hash_thread::AsicEnable for BitaxeAsicEnable {
     }
 }
 
+/// Adapter implementing `BaudRateControl` for the Bitaxe data port.
+struct BitaxeBaudControl {
+    /// Control handle for the data serial port, shared with the blocking
+    /// pool while it is reconfigured
+    control: Arc<SerialControl>,
+}
+
+#[async_trait]
+impl crate::asic::hash_thread::BaudRateControl for BitaxeBaudControl {
+    fn initial_baud_rate(&self) -> u32 {
+        BitaxeBoard::INITIAL_BAUD_RATE
+    }
+
+    fn target_baud_rate(&self) -> u32 {
+        BitaxeBoard::TARGET_BAUD_RATE
+    }
+
+    async fn set_baud_rate(&mut self, baud: u32) -> anyhow::Result<()> {
+        if self.control.current_baud_rate() == baud {
+            return Ok(());
+        }
+        // set_baud_rate() blocks until pending output has drained, so keep
+        // it off the runtime's worker threads
+        let control = Arc::clone(&self.control);
+        tokio::task::spawn_blocking(move || control.set_baud_rate(baud))
+            .await?
+            .map_err(|e| anyhow::anyhow!("Failed to set data port baud rate: {}", e))
+    }
+}
+
 /// A wrapper around AsyncRead that traces raw bytes as they're read
 struct TracingReader<R> {
     inner: R,
@@ -130,9 +220,14 @@ pub struct BitaxeBoard {
     data_writer: Option<FramedWrite<SerialWriter, bm13xx::FrameCodec>>,
     /// Reader for receiving responses from chips (transferred to hash thread)
     data_reader: Option<FramedRead<TracingReader<SerialReader>, bm13xx::FrameCodec>>,
-    /// Control handle for data channel (for baud rate changes)
-    #[expect(dead_code, reason = "will be used when baud rate change is fixed")]
-    data_control: SerialControl,
+    /// Control handle for data channel (transferred to hash thread for baud
+    /// rate changes)
+    data_control: Option<SerialControl>,
+    /// Path of the data serial port, for reopening once a disabled hash
+    /// thread has released it
+    data_path: String,
+    /// Records the data link when capture is configured, across reopens
+    data_capture: Option<CaptureTap>,
     /// Discovered chip information (passive record-keeping)
     chip_infos: Vec<ChipInfo>,
     /// Thread shutdown signal (board-to-thread implementation detail)
@@ -141,9 +236,29 @@ pub struct BitaxeBoard {
     stats_task_handle: Option<tokio::task::JoinHandle<()>>,
     /// Serial number from USB device info
     serial_number: Option<String>,
+    /// Stable board ID (serial number, or USB port path if there is none)
+    board_id: String,
     /// Channel for publishing board state to the API server.
-    /// Taken by `spawn_stats_monitor` which publishes periodic snapshots.
+    /// Taken by `spawn_stats_monitor`, which hands it to the stats aggregator.
     state_tx: Option<watch::Sender<BoardState>>,
+    /// Feeds the stats aggregator, once started
+    stats: Option<BoardStatsHandle>,
+    /// Chip's own temperature reading (sender transferred to hash thread)
+    chip_temp_tx: ReadingSender<f32>,
+    /// Chip's own temperature reading, as published by the hash thread
+    chip_temp_rx: ReadingReceiver<f32>,
+    /// Chip power state (sender transferred to hash thread)
+    power_state_tx: watch::Sender<ChipPowerState>,
+    /// Active operating profile (read by the stats task)
+    profile_tx: watch::Sender<Profile>,
+    /// Retunes the current hash thread, if one is running
+    frequency: Option<FrequencyControl>,
+    /// Core frequency set through the API, in place of the profile's
+    frequency_override: Option<f32>,
+    /// Reads chip registers through the current hash thread
+    registers: Option<RegisterAccess>,
+    /// Firmware update writing to the microcontroller, if one was started
+    firmware_task: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl BitaxeBoard {
@@ -151,11 +266,10 @@ impl BitaxeBoard {
     const ASIC_RESET_PIN: u8 = 0;
 
     /// Bitaxe Gamma board configuration
-    /// The Gamma uses a BM1370 chip and runs at 1Mbps after initialization
-    #[expect(dead_code, reason = "will be used when baud rate change is fixed")]
+    /// The Gamma uses a BM1370 chip which starts at 115200 baud out of reset
+    /// and runs at 1Mbps after initialization
+    const INITIAL_BAUD_RATE: u32 = 115_200;
     const TARGET_BAUD_RATE: u32 = 1_000_000;
-    #[expect(dead_code, reason = "will be used when baud rate change is fixed")]
-    const CHIP_BAUD_REGISTER: bm13xx::protocol::BaudRate = bm13xx::protocol::BaudRate::Baud1M;
     const EXPECTED_CHIP_ID: [u8; 2] = [0x13, 0x70]; // BM1370
 
     /// Creates a new BitaxeBoard instance with the provided serial streams.
@@ -163,6 +277,9 @@ impl BitaxeBoard {
     /// # Arguments
     /// * `control` - Serial stream for sending board control commands
     /// * `data_path` - Path to the data serial port (e.g., "/dev/ttyACM1")
+    /// * `serial_number` - USB serial number, if the device reports one
+    /// * `board_id` - Stable board ID, see
+    ///   [`UsbDeviceInfo::board_id`](crate::transport::UsbDeviceInfo::board_id)
     ///
     /// # Returns
     /// A new BitaxeBoard instance ready for hardware operations
@@ -174,36 +291,92 @@ impl BitaxeBoard {
         control: tokio_serial::SerialStream,
         data_path: &str,
         serial_number: Option<String>,
+        board_id: String,
         state_tx: watch::Sender<BoardState>,
     ) -> Result<Self, BoardError> {
         // Create control channel and I2C controller
         let control_channel = ControlChannel::new(control);
         let i2c = BitaxeRawI2c::new(control_channel.clone());
 
-        // Create SerialStream for data channel at initial baud rate
-        let data_stream = SerialStream::new(data_path, 115200).map_err(|e| {
-            BoardError::InitializationFailed(format!("Failed to open data port: {}", e))
-        })?;
-        let (data_reader, data_writer, data_control) = data_stream.split();
-
-        // Wrap the data reader with tracing
-        let tracing_reader = TracingReader::new(data_reader, "Data");
+        let (chip_temp_tx, chip_temp_rx) = reading_channel();
+        let (power_state_tx, _) = watch::channel(ChipPowerState::Off);
 
-        Ok(BitaxeBoard {
+        let mut board = BitaxeBoard {
             control_channel,
             asic_nrst: None,
             i2c,
             fan_controller: None,
             regulator: None,
-            data_writer: Some(FramedWrite::new(data_writer, bm13xx::FrameCodec)),
-            data_reader: Some(FramedRead::new(tracing_reader, bm13xx::FrameCodec)),
-            data_control,
+            data_writer: None,
+            data_reader: None,
+            data_control: None,
+            data_path: data_path.to_string(),
+            data_capture: None,
             chip_infos: Vec::new(),
             thread_shutdown: None,
             stats_task_handle: None,
             serial_number,
+            board_id,
             state_tx: Some(state_tx),
-        })
+            stats: None,
+            chip_temp_tx,
+            chip_temp_rx,
+            power_state_tx,
+            profile_tx: watch::channel(Profile::default()).0,
+            frequency: None,
+            frequency_override: None,
+            registers: None,
+            firmware_task: None,
+        };
+        board.open_data_port()?;
+
+        Ok(board)
+    }
+
+    /// Open the data serial port at the chips' power-up baud rate.
+    fn open_data_port(&mut self) -> Result<(), BoardError> {
+        let mut data_stream =
+            SerialStream::new(&self.data_path, Self::INITIAL_BAUD_RATE).map_err(|e| {
+                BoardError::InitializationFailed(format!("Failed to open data port: {}", e))
+            })?;
+        if let Some(tap) = self.data_capture() {
+            data_stream = data_stream.with_capture(tap);
+        }
+        let (data_reader, data_writer, data_control) = data_stream.split();
+
+        // Wrap the data reader with tracing
+        let tracing_reader = TracingReader::new(data_reader, "Data");
+
+        self.data_writer = Some(FramedWrite::new(data_writer, bm13xx::FrameCodec::default()));
+        self.data_reader = Some(FramedRead::new(
+            tracing_reader,
+            bm13xx::FrameCodec::default(),
+        ));
+        self.data_control = Some(data_control);
+        Ok(())
+    }
+
+    /// Tap for the data link, opened on first use when capture is
+    /// configured.
+    fn data_capture(&mut self) -> Option<CaptureTap> {
+        if self.data_capture.is_none()
+            && let Some(ref dir) = config::board_config().capture_dir
+        {
+            let started = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            let path = dir.join(format!("bitaxe-{}-{started}.csv", self.board_id));
+            match CaptureTap::create(&path) {
+                Ok(tap) => {
+                    info!(board = %self.board_id, path = %path.display(), "Capturing data serial link");
+                    self.data_capture = Some(tap);
+                }
+                Err(e) => {
+                    warn!(board = %self.board_id, path = %path.display(), error = %e, "Failed to open serial capture");
+                }
+            }
+        }
+        self.data_capture.clone()
     }
 
     /// Performs a momentary reset of the mining chips via GPIO control.
@@ -323,14 +496,12 @@ impl BitaxeBoard {
                             debug!("Discovered chip {:?} ({:02x}{:02x}) at address {address}",
                                          chip_type, chip_id[0], chip_id[1]);
 
-                            let chip_info = ChipInfo {
-                                chip_id,
-                                core_count: core_count.into(),
-                                address,
-                                supports_version_rolling: true, // BM1370 supports this
-                            };
-
-                            self.chip_infos.push(chip_info);
+                            if chip_type.model().is_none() {
+                                warn!("No model data for chip {:?}, assuming no version rolling",
+                                      chip_type);
+                            }
+                            self.chip_infos
+                                .push(bm13xx::model::chip_info(chip_type, core_count, address));
                         }
                         Some(Ok(_)) => {
                             warn!("Unexpected response during chip discovery");
@@ -418,8 +589,8 @@ impl BitaxeBoard {
                 // Delay before setting voltage
                 tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
 
-                // Set initial output voltage, default for BM1370 from esp-miner
-                const DEFAULT_VOUT: f32 = 1.15;
+                // Set initial output voltage, nominal for the BM1370
+                const DEFAULT_VOUT: f32 = bm13xx::model::BM1370.nominal_voltage_v;
                 match tps546.set_vout(DEFAULT_VOUT).await {
                     Ok(()) => {
                         debug!("Core voltage set to {DEFAULT_VOUT}V");
@@ -684,6 +855,34 @@ impl BitaxeBoard {
         Ok(())
     }
 
+    /// Set the core voltage through the regulator.
+    async fn set_core_voltage(&mut self, volts: f32) -> Result<(), BoardError> {
+        let Some(ref regulator) = self.regulator else {
+            return Err(BoardError::HardwareControl(
+                "voltage regulator not initialized".into(),
+            ));
+        };
+        regulator.lock().await.set_vout(volts).await.map_err(|e| {
+            BoardError::HardwareControl(format!("Failed to set core voltage: {}", e))
+        })?;
+        debug!("Core voltage set to {volts}V");
+        Ok(())
+    }
+
+    /// Core frequency to run at under `profile`: the one set through the
+    /// API, else the profile's.
+    fn frequency_mhz(&self, profile: Profile) -> f32 {
+        self.frequency_override
+            .unwrap_or(ProfileSettings::for_profile(profile).frequency_mhz)
+    }
+
+    /// Whether a firmware update is still writing to the microcontroller.
+    fn updating_firmware(&self) -> bool {
+        self.firmware_task
+            .as_ref()
+            .is_some_and(|t| !t.is_finished())
+    }
+
     /// Number of discovered chips on this board.
     pub fn chip_count(&self) -> usize {
         self.chip_infos.len()
@@ -693,6 +892,7 @@ impl BitaxeBoard {
     fn spawn_stats_monitor(&mut self) {
         // Clone data needed for the monitoring task
         let i2c = self.i2c.clone();
+        let telemetry = BitaxeRawTelemetry::new(self.control_channel.clone());
 
         // Clone the regulator Arc for stats monitoring
         let regulator = self
@@ -702,139 +902,340 @@ impl BitaxeBoard {
 
         // Capture board info for logging
         let board_info = self.board_info();
-        let board_name = format!(
-            "bitaxe-{}",
-            board_info.serial_number.as_deref().unwrap_or("unknown")
-        );
+        let board_name = format!("bitaxe-{}", self.board_id);
         let board_model = board_info.model.clone();
         let board_serial = board_info.serial_number.clone();
 
-        // Take the state sender so this task owns publishing
+        let chip_temp_rx = self.chip_temp_rx.clone();
+        let power_state_rx = self.power_state_tx.subscribe();
+        let profile_rx = self.profile_tx.subscribe();
+        let fan_slew = config::board_config().thermal_slew();
+        // The thread reads the die once per thermal tick
+        let die_max_age = config::board_config().thermal().stale_after();
+
+        // The aggregator owns publishing; this task feeds it sensor readings
         let state_tx = self
             .state_tx
             .take()
             .expect("state_tx must be present when spawning stats monitor");
+        let identity = state_tx.borrow().clone();
+        let (stats, _) = stats::spawn(identity, state_tx);
+        self.stats = Some(stats.clone());
+
+        let handle = tokio::spawn(
+            async move {
+                const STATS_INTERVAL: Duration = Duration::from_secs(5);
+                let mut interval = tokio::time::interval(STATS_INTERVAL);
+                interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+                // Create fan controller for the stats task
+                let mut fan_ctrl = Emc2101::new(i2c);
 
-        let handle = tokio::spawn(async move {
-            const STATS_INTERVAL: Duration = Duration::from_secs(5);
-            let mut interval = tokio::time::interval(STATS_INTERVAL);
-            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                const LOG_INTERVAL: Duration = Duration::from_secs(30);
+                let mut last_log = tokio::time::Instant::now();
 
-            // Create fan controller for the stats task
-            let mut fan_ctrl = Emc2101::new(i2c);
+                // Firmware telemetry pages, until the firmware says otherwise
+                let mut psu_supported = true;
+                let mut tach_supported = true;
+                let mut vdd_supported = true;
+                let mut psu_power_good = true;
 
-            const LOG_INTERVAL: Duration = Duration::from_secs(30);
-            let mut last_log = tokio::time::Instant::now();
+                // Core power by chip power state, to show what idling saves
+                let mut idle_power = IdlePowerMeter::default();
 
-            // Discard first tick (fires immediately, ADC readings may not be settled)
-            interval.tick().await;
+                // Whether the fan is held at full speed for want of a die
+                // temperature
+                let mut fan_forced = false;
 
-            loop {
+                // Discard first tick (fires immediately, ADC readings may not be settled)
                 interval.tick().await;
 
-                // -- Read sensor values --
-
-                let asic_temp = fan_ctrl.get_external_temperature().await.ok();
-                let fan_percent = fan_ctrl.get_fan_speed().await.ok().map(u8::from);
-                let fan_rpm = fan_ctrl.get_rpm().await.ok();
-
-                let (vin_mv, vout_mv, iout_ma, power_mw, vr_temp) = {
-                    let mut reg = regulator.lock().await;
-                    (
-                        reg.get_vin().await.ok(),
-                        reg.get_vout().await.ok(),
-                        reg.get_iout().await.ok(),
-                        reg.get_power().await.ok(),
-                        reg.get_temperature().await.ok(),
-                    )
-                };
-
-                if let Some(mv) = vout_mv {
-                    let volts = mv as f32 / 1000.0;
-                    if volts < 1.0 {
-                        warn!("Core voltage low: {:.3}V", volts);
+                loop {
+                    interval.tick().await;
+
+                    // -- Read sensor values --
+
+                    let asic_temp = fan_ctrl.get_external_temperature().await.ok();
+                    let die_temp = chip_temp_rx.fresh(die_max_age);
+                    let fan_percent = fan_ctrl.get_fan_speed().await.ok().map(u8::from);
+
+                    // The thread reads the die every few seconds while the
+                    // chip runs; once it has, silence means it stopped, and
+                    // the chip may be heating unseen
+                    let hashing = *power_state_rx.borrow() == ChipPowerState::Hashing;
+                    let die_unseen = hashing
+                        && chip_temp_rx
+                            .latest()
+                            .is_some_and(|reading| reading.age() > die_max_age);
+                    if die_unseen != fan_forced {
+                        fan_forced = die_unseen;
+                        if fan_forced {
+                            warn!(board = %board_name, "Die temperature readings stopped, running fan at full speed");
+                        } else {
+                            info!(board = %board_name, "Die temperature readings resumed");
+                            // Under a slew limit the profile's speed is
+                            // approached from full below
+                            let percent = ProfileSettings::for_profile(*profile_rx.borrow()).fan_percent;
+                            if fan_slew.is_none()
+                                && let Err(e) = fan_ctrl.set_fan_speed(Percent::new_clamped(percent)).await
+                            {
+                                warn!("Failed to set fan speed: {}", e);
+                            }
+                        }
+                    }
+                    if fan_forced
+                        && fan_percent != Some(100)
+                        && let Err(e) = fan_ctrl.set_fan_speed(Percent::new_clamped(100)).await
+                    {
+                        warn!("Failed to set fan speed: {}", e);
+                    }
+
+                    // Under a slew limit the profile's fan speed is
+                    // approached from here rather than set at once
+                    if let (Some(slew), Some(percent), false) = (fan_slew, fan_percent, fan_forced) {
+                        let target = ProfileSettings::for_profile(*profile_rx.borrow()).fan_percent;
+                        let next = slew.fan_step(percent, target, STATS_INTERVAL);
+                        if next != percent
+                            && let Err(e) = fan_ctrl.set_fan_speed(Percent::new_clamped(next)).await
+                        {
+                            warn!("Failed to set fan speed: {}", e);
+                        }
+                    }
+                    let fan_rpm = match fan_ctrl.get_rpm().await.ok() {
+                        Some(rpm) => Some(rpm),
+                        None => read_if_supported(
+                            &mut tach_supported,
+                            "fan tach",
+                            telemetry.fan_tach(0),
+                        )
+                        .await
+                        .map(|tach| u32::from(tach.rpm)),
+                    };
+
+                    let psu =
+                        read_if_supported(&mut psu_supported, "PSU status", telemetry.psu_status())
+                            .await;
+                    let mcu_vdd_mv =
+                        read_if_supported(&mut vdd_supported, "ADC VDD", telemetry.read_vdd())
+                            .await;
+
+                    if let Some(status) = psu
+                        && status.power_good != psu_power_good
+                    {
+                        psu_power_good = status.power_good;
+                        if psu_power_good {
+                            info!(board = %board_name, "Input supply power good again");
+                        } else {
+                            warn!(
+                                board = %board_name,
+                                faults = format!("{:#04x}", status.faults),
+                                input_v = status.input_mv as f32 / 1000.0,
+                                "Input supply lost power-good"
+                            );
+                        }
+                    }
+
+                    let (vin_mv, vout_mv, iout_ma, power_mw, vr_temp) = {
+                        let mut reg = regulator.lock().await;
+                        (
+                            reg.get_vin().await.ok(),
+                            reg.get_vout().await.ok(),
+                            reg.get_iout().await.ok(),
+                            reg.get_power().await.ok(),
+                            reg.get_temperature().await.ok(),
+                        )
+                    };
+
+                    if let Some(mv) = vout_mv {
+                        let volts = mv as f32 / 1000.0;
+                        if volts < 1.0 {
+                            warn!("Core voltage low: {:.3}V", volts);
+                        }
                     }
-                }
 
-                // Check power status -- critical faults will return error
-                {
-                    let mut reg = regulator.lock().await;
-                    if let Err(e) = reg.check_status().await {
-                        error!("CRITICAL: Power controller fault detected: {}", e);
+                    // Check power status -- critical faults will return error
+                    {
+                        let mut reg = regulator.lock().await;
+                        if let Err(e) = reg.check_status().await {
+                            error!("CRITICAL: Power controller fault detected: {}", e);
 
-                        warn!("Attempting to clear power controller faults...");
-                        if let Err(clear_err) = reg.clear_faults().await {
-                            error!("Failed to clear faults: {}", clear_err);
+                            warn!("Attempting to clear power controller faults...");
+                            if let Err(clear_err) = reg.clear_faults().await {
+                                error!("Failed to clear faults: {}", clear_err);
+                            }
+
+                            continue;
                         }
+                    }
 
-                        continue;
+                    if let Some(mw) = power_mw {
+                        idle_power.record(*power_state_rx.borrow(), mw as f32 / 1000.0);
                     }
-                }
 
-                // -- Publish BoardState --
-
-                let _ = state_tx.send(BoardState {
-                    name: board_name.clone(),
-                    model: board_model.clone(),
-                    serial: board_serial.clone(),
-                    fans: vec![Fan {
-                        name: "fan".into(),
-                        rpm: fan_rpm,
-                        percent: fan_percent,
-                        target_percent: None,
-                    }],
-                    temperatures: vec![
-                        TemperatureSensor {
-                            name: "asic".into(),
-                            temperature_c: asic_temp,
-                        },
-                        TemperatureSensor {
-                            name: "vr".into(),
-                            temperature_c: vr_temp.map(|t| t as f32),
-                        },
-                    ],
-                    powers: vec![
-                        PowerMeasurement {
-                            name: "input".into(),
-                            voltage_v: vin_mv.map(|mv| mv as f32 / 1000.0),
-                            current_a: None,
-                            power_w: None,
-                        },
-                        PowerMeasurement {
-                            name: "core".into(),
-                            voltage_v: vout_mv.map(|mv| mv as f32 / 1000.0),
-                            current_a: iout_ma.map(|ma| ma as f32 / 1000.0),
-                            power_w: power_mw.map(|mw| mw as f32 / 1000.0),
-                        },
-                    ],
-                    threads: Vec::new(),
-                });
-
-                // -- Log summary (throttled) --
-
-                if last_log.elapsed() >= LOG_INTERVAL {
-                    last_log = tokio::time::Instant::now();
-                    info!(
-                        board = %board_model,
-                        serial = ?board_serial,
-                        asic_temp_c = ?asic_temp,
-                        fan_percent = ?fan_percent,
-                        fan_rpm = ?fan_rpm,
-                        vr_temp_c = ?vr_temp,
-                        power_w = ?power_mw.map(|mw| mw as f32 / 1000.0),
-                        current_a = ?iout_ma.map(|ma| ma as f32 / 1000.0),
-                        vin_v = ?vin_mv.map(|mv| mv as f32 / 1000.0),
-                        vout_v = ?vout_mv.map(|mv| mv as f32 / 1000.0),
-                        "Board status."
-                    );
+                    // -- Publish sensor readings --
+
+                    stats.update_sensors(SensorReadings {
+                        fans: vec![Fan {
+                            name: "fan".into(),
+                            rpm: fan_rpm,
+                            percent: fan_percent,
+                            target_percent: None,
+                        }],
+                        temperatures: vec![
+                            TemperatureSensor {
+                                name: "asic".into(),
+                                temperature_c: asic_temp,
+                            },
+                            TemperatureSensor {
+                                name: "asic-die".into(),
+                                temperature_c: die_temp,
+                            },
+                            TemperatureSensor {
+                                name: "vr".into(),
+                                temperature_c: vr_temp.map(|t| t as f32),
+                            },
+                        ],
+                        powers: vec![
+                            PowerMeasurement {
+                                name: "input".into(),
+                                voltage_v: vin_mv
+                                    .or(psu.map(|p| u32::from(p.input_mv)))
+                                    .map(|mv| mv as f32 / 1000.0),
+                                current_a: psu.map(|p| p.input_ma as f32 / 1000.0),
+                                power_w: psu.map(|p| p.input_mw() as f32 / 1000.0),
+                            },
+                            PowerMeasurement {
+                                name: "core".into(),
+                                voltage_v: vout_mv.map(|mv| mv as f32 / 1000.0),
+                                current_a: iout_ma.map(|ma| ma as f32 / 1000.0),
+                                power_w: power_mw.map(|mw| mw as f32 / 1000.0),
+                            },
+                            PowerMeasurement {
+                                name: "mcu".into(),
+                                voltage_v: mcu_vdd_mv.map(|mv| mv as f32 / 1000.0),
+                                current_a: None,
+                                power_w: None,
+                            },
+                        ],
+                        idle_power: idle_power.summary(),
+                    });
+
+                    // -- Log summary (throttled) --
+
+                    if last_log.elapsed() >= LOG_INTERVAL {
+                        last_log = tokio::time::Instant::now();
+
+                        let profile = *profile_rx.borrow();
+                        let budget_w = ProfileSettings::for_profile(profile).power_budget_w;
+                        if let Some(input_w) = psu.map(|p| p.input_mw() as f32 / 1000.0)
+                            && input_w > budget_w
+                        {
+                            warn!(
+                                board = %board_name,
+                                %profile,
+                                input_w,
+                                budget_w,
+                                "Input power over profile budget"
+                            );
+                        }
+
+                        info!(
+                            board = %board_model,
+                            serial = ?board_serial,
+                            asic_temp_c = ?asic_temp,
+                            asic_die_temp_c = ?die_temp,
+                            fan_percent = ?fan_percent,
+                            fan_rpm = ?fan_rpm,
+                            vr_temp_c = ?vr_temp,
+                            power_w = ?power_mw.map(|mw| mw as f32 / 1000.0),
+                            current_a = ?iout_ma.map(|ma| ma as f32 / 1000.0),
+                            vin_v = ?vin_mv.map(|mv| mv as f32 / 1000.0),
+                            vout_v = ?vout_mv.map(|mv| mv as f32 / 1000.0),
+                            "Board status."
+                        );
+                    }
                 }
             }
-        });
+            .in_current_span(),
+        );
 
         self.stats_task_handle = Some(handle);
     }
 }
 
+/// Flash `image` to the microcontroller, publishing progress as it goes.
+async fn flash_firmware(
+    firmware: BitaxeRawFirmware,
+    image: Vec<u8>,
+    stats: Option<BoardStatsHandle>,
+    board_name: String,
+) {
+    let total_bytes = image.len() as u32;
+    let mut last = FirmwareUpdateState {
+        phase: FirmwareUpdatePhase::Erasing,
+        written_bytes: 0,
+        total_bytes,
+        error: None,
+    };
+    let publish = |state: &FirmwareUpdateState| {
+        if let Some(ref stats) = stats {
+            stats.update_firmware(Some(state.clone()));
+        }
+    };
+
+    let result = firmware
+        .flash(&image, |progress| {
+            (last.phase, last.written_bytes) = match progress {
+                FlashProgress::Erasing => (FirmwareUpdatePhase::Erasing, 0),
+                FlashProgress::Writing { written, .. } => (FirmwareUpdatePhase::Writing, written),
+                FlashProgress::Verifying => (FirmwareUpdatePhase::Verifying, total_bytes),
+                FlashProgress::Rebooting => (FirmwareUpdatePhase::Rebooting, total_bytes),
+            };
+            publish(&last);
+        })
+        .await;
+
+    match result {
+        Ok(()) => info!(board = %board_name, "Firmware written, rebooting into it"),
+        Err(e) => {
+            error!(board = %board_name, error = %e, "Firmware update failed");
+            last.phase = FirmwareUpdatePhase::Failed;
+            last.error = Some(e.to_string());
+            publish(&last);
+        }
+    }
+}
+
+/// Poll an optional firmware reading.
+///
+/// Once the firmware reports the reading unsupported, stops polling it
+/// for the rest of the session. Other failures are transient and just
+/// yield no value this time.
+async fn read_if_supported<T>(
+    supported: &mut bool,
+    what: &str,
+    read: impl Future<Output = hw_trait::Result<T>>,
+) -> Option<T> {
+    if !*supported {
+        return None;
+    }
+    match read.await {
+        Ok(value) => Some(value),
+        Err(HwError::NotSupported(_)) => {
+            debug!(
+                reading = what,
+                "Not supported by firmware, no longer polling"
+            );
+            *supported = false;
+            None
+        }
+        Err(e) => {
+            trace!(reading = what, error = %e, "Telemetry read failed");
+            None
+        }
+    }
+}
+
 #[async_trait]
 impl Board for BitaxeBoard {
     fn board_info(&self) -> BoardInfo {
@@ -875,15 +1276,34 @@ impl Board for BitaxeBoard {
             }
         }
 
-        // Cancel the statistics monitoring task
+        // Cancel the statistics monitoring task; dropping the last stats
+        // handle stops the aggregator after a final publish
         if let Some(handle) = self.stats_task_handle.take() {
             handle.abort();
         }
+        self.stats = None;
+
+        // An update cut short before its reboot leaves the old firmware
+        if let Some(task) = self.firmware_task.take() {
+            task.abort();
+        }
 
         Ok(())
     }
 
     async fn create_hash_threads(&mut self) -> Result<Vec<Box<dyn HashThread>>, BoardError> {
+        // The microcontroller may reboot under the chips at any moment
+        if self.updating_firmware() {
+            return Err(BoardError::HardwareControl(
+                "firmware update in progress".into(),
+            ));
+        }
+
+        // A previous thread took the data port with it; open it afresh
+        if self.data_reader.is_none() && self.thread_shutdown.is_none() {
+            self.open_data_port()?;
+        }
+
         // Create removal signal channel (starts as Running)
         let (removal_tx, removal_rx) = watch::channel(ThreadRemovalSignal::Running);
 
@@ -914,17 +1334,38 @@ impl Board for BitaxeBoard {
             ))?;
         let asic_enable = BitaxeAsicEnable { nrst_pin };
 
+        // Thread owns baud rate changes since it drives chip initialization
+        let baud_control = self.data_control.take().map(|control| {
+            Box::new(BitaxeBaudControl {
+                control: Arc::new(control),
+            }) as _
+        });
+
+        // Build thread name from board model and serial
+        let thread_name = match &self.serial_number {
+            Some(serial) => format!("Bitaxe-Gamma-{}", &serial[..8.min(serial.len())]),
+            None => format!("Bitaxe-Gamma-{}", self.board_id),
+        };
+
+        let (thread_status, job_dispatch) = self
+            .stats
+            .as_ref()
+            .map(|stats| stats.register_thread(&thread_name))
+            .unzip();
+
         // Bundle peripherals for thread
         let peripherals = BoardPeripherals {
             asic_enable: Some(Box::new(asic_enable)),
             voltage_regulator: None, // Not used by hash thread yet
+            baud_control,
+            chip_temperature: Some(self.chip_temp_tx.clone()),
+            power_state: Some(self.power_state_tx.clone()),
+            thread_status,
+            job_dispatch,
         };
 
-        // Build thread name from board model and serial
-        let thread_name = match &self.serial_number {
-            Some(serial) => format!("Bitaxe-Gamma-{}", &serial[..8.min(serial.len())]),
-            None => "Bitaxe-Gamma".to_string(),
-        };
+        // The reader's codec counts the frames lost on the link
+        let link_stats = data_reader.decoder().clone();
 
         // Create BM13xxThread with streams and peripherals
         let thread = BM13xxThread::new(
@@ -933,12 +1374,172 @@ impl Board for BitaxeBoard {
             data_writer,
             peripherals,
             removal_rx,
-        );
+        )
+        .with_device_id(self.board_id.clone())
+        .with_chip_count(self.chip_count())
+        .with_derating(config::board_config().derating_curve())
+        .with_warmup(config::board_config().warmup())
+        .with_thermal_slew(config::board_config().thermal_slew())
+        .with_thermal_config(config::board_config().thermal())
+        .with_nonce_timeout(config::board_config().nonce_timeout())
+        .with_ntime_roll_interval(config::board_config().ntime_roll_interval())
+        .with_link_stats(link_stats)
+        .with_target_frequency(self.frequency_mhz(*self.profile_tx.borrow()));
+        self.frequency = Some(thread.frequency_control());
+        self.registers = Some(thread.register_access());
 
         debug!("Created BM13xx hash thread from BitaxeBoard");
 
         Ok(vec![Box::new(thread)])
     }
+
+    async fn disable_hash_threads(&mut self) -> Result<(), BoardError> {
+        /// Long enough for the thread to forward in-flight shares.
+        const THREAD_STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+        let Some(tx) = self.thread_shutdown.take() else {
+            return Ok(());
+        };
+
+        if tx.send(ThreadRemovalSignal::UserRequested).is_err() {
+            // Thread already gone
+            return Ok(());
+        }
+
+        // The thread drops its receiver on exit, releasing the data port
+        if time::timeout(THREAD_STOP_TIMEOUT, tx.closed())
+            .await
+            .is_err()
+        {
+            return Err(BoardError::HardwareControl(
+                "hash thread did not stop in time".into(),
+            ));
+        }
+
+        // The thread held the chip in reset on its way out
+        self.frequency = None;
+        self.registers = None;
+        debug!("Hash threads disabled");
+
+        Ok(())
+    }
+
+    async fn apply_profile(&mut self, profile: Profile) -> Result<(), BoardError> {
+        let old = ProfileSettings::for_profile(*self.profile_tx.borrow());
+        let new = ProfileSettings::for_profile(profile);
+
+        // Raise voltage before frequency, and lower it after
+        if new.core_voltage_v > old.core_voltage_v {
+            self.set_core_voltage(new.core_voltage_v).await?;
+        }
+        if let Some(ref frequency) = self.frequency {
+            frequency.set_target(self.frequency_mhz(profile));
+        }
+        if new.core_voltage_v < old.core_voltage_v {
+            self.set_core_voltage(new.core_voltage_v).await?;
+        }
+
+        // Under a slew limit the stats task moves the fan over
+        if config::board_config().thermal_slew().is_none()
+            && let Some(ref mut fan) = self.fan_controller
+            && let Err(e) = fan
+                .set_fan_speed(Percent::new_clamped(new.fan_percent))
+                .await
+        {
+            warn!("Failed to set fan speed: {}", e);
+        }
+
+        self.profile_tx.send_replace(profile);
+        info!(
+            board = %self.board_id,
+            %profile,
+            frequency_mhz = self.frequency_mhz(profile),
+            core_voltage_v = new.core_voltage_v,
+            fan_percent = new.fan_percent,
+            "Profile applied"
+        );
+        Ok(())
+    }
+
+    async fn set_frequency(&mut self, mhz: Option<f32>) -> Result<(), BoardError> {
+        if let Some(mhz) = mhz
+            && !FREQUENCY_RANGE_MHZ.contains(&mhz)
+        {
+            return Err(BoardError::HardwareControl(format!(
+                "frequency {mhz} MHz outside {}--{} MHz",
+                FREQUENCY_RANGE_MHZ.start(),
+                FREQUENCY_RANGE_MHZ.end()
+            )));
+        }
+
+        self.frequency_override = mhz;
+        let target = self.frequency_mhz(*self.profile_tx.borrow());
+        if let Some(ref frequency) = self.frequency {
+            frequency.set_target(target);
+        }
+        info!(board = %self.board_id, frequency_mhz = target, "Core frequency set");
+        Ok(())
+    }
+
+    async fn update_firmware(&mut self, image: Vec<u8>) -> Result<(), BoardError> {
+        if self.thread_shutdown.is_some() {
+            return Err(BoardError::HardwareControl(
+                "hash threads must be disabled first".into(),
+            ));
+        }
+        if self.updating_firmware() {
+            return Err(BoardError::HardwareControl(
+                "firmware update already in progress".into(),
+            ));
+        }
+
+        // Refuse what can be refused before anything is erased
+        let firmware = BitaxeRawFirmware::new(self.control_channel.clone());
+        let info = firmware
+            .info()
+            .await
+            .map_err(|e| BoardError::HardwareControl(e.to_string()))?;
+        info.check_image(&image)
+            .map_err(|e| BoardError::HardwareControl(e.to_string()))?;
+        let telemetry = BitaxeRawTelemetry::new(self.control_channel.clone());
+        if let Ok(status) = telemetry.psu_status().await
+            && !status.power_good
+        {
+            return Err(BoardError::HardwareControl(
+                "input supply not power-good".into(),
+            ));
+        }
+
+        info!(
+            board = %self.board_id,
+            running_version = %info.version,
+            image_bytes = image.len(),
+            "Firmware update started"
+        );
+        if let Some(ref stats) = self.stats {
+            stats.update_firmware(None);
+        }
+        self.firmware_task = Some(tokio::spawn(
+            flash_firmware(firmware, image, self.stats.clone(), self.board_id.clone())
+                .in_current_span(),
+        ));
+        Ok(())
+    }
+
+    async fn dump_registers(
+        &mut self,
+        chip_address: u8,
+        diff: bool,
+    ) -> Result<ChipRegisterDump, BoardError> {
+        let registers = self
+            .registers
+            .as_ref()
+            .ok_or_else(|| BoardError::HardwareControl("no hash thread running".into()))?;
+        registers
+            .dump(chip_address, diff)
+            .await
+            .map_err(|e| BoardError::HardwareControl(e.to_string()))
+    }
 }
 
 // Factory function to create a Bitaxe board from USB device info
@@ -960,6 +1561,7 @@ async fn create_from_usb(
 
     debug!(
         serial = ?device.serial_number,
+        id = %device.board_id(),
         control = %serial_ports[0],
         data = %serial_ports[1],
         "Opening Bitaxe Gamma serial ports"
@@ -969,9 +1571,10 @@ async fn create_from_usb(
     let control_port = tokio_serial::new(&serial_ports[0], 115200).open_native_async()?;
 
     // Create watch channel for board state, seeded with identity
+    let board_id = device.board_id();
     let serial = device.serial_number.clone();
     let initial_state = BoardState {
-        name: format!("bitaxe-{}", serial.as_deref().unwrap_or("unknown")),
+        name: format!("bitaxe-{board_id}"),
         model: "Bitaxe Gamma".into(),
         serial,
         ..Default::default()
@@ -983,6 +1586,7 @@ async fn create_from_usb(
         control_port,
         &serial_ports[1],
         device.serial_number.clone(),
+        board_id,
         state_tx,
     )
     .map_err(|e| crate::error::Error::Hardware(format!("Failed to create board: {}", e)))?;
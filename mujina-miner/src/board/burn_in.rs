@@ -0,0 +1,533 @@
+//! Hashboard burn-in.
+//!
+//! A burn-in holds a board at each of a series of core frequencies, lowest
+//! first, for a set time, watching the board's published state: hottest
+//! temperature, power, hashrate, and the share of reported nonces that are
+//! hardware errors. A step fails as soon as it runs too hot or draws too
+//! much power, or at its end if the chips reported nothing or too many
+//! errors, and a failed step ends the run. The report recommends the
+//! highest frequency that passed along with every step below it.
+//!
+//! Reports are written to a directory as `<serial>.json`, so a board's
+//! last result outlives restarts and follows it between hosts; see
+//! [`load_report`]. The backplane drives burn-ins; this module only
+//! decides what happens next.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::time::Instant;
+
+use crate::api_client::types::{
+    BoardState, BurnInProgress, BurnInReport, BurnInRequest, BurnInStepResult,
+};
+
+/// How often a running burn-in samples the board's state.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Frequencies stepped through unless the request says otherwise, in MHz.
+const DEFAULT_FREQUENCIES_MHZ: [f32; 4] = [400.0, 450.0, 500.0, 550.0];
+
+/// How long each step runs unless the request says otherwise.
+const DEFAULT_STEP_DURATION: Duration = Duration::from_secs(300);
+
+/// Hottest reading a passing step may reach unless the request says
+/// otherwise.
+const DEFAULT_MAX_TEMP_C: f32 = 75.0;
+
+/// Largest share of hardware errors a passing step may have unless the
+/// request says otherwise.
+const DEFAULT_MAX_ERROR_RATE: f64 = 0.01;
+
+/// What a burn-in steps through and what it holds each step to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BurnInPlan {
+    /// Core frequencies, lowest first.
+    pub frequencies_mhz: Vec<f32>,
+    pub step_duration: Duration,
+    pub max_temp_c: f32,
+    pub max_error_rate: f64,
+    pub max_power_w: Option<f32>,
+}
+
+impl Default for BurnInPlan {
+    fn default() -> Self {
+        Self {
+            frequencies_mhz: DEFAULT_FREQUENCIES_MHZ.to_vec(),
+            step_duration: DEFAULT_STEP_DURATION,
+            max_temp_c: DEFAULT_MAX_TEMP_C,
+            max_error_rate: DEFAULT_MAX_ERROR_RATE,
+            max_power_w: None,
+        }
+    }
+}
+
+impl TryFrom<BurnInRequest> for BurnInPlan {
+    type Error = String;
+
+    /// Fill in defaults and check the request. Frequencies may be given
+    /// in any order.
+    fn try_from(request: BurnInRequest) -> Result<Self, Self::Error> {
+        let defaults = Self::default();
+        let mut frequencies_mhz = request.frequencies_mhz.unwrap_or(defaults.frequencies_mhz);
+        if frequencies_mhz.is_empty() {
+            return Err("no frequencies to step through".into());
+        }
+        if frequencies_mhz.iter().any(|f| !f.is_finite() || *f <= 0.0) {
+            return Err("frequencies must be positive".into());
+        }
+        frequencies_mhz.sort_by(f32::total_cmp);
+        frequencies_mhz.dedup();
+
+        let step_duration = request
+            .step_secs
+            .map_or(defaults.step_duration, Duration::from_secs);
+        if step_duration < SAMPLE_INTERVAL {
+            return Err(format!(
+                "steps must last at least {} s",
+                SAMPLE_INTERVAL.as_secs()
+            ));
+        }
+        let max_error_rate = request.max_error_rate.unwrap_or(defaults.max_error_rate);
+        if !(0.0..=1.0).contains(&max_error_rate) {
+            return Err("error rate limit must be between 0 and 1".into());
+        }
+
+        Ok(Self {
+            frequencies_mhz,
+            step_duration,
+            max_temp_c: request.max_temp_c.unwrap_or(defaults.max_temp_c),
+            max_error_rate,
+            max_power_w: request.max_power_w,
+        })
+    }
+}
+
+/// Readings taken from a board's published state.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Sample {
+    /// Hottest temperature sensor
+    pub temperature_c: Option<f32>,
+    /// Input power if measured, else core power
+    pub power_w: Option<f32>,
+    /// Hashrate of all threads, in hashes per second
+    pub hashrate: u64,
+    /// Nonces reported by all threads so far
+    pub nonces: u64,
+    /// Hardware errors among them
+    pub hardware_errors: u64,
+}
+
+impl Sample {
+    pub fn from_state(state: &BoardState) -> Self {
+        let power = |name: &str| {
+            state
+                .powers
+                .iter()
+                .find(|p| p.name == name)
+                .and_then(|p| p.power_w)
+        };
+        Self {
+            temperature_c: state
+                .temperatures
+                .iter()
+                .filter_map(|t| t.temperature_c)
+                .reduce(f32::max),
+            power_w: power("input").or_else(|| power("core")),
+            hashrate: state.threads.iter().map(|t| t.hashrate).sum(),
+            nonces: state.threads.iter().map(|t| t.nonces).sum(),
+            hardware_errors: state.threads.iter().map(|t| t.hardware_errors).sum(),
+        }
+    }
+}
+
+/// What the backplane should do after a sample.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BurnInAction {
+    /// Keep the board where it is.
+    Hold,
+    /// Move the board to the next step's frequency.
+    SetFrequency(f32),
+    /// The run is over; hand the board back to its profile.
+    Finish(BurnInReport),
+}
+
+/// Measurements accumulated over the current step.
+#[derive(Debug, Default)]
+struct StepTally {
+    /// Counters at the start of the step, once sampled
+    baseline: Option<Sample>,
+    last: Sample,
+    max_temp_c: Option<f32>,
+    power_sum_w: f32,
+    power_samples: u32,
+    hashrate_sum: u64,
+    samples: u64,
+}
+
+impl StepTally {
+    fn record(&mut self, sample: Sample) {
+        self.baseline.get_or_insert(sample);
+        self.last = sample;
+        if let Some(t) = sample.temperature_c {
+            self.max_temp_c = Some(self.max_temp_c.map_or(t, |max| max.max(t)));
+        }
+        if let Some(p) = sample.power_w {
+            self.power_sum_w += p;
+            self.power_samples += 1;
+        }
+        self.hashrate_sum += sample.hashrate;
+        self.samples += 1;
+    }
+
+    /// Nonces and hardware errors since the step started.
+    fn counts(&self) -> (u64, u64) {
+        let baseline = self.baseline.unwrap_or_default();
+        (
+            self.last.nonces.saturating_sub(baseline.nonces),
+            self.last
+                .hardware_errors
+                .saturating_sub(baseline.hardware_errors),
+        )
+    }
+}
+
+/// A burn-in under way on one board.
+#[derive(Debug)]
+pub struct BurnIn {
+    board: String,
+    serial: Option<String>,
+    plan: BurnInPlan,
+    started_at: SystemTime,
+    step: usize,
+    step_started: Instant,
+    tally: StepTally,
+    results: Vec<BurnInStepResult>,
+}
+
+impl BurnIn {
+    /// Start a burn-in, returning it with the first step's frequency.
+    pub fn start(
+        board: String,
+        serial: Option<String>,
+        plan: BurnInPlan,
+        now: Instant,
+    ) -> (Self, f32) {
+        let first_mhz = plan.frequencies_mhz[0];
+        let burn_in = Self {
+            board,
+            serial,
+            plan,
+            started_at: SystemTime::now(),
+            step: 0,
+            step_started: now,
+            tally: StepTally::default(),
+            results: Vec::new(),
+        };
+        (burn_in, first_mhz)
+    }
+
+    /// Account for a sample of the board's state.
+    pub fn record(&mut self, sample: Sample, now: Instant) -> BurnInAction {
+        self.tally.record(sample);
+
+        if let Some(failure) = self.limit_exceeded(sample) {
+            return BurnInAction::Finish(self.end_step(now, Some(failure)));
+        }
+        if now.duration_since(self.step_started) < self.plan.step_duration {
+            return BurnInAction::Hold;
+        }
+
+        let failure = self.step_failure();
+        let failed = failure.is_some();
+        let report = self.end_step(now, failure);
+        if failed || self.step == self.plan.frequencies_mhz.len() {
+            return BurnInAction::Finish(report);
+        }
+        BurnInAction::SetFrequency(self.plan.frequencies_mhz[self.step])
+    }
+
+    /// End the run early, failing the current step with `reason`.
+    pub fn abort(&mut self, now: Instant, reason: String) -> BurnInReport {
+        self.end_step(now, Some(reason))
+    }
+
+    pub fn progress(&self, now: Instant) -> BurnInProgress {
+        let elapsed = now.duration_since(self.step_started);
+        BurnInProgress {
+            step: self.step + 1,
+            steps: self.plan.frequencies_mhz.len(),
+            frequency_mhz: self.plan.frequencies_mhz[self.step],
+            step_remaining_secs: self.plan.step_duration.saturating_sub(elapsed).as_secs(),
+        }
+    }
+
+    /// A limit the step may not exceed even briefly.
+    fn limit_exceeded(&self, sample: Sample) -> Option<String> {
+        if let Some(t) = sample.temperature_c
+            && t > self.plan.max_temp_c
+        {
+            return Some(format!(
+                "reached {t:.1} °C, over {:.1} °C",
+                self.plan.max_temp_c
+            ));
+        }
+        if let (Some(p), Some(max)) = (sample.power_w, self.plan.max_power_w)
+            && p > max
+        {
+            return Some(format!("drew {p:.1} W, over {max:.1} W"));
+        }
+        None
+    }
+
+    /// Why a completed step failed, if it did.
+    fn step_failure(&self) -> Option<String> {
+        let (nonces, errors) = self.tally.counts();
+        if nonces == 0 {
+            return Some("no nonces reported".into());
+        }
+        let rate = errors as f64 / nonces as f64;
+        (rate > self.plan.max_error_rate).then(|| {
+            format!(
+                "{:.2}% hardware errors, over {:.2}%",
+                rate * 100.0,
+                self.plan.max_error_rate * 100.0
+            )
+        })
+    }
+
+    /// Record the current step's result and move on to the next, returning
+    /// the report as it stands.
+    fn end_step(&mut self, now: Instant, failure: Option<String>) -> BurnInReport {
+        let tally = std::mem::take(&mut self.tally);
+        let (nonces, hardware_errors) = tally.counts();
+        self.results.push(BurnInStepResult {
+            frequency_mhz: self.plan.frequencies_mhz[self.step],
+            duration_secs: now.duration_since(self.step_started).as_secs(),
+            max_temp_c: tally.max_temp_c,
+            mean_power_w: (tally.power_samples > 0)
+                .then(|| tally.power_sum_w / tally.power_samples as f32),
+            mean_hashrate: tally.hashrate_sum / tally.samples.max(1),
+            nonces,
+            hardware_errors,
+            error_rate: if nonces == 0 {
+                0.0
+            } else {
+                hardware_errors as f64 / nonces as f64
+            },
+            failure,
+        });
+        self.step += 1;
+        self.step_started = now;
+        self.report()
+    }
+
+    fn report(&self) -> BurnInReport {
+        // Steps run in ascending order and stop at the first failure
+        let safe_frequency_mhz = self
+            .results
+            .iter()
+            .take_while(|r| r.failure.is_none())
+            .last()
+            .map(|r| r.frequency_mhz);
+        BurnInReport {
+            board: self.board.clone(),
+            serial: self.serial.clone(),
+            started_at: self
+                .started_at
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            passed: self.results.len() == self.plan.frequencies_mhz.len()
+                && self.results.iter().all(|r| r.failure.is_none()),
+            safe_frequency_mhz,
+            max_temp_c: self.plan.max_temp_c,
+            max_error_rate: self.plan.max_error_rate,
+            max_power_w: self.plan.max_power_w,
+            steps: self.results.clone(),
+        }
+    }
+}
+
+/// File a board's report is kept in: its serial, or its name without one.
+fn report_path(dir: &Path, key: &str) -> PathBuf {
+    let file: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    dir.join(format!("{file}.json"))
+}
+
+/// Write a report to `dir`, replacing the board's previous one.
+pub fn save_report(dir: &Path, report: &BurnInReport) -> io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = report_path(dir, report.serial.as_deref().unwrap_or(&report.board));
+    std::fs::write(&path, serde_json::to_vec_pretty(report)?)?;
+    Ok(path)
+}
+
+/// The last report written for the board with serial (or name) `key`.
+pub fn load_report(dir: &Path, key: &str) -> Option<BurnInReport> {
+    let data = std::fs::read(report_path(dir, key)).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan() -> BurnInPlan {
+        BurnInPlan {
+            frequencies_mhz: vec![400.0, 450.0, 500.0],
+            step_duration: Duration::from_secs(60),
+            ..Default::default()
+        }
+    }
+
+    fn sample(temperature_c: f32, nonces: u64, hardware_errors: u64) -> Sample {
+        Sample {
+            temperature_c: Some(temperature_c),
+            power_w: Some(15.0),
+            hashrate: 1_000_000_000_000,
+            nonces,
+            hardware_errors,
+        }
+    }
+
+    /// Feed a step's first and last samples, a minute apart, with 1000
+    /// nonces between them.
+    fn run_step(
+        burn_in: &mut BurnIn,
+        now: &mut Instant,
+        temperature_c: f32,
+        nonces: &mut u64,
+        errors_per_step: u64,
+    ) -> BurnInAction {
+        burn_in.record(sample(temperature_c, *nonces, 0), *now);
+        *now += Duration::from_secs(60);
+        *nonces += 1000;
+        burn_in.record(sample(temperature_c, *nonces, errors_per_step), *now)
+    }
+
+    #[test]
+    fn plan_fills_defaults_and_sorts() {
+        let plan = BurnInPlan::try_from(BurnInRequest {
+            frequencies_mhz: Some(vec![500.0, 400.0, 500.0]),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(plan.frequencies_mhz, [400.0, 500.0]);
+        assert_eq!(plan.step_duration, DEFAULT_STEP_DURATION);
+
+        for bad in [
+            BurnInRequest {
+                frequencies_mhz: Some(vec![]),
+                ..Default::default()
+            },
+            BurnInRequest {
+                step_secs: Some(1),
+                ..Default::default()
+            },
+            BurnInRequest {
+                max_error_rate: Some(2.0),
+                ..Default::default()
+            },
+        ] {
+            assert!(BurnInPlan::try_from(bad).is_err());
+        }
+    }
+
+    #[test]
+    fn passing_run_recommends_top_step() {
+        let mut now = Instant::now();
+        let (mut burn_in, first) = BurnIn::start("b".into(), Some("s1".into()), plan(), now);
+        assert_eq!(first, 400.0);
+
+        let mut nonces = 0;
+        let action = run_step(&mut burn_in, &mut now, 60.0, &mut nonces, 0);
+        assert_eq!(action, BurnInAction::SetFrequency(450.0));
+        let action = run_step(&mut burn_in, &mut now, 62.0, &mut nonces, 0);
+        assert_eq!(action, BurnInAction::SetFrequency(500.0));
+        let BurnInAction::Finish(report) = run_step(&mut burn_in, &mut now, 64.0, &mut nonces, 0)
+        else {
+            panic!("expected the run to finish");
+        };
+
+        assert!(report.passed);
+        assert_eq!(report.safe_frequency_mhz, Some(500.0));
+        assert_eq!(report.steps.len(), 3);
+        assert_eq!(report.steps[2].nonces, 1000);
+        assert_eq!(report.steps[2].max_temp_c, Some(64.0));
+        assert_eq!(report.steps[2].mean_power_w, Some(15.0));
+    }
+
+    #[test]
+    fn errors_fail_step_and_end_run() {
+        let mut now = Instant::now();
+        let (mut burn_in, _) = BurnIn::start("b".into(), None, plan(), now);
+
+        let mut nonces = 0;
+        run_step(&mut burn_in, &mut now, 60.0, &mut nonces, 0);
+        // 5% hardware errors at 450 MHz
+        let BurnInAction::Finish(report) = run_step(&mut burn_in, &mut now, 62.0, &mut nonces, 50)
+        else {
+            panic!("expected the run to finish");
+        };
+
+        assert!(!report.passed);
+        assert_eq!(report.safe_frequency_mhz, Some(400.0));
+        assert_eq!(report.steps.len(), 2);
+        assert_eq!(report.steps[1].error_rate, 0.05);
+        assert!(report.steps[1].failure.is_some());
+    }
+
+    #[test]
+    fn overheating_ends_run_at_once() {
+        let now = Instant::now();
+        let (mut burn_in, _) = BurnIn::start("b".into(), None, plan(), now);
+
+        let action = burn_in.record(sample(80.0, 0, 0), now + Duration::from_secs(5));
+        let BurnInAction::Finish(report) = action else {
+            panic!("expected the run to finish");
+        };
+        assert!(!report.passed);
+        assert_eq!(report.safe_frequency_mhz, None);
+        assert_eq!(report.steps[0].max_temp_c, Some(80.0));
+    }
+
+    #[test]
+    fn silent_chips_fail_step() {
+        let mut now = Instant::now();
+        let (mut burn_in, _) = BurnIn::start("b".into(), None, plan(), now);
+        burn_in.record(sample(50.0, 0, 0), now);
+        now += Duration::from_secs(60);
+        let BurnInAction::Finish(report) = burn_in.record(sample(50.0, 0, 0), now) else {
+            panic!("expected the run to finish");
+        };
+        assert_eq!(
+            report.steps[0].failure.as_deref(),
+            Some("no nonces reported")
+        );
+    }
+
+    #[test]
+    fn reports_are_kept_per_serial() {
+        let dir = std::env::temp_dir().join(format!("mujina-burn-in-{}", std::process::id()));
+        let now = Instant::now();
+        let (mut burn_in, _) = BurnIn::start("b".into(), Some("ab/12".into()), plan(), now);
+        let report = burn_in.abort(now, "stopped".into());
+
+        let path = save_report(&dir, &report).unwrap();
+        assert_eq!(path, dir.join("ab_12.json"));
+        let loaded = load_report(&dir, "ab/12").unwrap();
+        assert_eq!(loaded.steps, report.steps);
+        assert!(load_report(&dir, "other").is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -67,7 +67,7 @@ async fn create_from_usb(
 ) -> crate::error::Result<(Box<dyn Board + Send>, super::BoardRegistration)> {
     let serial = device.serial_number.clone();
     let initial_state = BoardState {
-        name: format!("emberone-{}", serial.as_deref().unwrap_or("unknown")),
+        name: format!("emberone-{}", device.board_id()),
         model: "EmberOne".into(),
         serial,
         ..Default::default()
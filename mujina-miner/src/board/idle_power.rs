@@ -0,0 +1,87 @@
+//! Core power by chip power state.
+//!
+//! Hash threads gate their chips' core clocks while idle (see
+//! [`ChipPowerState`]). Averaging the board's core power reading
+//! separately for hashing and for low power shows what idling actually
+//! saves on a given board.
+
+use crate::api_client::types::IdlePower;
+use crate::asic::hash_thread::ChipPowerState;
+
+/// Weight of each new reading in the running averages.
+const SMOOTHING: f32 = 0.2;
+
+/// Running average of core power in each chip power state.
+#[derive(Debug, Default)]
+pub struct IdlePowerMeter {
+    hashing_w: Option<f32>,
+    low_power_w: Option<f32>,
+    last_state: Option<ChipPowerState>,
+}
+
+impl IdlePowerMeter {
+    /// Account for a power reading taken while the chips were in `state`.
+    ///
+    /// A reading taken across a state change mixes both states, so the
+    /// first one after each change is skipped.
+    pub fn record(&mut self, state: ChipPowerState, power_w: f32) {
+        let settled = self.last_state.replace(state) == Some(state);
+        if !settled || !power_w.is_finite() {
+            return;
+        }
+
+        let average = match state {
+            ChipPowerState::Hashing => &mut self.hashing_w,
+            ChipPowerState::LowPower => &mut self.low_power_w,
+            ChipPowerState::Off => return,
+        };
+        *average = Some(average.map_or(power_w, |avg| avg + SMOOTHING * (power_w - avg)));
+    }
+
+    /// Averages so far, for the API.
+    pub fn summary(&self) -> IdlePower {
+        IdlePower {
+            hashing_w: self.hashing_w,
+            low_power_w: self.low_power_w,
+            saving_w: self
+                .hashing_w
+                .zip(self.low_power_w)
+                .map(|(hashing, idle)| hashing - idle),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages_each_state_separately() {
+        let mut meter = IdlePowerMeter::default();
+        for power_w in [15.0, 15.0, 15.0] {
+            meter.record(ChipPowerState::Hashing, power_w);
+        }
+        assert_eq!(meter.summary().hashing_w, Some(15.0));
+        assert_eq!(meter.summary().saving_w, None);
+
+        for power_w in [9.0, 3.0, 3.0] {
+            meter.record(ChipPowerState::LowPower, power_w);
+        }
+        let summary = meter.summary();
+        assert_eq!(summary.low_power_w, Some(3.0));
+        assert_eq!(summary.saving_w, Some(12.0));
+    }
+
+    #[test]
+    fn skips_reading_across_state_change() {
+        let mut meter = IdlePowerMeter::default();
+        meter.record(ChipPowerState::Hashing, 15.0);
+        assert_eq!(meter.summary().hashing_w, None);
+
+        meter.record(ChipPowerState::Hashing, 15.0);
+        meter.record(ChipPowerState::Off, 0.5);
+        meter.record(ChipPowerState::Off, 0.5);
+        assert_eq!(meter.summary().hashing_w, Some(15.0));
+        assert_eq!(meter.summary().low_power_w, None);
+    }
+}
@@ -1,14 +1,22 @@
 pub(crate) mod bitaxe;
+pub(crate) mod burn_in;
+#[cfg(feature = "cpu-miner")]
 pub mod cpu;
 pub(crate) mod emberone;
+pub(crate) mod idle_power;
 pub mod pattern;
+pub mod remote;
+pub(crate) mod sim;
+pub(crate) mod stats;
 
 use async_trait::async_trait;
 use std::{error::Error, fmt, future::Future, pin::Pin};
 use tokio::sync::watch;
 
 use crate::{
-    api_client::types::BoardState, asic::hash_thread::HashThread, transport::UsbDeviceInfo,
+    api_client::types::{BoardState, ChipRegisterDump, Profile},
+    asic::hash_thread::HashThread,
+    transport::{NetworkDeviceInfo, UsbDeviceInfo},
 };
 
 /// Represents a mining board containing one or more ASIC chips.
@@ -36,6 +44,71 @@ pub trait Board: Send {
     /// Board-to-thread shutdown is implementation-specific (not exposed through
     /// HashThread trait). Call board.shutdown() to trigger thread shutdown.
     async fn create_hash_threads(&mut self) -> Result<Vec<Box<dyn HashThread>>, BoardError>;
+
+    /// Take this board's hash threads out of service at the user's request.
+    ///
+    /// Unlike [`shutdown`](Board::shutdown), the board stays up and keeps
+    /// monitoring its hardware. Boards that support this must be able to
+    /// hand out fresh threads from
+    /// [`create_hash_threads`](Board::create_hash_threads) afterwards, which
+    /// is how the board is brought back into service.
+    async fn disable_hash_threads(&mut self) -> Result<(), BoardError> {
+        Err(BoardError::HardwareControl(
+            "disabling hash threads not supported by this board".into(),
+        ))
+    }
+
+    /// Switch to an operating profile.
+    ///
+    /// Each board maps the profile to its own frequency, voltage and fan
+    /// settings. Applies to running hash threads and to threads created
+    /// later. Boards without tunable hardware ignore profiles.
+    async fn apply_profile(&mut self, _profile: Profile) -> Result<(), BoardError> {
+        Ok(())
+    }
+
+    /// Set a fan's target duty cycle, or hand it back to automatic control
+    /// with `None`.
+    async fn set_fan_target(&mut self, _fan: &str, _percent: Option<u8>) -> Result<(), BoardError> {
+        Err(BoardError::HardwareControl(
+            "fan control not supported by this board".into(),
+        ))
+    }
+
+    /// Set the chips' core frequency in MHz, or hand it back to the
+    /// profile with `None`.
+    ///
+    /// Holds across profile switches until cleared. Thermal derating still
+    /// caps it.
+    async fn set_frequency(&mut self, _mhz: Option<f32>) -> Result<(), BoardError> {
+        Err(BoardError::HardwareControl(
+            "frequency control not supported by this board".into(),
+        ))
+    }
+
+    /// Start replacing the board's management firmware with `image`.
+    ///
+    /// Returns once the image has passed the board's checks and the update
+    /// is under way; progress is published in the board's state. The hash
+    /// threads must be out of service first. A successful update ends with
+    /// the board rebooting, dropping off and reconnecting.
+    async fn update_firmware(&mut self, _image: Vec<u8>) -> Result<(), BoardError> {
+        Err(BoardError::HardwareControl(
+            "firmware update not supported by this board".into(),
+        ))
+    }
+
+    /// Read back every known register of the chip at `chip_address`,
+    /// optionally comparing them against what initialization wrote.
+    async fn dump_registers(
+        &mut self,
+        _chip_address: u8,
+        _diff: bool,
+    ) -> Result<ChipRegisterDump, BoardError> {
+        Err(BoardError::HardwareControl(
+            "register access not supported by this board".into(),
+        ))
+    }
 }
 
 /// Information about a board
@@ -83,7 +156,7 @@ impl From<std::io::Error> for BoardError {
 /// Registration data returned by board factory functions.
 ///
 /// Bundles the channels needed for the rest of the system to communicate
-/// with a board. The backplane forwards this to the API server after
+/// with a board. The backplane publishes it on the event bus after
 /// creating a board.
 pub struct BoardRegistration {
     /// Watch receiver for the board's current state.
@@ -105,8 +178,8 @@ type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 ///    watch receiver
 ///
 /// The backplane calls the factory when a matching USB device is
-/// discovered, then forwards the [`BoardRegistration`] to the API
-/// server.
+/// discovered, then publishes the [`BoardRegistration`]'s state on the
+/// event bus for the API server and other subscribers.
 pub type BoardFactoryFn =
     fn(
         UsbDeviceInfo,
@@ -173,3 +246,45 @@ impl VirtualBoardRegistry {
         inventory::iter::<VirtualBoardDescriptor>().find(|desc| desc.device_type == device_type)
     }
 }
+
+// ---------------------------------------------------------------------------
+// Network board support (boards served over TCP by a remote agent, etc.)
+// ---------------------------------------------------------------------------
+
+/// Factory function signature for creating a network-attached board.
+///
+/// Same contract as [`BoardFactoryFn`], given the endpoint the board is
+/// served on. The board connects to it itself; the device info's
+/// [`LinkLatency`](crate::transport::LinkLatency) is what its hash
+/// threads should report from [`HashThread::link_latency`].
+///
+/// [`HashThread::link_latency`]: crate::asic::hash_thread::HashThread::link_latency
+pub type NetworkBoardFactoryFn =
+    fn(
+        NetworkDeviceInfo,
+    ) -> BoxFuture<'static, crate::error::Result<(Box<dyn Board + Send>, BoardRegistration)>>;
+
+/// Descriptor for network-attached boards.
+///
+/// Registered via `inventory::submit!` like USB boards, matching the
+/// kind named in a configured endpoint (`kind@host:port`).
+pub struct NetworkBoardDescriptor {
+    /// Board kind as configured (e.g., "agent")
+    pub kind: &'static str,
+    /// Human-readable board name
+    pub name: &'static str,
+    /// Factory function to create the board
+    pub create_fn: NetworkBoardFactoryFn,
+}
+
+inventory::collect!(NetworkBoardDescriptor);
+
+/// Registry for network board descriptors.
+pub struct NetworkBoardRegistry;
+
+impl NetworkBoardRegistry {
+    /// Find a network board descriptor by kind.
+    pub fn find(&self, kind: &str) -> Option<&'static NetworkBoardDescriptor> {
+        inventory::iter::<NetworkBoardDescriptor>().find(|desc| desc.kind == kind)
+    }
+}
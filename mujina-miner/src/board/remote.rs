@@ -0,0 +1,831 @@
+//! Boards served by an agent on another machine.
+//!
+//! A [`RemoteBoard`] stands in for a board attached to an agent (see
+//! [`crate::agent`]). It keeps one connection to the agent, over which its
+//! [`RemoteThread`]s send their assignments and receive shares and status,
+//! and through which the board passes on profile, frequency and fan
+//! commands. Configured as `agent@host:port` among the network boards.
+//!
+//! When the connection drops, the board redials the agent and hands each
+//! thread's last task back to it, so a brief outage costs only the work
+//! missed while it lasted. Whether the board is kept that long is up to
+//! the backplane's reconnect grace.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use super::{Board, BoardError, BoardInfo, BoardRegistration, NetworkBoardDescriptor};
+use crate::{
+    agent::protocol::{
+        self, AgentMessage, Assignment, ControllerMessage, ProtocolError, ThreadInfo,
+    },
+    api_client::types::{BoardState, Profile},
+    asic::hash_thread::{
+        AssignmentParameters, HashTask, HashThread, HashThreadCapabilities, HashThreadError,
+        HashThreadEvent, HashThreadStatus, ShareSender,
+    },
+    config,
+    tracing::prelude::*,
+    transport::{LinkLatency, NetworkDeviceInfo},
+};
+
+/// How often the agent is pinged to measure the link.
+const PING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Pings unanswered before the link is taken for dead.
+const PINGS_LOST: u32 = 3;
+
+/// How long the agent gets to answer a request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest wait between attempts to reach the agent again.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+type Session = protocol::Session<TcpStream>;
+
+/// State shared by a remote board, its threads and its connection.
+struct Link {
+    outgoing: mpsc::Sender<ControllerMessage>,
+    next_id: AtomicU64,
+    /// Requests awaiting the agent's reply, by ID
+    pending: Mutex<HashMap<u64, oneshot::Sender<Result<(), String>>>>,
+    /// Where each task's shares go, with the thread it is assigned to
+    routes: Mutex<HashMap<u64, (usize, ShareSender)>>,
+    threads: Vec<ThreadLink>,
+}
+
+#[derive(Default)]
+struct ThreadLink {
+    status: Mutex<HashThreadStatus>,
+    /// Last assignment, handed back to the agent after a reconnect
+    assigned: Mutex<Option<Assignment>>,
+}
+
+impl Link {
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Send a request and wait for the agent's reply.
+    async fn request(&self, make: impl FnOnce(u64) -> ControllerMessage) -> Result<(), String> {
+        let id = self.next_id();
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, reply_tx);
+        if self.outgoing.send(make(id)).await.is_err() {
+            self.pending.lock().unwrap().remove(&id);
+            return Err("agent link closed".into());
+        }
+        match tokio::time::timeout(REQUEST_TIMEOUT, reply_rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err("agent link lost".into()),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                Err("agent didn't answer".into())
+            }
+        }
+    }
+
+    /// Forget a thread's share routes, except `keep`.
+    fn drop_routes(&self, thread: usize, keep: Option<u64>) {
+        self.routes
+            .lock()
+            .unwrap()
+            .retain(|task, (owner, _)| *owner != thread || Some(*task) == keep);
+    }
+
+    /// Most recent task assigned to `thread`.
+    fn latest_task(&self, thread: usize) -> Option<u64> {
+        self.routes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, (owner, _))| *owner == thread)
+            .map(|(task, _)| *task)
+            .max()
+    }
+}
+
+/// A board attached to an agent elsewhere on the network.
+pub struct RemoteBoard {
+    info: NetworkDeviceInfo,
+    model: String,
+    serial: Option<String>,
+    threads: Vec<ThreadInfo>,
+    /// Handed to the threads when they are created
+    event_rxs: Option<Vec<mpsc::Receiver<HashThreadEvent>>>,
+    link: Arc<Link>,
+    shutdown: CancellationToken,
+    connection: Option<JoinHandle<()>>,
+}
+
+impl RemoteBoard {
+    /// Connect and authenticate to the agent at `info`'s endpoint.
+    pub async fn connect(
+        info: NetworkDeviceInfo,
+        token: String,
+    ) -> Result<(Self, BoardRegistration), ProtocolError> {
+        let (session, board, threads) = dial(&info.endpoint.addr, &token).await?;
+        info!(
+            board = %board.name,
+            threads = threads.len(),
+            endpoint = %info.endpoint,
+            "Connected to agent."
+        );
+
+        let (outgoing_tx, outgoing_rx) = mpsc::channel(64);
+        let link = Arc::new(Link {
+            outgoing: outgoing_tx,
+            next_id: AtomicU64::new(1),
+            pending: Mutex::default(),
+            routes: Mutex::default(),
+            threads: threads.iter().map(|_| ThreadLink::default()).collect(),
+        });
+        let (event_txs, event_rxs): (Vec<_>, Vec<_>) =
+            threads.iter().map(|_| mpsc::channel(32)).unzip();
+        let model = board.model.clone();
+        let serial = board.serial.clone();
+        let (state_tx, state_rx) = watch::channel(board);
+
+        let shutdown = CancellationToken::new();
+        let link_task = LinkTask {
+            link: link.clone(),
+            outgoing_rx,
+            event_txs,
+            state_tx,
+            addr: info.endpoint.addr.clone(),
+            token,
+            latency: info.latency.clone(),
+            shutdown: shutdown.clone(),
+        };
+        let connection = tokio::spawn(link_task.run(session).in_current_span());
+
+        let board = Self {
+            info,
+            model,
+            serial,
+            threads,
+            event_rxs: Some(event_rxs),
+            link,
+            shutdown,
+            connection: Some(connection),
+        };
+        Ok((board, BoardRegistration { state_rx }))
+    }
+
+    async fn request(&self, make: impl FnOnce(u64) -> ControllerMessage) -> Result<(), BoardError> {
+        self.link
+            .request(make)
+            .await
+            .map_err(BoardError::HardwareControl)
+    }
+}
+
+#[async_trait]
+impl Board for RemoteBoard {
+    fn board_info(&self) -> BoardInfo {
+        BoardInfo {
+            model: self.model.clone(),
+            firmware_version: None,
+            serial_number: self.serial.clone(),
+        }
+    }
+
+    async fn shutdown(&mut self) -> Result<(), BoardError> {
+        self.shutdown.cancel();
+        if let Some(connection) = self.connection.take() {
+            let _ = connection.await;
+        }
+        Ok(())
+    }
+
+    async fn create_hash_threads(&mut self) -> Result<Vec<Box<dyn HashThread>>, BoardError> {
+        let event_rxs = self.event_rxs.take().ok_or_else(|| {
+            BoardError::HardwareControl("remote hash threads already created".into())
+        })?;
+        let threads = self
+            .threads
+            .iter()
+            .zip(event_rxs)
+            .enumerate()
+            .map(|(index, (info, event_rx))| {
+                Box::new(RemoteThread {
+                    index,
+                    name: info.name.clone(),
+                    device_id: info.device_id.clone(),
+                    capabilities: info.capabilities(),
+                    latency: self.info.latency.clone(),
+                    link: self.link.clone(),
+                    event_rx: Some(event_rx),
+                    current: None,
+                }) as Box<dyn HashThread>
+            })
+            .collect();
+        Ok(threads)
+    }
+
+    async fn apply_profile(&mut self, profile: Profile) -> Result<(), BoardError> {
+        self.request(|id| ControllerMessage::SetProfile { id, profile })
+            .await
+    }
+
+    async fn set_fan_target(&mut self, fan: &str, percent: Option<u8>) -> Result<(), BoardError> {
+        let fan = fan.to_string();
+        self.request(|id| ControllerMessage::SetFanTarget { id, fan, percent })
+            .await
+    }
+
+    async fn set_frequency(&mut self, mhz: Option<f32>) -> Result<(), BoardError> {
+        self.request(|id| ControllerMessage::SetFrequency { id, mhz })
+            .await
+    }
+}
+
+/// A hash thread on an agent's board.
+pub struct RemoteThread {
+    /// Position in the agent's welcome, which addresses the thread
+    index: usize,
+    name: String,
+    device_id: Option<String>,
+    capabilities: HashThreadCapabilities,
+    latency: LinkLatency,
+    link: Arc<Link>,
+    event_rx: Option<mpsc::Receiver<HashThreadEvent>>,
+    current: Option<HashTask>,
+}
+
+impl RemoteThread {
+    async fn assign(
+        &mut self,
+        task: HashTask,
+        replace: bool,
+    ) -> Result<Option<HashTask>, HashThreadError> {
+        let id = self.link.next_id();
+        let assignment = Assignment::new(self.index, id, replace, &task);
+
+        // Shares of a replaced task are stale; those of an updated one
+        // still count until it is updated in turn
+        let keep = if replace {
+            None
+        } else {
+            self.link.latest_task(self.index)
+        };
+        self.link.drop_routes(self.index, keep);
+        self.link
+            .routes
+            .lock()
+            .unwrap()
+            .insert(id, (self.index, task.share_tx.clone()));
+        *self.link.threads[self.index].assigned.lock().unwrap() = Some(assignment.clone());
+
+        self.link
+            .outgoing
+            .send(ControllerMessage::Assign(Box::new(assignment)))
+            .await
+            .map_err(|_| HashThreadError::ThreadOffline)?;
+        Ok(self.current.replace(task))
+    }
+}
+
+#[async_trait]
+impl HashThread for RemoteThread {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> &HashThreadCapabilities {
+        &self.capabilities
+    }
+
+    async fn negotiate(&mut self, params: AssignmentParameters) -> Result<(), HashThreadError> {
+        let thread = self.index;
+        self.link
+            .request(|id| ControllerMessage::Negotiate {
+                id,
+                thread,
+                max_ntime_roll: params.max_ntime_roll,
+            })
+            .await
+            .map_err(HashThreadError::WorkAssignmentFailed)
+    }
+
+    fn device_id(&self) -> Option<&str> {
+        self.device_id.as_deref()
+    }
+
+    fn link_latency(&self) -> Option<Duration> {
+        self.latency.get()
+    }
+
+    async fn update_task(&mut self, task: HashTask) -> Result<Option<HashTask>, HashThreadError> {
+        self.assign(task, false).await
+    }
+
+    async fn replace_task(&mut self, task: HashTask) -> Result<Option<HashTask>, HashThreadError> {
+        self.assign(task, true).await
+    }
+
+    async fn go_idle(&mut self) -> Result<Option<HashTask>, HashThreadError> {
+        self.link.drop_routes(self.index, None);
+        *self.link.threads[self.index].assigned.lock().unwrap() = None;
+        self.link
+            .outgoing
+            .send(ControllerMessage::GoIdle { thread: self.index })
+            .await
+            .map_err(|_| HashThreadError::ThreadOffline)?;
+        Ok(self.current.take())
+    }
+
+    fn take_event_receiver(&mut self) -> Option<mpsc::Receiver<HashThreadEvent>> {
+        self.event_rx.take()
+    }
+
+    fn status(&self) -> HashThreadStatus {
+        self.link.threads[self.index].status.lock().unwrap().clone()
+    }
+}
+
+/// Carries a remote board's traffic, redialing the agent when the
+/// connection drops.
+struct LinkTask {
+    link: Arc<Link>,
+    outgoing_rx: mpsc::Receiver<ControllerMessage>,
+    /// Closing these tells the scheduler the threads are gone
+    event_txs: Vec<mpsc::Sender<HashThreadEvent>>,
+    state_tx: watch::Sender<BoardState>,
+    addr: String,
+    token: String,
+    latency: LinkLatency,
+    shutdown: CancellationToken,
+}
+
+impl LinkTask {
+    async fn run(mut self, mut session: Session) {
+        loop {
+            match self.serve(&mut session).await {
+                Ok(()) => break,
+                Err(e) => warn!(error = %e, "Lost the agent, reconnecting."),
+            }
+            self.fail_pending();
+
+            let Some(new_session) = self.reconnect().await else {
+                break;
+            };
+            session = new_session;
+            info!("Reconnected to agent.");
+
+            // The agent idled the threads when the link dropped
+            let assigned: Vec<_> = self
+                .link
+                .threads
+                .iter()
+                .filter_map(|thread| thread.assigned.lock().unwrap().clone())
+                .collect();
+            for mut assignment in assigned {
+                assignment.replace = true;
+                if let Err(e) = session
+                    .send(&ControllerMessage::Assign(Box::new(assignment)))
+                    .await
+                {
+                    warn!(error = %e, "Failed to resume work on the agent");
+                }
+            }
+        }
+
+        self.fail_pending();
+        for event_tx in &self.event_txs {
+            let _ = event_tx.try_send(HashThreadEvent::GoingOffline);
+        }
+    }
+
+    /// Relay traffic until shutdown (`Ok`) or the connection fails.
+    async fn serve(&mut self, session: &mut Session) -> Result<(), ProtocolError> {
+        let mut ping = tokio::time::interval(PING_INTERVAL);
+        let mut ping_sent: Option<(u64, Instant)> = None;
+        let mut pings_lost = 0;
+
+        loop {
+            tokio::select! {
+                _ = self.shutdown.cancelled() => return Ok(()),
+
+                message = self.outgoing_rx.recv() => match message {
+                    Some(message) => session.send(&message).await?,
+                    None => return Ok(()),
+                },
+
+                _ = ping.tick() => {
+                    if ping_sent.is_some() {
+                        pings_lost += 1;
+                        if pings_lost >= PINGS_LOST {
+                            return Err(ProtocolError::Closed);
+                        }
+                    }
+                    let id = self.link.next_id();
+                    ping_sent = Some((id, Instant::now()));
+                    session.send(&ControllerMessage::Ping { id }).await?;
+                }
+
+                message = session.recv::<AgentMessage>() => match message? {
+                    AgentMessage::Pong { id } => {
+                        if let Some((sent_id, sent_at)) = ping_sent
+                            && sent_id == id
+                        {
+                            self.latency.record(sent_at.elapsed());
+                            ping_sent = None;
+                            pings_lost = 0;
+                        }
+                    }
+                    message => self.handle(message).await?,
+                },
+            }
+        }
+    }
+
+    async fn handle(&mut self, message: AgentMessage) -> Result<(), ProtocolError> {
+        match message {
+            AgentMessage::State { board } => {
+                self.state_tx.send_replace(board);
+            }
+            AgentMessage::Share {
+                thread,
+                task,
+                share,
+            } => {
+                let route = self.link.routes.lock().unwrap().get(&task).cloned();
+                let Some((_, share_tx)) = route else {
+                    trace!(thread, task, "Share for a task no longer assigned");
+                    return Ok(());
+                };
+                match share.to_share() {
+                    Ok(share) => {
+                        let _ = share_tx.send(share).await;
+                    }
+                    Err(e) => warn!(thread, error = %e, "Malformed share from agent"),
+                }
+            }
+            AgentMessage::Status { thread, status } => {
+                let status = HashThreadStatus::from(&status);
+                if let Some(link) = self.link.threads.get(thread) {
+                    *link.status.lock().unwrap() = status.clone();
+                }
+                if let Some(event_tx) = self.event_txs.get(thread) {
+                    // Superseded by the next one if the scheduler is busy
+                    let _ = event_tx.try_send(HashThreadEvent::StatusUpdate(Box::new(status)));
+                }
+            }
+            AgentMessage::WorkDepletionWarning {
+                thread,
+                estimated_remaining_ms,
+            } => {
+                let event = HashThreadEvent::WorkDepletionWarning {
+                    estimated_remaining_ms,
+                };
+                self.forward(thread, event).await;
+            }
+            AgentMessage::WorkExhausted {
+                thread,
+                en2_searched,
+            } => {
+                self.forward(thread, HashThreadEvent::WorkExhausted { en2_searched })
+                    .await;
+            }
+            AgentMessage::GoingOffline { thread } => {
+                self.forward(thread, HashThreadEvent::GoingOffline).await;
+            }
+            AgentMessage::Reply { id, error } => {
+                if let Some(reply_tx) = self.link.pending.lock().unwrap().remove(&id) {
+                    let _ = reply_tx.send(error.map_or(Ok(()), Err));
+                }
+            }
+            AgentMessage::Refused { reason } => return Err(ProtocolError::Refused(reason)),
+            AgentMessage::Pong { .. } => {}
+            AgentMessage::Challenge { .. }
+            | AgentMessage::Accepted { .. }
+            | AgentMessage::Welcome { .. } => {
+                return Err(ProtocolError::Unexpected("traffic after the handshake"));
+            }
+        }
+        Ok(())
+    }
+
+    async fn forward(&self, thread: usize, event: HashThreadEvent) {
+        if let Some(event_tx) = self.event_txs.get(thread) {
+            let _ = event_tx.send(event).await;
+        }
+    }
+
+    /// Fail the requests awaiting replies that won't come.
+    fn fail_pending(&self) {
+        self.link.pending.lock().unwrap().clear();
+    }
+
+    /// Dial the agent until it answers or the board shuts down.
+    ///
+    /// Messages sent meanwhile are dropped; the threads' assignments are
+    /// replayed once connected.
+    async fn reconnect(&mut self) -> Option<Session> {
+        let mut delay = Duration::from_secs(1);
+        loop {
+            let sleep = tokio::time::sleep(delay);
+            tokio::pin!(sleep);
+            loop {
+                tokio::select! {
+                    _ = self.shutdown.cancelled() => return None,
+                    _ = &mut sleep => break,
+                    message = self.outgoing_rx.recv() => {
+                        message?;
+                    }
+                }
+            }
+
+            match dial(&self.addr, &self.token).await {
+                Ok((session, board, threads)) => {
+                    if threads.len() != self.link.threads.len() {
+                        warn!(
+                            was = self.link.threads.len(),
+                            now = threads.len(),
+                            "Agent came back with a different number of threads"
+                        );
+                    }
+                    self.state_tx.send_replace(board);
+                    return Some(session);
+                }
+                Err(e) => {
+                    debug!(error = %e, "Agent still unreachable");
+                    delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                }
+            }
+        }
+    }
+}
+
+/// Open a connection to an agent and authenticate.
+async fn dial(
+    addr: &str,
+    token: &str,
+) -> Result<(Session, BoardState, Vec<ThreadInfo>), ProtocolError> {
+    let stream = TcpStream::connect(addr).await?;
+    let _ = stream.set_nodelay(true);
+    let conn = protocol::framed(stream);
+    tokio::time::timeout(REQUEST_TIMEOUT, protocol::authenticate(conn, token))
+        .await
+        .map_err(|_| ProtocolError::Closed)?
+}
+
+// ---------------------------------------------------------------------------
+// Network board registration
+// ---------------------------------------------------------------------------
+
+/// Factory function for boards served by an agent.
+async fn create_remote_board(
+    info: NetworkDeviceInfo,
+) -> crate::error::Result<(Box<dyn Board + Send>, BoardRegistration)> {
+    let token = config::board_config()
+        .agent_token
+        .clone()
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| {
+            crate::error::Error::Config("agent boards need boards.agent_token".into())
+        })?;
+    let (board, registration) = RemoteBoard::connect(info, token)
+        .await
+        .map_err(|e| crate::error::Error::Protocol(e.to_string()))?;
+    Ok((Box::new(board), registration))
+}
+
+inventory::submit! {
+    NetworkBoardDescriptor {
+        kind: "agent",
+        name: "Agent",
+        create_fn: |info| Box::pin(create_remote_board(info)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::hashes::Hash;
+    use bitcoin::{BlockHash, CompactTarget, TxMerkleNode, block::Version};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::{
+        agent::{AgentServer, LocalBoard},
+        api::commands::{BoardCommand, CommandBus},
+        asic::hash_thread::Share,
+        job_source::{GeneralPurposeBits, JobTemplate, MerkleRootKind, VersionTemplate},
+        types::{Difficulty, HashRate},
+    };
+
+    /// Agent-side thread that hands its tasks to the test.
+    struct StubThread {
+        capabilities: HashThreadCapabilities,
+        tasks: mpsc::UnboundedSender<HashTask>,
+        event_rx: Option<mpsc::Receiver<HashThreadEvent>>,
+    }
+
+    #[async_trait]
+    impl HashThread for StubThread {
+        fn name(&self) -> &str {
+            "stub"
+        }
+
+        fn capabilities(&self) -> &HashThreadCapabilities {
+            &self.capabilities
+        }
+
+        async fn negotiate(
+            &mut self,
+            _params: AssignmentParameters,
+        ) -> Result<(), HashThreadError> {
+            Ok(())
+        }
+
+        async fn update_task(
+            &mut self,
+            task: HashTask,
+        ) -> Result<Option<HashTask>, HashThreadError> {
+            let _ = self.tasks.send(task);
+            Ok(None)
+        }
+
+        async fn replace_task(
+            &mut self,
+            task: HashTask,
+        ) -> Result<Option<HashTask>, HashThreadError> {
+            let _ = self.tasks.send(task);
+            Ok(None)
+        }
+
+        async fn go_idle(&mut self) -> Result<Option<HashTask>, HashThreadError> {
+            Ok(None)
+        }
+
+        fn take_event_receiver(&mut self) -> Option<mpsc::Receiver<HashThreadEvent>> {
+            self.event_rx.take()
+        }
+
+        fn status(&self) -> HashThreadStatus {
+            HashThreadStatus::default()
+        }
+    }
+
+    const NTIME: u32 = 0x6839_5e10;
+
+    fn task(share_tx: mpsc::Sender<Share>) -> HashTask {
+        let template = JobTemplate {
+            id: "7".into(),
+            prev_blockhash: BlockHash::all_zeros(),
+            version: VersionTemplate::new(
+                Version::from_consensus(0x2000_0000),
+                GeneralPurposeBits::full(),
+            )
+            .unwrap(),
+            bits: CompactTarget::from_consensus(0x1702_8c61),
+            share_target: Difficulty::from(1024).to_target(),
+            time: NTIME,
+            merkle_root: MerkleRootKind::Fixed(TxMerkleNode::all_zeros()),
+        };
+        HashTask {
+            template: Arc::new(template),
+            en2_range: None,
+            en2: None,
+            share_target: Difficulty::from(256).to_target(),
+            ntime: NTIME,
+            share_tx: ShareSender::new(share_tx, Default::default()),
+        }
+    }
+
+    struct Agent {
+        info: NetworkDeviceInfo,
+        tasks: mpsc::UnboundedReceiver<HashTask>,
+        board_cmd_rx: mpsc::Receiver<BoardCommand>,
+        // Keeps the stub thread's event channel open
+        _event_tx: mpsc::Sender<HashThreadEvent>,
+        shutdown: CancellationToken,
+    }
+
+    async fn agent() -> Agent {
+        let (tasks_tx, tasks) = mpsc::unbounded_channel();
+        let (event_tx, event_rx) = mpsc::channel(8);
+        let thread = StubThread {
+            capabilities: HashThreadCapabilities {
+                hashrate_estimate: HashRate(1_000_000_000_000),
+                version_rolling: GeneralPurposeBits::full(),
+                max_ntime_roll: 0,
+                iterates_extranonce2: false,
+                reporting_difficulty: None,
+            },
+            tasks: tasks_tx,
+            event_rx: Some(event_rx),
+        };
+        let (_state_tx, state_rx) = watch::channel(BoardState {
+            name: "stub-1".into(),
+            model: "Stub".into(),
+            ..Default::default()
+        });
+        let (scheduler_cmd_tx, _) = mpsc::channel(1);
+        let (board_cmd_tx, board_cmd_rx) = mpsc::channel(1);
+        let board = LocalBoard {
+            name: "stub-1".into(),
+            state_rx,
+            threads: vec![Box::new(thread)],
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let shutdown = CancellationToken::new();
+        let server = AgentServer::new(
+            board,
+            "secret".into(),
+            CommandBus::new(scheduler_cmd_tx, board_cmd_tx),
+        );
+        tokio::spawn(server.run(listener, shutdown.clone()));
+
+        let endpoint: crate::transport::NetworkEndpoint = format!("agent@{addr}").parse().unwrap();
+        Agent {
+            info: NetworkDeviceInfo {
+                device_id: endpoint.device_id(),
+                endpoint,
+                latency: LinkLatency::default(),
+            },
+            tasks,
+            board_cmd_rx,
+            _event_tx: event_tx,
+            shutdown,
+        }
+    }
+
+    #[tokio::test]
+    async fn wrong_token_is_refused() {
+        let agent = agent().await;
+        let result = RemoteBoard::connect(agent.info.clone(), "guess".into()).await;
+        assert!(matches!(result, Err(ProtocolError::Refused(_))));
+        agent.shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn remote_threads_mine_on_the_agent() {
+        let mut agent = agent().await;
+        let (mut board, registration) = RemoteBoard::connect(agent.info.clone(), "secret".into())
+            .await
+            .unwrap();
+        assert_eq!(registration.state_rx.borrow().name, "stub-1");
+
+        let mut threads = board.create_hash_threads().await.unwrap();
+        assert_eq!(threads.len(), 1);
+        let thread = &mut threads[0];
+        assert_eq!(thread.name(), "stub");
+        assert_eq!(
+            thread.capabilities().hashrate_estimate,
+            HashRate(1_000_000_000_000)
+        );
+        thread
+            .negotiate(AssignmentParameters { max_ntime_roll: 0 })
+            .await
+            .unwrap();
+
+        // Work assigned here reaches the agent's thread...
+        let (share_tx, mut share_rx) = mpsc::channel(8);
+        thread.replace_task(task(share_tx)).await.unwrap();
+        let remote_task = agent.tasks.recv().await.unwrap();
+        assert_eq!(remote_task.template.id, "7");
+        assert_eq!(remote_task.ntime, NTIME);
+
+        // ...and its shares come back here
+        let share = Share {
+            nonce: 0x1234_5678,
+            hash: BlockHash::all_zeros(),
+            version: Version::from_consensus(0x2000_0000),
+            ntime: NTIME,
+            extranonce2: None,
+            expected_work: remote_task.share_target.to_work(),
+        };
+        remote_task.share_tx.send(share).await.unwrap();
+        let received = share_rx.recv().await.unwrap();
+        assert_eq!(received.nonce, 0x1234_5678);
+
+        // Board commands are carried out by the agent's backplane
+        tokio::spawn(async move {
+            if let Some(BoardCommand::SetFrequency {
+                board,
+                frequency_mhz,
+                reply,
+            }) = agent.board_cmd_rx.recv().await
+            {
+                assert_eq!((board.as_str(), frequency_mhz), ("stub-1", Some(400.0)));
+                let _ = reply.send(Ok(()));
+            }
+        });
+        board.set_frequency(Some(400.0)).await.unwrap();
+
+        board.shutdown().await.unwrap();
+        agent.shutdown.cancel();
+    }
+}
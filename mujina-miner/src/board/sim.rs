@@ -0,0 +1,593 @@
+//! Simulated board for development without hardware.
+//!
+//! Stands in for a single-chip board so the thermal control path and the
+//! API can be exercised on a laptop. There is no chip: the board hashes
+//! nothing (run the CPU miner alongside it for that) and its sensor
+//! readings come from a [`ThermalModel`] driven by the simulated core
+//! frequency and fan speed.
+//!
+//! The board reacts the way hardware would. Profiles set its frequency
+//! and automatic fan speed, fan targets set through the API override the
+//! fan, the configured derating curve caps the frequency as the model
+//! heats up, and disabling the board drops it to idle power.
+
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use super::{
+    Board, BoardError, BoardInfo, VirtualBoardDescriptor,
+    idle_power::IdlePowerMeter,
+    stats::{self, BoardStatsHandle, SensorReadings},
+};
+use crate::{
+    api_client::types::{BoardState, Fan, PowerMeasurement, Profile, TemperatureSensor},
+    asic::{
+        derating::{DeratingCurve, FrequencyLimiter},
+        hash_thread::{ChipPowerState, HashThread},
+        slew::ThermalSlew,
+        thermal::ThermalConfig,
+    },
+    config,
+    tracing::prelude::*,
+};
+
+/// Name of the board's only fan.
+const FAN_NAME: &str = "fan";
+
+/// Fan speed at 100% duty.
+const MAX_FAN_RPM: u32 = 6000;
+
+/// Room temperature the board starts at and cools towards.
+const AMBIENT_C: f32 = 25.0;
+
+/// First-order thermal model of a hash chip and its heatsink.
+///
+/// Core power grows linearly with frequency. The chip settles at ambient
+/// plus power times the thermal resistance to ambient, which falls as the
+/// fan speeds up, and approaches that temperature exponentially.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThermalModel {
+    ambient_c: f32,
+    temperature_c: f32,
+}
+
+impl ThermalModel {
+    /// Core power with the clocks gated.
+    const IDLE_W: f32 = 2.0;
+    /// Additional core power per MHz of core frequency.
+    const W_PER_MHZ: f32 = 0.03;
+    /// Thermal resistance to ambient with the fan stopped, °C/W.
+    const STILL_AIR_C_PER_W: f32 = 6.0;
+    /// Thermal resistance to ambient at full fan speed, °C/W.
+    const FULL_FAN_C_PER_W: f32 = 2.0;
+    /// Time to cover 63% of the way to a new steady state.
+    const TIME_CONSTANT: Duration = Duration::from_secs(20);
+
+    /// A model that starts at ambient temperature.
+    pub fn new(ambient_c: f32) -> Self {
+        Self {
+            ambient_c,
+            temperature_c: ambient_c,
+        }
+    }
+
+    pub fn temperature_c(&self) -> f32 {
+        self.temperature_c
+    }
+
+    /// Core power at `frequency_mhz`; zero means clocks gated.
+    pub fn power_w(frequency_mhz: f32) -> f32 {
+        Self::IDLE_W + Self::W_PER_MHZ * frequency_mhz.max(0.0)
+    }
+
+    /// Temperature the chip settles at under constant conditions.
+    pub fn steady_state_c(&self, frequency_mhz: f32, fan_percent: u8) -> f32 {
+        let fan = f32::from(fan_percent.min(100)) / 100.0;
+        let resistance =
+            Self::STILL_AIR_C_PER_W - (Self::STILL_AIR_C_PER_W - Self::FULL_FAN_C_PER_W) * fan;
+        self.ambient_c + Self::power_w(frequency_mhz) * resistance
+    }
+
+    /// Advance the model by `dt` at the given frequency and fan speed.
+    pub fn step(&mut self, frequency_mhz: f32, fan_percent: u8, dt: Duration) {
+        let target = self.steady_state_c(frequency_mhz, fan_percent);
+        let fraction = 1.0 - (-dt.as_secs_f32() / Self::TIME_CONSTANT.as_secs_f32()).exp();
+        self.temperature_c += (target - self.temperature_c) * fraction;
+    }
+}
+
+/// Operating point of the simulated board under a [`Profile`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ProfileSettings {
+    frequency_mhz: f32,
+    /// Fan speed while the fan is in automatic mode
+    fan_percent: u8,
+}
+
+impl ProfileSettings {
+    fn for_profile(profile: Profile) -> Self {
+        match profile {
+            Profile::Quiet => Self {
+                frequency_mhz: 490.0,
+                fan_percent: 60,
+            },
+            Profile::Balanced => Self {
+                frequency_mhz: 525.0,
+                fan_percent: 100,
+            },
+            Profile::Turbo => Self {
+                frequency_mhz: 575.0,
+                fan_percent: 100,
+            },
+        }
+    }
+}
+
+/// Core frequencies that can be set in place of the profile's, in MHz,
+/// as on the Bitaxe the board stands in for.
+const FREQUENCY_RANGE_MHZ: RangeInclusive<f32> = 50.0..=575.0;
+
+/// Settings the simulation task follows, written by the board.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Controls {
+    profile: Profile,
+    /// Fan override, or `None` for the profile's fan speed
+    fan_target: Option<u8>,
+    /// Frequency override, or `None` for the profile's frequency
+    frequency_mhz: Option<f32>,
+    /// Whether the board is in service; out of service it idles
+    hashing: bool,
+    /// Limit on how fast frequency and fan changes may move the
+    /// temperature
+    slew: Option<ThermalSlew>,
+    /// How often the model advances and the controller acts
+    thermal: ThermalConfig,
+}
+
+/// Simulated mining board.
+pub struct SimBoard {
+    serial: String,
+    controls: watch::Sender<Controls>,
+    task: JoinHandle<()>,
+}
+
+impl SimBoard {
+    /// Create the board and start simulating.
+    ///
+    /// The frequency is capped by `derating` as the model heats up.
+    pub fn new(
+        serial: String,
+        derating: DeratingCurve,
+        state_tx: watch::Sender<BoardState>,
+    ) -> Self {
+        let identity = state_tx.borrow().clone();
+        let (stats, _) = stats::spawn(identity, state_tx);
+        let (controls, controls_rx) = watch::channel(Controls {
+            profile: Profile::default(),
+            fan_target: None,
+            frequency_mhz: None,
+            hashing: false,
+            slew: None,
+            thermal: ThermalConfig::default(),
+        });
+        let task = tokio::spawn(simulate(controls_rx, derating, stats).in_current_span());
+
+        Self {
+            serial,
+            controls,
+            task,
+        }
+    }
+
+    /// Move frequency and fan slowly enough that the temperature changes
+    /// by at most the slew limit, except when derating.
+    pub fn with_thermal_slew(self, slew: Option<ThermalSlew>) -> Self {
+        self.controls.send_modify(|c| c.slew = slew);
+        self
+    }
+
+    /// Advance the model, read its temperature and step eased changes on
+    /// `config`'s timing instead of the defaults.
+    pub fn with_thermal_config(self, config: ThermalConfig) -> Self {
+        self.controls.send_modify(|c| c.thermal = config);
+        self
+    }
+}
+
+#[async_trait]
+impl Board for SimBoard {
+    fn board_info(&self) -> BoardInfo {
+        BoardInfo {
+            model: "Simulated Board".into(),
+            firmware_version: None,
+            serial_number: Some(self.serial.clone()),
+        }
+    }
+
+    async fn shutdown(&mut self) -> Result<(), BoardError> {
+        self.task.abort();
+        Ok(())
+    }
+
+    async fn create_hash_threads(&mut self) -> Result<Vec<Box<dyn HashThread>>, BoardError> {
+        self.controls.send_modify(|c| c.hashing = true);
+        Ok(Vec::new())
+    }
+
+    async fn disable_hash_threads(&mut self) -> Result<(), BoardError> {
+        self.controls.send_modify(|c| c.hashing = false);
+        Ok(())
+    }
+
+    async fn apply_profile(&mut self, profile: Profile) -> Result<(), BoardError> {
+        self.controls.send_modify(|c| c.profile = profile);
+        Ok(())
+    }
+
+    async fn set_fan_target(&mut self, fan: &str, percent: Option<u8>) -> Result<(), BoardError> {
+        if fan != FAN_NAME {
+            return Err(BoardError::HardwareControl(format!("no fan named {fan}")));
+        }
+        if percent.is_some_and(|p| p > 100) {
+            return Err(BoardError::HardwareControl("fan target above 100%".into()));
+        }
+        self.controls.send_modify(|c| c.fan_target = percent);
+        Ok(())
+    }
+
+    async fn set_frequency(&mut self, mhz: Option<f32>) -> Result<(), BoardError> {
+        if let Some(mhz) = mhz
+            && !FREQUENCY_RANGE_MHZ.contains(&mhz)
+        {
+            return Err(BoardError::HardwareControl(format!(
+                "frequency {mhz} MHz outside {}--{} MHz",
+                FREQUENCY_RANGE_MHZ.start(),
+                FREQUENCY_RANGE_MHZ.end()
+            )));
+        }
+        self.controls.send_modify(|c| c.frequency_mhz = mhz);
+        Ok(())
+    }
+}
+
+/// Advance the model and publish its readings until the board goes away.
+async fn simulate(
+    controls: watch::Receiver<Controls>,
+    derating: DeratingCurve,
+    stats: BoardStatsHandle,
+) {
+    let mut model = ThermalModel::new(AMBIENT_C);
+    let mut idle_power = IdlePowerMeter::default();
+    let mut frequency_mhz = 0.0;
+    // Fan duty being driven, once the first step has set it
+    let mut fan_duty: Option<u8> = None;
+    // Ticks since eased changes last took a step
+    let mut ticks_since_adjust = 0;
+
+    let mut thermal = controls.borrow().thermal;
+    let mut limiter = FrequencyLimiter::new(derating).with_overshoot(thermal.overshoot_c);
+    let mut interval = tokio::time::interval(thermal.tick);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+        let controls = *controls.borrow();
+        if controls.thermal != thermal {
+            if controls.thermal.tick != thermal.tick {
+                interval = tokio::time::interval(controls.thermal.tick);
+                interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                interval.tick().await;
+            }
+            if controls.thermal.overshoot_c != limiter.overshoot_c() {
+                limiter = FrequencyLimiter::new(limiter.curve().clone())
+                    .with_overshoot(controls.thermal.overshoot_c);
+            }
+            thermal = controls.thermal;
+        }
+        let tick = thermal.tick;
+        ticks_since_adjust += 1;
+        let adjust = ticks_since_adjust >= thermal.ticks_per_adjustment();
+        if adjust {
+            ticks_since_adjust = 0;
+        }
+
+        let settings = ProfileSettings::for_profile(controls.profile);
+        let fan_target = controls.fan_target.unwrap_or(settings.fan_percent);
+        let fan_percent = *fan_duty.insert(match (controls.slew, fan_duty) {
+            (Some(slew), Some(percent)) if adjust => {
+                slew.fan_step(percent, fan_target, thermal.adjustment_period())
+            }
+            (Some(_), Some(percent)) => percent,
+            _ => fan_target,
+        });
+        let set_mhz = controls.frequency_mhz.unwrap_or(settings.frequency_mhz);
+        let temperature_c = model.temperature_c();
+
+        let max_mhz = limiter.update(temperature_c);
+        let ceiling = max_mhz.map_or(set_mhz, |max| max.min(set_mhz));
+        let mut target_mhz = if controls.hashing { ceiling } else { 0.0 };
+        // Derating down and idling happen at once, anything else at the
+        // slew limit
+        let derating = max_mhz.is_some_and(|max| max < frequency_mhz);
+        if let Some(slew) = controls.slew
+            && controls.hashing
+            && frequency_mhz > 0.0
+            && !derating
+        {
+            let next_mhz = if adjust {
+                slew.frequency_step(frequency_mhz, target_mhz, thermal.adjustment_period())
+            } else {
+                frequency_mhz
+            };
+            if next_mhz != frequency_mhz {
+                debug!(
+                    from_mhz = frequency_mhz,
+                    to_mhz = next_mhz,
+                    target_mhz,
+                    "Slewing core frequency"
+                );
+            }
+            target_mhz = next_mhz;
+        } else if controls.hashing && frequency_mhz > 0.0 && target_mhz != frequency_mhz {
+            if target_mhz < frequency_mhz {
+                warn!(
+                    temperature_c,
+                    from_mhz = frequency_mhz,
+                    to_mhz = target_mhz,
+                    "Derating core frequency"
+                );
+            } else {
+                info!(
+                    temperature_c,
+                    from_mhz = frequency_mhz,
+                    to_mhz = target_mhz,
+                    "Restoring core frequency"
+                );
+            }
+        }
+        frequency_mhz = target_mhz;
+
+        model.step(frequency_mhz, fan_percent, tick);
+
+        let power_w = ThermalModel::power_w(frequency_mhz);
+        let power_state = if controls.hashing {
+            ChipPowerState::Hashing
+        } else {
+            ChipPowerState::LowPower
+        };
+        idle_power.record(power_state, power_w);
+
+        let temperature_c = model.temperature_c();
+        stats.update_sensors(SensorReadings {
+            fans: vec![Fan {
+                name: FAN_NAME.into(),
+                rpm: Some(MAX_FAN_RPM * u32::from(fan_percent) / 100),
+                percent: Some(fan_percent),
+                target_percent: controls.fan_target,
+            }],
+            temperatures: vec![
+                TemperatureSensor {
+                    name: "asic".into(),
+                    temperature_c: Some(temperature_c),
+                },
+                // The die runs a little hotter than the package sensor
+                TemperatureSensor {
+                    name: "asic-die".into(),
+                    temperature_c: Some(temperature_c + 0.25 * power_w),
+                },
+            ],
+            powers: vec![PowerMeasurement {
+                name: "core".into(),
+                voltage_v: None,
+                current_a: None,
+                power_w: Some(power_w),
+            }],
+            idle_power: idle_power.summary(),
+        });
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Virtual board registration
+// ---------------------------------------------------------------------------
+
+/// Factory function for creating SimBoard instances.
+async fn create_sim_board()
+-> crate::error::Result<(Box<dyn Board + Send>, super::BoardRegistration)> {
+    let serial = "sim-0".to_string();
+    let initial_state = BoardState {
+        name: serial.clone(),
+        model: "Simulated Board".into(),
+        serial: Some(serial.clone()),
+        ..Default::default()
+    };
+    let (state_tx, state_rx) = watch::channel(initial_state);
+
+    let board = SimBoard::new(serial, config::board_config().derating_curve(), state_tx)
+        .with_thermal_slew(config::board_config().thermal_slew())
+        .with_thermal_config(config::board_config().thermal());
+    let registration = super::BoardRegistration { state_rx };
+    Ok((Box::new(board), registration))
+}
+
+inventory::submit! {
+    VirtualBoardDescriptor {
+        device_type: "sim_board",
+        name: "Simulated Board",
+        create_fn: || Box::pin(create_sim_board()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_settles_at_steady_state() {
+        let mut model = ThermalModel::new(AMBIENT_C);
+        let steady = model.steady_state_c(525.0, 100);
+        for _ in 0..300 {
+            model.step(525.0, 100, Duration::from_secs(1));
+        }
+        assert!((model.temperature_c() - steady).abs() < 0.1);
+
+        // More fan cools, more frequency heats
+        assert!(model.steady_state_c(525.0, 50) > steady);
+        assert!(model.steady_state_c(575.0, 100) > steady);
+        assert!(model.steady_state_c(0.0, 100) < steady);
+    }
+
+    fn temperature(state_rx: &watch::Receiver<BoardState>, sensor: &str) -> f32 {
+        state_rx
+            .borrow()
+            .temperatures
+            .iter()
+            .find(|t| t.name == sensor)
+            .and_then(|t| t.temperature_c)
+            .unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fan_target_and_derating_drive_temperature() {
+        let (state_tx, state_rx) = watch::channel(BoardState::default());
+        let derating: DeratingCurve = "70:300".parse().unwrap();
+        let mut board = SimBoard::new("sim-test".into(), derating, state_tx);
+        board.create_hash_threads().await.unwrap();
+
+        // Starved of airflow the chip overheats and is derated to 300 MHz
+        board.set_fan_target(FAN_NAME, Some(30)).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(600)).await;
+        let model = ThermalModel::new(AMBIENT_C);
+        let derated = model.steady_state_c(300.0, 30);
+        assert!((temperature(&state_rx, "asic") - derated).abs() < 0.5);
+        {
+            let state = state_rx.borrow();
+            assert_eq!(state.fans[0].percent, Some(30));
+            assert_eq!(state.fans[0].target_percent, Some(30));
+        }
+
+        // Back on automatic the fan runs flat out and the derating lifts
+        board.set_fan_target(FAN_NAME, None).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(600)).await;
+        let restored = model.steady_state_c(525.0, 100);
+        assert!((temperature(&state_rx, "asic") - restored).abs() < 0.5);
+        assert_eq!(state_rx.borrow().fans[0].target_percent, None);
+
+        assert!(board.set_fan_target("fan2", Some(50)).await.is_err());
+        board.shutdown().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn thermal_slew_eases_fan_onto_target() {
+        let (state_tx, state_rx) = watch::channel(BoardState::default());
+        let mut board = SimBoard::new("sim-test".into(), DeratingCurve::default(), state_tx)
+            .with_thermal_slew(Some(ThermalSlew::new(30.0).unwrap()));
+        board.create_hash_threads().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(state_rx.borrow().fans[0].percent, Some(100));
+
+        // 30 °C/min is a percent of fan duty a second
+        board.set_fan_target(FAN_NAME, Some(30)).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        let percent = state_rx.borrow().fans[0].percent.unwrap();
+        assert!((85..=95).contains(&percent), "fan at {percent}%");
+
+        tokio::time::sleep(Duration::from_secs(120)).await;
+        assert_eq!(state_rx.borrow().fans[0].percent, Some(30));
+        board.shutdown().await.unwrap();
+    }
+
+    /// Times the asic sensor climbs through `threshold_c` over `hours`.
+    async fn crossings(
+        state_rx: &watch::Receiver<BoardState>,
+        threshold_c: f32,
+        hours: u64,
+    ) -> usize {
+        let mut crossings = 0;
+        let mut above = false;
+        for _ in 0..hours * 3600 {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            let now_above = state_rx
+                .borrow()
+                .temperatures
+                .iter()
+                .any(|t| t.name == "asic" && t.temperature_c >= Some(threshold_c));
+            crossings += usize::from(now_above && !above);
+            above = now_above;
+        }
+        crossings
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn overshoot_margin_sets_how_often_derating_cycles() {
+        // At 60% fan the chip overheats at full frequency and cools below
+        // the band at the derated one, so it cycles between the two
+        let run = |overshoot_c| async move {
+            let (state_tx, state_rx) = watch::channel(BoardState::default());
+            let mut board = SimBoard::new("sim-test".into(), "70:300".parse().unwrap(), state_tx)
+                .with_thermal_config(ThermalConfig {
+                    overshoot_c,
+                    ..Default::default()
+                });
+            board.create_hash_threads().await.unwrap();
+            board.set_fan_target(FAN_NAME, Some(60)).await.unwrap();
+            let cycles = crossings(&state_rx, 70.0, 2).await;
+            let final_c = temperature(&state_rx, "asic");
+            board.shutdown().await.unwrap();
+            (cycles, final_c)
+        };
+
+        let (cycles, _) = run(3.0).await;
+        assert!(cycles > 50, "{cycles} cycles in two hours");
+
+        // The derated chip never cools past a wider margin: it stays put
+        let model = ThermalModel::new(AMBIENT_C);
+        let (cycles, final_c) = run(8.0).await;
+        assert!(cycles <= 1, "{cycles} cycles in two hours");
+        assert!((final_c - model.steady_state_c(300.0, 60)).abs() < 0.5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn frequency_override_outlasts_profile_switch() {
+        let (state_tx, state_rx) = watch::channel(BoardState::default());
+        let mut board = SimBoard::new("sim-test".into(), DeratingCurve::default(), state_tx);
+        board.create_hash_threads().await.unwrap();
+        let model = ThermalModel::new(AMBIENT_C);
+
+        board.set_frequency(Some(400.0)).await.unwrap();
+        board.apply_profile(Profile::Turbo).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(600)).await;
+        let slowed = model.steady_state_c(400.0, 100);
+        assert!((temperature(&state_rx, "asic") - slowed).abs() < 0.5);
+
+        // Cleared, the profile's frequency returns
+        board.set_frequency(None).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(600)).await;
+        let turbo = model.steady_state_c(575.0, 100);
+        assert!((temperature(&state_rx, "asic") - turbo).abs() < 0.5);
+
+        assert!(board.set_frequency(Some(900.0)).await.is_err());
+        board.shutdown().await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn disabled_board_cools_to_idle() {
+        let (state_tx, state_rx) = watch::channel(BoardState::default());
+        let mut board = SimBoard::new("sim-test".into(), DeratingCurve::default(), state_tx);
+        board.create_hash_threads().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(300)).await;
+        let hashing = temperature(&state_rx, "asic");
+
+        board.disable_hash_threads().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(300)).await;
+        let idle = temperature(&state_rx, "asic");
+        assert!(idle < hashing);
+
+        let saving = state_rx.borrow().idle_power.saving_w.unwrap();
+        assert!((saving - ThermalModel::W_PER_MHZ * 525.0).abs() < 0.5);
+    }
+}
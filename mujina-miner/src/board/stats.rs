@@ -0,0 +1,356 @@
+//! Board statistics aggregation.
+//!
+//! A board's telemetry comes from several places: its sensor polling loop
+//! reads fans, temperatures, and power, while each hash thread reports its
+//! own hashrate and chip statistics. The aggregator collects the latest of
+//! each and publishes them together as one [`BoardState`] on a timer, so
+//! it is the only writer of the board's state channel and API readers
+//! always see a consistent snapshot.
+//!
+//! Sensor readings are stamped as they arrive. If the polling loop stops
+//! (it died, or hangs on a bus), the board's state shows its sensors as
+//! stale with their values null, rather than the last readings forever.
+
+use std::time::Duration;
+
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+
+use crate::api_client::types::{
+    BoardState, ChipNonceReport, Fan, FirmwareUpdateState, IdlePower, JobDispatchStats,
+    PowerMeasurement, TemperatureSensor, ThreadState,
+};
+use crate::asic::hash_thread::HashThreadStatus;
+use crate::tracing::prelude::*;
+use crate::types::{ReadingReceiver, ReadingSender, reading_channel};
+
+/// How often the aggregated state is published.
+pub const PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Age past which sensor readings are shown as stale.
+///
+/// Several times the slowest polling loop's interval.
+pub const SENSORS_MAX_AGE: Duration = Duration::from_secs(30);
+
+/// Readings from a board's own sensors, as gathered by its polling loop.
+#[derive(Clone, Debug, Default)]
+pub struct SensorReadings {
+    pub fans: Vec<Fan>,
+    pub temperatures: Vec<TemperatureSensor>,
+    pub powers: Vec<PowerMeasurement>,
+    pub idle_power: IdlePower,
+}
+
+impl SensorReadings {
+    /// The same sensors with nothing known about them.
+    fn blanked(self) -> Self {
+        Self {
+            fans: self
+                .fans
+                .into_iter()
+                .map(|fan| Fan {
+                    rpm: None,
+                    percent: None,
+                    ..fan
+                })
+                .collect(),
+            temperatures: self
+                .temperatures
+                .into_iter()
+                .map(|sensor| TemperatureSensor {
+                    temperature_c: None,
+                    ..sensor
+                })
+                .collect(),
+            powers: self
+                .powers
+                .into_iter()
+                .map(|power| PowerMeasurement {
+                    name: power.name,
+                    voltage_v: None,
+                    current_a: None,
+                    power_w: None,
+                })
+                .collect(),
+            idle_power: self.idle_power,
+        }
+    }
+}
+
+/// A hash thread's status feed, as registered with the aggregator.
+struct ThreadFeed {
+    name: String,
+    status: watch::Receiver<HashThreadStatus>,
+    job_dispatch: watch::Receiver<Option<JobDispatchStats>>,
+}
+
+/// Feeds a board's stats aggregator.
+///
+/// Clones feed the same aggregator, which runs until every handle is
+/// dropped.
+#[derive(Clone)]
+pub struct BoardStatsHandle {
+    sensors: ReadingSender<SensorReadings>,
+    firmware_update: watch::Sender<Option<FirmwareUpdateState>>,
+    threads: mpsc::UnboundedSender<ThreadFeed>,
+}
+
+impl BoardStatsHandle {
+    /// Replace the board's sensor readings.
+    pub fn update_sensors(&self, readings: SensorReadings) {
+        self.sensors.publish(readings);
+    }
+
+    /// Replace the board's firmware update progress, or clear it with `None`.
+    pub fn update_firmware(&self, progress: Option<FirmwareUpdateState>) {
+        self.firmware_update.send_replace(progress);
+    }
+
+    /// Register a hash thread, returning where it publishes its status
+    /// and its job dispatch timing.
+    ///
+    /// The thread drops out of the board's state once the status sender
+    /// is dropped, e.g. when the thread exits.
+    pub fn register_thread(
+        &self,
+        name: impl Into<String>,
+    ) -> (
+        watch::Sender<HashThreadStatus>,
+        watch::Sender<Option<JobDispatchStats>>,
+    ) {
+        let (status_tx, status) = watch::channel(HashThreadStatus::default());
+        let (job_dispatch_tx, job_dispatch) = watch::channel(None);
+        let _ = self.threads.send(ThreadFeed {
+            name: name.into(),
+            status,
+            job_dispatch,
+        });
+        (status_tx, job_dispatch_tx)
+    }
+}
+
+/// Spawn the aggregator for a board.
+///
+/// `identity` supplies the board's name, model, and serial; the rest of
+/// each published state comes from the handle's feeds.
+pub fn spawn(
+    identity: BoardState,
+    state_tx: watch::Sender<BoardState>,
+) -> (BoardStatsHandle, JoinHandle<()>) {
+    let (sensors, sensors_rx) = reading_channel();
+    let (firmware_update, firmware_update_rx) = watch::channel(None);
+    let (threads, threads_rx) = mpsc::unbounded_channel();
+    let aggregator = Aggregator {
+        identity,
+        sensors: sensors_rx,
+        firmware_update: firmware_update_rx,
+        threads: Vec::new(),
+        sensors_stale: false,
+    };
+    let task = tokio::spawn(aggregator.run(threads_rx, state_tx).in_current_span());
+    (
+        BoardStatsHandle {
+            sensors,
+            firmware_update,
+            threads,
+        },
+        task,
+    )
+}
+
+struct Aggregator {
+    identity: BoardState,
+    sensors: ReadingReceiver<SensorReadings>,
+    firmware_update: watch::Receiver<Option<FirmwareUpdateState>>,
+    threads: Vec<ThreadFeed>,
+    /// Whether the last snapshot showed the sensors as stale
+    sensors_stale: bool,
+}
+
+impl Aggregator {
+    async fn run(
+        mut self,
+        mut registrations: mpsc::UnboundedReceiver<ThreadFeed>,
+        state_tx: watch::Sender<BoardState>,
+    ) {
+        let mut interval = tokio::time::interval(PUBLISH_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    state_tx.send_replace(self.snapshot());
+                }
+                feed = registrations.recv() => match feed {
+                    Some(feed) => self.threads.push(feed),
+                    // Every handle is gone; publish what we have and stop
+                    None => break,
+                },
+            }
+        }
+        state_tx.send_replace(self.snapshot());
+    }
+
+    /// Merge the latest feeds into one board state.
+    fn snapshot(&mut self) -> BoardState {
+        // A closed feed means the thread has exited
+        self.threads.retain(|t| t.status.has_changed().is_ok());
+
+        let stale = self.sensors.is_stale(SENSORS_MAX_AGE);
+        if stale != self.sensors_stale {
+            self.sensors_stale = stale;
+            if stale {
+                warn!(board = %self.identity.name, "Sensor readings stopped");
+            } else {
+                info!(board = %self.identity.name, "Sensor readings resumed");
+            }
+        }
+        let sensors = self
+            .sensors
+            .latest()
+            .map(|reading| reading.value)
+            .unwrap_or_default();
+        let sensors = if stale { sensors.blanked() } else { sensors };
+        BoardState {
+            sensors_stale: stale,
+            fans: sensors.fans,
+            temperatures: sensors.temperatures,
+            powers: sensors.powers,
+            idle_power: sensors.idle_power,
+            firmware_update: self.firmware_update.borrow().clone(),
+            threads: self
+                .threads
+                .iter()
+                .map(|t| {
+                    let status = t.status.borrow();
+                    ThreadState {
+                        name: t.name.clone(),
+                        hashrate: status.hashrate.into(),
+                        is_active: status.is_active,
+                        nonces: status.chip_shares_found,
+                        hardware_errors: status.hardware_errors,
+                        chip_resets: status.chip_resets,
+                        quarantined: status.quarantined,
+                        link_errors: status.link_errors,
+                        link_degraded: status.link_degraded,
+                        job_dispatch: t.job_dispatch.borrow().clone(),
+                    }
+                })
+                .collect(),
+            nonce_reports: self
+                .threads
+                .iter()
+                .flat_map(|t| {
+                    t.status
+                        .borrow()
+                        .nonce_map
+                        .iter()
+                        .map(|report| ChipNonceReport {
+                            thread: t.name.clone(),
+                            ..report.clone()
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect(),
+            ..self.identity.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::HashRate;
+
+    fn identity() -> BoardState {
+        BoardState {
+            name: "test-board".into(),
+            model: "Test".into(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn publishes_sensors_and_threads_together() {
+        let (state_tx, mut state_rx) = watch::channel(identity());
+        let (handle, _task) = spawn(identity(), state_tx);
+
+        handle.update_sensors(SensorReadings {
+            temperatures: vec![TemperatureSensor {
+                name: "asic".into(),
+                temperature_c: Some(55.0),
+            }],
+            ..Default::default()
+        });
+        let (status_tx, _job_dispatch_tx) = handle.register_thread("thread-0");
+        status_tx.send_modify(|s| {
+            s.hashrate = HashRate::from_gigahashes(500.0);
+            s.is_active = true;
+        });
+
+        tokio::time::sleep(PUBLISH_INTERVAL * 2).await;
+        state_rx.changed().await.unwrap();
+        let state = state_rx.borrow_and_update().clone();
+        assert_eq!(state.name, "test-board");
+        assert_eq!(state.temperatures[0].temperature_c, Some(55.0));
+        assert_eq!(state.threads.len(), 1);
+        assert_eq!(state.threads[0].name, "thread-0");
+        assert_eq!(state.threads[0].hashrate, 500_000_000_000);
+        assert!(state.threads[0].is_active);
+
+        // An exited thread drops out at the next publish
+        drop(status_tx);
+        tokio::time::sleep(PUBLISH_INTERVAL * 2).await;
+        assert!(state_rx.borrow_and_update().threads.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stops_once_handles_are_dropped() {
+        let (state_tx, state_rx) = watch::channel(identity());
+        let (handle, task) = spawn(identity(), state_tx);
+
+        handle.update_sensors(SensorReadings {
+            fans: vec![Fan {
+                name: "fan".into(),
+                rpm: Some(3000),
+                percent: Some(40),
+                target_percent: None,
+            }],
+            ..Default::default()
+        });
+        drop(handle);
+        task.await.unwrap();
+
+        // Final snapshot carries the last readings
+        assert_eq!(state_rx.borrow().fans[0].rpm, Some(3000));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sensors_not_updated_show_as_stale() {
+        let (state_tx, mut state_rx) = watch::channel(identity());
+        let (handle, _task) = spawn(identity(), state_tx);
+
+        handle.update_sensors(SensorReadings {
+            temperatures: vec![TemperatureSensor {
+                name: "asic".into(),
+                temperature_c: Some(55.0),
+            }],
+            ..Default::default()
+        });
+        tokio::time::sleep(PUBLISH_INTERVAL * 2).await;
+        let state = state_rx.borrow_and_update().clone();
+        assert!(!state.sensors_stale);
+        assert_eq!(state.temperatures[0].temperature_c, Some(55.0));
+
+        // The polling loop has stopped; the sensor stays listed, unread
+        tokio::time::sleep(SENSORS_MAX_AGE).await;
+        let state = state_rx.borrow_and_update().clone();
+        assert!(state.sensors_stale);
+        assert_eq!(state.temperatures[0].name, "asic");
+        assert_eq!(state.temperatures[0].temperature_c, None);
+
+        handle.update_sensors(SensorReadings::default());
+        tokio::time::sleep(PUBLISH_INTERVAL * 2).await;
+        assert!(!state_rx.borrow_and_update().sensors_stale);
+    }
+}
@@ -0,0 +1,79 @@
+//! Version and build details embedded at compile time.
+//!
+//! `build.rs` records the git commit, build time and enabled features;
+//! this module turns them into the identification the miner presents to
+//! pools (the `mining.subscribe` user agent) and reports over the API.
+
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+use crate::api_client::types::BuildInfo;
+
+/// Crate version.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Short hash of the commit built from, when known.
+pub const GIT_HASH: Option<&str> = option_env!("MUJINA_GIT_HASH");
+
+/// Build time in Unix seconds.
+const BUILD_TIMESTAMP: &str = env!("MUJINA_BUILD_TIMESTAMP");
+
+/// Enabled Cargo features, comma separated.
+const FEATURES: &str = env!("MUJINA_FEATURES");
+
+/// User agent sent to pools unless one is configured.
+///
+/// `mujina-miner/<version>`, with the commit appended when known, e.g.
+/// `mujina-miner/0.1.0+3f2a9c1d4e5b`.
+pub fn default_user_agent() -> String {
+    match GIT_HASH {
+        Some(hash) => format!("mujina-miner/{VERSION}+{hash}"),
+        None => format!("mujina-miner/{VERSION}"),
+    }
+}
+
+/// Build time as RFC 3339, if it could be recorded.
+pub fn build_date() -> Option<String> {
+    let secs: i64 = BUILD_TIMESTAMP.parse().ok()?;
+    OffsetDateTime::from_unix_timestamp(secs)
+        .ok()?
+        .format(&Rfc3339)
+        .ok()
+}
+
+/// Enabled Cargo features.
+pub fn features() -> Vec<String> {
+    FEATURES
+        .split(',')
+        .filter(|f| !f.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Build details for the API, with the user agent actually in use.
+pub fn build_info(user_agent: &str) -> BuildInfo {
+    BuildInfo {
+        version: VERSION.to_owned(),
+        git_hash: GIT_HASH.map(str::to_owned),
+        build_date: build_date(),
+        features: features(),
+        user_agent: user_agent.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_user_agent_names_version() {
+        let agent = default_user_agent();
+        assert!(agent.starts_with(&format!("mujina-miner/{VERSION}")));
+        assert!(!agent.contains(char::is_whitespace));
+    }
+
+    #[test]
+    fn build_date_is_rfc3339() {
+        let date = build_date().expect("build.rs records a timestamp");
+        assert!(OffsetDateTime::parse(&date, &Rfc3339).is_ok(), "{date}");
+    }
+}
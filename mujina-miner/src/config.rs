@@ -1,101 +1,1472 @@
 //! Configuration management for mujina-miner.
 //!
-//! This module handles loading and validating configuration from TOML files,
-//! environment variables, and command-line arguments. It supports hot-reload
-//! via file watching.
+//! Settings are layered, each layer overriding the one before it:
+//!
+//! 1. A TOML file: the path given with `--config`, else `MUJINA_CONFIG`,
+//!    else [`DEFAULT_CONFIG_PATH`] if it exists
+//! 2. Environment variables
+//! 3. Command-line flags
+//!
+//! Every setting is optional. Anything left unset by all layers falls back
+//! to the built-in default of the component that uses it. See
+//! `docs/configuration.md` for the full list of settings.
 
-use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::{AlertRule, notify};
+use crate::api_client::types::Profile;
+use crate::asic::{
+    bm13xx::job_watchdog::DEFAULT_NONCE_TIMEOUT, bm13xx::thread::DEFAULT_NTIME_ROLL_INTERVAL,
+    derating::DeratingCurve, slew::ThermalSlew, thermal::ThermalConfig, warmup::WarmupConfig,
+};
+use crate::job_source::SuggestStrategy;
+use crate::stratum_v1::PoolQuirks;
+use crate::transport::{NetworkEndpoint, network::DEFAULT_RECONNECT_GRACE};
+use crate::types::{Difficulty, HashRate};
+
+/// Config file read when no other path is given, if it exists.
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/mujina/mujina.toml";
+
+/// Directory burn-in reports are kept in unless another is configured.
+pub const DEFAULT_BURN_IN_DIR: &str = "/var/lib/mujina/burn-in";
+
+/// Command-line usage for the daemon.
+pub const USAGE: &str = "\
+Usage: mujina-minerd [options]
+
+Options:
+  --config <path>         Config file (default: /etc/mujina/mujina.toml)
+  --pool-url <url>        Pool address, e.g. stratum+tcp://host:3333
+  --pool-user <user>      Pool username; {board_serial} is replaced per board
+  --pool-pass <pass>      Pool password
+  --user-agent <agent>    User agent sent to the pool (default mujina-miner/<version>)
+  --ntime-correction      Start lagging jobs at the pool's estimated clock
+  --forced-difficulty <d> Hash at this share difficulty, e.g. 0.01 or 1K (testing)
+  --pool-quirks <list>    Pool quirks to work around, e.g. no_configure,version_bits_always
+  --suggest-difficulty <strategy>
+                          Difficulty suggestions: material_change, share_rate or never
+  --offline-queue-secs <secs>
+                          Hold shares found while the pool is unreachable this long
+  --solo-url <url>        Solo pool mined on a few threads alongside the pool
+  --solo-user <user>      Solo pool username, usually a payout address
+  --solo-pass <pass>      Solo pool password
+  --solo-threads <n>      Threads given to the solo pool (default 1)
+  --solo-suggest-difficulty <strategy>
+                          Difficulty suggestions to the solo pool
+  --solo-offline-queue-secs <secs>
+                          Hold shares found while the solo pool is unreachable
+  --api-listen <addr>     API listen address, with or without port
+  --api-socket <path>     Also serve the API on this UNIX domain socket
+  --log-level <filter>    Log filter, e.g. info or mujina_miner=debug
+  --decision-log <path>   Record scheduler decisions to this file for replay
+  --share-audit <n>       Keep an audit trail of the last n shares
+  --share-proofs <path>   Append a verifiable record of each accepted share to this file
+  --idle-backfill         Mine dummy work to keep chips warm while no pool has work
+  --no-usb                Disable USB board discovery
+  --simulate              Add a simulated board (development without hardware)
+  --network-boards <list> Boards served over the network, e.g. agent@10.0.0.5:4029
+  --network-grace-secs <secs>
+                          Keep an unreachable network board this long (default 30)
+  --agent                 Serve the local board to a remote controller instead of mining
+  --agent-listen <addr>   Agent listen address, with or without port (default 127.0.0.1:4029)
+  --agent-token <token>   Shared secret between agents and their controller
+  --proxy-listen <addr>   Serve jobs to downstream Stratum miners on this address
+  --derating <table>      Thermal derating, e.g. 70:450,80:350
+  --warmup-secs <secs>    Enable staged warm-up with this stage length
+  --max-temp-slew <c>     Ease frequency and fan changes to at most this many °C/min
+  --thermal-tick-secs <secs>
+                          Read chip temperatures this often (default 5)
+  --thermal-adjust-secs <secs>
+                          Step eased frequency and fan changes this often (default: every tick)
+  --thermal-overshoot <c> Cool this far below a derating band before leaving it (default 3)
+  --ambient-reference <c> Derate earlier by how far a pushed room temperature exceeds this
+  --nonce-timeout-secs <secs>
+                          Resend a job the chip hasn't answered after this long
+  --ntime-roll-secs <secs>
+                          Resend the current job with a fresh ntime this often (default 1)
+  --profile <name>        Operating profile: quiet, balanced or turbo
+  --capture-dir <path>    Capture each board's data serial link into this directory
+  --burn-in-dir <path>    Keep burn-in reports in this directory
+  --list-devices          Print detected hash boards as JSON and exit
+  -h, --help              Show this help
+";
+
+/// Errors from loading configuration.
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("help requested")]
+    Help,
+
+    #[error("unknown option: {0}")]
+    UnknownOption(String),
+
+    #[error("option {0} requires a value")]
+    MissingValue(String),
+
+    #[error("invalid value {value:?} for {key}: {reason}")]
+    InvalidValue {
+        key: String,
+        value: String,
+        reason: String,
+    },
+
+    #[error("failed to read {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("failed to parse {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error("failed to write {path}: {source}")]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
 
 /// Main configuration structure for the miner.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
     /// Daemon configuration
     pub daemon: DaemonConfig,
 
     /// Pool configuration
-    pub pools: Vec<PoolConfig>,
+    pub pool: PoolConfig,
 
-    /// Hardware configuration
-    pub hardware: HardwareConfig,
+    /// Solo pool mined on a share of the threads
+    pub solo: SoloConfig,
 
     /// API server configuration
     pub api: ApiConfig,
+
+    /// Settings applied to every board
+    pub boards: BoardConfig,
+
+    /// Agent mode, serving the local board to a remote controller
+    pub agent: AgentConfig,
+
+    /// Stratum v1 server for downstream miners
+    pub proxy: ProxyConfig,
+
+    /// Alert rules and where alerts are sent; file only
+    pub alerts: AlertsConfig,
+
+    /// File the configuration is read from and saved to, set by
+    /// [`Config::load`]; never part of the file itself
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
 }
 
 /// Daemon process configuration.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct DaemonConfig {
-    /// PID file location
-    pub pid_file: Option<PathBuf>,
+    /// Log filter in `RUST_LOG` syntax
+    pub log_level: Option<String>,
+
+    /// File to record scheduler decisions in, for replay
+    pub decision_log: Option<PathBuf>,
+
+    /// Number of recent shares to keep an audit trail for; unset or zero
+    /// disables the share audit log
+    pub share_audit: Option<usize>,
 
-    /// Log level
-    pub log_level: String,
+    /// File to append a proof of every accepted share to, for
+    /// `mujina-cli verify-shares`
+    pub share_proofs: Option<PathBuf>,
 
-    /// Use systemd notification
-    #[serde(default)]
-    pub systemd: bool,
+    /// Mine dummy work while no pool has work, keeping the chips warm
+    /// (default false)
+    pub idle_backfill: Option<bool>,
 }
 
 /// Pool connection configuration.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct PoolConfig {
-    /// Pool URL (stratum+tcp://...)
-    pub url: String,
+    /// Pool URL (stratum+tcp://...); without one the miner uses dummy work
+    pub url: Option<String>,
 
     /// Worker name
-    pub worker: String,
+    pub user: Option<String>,
 
-    /// Password (if required)
+    /// Password
     pub password: Option<String>,
 
-    /// Priority (lower is higher priority)
-    #[serde(default)]
-    pub priority: u32,
+    /// User agent sent in `mining.subscribe`; defaults to
+    /// `mujina-miner/<version>` from the build info
+    pub user_agent: Option<String>,
+
+    /// Move job ntime that lags the pool's clock (as estimated from
+    /// earlier jobs) forward to it (default false)
+    pub ntime_correction: Option<bool>,
+
+    /// Share difficulty to hash at instead of the pool's, for testing;
+    /// only shares meeting the pool's target are submitted
+    pub forced_difficulty: Option<Difficulty>,
+
+    /// Pool quirks to work around, on top of the profile built in for
+    /// known pools, e.g. `["version_bits_always"]`
+    pub quirks: Option<PoolQuirks>,
+
+    /// When and what share difficulty to suggest to the pool (default
+    /// `material_change`)
+    pub suggest_difficulty: Option<SuggestStrategy>,
+
+    /// Seconds to hold shares found while the pool is unreachable, to
+    /// submit on reconnect; unset drops them with the connection's work
+    pub offline_queue_secs: Option<u64>,
 }
 
-/// Hardware configuration.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct HardwareConfig {
-    /// Temperature limits
-    pub temp_limit: f32,
+/// Solo pool configuration, for lottery mining alongside the pool.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SoloConfig {
+    /// Solo pool URL (stratum+tcp://...); unset disables solo mining
+    pub url: Option<String>,
+
+    /// Worker name, usually the payout address
+    pub user: Option<String>,
+
+    /// Password
+    pub password: Option<String>,
+
+    /// Threads to mine solo jobs on when a pool is also configured
+    /// (default 1); at least one thread always stays with the pool
+    pub threads: Option<usize>,
 
-    /// Fan control settings
-    pub fan_min_rpm: u32,
-    pub fan_max_rpm: u32,
+    /// When and what share difficulty to suggest to the solo pool
+    /// (default `material_change`)
+    pub suggest_difficulty: Option<SuggestStrategy>,
 
-    /// Power limits
-    pub power_limit: Option<f32>,
+    /// Seconds to hold shares, and blocks, found while the solo pool is
+    /// unreachable, to submit on reconnect; unset drops them
+    pub offline_queue_secs: Option<u64>,
 }
 
 /// API server configuration.
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ApiConfig {
-    /// Listen address
-    pub listen: String,
+    /// Listen address, with or without a port
+    pub listen: Option<String>,
+
+    /// UNIX domain socket to also serve the API on, for local use
+    pub socket: Option<PathBuf>,
+}
 
-    /// Enable TLS
-    #[serde(default)]
-    pub tls: bool,
+/// Agent mode configuration.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentConfig {
+    /// Serve the local board to a remote controller instead of mining
+    /// (default false)
+    pub enabled: Option<bool>,
 
-    /// TLS certificate path
-    pub cert_path: Option<PathBuf>,
+    /// Listen address, with or without a port
+    pub listen: Option<String>,
+}
 
-    /// TLS key path
-    pub key_path: Option<PathBuf>,
+/// Proxy mode configuration.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProxyConfig {
+    /// Listen address for downstream miners, with or without a port;
+    /// the proxy is off when unset
+    pub listen: Option<String>,
+}
+
+/// Alerting configuration.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertsConfig {
+    /// Rules to alert on, e.g. `temp > 80 for 5m`; alerting is off when
+    /// unset or empty
+    pub rules: Option<Vec<AlertRule>>,
+
+    /// URLs each alert is posted to as JSON
+    pub webhooks: Option<Vec<String>>,
+
+    /// MQTT broker and topic alerts are published to, as
+    /// `mqtt://[user[:password]@]host[:port]/topic`
+    pub mqtt: Option<String>,
+
+    /// Hashrate percentage rules compare against; unset uses the highest
+    /// hashrate seen
+    pub expected_hashrate: Option<HashRate>,
+
+    /// Number of recent alerts the API keeps (default 100)
+    pub history: Option<usize>,
+}
+
+/// Board configuration.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoardConfig {
+    /// Discover USB boards (default true)
+    pub usb_discovery: Option<bool>,
+
+    /// Add a simulated board with modelled sensors (default false)
+    pub simulate: Option<bool>,
+
+    /// Boards served over the network, as `kind@host:port`
+    pub network: Option<Vec<NetworkEndpoint>>,
+
+    /// Seconds an unreachable network board is kept for its link to come
+    /// back (default 30)
+    pub network_grace_secs: Option<u64>,
+
+    /// Shared secret agents authenticate their controller with; the
+    /// controller presents it to `agent@` network boards
+    pub agent_token: Option<String>,
+
+    /// Thermal derating table, `temp_c:max_mhz` pairs
+    pub derating: Option<String>,
+
+    /// Warm-up stage length in seconds; unset disables warm-up
+    pub warmup_secs: Option<u64>,
+
+    /// Fastest temperature change, in °C per minute, that frequency and
+    /// fan changes may cause; unset applies them at once
+    pub max_temp_slew: Option<f32>,
+
+    /// Seconds between chip temperature reads (default 5)
+    pub thermal_tick_secs: Option<u64>,
+
+    /// Seconds between steps of eased frequency and fan changes (default
+    /// every tick)
+    pub thermal_adjust_secs: Option<u64>,
+
+    /// °C below a derating band's threshold the temperature must fall
+    /// before the band is left (default 3)
+    pub thermal_overshoot: Option<f32>,
+
+    /// Room temperature, °C, the derating table was written for; while
+    /// an external sensor reports a warmer room, derating starts that
+    /// much earlier. Unset ignores the room temperature
+    pub ambient_reference: Option<f32>,
+
+    /// Seconds a chip gets to report a nonce for a new job (default 30)
+    pub nonce_timeout_secs: Option<u64>,
+
+    /// Seconds between re-sends of the current job with a rolled ntime
+    /// (default 1)
+    pub ntime_roll_secs: Option<u64>,
+
+    /// Operating profile (default balanced)
+    pub profile: Option<Profile>,
+
+    /// Directory to capture each board's data serial link into, for
+    /// `mujina-dissect`; unset disables capture
+    pub capture_dir: Option<PathBuf>,
+
+    /// Directory burn-in reports are kept in, one per board serial
+    pub burn_in_dir: Option<PathBuf>,
 }
 
 impl Config {
-    /// Load configuration from the default location.
-    pub fn load() -> anyhow::Result<Self> {
-        // TODO: Implement config loading from /etc/mujina/mujina.toml
-        // and ~/.config/mujina/mujina.toml with proper merging
-        unimplemented!("Config loading not yet implemented")
+    /// Load all layers: config file, environment, then `args` (command-line
+    /// flags, without the program name).
+    pub fn load<I>(args: I) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let (cli_path, cli) = Self::from_args(args)?;
+
+        let path = cli_path.or_else(|| std::env::var_os("MUJINA_CONFIG").map(PathBuf::from));
+        let mut config = match &path {
+            Some(path) => Self::load_from(path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::load_from(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => Self::default(),
+        };
+
+        config.merge(Self::from_env()?);
+        config.merge(cli);
+        config.path = Some(path.unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH)));
+        Ok(config)
     }
 
     /// Load configuration from a specific file.
-    pub fn load_from(_path: &Path) -> anyhow::Result<Self> {
-        // TODO: Implement TOML parsing
-        unimplemented!("Config loading not yet implemented")
+    pub fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let config: Self = toml::from_str(&text).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        config.boards.validate()?;
+        config.alerts.validate()?;
+        Ok(config)
+    }
+
+    /// Check this configuration and write it to `path`, replacing the
+    /// file whole.
+    ///
+    /// The new file is written beside the old and renamed over it, so a
+    /// failed write leaves the old one in place. It holds passwords, so
+    /// it is created readable by its owner only.
+    pub fn save_to(&self, path: &Path) -> Result<(), ConfigError> {
+        self.boards.validate()?;
+        self.alerts.validate()?;
+        let write_error = |source| ConfigError::Write {
+            path: path.to_path_buf(),
+            source,
+        };
+        let text = toml::to_string(self).map_err(|e| write_error(std::io::Error::other(e)))?;
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(dir).map_err(write_error)?;
+        }
+        let temp = path.with_extension("toml.new");
+        // A temp file left by an earlier failed save keeps its mode when
+        // opened, so start from a fresh one
+        match std::fs::remove_file(&temp) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(write_error(e)),
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&temp)
+            .map_err(write_error)?;
+        file.write_all(text.as_bytes())
+            .and_then(|()| file.sync_all())
+            .map_err(write_error)?;
+        std::fs::rename(&temp, path).map_err(write_error)
+    }
+
+    /// This configuration with its secrets left out: the pool and solo
+    /// passwords, the agent token, and the alert destinations, whose URLs
+    /// may carry credentials.
+    ///
+    /// Safe to hand out, and to copy to another unit, which keeps its own
+    /// secrets (see [`Config::keep_secrets`]).
+    pub fn redacted(&self) -> Self {
+        let mut config = self.clone();
+        config.pool.password = None;
+        config.solo.password = None;
+        config.boards.agent_token = None;
+        config.alerts.webhooks = None;
+        config.alerts.mqtt = None;
+        config.path = None;
+        config
+    }
+
+    /// Fill in the secrets this configuration leaves out from `current`.
+    ///
+    /// A secret set empty (`password = ""`, `webhooks = []`) is cleared
+    /// instead, so an import can drop one as well as keep or replace it.
+    pub fn keep_secrets(&mut self, current: &Self) {
+        fn keep<T: Clone + Default + PartialEq>(secret: &mut Option<T>, current: &Option<T>) {
+            match secret {
+                None => secret.clone_from(current),
+                Some(value) if *value == T::default() => *secret = None,
+                Some(_) => {}
+            }
+        }
+
+        keep(&mut self.pool.password, &current.pool.password);
+        keep(&mut self.solo.password, &current.solo.password);
+        keep(&mut self.boards.agent_token, &current.boards.agent_token);
+        keep(&mut self.alerts.webhooks, &current.alerts.webhooks);
+        keep(&mut self.alerts.mqtt, &current.alerts.mqtt);
+    }
+
+    /// Read the environment variable layer.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_vars(|key| std::env::var(key).ok())
+    }
+
+    /// Build the environment layer from a variable lookup.
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let share_audit = var("MUJINA_SHARE_AUDIT")
+            .map(|v| parse_share_audit("MUJINA_SHARE_AUDIT", &v))
+            .transpose()?;
+        let warmup_secs = var("MUJINA_WARMUP_SECS")
+            .map(|v| parse_secs("MUJINA_WARMUP_SECS", &v))
+            .transpose()?;
+        let max_temp_slew = var("MUJINA_MAX_TEMP_SLEW")
+            .map(|v| parse_celsius("MUJINA_MAX_TEMP_SLEW", &v))
+            .transpose()?;
+        let thermal_tick_secs = var("MUJINA_THERMAL_TICK_SECS")
+            .map(|v| parse_secs("MUJINA_THERMAL_TICK_SECS", &v))
+            .transpose()?;
+        let thermal_adjust_secs = var("MUJINA_THERMAL_ADJUST_SECS")
+            .map(|v| parse_secs("MUJINA_THERMAL_ADJUST_SECS", &v))
+            .transpose()?;
+        let thermal_overshoot = var("MUJINA_THERMAL_OVERSHOOT")
+            .map(|v| parse_celsius("MUJINA_THERMAL_OVERSHOOT", &v))
+            .transpose()?;
+        let ambient_reference = var("MUJINA_AMBIENT_REFERENCE")
+            .map(|v| parse_celsius("MUJINA_AMBIENT_REFERENCE", &v))
+            .transpose()?;
+        let nonce_timeout_secs = var("MUJINA_NONCE_TIMEOUT_SECS")
+            .map(|v| parse_secs("MUJINA_NONCE_TIMEOUT_SECS", &v))
+            .transpose()?;
+        let ntime_roll_secs = var("MUJINA_NTIME_ROLL_SECS")
+            .map(|v| parse_secs("MUJINA_NTIME_ROLL_SECS", &v))
+            .transpose()?;
+        let forced_difficulty = var("MUJINA_POOL_FORCED_DIFFICULTY")
+            .map(|v| parse_difficulty("MUJINA_POOL_FORCED_DIFFICULTY", &v))
+            .transpose()?;
+        let quirks = var("MUJINA_POOL_QUIRKS")
+            .map(|v| parse_quirks("MUJINA_POOL_QUIRKS", &v))
+            .transpose()?;
+        let pool_suggest = var("MUJINA_POOL_SUGGEST_DIFFICULTY")
+            .map(|v| parse_suggest_strategy("MUJINA_POOL_SUGGEST_DIFFICULTY", &v))
+            .transpose()?;
+        let solo_suggest = var("MUJINA_SOLO_SUGGEST_DIFFICULTY")
+            .map(|v| parse_suggest_strategy("MUJINA_SOLO_SUGGEST_DIFFICULTY", &v))
+            .transpose()?;
+        let pool_offline_queue = var("MUJINA_POOL_OFFLINE_QUEUE_SECS")
+            .map(|v| parse_secs("MUJINA_POOL_OFFLINE_QUEUE_SECS", &v))
+            .transpose()?;
+        let solo_offline_queue = var("MUJINA_SOLO_OFFLINE_QUEUE_SECS")
+            .map(|v| parse_secs("MUJINA_SOLO_OFFLINE_QUEUE_SECS", &v))
+            .transpose()?;
+        let solo_threads = var("MUJINA_SOLO_THREADS")
+            .map(|v| parse_solo_threads("MUJINA_SOLO_THREADS", &v))
+            .transpose()?;
+        let profile = var("MUJINA_PROFILE")
+            .map(|v| parse_profile("MUJINA_PROFILE", &v))
+            .transpose()?;
+        let network = var("MUJINA_NETWORK_BOARDS")
+            .map(|v| parse_network_boards("MUJINA_NETWORK_BOARDS", &v))
+            .transpose()?;
+        let network_grace_secs = var("MUJINA_NETWORK_GRACE_SECS")
+            .map(|v| parse_secs("MUJINA_NETWORK_GRACE_SECS", &v))
+            .transpose()?;
+
+        let config = Self {
+            daemon: DaemonConfig {
+                log_level: var("RUST_LOG"),
+                decision_log: var("MUJINA_DECISION_LOG").map(PathBuf::from),
+                share_audit,
+                share_proofs: var("MUJINA_SHARE_PROOFS").map(PathBuf::from),
+                idle_backfill: var("MUJINA_IDLE_BACKFILL").map(|_| true),
+            },
+            pool: PoolConfig {
+                url: var("MUJINA_POOL_URL"),
+                user: var("MUJINA_POOL_USER"),
+                password: var("MUJINA_POOL_PASS"),
+                user_agent: var("MUJINA_USER_AGENT"),
+                ntime_correction: var("MUJINA_NTIME_CORRECTION").map(|_| true),
+                forced_difficulty,
+                quirks,
+                suggest_difficulty: pool_suggest,
+                offline_queue_secs: pool_offline_queue,
+            },
+            solo: SoloConfig {
+                url: var("MUJINA_SOLO_URL"),
+                user: var("MUJINA_SOLO_USER"),
+                password: var("MUJINA_SOLO_PASS"),
+                threads: solo_threads,
+                suggest_difficulty: solo_suggest,
+                offline_queue_secs: solo_offline_queue,
+            },
+            api: ApiConfig {
+                listen: var("MUJINA_API_LISTEN"),
+                socket: var("MUJINA_API_SOCKET").map(PathBuf::from),
+            },
+            agent: AgentConfig {
+                enabled: var("MUJINA_AGENT").map(|_| true),
+                listen: var("MUJINA_AGENT_LISTEN"),
+            },
+            proxy: ProxyConfig {
+                listen: var("MUJINA_PROXY_LISTEN"),
+            },
+            alerts: AlertsConfig::default(),
+            path: None,
+            boards: BoardConfig {
+                usb_discovery: var("MUJINA_USB_DISABLE").map(|_| false),
+                simulate: var("MUJINA_SIMULATE").map(|_| true),
+                network,
+                network_grace_secs,
+                agent_token: var("MUJINA_AGENT_TOKEN"),
+                derating: var("MUJINA_DERATING"),
+                warmup_secs,
+                max_temp_slew,
+                thermal_tick_secs,
+                thermal_adjust_secs,
+                thermal_overshoot,
+                ambient_reference,
+                nonce_timeout_secs,
+                ntime_roll_secs,
+                profile,
+                capture_dir: var("MUJINA_CAPTURE_DIR").map(PathBuf::from),
+                burn_in_dir: var("MUJINA_BURN_IN_DIR").map(PathBuf::from),
+            },
+        };
+        config.boards.validate()?;
+        Ok(config)
+    }
+
+    /// Parse command-line flags into a layer, plus the `--config` path.
+    ///
+    /// Accepts both `--flag value` and `--flag=value`.
+    pub fn from_args<I>(args: I) -> Result<(Option<PathBuf>, Self), ConfigError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut path = None;
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| ConfigError::MissingValue(flag.clone()))
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => return Err(ConfigError::Help),
+                "--config" => path = Some(PathBuf::from(value()?)),
+                "--pool-url" => config.pool.url = Some(value()?),
+                "--pool-user" => config.pool.user = Some(value()?),
+                "--pool-pass" => config.pool.password = Some(value()?),
+                "--user-agent" => config.pool.user_agent = Some(value()?),
+                "--ntime-correction" => config.pool.ntime_correction = Some(true),
+                "--forced-difficulty" => {
+                    config.pool.forced_difficulty = Some(parse_difficulty(&flag, &value()?)?)
+                }
+                "--pool-quirks" => config.pool.quirks = Some(parse_quirks(&flag, &value()?)?),
+                "--suggest-difficulty" => {
+                    config.pool.suggest_difficulty = Some(parse_suggest_strategy(&flag, &value()?)?)
+                }
+                "--offline-queue-secs" => {
+                    config.pool.offline_queue_secs = Some(parse_secs(&flag, &value()?)?)
+                }
+                "--solo-url" => config.solo.url = Some(value()?),
+                "--solo-user" => config.solo.user = Some(value()?),
+                "--solo-pass" => config.solo.password = Some(value()?),
+                "--solo-threads" => {
+                    config.solo.threads = Some(parse_solo_threads(&flag, &value()?)?)
+                }
+                "--solo-suggest-difficulty" => {
+                    config.solo.suggest_difficulty = Some(parse_suggest_strategy(&flag, &value()?)?)
+                }
+                "--solo-offline-queue-secs" => {
+                    config.solo.offline_queue_secs = Some(parse_secs(&flag, &value()?)?)
+                }
+                "--api-listen" => config.api.listen = Some(value()?),
+                "--api-socket" => config.api.socket = Some(PathBuf::from(value()?)),
+                "--log-level" => config.daemon.log_level = Some(value()?),
+                "--decision-log" => config.daemon.decision_log = Some(PathBuf::from(value()?)),
+                "--share-audit" => {
+                    config.daemon.share_audit = Some(parse_share_audit(&flag, &value()?)?)
+                }
+                "--share-proofs" => config.daemon.share_proofs = Some(PathBuf::from(value()?)),
+                "--idle-backfill" => config.daemon.idle_backfill = Some(true),
+                "--no-usb" => config.boards.usb_discovery = Some(false),
+                "--simulate" => config.boards.simulate = Some(true),
+                "--network-boards" => {
+                    config.boards.network = Some(parse_network_boards(&flag, &value()?)?)
+                }
+                "--network-grace-secs" => {
+                    config.boards.network_grace_secs = Some(parse_secs(&flag, &value()?)?)
+                }
+                "--agent" => config.agent.enabled = Some(true),
+                "--agent-listen" => config.agent.listen = Some(value()?),
+                "--agent-token" => config.boards.agent_token = Some(value()?),
+                "--proxy-listen" => config.proxy.listen = Some(value()?),
+                "--derating" => config.boards.derating = Some(value()?),
+                "--warmup-secs" => config.boards.warmup_secs = Some(parse_secs(&flag, &value()?)?),
+                "--max-temp-slew" => {
+                    config.boards.max_temp_slew = Some(parse_celsius(&flag, &value()?)?)
+                }
+                "--thermal-tick-secs" => {
+                    config.boards.thermal_tick_secs = Some(parse_secs(&flag, &value()?)?)
+                }
+                "--thermal-adjust-secs" => {
+                    config.boards.thermal_adjust_secs = Some(parse_secs(&flag, &value()?)?)
+                }
+                "--thermal-overshoot" => {
+                    config.boards.thermal_overshoot = Some(parse_celsius(&flag, &value()?)?)
+                }
+                "--ambient-reference" => {
+                    config.boards.ambient_reference = Some(parse_celsius(&flag, &value()?)?)
+                }
+                "--nonce-timeout-secs" => {
+                    config.boards.nonce_timeout_secs = Some(parse_secs(&flag, &value()?)?)
+                }
+                "--ntime-roll-secs" => {
+                    config.boards.ntime_roll_secs = Some(parse_secs(&flag, &value()?)?)
+                }
+                "--profile" => config.boards.profile = Some(parse_profile(&flag, &value()?)?),
+                "--capture-dir" => config.boards.capture_dir = Some(PathBuf::from(value()?)),
+                "--burn-in-dir" => config.boards.burn_in_dir = Some(PathBuf::from(value()?)),
+                _ => return Err(ConfigError::UnknownOption(flag)),
+            }
+        }
+
+        config.boards.validate()?;
+        Ok((path, config))
+    }
+
+    /// Overlay `other` on this config; values set in `other` win.
+    pub fn merge(&mut self, other: Self) {
+        fn take<T>(base: &mut Option<T>, over: Option<T>) {
+            if over.is_some() {
+                *base = over;
+            }
+        }
+
+        take(&mut self.daemon.log_level, other.daemon.log_level);
+        take(&mut self.daemon.decision_log, other.daemon.decision_log);
+        take(&mut self.daemon.share_audit, other.daemon.share_audit);
+        take(&mut self.daemon.share_proofs, other.daemon.share_proofs);
+        take(&mut self.daemon.idle_backfill, other.daemon.idle_backfill);
+        take(&mut self.pool.url, other.pool.url);
+        take(&mut self.pool.user, other.pool.user);
+        take(&mut self.pool.password, other.pool.password);
+        take(&mut self.pool.user_agent, other.pool.user_agent);
+        take(&mut self.pool.ntime_correction, other.pool.ntime_correction);
+        take(
+            &mut self.pool.forced_difficulty,
+            other.pool.forced_difficulty,
+        );
+        take(&mut self.pool.quirks, other.pool.quirks);
+        take(
+            &mut self.pool.suggest_difficulty,
+            other.pool.suggest_difficulty,
+        );
+        take(
+            &mut self.pool.offline_queue_secs,
+            other.pool.offline_queue_secs,
+        );
+        take(&mut self.solo.url, other.solo.url);
+        take(&mut self.solo.user, other.solo.user);
+        take(&mut self.solo.password, other.solo.password);
+        take(&mut self.solo.threads, other.solo.threads);
+        take(
+            &mut self.solo.suggest_difficulty,
+            other.solo.suggest_difficulty,
+        );
+        take(
+            &mut self.solo.offline_queue_secs,
+            other.solo.offline_queue_secs,
+        );
+        take(&mut self.api.listen, other.api.listen);
+        take(&mut self.api.socket, other.api.socket);
+        take(&mut self.boards.usb_discovery, other.boards.usb_discovery);
+        take(&mut self.boards.simulate, other.boards.simulate);
+        take(&mut self.boards.network, other.boards.network);
+        take(
+            &mut self.boards.network_grace_secs,
+            other.boards.network_grace_secs,
+        );
+        take(&mut self.boards.agent_token, other.boards.agent_token);
+        take(&mut self.boards.derating, other.boards.derating);
+        take(&mut self.boards.warmup_secs, other.boards.warmup_secs);
+        take(&mut self.boards.max_temp_slew, other.boards.max_temp_slew);
+        take(
+            &mut self.boards.thermal_tick_secs,
+            other.boards.thermal_tick_secs,
+        );
+        take(
+            &mut self.boards.thermal_adjust_secs,
+            other.boards.thermal_adjust_secs,
+        );
+        take(
+            &mut self.boards.thermal_overshoot,
+            other.boards.thermal_overshoot,
+        );
+        take(
+            &mut self.boards.ambient_reference,
+            other.boards.ambient_reference,
+        );
+        take(
+            &mut self.boards.nonce_timeout_secs,
+            other.boards.nonce_timeout_secs,
+        );
+        take(
+            &mut self.boards.ntime_roll_secs,
+            other.boards.ntime_roll_secs,
+        );
+        take(&mut self.boards.profile, other.boards.profile);
+        take(&mut self.boards.capture_dir, other.boards.capture_dir);
+        take(&mut self.boards.burn_in_dir, other.boards.burn_in_dir);
+        take(&mut self.agent.enabled, other.agent.enabled);
+        take(&mut self.agent.listen, other.agent.listen);
+        take(&mut self.proxy.listen, other.proxy.listen);
+        take(&mut self.alerts.rules, other.alerts.rules);
+        take(&mut self.alerts.webhooks, other.alerts.webhooks);
+        take(&mut self.alerts.mqtt, other.alerts.mqtt);
+        take(
+            &mut self.alerts.expected_hashrate,
+            other.alerts.expected_hashrate,
+        );
+        take(&mut self.alerts.history, other.alerts.history);
+    }
+}
+
+impl AlertsConfig {
+    fn validate(&self) -> Result<(), ConfigError> {
+        for url in self.webhooks.iter().flatten() {
+            if let Err(reason) = notify::check_webhook(url) {
+                return Err(ConfigError::InvalidValue {
+                    key: "alerts.webhooks".into(),
+                    value: url.clone(),
+                    reason,
+                });
+            }
+        }
+        if let Some(ref url) = self.mqtt
+            && let Err(reason) = url.parse::<notify::MqttTarget>()
+        {
+            return Err(ConfigError::InvalidValue {
+                key: "alerts.mqtt".into(),
+                value: url.clone(),
+                reason,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl BoardConfig {
+    /// Thermal derating curve; empty (no derating) when unset.
+    pub fn derating_curve(&self) -> DeratingCurve {
+        self.derating
+            .as_deref()
+            .and_then(|table| table.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Warm-up parameters, when warm-up is enabled.
+    pub fn warmup(&self) -> Option<WarmupConfig> {
+        self.warmup_secs.map(|secs| WarmupConfig {
+            stage_duration: Duration::from_secs(secs),
+            ..Default::default()
+        })
+    }
+
+    /// Thermal slew limit, when frequency and fan changes are eased in.
+    pub fn thermal_slew(&self) -> Option<ThermalSlew> {
+        self.max_temp_slew
+            .and_then(|rate| ThermalSlew::new(rate).ok())
+    }
+
+    /// Thermal control loop timing.
+    pub fn thermal(&self) -> ThermalConfig {
+        let defaults = ThermalConfig::default();
+        let tick = self
+            .thermal_tick_secs
+            .map_or(defaults.tick, Duration::from_secs);
+        ThermalConfig {
+            tick,
+            adjust_interval: self.thermal_adjust_secs.map_or(tick, Duration::from_secs),
+            overshoot_c: self.thermal_overshoot.unwrap_or(defaults.overshoot_c),
+            ambient_reference_c: self.ambient_reference,
+        }
+    }
+
+    /// Directory burn-in reports are kept in.
+    pub fn burn_in_dir(&self) -> PathBuf {
+        self.burn_in_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_BURN_IN_DIR))
+    }
+
+    /// How long an unreachable network board is kept for its link to come
+    /// back.
+    pub fn network_grace(&self) -> Duration {
+        self.network_grace_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_RECONNECT_GRACE)
+    }
+
+    /// Time a chip gets to report a nonce for a new job before the job is
+    /// sent again.
+    pub fn nonce_timeout(&self) -> Duration {
+        self.nonce_timeout_secs
+            .map_or(DEFAULT_NONCE_TIMEOUT, Duration::from_secs)
+    }
+
+    /// Time between re-sends of a BM13xx thread's current job with a
+    /// rolled ntime.
+    pub fn ntime_roll_interval(&self) -> Duration {
+        self.ntime_roll_secs
+            .map_or(DEFAULT_NTIME_ROLL_INTERVAL, Duration::from_secs)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(ref table) = self.derating
+            && let Err(e) = table.parse::<DeratingCurve>()
+        {
+            return Err(ConfigError::InvalidValue {
+                key: "derating".into(),
+                value: table.clone(),
+                reason: e.to_string(),
+            });
+        }
+        if self.warmup_secs == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                key: "warmup_secs".into(),
+                value: "0".into(),
+                reason: "must be positive".into(),
+            });
+        }
+        if let Some(rate) = self.max_temp_slew
+            && let Err(e) = ThermalSlew::new(rate)
+        {
+            return Err(ConfigError::InvalidValue {
+                key: "max_temp_slew".into(),
+                value: rate.to_string(),
+                reason: e.to_string(),
+            });
+        }
+        if self.thermal_tick_secs == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                key: "thermal_tick_secs".into(),
+                value: "0".into(),
+                reason: "must be positive".into(),
+            });
+        }
+        if let Some(margin) = self.thermal_overshoot
+            && !(margin.is_finite() && margin >= 0.0)
+        {
+            return Err(ConfigError::InvalidValue {
+                key: "thermal_overshoot".into(),
+                value: margin.to_string(),
+                reason: "must be a non-negative number of °C".into(),
+            });
+        }
+        if let Some(reference) = self.ambient_reference
+            && !reference.is_finite()
+        {
+            return Err(ConfigError::InvalidValue {
+                key: "ambient_reference".into(),
+                value: reference.to_string(),
+                reason: "must be a number of °C".into(),
+            });
+        }
+        if self.nonce_timeout_secs == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                key: "nonce_timeout_secs".into(),
+                value: "0".into(),
+                reason: "must be positive".into(),
+            });
+        }
+        if self.ntime_roll_secs == Some(0) {
+            return Err(ConfigError::InvalidValue {
+                key: "ntime_roll_secs".into(),
+                value: "0".into(),
+                reason: "must be positive".into(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn parse_secs(key: &str, value: &str) -> Result<u64, ConfigError> {
+    value
+        .parse()
+        .map_err(|e: std::num::ParseIntError| ConfigError::InvalidValue {
+            key: key.into(),
+            value: value.into(),
+            reason: e.to_string(),
+        })
+}
+
+fn parse_celsius(key: &str, value: &str) -> Result<f32, ConfigError> {
+    value
+        .parse()
+        .map_err(|e: std::num::ParseFloatError| ConfigError::InvalidValue {
+            key: key.into(),
+            value: value.into(),
+            reason: e.to_string(),
+        })
+}
+
+fn parse_share_audit(key: &str, value: &str) -> Result<usize, ConfigError> {
+    value
+        .parse()
+        .map_err(|e: std::num::ParseIntError| ConfigError::InvalidValue {
+            key: key.into(),
+            value: value.into(),
+            reason: e.to_string(),
+        })
+}
+
+fn parse_solo_threads(key: &str, value: &str) -> Result<usize, ConfigError> {
+    let invalid = |reason: String| ConfigError::InvalidValue {
+        key: key.into(),
+        value: value.into(),
+        reason,
+    };
+    match value.parse::<usize>() {
+        Ok(0) => Err(invalid("must be positive".into())),
+        Ok(threads) => Ok(threads),
+        Err(e) => Err(invalid(e.to_string())),
+    }
+}
+
+fn parse_difficulty(key: &str, value: &str) -> Result<Difficulty, ConfigError> {
+    value.parse().map_err(
+        |e: crate::types::DifficultyParseError| ConfigError::InvalidValue {
+            key: key.into(),
+            value: value.into(),
+            reason: e.to_string(),
+        },
+    )
+}
+
+fn parse_quirks(key: &str, value: &str) -> Result<PoolQuirks, ConfigError> {
+    value.parse().map_err(|reason| ConfigError::InvalidValue {
+        key: key.into(),
+        value: value.into(),
+        reason,
+    })
+}
+
+fn parse_suggest_strategy(key: &str, value: &str) -> Result<SuggestStrategy, ConfigError> {
+    value.parse().map_err(|reason| ConfigError::InvalidValue {
+        key: key.into(),
+        value: value.into(),
+        reason,
+    })
+}
+
+fn parse_network_boards(key: &str, value: &str) -> Result<Vec<NetworkEndpoint>, ConfigError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|endpoint| !endpoint.is_empty())
+        .map(|endpoint| {
+            endpoint
+                .parse()
+                .map_err(|reason| ConfigError::InvalidValue {
+                    key: key.into(),
+                    value: value.into(),
+                    reason,
+                })
+        })
+        .collect()
+}
+
+fn parse_profile(key: &str, value: &str) -> Result<Profile, ConfigError> {
+    value.parse().map_err(|reason| ConfigError::InvalidValue {
+        key: key.into(),
+        value: value.into(),
+        reason,
+    })
+}
+
+static BOARD_CONFIG: OnceLock<BoardConfig> = OnceLock::new();
+
+/// Install the board settings used when boards create hash threads.
+///
+/// Called once by the daemon at startup; later calls are ignored.
+pub fn install_board_config(config: BoardConfig) {
+    let _ = BOARD_CONFIG.set(config);
+}
+
+/// Board settings installed at startup, or defaults if none were.
+pub fn board_config() -> &'static BoardConfig {
+    BOARD_CONFIG.get_or_init(BoardConfig::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn layers_override_in_order() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [pool]
+            url = "stratum+tcp://file:3333"
+            user = "file-user"
+
+            [api]
+            listen = "0.0.0.0"
+            "#,
+        )
+        .unwrap();
+
+        config.merge(
+            Config::from_vars(|key| match key {
+                "MUJINA_POOL_URL" => Some("stratum+tcp://env:3333".into()),
+                "MUJINA_USB_DISABLE" => Some("1".into()),
+                _ => None,
+            })
+            .unwrap(),
+        );
+        let (_, cli) = Config::from_args(args(&["--pool-url=stratum+tcp://cli:3333"])).unwrap();
+        config.merge(cli);
+
+        assert_eq!(config.pool.url.as_deref(), Some("stratum+tcp://cli:3333"));
+        assert_eq!(config.pool.user.as_deref(), Some("file-user"));
+        assert_eq!(config.api.listen.as_deref(), Some("0.0.0.0"));
+        assert_eq!(config.boards.usb_discovery, Some(false));
+    }
+
+    #[test]
+    fn parses_cli_flags() {
+        let (path, config) = Config::from_args(args(&[
+            "--config",
+            "/tmp/m.toml",
+            "--log-level",
+            "debug",
+            "--decision-log=/tmp/decisions.jsonl",
+            "--share-audit",
+            "100",
+            "--share-proofs=/var/lib/mujina/proofs.jsonl",
+            "--idle-backfill",
+            "--derating=70:450,80:350",
+            "--warmup-secs",
+            "30",
+            "--max-temp-slew=6",
+            "--nonce-timeout-secs=10",
+            "--ntime-roll-secs=5",
+            "--no-usb",
+            "--simulate",
+            "--profile",
+            "quiet",
+            "--user-agent=rig-7/1.0",
+            "--ntime-correction",
+            "--forced-difficulty=0.5",
+            "--pool-quirks=no_configure,version_bits_always",
+            "--solo-url=stratum+tcp://solo:3333",
+            "--solo-threads",
+            "2",
+            "--offline-queue-secs=300",
+            "--solo-offline-queue-secs",
+            "600",
+            "--api-socket=/run/mujina/api.sock",
+            "--capture-dir",
+            "/var/log/mujina",
+            "--burn-in-dir=/srv/burn-in",
+        ]))
+        .unwrap();
+
+        assert_eq!(path, Some(PathBuf::from("/tmp/m.toml")));
+        assert_eq!(config.daemon.log_level.as_deref(), Some("debug"));
+        assert_eq!(
+            config.daemon.decision_log,
+            Some(PathBuf::from("/tmp/decisions.jsonl"))
+        );
+        assert_eq!(config.daemon.share_audit, Some(100));
+        assert_eq!(
+            config.daemon.share_proofs,
+            Some(PathBuf::from("/var/lib/mujina/proofs.jsonl"))
+        );
+        assert_eq!(config.daemon.idle_backfill, Some(true));
+        assert_eq!(
+            config.api.socket,
+            Some(PathBuf::from("/run/mujina/api.sock"))
+        );
+        assert_eq!(
+            config.boards.derating_curve().max_frequency(75.0),
+            Some(450.0)
+        );
+        assert_eq!(
+            config.boards.warmup().unwrap().stage_duration,
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            config.boards.thermal_slew().map(|s| s.max_c_per_min()),
+            Some(6.0)
+        );
+        assert_eq!(config.boards.nonce_timeout(), Duration::from_secs(10));
+        assert_eq!(config.boards.ntime_roll_interval(), Duration::from_secs(5));
+        assert_eq!(config.boards.usb_discovery, Some(false));
+        assert_eq!(config.boards.simulate, Some(true));
+        assert_eq!(config.boards.profile, Some(Profile::Quiet));
+        assert_eq!(
+            config.boards.capture_dir,
+            Some(PathBuf::from("/var/log/mujina"))
+        );
+        assert_eq!(config.boards.burn_in_dir(), PathBuf::from("/srv/burn-in"));
+        assert_eq!(config.pool.user_agent.as_deref(), Some("rig-7/1.0"));
+        assert_eq!(config.pool.ntime_correction, Some(true));
+        assert_eq!(
+            config.pool.forced_difficulty,
+            Some(Difficulty::from_f64(0.5))
+        );
+        let quirks = config.pool.quirks.unwrap();
+        assert!(quirks.no_configure && quirks.version_bits_always);
+        assert!(!quirks.no_suggest_difficulty);
+        assert_eq!(config.solo.url.as_deref(), Some("stratum+tcp://solo:3333"));
+        assert_eq!(config.solo.threads, Some(2));
+        assert_eq!(config.pool.offline_queue_secs, Some(300));
+        assert_eq!(config.solo.offline_queue_secs, Some(600));
+    }
+
+    #[test]
+    fn pool_quirks_read_as_a_list() {
+        let config: Config =
+            toml::from_str("[pool]\nquirks = [\"no_suggest_difficulty\"]").unwrap();
+        let quirks = config.pool.quirks.unwrap();
+        assert!(quirks.no_suggest_difficulty);
+        assert!(!quirks.no_configure);
+
+        assert!(toml::from_str::<Config>("[pool]\nquirks = [\"bogus\"]").is_err());
+        assert!(matches!(
+            Config::from_args(args(&["--pool-quirks", "bogus"])),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn network_boards_read_from_every_layer() {
+        let config: Config = toml::from_str(
+            "[boards]\nnetwork = [\"agent@10.0.0.5:4029\"]\nnetwork_grace_secs = 60",
+        )
+        .unwrap();
+        let network = config.boards.network.as_deref().unwrap();
+        assert_eq!(network[0].kind, "agent");
+        assert_eq!(network[0].addr, "10.0.0.5:4029");
+        assert_eq!(config.boards.network_grace(), Duration::from_secs(60));
+        assert!(toml::from_str::<Config>("[boards]\nnetwork = [\"10.0.0.5\"]").is_err());
+
+        let env = Config::from_vars(|key| match key {
+            "MUJINA_NETWORK_BOARDS" => Some("agent@a:1, agent@b:2".into()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(env.boards.network.as_ref().map(|n| n.len()), Some(2));
+        assert_eq!(env.boards.network_grace(), Duration::from_secs(30));
+
+        assert!(matches!(
+            Config::from_args(args(&["--network-boards", "agent@nowhere"])),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn agent_mode_reads_from_every_layer() {
+        let mut config: Config = toml::from_str(
+            "[agent]\nenabled = true\nlisten = \"10.0.0.5\"\n[boards]\nagent_token = \"s3cret\"",
+        )
+        .unwrap();
+        assert_eq!(config.agent.enabled, Some(true));
+        assert_eq!(config.agent.listen.as_deref(), Some("10.0.0.5"));
+        assert_eq!(config.boards.agent_token.as_deref(), Some("s3cret"));
+
+        let env = Config::from_vars(|key| match key {
+            "MUJINA_AGENT_TOKEN" => Some("from-env".into()),
+            _ => None,
+        })
+        .unwrap();
+        config.merge(env);
+        let (_, cli) = Config::from_args(args(&["--agent-listen=0.0.0.0:4100"])).unwrap();
+        config.merge(cli);
+        assert_eq!(config.agent.enabled, Some(true));
+        assert_eq!(config.agent.listen.as_deref(), Some("0.0.0.0:4100"));
+        assert_eq!(config.boards.agent_token.as_deref(), Some("from-env"));
+    }
+
+    #[test]
+    fn proxy_listen_reads_from_every_layer() {
+        let mut config: Config = toml::from_str("[proxy]\nlisten = \"0.0.0.0\"").unwrap();
+        assert_eq!(config.proxy.listen.as_deref(), Some("0.0.0.0"));
+
+        let env = Config::from_vars(|key| match key {
+            "MUJINA_PROXY_LISTEN" => Some("10.0.0.2".into()),
+            _ => None,
+        })
+        .unwrap();
+        config.merge(env);
+        assert_eq!(config.proxy.listen.as_deref(), Some("10.0.0.2"));
+
+        let (_, cli) = Config::from_args(args(&["--proxy-listen", "127.0.0.1:3334"])).unwrap();
+        config.merge(cli);
+        assert_eq!(config.proxy.listen.as_deref(), Some("127.0.0.1:3334"));
+    }
+
+    #[test]
+    fn alerts_are_checked_on_load() {
+        let config: Config = toml::from_str(
+            "[alerts]\nrules = [\"pool disconnected 3x in 1h\"]\nexpected_hashrate = \"1.2T\"",
+        )
+        .unwrap();
+        assert_eq!(config.alerts.rules.as_ref().map(Vec::len), Some(1));
+        assert_eq!(
+            config.alerts.expected_hashrate,
+            Some(HashRate::from_terahashes(1.2))
+        );
+
+        assert!(toml::from_str::<Config>("[alerts]\nrules = [\"temp > 80\"]").is_err());
+
+        let config: Config =
+            toml::from_str("[alerts]\nwebhooks = [\"ftp://hooks.example\"]").unwrap();
+        assert!(matches!(
+            config.alerts.validate(),
+            Err(ConfigError::InvalidValue { key, .. }) if key == "alerts.webhooks"
+        ));
+    }
+
+    #[test]
+    fn exported_config_leaves_secrets_behind() {
+        let golden: Config = toml::from_str(
+            "[pool]\nurl = \"stratum+tcp://pool:3333\"\npassword = \"golden\"
+[boards]\nagent_token = \"golden-token\"\nderating = \"70:450\"
+[alerts]\nrules = [\"temp > 80 for 5m\"]\nmqtt = \"mqtt://user:pw@broker/alerts\"",
+        )
+        .unwrap();
+        let exported = golden.redacted();
+        assert_eq!(exported.pool.password, None);
+        assert_eq!(exported.boards.agent_token, None);
+        assert_eq!(exported.alerts.mqtt, None);
+        assert_eq!(exported.boards.derating.as_deref(), Some("70:450"));
+        assert_eq!(exported.alerts.rules, golden.alerts.rules);
+
+        let unit: Config = toml::from_str(
+            "[solo]\npassword = \"unit\"\n[alerts]\nwebhooks = [\"https://hooks.example/a\"]",
+        )
+        .unwrap();
+        let mut imported = exported;
+        imported.alerts.webhooks = Some(vec![]);
+        imported.keep_secrets(&unit);
+        assert_eq!(imported.solo.password.as_deref(), Some("unit"));
+        assert_eq!(imported.pool.password, None);
+        assert_eq!(imported.alerts.webhooks, None);
+
+        let path = std::env::temp_dir().join(format!("mujina-config-{}.toml", std::process::id()));
+        imported.save_to(&path).unwrap();
+        assert_eq!(Config::load_from(&path).unwrap(), imported);
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        std::fs::remove_file(&path).unwrap();
+
+        imported.boards.warmup_secs = Some(0);
+        assert!(matches!(
+            imported.save_to(&path),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn thermal_timing_defaults_and_overrides() {
+        let defaults = BoardConfig::default().thermal();
+        assert_eq!(defaults, ThermalConfig::default());
+
+        let config: Config = toml::from_str(
+            "[boards]
+thermal_tick_secs = 2
+thermal_overshoot = 5.0",
+        )
+        .unwrap();
+        let thermal = config.boards.thermal();
+        assert_eq!(thermal.tick, Duration::from_secs(2));
+        assert_eq!(thermal.adjust_interval, Duration::from_secs(2));
+        assert_eq!(thermal.overshoot_c, 5.0);
+
+        let env = Config::from_vars(|key| match key {
+            "MUJINA_THERMAL_ADJUST_SECS" => Some("30".into()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(
+            env.boards.thermal().adjust_interval,
+            Duration::from_secs(30)
+        );
+        let (_, cli) = Config::from_args(args(&["--thermal-tick-secs", "10"])).unwrap();
+        assert_eq!(cli.boards.thermal().tick, Duration::from_secs(10));
+        assert_eq!(cli.boards.thermal().ambient_reference_c, None);
+
+        let (_, cli) = Config::from_args(args(&["--ambient-reference", "25"])).unwrap();
+        assert_eq!(cli.boards.thermal().ambient_reference_c, Some(25.0));
+    }
+
+    #[test]
+    fn suggest_strategy_set_per_source() {
+        let config: Config = toml::from_str(
+            "[pool]\nsuggest_difficulty = \"never\"\n[solo]\nsuggest_difficulty = \"share_rate\"",
+        )
+        .unwrap();
+        assert_eq!(config.pool.suggest_difficulty, Some(SuggestStrategy::Never));
+        assert_eq!(
+            config.solo.suggest_difficulty,
+            Some(SuggestStrategy::ShareRate)
+        );
+
+        let (_, cli) = Config::from_args(args(&["--suggest-difficulty=share_rate"])).unwrap();
+        assert_eq!(
+            cli.pool.suggest_difficulty,
+            Some(SuggestStrategy::ShareRate)
+        );
+        assert!(matches!(
+            Config::from_args(args(&["--solo-suggest-difficulty", "often"])),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert!(matches!(
+            Config::from_args(args(&["--bogus"])),
+            Err(ConfigError::UnknownOption(_))
+        ));
+        assert!(matches!(
+            Config::from_args(args(&["--pool-url"])),
+            Err(ConfigError::MissingValue(_))
+        ));
+        assert!(matches!(
+            Config::from_args(args(&["--derating", "70:300,80:400"])),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Config::from_args(args(&["--max-temp-slew", "0"])),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Config::from_args(args(&["--thermal-tick-secs", "0"])),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Config::from_args(args(&["--ntime-roll-secs", "0"])),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Config::from_args(args(&["--thermal-overshoot=-1"])),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Config::from_args(args(&["--profile", "loud"])),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Config::from_args(args(&["--forced-difficulty", "0"])),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Config::from_args(args(&["--solo-threads", "0"])),
+            Err(ConfigError::InvalidValue { .. })
+        ));
+        assert!(toml::from_str::<Config>("[pool]\nurl_typo = 'x'").is_err());
     }
 }
@@ -56,6 +56,9 @@ pub enum MinerCommand {
         response_tx: tokio::sync::oneshot::Sender<Result<Option<HashTask>, HashThreadError>>,
     },
 
+    /// Limit how far ntime is rolled past each task's value.
+    Negotiate { max_ntime_roll: u32 },
+
     /// Shutdown the thread.
     Shutdown,
 }
@@ -87,7 +90,11 @@ pub fn run_mining_loop(
     let mut cached_merkle_root: Option<bitcoin::TxMerkleNode> = None;
     let mut nonce: u32 = 0;
     let mut last_ntime_tick = Instant::now();
+    let mut max_ntime_roll = u32::MAX;
+    // Latest ntime the current task may be rolled to
+    let mut ntime_limit = u32::MAX;
     let mut shares_found: u64 = 0;
+    let mut pool_shares: u64 = 0;
     let mut hashes_computed: u64 = 0;
     let mut last_hashrate_update = Instant::now();
 
@@ -103,24 +110,31 @@ pub fn run_mining_loop(
                 Ok(cmd) => match cmd {
                     MinerCommand::UpdateTask { task, response_tx } => {
                         cached_merkle_root = compute_merkle_root(&task);
+                        ntime_limit = task.ntime.saturating_add(max_ntime_roll);
                         let old = current_task.replace(task);
                         nonce = 0;
-                        update_status(&status, true, shares_found);
+                        update_status(&status, current_task.as_ref(), shares_found, pool_shares);
                         let _ = response_tx.send(Ok(old));
                     }
                     MinerCommand::ReplaceTask { task, response_tx } => {
                         cached_merkle_root = compute_merkle_root(&task);
+                        ntime_limit = task.ntime.saturating_add(max_ntime_roll);
                         let old = current_task.replace(task);
                         nonce = 0;
-                        update_status(&status, true, shares_found);
+                        update_status(&status, current_task.as_ref(), shares_found, pool_shares);
                         let _ = response_tx.send(Ok(old));
                     }
                     MinerCommand::GoIdle { response_tx } => {
                         cached_merkle_root = None;
                         let old = current_task.take();
-                        update_status(&status, false, shares_found);
+                        update_status(&status, None, shares_found, pool_shares);
                         let _ = response_tx.send(Ok(old));
                     }
+                    MinerCommand::Negotiate {
+                        max_ntime_roll: roll,
+                    } => {
+                        max_ntime_roll = roll;
+                    }
                     MinerCommand::Shutdown => {
                         return;
                     }
@@ -153,28 +167,45 @@ pub fn run_mining_loop(
                     match cmd {
                         MinerCommand::ReplaceTask { task, response_tx } => {
                             cached_merkle_root = compute_merkle_root(&task);
+                            ntime_limit = task.ntime.saturating_add(max_ntime_roll);
                             let old = current_task.replace(task);
                             nonce = 0;
-                            update_status(&status, true, shares_found);
+                            update_status(
+                                &status,
+                                current_task.as_ref(),
+                                shares_found,
+                                pool_shares,
+                            );
                             let _ = response_tx.send(Ok(old));
                             // Continue with new task in next iteration
                             break;
                         }
                         MinerCommand::UpdateTask { task, response_tx } => {
                             cached_merkle_root = compute_merkle_root(&task);
+                            ntime_limit = task.ntime.saturating_add(max_ntime_roll);
                             let old = current_task.replace(task);
                             nonce = 0;
-                            update_status(&status, true, shares_found);
+                            update_status(
+                                &status,
+                                current_task.as_ref(),
+                                shares_found,
+                                pool_shares,
+                            );
                             let _ = response_tx.send(Ok(old));
                             break;
                         }
                         MinerCommand::GoIdle { response_tx } => {
                             cached_merkle_root = None;
                             let old = current_task.take();
-                            update_status(&status, false, shares_found);
+                            update_status(&status, None, shares_found, pool_shares);
                             let _ = response_tx.send(Ok(old));
                             break;
                         }
+                        MinerCommand::Negotiate {
+                            max_ntime_roll: roll,
+                        } => {
+                            max_ntime_roll = roll;
+                        }
                         MinerCommand::Shutdown => return,
                     }
                 }
@@ -184,6 +215,9 @@ pub fn run_mining_loop(
                     && let Some(share) = try_nonce(task, merkle_root, nonce)
                 {
                     shares_found += 1;
+                    if task.template.share_target.is_met_by(share.hash) {
+                        pool_shares += 1;
+                    }
                     debug!(
                         thread = %thread_name,
                         nonce = %format!("{:#010x}", share.nonce),
@@ -206,7 +240,9 @@ pub fn run_mining_loop(
 
             // Roll ntime every second
             if last_ntime_tick.elapsed() >= Duration::from_secs(1) {
-                if let Some(ref mut task) = current_task {
+                if let Some(ref mut task) = current_task
+                    && task.ntime < ntime_limit
+                {
                     task.ntime += 1;
                 }
                 last_ntime_tick = Instant::now();
@@ -229,6 +265,8 @@ pub fn run_mining_loop(
                     let mut s = status.write().unwrap();
                     s.hashrate = hashrate;
                     s.chip_shares_found = shares_found;
+                    s.shares_forwarded = shares_found;
+                    s.pool_shares_submitted = pool_shares;
                 }
 
                 hashes_computed = 0;
@@ -282,11 +320,22 @@ fn try_nonce(task: &HashTask, merkle_root: bitcoin::TxMerkleNode, nonce: u32) ->
     }
 }
 
-/// Update shared status.
-fn update_status(status: &Arc<RwLock<HashThreadStatus>>, is_active: bool, shares_found: u64) {
+/// Update shared status for the task now being worked on, if any.
+///
+/// With no chip in front of it, every share found is forwarded.
+fn update_status(
+    status: &Arc<RwLock<HashThreadStatus>>,
+    task: Option<&HashTask>,
+    shares_found: u64,
+    pool_shares: u64,
+) {
     let mut s = status.write().unwrap();
-    s.is_active = is_active;
+    s.is_active = task.is_some();
+    s.share_target = task.map(|t| t.share_target);
+    s.pool_target = task.map(|t| t.template.share_target);
     s.chip_shares_found = shares_found;
+    s.shares_forwarded = shares_found;
+    s.pool_shares_submitted = pool_shares;
 }
 
 #[cfg(test)]
@@ -330,7 +379,7 @@ mod tests {
             en2: None,
             share_target: easy_target,
             ntime: 1234567890,
-            share_tx,
+            share_tx: share_tx.into(),
         }
     }
 
@@ -408,13 +457,13 @@ mod tests {
             bits: *block_881423::BITS,
             share_target: easy_target,
             time: block_881423::TIME,
-            merkle_root: MerkleRootKind::Computed(MerkleRootTemplate {
-                coinbase1: block_881423::coinbase1_bytes().to_vec(),
-                extranonce1: block_881423::extranonce1_bytes().to_vec(),
+            merkle_root: MerkleRootKind::Computed(Box::new(MerkleRootTemplate::new(
+                block_881423::coinbase1_bytes().to_vec(),
+                block_881423::extranonce1_bytes().to_vec(),
                 extranonce2_range,
-                coinbase2: block_881423::coinbase2_bytes().to_vec(),
-                merkle_branches: block_881423::MERKLE_BRANCHES.clone(),
-            }),
+                block_881423::coinbase2_bytes().to_vec(),
+                block_881423::MERKLE_BRANCHES.clone(),
+            ))),
         });
 
         let (share_tx, _share_rx) = tokio_mpsc::channel(100);
@@ -425,7 +474,7 @@ mod tests {
             en2: Some(en2),
             share_target: easy_target,
             ntime: block_881423::TIME,
-            share_tx,
+            share_tx: share_tx.into(),
         };
 
         // With computed merkle root and easy target, we should find shares
@@ -2,6 +2,11 @@
 //!
 //! Provides a virtual mining board that uses CPU cores for SHA-256 hashing.
 //! Useful for testing and development without physical ASIC hardware.
+//! It is also the simplest complete implementation of
+//! [`HashThread`](crate::asic::hash_thread::HashThread), and drives the
+//! scheduler's end-to-end test from job to submitted share.
+//!
+//! Built with the `cpu-miner` feature, on by default.
 //!
 //! # Configuration
 //!
@@ -15,9 +15,11 @@ use tokio::sync::mpsc as tokio_mpsc;
 use super::hasher::{self, MinerCommand};
 use crate::{
     asic::hash_thread::{
-        HashTask, HashThread, HashThreadCapabilities, HashThreadError, HashThreadEvent,
-        HashThreadStatus,
+        AssignmentParameters, HashTask, HashThread, HashThreadCapabilities, HashThreadError,
+        HashThreadEvent, HashThreadStatus,
     },
+    job_source::GeneralPurposeBits,
+    tracing::prelude::*,
     types::HashRate,
 };
 
@@ -73,11 +75,13 @@ impl CpuHashThread {
         let status_clone = Arc::clone(&status);
         let shutdown_clone = Arc::clone(&shutdown);
         let thread_name = name.clone();
+        let span = info_span!("hash_thread", thread = %name);
 
         // Spawn the mining thread
         let handle = std::thread::Builder::new()
             .name(format!("cpu-miner-{}", name))
             .spawn(move || {
+                let _span = span.enter();
                 hasher::run_mining_loop(
                     thread_name,
                     cmd_rx,
@@ -97,6 +101,13 @@ impl CpuHashThread {
             capabilities: HashThreadCapabilities {
                 // Conservative estimate: ~5 MH/s per core on modern hardware
                 hashrate_estimate: HashRate::from_megahashes(5.0),
+                // Hashes the base version only
+                version_rolling: GeneralPurposeBits::none(),
+                // Rolls ntime once a second for as long as a task runs
+                max_ntime_roll: u32::MAX,
+                iterates_extranonce2: false,
+                // Every nonce is checked against the share target in software
+                reporting_difficulty: None,
             },
             shutdown,
             _thread_handle: Some(handle),
@@ -126,6 +137,14 @@ impl HashThread for CpuHashThread {
         &self.capabilities
     }
 
+    async fn negotiate(&mut self, params: AssignmentParameters) -> Result<(), HashThreadError> {
+        self.command_tx
+            .send(MinerCommand::Negotiate {
+                max_ntime_roll: params.max_ntime_roll,
+            })
+            .map_err(|_| HashThreadError::ChannelClosed("command channel closed".into()))
+    }
+
     async fn update_task(
         &mut self,
         new_task: HashTask,
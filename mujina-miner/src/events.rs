@@ -0,0 +1,276 @@
+//! In-process event bus with typed topics.
+//!
+//! Subsystems publish what happens to them (shares found, boards coming
+//! and going, thermal readings, pool state changes) into an [`EventBus`],
+//! and any number of consumers subscribe to the topics they care about:
+//! the API server, its event stream, or a program embedding the miner.
+//! Publishers don't know who listens, so adding a consumer is one
+//! `subscribe` call rather than a new channel threaded through every
+//! constructor.
+//!
+//! Each topic is a marker type implementing [`Topic`], which fixes the
+//! event type carried on it:
+//!
+//! ```
+//! use mujina_miner::events::{EventBus, PoolEvent, PoolEventKind, Pools};
+//!
+//! let bus = EventBus::new();
+//! let mut pools = bus.subscribe::<Pools>();
+//! bus.publish::<Pools>(PoolEvent {
+//!     source: "pool".into(),
+//!     kind: PoolEventKind::WorkCleared,
+//! });
+//! assert_eq!(pools.try_recv().unwrap().source, "pool");
+//! ```
+//!
+//! Topics are broadcast channels: a subscriber sees events published after
+//! it subscribed, and one that falls more than [`CAPACITY`] events behind
+//! skips the oldest and is told how many it missed. Publishing never waits
+//! on a slow subscriber.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tokio::sync::{broadcast, watch};
+
+use crate::api_client::types::{BoardState, Fan, PowerMeasurement, TemperatureSensor};
+use crate::types::Difficulty;
+
+/// Events each topic buffers for subscribers that fall behind.
+pub const CAPACITY: usize = 256;
+
+/// A kind of event published on the bus.
+pub trait Topic: 'static {
+    /// What is published on the topic.
+    type Event: Clone + Send + 'static;
+
+    /// Name the topic goes by outside the process, e.g. in the API's
+    /// event stream.
+    const NAME: &'static str;
+}
+
+/// Shares found by hash threads.
+pub struct Shares;
+
+impl Topic for Shares {
+    type Event = ShareEvent;
+    const NAME: &'static str = "shares";
+}
+
+/// Boards connecting and disconnecting.
+pub struct Boards;
+
+impl Topic for Boards {
+    type Event = BoardEvent;
+    const NAME: &'static str = "boards";
+}
+
+/// Periodic temperature, fan and power readings from each board.
+pub struct Thermal;
+
+impl Topic for Thermal {
+    type Event = ThermalEvent;
+    const NAME: &'static str = "thermal";
+}
+
+/// Work and share verdicts from job sources.
+pub struct Pools;
+
+impl Topic for Pools {
+    type Event = PoolEvent;
+    const NAME: &'static str = "pools";
+}
+
+/// A share found by a hash thread, before it goes to its source.
+#[derive(Clone, Debug, Serialize)]
+pub struct ShareEvent {
+    pub source: String,
+    pub thread: String,
+    pub job_id: String,
+    pub difficulty: Difficulty,
+    /// Whether it meets the source's share target and is submitted.
+    pub met_target: bool,
+}
+
+/// A board connecting or disconnecting.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BoardEvent {
+    Added {
+        name: String,
+        /// Follows the board's state until it goes away.
+        #[serde(skip)]
+        state_rx: watch::Receiver<BoardState>,
+    },
+    Removed {
+        name: String,
+    },
+}
+
+/// One board's thermal readings.
+#[derive(Clone, Debug, Serialize)]
+pub struct ThermalEvent {
+    pub board: String,
+    pub temperatures: Vec<TemperatureSensor>,
+    pub fans: Vec<Fan>,
+    pub powers: Vec<PowerMeasurement>,
+}
+
+/// Something that happened to a job source's work or shares.
+#[derive(Clone, Debug, Serialize)]
+pub struct PoolEvent {
+    pub source: String,
+    #[serde(flatten)]
+    pub kind: PoolEventKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PoolEventKind {
+    /// A new job; `clean` if it replaces the source's earlier ones.
+    NewJob {
+        job_id: String,
+        clean: bool,
+    },
+    /// The source withdrew all its work.
+    WorkCleared,
+    ShareAccepted,
+    ShareRejected,
+}
+
+/// Broadcast channels by topic; cheap to clone, clones share the topics.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    /// `broadcast::Sender<T::Event>` by the `TypeId` of `T`
+    topics: Arc<Mutex<HashMap<TypeId, Box<dyn Any + Send>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publish an event to everyone subscribed to `T`.
+    ///
+    /// With no subscribers the event is dropped.
+    pub fn publish<T: Topic>(&self, event: T::Event) {
+        let _ = self.sender::<T>().send(event);
+    }
+
+    /// Receive events published to `T` from now on.
+    pub fn subscribe<T: Topic>(&self) -> broadcast::Receiver<T::Event> {
+        self.sender::<T>().subscribe()
+    }
+
+    /// Whether anyone is subscribed to `T`, to skip building events no
+    /// one will see.
+    pub fn has_subscribers<T: Topic>(&self) -> bool {
+        self.sender::<T>().receiver_count() > 0
+    }
+
+    fn sender<T: Topic>(&self) -> broadcast::Sender<T::Event> {
+        let mut topics = self.topics.lock().unwrap();
+        topics
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(broadcast::channel::<T::Event>(CAPACITY).0))
+            .downcast_ref::<broadcast::Sender<T::Event>>()
+            .expect("topic registered with its own event type")
+            .clone()
+    }
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventBus").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn work_cleared(source: &str) -> PoolEvent {
+        PoolEvent {
+            source: source.into(),
+            kind: PoolEventKind::WorkCleared,
+        }
+    }
+
+    #[test]
+    fn subscribers_see_only_their_topic() {
+        let bus = EventBus::new();
+        let mut pools = bus.subscribe::<Pools>();
+        let mut boards = bus.subscribe::<Boards>();
+
+        bus.publish::<Pools>(work_cleared("pool"));
+        bus.publish::<Boards>(BoardEvent::Removed {
+            name: "bitaxe-1".into(),
+        });
+
+        assert_eq!(pools.try_recv().unwrap().source, "pool");
+        assert!(pools.try_recv().is_err());
+        assert!(matches!(
+            boards.try_recv().unwrap(),
+            BoardEvent::Removed { name } if name == "bitaxe-1"
+        ));
+    }
+
+    #[test]
+    fn every_subscriber_gets_every_event() {
+        let bus = EventBus::new();
+        let mut first = bus.subscribe::<Pools>();
+        let mut second = bus.clone().subscribe::<Pools>();
+
+        bus.publish::<Pools>(work_cleared("a"));
+        bus.publish::<Pools>(work_cleared("b"));
+
+        for rx in [&mut first, &mut second] {
+            assert_eq!(rx.try_recv().unwrap().source, "a");
+            assert_eq!(rx.try_recv().unwrap().source, "b");
+        }
+    }
+
+    #[test]
+    fn publishing_without_subscribers_is_harmless() {
+        let bus = EventBus::new();
+        assert!(!bus.has_subscribers::<Pools>());
+        bus.publish::<Pools>(work_cleared("pool"));
+
+        // Only what follows subscribing is received
+        let mut pools = bus.subscribe::<Pools>();
+        assert!(bus.has_subscribers::<Pools>());
+        assert!(pools.try_recv().is_err());
+    }
+
+    #[test]
+    fn slow_subscribers_skip_ahead() {
+        let bus = EventBus::new();
+        let mut pools = bus.subscribe::<Pools>();
+        for i in 0..CAPACITY + 3 {
+            bus.publish::<Pools>(work_cleared(&i.to_string()));
+        }
+
+        assert!(matches!(
+            pools.try_recv(),
+            Err(broadcast::error::TryRecvError::Lagged(3))
+        ));
+        assert_eq!(pools.try_recv().unwrap().source, "3");
+    }
+
+    #[test]
+    fn events_serialize_with_their_kind() {
+        let event = PoolEvent {
+            source: "pool".into(),
+            kind: PoolEventKind::NewJob {
+                job_id: "1a".into(),
+                clean: true,
+            },
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({"source": "pool", "kind": "new_job", "job_id": "1a", "clean": true})
+        );
+    }
+}
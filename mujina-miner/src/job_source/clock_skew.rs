@@ -0,0 +1,173 @@
+//! Clock skew between this host and a pool, estimated from job ntime.
+//!
+//! Without NTP the host clock can drift minutes away from real time, and
+//! nothing in Stratum v1 reports the pool's clock directly. Each
+//! `mining.notify` carries one, though: a job's ntime is the pool's time
+//! when it built the block template. Comparing it with local time on
+//! arrival gives a sample of the skew.
+//!
+//! A sample can only underestimate the pool's clock: the job may have
+//! been built a while before it was sent (pools resend the current
+//! template with new transactions but the same ntime), and it spent time
+//! in flight. So the estimate is the largest recent sample, not the
+//! average.
+//!
+//! Rolled ntime follows the job rather than the host clock, so local
+//! skew alone doesn't spoil shares. A large skew still means one of the
+//! two clocks is wrong, and pools that check share ntime against their
+//! own clock reject timestamps that drift outside their window.
+
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Number of recent jobs the estimate is drawn from.
+const SAMPLE_HISTORY_LEN: usize = 16;
+
+/// Skew beyond which the source warns.
+pub const SKEW_WARN_THRESHOLD: Duration = Duration::from_secs(60);
+
+/// Furthest a corrected job ntime may be moved past the pool's value.
+///
+/// Matches the hash threads' own roll limit, which pools are known to
+/// accept.
+pub const MAX_CORRECTION: u32 = 600;
+
+/// Estimated offset of a pool's clock from the local one.
+#[derive(Debug, Default)]
+pub struct ClockSkew {
+    /// Recent samples of pool time minus local time, in seconds.
+    samples: VecDeque<i64>,
+    /// Whether the last estimate was past the warning threshold.
+    warned: bool,
+}
+
+/// Change in whether skew is past [`SKEW_WARN_THRESHOLD`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkewAlert {
+    /// Skew has grown past the threshold.
+    Exceeded(i64),
+    /// Skew is back under the threshold.
+    Recovered(i64),
+}
+
+impl ClockSkew {
+    /// Record a job with `ntime` that arrived at local time `local_secs`
+    /// (Unix seconds).
+    ///
+    /// Returns an alert when the estimate crosses the warning threshold.
+    pub fn observe(&mut self, ntime: u32, local_secs: u64) -> Option<SkewAlert> {
+        let sample = i64::from(ntime) - local_secs as i64;
+        if self.samples.len() == SAMPLE_HISTORY_LEN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+
+        let skew = self.skew_secs()?;
+        let exceeded = skew.unsigned_abs() > SKEW_WARN_THRESHOLD.as_secs();
+        if exceeded == self.warned {
+            return None;
+        }
+        self.warned = exceeded;
+        Some(if exceeded {
+            SkewAlert::Exceeded(skew)
+        } else {
+            SkewAlert::Recovered(skew)
+        })
+    }
+
+    /// Pool clock minus local clock in seconds, once a job has been seen.
+    ///
+    /// Positive when the pool is ahead.
+    pub fn skew_secs(&self) -> Option<i64> {
+        self.samples.iter().copied().max()
+    }
+
+    /// The pool's current time as estimated from local time `local_secs`.
+    pub fn pool_time(&self, local_secs: u64) -> Option<u32> {
+        let pool = local_secs as i64 + self.skew_secs()?;
+        u32::try_from(pool).ok()
+    }
+
+    /// ntime to start a job at, corrected to the pool's current time.
+    ///
+    /// A job whose ntime lags the pool's clock (a resent template) is
+    /// moved forward, by at most [`MAX_CORRECTION`]; ntime is never
+    /// moved backward.
+    pub fn corrected_ntime(&self, ntime: u32, local_secs: u64) -> u32 {
+        match self.pool_time(local_secs) {
+            Some(now) => now.clamp(ntime, ntime.saturating_add(MAX_CORRECTION)),
+            None => ntime,
+        }
+    }
+}
+
+/// Local time in Unix seconds.
+pub fn local_unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCAL: u64 = 1_700_000_000;
+
+    #[test]
+    fn estimate_is_largest_recent_sample() {
+        let mut skew = ClockSkew::default();
+        assert_eq!(skew.skew_secs(), None);
+
+        // Pool 20s ahead; the resent job lags by another 30s
+        skew.observe((LOCAL + 20) as u32, LOCAL);
+        skew.observe((LOCAL + 20) as u32, LOCAL + 30);
+        assert_eq!(skew.skew_secs(), Some(20));
+        assert_eq!(skew.pool_time(LOCAL + 100), Some((LOCAL + 120) as u32));
+
+        // Old samples age out
+        for i in 0..SAMPLE_HISTORY_LEN as u64 {
+            skew.observe((LOCAL - 5 + i) as u32, LOCAL + i);
+        }
+        assert_eq!(skew.skew_secs(), Some(-5));
+    }
+
+    #[test]
+    fn alerts_on_crossing_threshold() {
+        let mut skew = ClockSkew::default();
+        assert_eq!(skew.observe((LOCAL + 10) as u32, LOCAL), None);
+
+        // Host clock 5 minutes fast
+        let mut alerts = Vec::new();
+        for i in 1..=SAMPLE_HISTORY_LEN as u64 {
+            let local = LOCAL + i * 30;
+            alerts.extend(skew.observe((local - 300) as u32, local));
+        }
+        assert_eq!(alerts, [SkewAlert::Exceeded(-300)]);
+
+        // Clock fixed
+        assert_eq!(
+            skew.observe((LOCAL + 1000) as u32, LOCAL + 1000),
+            Some(SkewAlert::Recovered(0))
+        );
+    }
+
+    #[test]
+    fn correction_moves_lagging_jobs_forward_only() {
+        let mut skew = ClockSkew::default();
+        assert_eq!(skew.corrected_ntime(5000, LOCAL), 5000);
+
+        skew.observe((LOCAL - 100) as u32, LOCAL);
+        let lagging = (LOCAL - 160) as u32;
+        assert_eq!(skew.corrected_ntime(lagging, LOCAL), (LOCAL - 100) as u32);
+
+        // Already ahead of the pool's clock: left alone
+        let ahead = (LOCAL - 50) as u32;
+        assert_eq!(skew.corrected_ntime(ahead, LOCAL), ahead);
+
+        // Far behind: moved by the limit only
+        let stale = (LOCAL - 5000) as u32;
+        assert_eq!(skew.corrected_ntime(stale, LOCAL), stale + MAX_CORRECTION);
+    }
+}
@@ -0,0 +1,359 @@
+//! Strategies for suggesting a share difficulty to the pool.
+//!
+//! `mining.suggest_difficulty` asks the pool for a share difficulty that
+//! gives a comfortable share rate for our hashrate. Pools take it
+//! differently: some follow every suggestion, some let their own vardiff
+//! win, and some drop clients that suggest too often or too low. Which
+//! [`SuggestStrategy`] a source follows is set per source in the config,
+//! and a [`DifficultySuggester`] carries it out.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::types::{Difficulty, HashRate, ShareRate, target_for_share_rate};
+
+/// Target share rate for suggest_difficulty: 20 shares/min (one every 3 sec).
+const SUGGESTED_SHARE_RATE: ShareRate = ShareRate::from_interval(Duration::from_secs(3));
+
+/// Re-suggest when new difficulty is >2x or <0.5x the last-suggested value.
+const MATERIAL_CHANGE_FACTOR: f64 = 2.0;
+
+/// How often suggesters that watch the share rate are reviewed.
+pub const REVIEW_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Re-suggest under [`SuggestStrategy::ShareRate`] once the difficulty
+/// moves by more than this factor.
+const SHARE_RATE_DEADBAND: f64 = 1.25;
+
+/// Gains of the share rate controller, on the log of the share rate error
+/// per review.
+const KP: f64 = 0.5;
+const KI: f64 = 0.2;
+const KD: f64 = 0.1;
+
+/// Furthest the share rate controller moves the suggestion from the
+/// hashrate estimate, as a log factor (8x either way).
+const MAX_TRIM: f64 = 3.0 * std::f64::consts::LN_2;
+
+/// How a source decides when, and what, to suggest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestStrategy {
+    /// Suggest from the expected hashrate, again only when that moves the
+    /// difficulty by a factor of two.
+    #[default]
+    MaterialChange,
+    /// Steer the suggestion with a PID controller on the share rate
+    /// actually seen, correcting for a hashrate estimate that is off.
+    ShareRate,
+    /// Never suggest; take whatever difficulty the pool sets.
+    Never,
+}
+
+impl SuggestStrategy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::MaterialChange => "material_change",
+            Self::ShareRate => "share_rate",
+            Self::Never => "never",
+        }
+    }
+
+    /// Suggester that follows this strategy.
+    pub fn suggester(self) -> Box<dyn DifficultySuggester> {
+        match self {
+            Self::MaterialChange => Box::new(MaterialChange::default()),
+            Self::ShareRate => Box::new(ShareRateControl::default()),
+            Self::Never => Box::new(Never),
+        }
+    }
+}
+
+impl fmt::Display for SuggestStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SuggestStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "material_change" => Ok(Self::MaterialChange),
+            "share_rate" => Ok(Self::ShareRate),
+            "never" => Ok(Self::Never),
+            _ => Err("expected material_change, share_rate or never".into()),
+        }
+    }
+}
+
+/// Decides the difficulties a source suggests to its pool.
+pub trait DifficultySuggester: Send {
+    /// Difficulty to suggest in the handshake of a new connection.
+    fn initial(&mut self, hashrate: HashRate, now: Instant) -> Option<u64>;
+
+    /// Difficulty to suggest now, if any. Called when the expected
+    /// hashrate changes and every [`REVIEW_INTERVAL`].
+    fn review(&mut self, hashrate: HashRate, now: Instant) -> Option<u64>;
+
+    /// A share was sent to the pool at its share difficulty.
+    fn share_submitted(&mut self, _difficulty: Difficulty, _now: Instant) {}
+
+    /// Forget the last suggestion, so the next review makes one afresh.
+    fn resync(&mut self);
+
+    /// Difficulty last suggested, if any.
+    fn last_suggested(&self) -> Option<u64>;
+}
+
+/// Difficulty giving [`SUGGESTED_SHARE_RATE`] at `hashrate`.
+///
+/// Returns `None` for zero hashrate (nothing to suggest yet).
+pub fn difficulty_for(hashrate: HashRate) -> Option<u64> {
+    if hashrate.is_zero() {
+        return None;
+    }
+    let target = target_for_share_rate(SUGGESTED_SHARE_RATE, hashrate);
+    let diff = Difficulty::from_target(target).as_u64().max(1);
+    Some(diff)
+}
+
+/// Whether `new` is more than `factor` away from `prev`, either way.
+fn differs_by(prev: u64, new: u64, factor: f64) -> bool {
+    let ratio = new as f64 / prev as f64;
+    ratio >= factor || ratio <= 1.0 / factor
+}
+
+/// [`SuggestStrategy::MaterialChange`].
+#[derive(Debug, Default)]
+pub struct MaterialChange {
+    last: Option<u64>,
+}
+
+impl DifficultySuggester for MaterialChange {
+    fn initial(&mut self, hashrate: HashRate, _now: Instant) -> Option<u64> {
+        self.last = difficulty_for(hashrate);
+        self.last
+    }
+
+    fn review(&mut self, hashrate: HashRate, _now: Instant) -> Option<u64> {
+        let new = difficulty_for(hashrate)?;
+        if let Some(prev) = self.last
+            && !differs_by(prev, new, MATERIAL_CHANGE_FACTOR)
+        {
+            return None;
+        }
+        self.last = Some(new);
+        Some(new)
+    }
+
+    fn resync(&mut self) {
+        self.last = None;
+    }
+
+    fn last_suggested(&self) -> Option<u64> {
+        self.last
+    }
+}
+
+/// [`SuggestStrategy::ShareRate`].
+///
+/// Suggests the hashrate estimate's difficulty scaled by a trim factor.
+/// Each review compares the work submitted since the last one against the
+/// share rate the last suggestion should have given, and a PID controller
+/// on the log of that ratio sets the trim. The integral term settles on
+/// the estimate's error, so a board that hashes below its nominal rate is
+/// asked for a lower difficulty than the estimate alone would give.
+#[derive(Debug, Default)]
+pub struct ShareRateControl {
+    last: Option<u64>,
+    /// Start of the current measurement window
+    window_start: Option<Instant>,
+    /// Sum of the difficulties of shares submitted in the window
+    work: f64,
+    integral: f64,
+    previous_error: Option<f64>,
+    /// Log factor applied to the estimate's difficulty
+    trim: f64,
+}
+
+impl ShareRateControl {
+    /// Fold the window ending at `now` into the trim, if it is long
+    /// enough to judge.
+    fn update_trim(&mut self, now: Instant) {
+        let (Some(start), Some(last)) = (self.window_start, self.last) else {
+            self.window_start = Some(now);
+            return;
+        };
+        let elapsed = now.duration_since(start);
+        if elapsed < REVIEW_INTERVAL {
+            return;
+        }
+
+        // Count an empty window as half a share, so a slow window pulls
+        // the difficulty down without the log running off to infinity
+        let shares = (self.work / last as f64).max(0.5);
+        let seen = shares / elapsed.as_secs_f64();
+        let error = (seen / SUGGESTED_SHARE_RATE.as_per_second()).ln();
+
+        self.integral = (self.integral + error).clamp(-MAX_TRIM / KI, MAX_TRIM / KI);
+        let derivative = error - self.previous_error.unwrap_or(error);
+        self.previous_error = Some(error);
+        self.trim = (KP * error + KI * self.integral + KD * derivative).clamp(-MAX_TRIM, MAX_TRIM);
+
+        self.window_start = Some(now);
+        self.work = 0.0;
+    }
+}
+
+impl DifficultySuggester for ShareRateControl {
+    fn initial(&mut self, hashrate: HashRate, now: Instant) -> Option<u64> {
+        // The trim carries over; shares seen on the old connection don't
+        self.window_start = Some(now);
+        self.work = 0.0;
+        self.previous_error = None;
+        self.last = difficulty_for(hashrate)
+            .map(|base| ((base as f64 * self.trim.exp()).round() as u64).max(1));
+        self.last
+    }
+
+    fn review(&mut self, hashrate: HashRate, now: Instant) -> Option<u64> {
+        let base = difficulty_for(hashrate)?;
+        self.update_trim(now);
+        let new = ((base as f64 * self.trim.exp()).round() as u64).max(1);
+        if let Some(prev) = self.last
+            && !differs_by(prev, new, SHARE_RATE_DEADBAND)
+        {
+            return None;
+        }
+        self.last = Some(new);
+        Some(new)
+    }
+
+    fn share_submitted(&mut self, difficulty: Difficulty, _now: Instant) {
+        self.work += difficulty.as_f64();
+    }
+
+    fn resync(&mut self) {
+        self.last = None;
+    }
+
+    fn last_suggested(&self) -> Option<u64> {
+        self.last
+    }
+}
+
+/// [`SuggestStrategy::Never`].
+#[derive(Debug, Default)]
+pub struct Never;
+
+impl DifficultySuggester for Never {
+    fn initial(&mut self, _hashrate: HashRate, _now: Instant) -> Option<u64> {
+        None
+    }
+
+    fn review(&mut self, _hashrate: HashRate, _now: Instant) -> Option<u64> {
+        None
+    }
+
+    fn resync(&mut self) {}
+
+    fn last_suggested(&self) -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn difficulty_for_zero_hashrate() {
+        assert_eq!(difficulty_for(HashRate::default()), None);
+    }
+
+    #[test]
+    fn difficulty_for_bitaxe_gamma() {
+        // ~500 GH/s at 20 shares/min (3-sec interval) should yield ~349
+        let diff = difficulty_for(HashRate::from_gigahashes(500.0)).unwrap();
+        assert!(
+            (300..400).contains(&diff),
+            "Bitaxe Gamma difficulty {diff} not in expected range 300..400"
+        );
+    }
+
+    #[test]
+    fn difficulty_for_always_at_least_one() {
+        // Even at very low hashrate, difficulty should be at least 1
+        let diff = difficulty_for(HashRate::from_megahashes(1.0)).unwrap();
+        assert!(diff >= 1);
+    }
+
+    #[test]
+    fn parses_strategy_names() {
+        for strategy in [
+            SuggestStrategy::MaterialChange,
+            SuggestStrategy::ShareRate,
+            SuggestStrategy::Never,
+        ] {
+            assert_eq!(strategy.as_str().parse(), Ok(strategy));
+        }
+        assert!("sometimes".parse::<SuggestStrategy>().is_err());
+    }
+
+    #[test]
+    fn never_suggests_nothing() {
+        let mut suggester = SuggestStrategy::Never.suggester();
+        let now = Instant::now();
+        let hashrate = HashRate::from_terahashes(1.0);
+        assert_eq!(suggester.initial(hashrate, now), None);
+        assert_eq!(suggester.review(hashrate, now), None);
+    }
+
+    /// A board finding half the shares its hashrate estimate promises is
+    /// asked for a lower difficulty, and the controller settles there.
+    #[test]
+    fn share_rate_corrects_an_optimistic_estimate() {
+        let hashrate = HashRate::from_terahashes(1.0);
+        let base = difficulty_for(hashrate).unwrap();
+        let mut suggester = ShareRateControl::default();
+        let mut now = Instant::now();
+        assert_eq!(suggester.initial(hashrate, now), Some(base));
+
+        // Actual hashrate is half the estimate: work per minute at the
+        // target rate is half what the estimate's difficulty needs
+        let work_per_review = base as f64 * SUGGESTED_SHARE_RATE.as_per_minute() / 2.0;
+        for _ in 0..30 {
+            suggester.work += work_per_review;
+            now += REVIEW_INTERVAL;
+            suggester.review(hashrate, now);
+        }
+
+        let settled = suggester.last_suggested().unwrap() as f64 / base as f64;
+        assert!(
+            (0.4..0.625).contains(&settled),
+            "settled at {settled} of the estimate, expected about half"
+        );
+    }
+
+    #[test]
+    fn share_rate_holds_steady_when_estimate_is_right() {
+        let hashrate = HashRate::from_terahashes(1.0);
+        let base = difficulty_for(hashrate).unwrap();
+        let mut suggester = ShareRateControl::default();
+        let mut now = Instant::now();
+        suggester.initial(hashrate, now);
+
+        let work_per_review = base as f64 * SUGGESTED_SHARE_RATE.as_per_minute();
+        for _ in 0..10 {
+            suggester.share_submitted(Difficulty::from(work_per_review as u64), now);
+            now += REVIEW_INTERVAL;
+            assert_eq!(suggester.review(hashrate, now), None);
+        }
+    }
+}
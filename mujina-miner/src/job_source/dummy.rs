@@ -95,13 +95,13 @@ impl DummySource {
             time: block_881423::TIME,
 
             // Use computed merkle root with authentic coinbase parts
-            merkle_root: MerkleRootKind::Computed(MerkleRootTemplate {
-                coinbase1: block_881423::coinbase1_bytes().to_vec(),
-                extranonce1: block_881423::extranonce1_bytes().to_vec(),
+            merkle_root: MerkleRootKind::Computed(Box::new(MerkleRootTemplate::new(
+                block_881423::coinbase1_bytes().to_vec(),
+                block_881423::extranonce1_bytes().to_vec(),
                 extranonce2_range,
-                coinbase2: block_881423::coinbase2_bytes().to_vec(),
+                block_881423::coinbase2_bytes().to_vec(),
                 merkle_branches,
-            }),
+            ))),
         };
 
         Ok(Self {
@@ -196,6 +196,8 @@ mod tests {
             time: block_881423::TIME,
             version: *block_881423::VERSION,
             extranonce2: None,
+            device_id: None,
+            hash: bitcoin::hashes::Hash::all_zeros(),
         };
         command_tx
             .send(SourceCommand::SubmitShare(share))
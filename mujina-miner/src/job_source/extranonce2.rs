@@ -7,18 +7,24 @@
 //! - **Extranonce2** (variable size) - Typically rolled by software
 //! - **nTime** (32 bits) - Typically rolled by software
 //!
-//! This module provides three types for managing the extranonce2 dimension:
+//! This module provides these types for managing the extranonce2 dimension:
 //!
 //! - `Extranonce2`: An immutable value with a specific size (1-8 bytes)
 //! - `Extranonce2Range`: A range specification [min, max] with no position state
 //! - `Extranonce2Iter`: An iterator that generates `Extranonce2` values from a range
+//! - `Extranonce2Allocator`: A shared cursor handing out batches of a range
 //!
 //! Mining pools allocate a specific byte size for extranonce2 (typically 4-8 bytes),
 //! which determines how many unique coinbase transactions a miner can generate before
 //! needing new work. The range type provides splitting for dividing work between
 //! domains, while the iterator type handles sequential value generation.
+//! Where several consumers draw on one range as they go, rather than
+//! taking a fixed share up front, the allocator reserves batches for each
+//! without a lock and counts what has been handed out.
 
 use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use thiserror::Error;
 
@@ -256,6 +262,102 @@ impl Iterator for Extranonce2Iter {
     }
 }
 
+/// A shared cursor that reserves batches of extranonce2 values from a range.
+///
+/// Clones share the cursor, so a range can be handed to several threads
+/// that each reserve a batch (a sub-range) when they need more work. A
+/// reservation is a single atomic update: no two batches overlap, and the
+/// range is consumed in order with no gaps, so [`reserved()`] is exactly
+/// the number of values handed out.
+///
+/// The last value of a full 8-byte range is never handed out (see
+/// [`Extranonce2Range::len`]).
+///
+/// [`reserved()`]: Extranonce2Allocator::reserved
+#[derive(Debug, Clone)]
+pub struct Extranonce2Allocator {
+    range: Extranonce2Range,
+    /// Offset from `range.min` of the next value to hand out.
+    next: Arc<AtomicU64>,
+}
+
+impl Extranonce2Allocator {
+    /// Create an allocator over `range`, with nothing reserved yet.
+    pub fn new(range: Extranonce2Range) -> Self {
+        Self {
+            range,
+            next: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// The range values are reserved from.
+    pub fn range(&self) -> &Extranonce2Range {
+        &self.range
+    }
+
+    /// Reserve the next `n` values.
+    ///
+    /// The last batch may be shorter than `n`. Returns `None` once the
+    /// range is used up, or if `n` is 0.
+    pub fn reserve(&self, n: u64) -> Option<Extranonce2Range> {
+        if n == 0 {
+            return None;
+        }
+        let total = self.range.len();
+        let start = self
+            .next
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |offset| {
+                (offset < total).then(|| offset + n.min(total - offset))
+            })
+            .ok()?;
+        let count = n.min(total - start);
+        let min = self.range.min + start;
+        Some(Extranonce2Range {
+            min,
+            max: min + (count - 1),
+            size: self.range.size,
+        })
+    }
+
+    /// Number of values reserved so far.
+    pub fn reserved(&self) -> u64 {
+        self.next.load(Ordering::Acquire)
+    }
+
+    /// Number of values not yet reserved.
+    pub fn remaining(&self) -> u64 {
+        self.range.len() - self.reserved()
+    }
+
+    /// Iterate over successive reservations of `batch` values each.
+    ///
+    /// Shares the cursor with this allocator and its clones, so batches
+    /// taken elsewhere are skipped.
+    pub fn batches(&self, batch: u64) -> Extranonce2Batches {
+        Extranonce2Batches {
+            allocator: self.clone(),
+            batch,
+        }
+    }
+}
+
+/// Iterator over batches reserved from an [`Extranonce2Allocator`].
+///
+/// Created via `Extranonce2Allocator::batches()`.
+#[derive(Debug, Clone)]
+pub struct Extranonce2Batches {
+    allocator: Extranonce2Allocator,
+    batch: u64,
+}
+
+impl Iterator for Extranonce2Batches {
+    type Item = Extranonce2Range;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.allocator.reserve(self.batch)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -418,6 +520,63 @@ mod tests {
         assert_eq!(range.len(), u64::MAX);
     }
 
+    // Extranonce2Allocator tests
+    #[test]
+    fn test_allocator_reserves_in_order() {
+        let allocator = Extranonce2Allocator::new(Extranonce2Range::new_range(10, 19, 1).unwrap());
+
+        let first = allocator.reserve(4).unwrap();
+        assert_eq!((first.min, first.max), (10, 13));
+        let second = allocator.reserve(4).unwrap();
+        assert_eq!((second.min, second.max), (14, 17));
+        assert_eq!(allocator.reserved(), 8);
+        assert_eq!(allocator.remaining(), 2);
+
+        // Last batch is short, then nothing is left
+        let last = allocator.reserve(4).unwrap();
+        assert_eq!((last.min, last.max), (18, 19));
+        assert!(allocator.reserve(4).is_none());
+        assert_eq!(allocator.reserved(), 10);
+        assert!(allocator.reserve(0).is_none());
+    }
+
+    #[test]
+    fn test_allocator_batches_share_cursor() {
+        let allocator = Extranonce2Allocator::new(Extranonce2Range::new_range(0, 9, 1).unwrap());
+        let mut batches = allocator.batches(3);
+
+        assert_eq!(batches.next().unwrap().min, 0);
+        // A clone reserving elsewhere takes the next batch
+        allocator.clone().reserve(3);
+        let rest: Vec<(u64, u64)> = batches.map(|r| (r.min, r.max)).collect();
+        assert_eq!(rest, vec![(6, 8), (9, 9)]);
+    }
+
+    #[test]
+    fn test_allocator_concurrent_reservations_are_disjoint() {
+        let allocator = Extranonce2Allocator::new(Extranonce2Range::new_range(0, 9999, 2).unwrap());
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let allocator = allocator.clone();
+                std::thread::spawn(move || {
+                    allocator
+                        .batches(7)
+                        .flat_map(|r| r.iter().map(|en2| en2.value()).collect::<Vec<_>>())
+                        .collect::<Vec<u64>>()
+                })
+            })
+            .collect();
+        let mut values: Vec<u64> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        values.sort_unstable();
+
+        assert_eq!(values, (0..10000).collect::<Vec<u64>>());
+        assert_eq!(allocator.remaining(), 0);
+    }
+
     #[test]
     fn test_iter_combinators() {
         let range = Extranonce2Range::new_range(0, 99, 1).unwrap();
@@ -15,21 +15,53 @@
 //! the pool's actual difficulty. The pool may accept (if configured with low
 //! mindiff) or reject them---either is valid for testing.
 //!
+//! Alternatively, `pool.forced_difficulty` (`MUJINA_POOL_FORCED_DIFFICULTY`,
+//! `--forced-difficulty`) fixes the share difficulty instead. In that mode
+//! the wrapper remembers each job's pool target and only submits shares
+//! that meet it, so the local shares exercise the hashing and validation
+//! path without the pool seeing anything below its difficulty.
+//!
 //! Unreasonably fast rates (around 10 shares/sec/thread or more) may not
 //! be achieved because the scheduler's internal per-thread share filter
 //! caps the rate to prevent flooding.
 
+use std::collections::VecDeque;
+
+use bitcoin::pow::Target;
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, trace, warn};
 
-use super::{JobTemplate, SourceCommand, SourceEvent};
+use super::{JobTemplate, Share, SourceCommand, SourceEvent};
 use crate::types::{Difficulty, HashRate, ShareRate, target_for_share_rate};
 
+/// Pool targets remembered for filtering, one per recent job.
+const POOL_TARGETS_KEPT: usize = 32;
+
+/// What the wrapper forces the share target to.
+#[derive(Debug, Clone, Copy)]
+pub enum ForcedTarget {
+    /// Whatever target yields this share rate at the current hashrate.
+    Rate(ShareRate),
+
+    /// A fixed difficulty; shares are filtered to the pool's target before
+    /// submission.
+    Difficulty(Difficulty),
+}
+
+impl std::fmt::Display for ForcedTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rate(rate) => write!(f, "rate {}", rate),
+            Self::Difficulty(diff) => write!(f, "difficulty {}", diff),
+        }
+    }
+}
+
 /// Configuration for forced share rate wrapper.
 pub struct ForcedRateConfig {
-    /// Target share rate (shares per minute)
-    pub target_rate: ShareRate,
+    /// Share target to force
+    pub target: ForcedTarget,
 }
 
 impl ForcedRateConfig {
@@ -57,9 +89,16 @@ impl ForcedRateConfig {
             }
         };
         Some(Self {
-            target_rate: ShareRate::per_minute(shares_per_min),
+            target: ForcedTarget::Rate(ShareRate::per_minute(shares_per_min)),
         })
     }
+
+    /// Force a fixed share difficulty.
+    pub fn difficulty(difficulty: Difficulty) -> Self {
+        Self {
+            target: ForcedTarget::Difficulty(difficulty),
+        }
+    }
 }
 
 /// Wrapper that overrides share_target to force a specific share rate.
@@ -68,8 +107,11 @@ impl ForcedRateConfig {
 /// intercepting job templates to replace their share_target with one computed
 /// to achieve a target share rate at the current hashrate.
 pub struct ForcedRateSource {
-    /// Cached target share rate (computed once from config)
-    target_rate: ShareRate,
+    /// Share target to force
+    target: ForcedTarget,
+
+    /// Pool share targets of recent jobs, newest last (difficulty mode only)
+    pool_targets: VecDeque<(String, Target)>,
 
     /// Upstream event receiver (from inner source)
     inner_event_rx: mpsc::Receiver<SourceEvent>,
@@ -107,7 +149,8 @@ impl ForcedRateSource {
         shutdown: CancellationToken,
     ) -> Self {
         Self {
-            target_rate: config.target_rate,
+            target: config.target,
+            pool_targets: VecDeque::with_capacity(POOL_TARGETS_KEPT),
             inner_event_rx,
             outer_event_tx,
             inner_command_tx,
@@ -118,18 +161,27 @@ impl ForcedRateSource {
     }
 
     /// Modify job template to use our computed share_target.
-    fn modify_job(&self, mut job: JobTemplate) -> JobTemplate {
-        if self.hashrate.is_zero() {
-            // No hashrate yet; pass through unmodified
-            return job;
-        }
-
-        let target = target_for_share_rate(self.target_rate, self.hashrate);
+    fn modify_job(&mut self, mut job: JobTemplate) -> JobTemplate {
+        let target = match self.target {
+            ForcedTarget::Rate(_) if self.hashrate.is_zero() => {
+                // No hashrate yet; pass through unmodified
+                return job;
+            }
+            ForcedTarget::Rate(rate) => target_for_share_rate(rate, self.hashrate),
+            ForcedTarget::Difficulty(difficulty) => {
+                if self.pool_targets.len() == POOL_TARGETS_KEPT {
+                    self.pool_targets.pop_front();
+                }
+                self.pool_targets
+                    .push_back((job.id.clone(), job.share_target));
+                difficulty.to_target()
+            }
+        };
 
         trace!(
             job_id = %job.id,
             hashrate = %self.hashrate,
-            target_rate = %self.target_rate,
+            forced = %self.target,
             difficulty = %Difficulty::from_target(target),
             "Forcing share target"
         );
@@ -138,6 +190,37 @@ impl ForcedRateSource {
         job
     }
 
+    /// Whether a share should go on to the pool.
+    ///
+    /// In difficulty mode, only shares meeting their job's pool target do;
+    /// in rate mode, every share does.
+    fn should_submit(&self, share: &Share) -> bool {
+        if let ForcedTarget::Rate(_) = self.target {
+            return true;
+        }
+        let pool_target = self
+            .pool_targets
+            .iter()
+            .rev()
+            .find(|(id, _)| *id == share.job_id)
+            .map(|(_, target)| *target);
+        match pool_target {
+            Some(target) if target.is_met_by(share.hash) => true,
+            Some(_) => {
+                trace!(
+                    job_id = %share.job_id,
+                    difficulty = %Difficulty::from_hash(&share.hash),
+                    "Share below pool difficulty, not submitting"
+                );
+                false
+            }
+            None => {
+                debug!(job_id = %share.job_id, "Share for unknown job, not submitting");
+                false
+            }
+        }
+    }
+
     /// Run the wrapper, forwarding events and commands between scheduler and
     /// inner source.
     pub async fn run(mut self) -> anyhow::Result<()> {
@@ -156,7 +239,23 @@ impl ForcedRateSource {
                         SourceEvent::ReplaceJob(job) => {
                             SourceEvent::ReplaceJob(self.modify_job(job))
                         }
-                        SourceEvent::ClearJobs => SourceEvent::ClearJobs,
+                        SourceEvent::ClearJobs => {
+                            self.pool_targets.clear();
+                            SourceEvent::ClearJobs
+                        }
+                        SourceEvent::ShareResult {
+                            job_id,
+                            accepted,
+                            latency,
+                        } => SourceEvent::ShareResult {
+                            job_id,
+                            accepted,
+                            latency,
+                        },
+                        SourceEvent::Remediation(state) => SourceEvent::Remediation(state),
+                        SourceEvent::NtimeGuard(state) => SourceEvent::NtimeGuard(state),
+                        SourceEvent::JobsCoalesced(n) => SourceEvent::JobsCoalesced(n),
+                        SourceEvent::OfflineQueue(state) => SourceEvent::OfflineQueue(state),
                     };
                     self.outer_event_tx.send(modified).await?;
                 }
@@ -167,9 +266,15 @@ impl ForcedRateSource {
                         debug!("Scheduler closed command channel, shutting down wrapper");
                         break;
                     };
-                    if let SourceCommand::UpdateHashRate(hr) = &cmd {
-                        trace!(hashrate = %hr, "Hashrate updated");
-                        self.hashrate = *hr;
+                    match &cmd {
+                        SourceCommand::UpdateHashRate(hr) => {
+                            trace!(hashrate = %hr, "Hashrate updated");
+                            self.hashrate = *hr;
+                        }
+                        SourceCommand::SubmitShare(share) if !self.should_submit(share) => {
+                            continue;
+                        }
+                        _ => {}
                     }
                     self.inner_command_tx.send(cmd).await?;
                 }
@@ -192,7 +297,7 @@ mod tests {
     use bitcoin::BlockHash;
     use bitcoin::block::Version;
     use bitcoin::hashes::Hash;
-    use bitcoin::pow::{CompactTarget, Target};
+    use bitcoin::pow::CompactTarget;
 
     /// Test harness holding all channel endpoints for a ForcedRateSource.
     struct TestHarness {
@@ -210,13 +315,17 @@ mod tests {
 
     impl TestHarness {
         fn new(target_rate: ShareRate) -> Self {
+            Self::with_target(ForcedTarget::Rate(target_rate))
+        }
+
+        fn with_target(target: ForcedTarget) -> Self {
             let (inner_event_tx, inner_event_rx) = mpsc::channel(10);
             let (outer_event_tx, outer_event_rx) = mpsc::channel(10);
             let (inner_cmd_tx, inner_cmd_rx) = mpsc::channel(10);
             let (outer_cmd_tx, outer_cmd_rx) = mpsc::channel(10);
             let shutdown = CancellationToken::new();
 
-            let config = ForcedRateConfig { target_rate };
+            let config = ForcedRateConfig { target };
             let wrapper = ForcedRateSource::new(
                 config,
                 inner_event_rx,
@@ -275,7 +384,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_zero_hashrate_passes_job_unmodified() {
-        let harness = TestHarness::new(ShareRate::per_minute(6.0));
+        let mut harness = TestHarness::new(ShareRate::per_minute(6.0));
 
         // Hashrate is zero by default
         assert!(harness.wrapper.hashrate.is_zero());
@@ -410,4 +519,70 @@ mod tests {
         shutdown.cancel();
         handle.await.unwrap().unwrap();
     }
+
+    fn make_share(job_id: &str, difficulty: u64) -> Share {
+        // Hash exactly at the difficulty's target (little-endian bytes)
+        let target = Difficulty::from(difficulty).to_target();
+        Share {
+            job_id: job_id.to_string(),
+            nonce: 0,
+            time: 0,
+            version: Version::from_consensus(0x20000000),
+            extranonce2: None,
+            device_id: None,
+            hash: BlockHash::from_byte_array(target.to_le_bytes()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_forced_difficulty_filters_shares_to_pool_target() {
+        let forced = Difficulty::from(1);
+        let harness = TestHarness::with_target(ForcedTarget::Difficulty(forced));
+        let TestHarness {
+            wrapper,
+            inner_event_tx,
+            mut outer_event_rx,
+            mut inner_cmd_rx,
+            outer_cmd_tx,
+            shutdown,
+        } = harness;
+
+        let handle = tokio::spawn(wrapper.run());
+
+        // Job arrives with the pool's difficulty and leaves with ours,
+        // even before any hashrate is known
+        let pool_target = Difficulty::from(1000).to_target();
+        inner_event_tx
+            .send(SourceEvent::ReplaceJob(make_test_job("job-1", pool_target)))
+            .await
+            .unwrap();
+        match outer_event_rx.recv().await.unwrap() {
+            SourceEvent::ReplaceJob(job) => assert_eq!(job.share_target, forced.to_target()),
+            _ => panic!("Expected ReplaceJob"),
+        }
+
+        // Below pool difficulty, unknown job, then one that qualifies
+        for share in [
+            make_share("job-1", 10),
+            make_share("job-0", 5000),
+            make_share("job-1", 5000),
+        ] {
+            outer_cmd_tx
+                .send(SourceCommand::SubmitShare(share))
+                .await
+                .unwrap();
+        }
+
+        match inner_cmd_rx.recv().await.unwrap() {
+            SourceCommand::SubmitShare(share) => {
+                assert_eq!(share.job_id, "job-1");
+                assert!(Difficulty::from_hash(&share.hash) >= Difficulty::from(1000));
+            }
+            _ => panic!("Expected SubmitShare"),
+        }
+        assert!(inner_cmd_rx.try_recv().is_err());
+
+        shutdown.cancel();
+        handle.await.unwrap().unwrap();
+    }
 }
@@ -0,0 +1,313 @@
+//! Job source health tracking.
+//!
+//! The scheduler keeps a [`SourceHealth`] per registered source and feeds it
+//! what it observes: jobs arriving, disconnects (ClearJobs) and the pool's
+//! verdicts on submitted shares. The resulting score ranks sources for
+//! failover, so a pool that keeps dropping the connection or rejecting work
+//! loses its place to a steadier one.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Window over which disconnects count against a source.
+const FLAP_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// Number of recent share verdicts used for the reject rate.
+const VERDICT_HISTORY_LEN: usize = 100;
+
+/// Number of recent job intervals used for notify regularity.
+const INTERVAL_HISTORY_LEN: usize = 16;
+
+/// Submission latency at or below which a source gets full marks.
+const LATENCY_GOOD: Duration = Duration::from_millis(500);
+
+/// Submission latency at or above which a source gets no latency credit.
+const LATENCY_BAD: Duration = Duration::from_secs(5);
+
+/// Smoothing factor for the latency moving average.
+const LATENCY_ALPHA: f64 = 0.2;
+
+/// Score weights; they sum to one.
+const WEIGHT_CONNECTION: f64 = 0.4;
+const WEIGHT_REJECTS: f64 = 0.3;
+const WEIGHT_REGULARITY: f64 = 0.15;
+const WEIGHT_LATENCY: f64 = 0.15;
+
+/// Observed behavior of one job source.
+#[derive(Debug)]
+pub struct SourceHealth {
+    /// When tracking started.
+    registered_at: Instant,
+
+    /// Start of the current connected period, if connected.
+    connected_since: Option<Instant>,
+
+    /// Connected time accumulated over finished periods.
+    connected_total: Duration,
+
+    /// Recent disconnect times, oldest first.
+    disconnects: VecDeque<Instant>,
+
+    /// Arrival time of the last job.
+    last_job_at: Option<Instant>,
+
+    /// Recent intervals between jobs, in seconds.
+    job_intervals: VecDeque<f64>,
+
+    /// Recent share verdicts (true = accepted), oldest first.
+    verdicts: VecDeque<bool>,
+
+    /// Moving average of share submission round-trip time.
+    latency: Option<Duration>,
+}
+
+impl SourceHealth {
+    /// Start tracking a source that has not delivered work yet.
+    pub fn new(now: Instant) -> Self {
+        Self {
+            registered_at: now,
+            connected_since: None,
+            connected_total: Duration::ZERO,
+            disconnects: VecDeque::new(),
+            last_job_at: None,
+            job_intervals: VecDeque::new(),
+            verdicts: VecDeque::new(),
+            latency: None,
+        }
+    }
+
+    /// Record a job from the source. A job means the source is connected.
+    pub fn record_job(&mut self, now: Instant) {
+        if self.connected_since.is_none() {
+            self.connected_since = Some(now);
+        }
+
+        if let Some(last) = self.last_job_at.replace(now) {
+            if self.job_intervals.len() == INTERVAL_HISTORY_LEN {
+                self.job_intervals.pop_front();
+            }
+            self.job_intervals
+                .push_back(now.duration_since(last).as_secs_f64());
+        }
+    }
+
+    /// Record the source dropping its work (pool disconnect).
+    pub fn record_disconnect(&mut self, now: Instant) {
+        if let Some(since) = self.connected_since.take() {
+            self.connected_total += now.duration_since(since);
+        }
+        // Intervals spanning an outage say nothing about notify cadence
+        self.last_job_at = None;
+        self.disconnects.push_back(now);
+        self.prune(now);
+    }
+
+    /// Record the pool's verdict on a submitted share.
+    pub fn record_share_result(&mut self, accepted: bool, latency: Duration) {
+        if self.verdicts.len() == VERDICT_HISTORY_LEN {
+            self.verdicts.pop_front();
+        }
+        self.verdicts.push_back(accepted);
+
+        self.latency = Some(match self.latency {
+            Some(avg) => avg.mul_f64(1.0 - LATENCY_ALPHA) + latency.mul_f64(LATENCY_ALPHA),
+            None => latency,
+        });
+    }
+
+    /// Whether the source has had a job since it last dropped its work.
+    pub fn is_connected(&self) -> bool {
+        self.connected_since.is_some()
+    }
+
+    /// Fraction of time since registration spent connected (0.0--1.0).
+    pub fn uptime_ratio(&self, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.registered_at);
+        if elapsed.is_zero() {
+            return 1.0;
+        }
+        let current = self
+            .connected_since
+            .map(|since| now.duration_since(since))
+            .unwrap_or_default();
+        ((self.connected_total + current).as_secs_f64() / elapsed.as_secs_f64()).min(1.0)
+    }
+
+    /// Disconnects within the flap window.
+    pub fn recent_disconnects(&mut self, now: Instant) -> u32 {
+        self.prune(now);
+        self.disconnects.len() as u32
+    }
+
+    /// Rejected fraction of recent verdicts (0.0--1.0).
+    pub fn reject_ratio(&self) -> f64 {
+        if self.verdicts.is_empty() {
+            return 0.0;
+        }
+        let rejected = self.verdicts.iter().filter(|&&accepted| !accepted).count();
+        rejected as f64 / self.verdicts.len() as f64
+    }
+
+    /// Coefficient of variation of job intervals; 0.0 is perfectly regular.
+    ///
+    /// Returns 0.0 until a few intervals have been seen.
+    pub fn notify_jitter(&self) -> f64 {
+        if self.job_intervals.len() < 3 {
+            return 0.0;
+        }
+        let n = self.job_intervals.len() as f64;
+        let mean = self.job_intervals.iter().sum::<f64>() / n;
+        if mean <= 0.0 {
+            return 0.0;
+        }
+        let variance = self
+            .job_intervals
+            .iter()
+            .map(|x| (x - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        variance.sqrt() / mean
+    }
+
+    /// Mean time between recent jobs, once a few have been seen.
+    pub fn job_interval(&self) -> Option<Duration> {
+        if self.job_intervals.len() < 3 {
+            return None;
+        }
+        let mean = self.job_intervals.iter().sum::<f64>() / self.job_intervals.len() as f64;
+        Some(Duration::from_secs_f64(mean))
+    }
+
+    /// Smoothed share submission round-trip time, once shares have been
+    /// submitted.
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    /// Overall health score (0--100); higher is healthier.
+    ///
+    /// Combines connection stability (uptime, scaled down by recent
+    /// disconnects), reject rate, notify regularity and submission
+    /// latency. Factors without data yet count as healthy, so a new
+    /// source starts near the top and earns its way down.
+    pub fn score(&mut self, now: Instant) -> u8 {
+        let connection = self.uptime_ratio(now) / (1.0 + f64::from(self.recent_disconnects(now)));
+        let rejects = 1.0 - self.reject_ratio();
+        let regularity = 1.0 / (1.0 + self.notify_jitter());
+        let latency = self.latency.map_or(1.0, |latency| {
+            let span = (LATENCY_BAD - LATENCY_GOOD).as_secs_f64();
+            let excess = latency.saturating_sub(LATENCY_GOOD).as_secs_f64();
+            (1.0 - excess / span).clamp(0.0, 1.0)
+        });
+
+        let score = WEIGHT_CONNECTION * connection
+            + WEIGHT_REJECTS * rejects
+            + WEIGHT_REGULARITY * regularity
+            + WEIGHT_LATENCY * latency;
+        (score * 100.0).round().clamp(0.0, 100.0) as u8
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while self
+            .disconnects
+            .front()
+            .is_some_and(|&t| now.duration_since(t) > FLAP_WINDOW)
+        {
+            self.disconnects.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steady_source_scores_full() {
+        let start = Instant::now();
+        let mut health = SourceHealth::new(start);
+        for i in 0..10 {
+            health.record_job(start + Duration::from_secs(30 * i));
+            health.record_share_result(true, Duration::from_millis(100));
+        }
+
+        assert_eq!(health.score(start + Duration::from_secs(300)), 100);
+    }
+
+    #[test]
+    fn job_interval_follows_the_notify_cadence() {
+        let start = Instant::now();
+        let mut health = SourceHealth::new(start);
+        for i in 0..3 {
+            health.record_job(start + Duration::from_secs(30 * i));
+        }
+        assert_eq!(health.job_interval(), None);
+
+        health.record_job(start + Duration::from_secs(90));
+        assert_eq!(health.job_interval(), Some(Duration::from_secs(30)));
+
+        // The pool slows down; old intervals age out of the history
+        for i in 1..=INTERVAL_HISTORY_LEN as u64 {
+            health.record_job(start + Duration::from_secs(90 + 120 * i));
+        }
+        assert_eq!(health.job_interval(), Some(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn flapping_source_is_demoted() {
+        let start = Instant::now();
+        let mut steady = SourceHealth::new(start);
+        let mut flapping = SourceHealth::new(start);
+
+        for i in 0..10 {
+            let t = start + Duration::from_secs(30 * i);
+            steady.record_job(t);
+            flapping.record_job(t);
+            flapping.record_disconnect(t + Duration::from_secs(5));
+        }
+
+        let now = start + Duration::from_secs(300);
+        assert_eq!(flapping.recent_disconnects(now), 10);
+        assert!(flapping.score(now) + 30 < steady.score(now));
+    }
+
+    #[test]
+    fn disconnects_expire_after_window() {
+        let start = Instant::now();
+        let mut health = SourceHealth::new(start);
+        health.record_job(start);
+        health.record_disconnect(start + Duration::from_secs(1));
+
+        assert_eq!(health.recent_disconnects(start + Duration::from_secs(2)), 1);
+        assert_eq!(health.recent_disconnects(start + FLAP_WINDOW * 2), 0);
+    }
+
+    #[test]
+    fn rejects_and_latency_lower_score() {
+        let start = Instant::now();
+        let mut health = SourceHealth::new(start);
+        health.record_job(start);
+        for i in 0..10 {
+            health.record_share_result(i % 2 == 0, LATENCY_BAD);
+        }
+
+        assert_eq!(health.reject_ratio(), 0.5);
+        let now = start + Duration::from_secs(60);
+        // Full connection and regularity credit, half the reject credit,
+        // none for latency
+        assert_eq!(health.score(now), 70);
+    }
+
+    #[test]
+    fn irregular_notifies_raise_jitter() {
+        let start = Instant::now();
+        let mut health = SourceHealth::new(start);
+        for secs in [0, 1, 60, 61, 120, 121] {
+            health.record_job(start + Duration::from_secs(secs));
+        }
+
+        assert!(health.notify_jitter() > 0.9);
+    }
+}
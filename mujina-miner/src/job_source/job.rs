@@ -84,4 +84,12 @@ pub struct Share {
 
     /// Extranonce2
     pub extranonce2: Option<Extranonce2>,
+
+    /// ID of the device that found the share, for per-worker attribution
+    /// (None when the thread doesn't identify its hardware)
+    pub device_id: Option<String>,
+
+    /// Hash of the solved header, for ranking shares by the difficulty
+    /// they achieved
+    pub hash: BlockHash,
 }
@@ -0,0 +1,145 @@
+//! Coalescing bursts of jobs from a pool.
+//!
+//! At a block change many pools send several notifies within a few
+//! hundred milliseconds: the empty template first, then the same block
+//! with transactions, sometimes again with more. Each job sent to the
+//! scheduler restarts work on every chip, which costs serial bandwidth
+//! and a moment of hashing per restart, for jobs that are replaced almost
+//! at once.
+//!
+//! [`JobBurst`] passes the first job of a burst through immediately, so a
+//! new block is mined on without delay, then holds later ones until
+//! [`BURST_WINDOW`] has passed since the last job went out. A held job is
+//! replaced by any that arrives before then, so only the latest of the
+//! burst is dispatched. If any of the replaced jobs invalidated earlier
+//! work (`clean_jobs`), so does the one dispatched in their place.
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Least time between two jobs dispatched to the scheduler.
+pub const BURST_WINDOW: Duration = Duration::from_millis(250);
+
+/// Jobs held back while a burst lasts.
+#[derive(Debug)]
+pub struct JobBurst<T> {
+    /// When the next job may go out without being held
+    quiet_at: Option<Instant>,
+    /// Job held back, and whether it or any job it replaced was clean
+    pending: Option<(T, bool)>,
+    /// Jobs replaced before they were dispatched
+    coalesced: u64,
+}
+
+impl<T> Default for JobBurst<T> {
+    fn default() -> Self {
+        Self {
+            quiet_at: None,
+            pending: None,
+            coalesced: 0,
+        }
+    }
+}
+
+impl<T> JobBurst<T> {
+    /// Offer a job arriving at `now`.
+    ///
+    /// Returns the job if it should be dispatched now; otherwise it is
+    /// held until [`deadline`](Self::deadline).
+    pub fn offer(&mut self, job: T, clean_jobs: bool, now: Instant) -> Option<(T, bool)> {
+        if self.quiet_at.is_none_or(|quiet_at| now >= quiet_at) {
+            self.quiet_at = Some(now + BURST_WINDOW);
+            return Some((job, clean_jobs));
+        }
+        let mut clean_jobs = clean_jobs;
+        if let Some((_, was_clean)) = self.pending.take() {
+            self.coalesced += 1;
+            clean_jobs |= was_clean;
+        }
+        self.pending = Some((job, clean_jobs));
+        None
+    }
+
+    /// When the held job is due, if one is held.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.pending.as_ref().and(self.quiet_at)
+    }
+
+    /// Release the held job once its deadline has passed.
+    pub fn flush(&mut self, now: Instant) -> Option<(T, bool)> {
+        if self.deadline().is_none_or(|deadline| now < deadline) {
+            return None;
+        }
+        self.quiet_at = Some(now + BURST_WINDOW);
+        self.pending.take()
+    }
+
+    /// Take the held job, to dispatch it some other way.
+    pub fn take_pending(&mut self) -> Option<(T, bool)> {
+        self.pending.take()
+    }
+
+    /// Drop any held job and start afresh, e.g. after a disconnect.
+    pub fn clear(&mut self) {
+        self.quiet_at = None;
+        self.pending = None;
+    }
+
+    /// Jobs replaced before they were dispatched, so far.
+    pub fn coalesced(&self) -> u64 {
+        self.coalesced
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_job_of_a_burst_goes_out_at_once() {
+        let mut burst = JobBurst::default();
+        let now = Instant::now();
+        assert_eq!(burst.offer("a", true, now), Some(("a", true)));
+        assert_eq!(burst.deadline(), None);
+
+        // After a quiet spell, the next job is immediate too
+        let later = now + BURST_WINDOW;
+        assert_eq!(burst.offer("b", false, later), Some(("b", false)));
+        assert_eq!(burst.coalesced(), 0);
+    }
+
+    #[test]
+    fn only_the_latest_of_a_burst_is_dispatched() {
+        let mut burst = JobBurst::default();
+        let now = Instant::now();
+        burst.offer("a", false, now);
+
+        let step = Duration::from_millis(50);
+        assert_eq!(burst.offer("b", true, now + step), None);
+        assert_eq!(burst.offer("c", false, now + step * 2), None);
+        assert_eq!(burst.offer("d", false, now + step * 3), None);
+        assert_eq!(burst.deadline(), Some(now + BURST_WINDOW));
+        assert_eq!(burst.flush(now + step * 4), None);
+
+        // "d" stands in for "b", which invalidated earlier work
+        assert_eq!(burst.flush(now + BURST_WINDOW), Some(("d", true)));
+        assert_eq!(burst.coalesced(), 2);
+        assert_eq!(burst.deadline(), None);
+
+        // The flush opened a new window
+        assert_eq!(burst.offer("e", false, now + BURST_WINDOW + step), None);
+    }
+
+    #[test]
+    fn clear_drops_the_held_job() {
+        let mut burst = JobBurst::default();
+        let now = Instant::now();
+        burst.offer("a", false, now);
+        burst.offer("b", false, now);
+        burst.clear();
+
+        assert_eq!(burst.deadline(), None);
+        assert_eq!(burst.offer("c", false, now), Some(("c", false)));
+    }
+}
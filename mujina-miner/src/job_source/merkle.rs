@@ -1,10 +1,13 @@
 //! Merkle root specification for mining jobs.
 
+use std::fmt;
+use std::sync::OnceLock;
+
 use anyhow::Result;
 use bitcoin::Transaction;
 use bitcoin::consensus::deserialize;
 use bitcoin::hash_types::TxMerkleNode;
-use bitcoin::hashes::{Hash, sha256d};
+use bitcoin::hashes::{Hash, HashEngine, sha256, sha256d};
 
 use super::{Extranonce2, Extranonce2Range};
 
@@ -29,7 +32,7 @@ pub enum MerkleRootKind {
     /// to generate different merkle roots. Each unique extranonce2 value produces
     /// a different coinbase hash, requiring recomputation of the merkle tree.
     /// This is the standard mode for Stratum v1 pool mining.
-    Computed(MerkleRootTemplate),
+    Computed(Box<MerkleRootTemplate>),
 }
 
 /// Template for computing merkle roots from coinbase transaction parts.
@@ -63,40 +66,137 @@ pub struct MerkleRootTemplate {
     /// After hashing the coinbase transaction, these branches are used to climb
     /// the merkle tree to compute the final merkle root for the block header.
     pub merkle_branches: Vec<TxMerkleNode>,
+
+    /// Coinbase hashed up to extranonce2, filled in by the first root
+    /// computed.
+    prefix: OnceLock<Option<CoinbasePrefix>>,
+}
+
+/// SHA-256 state after the coinbase bytes before extranonce2.
+///
+/// Keeps the coinbase parts it was built from: the fields are public, and
+/// a template changed since no longer matches its prefix.
+#[derive(Clone)]
+struct CoinbasePrefix {
+    engine: sha256::HashEngine,
+    coinbase1: Vec<u8>,
+    extranonce1: Vec<u8>,
+    coinbase2: Vec<u8>,
+    /// Extranonce2 size the coinbase was checked with
+    extranonce2_size: u8,
+}
+
+impl fmt::Debug for CoinbasePrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CoinbasePrefix")
+            .field("bytes_hashed", &self.engine.n_bytes_hashed())
+            .field("extranonce2_size", &self.extranonce2_size)
+            .finish()
+    }
+}
+
+impl CoinbasePrefix {
+    /// Whether the prefix still describes `template` and an extranonce2
+    /// of `extranonce2_size` bytes.
+    fn applies_to(&self, template: &MerkleRootTemplate, extranonce2_size: u8) -> bool {
+        self.extranonce2_size == extranonce2_size
+            && self.coinbase1 == template.coinbase1
+            && self.extranonce1 == template.extranonce1
+            && self.coinbase2 == template.coinbase2
+    }
 }
 
 impl MerkleRootTemplate {
+    pub fn new(
+        coinbase1: Vec<u8>,
+        extranonce1: Vec<u8>,
+        extranonce2_range: Extranonce2Range,
+        coinbase2: Vec<u8>,
+        merkle_branches: Vec<TxMerkleNode>,
+    ) -> Self {
+        Self {
+            coinbase1,
+            extranonce1,
+            extranonce2_range,
+            coinbase2,
+            merkle_branches,
+            prefix: OnceLock::new(),
+        }
+    }
+
     /// Compute merkle root for a specific extranonce2 value.
     ///
-    /// Builds the complete coinbase transaction by concatenating parts with the
-    /// given extranonce2, computes its txid, then climbs the merkle tree using
-    /// the branches to produce the final merkle root.
+    /// Hashes the coinbase transaction for the given extranonce2 to get its
+    /// txid, then climbs the merkle tree using the branches to produce the
+    /// final merkle root. The coinbase bytes before extranonce2 are hashed
+    /// once per template, so each root only hashes extranonce2 and
+    /// coinbase2; a coinbase whose txid isn't the hash of its bytes (one
+    /// sent with witness data) is built and parsed in full every time.
     ///
     /// This is a pure function - it doesn't modify the template. Callers manage
     /// extranonce2 iteration externally via `Extranonce2Iter`.
     pub fn compute_merkle_root(&self, extranonce2: &Extranonce2) -> Result<TxMerkleNode> {
-        // Build complete coinbase transaction
+        let prefix = self
+            .prefix
+            .get_or_init(|| self.hash_prefix(extranonce2.size()))
+            .as_ref()
+            .filter(|prefix| prefix.applies_to(self, extranonce2.size()));
+        let mut current_hash = match prefix {
+            Some(prefix) => {
+                let mut engine = prefix.engine.clone();
+                engine.input(&extranonce2.value().to_le_bytes()[..extranonce2.size() as usize]);
+                engine.input(&self.coinbase2);
+                sha256d::Hash::from_engine(engine).to_byte_array()
+            }
+            None => self
+                .parse_coinbase(extranonce2)?
+                .compute_txid()
+                .to_byte_array(),
+        };
+
+        // Climb the merkle tree
+        for branch in &self.merkle_branches {
+            let mut engine = sha256d::Hash::engine();
+            engine.input(&current_hash);
+            engine.input(branch.as_byte_array());
+            current_hash = sha256d::Hash::from_engine(engine).to_byte_array();
+        }
+
+        Ok(TxMerkleNode::from_byte_array(current_hash))
+    }
+
+    /// Build and parse the coinbase transaction for `extranonce2`.
+    fn parse_coinbase(&self, extranonce2: &Extranonce2) -> Result<Transaction> {
         let mut coinbase_bytes = Vec::new();
         coinbase_bytes.extend_from_slice(&self.coinbase1);
         coinbase_bytes.extend_from_slice(&self.extranonce1);
         extranonce2.extend_vec(&mut coinbase_bytes);
         coinbase_bytes.extend_from_slice(&self.coinbase2);
+        Ok(deserialize(&coinbase_bytes)?)
+    }
 
-        // Parse and compute coinbase txid
-        let coinbase_tx: Transaction = deserialize(&coinbase_bytes)?;
-        let mut current_hash = coinbase_tx.compute_txid().to_byte_array();
-
-        // Climb the merkle tree
-        for branch in &self.merkle_branches {
-            let mut combined = Vec::new();
-            combined.extend_from_slice(&current_hash);
-            combined.extend_from_slice(branch.as_byte_array());
-
-            let parent_hash = sha256d::Hash::hash(&combined);
-            current_hash = parent_hash.to_byte_array();
+    /// Hash the coinbase up to extranonce2, if its txid is the hash of its
+    /// bytes as sent.
+    ///
+    /// That holds when it parses as a transaction without witness data.
+    /// The layout doesn't depend on extranonce2's value, only its size, so
+    /// one check covers every extranonce2 of `extranonce2_size` bytes.
+    fn hash_prefix(&self, extranonce2_size: u8) -> Option<CoinbasePrefix> {
+        let extranonce2 = Extranonce2::new(0, extranonce2_size).ok()?;
+        let coinbase = self.parse_coinbase(&extranonce2).ok()?;
+        if coinbase.input.iter().any(|input| !input.witness.is_empty()) {
+            return None;
         }
-
-        Ok(TxMerkleNode::from_byte_array(current_hash))
+        let mut engine = sha256d::Hash::engine();
+        engine.input(&self.coinbase1);
+        engine.input(&self.extranonce1);
+        Some(CoinbasePrefix {
+            engine,
+            coinbase1: self.coinbase1.clone(),
+            extranonce1: self.extranonce1.clone(),
+            coinbase2: self.coinbase2.clone(),
+            extranonce2_size,
+        })
     }
 }
 
@@ -105,18 +205,57 @@ mod tests {
     use super::*;
     use crate::job_source::test_blocks::block_881423;
 
+    /// Template for block 881,423 with coinbase parts as the pool sent
+    /// them, in legacy format.
+    fn legacy_template() -> MerkleRootTemplate {
+        MerkleRootTemplate::new(
+            block_881423::coinbase1_legacy_bytes(),
+            block_881423::extranonce1_bytes().to_vec(),
+            Extranonce2Range::new(block_881423::EXTRANONCE2.size()).unwrap(),
+            block_881423::coinbase2_legacy_bytes(),
+            block_881423::MERKLE_BRANCHES.clone(),
+        )
+    }
+
+    /// Merkle root computed the straightforward way: build and parse the
+    /// whole coinbase, take its txid, climb the branches.
+    fn reference_merkle_root(
+        template: &MerkleRootTemplate,
+        extranonce2: &Extranonce2,
+    ) -> Result<TxMerkleNode> {
+        let mut current_hash = template
+            .parse_coinbase(extranonce2)?
+            .compute_txid()
+            .to_byte_array();
+        for branch in &template.merkle_branches {
+            let mut combined = Vec::new();
+            combined.extend_from_slice(&current_hash);
+            combined.extend_from_slice(branch.as_byte_array());
+            current_hash = TxMerkleNode::hash(&combined).to_byte_array();
+        }
+        Ok(TxMerkleNode::from_byte_array(current_hash))
+    }
+
+    fn cached_prefix_len(template: &MerkleRootTemplate) -> Option<usize> {
+        template
+            .prefix
+            .get()
+            .and_then(Option::as_ref)
+            .map(|prefix| prefix.engine.n_bytes_hashed())
+    }
+
     #[test]
     fn test_compute_merkle_root_with_block_881423() {
         let extranonce2 = *block_881423::EXTRANONCE2;
 
         // Construct a template from golden values
-        let template = MerkleRootTemplate {
-            coinbase1: block_881423::coinbase1_bytes().to_vec(),
-            extranonce1: block_881423::extranonce1_bytes().to_vec(),
-            extranonce2_range: Extranonce2Range::new(extranonce2.size()).unwrap(),
-            coinbase2: block_881423::coinbase2_bytes().to_vec(),
-            merkle_branches: block_881423::MERKLE_BRANCHES.clone(),
-        };
+        let template = MerkleRootTemplate::new(
+            block_881423::coinbase1_bytes().to_vec(),
+            block_881423::extranonce1_bytes().to_vec(),
+            Extranonce2Range::new(extranonce2.size()).unwrap(),
+            block_881423::coinbase2_bytes().to_vec(),
+            block_881423::MERKLE_BRANCHES.clone(),
+        );
 
         // Compute merkle root
         let computed_merkle_root = template
@@ -129,5 +268,94 @@ mod tests {
             *block_881423::MERKLE_ROOT,
             "Computed merkle root doesn't match block 881,423"
         );
+
+        // The coinbase as mined carries a witness, so its txid isn't the
+        // hash of its bytes and there is no prefix to cache
+        assert_eq!(cached_prefix_len(&template), None);
+    }
+
+    #[test]
+    fn legacy_coinbase_uses_cached_prefix() {
+        let template = legacy_template();
+        assert_eq!(
+            template
+                .compute_merkle_root(&block_881423::EXTRANONCE2)
+                .unwrap(),
+            *block_881423::MERKLE_ROOT
+        );
+        assert_eq!(
+            cached_prefix_len(&template),
+            Some(template.coinbase1.len() + template.extranonce1.len())
+        );
+    }
+
+    #[test]
+    fn cached_prefix_matches_reference() {
+        let template = legacy_template();
+        let size = block_881423::EXTRANONCE2.size();
+        let values = [
+            0,
+            1,
+            0xff,
+            0x1234_5678,
+            u32::MAX as u64,
+            block_881423::EXTRANONCE2.value(),
+        ];
+        for value in values {
+            let extranonce2 = Extranonce2::new(value, size).unwrap();
+            assert_eq!(
+                template.compute_merkle_root(&extranonce2).unwrap(),
+                reference_merkle_root(&template, &extranonce2).unwrap(),
+                "extranonce2 {value:#x}"
+            );
+        }
+    }
+
+    #[test]
+    fn other_extranonce2_size_skips_cached_prefix() {
+        let template = legacy_template();
+        template
+            .compute_merkle_root(&block_881423::EXTRANONCE2)
+            .unwrap();
+        let extranonce2 = Extranonce2::new(0x1234_5678_9abc, 8).unwrap();
+        assert_eq!(
+            template.compute_merkle_root(&extranonce2).ok(),
+            reference_merkle_root(&template, &extranonce2).ok()
+        );
+    }
+
+    #[test]
+    fn changed_template_skips_stale_prefix() {
+        let mut template = legacy_template();
+        template
+            .compute_merkle_root(&block_881423::EXTRANONCE2)
+            .unwrap();
+
+        template.extranonce1 = vec![0xaa, 0xbb, 0xcc, 0xdd];
+        assert_eq!(
+            template
+                .compute_merkle_root(&block_881423::EXTRANONCE2)
+                .unwrap(),
+            reference_merkle_root(&template, &block_881423::EXTRANONCE2).unwrap()
+        );
+    }
+
+    /// Floor on merkle root computation, which runs for every nonce
+    /// validated and every job sent to a chip. Timing-sensitive, so it's
+    /// left out of the plain test run; `just perf` runs it in release.
+    #[test]
+    #[ignore = "benchmark"]
+    fn merkle_root_throughput() {
+        const ROOTS: u32 = 2_000;
+        let extranonce2 = *block_881423::EXTRANONCE2;
+        let template = legacy_template();
+
+        let start = std::time::Instant::now();
+        for _ in 0..ROOTS {
+            std::hint::black_box(template.compute_merkle_root(&extranonce2).unwrap());
+        }
+        let rate = ROOTS as f64 / start.elapsed().as_secs_f64();
+
+        assert!(rate >= 500.0, "only {rate:.0} merkle roots/s");
     }
 }
@@ -43,11 +43,15 @@
 
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
 use tokio::sync::mpsc;
 
+use super::ntime_window::NtimeGuardState;
+use super::remediation::RemediationState;
 use super::{JobTemplate, Share};
+use crate::api_client::types::OfflineQueueState;
 use crate::types::HashRate;
 
 /// Handle to a job source (identity + communication).
@@ -139,6 +143,43 @@ pub enum SourceEvent {
     /// Scheduler should cancel all work from this source and wait for new job.
     /// Used during pool disconnection or when awaiting new block.
     ClearJobs,
+
+    /// The pool's verdict on a submitted share.
+    ///
+    /// Feeds the scheduler's health score for this source (reject rate and
+    /// submission latency). Sources without an upstream verdict don't send
+    /// it.
+    ShareResult {
+        /// Job the share was for.
+        job_id: String,
+        /// Whether the share was accepted.
+        accepted: bool,
+        /// Round-trip time from submission to verdict.
+        latency: Duration,
+    },
+
+    /// The source's reject rate remediation changed state.
+    ///
+    /// Reported for the API only; the scheduler takes no action on it.
+    Remediation(RemediationState),
+
+    /// The source clamped a job's ntime or dropped a share outside the
+    /// pool's ntime window.
+    ///
+    /// Reported for the API only; the scheduler takes no action on it.
+    NtimeGuard(NtimeGuardState),
+
+    /// Total jobs the source has replaced with a later one before sending
+    /// them, while the pool sent jobs in a burst.
+    ///
+    /// Reported for the API only; the scheduler takes no action on it.
+    JobsCoalesced(u64),
+
+    /// The source held, recovered or discarded shares found while its pool
+    /// was unreachable.
+    ///
+    /// Reported for the API only; the scheduler takes no action on it.
+    OfflineQueue(OfflineQueueState),
 }
 
 /// Commands to sources (pull, coordinator-initiated).
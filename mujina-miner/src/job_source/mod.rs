@@ -42,31 +42,48 @@
 //! ## Share Difficulty
 //!
 //! Sources receive share difficulty from their upstream (pool, node, etc.)
-//! and report it directly in [`JobTemplate::share_target`]. We avoid
-//! suggesting difficulty to pools because some (notably Ocean) disconnect
-//! clients that suggest inappropriately low values.
+//! and report it directly in [`JobTemplate::share_target`]. Pool sources
+//! may suggest a difficulty suited to the expected hashrate, following a
+//! [`SuggestStrategy`] chosen per source, since pools react differently
+//! to suggestions (notably Ocean disconnects clients that suggest
+//! inappropriately low values).
 //!
 //! Rate limiting to prevent share flooding is the scheduler's responsibility.
 //! Sources declare their maximum share rate at registration time, and the
 //! scheduler enforces it.
 
 // Submodules
+mod clock_skew;
+mod difficulty;
 pub mod dummy;
 mod extranonce2;
 pub mod forced_rate;
+mod health;
 pub(crate) mod job;
+mod job_burst;
 mod merkle;
 mod messages;
+mod ntime_window;
+mod remediation;
 pub mod stratum_v1;
+mod submit_queue;
 pub mod test_blocks;
 mod version;
 
 // Re-export types from submodules
-pub use extranonce2::{Extranonce2, Extranonce2Error, Extranonce2Iter, Extranonce2Range};
+pub use difficulty::{DifficultySuggester, SuggestStrategy};
+pub use extranonce2::{
+    Extranonce2, Extranonce2Allocator, Extranonce2Batches, Extranonce2Error, Extranonce2Iter,
+    Extranonce2Range,
+};
+pub use health::SourceHealth;
 pub use job::{JobTemplate, Share};
 pub use merkle::{MerkleRootKind, MerkleRootTemplate};
 pub use messages::{SourceCommand, SourceEvent, SourceHandle};
-pub use version::{GeneralPurposeBits, VersionTemplate, VersionTemplateError};
+pub use version::{
+    BIP320_VERSION_MASK, GeneralPurposeBits, VersionTemplate, VersionTemplateError,
+    effective_rolling_mask,
+};
 
 // TODO: Add HeaderTemplate type (Level 2 in the hierarchy)
 // TODO: Implement dummy source
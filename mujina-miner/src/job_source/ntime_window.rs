@@ -0,0 +1,156 @@
+//! Keeping share ntime inside the range a pool accepts for each job.
+//!
+//! A pool checks a share's ntime against the job it was mined on: ckpool,
+//! for one, refuses anything before the job's ntime or more than 7000 s
+//! past it. Several things here push ntime forward from the value the pool
+//! sent: hash threads roll it, a re-issued job starts where the rolling
+//! left off, and ntime correction moves a lagging job to the pool's
+//! estimated clock. Each is bounded, but on a job that lives long enough
+//! they add up, and every share past the limit comes back "time-too-new".
+//!
+//! [`NtimeWindow`] remembers the pool's ntime for recent jobs. A job is
+//! started no later than leaves the threads room to roll without leaving
+//! the window, and a share outside it is dropped rather than submitted.
+
+use std::collections::VecDeque;
+
+pub use crate::api_client::types::NtimeGuardState;
+use crate::scheduler::NTIME_ROLL_LIMIT;
+
+use super::Share;
+
+/// Furthest past a job's ntime that a share's may be, in seconds.
+pub const NTIME_TOLERANCE: u32 = 7000;
+
+/// Number of jobs whose windows are remembered.
+const JOB_HISTORY_LEN: usize = 16;
+
+/// Accepted ntime ranges of recent jobs.
+#[derive(Debug, Default)]
+pub struct NtimeWindow {
+    /// Job ID and the pool's ntime for it, oldest first.
+    jobs: VecDeque<(String, u32)>,
+    state: NtimeGuardState,
+}
+
+impl NtimeWindow {
+    /// Record a job from the pool with the ntime it was sent with.
+    ///
+    /// A `clean_jobs` job supersedes every earlier one.
+    pub fn note_job(&mut self, job_id: &str, ntime: u32, clean_jobs: bool) {
+        if clean_jobs {
+            self.jobs.clear();
+        }
+        self.jobs.retain(|(id, _)| id != job_id);
+        self.jobs.push_back((job_id.to_string(), ntime));
+        if self.jobs.len() > JOB_HISTORY_LEN {
+            self.jobs.pop_front();
+        }
+    }
+
+    /// Accepted ntime range for a job, if it is known.
+    fn range(&self, job_id: &str) -> Option<(u32, u32)> {
+        self.jobs
+            .iter()
+            .find(|(id, _)| id == job_id)
+            .map(|&(_, ntime)| (ntime, ntime.saturating_add(NTIME_TOLERANCE)))
+    }
+
+    /// Start time for work on a job, pulled back if rolling from `time`
+    /// could leave the job's window.
+    pub fn clamp_start(&mut self, job_id: &str, time: u32) -> u32 {
+        let Some((earliest, latest)) = self.range(job_id) else {
+            return time;
+        };
+        let limit = latest.saturating_sub(NTIME_ROLL_LIMIT).max(earliest);
+        if time <= limit {
+            return time;
+        }
+        self.state.clamped_jobs += 1;
+        limit
+    }
+
+    /// Whether a share's ntime is inside its job's window.
+    ///
+    /// Shares on jobs no longer remembered pass; whether they are stale
+    /// is for the pool to say. A share outside the window is counted as
+    /// dropped.
+    pub fn admits(&mut self, share: &Share) -> bool {
+        let Some((earliest, latest)) = self.range(&share.job_id) else {
+            return true;
+        };
+        if (earliest..=latest).contains(&share.time) {
+            return true;
+        }
+        self.state.dropped_shares += 1;
+        false
+    }
+
+    /// Counts for the API.
+    pub fn state(&self) -> NtimeGuardState {
+        self.state.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::BlockHash;
+    use bitcoin::block::Version;
+    use bitcoin::hashes::Hash;
+
+    use super::*;
+
+    const NTIME: u32 = 0x6500_0000;
+
+    fn share(job_id: &str, time: u32) -> Share {
+        Share {
+            job_id: job_id.into(),
+            nonce: 0,
+            time,
+            version: Version::TWO,
+            extranonce2: None,
+            device_id: None,
+            hash: BlockHash::all_zeros(),
+        }
+    }
+
+    #[test]
+    fn start_leaves_room_to_roll() {
+        let mut window = NtimeWindow::default();
+        window.note_job("a", NTIME, true);
+
+        assert_eq!(window.clamp_start("a", NTIME + 120), NTIME + 120);
+        assert_eq!(window.state().clamped_jobs, 0);
+
+        let limit = NTIME + NTIME_TOLERANCE - NTIME_ROLL_LIMIT;
+        assert_eq!(window.clamp_start("a", NTIME + NTIME_TOLERANCE), limit);
+        assert_eq!(window.state().clamped_jobs, 1);
+
+        // Unknown jobs are left alone
+        assert_eq!(window.clamp_start("b", u32::MAX), u32::MAX);
+    }
+
+    #[test]
+    fn drops_shares_outside_the_window() {
+        let mut window = NtimeWindow::default();
+        window.note_job("a", NTIME, true);
+
+        assert!(window.admits(&share("a", NTIME)));
+        assert!(window.admits(&share("a", NTIME + NTIME_TOLERANCE)));
+        assert!(!window.admits(&share("a", NTIME + NTIME_TOLERANCE + 1)));
+        assert!(!window.admits(&share("a", NTIME - 1)));
+        assert!(window.admits(&share("gone", NTIME - 1)));
+        assert_eq!(window.state().dropped_shares, 2);
+    }
+
+    #[test]
+    fn clean_jobs_forgets_earlier_windows() {
+        let mut window = NtimeWindow::default();
+        window.note_job("a", NTIME, true);
+        window.note_job("b", NTIME + 30, true);
+
+        // "a" is no longer known, so its shares aren't judged here
+        assert!(window.admits(&share("a", NTIME + NTIME_TOLERANCE + 1)));
+        assert!(!window.admits(&share("b", NTIME)));
+    }
+}
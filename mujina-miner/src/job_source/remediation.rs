@@ -0,0 +1,201 @@
+//! Automatic remediation of a high share reject rate.
+//!
+//! When a pool rejects a large fraction of shares, the cause is usually one
+//! of a few things this side can fix: ntime that has drifted from the
+//! pool's clock, work left over from a job the pool has moved on from, a
+//! share difficulty out of step with the pool's, or a session gone bad.
+//! [`Remediator`] watches share verdicts and, once the reject rate crosses
+//! [`REJECT_THRESHOLD`], walks through [`RemediationStep`]s in that order,
+//! giving each a fresh batch of verdicts to prove itself. If none helps, it
+//! backs off before trying again, doubling the wait each time.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+pub use crate::api_client::types::{RemediationState, RemediationStep};
+
+/// Reject fraction at or above which remediation starts.
+pub const REJECT_THRESHOLD: f64 = 0.3;
+
+/// Verdicts needed before judging the reject rate, at the start and after
+/// each step.
+const MIN_VERDICTS: usize = 10;
+
+/// Wait after an unsuccessful sequence before trying again.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// Longest wait between sequences.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// Steps in the order they are tried.
+const STEPS: [RemediationStep; 4] = [
+    RemediationStep::ResyncNtime,
+    RemediationStep::RefreshJob,
+    RemediationStep::ResyncDifficulty,
+    RemediationStep::Reconnect,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    /// Watching the reject rate.
+    Watching,
+    /// Waiting to see whether `STEPS[index]` helped.
+    Trying(usize),
+    /// Gave up until the given time.
+    BackingOff(Instant),
+}
+
+/// Decides when and how to remediate from a source's share verdicts.
+#[derive(Debug)]
+pub struct Remediator {
+    /// Verdicts since the last phase change, newest last.
+    verdicts: VecDeque<bool>,
+    phase: Phase,
+    backoff: Duration,
+    sequences: u64,
+}
+
+impl Default for Remediator {
+    fn default() -> Self {
+        Self {
+            verdicts: VecDeque::with_capacity(MIN_VERDICTS),
+            phase: Phase::Watching,
+            backoff: INITIAL_BACKOFF,
+            sequences: 0,
+        }
+    }
+}
+
+impl Remediator {
+    /// Record a share verdict, returning a step to take now, if any.
+    pub fn record_verdict(&mut self, accepted: bool, now: Instant) -> Option<RemediationStep> {
+        if let Phase::BackingOff(until) = self.phase {
+            if now < until {
+                return None;
+            }
+            self.enter(Phase::Watching);
+        }
+
+        if self.verdicts.len() == MIN_VERDICTS {
+            self.verdicts.pop_front();
+        }
+        self.verdicts.push_back(accepted);
+        if self.verdicts.len() < MIN_VERDICTS {
+            return None;
+        }
+        let failing = self.reject_ratio() >= REJECT_THRESHOLD;
+
+        match self.phase {
+            Phase::Watching if failing => {
+                self.sequences += 1;
+                self.enter(Phase::Trying(0));
+                Some(STEPS[0])
+            }
+            Phase::Watching => None,
+            Phase::Trying(_) if !failing => {
+                self.backoff = INITIAL_BACKOFF;
+                self.enter(Phase::Watching);
+                None
+            }
+            Phase::Trying(index) if index + 1 < STEPS.len() => {
+                self.enter(Phase::Trying(index + 1));
+                Some(STEPS[index + 1])
+            }
+            Phase::Trying(_) => {
+                self.enter(Phase::BackingOff(now + self.backoff));
+                self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+                None
+            }
+            Phase::BackingOff(_) => unreachable!("left above"),
+        }
+    }
+
+    /// Whether the last sequence ran out of steps and is backing off.
+    pub fn is_backing_off(&self) -> bool {
+        matches!(self.phase, Phase::BackingOff(_))
+    }
+
+    /// Current state, for the API.
+    pub fn state(&self, now: Instant) -> RemediationState {
+        RemediationState {
+            step: match self.phase {
+                Phase::Trying(index) => Some(STEPS[index]),
+                _ => None,
+            },
+            retry_in_secs: match self.phase {
+                Phase::BackingOff(until) => Some(until.saturating_duration_since(now).as_secs()),
+                _ => None,
+            },
+            sequences: self.sequences,
+        }
+    }
+
+    fn enter(&mut self, phase: Phase) {
+        self.phase = phase;
+        self.verdicts.clear();
+    }
+
+    fn reject_ratio(&self) -> f64 {
+        let rejected = self.verdicts.iter().filter(|&&a| !a).count();
+        rejected as f64 / self.verdicts.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `n` verdicts, returning the steps taken.
+    fn feed(r: &mut Remediator, accepted: bool, n: usize, now: Instant) -> Vec<RemediationStep> {
+        (0..n)
+            .filter_map(|_| r.record_verdict(accepted, now))
+            .collect()
+    }
+
+    #[test]
+    fn healthy_source_is_left_alone() {
+        let mut r = Remediator::default();
+        let now = Instant::now();
+        assert!(feed(&mut r, true, 100, now).is_empty());
+        assert_eq!(r.state(now), RemediationState::default());
+    }
+
+    #[test]
+    fn walks_steps_in_order_then_backs_off() {
+        let mut r = Remediator::default();
+        let now = Instant::now();
+
+        assert_eq!(feed(&mut r, false, MIN_VERDICTS, now), [STEPS[0]]);
+        assert_eq!(r.state(now).step, Some(RemediationStep::ResyncNtime));
+        assert_eq!(feed(&mut r, false, MIN_VERDICTS * 3, now), STEPS[1..]);
+
+        // Nothing helped
+        assert!(feed(&mut r, false, MIN_VERDICTS, now).is_empty());
+        assert!(r.is_backing_off());
+        assert_eq!(r.state(now).retry_in_secs, Some(INITIAL_BACKOFF.as_secs()));
+
+        // Retries once the backoff expires, then backs off for longer
+        assert!(feed(&mut r, false, MIN_VERDICTS, now).is_empty());
+        let later = now + INITIAL_BACKOFF;
+        assert_eq!(feed(&mut r, false, MIN_VERDICTS, later), [STEPS[0]]);
+        feed(&mut r, false, MIN_VERDICTS * 4, later);
+        assert_eq!(
+            r.state(later).retry_in_secs,
+            Some(INITIAL_BACKOFF.as_secs() * 2)
+        );
+        assert_eq!(r.state(later).sequences, 2);
+    }
+
+    #[test]
+    fn stops_once_a_step_helps() {
+        let mut r = Remediator::default();
+        let now = Instant::now();
+
+        feed(&mut r, false, MIN_VERDICTS, now);
+        assert!(feed(&mut r, true, MIN_VERDICTS, now).is_empty());
+        assert_eq!(r.state(now).step, None);
+        assert!(!r.is_backing_off());
+    }
+}
@@ -5,30 +5,35 @@
 //! the internal JobTemplate/Share types used by the scheduler.
 
 use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, VecDeque};
 use std::hash::{BuildHasher, Hasher};
 use std::time::Duration;
 
 use anyhow::Result;
+use bitcoin::BlockHash;
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
+use crate::api_client::types::OfflineQueueState;
+use crate::share_audit::ShareAudit;
+use crate::share_proof;
 use crate::stratum_v1::{
-    ClientCommand, ClientEvent, Connector, JobNotification, PoolConfig, StratumV1Client,
+    ClientCommand, ClientEvent, Connector, JobNotification, PoolConfig, PoolQuirks, StratumV1Client,
 };
 use crate::tracing::prelude::*;
-use crate::types::{Difficulty, HashRate, ShareRate, target_for_share_rate};
-
+use crate::types::{Difficulty, HashRate};
+
+use super::clock_skew::{self, ClockSkew, SkewAlert};
+use super::difficulty::{DifficultySuggester, REVIEW_INTERVAL, SuggestStrategy};
+use super::job_burst::JobBurst;
+use super::ntime_window::NtimeWindow;
+use super::remediation::{REJECT_THRESHOLD, RemediationStep, Remediator};
+use super::submit_queue::{Dequeued, SubmitQueue};
 use super::{
     Extranonce2Range, GeneralPurposeBits, JobTemplate, MerkleRootKind, MerkleRootTemplate, Share,
-    SourceCommand, SourceEvent, VersionTemplate,
+    SourceCommand, SourceEvent, VersionTemplate, effective_rolling_mask,
 };
 
-/// Target share rate for suggest_difficulty: 20 shares/min (one every 3 sec).
-const SUGGESTED_SHARE_RATE: ShareRate = ShareRate::from_interval(Duration::from_secs(3));
-
-/// Re-suggest when new difficulty is >2x or <0.5x the last-suggested value.
-const MATERIAL_CHANGE_FACTOR: f64 = 2.0;
-
 /// Minimum connection duration before backoff resets on disconnect.
 ///
 /// If a connection was alive for at least this long, the next reconnect
@@ -37,6 +42,23 @@ const MATERIAL_CHANGE_FACTOR: f64 = 2.0;
 /// flapping pool that accepts and immediately drops.
 const STABLE_CONNECTION_THRESHOLD: Duration = Duration::from_secs(60);
 
+/// Most shares held back while the pool is slow or reconnecting.
+const SUBMIT_QUEUE_CAPACITY: usize = 64;
+
+/// Age past which a queued share is assumed stale and dropped.
+const SUBMIT_QUEUE_MAX_AGE: Duration = Duration::from_secs(120);
+
+/// Commands buffered between the source and the client task.
+///
+/// The client submits one share at a time, waiting for each verdict.
+/// Shares in this buffer go out in arrival order, so it is kept small;
+/// any backlog collects in the submit queue, where it is ranked.
+const CLIENT_COMMAND_BUFFER: usize = 2;
+
+/// Submitted shares remembered for matching verdicts in the share audit log
+/// and share proofs.
+const AWAITING_VERDICT_LEN: usize = 16;
+
 /// Exponential backoff for reconnection timing.
 ///
 /// Starts at `initial` and doubles after each call to `next_delay()`,
@@ -125,11 +147,83 @@ pub struct StratumV1Source {
     /// Expected hashrate (an estimate, not a measurement)
     expected_hashrate: HashRate,
 
-    /// Last difficulty we suggested to the pool (for material-change detection)
-    last_suggested_difficulty: Option<u64>,
+    /// Decides when and what difficulty to suggest to the pool
+    suggester: Box<dyn DifficultySuggester>,
+
+    /// Most recent job from the pool and when it arrived, for re-issuing
+    /// when session parameters change mid-job
+    last_job: Option<(JobNotification, tokio::time::Instant)>,
 
     /// Factory for creating transport connections.
     connector: Box<dyn Connector>,
+
+    /// Pool verdicts per worker name, for per-device accounting
+    worker_shares: HashMap<String, WorkerShareCounts>,
+
+    /// Shares waiting for the client to take them
+    submit_queue: SubmitQueue,
+
+    /// Extranonce1 of the most recent session, to tell whether a
+    /// reconnect resumed it
+    last_extranonce1: Option<Vec<u8>>,
+
+    /// Pool clock relative to ours, from job ntime
+    clock_skew: ClockSkew,
+
+    /// Move lagging job ntime forward to the pool's estimated clock
+    ntime_correction: bool,
+
+    /// Ntime the pool accepts on each recent job
+    ntime_window: NtimeWindow,
+
+    /// Jobs held back while the pool sends them in a burst
+    job_burst: JobBurst<JobNotification>,
+
+    /// Responds to a high share reject rate
+    remediator: Remediator,
+
+    /// Remediation step that needs the connection loop to carry it out
+    pending_remediation: Option<RemediationStep>,
+
+    /// Where share trips are stamped, if anywhere
+    share_audit: ShareAudit,
+
+    /// Shares sent to the pool and awaiting a verdict, oldest first, so
+    /// the verdict can be stamped in the share audit log
+    awaiting_verdict: VecDeque<(String, u32, BlockHash)>,
+
+    /// Quirks of the pool: its known profile, any configured, and those
+    /// the client has found, kept across reconnects
+    quirks: PoolQuirks,
+
+    /// How long shares found while the pool is unreachable are held for
+    /// it, if they are
+    offline_max_age: Option<Duration>,
+
+    /// When the pool became unreachable, while it is
+    offline_since: Option<tokio::time::Instant>,
+
+    /// Previous block of the job the hash threads were left on when the
+    /// pool became unreachable, while they still are
+    offline_prev_hash: Option<BlockHash>,
+
+    /// Held shares submitted after reconnecting, and how many the pool
+    /// accepted
+    recovered: u64,
+    recovered_accepted: u64,
+
+    /// Recovered shares awaiting a verdict, oldest first
+    recovered_awaiting: VecDeque<(String, u32)>,
+
+    /// Offline queue counts last reported to the scheduler
+    offline_report: OfflineQueueState,
+}
+
+/// Accepted/rejected share counts for one worker name.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct WorkerShareCounts {
+    accepted: u64,
+    rejected: u64,
 }
 
 /// Protocol state after successful subscription.
@@ -146,6 +240,9 @@ struct ProtocolState {
 
     /// Authorized version mask (from mining.configure or mining.set_version_mask)
     version_mask: Option<u32>,
+
+    /// Whether mining.subscribe has completed on this connection
+    subscribed: bool,
 }
 
 impl StratumV1Source {
@@ -157,6 +254,7 @@ impl StratumV1Source {
         shutdown: CancellationToken,
         connector: Box<dyn Connector>,
     ) -> Self {
+        let quirks = PoolQuirks::for_url(&config.url);
         Self {
             config,
             event_tx,
@@ -165,11 +263,69 @@ impl StratumV1Source {
             state: None,
             first_share_logged: false,
             expected_hashrate: HashRate::default(),
-            last_suggested_difficulty: None,
+            suggester: SuggestStrategy::default().suggester(),
+            last_job: None,
             connector,
+            worker_shares: HashMap::new(),
+            submit_queue: SubmitQueue::new(SUBMIT_QUEUE_CAPACITY, SUBMIT_QUEUE_MAX_AGE),
+            last_extranonce1: None,
+            clock_skew: ClockSkew::default(),
+            ntime_correction: false,
+            ntime_window: NtimeWindow::default(),
+            job_burst: JobBurst::default(),
+            remediator: Remediator::default(),
+            pending_remediation: None,
+            share_audit: ShareAudit::disabled(),
+            awaiting_verdict: VecDeque::new(),
+            quirks,
+            offline_max_age: None,
+            offline_since: None,
+            offline_prev_hash: None,
+            recovered: 0,
+            recovered_accepted: 0,
+            recovered_awaiting: VecDeque::new(),
+            offline_report: OfflineQueueState::default(),
         }
     }
 
+    /// Start jobs at the pool's current time as estimated from clock
+    /// skew, rather than at a job's ntime when that lags behind.
+    pub fn with_ntime_correction(mut self, enabled: bool) -> Self {
+        self.ntime_correction = enabled;
+        self
+    }
+
+    /// Stamp each share's progress in `share_audit`.
+    pub fn with_share_audit(mut self, share_audit: ShareAudit) -> Self {
+        self.share_audit = share_audit;
+        self
+    }
+
+    /// Suggest difficulty to the pool following `strategy`.
+    pub fn with_suggest_strategy(mut self, strategy: SuggestStrategy) -> Self {
+        self.suggester = strategy.suggester();
+        self
+    }
+
+    /// Work around `quirks` as well as those in the pool's known profile.
+    pub fn with_quirks(mut self, quirks: PoolQuirks) -> Self {
+        self.quirks = self.quirks.union(quirks);
+        self
+    }
+
+    /// Hold shares found while the pool is unreachable for up to
+    /// `max_age`, and submit them on reconnect if the pool resumes the
+    /// session.
+    ///
+    /// Meanwhile the hash threads stay on the last job, for up to
+    /// `max_age` too, rather than being cleared.
+    pub fn with_offline_queue(mut self, max_age: Duration) -> Self {
+        self.offline_max_age = Some(max_age);
+        self.submit_queue = SubmitQueue::new(SUBMIT_QUEUE_CAPACITY, SUBMIT_QUEUE_MAX_AGE)
+            .with_offline_max_age(max_age);
+        self
+    }
+
     /// Human-readable name derived from pool URL (e.g., "solo.ckpool.org:3333").
     pub fn name(&self) -> String {
         self.config
@@ -192,10 +348,11 @@ impl StratumV1Source {
         let extranonce2_range = Extranonce2Range::new(state.extranonce2_size as u8)?;
 
         // Convert version to VersionTemplate
-        // Use authorized mask from pool (or none if pool didn't authorize version rolling)
+        // Use authorized mask from pool (or none if pool didn't authorize version rolling),
+        // clipped to BIP320's bits; threads narrow it further to what their hardware rolls
         let gp_bits_mask = state
             .version_mask
-            .map(|mask| GeneralPurposeBits::from(&mask.to_be_bytes()))
+            .map(|mask| effective_rolling_mask(mask, GeneralPurposeBits::full()))
             .unwrap_or_else(GeneralPurposeBits::none);
 
         let version_template = VersionTemplate::new(job.version, gp_bits_mask)?;
@@ -204,20 +361,27 @@ impl StratumV1Source {
         let share_difficulty = state.share_difficulty.unwrap_or(Difficulty::from(1));
         let share_target = share_difficulty.to_target();
 
+        let time = if self.ntime_correction {
+            self.clock_skew
+                .corrected_ntime(job.ntime, clock_skew::local_unix_secs())
+        } else {
+            job.ntime
+        };
+
         Ok(JobTemplate {
             id: job.job_id,
             prev_blockhash: job.prev_hash,
             version: version_template,
             bits: job.nbits,
             share_target,
-            time: job.ntime,
-            merkle_root: MerkleRootKind::Computed(MerkleRootTemplate {
-                coinbase1: job.coinbase1,
-                extranonce1: state.extranonce1.clone(),
+            time,
+            merkle_root: MerkleRootKind::Computed(Box::new(MerkleRootTemplate::new(
+                job.coinbase1,
+                state.extranonce1.clone(),
                 extranonce2_range,
-                coinbase2: job.coinbase2,
-                merkle_branches: job.merkle_branches,
-            }),
+                job.coinbase2,
+                job.merkle_branches,
+            ))),
         })
     }
 
@@ -245,6 +409,7 @@ impl StratumV1Source {
                         extranonce2_size: 0,
                         share_difficulty: None,
                         version_mask: authorized_mask,
+                        subscribed: false,
                     });
                 }
             }
@@ -259,56 +424,140 @@ impl StratumV1Source {
                     "Subscribed."
                 );
 
+                // Shares held over a reconnect are only good if the pool
+                // resumed the session they were found in. The scheduler
+                // tells the sessions apart by extranonce1 too, carrying on
+                // with or dropping the work handed out for it.
+                match &self.last_extranonce1 {
+                    Some(last) if *last == extranonce1 => info!("Pool resumed session"),
+                    Some(_) => info!("Pool started a new session"),
+                    None => {}
+                }
+                if let Some(since) = self.offline_since.take() {
+                    self.submit_queue.set_offline(false);
+                    info!(
+                        pool = %self.name(),
+                        offline_secs = since.elapsed().as_secs(),
+                        held = self.submit_queue.len(),
+                        "Back online"
+                    );
+                }
+                if self.last_extranonce1.as_ref() != Some(&extranonce1)
+                    && !self.submit_queue.is_empty()
+                {
+                    debug!(
+                        dropped = self.submit_queue.len(),
+                        "New pool session, dropping queued shares"
+                    );
+                    self.submit_queue.clear();
+                }
+                self.last_extranonce1 = Some(extranonce1.clone());
+
                 // Update or create protocol state
                 // Preserve version_mask if already set by VersionRollingConfigured
                 if let Some(state) = &mut self.state {
                     state.extranonce1 = extranonce1;
                     state.extranonce2_size = extranonce2_size;
+                    state.subscribed = true;
                 } else {
                     self.state = Some(ProtocolState {
                         extranonce1,
                         extranonce2_size,
                         share_difficulty: None,
                         version_mask: None,
+                        subscribed: true,
                     });
                 }
             }
 
+            ClientEvent::Authorized { worker } => {
+                debug!(%worker, "Worker authorized");
+            }
+
             ClientEvent::NewJob(job) => {
                 debug!(job_id = %job.job_id, clean_jobs = job.clean_jobs, "Received job from pool");
 
+                self.observe_ntime(job.ntime);
                 let clean_jobs = job.clean_jobs;
-                let template = self.job_to_template(job)?;
-                let event = if clean_jobs {
-                    SourceEvent::ReplaceJob(template)
+                // Pools start a resumed session with clean_jobs whether or
+                // not the block changed; shares held while offline are
+                // good as long as it hasn't.
+                let same_block = self
+                    .offline_prev_hash
+                    .take()
+                    .is_some_and(|prev_hash| prev_hash == job.prev_hash);
+                self.submit_queue
+                    .note_job(&job.job_id, clean_jobs && !same_block);
+                self.ntime_window
+                    .note_job(&job.job_id, job.ntime, clean_jobs);
+                let now = tokio::time::Instant::now();
+                self.last_job = Some((job.clone(), now));
+                if let Some((job, clean_jobs)) = self.job_burst.offer(job, clean_jobs, now) {
+                    self.dispatch_job(job, clean_jobs).await?;
                 } else {
-                    SourceEvent::UpdateJob(template)
-                };
-
-                self.event_tx.send(event).await?;
+                    trace!("Holding job back until the burst ends");
+                }
             }
 
             ClientEvent::DifficultyChanged(diff) => {
                 let difficulty = Difficulty::from(diff);
                 debug!(difficulty = %difficulty, "Pool difficulty changed");
-                if let Some(state) = &mut self.state {
-                    state.share_difficulty = Some(difficulty);
+                let Some(state) = &mut self.state else {
+                    return Ok(());
+                };
+                let previous = state.share_difficulty.replace(difficulty);
+                if previous.map(Difficulty::to_target) == Some(difficulty.to_target()) {
+                    return Ok(());
                 }
+
+                // Apply the new share target to the job being worked now
+                // instead of waiting for the next notify. Shares found under
+                // the old target remain valid, so this is an update.
+                self.reissue_last_job(false).await?;
             }
 
             ClientEvent::VersionMaskSet(mask) => {
                 info!(mask = format!("{:#010x}", mask), "Version mask set");
-                if let Some(state) = &mut self.state {
-                    state.version_mask = Some(mask);
+                let Some(state) = &mut self.state else {
+                    return Ok(());
+                };
+                if state.version_mask == Some(mask) {
+                    return Ok(());
                 }
+                state.version_mask = Some(mask);
+
+                // Re-issue the current job under the new mask. Work rolled
+                // under the old mask may set bits the pool no longer
+                // accepts, so in-flight tasks are replaced, not updated.
+                self.reissue_last_job(true).await?;
             }
 
-            ClientEvent::ShareAccepted { job_id, nonce } => {
+            ClientEvent::ShareAccepted {
+                job_id,
+                nonce,
+                worker,
+                latency,
+            } => {
+                self.note_verdict(&job_id, Some(nonce), true);
+                self.note_recovered_verdict(&job_id, Some(nonce), true);
+                self.event_tx
+                    .send(SourceEvent::ShareResult {
+                        job_id: job_id.clone(),
+                        accepted: true,
+                        latency,
+                    })
+                    .await?;
+
+                self.remediate(true).await?;
+
+                let counts = self.worker_shares.entry(worker.clone()).or_default();
+                counts.accepted += 1;
+                let accepted = counts.accepted;
                 if !self.first_share_logged {
                     self.first_share_logged = true;
                     info!(
                         pool = %self.config.url,
-                        user = %self.config.username,
+                        user = %worker,
                         nonce = format!("{:#x}", nonce),
                         job_id = %job_id,
                         "First share accepted."
@@ -316,16 +565,47 @@ impl StratumV1Source {
                 } else {
                     debug!(
                         pool = %self.config.url,
-                        user = %self.config.username,
+                        user = %worker,
                         nonce = format!("{:#x}", nonce),
                         job_id = %job_id,
+                        accepted,
                         "Share accepted."
                     );
                 }
             }
 
-            ClientEvent::ShareRejected { job_id, reason } => {
-                warn!(job_id = %job_id, reason = %reason, "Share rejected by pool");
+            ClientEvent::ShareRejected {
+                job_id,
+                worker,
+                latency,
+                reason,
+            } => {
+                self.note_verdict(&job_id, None, false);
+                self.note_recovered_verdict(&job_id, None, false);
+                self.event_tx
+                    .send(SourceEvent::ShareResult {
+                        job_id: job_id.clone(),
+                        accepted: false,
+                        latency,
+                    })
+                    .await?;
+
+                let counts = self.worker_shares.entry(worker.clone()).or_default();
+                counts.rejected += 1;
+                warn!(
+                    job_id = %job_id,
+                    user = %worker,
+                    reason = %reason,
+                    rejected = counts.rejected,
+                    "Share rejected by pool"
+                );
+
+                self.remediate(false).await?;
+            }
+
+            ClientEvent::QuirksLearned(found) => {
+                self.quirks = self.quirks.union(found);
+                info!(pool = %self.name(), quirks = %found, "Working around pool quirk");
             }
 
             ClientEvent::Disconnected => {
@@ -344,6 +624,165 @@ impl StratumV1Source {
         Ok(())
     }
 
+    /// Feed a share verdict to the remediator and start any step it picks.
+    ///
+    /// Steps that act on the connection itself are left in
+    /// `pending_remediation` for the connection loop.
+    async fn remediate(&mut self, accepted: bool) -> Result<()> {
+        let now = tokio::time::Instant::now();
+        let before = self.remediator.state(now);
+        let step = self.remediator.record_verdict(accepted, now);
+        let after = self.remediator.state(now);
+        if after == before {
+            return Ok(());
+        }
+
+        match step {
+            Some(step) => warn!(
+                pool = %self.name(),
+                %step,
+                threshold_percent = (REJECT_THRESHOLD * 100.0) as u8,
+                "High share reject rate, remediating"
+            ),
+            None if self.remediator.is_backing_off() => warn!(
+                pool = %self.name(),
+                retry_in_secs = ?after.retry_in_secs,
+                "Remediation didn't lower the reject rate, backing off"
+            ),
+            None => info!(
+                pool = %self.name(),
+                step = ?before.step.map(RemediationStep::as_str),
+                "Share reject rate back to normal"
+            ),
+        }
+        self.event_tx.send(SourceEvent::Remediation(after)).await?;
+
+        match step {
+            Some(RemediationStep::ResyncNtime) => {
+                // Start work at the pool's clock rather than the job's ntime
+                self.ntime_correction = true;
+                self.reissue_last_job(true).await?;
+            }
+            Some(RemediationStep::RefreshJob) => {
+                self.submit_queue.clear();
+                self.reissue_last_job(true).await?;
+            }
+            Some(step @ (RemediationStep::ResyncDifficulty | RemediationStep::Reconnect)) => {
+                self.pending_remediation = Some(step);
+            }
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    /// Send a job from the pool to the scheduler.
+    async fn dispatch_job(&mut self, job: JobNotification, clean_jobs: bool) -> Result<()> {
+        let template = self.job_to_template(job)?;
+        let event = if clean_jobs {
+            SourceEvent::ReplaceJob(template)
+        } else {
+            SourceEvent::UpdateJob(template)
+        };
+        self.event_tx.send(event).await?;
+        Ok(())
+    }
+
+    /// Dispatch the job held back during a burst, now that it is due.
+    async fn flush_job_burst(&mut self) -> Result<()> {
+        let Some((job, clean_jobs)) = self.job_burst.flush(tokio::time::Instant::now()) else {
+            return Ok(());
+        };
+        debug!(
+            job_id = %job.job_id,
+            coalesced = self.job_burst.coalesced(),
+            "Dispatching latest job of a burst"
+        );
+        self.dispatch_job(job, clean_jobs).await?;
+        self.event_tx
+            .send(SourceEvent::JobsCoalesced(self.job_burst.coalesced()))
+            .await?;
+        Ok(())
+    }
+
+    /// Update the clock skew estimate from a job's ntime.
+    fn observe_ntime(&mut self, ntime: u32) {
+        match self
+            .clock_skew
+            .observe(ntime, clock_skew::local_unix_secs())
+        {
+            Some(SkewAlert::Exceeded(skew_secs)) => warn!(
+                pool = %self.name(),
+                skew_secs,
+                "Pool ntime and local clock disagree; check NTP. \
+                 Shares may be rejected for bad ntime"
+            ),
+            Some(SkewAlert::Recovered(skew_secs)) => {
+                info!(pool = %self.name(), skew_secs, "Clock skew back within limits")
+            }
+            None => {}
+        }
+        trace!(skew_secs = ?self.clock_skew.skew_secs(), "Clock skew estimate");
+    }
+
+    /// Re-send the most recent job with the current protocol state.
+    ///
+    /// Used when session parameters (difficulty, version mask) change
+    /// between notifies. `replace` invalidates work done under the old
+    /// parameters.
+    async fn reissue_last_job(&mut self, replace: bool) -> Result<()> {
+        let Some((mut job, received_at)) = self.last_job.clone() else {
+            return Ok(());
+        };
+        // A job held back in a burst is the last job; this sends it
+        let replace = match self.job_burst.take_pending() {
+            Some((_, clean_jobs)) => replace || clean_jobs,
+            None => replace,
+        };
+
+        // Threads have been rolling ntime forward since the job arrived.
+        // Start the re-issued job just past that point so it doesn't
+        // re-hash headers already covered (duplicate shares).
+        let rolled = received_at.elapsed().as_secs() as u32 + 1;
+        job.ntime = job.ntime.saturating_add(rolled);
+
+        debug!(job_id = %job.job_id, replace, "Re-issuing job with updated session parameters");
+        let mut template = self.job_to_template(job)?;
+        self.keep_in_ntime_window(&mut template).await?;
+        let event = if replace {
+            SourceEvent::ReplaceJob(template)
+        } else {
+            SourceEvent::UpdateJob(template)
+        };
+        self.event_tx.send(event).await?;
+
+        Ok(())
+    }
+
+    /// Pull a template's start time back if threads rolling ntime from it
+    /// could pass what the pool accepts for the job.
+    ///
+    /// Re-issues start past the ntime already rolled, and ntime correction
+    /// can move them further, so a long-lived job drifts towards the edge.
+    async fn keep_in_ntime_window(&mut self, template: &mut JobTemplate) -> Result<()> {
+        let time = self.ntime_window.clamp_start(&template.id, template.time);
+        if time == template.time {
+            return Ok(());
+        }
+        warn!(
+            pool = %self.name(),
+            job_id = %template.id,
+            ntime = template.time,
+            clamped = time,
+            "Job ntime near the pool's limit, starting it earlier"
+        );
+        template.time = time;
+        self.event_tx
+            .send(SourceEvent::NtimeGuard(self.ntime_window.state()))
+            .await?;
+        Ok(())
+    }
+
     /// Convert Share to SubmitParams.
     fn share_to_submit_params(&self, share: Share) -> Result<crate::stratum_v1::SubmitParams> {
         let state = self
@@ -359,14 +798,18 @@ impl StratumV1Source {
 
         // Extract version bits if version rolling was authorized
         // Always include version_bits parameter when pool authorized rolling,
-        // even if the value is 0x00000000 (pool requires the field)
-        let version_bits = state.version_mask.map(|mask| {
-            let rolled = share.version.to_consensus() as u32;
-            rolled & mask
-        });
+        // even if the value is 0x00000000 (pool requires the field). Some
+        // pools want it without rolling too.
+        let version_bits = state
+            .version_mask
+            .map(|mask| {
+                let rolled = share.version.to_consensus() as u32;
+                rolled & mask
+            })
+            .or(self.quirks.version_bits_always.then_some(0));
 
         Ok(crate::stratum_v1::SubmitParams {
-            username: self.config.username.clone(),
+            username: self.config.worker_name(share.device_id.as_deref()),
             job_id: share.job_id,
             extranonce2,
             ntime: share.time,
@@ -375,43 +818,157 @@ impl StratumV1Source {
         })
     }
 
-    /// Compute the suggested difficulty for the given hashrate.
+    /// Hold a share until the client can take it.
+    fn enqueue_share(&mut self, share: Share) {
+        self.share_audit.enqueued(share.hash);
+        trace!(
+            job_id = %share.job_id,
+            nonce = format!("{:#x}", share.nonce),
+            queued = self.submit_queue.len(),
+            "Queueing share"
+        );
+        let dropped = self.submit_queue.dropped();
+        self.submit_queue.push(share, tokio::time::Instant::now());
+        if self.submit_queue.dropped() > dropped {
+            debug!(
+                pool = %self.name(),
+                dropped = self.submit_queue.dropped(),
+                "Submit queue full, dropped least valuable share"
+            );
+        }
+    }
+
+    /// Note a share sent to the pool, for the share audit log and share
+    /// proofs.
+    fn note_submitted(&mut self, job_id: &str, nonce: u32, hash: BlockHash) {
+        if !self.share_audit.is_enabled() && !share_proof::installed().is_enabled() {
+            return;
+        }
+        self.share_audit.submitted(hash);
+        if self.awaiting_verdict.len() == AWAITING_VERDICT_LEN {
+            self.awaiting_verdict.pop_front();
+        }
+        self.awaiting_verdict
+            .push_back((job_id.to_string(), nonce, hash));
+    }
+
+    /// Stamp a pool verdict on the share it answers.
     ///
-    /// Returns `None` for zero hashrate (nothing to suggest yet).
-    fn compute_suggested_difficulty(hashrate: HashRate) -> Option<u64> {
-        if hashrate.is_zero() {
-            return None;
+    /// Rejections don't name the nonce, so they go to the oldest share
+    /// awaiting a verdict on the job; pools answer in order.
+    fn note_verdict(&mut self, job_id: &str, nonce: Option<u32>, accepted: bool) {
+        let position = self
+            .awaiting_verdict
+            .iter()
+            .position(|(id, n, _)| id == job_id && nonce.is_none_or(|nonce| *n == nonce));
+        if let Some((_, _, hash)) = position.and_then(|i| self.awaiting_verdict.remove(i)) {
+            self.share_audit.acked(hash, accepted);
+            if accepted {
+                share_proof::installed().accepted(hash);
+            } else {
+                share_proof::installed().rejected(hash);
+            }
         }
-        let target = target_for_share_rate(SUGGESTED_SHARE_RATE, hashrate);
-        let diff = Difficulty::from_target(target).as_u64().max(1);
-        Some(diff)
     }
 
-    /// Send `SuggestDifficulty` if the computed value changed materially
-    /// (factor of 2) from the last suggestion.
-    async fn maybe_suggest_difficulty(&mut self, client_command_tx: &mpsc::Sender<ClientCommand>) {
-        let Some(new_diff) = Self::compute_suggested_difficulty(self.expected_hashrate) else {
+    /// Count a pool verdict on a share held while offline.
+    fn note_recovered_verdict(&mut self, job_id: &str, nonce: Option<u32>, accepted: bool) {
+        let position = self
+            .recovered_awaiting
+            .iter()
+            .position(|(id, n)| id == job_id && nonce.is_none_or(|nonce| *n == nonce));
+        if position
+            .and_then(|i| self.recovered_awaiting.remove(i))
+            .is_some()
+            && accepted
+        {
+            self.recovered_accepted += 1;
+        }
+    }
+
+    /// Start holding shares for the pool to come back, leaving the hash
+    /// threads on the last job.
+    fn go_offline(&mut self, max_age: Duration) {
+        if self.offline_since.is_some() {
+            return;
+        }
+        warn!(
+            pool = %self.name(),
+            max_age_secs = max_age.as_secs(),
+            "Pool unreachable, holding shares for it"
+        );
+        self.offline_since = Some(tokio::time::Instant::now());
+        // A connection dropped before its first job leaves the threads on
+        // the one from before it
+        if let Some((job, _)) = &self.last_job {
+            self.offline_prev_hash = Some(job.prev_hash);
+        }
+        self.submit_queue.set_offline(true);
+    }
+
+    /// When the hash threads should be taken off the last job, while the
+    /// pool is unreachable and they are still on it.
+    fn offline_work_deadline(&self) -> Option<tokio::time::Instant> {
+        self.offline_prev_hash?;
+        Some(self.offline_since? + self.offline_max_age?)
+    }
+
+    /// Clear the work left running while offline if it has been running
+    /// long enough that shares from it would be discarded anyway.
+    async fn expire_offline_work(&mut self) {
+        let Some(deadline) = self.offline_work_deadline() else {
             return;
         };
+        if tokio::time::Instant::now() < deadline {
+            return;
+        }
+        info!(pool = %self.name(), "Pool still unreachable, clearing held work");
+        self.offline_prev_hash = None;
+        if let Err(e) = self.event_tx.send(SourceEvent::ClearJobs).await {
+            warn!(error = %e, "Failed to send ClearJobs");
+        }
+    }
 
-        let dominated = match self.last_suggested_difficulty {
-            Some(prev) => {
-                let ratio = new_diff as f64 / prev as f64;
-                ratio >= MATERIAL_CHANGE_FACTOR || ratio <= 1.0 / MATERIAL_CHANGE_FACTOR
-            }
-            None => true,
+    /// Report the offline queue counts if they changed.
+    async fn report_offline_queue(&mut self) {
+        if self.offline_max_age.is_none() {
+            return;
+        }
+        let state = OfflineQueueState {
+            held: self.submit_queue.held_offline(),
+            recovered: self.recovered,
+            recovered_accepted: self.recovered_accepted,
+            discarded: self.submit_queue.dropped_offline(),
         };
+        if state == self.offline_report {
+            return;
+        }
+        self.offline_report = state.clone();
+        if let Err(e) = self.event_tx.send(SourceEvent::OfflineQueue(state)).await {
+            warn!(error = %e, "Failed to report offline queue state");
+        }
+    }
+
+    /// Whether a queued share can go to the client now.
+    fn ready_to_submit(&self) -> bool {
+        !self.submit_queue.is_empty() && self.state.as_ref().is_some_and(|s| s.subscribed)
+    }
 
-        if !dominated {
+    /// Send `SuggestDifficulty` if the suggester has a new value.
+    async fn maybe_suggest_difficulty(&mut self, client_command_tx: &mpsc::Sender<ClientCommand>) {
+        if self.quirks.no_suggest_difficulty {
             return;
         }
+        let now = tokio::time::Instant::now();
+        let Some(new_diff) = self.suggester.review(self.expected_hashrate, now) else {
+            return;
+        };
 
         debug!(
             difficulty = new_diff,
             hashrate = %self.expected_hashrate,
             "Suggesting difficulty to pool"
         );
-        self.last_suggested_difficulty = Some(new_diff);
 
         if let Err(e) = client_command_tx
             .send(ClientCommand::SuggestDifficulty(new_diff))
@@ -455,11 +1012,16 @@ impl StratumV1Source {
         }
 
         // Phase 2: connect with automatic reconnection.
+        if !self.quirks.is_empty() {
+            info!(pool = %self.config.url, quirks = %self.quirks, "Working around pool quirks");
+        }
         let mut backoff = ExponentialBackoff::new(Duration::from_secs(1), Duration::from_secs(60));
 
         loop {
             // Reset per-connection state so a fresh handshake starts clean.
             self.state = None;
+            self.last_job = None;
+            self.job_burst.clear();
             self.first_share_logged = false;
 
             info!(pool = %self.config.url, "Connecting to pool");
@@ -472,8 +1034,13 @@ impl StratumV1Source {
                     return Err(e);
                 }
                 ConnectOutcome::Disconnected => {
-                    // Invalidate stale work from the dead connection.
-                    if let Err(e) = self.event_tx.send(SourceEvent::ClearJobs).await {
+                    if let Some(max_age) = self.offline_max_age {
+                        // Keep hashing the last job; what it finds may
+                        // still be good once the pool is back
+                        self.go_offline(max_age);
+                        self.expire_offline_work().await;
+                    } else if let Err(e) = self.event_tx.send(SourceEvent::ClearJobs).await {
+                        // Invalidate stale work from the dead connection.
                         warn!(error = %e, "Failed to send ClearJobs");
                     }
                     if connected_at.elapsed() >= STABLE_CONNECTION_THRESHOLD {
@@ -499,12 +1066,13 @@ impl StratumV1Source {
     /// until disconnect or shutdown, and returns the outcome.
     async fn connect_and_run(&mut self) -> ConnectOutcome {
         let (client_event_tx, mut client_event_rx) = mpsc::channel(100);
-        let (client_command_tx, client_command_rx) = mpsc::channel(100);
+        let (client_command_tx, client_command_rx) = mpsc::channel(CLIENT_COMMAND_BUFFER);
 
         // Compute initial difficulty so the client can send it inline
         // during the handshake, before the first job arrives.
-        let initial_difficulty = Self::compute_suggested_difficulty(self.expected_hashrate);
-        self.last_suggested_difficulty = initial_difficulty;
+        let initial_difficulty = self
+            .suggester
+            .initial(self.expected_hashrate, tokio::time::Instant::now());
 
         let client = StratumV1Client::with_commands(
             self.config.clone(),
@@ -512,7 +1080,8 @@ impl StratumV1Source {
             client_command_rx,
             self.shutdown.clone(),
             initial_difficulty,
-        );
+        )
+        .with_quirks(self.quirks);
 
         let transport = tokio::select! {
             result = self.connector.connect() => {
@@ -532,10 +1101,18 @@ impl StratumV1Source {
             }
         };
 
-        let client_handle = tokio::spawn(async move { client.run_with_transport(transport).await });
+        let client_handle = tokio::spawn(
+            async move { client.run_with_transport(transport).await }.in_current_span(),
+        );
+
+        let mut suggest_review = tokio::time::interval_at(
+            tokio::time::Instant::now() + REVIEW_INTERVAL,
+            REVIEW_INTERVAL,
+        );
 
         // Main event loop
         loop {
+            let burst_deadline = self.job_burst.deadline();
             tokio::select! {
                 event_opt = client_event_rx.recv() => {
                     match event_opt {
@@ -543,6 +1120,18 @@ impl StratumV1Source {
                             if let Err(e) = self.handle_client_event(event).await {
                                 warn!(error = %e, "Error handling client event");
                             }
+                            self.report_offline_queue().await;
+                            match self.pending_remediation.take() {
+                                Some(RemediationStep::ResyncDifficulty) => {
+                                    self.suggester.resync();
+                                    self.maybe_suggest_difficulty(&client_command_tx).await;
+                                }
+                                Some(RemediationStep::Reconnect) => {
+                                    client_handle.abort();
+                                    return ConnectOutcome::Disconnected;
+                                }
+                                _ => {}
+                            }
                         }
                         None => {
                             // Client task exited; check why below.
@@ -554,34 +1143,95 @@ impl StratumV1Source {
                 Some(cmd) = self.command_rx.recv() => {
                     match cmd {
                         SourceCommand::SubmitShare(share) => {
-                            debug!(
+                            self.enqueue_share(share);
+                            self.report_offline_queue().await;
+                        }
+
+                        SourceCommand::UpdateHashRate(rate) => {
+                            self.expected_hashrate = rate;
+                            self.maybe_suggest_difficulty(&client_command_tx).await;
+                        }
+                    }
+                }
+
+                Ok(permit) = client_command_tx.reserve(), if self.ready_to_submit() => {
+                    if let Some(Dequeued { share, offline }) =
+                        self.submit_queue.pop(tokio::time::Instant::now())
+                    {
+                        if !self.ntime_window.admits(&share) {
+                            warn!(
                                 pool = %self.name(),
                                 job_id = %share.job_id,
-                                nonce = format!("{:#x}", share.nonce),
-                                "Submitting share"
+                                ntime = share.time,
+                                "Dropping share with ntime outside the pool's window"
                             );
-
-                            match self.share_to_submit_params(share) {
-                                Ok(submit_params) => {
-                                    if let Err(e) = client_command_tx.send(
-                                        ClientCommand::SubmitShare(submit_params)
-                                    ).await {
-                                        warn!(error = %e, "Failed to send share to client");
+                            let state = self.ntime_window.state();
+                            if let Err(e) = self.event_tx.send(SourceEvent::NtimeGuard(state)).await {
+                                warn!(error = %e, "Failed to report ntime guard state");
+                            }
+                            continue;
+                        }
+                        debug!(
+                            pool = %self.name(),
+                            job_id = %share.job_id,
+                            nonce = format!("{:#x}", share.nonce),
+                            "Submitting share"
+                        );
+                        let hash = share.hash;
+                        match self.share_to_submit_params(share) {
+                            Ok(submit_params) => {
+                                self.note_submitted(
+                                    &submit_params.job_id,
+                                    submit_params.nonce,
+                                    hash,
+                                );
+                                if offline {
+                                    info!(
+                                        pool = %self.name(),
+                                        job_id = %submit_params.job_id,
+                                        "Submitting share held while offline"
+                                    );
+                                    self.recovered += 1;
+                                    self.recovered_awaiting.push_back((
+                                        submit_params.job_id.clone(),
+                                        submit_params.nonce,
+                                    ));
+                                    if self.recovered_awaiting.len() > AWAITING_VERDICT_LEN {
+                                        self.recovered_awaiting.pop_front();
                                     }
                                 }
-                                Err(e) => {
-                                    warn!(error = %e, "Failed to convert share");
+                                if let Some(difficulty) =
+                                    self.state.as_ref().and_then(|s| s.share_difficulty)
+                                {
+                                    self.suggester.share_submitted(
+                                        difficulty,
+                                        tokio::time::Instant::now(),
+                                    );
+                                }
+                                permit.send(ClientCommand::SubmitShare(submit_params));
+                                if offline {
+                                    self.report_offline_queue().await;
                                 }
                             }
+                            Err(e) => {
+                                warn!(error = %e, "Failed to convert share");
+                            }
                         }
+                    }
+                }
 
-                        SourceCommand::UpdateHashRate(rate) => {
-                            self.expected_hashrate = rate;
-                            self.maybe_suggest_difficulty(&client_command_tx).await;
-                        }
+                _ = tokio::time::sleep_until(
+                    burst_deadline.unwrap_or_else(tokio::time::Instant::now)
+                ), if burst_deadline.is_some() => {
+                    if let Err(e) = self.flush_job_burst().await {
+                        warn!(error = %e, "Failed to dispatch held job");
                     }
                 }
 
+                _ = suggest_review.tick() => {
+                    self.maybe_suggest_difficulty(&client_command_tx).await;
+                }
+
                 _ = self.shutdown.cancelled() => {
                     return ConnectOutcome::Shutdown;
                 }
@@ -613,6 +1263,7 @@ impl StratumV1Source {
         tokio::pin!(sleep);
 
         loop {
+            let work_deadline = self.offline_work_deadline();
             tokio::select! {
                 _ = &mut sleep => {
                     return false;
@@ -622,11 +1273,18 @@ impl StratumV1Source {
                         SourceCommand::UpdateHashRate(rate) => {
                             self.expected_hashrate = rate;
                         }
-                        SourceCommand::SubmitShare(_) => {
-                            // No connection, drop silently.
+                        SourceCommand::SubmitShare(share) => {
+                            // Held in case the pool resumes the session
+                            self.enqueue_share(share);
+                            self.report_offline_queue().await;
                         }
                     }
                 }
+                _ = tokio::time::sleep_until(
+                    work_deadline.unwrap_or_else(tokio::time::Instant::now)
+                ), if work_deadline.is_some() => {
+                    self.expire_offline_work().await;
+                }
                 _ = self.shutdown.cancelled() => {
                     return true;
                 }
@@ -644,11 +1302,14 @@ mod tests {
     };
     use crate::asic::bm13xx::test_data::stratum_json;
     use crate::job_source::Extranonce2;
+    use crate::job_source::{job_burst, ntime_window};
     use crate::stratum_v1::{
         JobNotification, JsonRpcMessage, MockConnector, MockTransport, MockTransportHandle,
         StratumResult, Transport,
     };
+    use bitcoin::BlockHash;
     use bitcoin::block::Version;
+    use bitcoin::hashes::Hash;
     use serde_json::json;
 
     /// Connector that panics if called. For tests that never reach connect().
@@ -703,6 +1364,7 @@ mod tests {
             extranonce2_size,
             share_difficulty: share_difficulty.map(Difficulty::from),
             version_mask,
+            subscribed: true,
         });
 
         source
@@ -873,6 +1535,298 @@ mod tests {
         );
     }
 
+    /// A mid-session version mask change re-issues the current job as a
+    /// replacement carrying the new mask.
+    #[tokio::test]
+    async fn test_version_mask_change_reissues_current_job() {
+        let (event_tx, mut event_rx) = mpsc::channel(10);
+        let (_command_tx, command_rx) = mpsc::channel(10);
+        let mut source = StratumV1Source::new(
+            PoolConfig::default(),
+            command_rx,
+            event_tx,
+            CancellationToken::new(),
+            Box::new(NeverConnector),
+        );
+        source.state = Some(ProtocolState {
+            extranonce1: hex::decode(STRATUM_EXTRANONCE1).unwrap(),
+            extranonce2_size: STRATUM_EXTRANONCE2_SIZE,
+            share_difficulty: None,
+            version_mask: Some(0x1fffe000),
+            subscribed: true,
+        });
+
+        let params = json!([
+            "jobid",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "aa",
+            "bb",
+            [],
+            "20000000",
+            "1d00ffff",
+            "5a5a5a5a",
+            false
+        ]);
+        let job = JobNotification::from_stratum_params(params.as_array().unwrap()).unwrap();
+        source
+            .handle_client_event(ClientEvent::NewJob(job))
+            .await
+            .unwrap();
+        assert!(matches!(event_rx.try_recv(), Ok(SourceEvent::UpdateJob(_))));
+
+        // Same mask again: nothing to do
+        source
+            .handle_client_event(ClientEvent::VersionMaskSet(0x1fffe000))
+            .await
+            .unwrap();
+        assert!(event_rx.try_recv().is_err());
+
+        source
+            .handle_client_event(ClientEvent::VersionMaskSet(0x01ffe000))
+            .await
+            .unwrap();
+        match event_rx.try_recv() {
+            Ok(SourceEvent::ReplaceJob(template)) => {
+                assert_eq!(template.id, "jobid");
+                assert_eq!(template.version.gp_bits_mask().as_bytes(), &[0x0f, 0xff]);
+            }
+            other => panic!("expected ReplaceJob, got {other:?}"),
+        }
+    }
+
+    /// A difficulty change between notifies re-issues the current job as
+    /// an update carrying the new share target.
+    #[tokio::test]
+    async fn test_difficulty_change_updates_current_job() {
+        let (event_tx, mut event_rx) = mpsc::channel(10);
+        let (_command_tx, command_rx) = mpsc::channel(10);
+        let mut source = StratumV1Source::new(
+            PoolConfig::default(),
+            command_rx,
+            event_tx,
+            CancellationToken::new(),
+            Box::new(NeverConnector),
+        );
+        source.state = Some(ProtocolState {
+            extranonce1: hex::decode(STRATUM_EXTRANONCE1).unwrap(),
+            extranonce2_size: STRATUM_EXTRANONCE2_SIZE,
+            share_difficulty: Some(Difficulty::from(1000)),
+            version_mask: None,
+            subscribed: true,
+        });
+
+        let params = json!([
+            "jobid",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "aa",
+            "bb",
+            [],
+            "20000000",
+            "1d00ffff",
+            "5a5a5a5a",
+            false
+        ]);
+        let job = JobNotification::from_stratum_params(params.as_array().unwrap()).unwrap();
+        source
+            .handle_client_event(ClientEvent::NewJob(job))
+            .await
+            .unwrap();
+        assert!(event_rx.try_recv().is_ok());
+
+        // Unchanged difficulty: nothing to do
+        source
+            .handle_client_event(ClientEvent::DifficultyChanged(1000))
+            .await
+            .unwrap();
+        assert!(event_rx.try_recv().is_err());
+
+        source
+            .handle_client_event(ClientEvent::DifficultyChanged(8000))
+            .await
+            .unwrap();
+        match event_rx.try_recv() {
+            Ok(SourceEvent::UpdateJob(template)) => {
+                assert_eq!(template.id, "jobid");
+                assert_eq!(template.share_target, Difficulty::from(8000).to_target());
+            }
+            other => panic!("expected UpdateJob, got {other:?}"),
+        }
+    }
+
+    /// A job re-issued late in its life starts early enough that rolled
+    /// ntime stays where the pool accepts it.
+    #[tokio::test(start_paused = true)]
+    async fn late_reissue_is_clamped_to_the_ntime_window() {
+        let (event_tx, mut event_rx) = mpsc::channel(10);
+        let (_command_tx, command_rx) = mpsc::channel(10);
+        let mut source = StratumV1Source::new(
+            PoolConfig::default(),
+            command_rx,
+            event_tx,
+            CancellationToken::new(),
+            Box::new(NeverConnector),
+        );
+        source.state = Some(ProtocolState {
+            extranonce1: hex::decode(STRATUM_EXTRANONCE1).unwrap(),
+            extranonce2_size: STRATUM_EXTRANONCE2_SIZE,
+            share_difficulty: Some(Difficulty::from(1000)),
+            version_mask: None,
+            subscribed: true,
+        });
+
+        let params = json!([
+            "jobid",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "aa",
+            "bb",
+            [],
+            "20000000",
+            "1d00ffff",
+            "5a5a5a5a",
+            false
+        ]);
+        let job = JobNotification::from_stratum_params(params.as_array().unwrap()).unwrap();
+        source
+            .handle_client_event(ClientEvent::NewJob(job))
+            .await
+            .unwrap();
+        assert!(event_rx.try_recv().is_ok());
+
+        tokio::time::advance(Duration::from_secs(2 * 60 * 60)).await;
+        source
+            .handle_client_event(ClientEvent::DifficultyChanged(8000))
+            .await
+            .unwrap();
+
+        match event_rx.try_recv() {
+            Ok(SourceEvent::NtimeGuard(state)) => assert_eq!(state.clamped_jobs, 1),
+            other => panic!("expected NtimeGuard, got {other:?}"),
+        }
+        match event_rx.try_recv() {
+            Ok(SourceEvent::UpdateJob(template)) => {
+                let limit = ntime_window::NTIME_TOLERANCE - crate::scheduler::NTIME_ROLL_LIMIT;
+                assert_eq!(template.time, 0x5a5a5a5a + limit);
+            }
+            other => panic!("expected UpdateJob, got {other:?}"),
+        }
+    }
+
+    /// Notifies sent in quick succession reach the scheduler as the first
+    /// and the latest, the latest replacing work if any of them did.
+    #[tokio::test(start_paused = true)]
+    async fn notify_burst_dispatches_first_and_latest_jobs() {
+        let (event_tx, mut event_rx) = mpsc::channel(10);
+        let (_command_tx, command_rx) = mpsc::channel(10);
+        let mut source = StratumV1Source::new(
+            PoolConfig::default(),
+            command_rx,
+            event_tx,
+            CancellationToken::new(),
+            Box::new(NeverConnector),
+        );
+        source.state = Some(ProtocolState {
+            extranonce1: hex::decode(STRATUM_EXTRANONCE1).unwrap(),
+            extranonce2_size: STRATUM_EXTRANONCE2_SIZE,
+            share_difficulty: None,
+            version_mask: None,
+            subscribed: true,
+        });
+
+        let notify = |job_id: &str, clean_jobs: bool| {
+            let params = json!([
+                job_id,
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "aa",
+                "bb",
+                [],
+                "20000000",
+                "1d00ffff",
+                "5a5a5a5a",
+                clean_jobs
+            ]);
+            let job = JobNotification::from_stratum_params(params.as_array().unwrap()).unwrap();
+            ClientEvent::NewJob(job)
+        };
+
+        for (job_id, clean_jobs) in [("a", false), ("b", true), ("c", false), ("d", false)] {
+            source
+                .handle_client_event(notify(job_id, clean_jobs))
+                .await
+                .unwrap();
+        }
+        assert!(matches!(event_rx.try_recv(), Ok(SourceEvent::UpdateJob(t)) if t.id == "a"));
+        assert!(event_rx.try_recv().is_err());
+
+        tokio::time::advance(job_burst::BURST_WINDOW).await;
+        source.flush_job_burst().await.unwrap();
+        assert!(matches!(event_rx.try_recv(), Ok(SourceEvent::ReplaceJob(t)) if t.id == "d"));
+        assert!(matches!(
+            event_rx.try_recv(),
+            Ok(SourceEvent::JobsCoalesced(2))
+        ));
+    }
+
+    /// With ntime correction, a job whose ntime lags the pool's clock (as
+    /// seen in earlier jobs) starts at the pool's clock instead.
+    #[tokio::test(start_paused = true)]
+    async fn ntime_correction_starts_lagging_job_at_pool_clock() {
+        let (event_tx, mut event_rx) = mpsc::channel(10);
+        let (_command_tx, command_rx) = mpsc::channel(10);
+        let mut source = StratumV1Source::new(
+            PoolConfig::default(),
+            command_rx,
+            event_tx,
+            CancellationToken::new(),
+            Box::new(NeverConnector),
+        )
+        .with_ntime_correction(true);
+        source.state = Some(ProtocolState {
+            extranonce1: hex::decode(STRATUM_EXTRANONCE1).unwrap(),
+            extranonce2_size: STRATUM_EXTRANONCE2_SIZE,
+            share_difficulty: None,
+            version_mask: None,
+            subscribed: true,
+        });
+
+        let notify = |job_id: &str, ntime: u32| {
+            let params = json!([
+                job_id,
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "aa",
+                "bb",
+                [],
+                "20000000",
+                "1d00ffff",
+                format!("{ntime:08x}"),
+                false
+            ]);
+            let job = JobNotification::from_stratum_params(params.as_array().unwrap()).unwrap();
+            ClientEvent::NewJob(job)
+        };
+        let template_time = |event| match event {
+            Ok(SourceEvent::UpdateJob(template)) => template.time,
+            other => panic!("expected UpdateJob, got {other:?}"),
+        };
+
+        // Pool clock an hour behind ours
+        let pool_now = clock_skew::local_unix_secs() as u32 - 3600;
+        source
+            .handle_client_event(notify("fresh", pool_now))
+            .await
+            .unwrap();
+        assert_eq!(template_time(event_rx.try_recv()), pool_now);
+
+        // A template resent two minutes after it was built
+        tokio::time::advance(job_burst::BURST_WINDOW).await;
+        source
+            .handle_client_event(notify("resent", pool_now - 120))
+            .await
+            .unwrap();
+        let time = template_time(event_rx.try_recv());
+        assert!((pool_now..pool_now + 5).contains(&time), "{time}");
+        assert_eq!(source.clock_skew.skew_secs().map(|s| s / 10), Some(-360));
+    }
+
     /// Test share_to_submit_params with real capture data.
     ///
     /// Converts the share found by the Bitaxe Gamma back to Stratum format
@@ -897,6 +1851,8 @@ mod tests {
             time: *submit::NTIME,
             version: full_version,
             extranonce2: Some(extranonce2_from_bytes(&*submit::EXTRANONCE2)),
+            device_id: None,
+            hash: BlockHash::all_zeros(),
         };
 
         // Convert to SubmitParams
@@ -951,15 +1907,56 @@ mod tests {
             time: 0x65432100,
             version: Version::from_consensus(0x20000000),
             extranonce2: Some(extranonce2_from_bytes(&[0xde, 0xad, 0xbe, 0xef])),
+            device_id: None,
+            hash: BlockHash::all_zeros(),
         };
 
-        let params = source.share_to_submit_params(share).unwrap();
+        let params = source.share_to_submit_params(share.clone()).unwrap();
 
         // version_bits should be None when no version rolling authorized
         assert_eq!(
             params.version_bits, None,
             "version_bits should be None without version rolling"
         );
+
+        // ...unless the pool wants the field regardless
+        let source = source.with_quirks(PoolQuirks {
+            version_bits_always: true,
+            ..PoolQuirks::NONE
+        });
+        let params = source.share_to_submit_params(share).unwrap();
+        assert_eq!(params.version_bits, Some(0));
+    }
+
+    /// Shares from an identified device go out under its templated worker name.
+    #[test]
+    fn test_share_to_submit_params_templated_worker() {
+        let extranonce1 = hex::decode(STRATUM_EXTRANONCE1).unwrap();
+        let mut source = source_with_state(
+            extranonce1,
+            STRATUM_EXTRANONCE2_SIZE,
+            Some(POOL_SHARE_DIFFICULTY_INT),
+            None,
+        );
+        source.config.username = "testworker.{board_serial}".to_string();
+
+        let share = |device_id: Option<&str>| Share {
+            job_id: "testjob".to_string(),
+            nonce: 0x12345678,
+            time: 0x65432100,
+            version: Version::from_consensus(0x20000000),
+            extranonce2: None,
+            device_id: device_id.map(String::from),
+            hash: BlockHash::all_zeros(),
+        };
+
+        let params = source
+            .share_to_submit_params(share(Some("e2f56f9b")))
+            .unwrap();
+        assert_eq!(params.username, "testworker.e2f56f9b");
+
+        let params = source.share_to_submit_params(share(None)).unwrap();
+        assert_eq!(params.username, "testworker");
     }
 
     /// Test share_to_submit_params uses default extranonce2 when not provided.
@@ -979,6 +1976,8 @@ mod tests {
             time: 0x65432100,
             version: Version::from_consensus(0x20000000),
             extranonce2: None, // Not provided
+            device_id: None,
+            hash: BlockHash::all_zeros(),
         };
 
         let params = source.share_to_submit_params(share).unwrap();
@@ -1014,6 +2013,8 @@ mod tests {
             time: *submit::NTIME,
             version: full_version,
             extranonce2: Some(extranonce2_from_bytes(&*submit::EXTRANONCE2)),
+            device_id: None,
+            hash: BlockHash::all_zeros(),
         };
 
         // Convert to SubmitParams and then to JSON
@@ -1075,30 +2076,34 @@ mod tests {
     }
 
     #[test]
-    fn test_compute_suggested_difficulty_zero_hashrate() {
-        assert_eq!(
-            StratumV1Source::compute_suggested_difficulty(HashRate::default()),
-            None,
-        );
-    }
+    fn verdicts_are_stamped_on_the_shares_they_answer() {
+        use crate::share_audit::{ShareAudit, ShareOutcome};
+
+        let audit = ShareAudit::new(8);
+        let mut source =
+            source_with_state(vec![0; 4], 4, None, None).with_share_audit(audit.clone());
+        let hashes: Vec<_> = (1..=3u8)
+            .map(|n| BlockHash::from_byte_array([n; 32]))
+            .collect();
+        for (nonce, hash) in hashes.iter().enumerate() {
+            audit.received(*hash, "job-1", "thread-0", "pool");
+            source.note_submitted("job-1", nonce as u32, *hash);
+        }
 
-    #[test]
-    fn test_compute_suggested_difficulty_bitaxe_gamma() {
-        // ~500 GH/s at 20 shares/min (3-sec interval) should yield ~349
-        let diff = StratumV1Source::compute_suggested_difficulty(HashRate::from_gigahashes(500.0))
-            .unwrap();
-        assert!(
-            (300..400).contains(&diff),
-            "Bitaxe Gamma difficulty {diff} not in expected range 300..400"
-        );
-    }
+        // Accepts name the nonce; rejects go to the oldest on the job
+        source.note_verdict("job-1", Some(1), true);
+        source.note_verdict("job-1", None, false);
 
-    #[test]
-    fn test_compute_suggested_difficulty_always_at_least_one() {
-        // Even at very low hashrate, difficulty should be at least 1
-        let diff =
-            StratumV1Source::compute_suggested_difficulty(HashRate::from_megahashes(1.0)).unwrap();
-        assert!(diff >= 1);
+        let outcome = |hash: &BlockHash| {
+            audit
+                .recent()
+                .into_iter()
+                .find(|e| e.hash == hash.to_string())
+                .and_then(|e| e.outcome)
+        };
+        assert_eq!(outcome(&hashes[0]), Some(ShareOutcome::Rejected));
+        assert_eq!(outcome(&hashes[1]), Some(ShareOutcome::Accepted));
+        assert_eq!(outcome(&hashes[2]), None);
     }
 
     #[tokio::test]
@@ -1130,7 +2135,62 @@ mod tests {
             }
             other => panic!("expected SuggestDifficulty, got {other:?}"),
         }
-        assert!(source.last_suggested_difficulty.is_some());
+        assert!(source.suggester.last_suggested().is_some());
+    }
+
+    /// A pool found refusing suggestions is sent no more of them, on this
+    /// connection or the next.
+    #[tokio::test]
+    async fn test_maybe_suggest_difficulty_respects_learned_quirk() {
+        let (event_tx, _event_rx) = mpsc::channel(10);
+        let (_command_tx, command_rx) = mpsc::channel(10);
+        let config = PoolConfig {
+            url: "stratum+tcp://test:3333".to_string(),
+            ..Default::default()
+        };
+        let mut source = StratumV1Source::new(
+            config,
+            command_rx,
+            event_tx,
+            CancellationToken::new(),
+            Box::new(NeverConnector),
+        );
+        source.expected_hashrate = HashRate::from_terahashes(1.0);
+
+        source
+            .handle_client_event(ClientEvent::QuirksLearned(PoolQuirks {
+                no_suggest_difficulty: true,
+                ..PoolQuirks::NONE
+            }))
+            .await
+            .unwrap();
+
+        let (client_tx, mut client_rx) = mpsc::channel(10);
+        source.maybe_suggest_difficulty(&client_tx).await;
+        assert!(client_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_maybe_suggest_difficulty_never_strategy() {
+        let (event_tx, _event_rx) = mpsc::channel(10);
+        let (_command_tx, command_rx) = mpsc::channel(10);
+        let config = PoolConfig {
+            url: "stratum+tcp://test:3333".to_string(),
+            ..Default::default()
+        };
+        let mut source = StratumV1Source::new(
+            config,
+            command_rx,
+            event_tx,
+            CancellationToken::new(),
+            Box::new(NeverConnector),
+        )
+        .with_suggest_strategy(SuggestStrategy::Never);
+        source.expected_hashrate = HashRate::from_terahashes(1.0);
+
+        let (client_tx, mut client_rx) = mpsc::channel(10);
+        source.maybe_suggest_difficulty(&client_tx).await;
+        assert!(client_rx.try_recv().is_err());
     }
 
     #[tokio::test]
@@ -1539,6 +2599,145 @@ mod tests {
         assert!(result.is_err(), "expected fatal error, got Ok");
     }
 
+    /// Share on `job_id` whose hash meets `difficulty`.
+    fn share_at_difficulty(job_id: &str, nonce: u32, difficulty: u64) -> Share {
+        let target = Difficulty::from(difficulty).to_target();
+        Share {
+            job_id: job_id.into(),
+            nonce,
+            time: 0x5a5a5a5a,
+            version: Version::from_consensus(0x20000000),
+            extranonce2: None,
+            device_id: None,
+            hash: BlockHash::from_byte_array(target.to_le_bytes()),
+        }
+    }
+
+    /// Wait for the next mining.submit and return its nonce and ID.
+    async fn recv_submit(handle: &mut MockTransportHandle) -> (u32, u64) {
+        let msg = handle.recv().await;
+        assert_eq!(msg.method(), Some("mining.submit"));
+        let JsonRpcMessage::Request { params, id, .. } = &msg else {
+            panic!("expected Request");
+        };
+        let nonce = u32::from_str_radix(params[4].as_str().unwrap(), 16).unwrap();
+        (nonce, id.unwrap())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_pool_gets_most_valuable_shares_first() {
+        let (source, mut event_rx, command_tx, mock_tx, shutdown) = source_with_mock_transports();
+        let (transport, mut handle) = MockTransport::pair();
+        mock_tx.send(transport).await.unwrap();
+        let source_handle = tokio::spawn(source.run());
+
+        command_tx
+            .send(SourceCommand::UpdateHashRate(HashRate::from_gigahashes(
+                500.0,
+            )))
+            .await
+            .unwrap();
+        do_handshake(&mut handle).await;
+        handle.send(job_notification("job-1"));
+        event_rx.recv().await.unwrap();
+
+        // The pool sits on the first submit; shares behind it back up
+        let submit = |nonce, difficulty| {
+            SourceCommand::SubmitShare(share_at_difficulty("job-1", nonce, difficulty))
+        };
+        command_tx.send(submit(1, 100)).await.unwrap();
+        let (nonce, first_id) = recv_submit(&mut handle).await;
+        assert_eq!(nonce, 1);
+
+        for (nonce, difficulty) in [(2, 100), (3, 100), (4, 50), (5, 5000), (6, 300)] {
+            command_tx.send(submit(nonce, difficulty)).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Shares already handed to the client go in arrival order, the
+        // backlog by difficulty
+        let mut order = Vec::new();
+        let mut id = first_id;
+        for _ in 0..5 {
+            handle.send(JsonRpcMessage::Response {
+                id,
+                result: Some(json!(true)),
+                error: None,
+            });
+            let (nonce, next_id) = recv_submit(&mut handle).await;
+            order.push(nonce);
+            id = next_id;
+        }
+        let buffered = &order[..CLIENT_COMMAND_BUFFER];
+        assert_eq!(buffered, [2, 3]);
+        assert_eq!(order[CLIENT_COMMAND_BUFFER..], [5, 6, 4]);
+
+        shutdown.cancel();
+        source_handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shares_found_offline_are_submitted_on_reconnect() {
+        let (source, mut event_rx, command_tx, mock_tx, shutdown) = source_with_mock_transports();
+        let source = source.with_offline_queue(Duration::from_secs(600));
+        let (transport1, mut handle1) = MockTransport::pair();
+        let (transport2, mut handle2) = MockTransport::pair();
+        mock_tx.send(transport1).await.unwrap();
+        mock_tx.send(transport2).await.unwrap();
+        let source_handle = tokio::spawn(source.run());
+
+        command_tx
+            .send(SourceCommand::UpdateHashRate(HashRate::from_gigahashes(
+                500.0,
+            )))
+            .await
+            .unwrap();
+        do_handshake(&mut handle1).await;
+        handle1.send(job_notification("job-1"));
+        event_rx.recv().await.unwrap();
+
+        // The pool goes away; the threads stay on job-1 and find a share
+        drop(handle1);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        command_tx
+            .send(SourceCommand::SubmitShare(share_at_difficulty(
+                "job-1", 7, 1000,
+            )))
+            .await
+            .unwrap();
+
+        // Back in the same session, on the same block
+        do_handshake(&mut handle2).await;
+        handle2.send(job_notification("job-2"));
+        let (nonce, id) = recv_submit(&mut handle2).await;
+        assert_eq!(nonce, 7);
+        handle2.send(JsonRpcMessage::Response {
+            id,
+            result: Some(json!(true)),
+            error: None,
+        });
+
+        let state = loop {
+            match event_rx.recv().await.unwrap() {
+                SourceEvent::OfflineQueue(state) if state.recovered_accepted == 1 => break state,
+                SourceEvent::ClearJobs => panic!("work cleared while holding shares"),
+                _ => {}
+            }
+        };
+        assert_eq!(
+            state,
+            OfflineQueueState {
+                held: 1,
+                recovered: 1,
+                recovered_accepted: 1,
+                discarded: 0,
+            }
+        );
+
+        shutdown.cancel();
+        source_handle.await.unwrap().unwrap();
+    }
+
     #[tokio::test(start_paused = true)]
     async fn shutdown_during_backoff() {
         let (source, mut event_rx, command_tx, mock_tx, shutdown) = source_with_mock_transports();
@@ -0,0 +1,372 @@
+//! Bounded queue of shares waiting to be submitted.
+//!
+//! A pool connection submits one share at a time and waits for the verdict,
+//! so a slow pool, or one that has dropped the connection, leaves shares
+//! piling up. Rather than let them pile up without limit, or drop them in
+//! arrival order, a source holds them here. When full, the least valuable
+//! share is dropped, and when the connection can take another, the most
+//! valuable goes first.
+//!
+//! Value is the difficulty the share achieved, then how recent its job is.
+//! A high-difficulty share counts for more (and might be a block); shares on
+//! the newest job are furthest from going stale. Shares on jobs a
+//! `clean_jobs` notify has superseded, and shares that have waited past
+//! `max_age`, would be rejected as stale anyway and are dropped first.
+//!
+//! Optionally, shares found while the pool is unreachable are held longer,
+//! up to `offline_max_age`, so a solo or lottery miner on a flaky link
+//! doesn't lose a block to a dropped connection. They are counted apart,
+//! for the API to show how many were recovered.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use super::Share;
+use crate::types::Difficulty;
+
+/// Number of job IDs remembered for ranking by recency.
+const JOB_HISTORY_LEN: usize = 16;
+
+/// A share held for submission.
+#[derive(Debug)]
+struct Queued {
+    share: Share,
+    difficulty: Difficulty,
+    queued_at: Instant,
+    /// Found while the pool was unreachable
+    offline: bool,
+}
+
+/// A share taken for submission.
+#[derive(Debug)]
+pub(crate) struct Dequeued {
+    pub share: Share,
+    /// Whether it was found while the pool was unreachable
+    pub offline: bool,
+}
+
+/// Bounded queue of shares ordered by value.
+#[derive(Debug)]
+pub(crate) struct SubmitQueue {
+    capacity: usize,
+    max_age: Duration,
+    entries: Vec<Queued>,
+
+    /// How long shares found while offline are held, if longer than
+    /// `max_age`.
+    offline_max_age: Option<Duration>,
+
+    /// Whether the pool is unreachable.
+    offline: bool,
+
+    /// Jobs shares are currently accepted for, oldest first.
+    jobs: VecDeque<String>,
+
+    /// Shares dropped without being submitted.
+    dropped: u64,
+
+    /// Shares queued while offline.
+    held_offline: u64,
+
+    /// Shares queued while offline and dropped without being submitted.
+    dropped_offline: u64,
+}
+
+impl SubmitQueue {
+    pub fn new(capacity: usize, max_age: Duration) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            max_age,
+            entries: Vec::new(),
+            offline_max_age: None,
+            offline: false,
+            jobs: VecDeque::new(),
+            dropped: 0,
+            held_offline: 0,
+            dropped_offline: 0,
+        }
+    }
+
+    /// Hold shares found while the pool is unreachable for up to
+    /// `max_age`.
+    pub fn with_offline_max_age(mut self, max_age: Duration) -> Self {
+        self.offline_max_age = Some(max_age);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Shares dropped so far, for full queue, staleness or age.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Shares queued while offline so far.
+    pub fn held_offline(&self) -> u64 {
+        self.held_offline
+    }
+
+    /// Shares queued while offline and dropped so far.
+    pub fn dropped_offline(&self) -> u64 {
+        self.dropped_offline
+    }
+
+    /// Note whether the pool is reachable. Shares pushed while it isn't
+    /// are held up to the offline max age, if one is set.
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline && self.offline_max_age.is_some();
+    }
+
+    /// Record a job from the pool.
+    ///
+    /// A `clean_jobs` job supersedes every earlier one, so shares queued
+    /// for those are dropped.
+    pub fn note_job(&mut self, job_id: &str, clean_jobs: bool) {
+        if clean_jobs {
+            self.jobs.clear();
+        }
+        self.jobs.retain(|id| id != job_id);
+        self.jobs.push_back(job_id.to_string());
+        if self.jobs.len() > JOB_HISTORY_LEN {
+            self.jobs.pop_front();
+        }
+
+        if clean_jobs {
+            let jobs = self.jobs.clone();
+            self.retain(|entry| jobs.contains(&entry.share.job_id));
+        }
+    }
+
+    /// Drop everything, e.g. when the session the shares belong to is gone.
+    pub fn clear(&mut self) {
+        self.retain(|_| false);
+        self.jobs.clear();
+    }
+
+    /// Queue a share found at `now`.
+    ///
+    /// Returns false if it was dropped because the queue is full of more
+    /// valuable shares.
+    pub fn push(&mut self, share: Share, now: Instant) -> bool {
+        self.expire(now);
+        let entry = Queued {
+            difficulty: Difficulty::from_hash(&share.hash),
+            share,
+            queued_at: now,
+            offline: self.offline,
+        };
+        if entry.offline {
+            self.held_offline += 1;
+        }
+
+        if self.entries.len() < self.capacity {
+            self.entries.push(entry);
+            return true;
+        }
+
+        let lowest = self.lowest();
+        if self.rank(&entry) > self.rank(&self.entries[lowest]) {
+            let replaced = std::mem::replace(&mut self.entries[lowest], entry);
+            self.note_dropped(&replaced);
+            true
+        } else {
+            self.note_dropped(&entry);
+            false
+        }
+    }
+
+    /// Take the most valuable share that is still fresh.
+    pub fn pop(&mut self, now: Instant) -> Option<Dequeued> {
+        self.expire(now);
+        let best = (0..self.entries.len()).max_by_key(|&i| self.rank(&self.entries[i]))?;
+        let entry = self.entries.swap_remove(best);
+        Some(Dequeued {
+            share: entry.share,
+            offline: entry.offline,
+        })
+    }
+
+    /// Drop shares that have waited longer than their max age.
+    fn expire(&mut self, now: Instant) {
+        let max_age = self.max_age;
+        let offline_max_age = self.offline_max_age.unwrap_or(max_age);
+        self.retain(|entry| {
+            let max_age = if entry.offline {
+                offline_max_age
+            } else {
+                max_age
+            };
+            now.duration_since(entry.queued_at) <= max_age
+        });
+    }
+
+    /// Keep the entries `keep` accepts, counting the rest as dropped.
+    fn retain(&mut self, keep: impl Fn(&Queued) -> bool) {
+        let (kept, gone): (Vec<_>, Vec<_>) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition(|entry| keep(entry));
+        self.entries = kept;
+        for entry in &gone {
+            self.note_dropped(entry);
+        }
+    }
+
+    fn note_dropped(&mut self, entry: &Queued) {
+        self.dropped += 1;
+        if entry.offline {
+            self.dropped_offline += 1;
+        }
+    }
+
+    fn lowest(&self) -> usize {
+        (0..self.entries.len())
+            .min_by_key(|&i| self.rank(&self.entries[i]))
+            .expect("queue is full")
+    }
+
+    /// Ordering key: difficulty, then job recency, then earliest queued.
+    fn rank(&self, entry: &Queued) -> (Difficulty, Option<usize>, std::cmp::Reverse<Instant>) {
+        let recency = self.jobs.iter().position(|id| *id == entry.share.job_id);
+        (
+            entry.difficulty,
+            recency,
+            std::cmp::Reverse(entry.queued_at),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::BlockHash;
+    use bitcoin::block::Version;
+    use bitcoin::hashes::Hash;
+
+    use super::*;
+
+    /// Share on `job_id` whose hash meets roughly `difficulty`.
+    fn share(job_id: &str, difficulty: u64) -> Share {
+        let target = Difficulty::from(difficulty).to_target();
+        Share {
+            job_id: job_id.into(),
+            nonce: difficulty as u32,
+            time: 0,
+            version: Version::TWO,
+            extranonce2: None,
+            device_id: None,
+            hash: BlockHash::from_byte_array(target.to_le_bytes()),
+        }
+    }
+
+    fn nonces(queue: &mut SubmitQueue, now: Instant) -> Vec<u32> {
+        std::iter::from_fn(|| queue.pop(now))
+            .map(|d| d.share.nonce)
+            .collect()
+    }
+
+    #[test]
+    fn pops_highest_difficulty_then_newest_job() {
+        let now = Instant::now();
+        let mut queue = SubmitQueue::new(8, Duration::from_secs(60));
+        queue.note_job("a", true);
+        queue.note_job("b", false);
+
+        queue.push(share("a", 100), now);
+        queue.push(share("a", 5000), now);
+        queue.push(share("b", 100), now);
+        queue.push(share("a", 300), now);
+
+        let order: Vec<(String, u32)> = std::iter::from_fn(|| queue.pop(now))
+            .map(|d| (d.share.job_id, d.share.nonce))
+            .collect();
+        assert_eq!(
+            order,
+            [
+                ("a".into(), 5000),
+                ("a".into(), 300),
+                ("b".into(), 100),
+                ("a".into(), 100)
+            ]
+        );
+    }
+
+    #[test]
+    fn full_queue_drops_least_valuable() {
+        let now = Instant::now();
+        let mut queue = SubmitQueue::new(2, Duration::from_secs(60));
+        queue.note_job("a", true);
+
+        assert!(queue.push(share("a", 200), now));
+        assert!(queue.push(share("a", 100), now));
+        // Better than the lowest: replaces it
+        assert!(queue.push(share("a", 400), now));
+        // Worse than everything queued: dropped
+        assert!(!queue.push(share("a", 50), now));
+
+        assert_eq!(queue.dropped(), 2);
+        assert_eq!(nonces(&mut queue, now), [400, 200]);
+    }
+
+    #[test]
+    fn clean_jobs_and_age_drop_stale_shares() {
+        let start = Instant::now();
+        let mut queue = SubmitQueue::new(8, Duration::from_secs(30));
+        queue.note_job("a", true);
+        queue.push(share("a", 1000), start);
+        queue.note_job("b", false);
+        queue.push(share("b", 100), start);
+
+        // A new block: neither old job is worth submitting
+        queue.note_job("c", true);
+        assert!(queue.is_empty());
+        assert_eq!(queue.dropped(), 2);
+
+        queue.push(share("c", 100), start);
+        queue.push(share("c", 200), start + Duration::from_secs(20));
+        assert_eq!(nonces(&mut queue, start + Duration::from_secs(40)), [200]);
+        assert_eq!(queue.dropped(), 3);
+    }
+
+    #[test]
+    fn shares_found_offline_are_held_longer_and_counted() {
+        let start = Instant::now();
+        let mut queue = SubmitQueue::new(8, Duration::from_secs(30))
+            .with_offline_max_age(Duration::from_secs(600));
+        queue.note_job("a", true);
+        queue.push(share("a", 100), start);
+
+        queue.set_offline(true);
+        queue.push(share("a", 200), start);
+        queue.push(share("a", 300), start);
+        queue.set_offline(false);
+        assert_eq!(queue.held_offline(), 2);
+
+        // The online share has aged out; the offline ones are still fresh
+        let later = start + Duration::from_secs(60);
+        let first = queue.pop(later).unwrap();
+        assert_eq!((first.share.nonce, first.offline), (300, true));
+        assert_eq!(queue.dropped(), 1);
+        assert_eq!(queue.dropped_offline(), 0);
+
+        assert!(queue.pop(start + Duration::from_secs(601)).is_none());
+        assert_eq!(queue.dropped_offline(), 1);
+    }
+
+    #[test]
+    fn offline_is_ignored_without_an_offline_max_age() {
+        let start = Instant::now();
+        let mut queue = SubmitQueue::new(8, Duration::from_secs(30));
+        queue.note_job("a", true);
+        queue.set_offline(true);
+        queue.push(share("a", 100), start);
+        assert_eq!(queue.held_offline(), 0);
+        assert!(queue.pop(start + Duration::from_secs(31)).is_none());
+    }
+}
@@ -197,6 +197,27 @@ pub mod block_881423 {
         &COINBASE_TX[62..]
     }
 
+    /// Bytes of witness data at the end of COINBASE_TX, before the locktime:
+    /// one stack item holding the 32-byte witness reserved value.
+    const WITNESS_LEN: usize = 1 + 1 + 32;
+
+    /// Coinbase1 as a Stratum pool sends it, in legacy format without the
+    /// SegWit marker and flag.
+    pub fn coinbase1_legacy_bytes() -> Vec<u8> {
+        [&COINBASE_TX[0..4], &COINBASE_TX[6..54]].concat()
+    }
+
+    /// Coinbase2 as a Stratum pool sends it, in legacy format without the
+    /// witness.
+    pub fn coinbase2_legacy_bytes() -> Vec<u8> {
+        let locktime = COINBASE_TX.len() - 4;
+        [
+            &COINBASE_TX[62..locktime - WITNESS_LEN],
+            &COINBASE_TX[locktime..],
+        ]
+        .concat()
+    }
+
     /// Extranonce1 as Extranonce2 type
     pub static EXTRANONCE1: LazyLock<Extranonce2> = LazyLock::new(|| {
         let bytes = extranonce1_bytes();
@@ -354,6 +375,25 @@ pub mod block_881423 {
             );
         }
 
+        #[test]
+        fn test_legacy_coinbase_has_same_txid() {
+            use bitcoin::Transaction;
+            use bitcoin::consensus::deserialize;
+
+            let legacy = [
+                coinbase1_legacy_bytes().as_slice(),
+                extranonce1_bytes(),
+                extranonce2_bytes(),
+                &coinbase2_legacy_bytes(),
+            ]
+            .concat();
+            let legacy: Transaction = deserialize(&legacy).expect("Valid transaction");
+            let segwit: Transaction = deserialize(COINBASE_TX).expect("Valid transaction");
+
+            assert!(legacy.input[0].witness.is_empty());
+            assert_eq!(legacy.compute_txid(), segwit.compute_txid());
+        }
+
         #[test]
         fn test_merkle_root_from_coinbase_and_branches() {
             use bitcoin::Transaction;
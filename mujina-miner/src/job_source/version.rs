@@ -2,6 +2,9 @@
 
 use bitcoin::block::Version;
 
+/// Version bits BIP320 sets aside for general purpose use (bits 13-28).
+pub const BIP320_VERSION_MASK: u32 = 0x1fff_e000;
+
 /// General purpose bits for version rolling (BIP320, bits 13-28).
 ///
 /// A 2-byte bit pattern occupying positions 13-28 of the block
@@ -40,6 +43,24 @@ impl GeneralPurposeBits {
             .all(|(m, b)| (b & !m) == 0)
     }
 
+    /// Bits set in both this mask and `other`.
+    pub fn intersect(&self, other: &GeneralPurposeBits) -> Self {
+        Self([self.0[0] & other.0[0], self.0[1] & other.0[1]])
+    }
+
+    /// Number of bits set.
+    ///
+    /// For a rolling mask, the number of rolled bits: the mask covers
+    /// `2^count_ones()` versions per header.
+    pub fn count_ones(&self) -> u32 {
+        u16::from_be_bytes(self.0).count_ones()
+    }
+
+    /// The 4-byte version mask form of these bits, as used by Stratum.
+    pub fn to_version_mask(&self) -> u32 {
+        (u16::from_be_bytes(self.0) as u32) << 13
+    }
+
     /// Apply these bits to a base version to produce the final version.
     ///
     /// Shifts these bits left 13 positions and ORs with the base version.
@@ -98,6 +119,26 @@ impl From<&[u8; 4]> for GeneralPurposeBits {
     }
 }
 
+/// Bits a thread can actually roll under a pool's version mask.
+///
+/// The pool's mask is clipped to the BIP320 general purpose bits, since
+/// rolling anything else changes the version's meaning, and then to the
+/// bits the hardware can roll.
+///
+/// # Example
+///
+/// ```
+/// use mujina_miner::job_source::{GeneralPurposeBits, effective_rolling_mask};
+///
+/// // Pool allows bits 13-24 plus bit 31, hardware rolls bits 13-28
+/// let mask = effective_rolling_mask(0x81ffe000, GeneralPurposeBits::full());
+/// assert_eq!(mask.to_version_mask(), 0x01ffe000);
+/// assert_eq!(mask.count_ones(), 12);
+/// ```
+pub fn effective_rolling_mask(pool_mask: u32, hardware: GeneralPurposeBits) -> GeneralPurposeBits {
+    GeneralPurposeBits::from(&(pool_mask & BIP320_VERSION_MASK).to_be_bytes()).intersect(&hardware)
+}
+
 /// Errors from VersionTemplate operations
 #[derive(Debug, thiserror::Error)]
 pub enum VersionTemplateError {
@@ -158,6 +199,11 @@ impl VersionTemplate {
         self.gp_bits_mask
     }
 
+    /// Bits hardware supporting `hardware` may roll on this template.
+    pub fn rolling_mask_for(&self, hardware: GeneralPurposeBits) -> GeneralPurposeBits {
+        self.gp_bits_mask.intersect(&hardware)
+    }
+
     /// Apply general purpose bits with validation.
     ///
     /// # Errors
@@ -255,6 +301,44 @@ mod tests {
         assert!(!mask.contains(&GeneralPurposeBits::new([0xf0, 0xff])));
     }
 
+    #[test]
+    fn test_gp_bits_intersect_and_count() {
+        let pool = GeneralPurposeBits::from(&0x01ffe000u32.to_be_bytes());
+        let chip = GeneralPurposeBits::new([0xff, 0x00]);
+
+        let both = pool.intersect(&chip);
+        assert_eq!(both.as_bytes(), &[0x0f, 0x00]);
+        assert_eq!(both.count_ones(), 4);
+        assert_eq!(both.to_version_mask(), 0x01e0_0000);
+
+        assert_eq!(GeneralPurposeBits::full().count_ones(), 16);
+        assert_eq!(GeneralPurposeBits::none().count_ones(), 0);
+        assert_eq!(
+            GeneralPurposeBits::full().to_version_mask(),
+            BIP320_VERSION_MASK
+        );
+    }
+
+    #[test]
+    fn test_effective_rolling_mask() {
+        // Bits outside BIP320's range are never rolled
+        let mask = effective_rolling_mask(0xffff_ffff, GeneralPurposeBits::full());
+        assert_eq!(mask.to_version_mask(), BIP320_VERSION_MASK);
+
+        // Hardware without rolling support rolls nothing
+        let mask = effective_rolling_mask(BIP320_VERSION_MASK, GeneralPurposeBits::none());
+        assert_eq!(mask.count_ones(), 0);
+
+        // Template narrows hardware the same way
+        let template = VersionTemplate::new(
+            Version::from_consensus(0x20000000),
+            GeneralPurposeBits::from(&0x1fffe000u32.to_be_bytes()),
+        )
+        .unwrap();
+        let chip = GeneralPurposeBits::new([0x00, 0xff]);
+        assert_eq!(template.rolling_mask_for(chip), chip);
+    }
+
     #[test]
     fn test_gp_bits_full() {
         let full = GeneralPurposeBits::full();
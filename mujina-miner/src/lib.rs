@@ -1,17 +1,29 @@
+pub mod agent;
+pub mod alerts;
 pub mod api;
 pub mod api_client;
 pub mod asic;
 pub mod backplane;
 pub mod board;
+pub mod build_info;
 pub mod config;
+#[cfg(feature = "cpu-miner")]
 pub mod cpu_miner;
 pub mod daemon;
 pub mod error;
+pub mod events;
 pub mod hw_trait;
 pub mod job_source;
 pub mod mgmt_protocol;
+pub mod miner;
 pub mod peripheral;
+pub mod proxy;
 pub mod scheduler;
+pub mod schema;
+pub mod self_check;
+pub mod sensors;
+pub mod share_audit;
+pub mod share_proof;
 pub mod stratum_v1;
 pub mod tracing;
 pub mod transport;
@@ -13,7 +13,7 @@ use tokio_serial::SerialStream;
 use tokio_stream::StreamExt;
 use tokio_util::codec::{FramedRead, FramedWrite};
 
-use super::{ControlCodec, Packet, Response};
+use super::{ControlCodec, ErrorCode, Packet, Response};
 
 /// Control channel for bitaxe-raw protocol communication.
 ///
@@ -31,6 +31,9 @@ struct ControlChannelInner {
 }
 
 impl ControlChannel {
+    /// How long most commands get to answer.
+    const RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
     /// Create a new control channel from a serial stream.
     pub fn new(stream: SerialStream) -> Self {
         let (reader, writer) = tokio::io::split(stream);
@@ -44,7 +47,18 @@ impl ControlChannel {
     }
 
     /// Send a raw packet and wait for response.
-    pub async fn send_packet(&self, mut packet: Packet) -> io::Result<Response> {
+    pub async fn send_packet(&self, packet: Packet) -> io::Result<Response> {
+        self.send_packet_within(packet, Self::RESPONSE_TIMEOUT)
+            .await
+    }
+
+    /// As [`send_packet`](Self::send_packet), for commands the firmware
+    /// takes longer to answer, such as erasing flash.
+    pub async fn send_packet_within(
+        &self,
+        mut packet: Packet,
+        timeout: Duration,
+    ) -> io::Result<Response> {
         let mut inner = self.inner.lock().await;
 
         // Assign packet ID
@@ -56,7 +70,6 @@ impl ControlChannel {
         inner.writer.send(packet).await?;
 
         // Wait for response with matching ID
-        let timeout = Duration::from_secs(1);
         let response = time::timeout(timeout, async {
             match inner.reader.next().await {
                 Some(Ok(resp)) => {
@@ -81,12 +94,18 @@ impl ControlChannel {
         .await
         .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "Control command timeout"))??;
 
-        // Check for protocol errors
+        // Check for protocol errors. Firmware without a page rejects its
+        // commands as invalid; report that as Unsupported so callers can
+        // tell a missing feature from a failing one.
         if let Some(error) = response.error() {
-            return Err(io::Error::other(format!(
-                "Control protocol error: {:?}",
-                error
-            )));
+            let kind = match error.code {
+                ErrorCode::InvalidCommand => io::ErrorKind::Unsupported,
+                _ => io::ErrorKind::Other,
+            };
+            return Err(io::Error::new(
+                kind,
+                format!("Control protocol error: {:?}", error),
+            ));
         }
 
         Ok(response)
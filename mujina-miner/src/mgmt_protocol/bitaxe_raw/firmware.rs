@@ -0,0 +1,218 @@
+//! Firmware updates using bitaxe-raw control protocol.
+//!
+//! The update runs entirely over the control channel: the image is written
+//! into a spare flash slot, the firmware checks it against the CRC-32 given
+//! up front, and only then is asked to boot it. An update cut short at any
+//! point before the reboot leaves the running firmware in place.
+
+use std::io;
+use std::time::Duration;
+
+use crc_all::CrcAlgo;
+
+use super::Packet;
+use super::channel::ControlChannel;
+use crate::hw_trait::{HwError, Result};
+
+/// Largest chunk sent per write, well inside the codec's packet limit.
+const MAX_CHUNK_LEN: usize = 1024;
+
+/// How long the firmware may take to erase the update slot.
+const ERASE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long the firmware may take to check the staged image.
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What the running firmware reports about itself and its update slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareInfo {
+    /// Largest image the update slot holds, in bytes
+    pub slot_len: u32,
+    /// Largest chunk the firmware accepts per write, in bytes
+    pub chunk_len: u16,
+    /// Version of the running firmware
+    pub version: String,
+}
+
+impl FirmwareInfo {
+    /// Parse an info response: `[slot_len:4 LE] [chunk_len:2 LE] [version...]`.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let Some((&[s0, s1, s2, s3, c0, c1], version)) = data.split_first_chunk::<6>() else {
+            return Err(HwError::InvalidParameter(format!(
+                "Expected at least 6 bytes in firmware info response, got {}",
+                data.len()
+            )));
+        };
+        Ok(Self {
+            slot_len: u32::from_le_bytes([s0, s1, s2, s3]),
+            chunk_len: u16::from_le_bytes([c0, c1]),
+            version: String::from_utf8_lossy(version).into_owned(),
+        })
+    }
+
+    /// Check that `image` can be staged in the update slot.
+    pub fn check_image(&self, image: &[u8]) -> Result<()> {
+        if image.is_empty() {
+            return Err(HwError::InvalidParameter("firmware image is empty".into()));
+        }
+        if image.len() > self.slot_len as usize {
+            return Err(HwError::InvalidParameter(format!(
+                "firmware image is {} bytes, update slot holds {}",
+                image.len(),
+                self.slot_len
+            )));
+        }
+        if self.chunk_len == 0 {
+            return Err(HwError::InvalidParameter(
+                "firmware accepts no write chunks".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Where an update has got to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashProgress {
+    Erasing,
+    Writing { written: u32, total: u32 },
+    Verifying,
+    Rebooting,
+}
+
+/// CRC-32 of a firmware image, as computed by zlib.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = CRC32_INIT;
+    CRC32.update_crc(&mut crc, data);
+    CRC32.finish_crc(&crc)
+}
+
+const CRC32_INIT: u32 = 0xffff_ffff;
+
+const CRC32: CrcAlgo<u32> = CrcAlgo::<u32>::new(
+    0x04c1_1db7, // polynomial
+    32,          // width
+    CRC32_INIT,  // init
+    0xffff_ffff, // xorout
+    true,        // reflect
+);
+
+/// Firmware updater using bitaxe-raw control protocol.
+///
+/// A thin Clone-able wrapper around the shared control channel, like the
+/// other bitaxe-raw handles. Firmware builds without the update page yield
+/// [`HwError::NotSupported`].
+#[derive(Clone)]
+pub struct BitaxeRawFirmware {
+    channel: ControlChannel,
+}
+
+impl BitaxeRawFirmware {
+    /// Create a firmware updater using the given control channel.
+    pub fn new(channel: ControlChannel) -> Self {
+        Self { channel }
+    }
+
+    /// Read the running firmware's version and update slot size.
+    pub async fn info(&self) -> Result<FirmwareInfo> {
+        let data = self.request(Packet::firmware_info(0), None).await?;
+        FirmwareInfo::parse(&data)
+    }
+
+    /// Stage `image`, have the firmware verify it, and reboot into it.
+    ///
+    /// `progress` hears about each step as it starts. Once this returns
+    /// the device is resetting, and its serial ports go away with it.
+    pub async fn flash(&self, image: &[u8], mut progress: impl FnMut(FlashProgress)) -> Result<()> {
+        let info = self.info().await?;
+        info.check_image(image)?;
+        let total = image.len() as u32;
+
+        progress(FlashProgress::Erasing);
+        self.request(
+            Packet::firmware_begin(0, total, crc32(image)),
+            Some(ERASE_TIMEOUT),
+        )
+        .await?;
+
+        let chunk_len = usize::from(info.chunk_len).min(MAX_CHUNK_LEN);
+        let mut written = 0;
+        for chunk in image.chunks(chunk_len) {
+            progress(FlashProgress::Writing { written, total });
+            self.request(Packet::firmware_write(0, written, chunk), None)
+                .await?;
+            written += chunk.len() as u32;
+        }
+        progress(FlashProgress::Writing { written, total });
+
+        progress(FlashProgress::Verifying);
+        self.request(Packet::firmware_finish(0), Some(VERIFY_TIMEOUT))
+            .await?;
+
+        progress(FlashProgress::Rebooting);
+        match self.request(Packet::firmware_reboot(0), None).await {
+            Ok(_) => Ok(()),
+            // The device reset before its answer got out; the image was
+            // already verified, so that is as good as an answer
+            Err(HwError::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::TimedOut
+                        | io::ErrorKind::UnexpectedEof
+                        | io::ErrorKind::BrokenPipe
+                ) =>
+            {
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn request(&self, packet: Packet, timeout: Option<Duration>) -> Result<Vec<u8>> {
+        let result = match timeout {
+            Some(timeout) => self.channel.send_packet_within(packet, timeout).await,
+            None => self.channel.send_packet(packet).await,
+        };
+        match result {
+            Ok(response) => Ok(response.data),
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => Err(HwError::NotSupported(
+                "firmware update not implemented by firmware".into(),
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_firmware_info() {
+        let info =
+            FirmwareInfo::parse(&[0x00, 0x00, 0x10, 0x00, 0x00, 0x01, b'1', b'.', b'2']).unwrap();
+        assert_eq!(info.slot_len, 0x0010_0000);
+        assert_eq!(info.chunk_len, 256);
+        assert_eq!(info.version, "1.2");
+
+        assert!(FirmwareInfo::parse(&[0x00, 0x00]).is_err());
+    }
+
+    #[test]
+    fn checks_image_fits_slot() {
+        let info = FirmwareInfo {
+            slot_len: 4,
+            chunk_len: 256,
+            version: String::new(),
+        };
+        assert!(info.check_image(&[0; 4]).is_ok());
+        assert!(info.check_image(&[]).is_err());
+        assert!(info.check_image(&[0; 5]).is_err());
+    }
+
+    #[test]
+    fn crc32_matches_zlib() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+}
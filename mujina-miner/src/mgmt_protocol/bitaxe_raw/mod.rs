@@ -27,6 +27,9 @@
 //! - `0x05` - I2C operations (peripheral communication)
 //! - `0x06` - GPIO operations (ASIC reset, status pins)
 //! - `0x07` - ADC operations (voltage monitoring)
+//! - `0x08` - PSU status (input power, power-good)
+//! - `0x09` - Fan tachometer readings
+//! - `0x0A` - Firmware update (staging and booting a new image)
 //!
 //! The bus field is always `0x00` in current firmware.
 //!
@@ -43,14 +46,37 @@
 //! - Read: `[addr] [read_len]` -> Response: `[data...]`
 //! - Write-Read: `[addr] [write_data...] [read_len]` -> Response: `[data...]`
 //!
+//! ## Telemetry Operations
+//!
+//! ADC, PSU, and fan pages return little-endian integers; see
+//! [`telemetry`] for the typed responses:
+//! - ADC VDD: `[0x50]` -> Response: `[mv:2]`
+//! - ADC channel: `[0x51] [channel]` -> Response: `[raw:2] [mv:2]`
+//! - PSU status: `[0x10]` -> Response: `[flags] [faults] [vin_mv:2] [iin_ma:2]`
+//! - Fan tach: `[0x10] [fan]` -> Response: `[rpm:2]`
+//!
+//! ## Firmware Update Operations
+//!
+//! The firmware stages a new image in a spare flash slot and only boots
+//! it once its CRC-32 checks out; see [`firmware`]:
+//! - Info: `[0x10]` -> Response: `[slot_len:4] [chunk_len:2] [version...]`
+//! - Begin: `[0x20] [image_len:4] [crc32:4]` erases the slot
+//! - Write: `[0x30] [offset:4] [bytes...]`
+//! - Finish: `[0x40]` verifies the staged image
+//! - Reboot: `[0x50]` boots the staged image
+//!
+//! Firmware builds without a page answer with `InvalidCommand`.
+//!
 //! ## Error Responses
 //!
 //! Errors are indicated by a response data field starting with `0xFF` followed
 //! by an error code. See [`ErrorCode`] for defined error types.
 
 pub mod channel;
+pub mod firmware;
 pub mod gpio;
 pub mod i2c;
+pub mod telemetry;
 
 use bytes::{BufMut, BytesMut};
 use std::{fmt, io};
@@ -85,6 +111,12 @@ pub enum Page {
     GPIO = 0x06,
     /// ADC operations (voltage monitoring)
     ADC = 0x07,
+    /// PSU status (input voltage/current, power-good)
+    PSU = 0x08,
+    /// Fan tachometer readings
+    FAN = 0x09,
+    /// Firmware update
+    FIRMWARE = 0x0A,
 }
 
 /// I2C commands
@@ -104,6 +136,32 @@ pub enum I2CCommand {
 #[repr(u8)]
 pub enum ADCCommand {
     ReadVDD = 0x50,
+    ReadChannel = 0x51,
+}
+
+/// PSU commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PSUCommand {
+    ReadStatus = 0x10,
+}
+
+/// Fan commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FANCommand {
+    ReadTach = 0x10,
+}
+
+/// Firmware update commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum FirmwareCommand {
+    Info = 0x10,
+    Begin = 0x20,
+    Write = 0x30,
+    Finish = 0x40,
+    Reboot = 0x50,
 }
 
 /// Control protocol error codes
@@ -168,6 +226,57 @@ impl Packet {
         Self::new(id, Page::GPIO, pin, vec![])
     }
 
+    /// Read the supply voltage seen by the microcontroller.
+    pub fn adc_read_vdd(id: u8) -> Self {
+        Self::new(id, Page::ADC, ADCCommand::ReadVDD as u8, vec![])
+    }
+
+    /// Read an ADC channel.
+    pub fn adc_read_channel(id: u8, channel: u8) -> Self {
+        Self::new(id, Page::ADC, ADCCommand::ReadChannel as u8, vec![channel])
+    }
+
+    /// Read the input power supply status.
+    pub fn psu_status(id: u8) -> Self {
+        Self::new(id, Page::PSU, PSUCommand::ReadStatus as u8, vec![])
+    }
+
+    /// Read a fan's tachometer.
+    pub fn fan_tach(id: u8, fan: u8) -> Self {
+        Self::new(id, Page::FAN, FANCommand::ReadTach as u8, vec![fan])
+    }
+
+    /// Read the running firmware's version and update slot size.
+    pub fn firmware_info(id: u8) -> Self {
+        Self::new(id, Page::FIRMWARE, FirmwareCommand::Info as u8, vec![])
+    }
+
+    /// Erase the update slot for an image of `len` bytes with checksum `crc32`.
+    pub fn firmware_begin(id: u8, len: u32, crc32: u32) -> Self {
+        let mut data = Vec::with_capacity(8);
+        data.put_u32_le(len);
+        data.put_u32_le(crc32);
+        Self::new(id, Page::FIRMWARE, FirmwareCommand::Begin as u8, data)
+    }
+
+    /// Write part of the image at `offset` into the update slot.
+    pub fn firmware_write(id: u8, offset: u32, chunk: &[u8]) -> Self {
+        let mut data = Vec::with_capacity(4 + chunk.len());
+        data.put_u32_le(offset);
+        data.extend_from_slice(chunk);
+        Self::new(id, Page::FIRMWARE, FirmwareCommand::Write as u8, data)
+    }
+
+    /// Check the staged image against the checksum given to `Begin`.
+    pub fn firmware_finish(id: u8) -> Self {
+        Self::new(id, Page::FIRMWARE, FirmwareCommand::Finish as u8, vec![])
+    }
+
+    /// Reboot into the staged image.
+    pub fn firmware_reboot(id: u8) -> Self {
+        Self::new(id, Page::FIRMWARE, FirmwareCommand::Reboot as u8, vec![])
+    }
+
     /// Encode packet to bytes
     pub fn encode(&self) -> Vec<u8> {
         let mut buf = Vec::new();
@@ -381,6 +490,40 @@ mod tests {
         assert_eq!(encoded[5], 0x05); // command byte is pin 5
     }
 
+    #[test]
+    fn test_telemetry_packet_encoding() {
+        let encoded = Packet::adc_read_channel(0x01, 3).encode();
+        assert_eq!(encoded, vec![0x07, 0x00, 0x01, 0x00, 0x07, 0x51, 0x03]);
+
+        let encoded = Packet::psu_status(0x02).encode();
+        assert_eq!(encoded, vec![0x06, 0x00, 0x02, 0x00, 0x08, 0x10]);
+
+        let encoded = Packet::fan_tach(0x03, 0).encode();
+        assert_eq!(encoded, vec![0x07, 0x00, 0x03, 0x00, 0x09, 0x10, 0x00]);
+    }
+
+    #[test]
+    fn test_firmware_packet_encoding() {
+        let encoded = Packet::firmware_begin(0x01, 0x0001_0000, 0xcbf4_3926).encode();
+        assert_eq!(
+            encoded,
+            vec![
+                0x0e, 0x00, 0x01, 0x00, 0x0a, 0x20, 0x00, 0x00, 0x01, 0x00, 0x26, 0x39, 0xf4, 0xcb
+            ]
+        );
+
+        let encoded = Packet::firmware_write(0x02, 0x100, &[0xaa, 0xbb]).encode();
+        assert_eq!(
+            encoded,
+            vec![
+                0x0c, 0x00, 0x02, 0x00, 0x0a, 0x30, 0x00, 0x01, 0x00, 0x00, 0xaa, 0xbb
+            ]
+        );
+
+        let encoded = Packet::firmware_reboot(0x03).encode();
+        assert_eq!(encoded, vec![0x06, 0x00, 0x03, 0x00, 0x0a, 0x50]);
+    }
+
     #[test]
     fn test_response_parsing() {
         // Success response with data
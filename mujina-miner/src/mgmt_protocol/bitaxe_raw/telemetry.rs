@@ -0,0 +1,195 @@
+//! ADC, PSU, and fan tachometer readings using bitaxe-raw control protocol.
+
+use std::io;
+
+use async_trait::async_trait;
+
+use super::Packet;
+use super::channel::ControlChannel;
+use crate::hw_trait::adc::{Adc, AdcChannel};
+use crate::hw_trait::{HwError, Result};
+
+/// Reading from one ADC channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdcReading {
+    /// Raw converter output
+    pub raw: u16,
+    /// Calibrated voltage at the pin
+    pub millivolts: u16,
+}
+
+impl AdcReading {
+    /// Parse an ADC channel response: `[raw:2 LE] [mv:2 LE]`.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let [r0, r1, m0, m1] = expect_len(data, "ADC channel")?;
+        Ok(Self {
+            raw: u16::from_le_bytes([r0, r1]),
+            millivolts: u16::from_le_bytes([m0, m1]),
+        })
+    }
+}
+
+/// Input power supply status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PsuStatus {
+    /// Supply reports its output in regulation
+    pub power_good: bool,
+    /// Firmware-defined fault bits, zero when healthy
+    pub faults: u8,
+    /// Input voltage
+    pub input_mv: u16,
+    /// Input current
+    pub input_ma: u16,
+}
+
+impl PsuStatus {
+    /// Bit 0 of the flags byte.
+    const POWER_GOOD: u8 = 0x01;
+
+    /// Parse a PSU status response:
+    /// `[flags] [faults] [vin_mv:2 LE] [iin_ma:2 LE]`.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let [flags, faults, v0, v1, i0, i1] = expect_len(data, "PSU status")?;
+        Ok(Self {
+            power_good: flags & Self::POWER_GOOD != 0,
+            faults,
+            input_mv: u16::from_le_bytes([v0, v1]),
+            input_ma: u16::from_le_bytes([i0, i1]),
+        })
+    }
+
+    /// Input power in milliwatts.
+    pub fn input_mw(&self) -> u32 {
+        u32::from(self.input_mv) * u32::from(self.input_ma) / 1000
+    }
+}
+
+/// Fan tachometer reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FanTach {
+    pub rpm: u16,
+}
+
+impl FanTach {
+    /// Parse a fan tach response: `[rpm:2 LE]`.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let [r0, r1] = expect_len(data, "fan tach")?;
+        Ok(Self {
+            rpm: u16::from_le_bytes([r0, r1]),
+        })
+    }
+}
+
+/// Check a response payload has exactly `N` bytes.
+fn expect_len<const N: usize>(data: &[u8], what: &str) -> Result<[u8; N]> {
+    data.try_into().map_err(|_| {
+        HwError::InvalidParameter(format!(
+            "Expected {} bytes in {} response, got {}",
+            N,
+            what,
+            data.len()
+        ))
+    })
+}
+
+/// Board telemetry reader using bitaxe-raw control protocol.
+///
+/// Like the GPIO and I2C handles, this is a thin Clone-able wrapper around
+/// the shared control channel. Firmware builds that don't implement a page
+/// yield [`HwError::NotSupported`] for its readings.
+#[derive(Clone)]
+pub struct BitaxeRawTelemetry {
+    channel: ControlChannel,
+}
+
+impl BitaxeRawTelemetry {
+    /// Create a telemetry reader using the given control channel.
+    pub fn new(channel: ControlChannel) -> Self {
+        Self { channel }
+    }
+
+    /// Supply voltage seen by the microcontroller, in millivolts.
+    pub async fn read_vdd(&self) -> Result<u16> {
+        let data = self.request(Packet::adc_read_vdd(0), "ADC VDD").await?;
+        let [m0, m1] = expect_len(&data, "ADC VDD")?;
+        Ok(u16::from_le_bytes([m0, m1]))
+    }
+
+    /// Read one ADC channel.
+    pub async fn read_adc(&self, channel: u8) -> Result<AdcReading> {
+        let data = self
+            .request(Packet::adc_read_channel(0, channel), "ADC channel")
+            .await?;
+        AdcReading::parse(&data)
+    }
+
+    /// Read the input power supply status.
+    pub async fn psu_status(&self) -> Result<PsuStatus> {
+        let data = self.request(Packet::psu_status(0), "PSU status").await?;
+        PsuStatus::parse(&data)
+    }
+
+    /// Read a fan's tachometer.
+    pub async fn fan_tach(&self, fan: u8) -> Result<FanTach> {
+        let data = self.request(Packet::fan_tach(0, fan), "fan tach").await?;
+        FanTach::parse(&data)
+    }
+
+    async fn request(&self, packet: Packet, what: &str) -> Result<Vec<u8>> {
+        match self.channel.send_packet(packet).await {
+            Ok(response) => Ok(response.data),
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => Err(HwError::NotSupported(
+                format!("{} not implemented by firmware", what),
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl Adc for BitaxeRawTelemetry {
+    async fn read_raw(&mut self, channel: AdcChannel) -> Result<u16> {
+        Ok(self.read_adc(channel.0).await?.raw)
+    }
+
+    async fn read_millivolts(&mut self, channel: AdcChannel) -> Result<u32> {
+        Ok(u32::from(self.read_adc(channel.0).await?.millivolts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_psu_status() {
+        let status = PsuStatus::parse(&[0x01, 0x00, 0x88, 0x13, 0xe8, 0x03]).unwrap();
+        assert!(status.power_good);
+        assert_eq!(status.faults, 0);
+        assert_eq!(status.input_mv, 5000);
+        assert_eq!(status.input_ma, 1000);
+        assert_eq!(status.input_mw(), 5000);
+
+        let status = PsuStatus::parse(&[0x00, 0x04, 0x00, 0x00, 0x00, 0x00]).unwrap();
+        assert!(!status.power_good);
+        assert_eq!(status.faults, 0x04);
+    }
+
+    #[test]
+    fn parses_adc_and_tach() {
+        let reading = AdcReading::parse(&[0xff, 0x0f, 0xe4, 0x0c]).unwrap();
+        assert_eq!(reading.raw, 4095);
+        assert_eq!(reading.millivolts, 3300);
+
+        assert_eq!(FanTach::parse(&[0xb8, 0x0b]).unwrap().rpm, 3000);
+    }
+
+    #[test]
+    fn rejects_short_responses() {
+        assert!(matches!(
+            PsuStatus::parse(&[0x01, 0x00]),
+            Err(HwError::InvalidParameter(_))
+        ));
+        assert!(FanTach::parse(&[]).is_err());
+    }
+}
@@ -0,0 +1,849 @@
+//! The miner's core, for embedding as a library.
+//!
+//! A [`Miner`] runs what the daemon runs: board discovery and the
+//! backplane, the scheduler, the configured job sources with thermal
+//! management on the boards, and by default the HTTP API. Programs that
+//! want their own controller build one from a [`Config`] with
+//! [`Miner::builder`], add job sources of their own if they like, and
+//! drive it through a [`MinerHandle`]: the same commands the API sends,
+//! plus subscriptions to the scheduler's state, the boards, and the
+//! [`events`](crate::events) the miner publishes.
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! use mujina_miner::{api_client::types::PauseLevel, config::Config, miner::Miner};
+//!
+//! let miner = Miner::builder(Config::default()).with_api(false).start().await?;
+//! let handle = miner.handle();
+//! handle.pause(PauseLevel::IdleChips).await?;
+//! println!("{} H/s", handle.state().hashrate);
+//! miner.stop().await;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Only one miner runs per process: boards read the settings in
+//! `[boards]` from a global that [`MinerBuilder::start`] installs.
+
+use anyhow::Context;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+use crate::api_client::types::{BoardState, MinerState, PauseLevel, Profile};
+use crate::tracing::prelude::*;
+use crate::{
+    alerts::{self, AlertEngine, AlertHistory, Notifier},
+    api::{
+        self, ApiConfig,
+        commands::{BoardCommand, CommandBus, CommandError, SchedulerCommand},
+    },
+    asic::hash_thread::HashThread,
+    backplane::{Backplane, BoardRegistry},
+    build_info,
+    config::{self, Config},
+    events::{BoardEvent, Boards, EventBus},
+    job_source::{
+        SourceCommand, SourceEvent,
+        dummy::DummySource,
+        forced_rate::{ForcedRateConfig, ForcedRateSource, ForcedTarget},
+        stratum_v1::StratumV1Source,
+    },
+    proxy::StratumProxy,
+    scheduler::{
+        self, SchedulerChannels, SourcePolicy, SourceRegistration, decision_log::DecisionLog,
+    },
+    self_check::{self, StartupChecks},
+    share_audit::ShareAudit,
+    share_proof::{self, ShareProofs},
+    stratum_v1::{PoolConfig as StratumPoolConfig, TcpConnector},
+    transport::{NetworkTransport, TransportEvent, UsbTransport},
+};
+
+/// Builds and starts a [`Miner`].
+pub struct MinerBuilder {
+    config: Config,
+    api: bool,
+    sources: Vec<SourceRegistration>,
+}
+
+impl MinerBuilder {
+    /// Serve the HTTP API as the daemon does (default true).
+    pub fn with_api(mut self, enabled: bool) -> Self {
+        self.api = enabled;
+        self
+    }
+
+    /// Register a job source of the caller's own alongside the
+    /// configured ones.
+    ///
+    /// The caller runs the source, feeding jobs into the registration's
+    /// event channel and taking shares from its command channel.
+    pub fn with_source(mut self, registration: SourceRegistration) -> Self {
+        self.sources.push(registration);
+        self
+    }
+
+    /// Start every component and return once they are running.
+    pub async fn start(self) -> anyhow::Result<Miner> {
+        let shutdown = CancellationToken::new();
+        let tracker = TaskTracker::new();
+
+        let mut startup_checks = StartupChecks {
+            config: self_check::check_config(&self.config),
+            ..Default::default()
+        };
+        let effective_config = self.config.clone();
+        let Config {
+            daemon,
+            pool,
+            solo,
+            api,
+            boards,
+            agent: _,
+            proxy,
+            alerts: alert_config,
+            path: _,
+        } = self.config;
+        let usb_discovery = boards.usb_discovery.unwrap_or(true);
+        let simulate = boards.simulate.unwrap_or(false);
+        let profile = boards.profile.unwrap_or_default();
+        let burn_in_dir = boards.burn_in_dir();
+        let network_boards = boards.network.clone().unwrap_or_default();
+        let network_grace = boards.network_grace();
+        config::install_board_config(boards);
+
+        // Create channels for component communication
+        let (transport_tx, transport_rx) = mpsc::channel::<TransportEvent>(100);
+        let (thread_tx, thread_rx) = mpsc::channel::<Box<dyn HashThread>>(10);
+        let (source_reg_tx, source_reg_rx) = mpsc::channel::<SourceRegistration>(10);
+
+        // Create and start USB transport discovery
+        if usb_discovery {
+            let usb_transport = UsbTransport::new(transport_tx.clone());
+            let discovery = match usb_transport.start_discovery(shutdown.clone()).await {
+                Ok(()) => tokio::task::spawn_blocking(|| {
+                    UsbTransport::enumerate().map(|devices| BoardRegistry.detect(&devices).len())
+                })
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|found| found.map_err(|e| e.to_string())),
+                Err(e) => {
+                    error!("Failed to start USB discovery: {}", e);
+                    Err(e.to_string())
+                }
+            };
+            startup_checks.usb = self_check::check_usb(discovery);
+        } else {
+            info!("USB discovery disabled");
+        }
+
+        // Probe configured network boards
+        if !network_boards.is_empty() {
+            info!(boards = network_boards.len(), "Network boards configured");
+            NetworkTransport::new(transport_tx.clone())
+                .start_discovery(network_boards, shutdown.clone());
+        }
+
+        inject_cpu_miner(&transport_tx).await;
+
+        if simulate {
+            info!("Simulated board enabled");
+            let event = TransportEvent::Simulated {
+                device_id: "sim-0".into(),
+            };
+            if let Err(e) = transport_tx.send(event).await {
+                error!("Failed to send simulated board event: {}", e);
+            }
+        }
+
+        // Event bus: subsystems publish, the API and the handle's
+        // subscribers consume. Board events are subscribed to before the
+        // backplane starts so no early board is missed.
+        let events = EventBus::new();
+        let api_board_events = self.api.then(|| events.subscribe::<Boards>());
+        let mut board_events = events.subscribe::<Boards>();
+
+        // Board command channel: API sends commands, backplane processes them.
+        let (board_cmd_tx, board_cmd_rx) = mpsc::channel::<BoardCommand>(16);
+
+        // Downstream miners join the scheduler as hash threads of their own
+        if let Some(listen) = proxy.listen {
+            let proxy = StratumProxy::bind(&listen, thread_tx.clone()).await?;
+            tracker.spawn(proxy.run(shutdown.clone()).instrument(info_span!("proxy")));
+        }
+
+        // Create and start backplane
+        let mut backplane = Backplane::new(transport_rx, thread_tx, events.clone(), board_cmd_rx)
+            .with_profile(profile)
+            .with_burn_in_dir(burn_in_dir)
+            .with_reconnect_grace(network_grace);
+        tracker.spawn({
+            let shutdown = shutdown.clone();
+            async move {
+                tokio::select! {
+                    result = backplane.run().instrument(info_span!("backplane")) => {
+                        if let Err(e) = result {
+                            error!("Backplane error: {}", e);
+                        }
+                    }
+                    _ = shutdown.cancelled() => {}
+                }
+
+                backplane.shutdown_all_boards().await;
+            }
+        });
+
+        // Create job source (Stratum v1 or Dummy)
+        // Controlled by the pool configuration:
+        // - url: Pool address (e.g., stratum+tcp://localhost:3333)
+        // - user: Worker username (optional, defaults to "mujina-testing");
+        //   "{board_serial}" in it is replaced per board, e.g. "addr.{board_serial}"
+        // - password: Worker password (optional, defaults to "x")
+        let (source_event_tx, source_event_rx) = mpsc::channel::<SourceEvent>(100);
+        let (source_cmd_tx, source_cmd_rx) = mpsc::channel(10);
+
+        let user_agent = pool
+            .user_agent
+            .unwrap_or_else(build_info::default_user_agent);
+        info!(%user_agent, "Miner identity");
+        let ntime_correction = pool.ntime_correction.unwrap_or(false);
+        let pool_quirks = pool.quirks.unwrap_or_default();
+        let pool_suggest = pool.suggest_difficulty.unwrap_or_default();
+        let pool_offline_queue = pool
+            .offline_queue_secs
+            .map(tokio::time::Duration::from_secs);
+
+        // Share audit log, stamped by the scheduler and pool sources
+        let share_audit = ShareAudit::new(daemon.share_audit.unwrap_or(0));
+        if share_audit.is_enabled() {
+            info!(shares = daemon.share_audit, "Share audit log enabled");
+        }
+
+        // Proofs of accepted shares, built by the scheduler and written
+        // by pool sources
+        if let Some(path) = &daemon.share_proofs {
+            info!(path = %path.display(), "Recording share proofs");
+            share_proof::install(
+                ShareProofs::create(path)
+                    .with_context(|| format!("failed to open share proofs {}", path.display()))?,
+            );
+        }
+
+        let has_pool = pool.url.is_some();
+        let has_solo = solo.url.is_some();
+        if let Some(pool_url) = pool.url {
+            // Use Stratum v1 source
+            let pool_user = pool.user.unwrap_or_else(|| "mujina-testing".to_string());
+            let pool_pass = pool.password.unwrap_or_else(|| "x".to_string());
+
+            let stratum_config = StratumPoolConfig {
+                url: pool_url.clone(),
+                username: pool_user,
+                password: pool_pass,
+                user_agent: user_agent.clone(),
+            };
+
+            // Optionally wrap with ForcedRateSource for testing; a configured
+            // difficulty takes precedence over MUJINA_POOL_FORCED_RATE
+            let forced_rate_config = pool
+                .forced_difficulty
+                .map(ForcedRateConfig::difficulty)
+                .or_else(ForcedRateConfig::from_env);
+            if let Some(forced_rate_config) = forced_rate_config {
+                info!(
+                    forced = %forced_rate_config.target,
+                    "Forced share target wrapper enabled"
+                );
+                let suffix = match forced_rate_config.target {
+                    ForcedTarget::Rate(_) => "forced-rate",
+                    ForcedTarget::Difficulty(_) => "forced-difficulty",
+                };
+
+                // Create inner channels (stratum <-> wrapper)
+                let (inner_event_tx, inner_event_rx) = mpsc::channel::<SourceEvent>(100);
+                let (inner_cmd_tx, inner_cmd_rx) = mpsc::channel::<SourceCommand>(10);
+
+                let stratum_source = StratumV1Source::new(
+                    stratum_config,
+                    inner_cmd_rx,
+                    inner_event_tx,
+                    shutdown.clone(),
+                    Box::new(TcpConnector::new(pool_url.clone())),
+                )
+                .with_ntime_correction(ntime_correction)
+                .with_share_audit(share_audit.clone())
+                .with_quirks(pool_quirks)
+                .with_suggest_strategy(pool_suggest);
+                let stratum_source = match pool_offline_queue {
+                    Some(max_age) => stratum_source.with_offline_queue(max_age),
+                    None => stratum_source,
+                };
+                let stratum_name = stratum_source.name();
+                let span = info_span!("source", source = %stratum_name);
+
+                // Spawn stratum source
+                tracker.spawn(
+                    async move {
+                        if let Err(e) = stratum_source.run().await {
+                            error!("Stratum v1 source error: {}", e);
+                        }
+                    }
+                    .instrument(span.clone()),
+                );
+
+                // Create and spawn wrapper (uses outer channels from above)
+                let forced_rate = ForcedRateSource::new(
+                    forced_rate_config,
+                    inner_event_rx,
+                    source_event_tx,
+                    inner_cmd_tx,
+                    source_cmd_rx,
+                    shutdown.clone(),
+                );
+
+                source_reg_tx
+                    .send(SourceRegistration {
+                        name: format!("{} ({})", stratum_name, suffix),
+                        url: Some(pool_url.clone()),
+                        event_rx: source_event_rx,
+                        command_tx: source_cmd_tx,
+                        policy: SourcePolicy::Shared,
+                    })
+                    .await?;
+
+                tracker.spawn(
+                    async move {
+                        if let Err(e) = forced_rate.run().await {
+                            error!("Forced rate wrapper error: {}", e);
+                        }
+                    }
+                    .instrument(span),
+                );
+            } else {
+                // Direct stratum source (no wrapper)
+                let stratum_source = StratumV1Source::new(
+                    stratum_config,
+                    source_cmd_rx,
+                    source_event_tx,
+                    shutdown.clone(),
+                    Box::new(TcpConnector::new(pool_url.clone())),
+                )
+                .with_ntime_correction(ntime_correction)
+                .with_share_audit(share_audit.clone())
+                .with_quirks(pool_quirks)
+                .with_suggest_strategy(pool_suggest);
+                let stratum_source = match pool_offline_queue {
+                    Some(max_age) => stratum_source.with_offline_queue(max_age),
+                    None => stratum_source,
+                };
+
+                let span = info_span!("source", source = %stratum_source.name());
+                source_reg_tx
+                    .send(SourceRegistration {
+                        name: stratum_source.name(),
+                        url: Some(pool_url),
+                        event_rx: source_event_rx,
+                        command_tx: source_cmd_tx,
+                        policy: SourcePolicy::Shared,
+                    })
+                    .await?;
+
+                tracker.spawn(
+                    async move {
+                        if let Err(e) = stratum_source.run().await {
+                            error!("Stratum v1 source error: {}", e);
+                        }
+                    }
+                    .instrument(span),
+                );
+            }
+        } else if solo.url.is_none() {
+            // Use DummySource
+            info!("Using dummy job source (configure a pool URL to use Stratum v1)");
+
+            let dummy_source = DummySource::new(
+                source_cmd_rx,
+                source_event_tx,
+                shutdown.clone(),
+                tokio::time::Duration::from_secs(30),
+            )?;
+
+            source_reg_tx
+                .send(SourceRegistration {
+                    name: "dummy".into(),
+                    url: None,
+                    event_rx: source_event_rx,
+                    command_tx: source_cmd_tx,
+                    policy: SourcePolicy::Shared,
+                })
+                .await?;
+
+            tracker.spawn(
+                async move {
+                    if let Err(e) = dummy_source.run().await {
+                        error!("DummySource error: {}", e);
+                    }
+                }
+                .instrument(info_span!("source", source = "dummy")),
+            );
+        }
+
+        // Solo pool: a few lottery threads alongside the pool, or every
+        // thread when it's the only source
+        if let Some(solo_url) = solo.url {
+            let policy = if has_pool {
+                SourcePolicy::Pinned {
+                    threads: solo.threads.unwrap_or(1),
+                }
+            } else {
+                SourcePolicy::Shared
+            };
+            info!(url = %solo_url, ?policy, "Solo mining enabled");
+
+            let (solo_event_tx, solo_event_rx) = mpsc::channel::<SourceEvent>(100);
+            let (solo_cmd_tx, solo_cmd_rx) = mpsc::channel(10);
+            let solo_config = StratumPoolConfig {
+                url: solo_url.clone(),
+                username: solo.user.unwrap_or_else(|| "mujina-testing".to_string()),
+                password: solo.password.unwrap_or_else(|| "x".to_string()),
+                user_agent: user_agent.clone(),
+            };
+            let solo_source = StratumV1Source::new(
+                solo_config,
+                solo_cmd_rx,
+                solo_event_tx,
+                shutdown.clone(),
+                Box::new(TcpConnector::new(solo_url.clone())),
+            )
+            .with_ntime_correction(ntime_correction)
+            .with_share_audit(share_audit.clone())
+            .with_suggest_strategy(solo.suggest_difficulty.unwrap_or_default());
+            let solo_source = match solo.offline_queue_secs {
+                Some(secs) => {
+                    solo_source.with_offline_queue(tokio::time::Duration::from_secs(secs))
+                }
+                None => solo_source,
+            };
+
+            // Named apart from the pool, which may be the same server
+            let name = format!("{} (solo)", solo_source.name());
+            let span = info_span!("source", source = %name);
+            source_reg_tx
+                .send(SourceRegistration {
+                    name,
+                    url: Some(solo_url),
+                    event_rx: solo_event_rx,
+                    command_tx: solo_cmd_tx,
+                    policy,
+                })
+                .await?;
+
+            tracker.spawn(
+                async move {
+                    if let Err(e) = solo_source.run().await {
+                        error!("Solo stratum v1 source error: {}", e);
+                    }
+                }
+                .instrument(span),
+            );
+        }
+
+        // Backfill: dummy work for the chips while no pool has any. Without
+        // a pool the dummy source above is already the real work.
+        if daemon.idle_backfill.unwrap_or(false) && (has_pool || has_solo) {
+            info!("Idle backfill enabled");
+            let (backfill_event_tx, backfill_event_rx) = mpsc::channel::<SourceEvent>(100);
+            let (backfill_cmd_tx, backfill_cmd_rx) = mpsc::channel(10);
+            let backfill_source = DummySource::new(
+                backfill_cmd_rx,
+                backfill_event_tx,
+                shutdown.clone(),
+                tokio::time::Duration::from_secs(30),
+            )?;
+
+            source_reg_tx
+                .send(SourceRegistration {
+                    name: "backfill".into(),
+                    url: None,
+                    event_rx: backfill_event_rx,
+                    command_tx: backfill_cmd_tx,
+                    policy: SourcePolicy::Backfill,
+                })
+                .await?;
+
+            tracker.spawn(
+                async move {
+                    if let Err(e) = backfill_source.run().await {
+                        error!("Backfill source error: {}", e);
+                    }
+                }
+                .instrument(info_span!("source", source = "backfill")),
+            );
+        }
+
+        // Sources the embedding program brought
+        for registration in self.sources {
+            info!(source = %registration.name, "Registering added source");
+            source_reg_tx.send(registration).await?;
+        }
+
+        // Miner state channel: scheduler publishes snapshots, API serves them.
+        let (miner_state_tx, miner_state_rx) = watch::channel(MinerState::default());
+
+        // Command channel: API sends commands, scheduler processes them.
+        let (scheduler_cmd_tx, scheduler_cmd_rx) = mpsc::channel::<SchedulerCommand>(16);
+
+        // Start the scheduler
+        let decision_log = match daemon.decision_log {
+            Some(path) => {
+                info!(path = %path.display(), "Recording scheduler decisions");
+                DecisionLog::create(&path)
+                    .with_context(|| format!("failed to create decision log {}", path.display()))?
+            }
+            None => DecisionLog::disabled(),
+        };
+        tracker.spawn(
+            scheduler::task(
+                shutdown.clone(),
+                SchedulerChannels {
+                    threads: thread_rx,
+                    sources: source_reg_rx,
+                    miner_state: miner_state_tx,
+                    commands: scheduler_cmd_rx,
+                },
+                decision_log,
+                share_audit.clone(),
+                events.clone(),
+            )
+            .instrument(info_span!("scheduler")),
+        );
+
+        // Boards as the handle's subscribers see them
+        let (boards_tx, _) = watch::channel(Vec::<watch::Receiver<BoardState>>::new());
+        tracker.spawn({
+            let boards_tx = boards_tx.clone();
+            let shutdown = shutdown.clone();
+            async move {
+                loop {
+                    let event = tokio::select! {
+                        event = board_events.recv() => event,
+                        _ = shutdown.cancelled() => break,
+                    };
+                    match event {
+                        Ok(BoardEvent::Added { state_rx, .. }) => {
+                            boards_tx.send_modify(|boards| {
+                                boards.retain(|rx| rx.has_changed().is_ok());
+                                boards.push(state_rx);
+                            });
+                        }
+                        Ok(BoardEvent::Removed { .. }) => {}
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            warn!(missed, "Missed board events");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            }
+        });
+
+        // Alert rules, checked against the scheduler's and boards' state
+        let alert_rules = alert_config.rules.unwrap_or_default();
+        let alert_history = if alert_rules.is_empty() {
+            AlertHistory::disabled()
+        } else {
+            let mqtt = alert_config
+                .mqtt
+                .as_deref()
+                .map(str::parse)
+                .transpose()
+                .map_err(|e: String| anyhow::anyhow!("invalid alerts.mqtt: {e}"))?;
+            let notifier = Notifier::new(alert_config.webhooks.unwrap_or_default(), mqtt);
+            let history =
+                AlertHistory::new(alert_config.history.unwrap_or(alerts::DEFAULT_HISTORY));
+            info!(rules = alert_rules.len(), "Alerting enabled");
+            tracker.spawn(
+                alerts::task(
+                    AlertEngine::new(alert_rules, alert_config.expected_hashrate),
+                    notifier,
+                    history.clone(),
+                    miner_state_rx.clone(),
+                    boards_tx.subscribe(),
+                    shutdown.clone(),
+                )
+                .instrument(info_span!("alerts")),
+            );
+            history
+        };
+
+        let commands = CommandBus::new(scheduler_cmd_tx, board_cmd_tx);
+
+        // Start the API server
+        if let Some(board_events) = api_board_events {
+            tracker.spawn({
+                let shutdown = shutdown.clone();
+                let commands = commands.clone();
+                let miner_state_rx = miner_state_rx.clone();
+                let events = events.clone();
+                async move {
+                    // ASCII 'M' (77) + 'U' (85) = 7785
+                    const API_PORT: u16 = 7785;
+
+                    let bind_addr = match api.listen {
+                        Some(addr) if addr.contains(':') => addr,
+                        Some(addr) => format!("{addr}:{API_PORT}"),
+                        None => format!("127.0.0.1:{API_PORT}"),
+                    };
+                    let config = ApiConfig {
+                        bind_addr,
+                        socket_path: api.socket,
+                        profile,
+                        user_agent,
+                        startup_checks,
+                        config: effective_config,
+                        alerts: alert_history,
+                    };
+                    if let Err(e) = api::serve(
+                        config,
+                        shutdown,
+                        miner_state_rx,
+                        events,
+                        board_events,
+                        commands,
+                        share_audit,
+                    )
+                    .await
+                    {
+                        error!("API server error: {}", e);
+                    }
+                }
+            });
+        }
+
+        tracker.close();
+
+        Ok(Miner {
+            handle: MinerHandle {
+                commands,
+                state_rx: miner_state_rx,
+                boards_rx: boards_tx.subscribe(),
+                source_reg_tx,
+                events,
+            },
+            shutdown,
+            tracker,
+            _transport_tx: transport_tx,
+        })
+    }
+}
+
+/// A running miner.
+///
+/// Runs until [`stop`](Self::stop)ped; dropping it leaves the tasks
+/// running with nothing to stop them.
+pub struct Miner {
+    handle: MinerHandle,
+    shutdown: CancellationToken,
+    tracker: TaskTracker,
+    /// Keeps the backplane's board events open while no transport is
+    /// discovering boards
+    _transport_tx: mpsc::Sender<TransportEvent>,
+}
+
+impl Miner {
+    /// Start building a miner from `config`.
+    pub fn builder(config: Config) -> MinerBuilder {
+        MinerBuilder {
+            config,
+            api: true,
+            sources: Vec::new(),
+        }
+    }
+
+    /// Handle for controlling and watching the miner.
+    pub fn handle(&self) -> MinerHandle {
+        self.handle.clone()
+    }
+
+    /// Shut every component down, boards last, and wait for them.
+    pub async fn stop(self) {
+        self.shutdown.cancel();
+        self.tracker.wait().await;
+    }
+}
+
+/// Controls and watches a running miner; cheap to clone.
+#[derive(Clone)]
+pub struct MinerHandle {
+    commands: CommandBus,
+    state_rx: watch::Receiver<MinerState>,
+    boards_rx: watch::Receiver<Vec<watch::Receiver<BoardState>>>,
+    source_reg_tx: mpsc::Sender<SourceRegistration>,
+    events: EventBus,
+}
+
+impl MinerHandle {
+    /// Latest state published by the scheduler.
+    ///
+    /// Boards are listed by [`boards`](Self::boards); the API merges the
+    /// two.
+    pub fn state(&self) -> MinerState {
+        self.state_rx.borrow().clone()
+    }
+
+    /// Follow the scheduler's state as it is published.
+    pub fn subscribe(&self) -> watch::Receiver<MinerState> {
+        self.state_rx.clone()
+    }
+
+    /// Snapshot every connected board.
+    pub fn boards(&self) -> Vec<BoardState> {
+        self.boards_rx
+            .borrow()
+            .iter()
+            .filter(|rx| rx.has_changed().is_ok())
+            .map(|rx| rx.borrow().clone())
+            .collect()
+    }
+
+    /// Follow boards as they arrive; each entry follows one board's
+    /// state until it goes away.
+    pub fn subscribe_boards(&self) -> watch::Receiver<Vec<watch::Receiver<BoardState>>> {
+        self.boards_rx.clone()
+    }
+
+    /// The miner's event bus, to subscribe to shares, board lifecycle,
+    /// thermal readings and pool events.
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Every command the API can send, for those without a method here.
+    pub fn commands(&self) -> &CommandBus {
+        &self.commands
+    }
+
+    /// Register a job source of the caller's own while the miner runs.
+    pub async fn add_source(&self, registration: SourceRegistration) -> anyhow::Result<()> {
+        self.source_reg_tx
+            .send(registration)
+            .await
+            .map_err(|_| anyhow::anyhow!("scheduler has stopped"))
+    }
+
+    /// Pause mining at `level`, or move an existing pause to it.
+    pub async fn pause(&self, level: PauseLevel) -> Result<(), CommandError> {
+        self.commands
+            .request(|reply| SchedulerCommand::PauseMining { level, reply })
+            .await
+    }
+
+    /// Resume mining after a pause.
+    pub async fn resume(&self) -> Result<(), CommandError> {
+        self.commands
+            .request(|reply| SchedulerCommand::ResumeMining { reply })
+            .await
+    }
+
+    /// Mine `source` whenever it has work, or hand the choice back to
+    /// failover with `None`.
+    pub async fn prefer_source(&self, source: Option<String>) -> Result<(), CommandError> {
+        self.commands
+            .request(|reply| SchedulerCommand::PreferSource { source, reply })
+            .await
+    }
+
+    /// Switch every board to an operating profile.
+    pub async fn set_profile(&self, profile: Profile) -> Result<(), CommandError> {
+        self.commands
+            .request(|reply| BoardCommand::SetProfile { profile, reply })
+            .await
+    }
+
+    /// Set a board's core frequency, or return it to the profile's with
+    /// `None`.
+    pub async fn set_frequency(
+        &self,
+        board: &str,
+        frequency_mhz: Option<f32>,
+    ) -> Result<(), CommandError> {
+        let board = board.to_string();
+        self.commands
+            .request(|reply| BoardCommand::SetFrequency {
+                board,
+                frequency_mhz,
+                reply,
+            })
+            .await
+    }
+
+    /// Set a fan's target duty cycle, or return it to automatic control
+    /// with `None`.
+    pub async fn set_fan_target(
+        &self,
+        board: &str,
+        fan: &str,
+        percent: Option<u8>,
+    ) -> Result<(), CommandError> {
+        let (board, fan) = (board.to_string(), fan.to_string());
+        self.commands
+            .request(|reply| BoardCommand::SetFanTarget {
+                board,
+                fan,
+                percent,
+                reply,
+            })
+            .await
+    }
+
+    /// Take a board's hash threads out of service, leaving it up.
+    pub async fn disable_board(&self, board: &str) -> Result<(), CommandError> {
+        let board = board.to_string();
+        self.commands
+            .request(|reply| BoardCommand::Disable { board, reply })
+            .await
+    }
+
+    /// Bring a disabled board back into service.
+    pub async fn enable_board(&self, board: &str) -> Result<(), CommandError> {
+        let board = board.to_string();
+        self.commands
+            .request(|reply| BoardCommand::Enable { board, reply })
+            .await
+    }
+}
+
+/// Inject the CPU miner's virtual device, if configured.
+#[cfg(feature = "cpu-miner")]
+pub(crate) async fn inject_cpu_miner(transport_tx: &mpsc::Sender<TransportEvent>) {
+    use crate::{
+        cpu_miner::CpuMinerConfig,
+        transport::{CpuDeviceInfo, cpu as cpu_transport},
+    };
+
+    let Some(config) = CpuMinerConfig::from_env() else {
+        return;
+    };
+    info!(
+        threads = config.thread_count,
+        duty = config.duty_percent,
+        "CPU miner enabled"
+    );
+    let event = TransportEvent::Cpu(cpu_transport::TransportEvent::CpuDeviceConnected(
+        CpuDeviceInfo {
+            device_id: format!("cpu-{}x{}%", config.thread_count, config.duty_percent),
+            thread_count: config.thread_count,
+            duty_percent: config.duty_percent,
+        },
+    ));
+    if let Err(e) = transport_tx.send(event).await {
+        error!("Failed to send CPU miner event: {}", e);
+    }
+}
+
+/// Without the CPU miner, say so rather than ignore its configuration.
+#[cfg(not(feature = "cpu-miner"))]
+pub(crate) async fn inject_cpu_miner(_transport_tx: &mpsc::Sender<TransportEvent>) {
+    if std::env::var_os("MUJINA_CPUMINER_THREADS").is_some() {
+        warn!("MUJINA_CPUMINER_THREADS is set, but this build has no CPU miner");
+    }
+}
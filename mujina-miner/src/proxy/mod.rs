@@ -0,0 +1,97 @@
+//! Proxy mode: serve the job source to downstream Stratum v1 miners.
+//!
+//! Small devices (a Bitaxe, a USB stick, an old S9 control board) each
+//! holding their own pool connection clutter the pool's worker list and
+//! each pay the connection's overhead. With `[proxy] listen` set, mujina
+//! also listens as a Stratum v1 pool: miners pointed at it mine the jobs
+//! mujina's own boards mine, and their shares go upstream over mujina's
+//! one pool connection, under its worker.
+//!
+//! Each downstream miner is a hash thread like any board's ([`session`]):
+//! the scheduler hands it tasks and slices of extranonce2 space, sets its
+//! share difficulty from its hashrate, and counts and filters its shares.
+//! How a task becomes a Stratum job, and a submission a share, is in
+//! [`work`].
+//!
+//! Miners are trusted no more than chips are: every submission is rebuilt
+//! and hashed before it goes upstream. Whatever username a miner
+//! authorizes with is accepted and ignored.
+
+pub mod session;
+pub mod work;
+
+use std::net::SocketAddr;
+
+use anyhow::Context;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::asic::hash_thread::HashThread;
+use crate::stratum_v1::{Connection, enable_keepalive};
+use crate::tracing::prelude::*;
+
+/// Port the proxy listens on unless configured otherwise.
+pub const DEFAULT_PORT: u16 = 3333;
+
+/// Listens for downstream miners and registers each with the scheduler.
+pub struct StratumProxy {
+    listener: TcpListener,
+    thread_tx: mpsc::Sender<Box<dyn HashThread>>,
+}
+
+impl StratumProxy {
+    /// Listen on `listen` (`host:port`, or a host on [`DEFAULT_PORT`]).
+    pub async fn bind(
+        listen: &str,
+        thread_tx: mpsc::Sender<Box<dyn HashThread>>,
+    ) -> anyhow::Result<Self> {
+        let bind_addr = match listen {
+            addr if addr.contains(':') => addr.to_string(),
+            addr => format!("{addr}:{DEFAULT_PORT}"),
+        };
+        let listener = TcpListener::bind(&bind_addr)
+            .await
+            .with_context(|| format!("failed to listen on {bind_addr}"))?;
+        info!(addr = %bind_addr, "Stratum proxy listening.");
+        Ok(Self {
+            listener,
+            thread_tx,
+        })
+    }
+
+    /// The address the proxy is listening on.
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accept miners until `shutdown` fires.
+    pub async fn run(self, shutdown: CancellationToken) {
+        loop {
+            let (stream, peer) = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                accepted = self.listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!(error = %e, "Failed to accept downstream miner");
+                        continue;
+                    }
+                },
+            };
+
+            info!(%peer, "Downstream miner connected.");
+            if let Err(e) = enable_keepalive(&stream) {
+                debug!(%peer, error = %e, "Failed to enable TCP keepalive");
+            }
+            tokio::spawn(
+                session::run(
+                    Connection::new(stream),
+                    peer,
+                    self.thread_tx.clone(),
+                    shutdown.clone(),
+                )
+                .instrument(info_span!("proxy", %peer)),
+            );
+        }
+    }
+}
@@ -0,0 +1,636 @@
+//! One downstream miner's connection.
+//!
+//! The session speaks Stratum v1 as a pool would, and offers the miner to
+//! the scheduler as a hash thread ([`ProxyThread`]). The scheduler assigns
+//! it tasks like any other thread; the session announces each as a job,
+//! checks what the miner submits against it and hands the shares back on
+//! the task's share channel.
+//!
+//! The miner is registered when it subscribes, and its subscribe is
+//! answered once the first task arrives: until then there is no
+//! extranonce1 to give it.
+
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
+
+use super::work::{DownstreamJob, EXTRANONCE2_SIZE, Reject, Submission};
+use crate::asic::hash_thread::{
+    AssignmentParameters, HashTask, HashThread, HashThreadCapabilities, HashThreadError,
+    HashThreadEvent, HashThreadStatus,
+};
+use crate::job_source::{BIP320_VERSION_MASK, GeneralPurposeBits};
+use crate::stratum_v1::{JsonRpcMessage, StratumError, Transport};
+use crate::tracing::prelude::*;
+use crate::types::HashRate;
+
+/// Jobs a miner may still submit against once newer ones are out.
+const RECENT_JOBS: usize = 8;
+
+type TaskResponse = oneshot::Sender<Result<Option<HashTask>, HashThreadError>>;
+
+/// Scheduler calls, carried to the session.
+enum Command {
+    Negotiate {
+        max_ntime_roll: u32,
+    },
+    UpdateTask {
+        task: HashTask,
+        response_tx: TaskResponse,
+    },
+    ReplaceTask {
+        task: HashTask,
+        response_tx: TaskResponse,
+    },
+    GoIdle {
+        response_tx: TaskResponse,
+    },
+}
+
+/// A downstream miner as the scheduler sees it.
+pub struct ProxyThread {
+    name: String,
+    command_tx: mpsc::Sender<Command>,
+    event_rx: Option<mpsc::Receiver<HashThreadEvent>>,
+    status: Arc<RwLock<HashThreadStatus>>,
+    capabilities: HashThreadCapabilities,
+}
+
+impl ProxyThread {
+    async fn send(&self, command: Command) -> Result<(), HashThreadError> {
+        self.command_tx
+            .send(command)
+            .await
+            .map_err(|_| HashThreadError::ThreadOffline)
+    }
+
+    async fn request(
+        &self,
+        command: impl FnOnce(TaskResponse) -> Command,
+    ) -> Result<Option<HashTask>, HashThreadError> {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.send(command(response_tx)).await?;
+        response_rx
+            .await
+            .map_err(|_| HashThreadError::ThreadOffline)?
+    }
+}
+
+#[async_trait]
+impl HashThread for ProxyThread {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> &HashThreadCapabilities {
+        &self.capabilities
+    }
+
+    async fn negotiate(&mut self, params: AssignmentParameters) -> Result<(), HashThreadError> {
+        self.send(Command::Negotiate {
+            max_ntime_roll: params.max_ntime_roll,
+        })
+        .await
+    }
+
+    async fn update_task(
+        &mut self,
+        new_task: HashTask,
+    ) -> Result<Option<HashTask>, HashThreadError> {
+        self.request(|response_tx| Command::UpdateTask {
+            task: new_task,
+            response_tx,
+        })
+        .await
+    }
+
+    async fn replace_task(
+        &mut self,
+        new_task: HashTask,
+    ) -> Result<Option<HashTask>, HashThreadError> {
+        self.request(|response_tx| Command::ReplaceTask {
+            task: new_task,
+            response_tx,
+        })
+        .await
+    }
+
+    async fn go_idle(&mut self) -> Result<Option<HashTask>, HashThreadError> {
+        self.request(|response_tx| Command::GoIdle { response_tx })
+            .await
+    }
+
+    fn take_event_receiver(&mut self) -> Option<mpsc::Receiver<HashThreadEvent>> {
+        self.event_rx.take()
+    }
+
+    fn status(&self) -> HashThreadStatus {
+        self.status.read().unwrap().clone()
+    }
+}
+
+/// Why a session ended.
+#[derive(Debug, PartialEq)]
+enum End {
+    /// The miner hung up, or the proxy is shutting down
+    Closed,
+    /// The scheduler let go of the thread
+    Dropped,
+    /// The miner can't follow a change of extranonce1
+    ExtranonceChanged,
+}
+
+/// State of one downstream connection.
+struct Session<T> {
+    transport: T,
+    peer: SocketAddr,
+    status: Arc<RwLock<HashThreadStatus>>,
+
+    /// The thread, until the miner subscribes and it goes to the scheduler
+    thread: Option<ProxyThread>,
+    event_tx: mpsc::Sender<HashThreadEvent>,
+    max_ntime_roll: u32,
+
+    /// Subscribe request waiting for the first task
+    pending_subscribe: Option<u64>,
+    extranonce1: Option<Vec<u8>>,
+    wants_set_extranonce: bool,
+    /// Version bits the miner asked to roll, if it configured rolling
+    requested_mask: Option<u32>,
+    sent_mask: Option<u32>,
+    sent_difficulty: Option<Value>,
+
+    /// Announced jobs, oldest first
+    jobs: VecDeque<(String, DownstreamJob)>,
+    next_job_id: u64,
+}
+
+/// Serve one downstream miner until it hangs up or `shutdown` fires.
+pub async fn run<T: Transport>(
+    transport: T,
+    peer: SocketAddr,
+    thread_tx: mpsc::Sender<Box<dyn HashThread>>,
+    shutdown: CancellationToken,
+) {
+    let (command_tx, mut command_rx) = mpsc::channel(8);
+    let (event_tx, event_rx) = mpsc::channel(8);
+    let status = Arc::new(RwLock::new(HashThreadStatus::default()));
+    let thread = ProxyThread {
+        name: format!("Stratum proxy ({peer})"),
+        command_tx,
+        event_rx: Some(event_rx),
+        status: status.clone(),
+        capabilities: HashThreadCapabilities {
+            // Small devices are what the proxy is for; the scheduler's
+            // estimate takes over once shares come in
+            hashrate_estimate: HashRate::from_terahashes(1.0),
+            // The miner rolls what it rolls; submissions are checked
+            // against the task's mask
+            version_rolling: GeneralPurposeBits::full(),
+            // Miners roll ntime as the clock advances
+            max_ntime_roll: u32::MAX,
+            iterates_extranonce2: true,
+            // Every submission is checked against the share target here
+            reporting_difficulty: None,
+        },
+    };
+
+    let mut session = Session {
+        transport,
+        peer,
+        status,
+        thread: Some(thread),
+        event_tx,
+        max_ntime_roll: 0,
+        pending_subscribe: None,
+        extranonce1: None,
+        wants_set_extranonce: false,
+        requested_mask: None,
+        sent_mask: None,
+        sent_difficulty: None,
+        jobs: VecDeque::new(),
+        next_job_id: 0,
+    };
+
+    let end = loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break End::Closed,
+
+            message = session.transport.read_message() => match message {
+                Ok(Some(JsonRpcMessage::Request { id, method, params })) => {
+                    if let Err(end) = session.handle_request(id, &method, params, &thread_tx).await {
+                        break end;
+                    }
+                }
+                Ok(Some(JsonRpcMessage::Response { .. })) => {}
+                Ok(None) => break End::Closed,
+                Err(StratumError::InvalidMessage(msg)) => {
+                    debug!(peer = %session.peer, error = %msg, "Malformed message from miner");
+                }
+                Err(e) => {
+                    debug!(peer = %session.peer, error = %e, "Downstream connection failed");
+                    break End::Closed;
+                }
+            },
+
+            command = command_rx.recv() => match command {
+                Some(command) => {
+                    if let Err(end) = session.handle_command(command).await {
+                        break end;
+                    }
+                }
+                None => break End::Dropped,
+            },
+        }
+    };
+
+    match end {
+        End::Closed => info!(peer = %session.peer, "Downstream miner disconnected."),
+        End::Dropped => info!(peer = %session.peer, "Scheduler released downstream miner."),
+        End::ExtranonceChanged => warn!(
+            peer = %session.peer,
+            "Pool extranonce1 changed and miner can't be told; disconnecting."
+        ),
+    }
+    let _ = session.event_tx.try_send(HashThreadEvent::GoingOffline);
+}
+
+impl<T: Transport> Session<T> {
+    async fn handle_request(
+        &mut self,
+        id: Option<u64>,
+        method: &str,
+        params: Value,
+        thread_tx: &mpsc::Sender<Box<dyn HashThread>>,
+    ) -> Result<(), End> {
+        let Some(id) = id else {
+            trace!(peer = %self.peer, method, "Ignoring notification from miner");
+            return Ok(());
+        };
+
+        match method {
+            "mining.configure" => {
+                let requested = params
+                    .get(1)
+                    .and_then(|options| options.get("version-rolling.mask"))
+                    .and_then(Value::as_str)
+                    .and_then(|mask| u32::from_str_radix(mask, 16).ok());
+                let mut result = serde_json::Map::new();
+                if let Some(requested) = requested {
+                    let granted = requested & BIP320_VERSION_MASK;
+                    self.requested_mask = Some(granted);
+                    self.sent_mask = Some(granted);
+                    result.insert("version-rolling".into(), json!(true));
+                    result.insert(
+                        "version-rolling.mask".into(),
+                        json!(format!("{granted:08x}")),
+                    );
+                }
+                self.respond(id, Ok(Value::Object(result))).await
+            }
+
+            "mining.subscribe" => {
+                let Some(thread) = self.thread.take() else {
+                    return self
+                        .respond(id, Err(Reject::Invalid("Already subscribed".into())))
+                        .await;
+                };
+                if thread_tx.send(Box::new(thread)).await.is_err() {
+                    return Err(End::Dropped);
+                }
+                info!(peer = %self.peer, "Downstream miner subscribed.");
+                self.pending_subscribe = Some(id);
+                Ok(())
+            }
+
+            // Shares go to the pool under the proxy's own worker
+            "mining.authorize" => self.respond(id, Ok(json!(true))).await,
+
+            "mining.extranonce.subscribe" => {
+                self.wants_set_extranonce = true;
+                self.respond(id, Ok(json!(true))).await
+            }
+
+            "mining.submit" => {
+                let result = self.submit(&params).await;
+                if let Err(reject) = &result {
+                    debug!(peer = %self.peer, ?reject, "Rejected downstream share");
+                }
+                self.respond(id, result.map(|()| json!(true))).await
+            }
+
+            // Difficulty follows the scheduler's share target
+            "mining.suggest_difficulty" => self.respond(id, Ok(json!(true))).await,
+
+            _ => {
+                debug!(peer = %self.peer, method, "Unsupported method from miner");
+                self.respond(id, Err(Reject::Invalid("Unsupported method".into())))
+                    .await
+            }
+        }
+    }
+
+    async fn handle_command(&mut self, command: Command) -> Result<(), End> {
+        match command {
+            Command::Negotiate { max_ntime_roll } => {
+                self.max_ntime_roll = max_ntime_roll;
+                Ok(())
+            }
+            Command::UpdateTask { task, response_tx } => {
+                let result = self.assign(task, false).await;
+                let _ = response_tx.send(result);
+                self.check_extranonce1()
+            }
+            Command::ReplaceTask { task, response_tx } => {
+                let result = self.assign(task, true).await;
+                let _ = response_tx.send(result);
+                self.check_extranonce1()
+            }
+            Command::GoIdle { response_tx } => {
+                let old = self
+                    .jobs
+                    .drain(..)
+                    .next_back()
+                    .map(|(_, job)| job.into_task());
+                self.update_status();
+                let _ = response_tx.send(Ok(old));
+                Ok(())
+            }
+        }
+    }
+
+    /// Announce a task to the miner as a new job.
+    async fn assign(
+        &mut self,
+        task: HashTask,
+        clean: bool,
+    ) -> Result<Option<HashTask>, HashThreadError> {
+        let job = DownstreamJob::new(task)
+            .map_err(|e| HashThreadError::WorkAssignmentFailed(e.to_string()))?;
+
+        let extranonce1 = job.extranonce1().to_vec();
+        if let Some(id) = self.pending_subscribe.take() {
+            let subscription = format!("{:x}", self.next_job_id);
+            let result = json!([
+                [
+                    ["mining.set_difficulty", subscription],
+                    ["mining.notify", subscription],
+                ],
+                hex::encode(&extranonce1),
+                EXTRANONCE2_SIZE,
+            ]);
+            self.write(JsonRpcMessage::Response {
+                id,
+                result: Some(result),
+                error: Some(Value::Null),
+            })
+            .await;
+            self.extranonce1 = Some(extranonce1);
+        } else if self.extranonce1.as_ref() != Some(&extranonce1) && self.wants_set_extranonce {
+            let params = json!([hex::encode(&extranonce1), EXTRANONCE2_SIZE]);
+            self.notify("mining.set_extranonce", params).await;
+            self.extranonce1 = Some(extranonce1);
+        }
+
+        if let Some(requested) = self.requested_mask {
+            let mask = requested & job.version_mask();
+            if self.sent_mask != Some(mask) {
+                self.notify("mining.set_version_mask", json!([format!("{mask:08x}")]))
+                    .await;
+                self.sent_mask = Some(mask);
+            }
+        }
+
+        let difficulty = job.difficulty();
+        if self.sent_difficulty.as_ref() != Some(&difficulty) {
+            self.notify("mining.set_difficulty", difficulty.clone())
+                .await;
+            self.sent_difficulty = Some(difficulty);
+        }
+
+        let job_id = format!("{:x}", self.next_job_id);
+        self.next_job_id += 1;
+        self.notify("mining.notify", job.notify_params(&job_id, clean))
+            .await;
+
+        let old = self.jobs.back().map(|(_, job)| job.task().clone());
+        if clean {
+            self.jobs.clear();
+        }
+        self.jobs.push_back((job_id, job));
+        while self.jobs.len() > RECENT_JOBS {
+            self.jobs.pop_front();
+        }
+        self.update_status();
+        Ok(old)
+    }
+
+    /// Disconnect a miner left with the wrong extranonce1.
+    ///
+    /// Without `mining.set_extranonce` there is no way to tell it; it
+    /// reconnects and subscribes afresh.
+    fn check_extranonce1(&self) -> Result<(), End> {
+        match (&self.extranonce1, self.jobs.back()) {
+            (Some(given), Some((_, job))) if given.as_slice() != job.extranonce1() => {
+                Err(End::ExtranonceChanged)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn submit(&mut self, params: &Value) -> Result<(), Reject> {
+        let submission = Submission::from_params(params)?;
+        let max_ntime_roll = self.max_ntime_roll;
+        let (_, job) = self
+            .jobs
+            .iter_mut()
+            .find(|(id, _)| *id == submission.job_id)
+            .ok_or(Reject::JobNotFound)?;
+
+        let share = match job.check(&submission, max_ntime_roll) {
+            Ok(share) => share,
+            Err(reject) => {
+                if let Reject::Invalid(_) = reject {
+                    self.status.write().unwrap().hardware_errors += 1;
+                }
+                return Err(reject);
+            }
+        };
+
+        let pool_worthy = job.task().template.share_target.is_met_by(share.hash);
+        {
+            let mut status = self.status.write().unwrap();
+            status.chip_shares_found += 1;
+            status.shares_forwarded += 1;
+            if pool_worthy {
+                status.pool_shares_submitted += 1;
+            }
+        }
+        // A closed channel means the task was replaced; the share is stale
+        let _ = job.task().share_tx.send(share).await;
+        Ok(())
+    }
+
+    fn update_status(&self) {
+        let task = self.jobs.back().map(|(_, job)| job.task());
+        let mut status = self.status.write().unwrap();
+        status.is_active = task.is_some();
+        status.share_target = task.map(|t| t.share_target);
+        status.pool_target = task.map(|t| t.template.share_target);
+    }
+
+    async fn respond(&mut self, id: u64, result: Result<Value, Reject>) -> Result<(), End> {
+        let message = match result {
+            Ok(result) => JsonRpcMessage::Response {
+                id,
+                result: Some(result),
+                error: Some(Value::Null),
+            },
+            Err(reject) => JsonRpcMessage::Response {
+                id,
+                result: Some(Value::Null),
+                error: Some(reject.to_error()),
+            },
+        };
+        self.write(message).await;
+        Ok(())
+    }
+
+    async fn notify(&mut self, method: &str, params: Value) {
+        self.write(JsonRpcMessage::notification(method, params))
+            .await;
+    }
+
+    /// Write to the miner; a failed write shows up as a failed read next.
+    async fn write(&mut self, message: JsonRpcMessage) {
+        if let Err(e) = self.transport.write_message(&message).await {
+            debug!(peer = %self.peer, error = %e, "Failed to write to downstream miner");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::proxy::work::tests::{task, winning_submission};
+    use crate::stratum_v1::{MockTransport, MockTransportHandle};
+    use crate::types::Target;
+
+    fn request(id: u64, method: &str, params: Value) -> JsonRpcMessage {
+        JsonRpcMessage::request(id, method, params)
+    }
+
+    /// Result of a response, panicking on anything else.
+    async fn result(miner: &mut MockTransportHandle, expected_id: u64) -> Value {
+        match miner.recv().await {
+            JsonRpcMessage::Response { id, result, .. } if id == expected_id => {
+                result.unwrap_or(Value::Null)
+            }
+            other => panic!("expected response to {expected_id}, got {other:?}"),
+        }
+    }
+
+    async fn notification(miner: &mut MockTransportHandle) -> (String, Value) {
+        match miner.recv().await {
+            JsonRpcMessage::Request {
+                id: None,
+                method,
+                params,
+            } => (method, params),
+            other => panic!("expected notification, got {other:?}"),
+        }
+    }
+
+    /// A session with a subscribed miner, and the thread it registered.
+    async fn subscribed() -> (MockTransportHandle, Box<dyn HashThread>, CancellationToken) {
+        let (transport, mut miner) = MockTransport::pair();
+        let (thread_tx, mut thread_rx) = mpsc::channel(1);
+        let shutdown = CancellationToken::new();
+        tokio::spawn(run(
+            transport,
+            "192.0.2.1:4000".parse().unwrap(),
+            thread_tx,
+            shutdown.clone(),
+        ));
+
+        miner.send(request(
+            1,
+            "mining.configure",
+            json!([["version-rolling"], {"version-rolling.mask": "ffffffff"}]),
+        ));
+        assert_eq!(
+            result(&mut miner, 1).await["version-rolling.mask"],
+            "1fffe000"
+        );
+        miner.send(request(2, "mining.subscribe", json!(["cgminer/4.12"])));
+        let mut thread = thread_rx.recv().await.unwrap();
+        thread
+            .negotiate(AssignmentParameters {
+                max_ntime_roll: 600,
+            })
+            .await
+            .unwrap();
+        (miner, thread, shutdown)
+    }
+
+    #[tokio::test]
+    async fn shares_from_downstream_reach_the_task() {
+        let (mut miner, mut thread, _shutdown) = subscribed().await;
+
+        let en2 = crate::job_source::test_blocks::block_881423::EXTRANONCE2.value();
+        let slice_min = en2 & !0xffff;
+        let mut task = task(slice_min, slice_min + 0xffff, Target::MAX);
+        let (share_tx, mut share_rx) = mpsc::channel(1);
+        task.share_tx = share_tx.into();
+        assert!(thread.replace_task(task).await.unwrap().is_none());
+
+        // Subscribe is answered now that there is an extranonce1
+        let subscription = result(&mut miner, 2).await;
+        assert_eq!(subscription[2], 2);
+        assert_eq!(notification(&mut miner).await.0, "mining.set_difficulty");
+        let (method, params) = notification(&mut miner).await;
+        assert_eq!(method, "mining.notify");
+        assert_eq!(params[8], true);
+
+        let submission = winning_submission();
+        let params = json!([
+            "worker",
+            params[0],
+            hex::encode(&submission.extranonce2),
+            format!("{:08x}", submission.ntime),
+            format!("{:08x}", submission.nonce),
+            format!("{:08x}", submission.version_bits.unwrap()),
+        ]);
+        miner.send(request(3, "mining.submit", params.clone()));
+        assert_eq!(result(&mut miner, 3).await, true);
+        let share = share_rx.recv().await.unwrap();
+        assert_eq!(
+            share.hash,
+            *crate::job_source::test_blocks::block_881423::BLOCK_HASH
+        );
+
+        miner.send(request(4, "mining.submit", params));
+        assert_eq!(result(&mut miner, 4).await, Value::Null);
+        assert_eq!(thread.status().shares_forwarded, 1);
+    }
+
+    #[tokio::test]
+    async fn miner_hanging_up_takes_its_thread_offline() {
+        let (miner, mut thread, _shutdown) = subscribed().await;
+        let mut events = thread.take_event_receiver().unwrap();
+
+        drop(miner);
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap();
+        assert!(matches!(event, Some(HashThreadEvent::GoingOffline)));
+        assert!(thread.go_idle().await.is_err());
+    }
+}
@@ -0,0 +1,416 @@
+//! The scheduler's tasks as Stratum v1 work for one downstream miner.
+//!
+//! The scheduler hands each downstream miner a [`HashTask`]: a job and a
+//! slice of the pool's extranonce2 space. A Stratum v1 miner can't be
+//! told to keep to a slice; it rolls every value of the extranonce2 size
+//! it was given at subscribe. So the proxy gives it a shorter extranonce2,
+//! [`EXTRANONCE2_SIZE`] bytes, and fixes the rest.
+//!
+//! Extranonce2 goes into the coinbase little-endian, so the bytes the
+//! miner rolls come first and the upper bytes follow them. The proxy picks
+//! a block of values within the slice whose upper bytes are all the same
+//! (the prefix) and serves them at the start of the coinbase's second
+//! part. The miner builds exactly the coinbase the pool expects. Its
+//! shares come back with the short extranonce2, and the proxy puts the
+//! prefix back before checking them.
+
+use std::collections::HashSet;
+
+use bitcoin::block::Header as BlockHeader;
+use bitcoin::hashes::Hash;
+use serde_json::{Value, json};
+
+use crate::asic::hash_thread::{HashTask, Share};
+use crate::job_source::{Extranonce2, Extranonce2Range, GeneralPurposeBits, MerkleRootKind};
+use crate::types::Difficulty;
+
+/// Extranonce2 size given to downstream miners, in bytes.
+///
+/// 65,536 coinbases per job outlast any small miner between the pool's
+/// job updates, and leave the slices the scheduler cuts room for a prefix.
+pub const EXTRANONCE2_SIZE: u8 = 2;
+
+/// Why a task can't be served to a downstream miner.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum WorkError {
+    #[error("header-only jobs can't be served over Stratum v1")]
+    HeaderOnly,
+
+    #[error("extranonce2 slice {min:#x}..={max:#x} has no room for a downstream extranonce2")]
+    SliceTooSmall { min: u64, max: u64 },
+}
+
+/// Why a downstream share was turned away.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reject {
+    /// Malformed, or outside what the job allows
+    Invalid(String),
+    /// Not a job the miner has, or one since replaced
+    JobNotFound,
+    /// Submitted before
+    Duplicate,
+    /// Doesn't meet the difficulty the miner was given
+    LowDifficulty,
+}
+
+impl Reject {
+    /// The `error` member of the response, in the usual Stratum shape.
+    pub fn to_error(&self) -> Value {
+        let (code, message) = match self {
+            Reject::Invalid(reason) => (20, reason.as_str()),
+            Reject::JobNotFound => (21, "Job not found"),
+            Reject::Duplicate => (22, "Duplicate share"),
+            Reject::LowDifficulty => (23, "Low difficulty share"),
+        };
+        json!([code, message, null])
+    }
+}
+
+/// A share as a downstream miner submits it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Submission {
+    pub job_id: String,
+    pub extranonce2: Vec<u8>,
+    pub ntime: u32,
+    pub nonce: u32,
+    pub version_bits: Option<u32>,
+}
+
+impl Submission {
+    /// Parse `mining.submit` params: worker, job, extranonce2, ntime,
+    /// nonce and, with version rolling, the rolled version bits.
+    pub fn from_params(params: &Value) -> Result<Self, Reject> {
+        let invalid = |what: &str| Reject::Invalid(format!("Invalid {what}"));
+        let field = |i: usize| params.get(i).and_then(Value::as_str);
+        let word = |i: usize, what: &str| {
+            field(i)
+                .and_then(|s| u32::from_str_radix(s, 16).ok())
+                .ok_or_else(|| invalid(what))
+        };
+
+        Ok(Self {
+            job_id: field(1).ok_or_else(|| invalid("job ID"))?.to_string(),
+            extranonce2: field(2)
+                .and_then(|s| hex::decode(s).ok())
+                .ok_or_else(|| invalid("extranonce2"))?,
+            ntime: word(3, "ntime")?,
+            nonce: word(4, "nonce")?,
+            version_bits: match field(5) {
+                Some(_) => Some(word(5, "version bits")?),
+                None => None,
+            },
+        })
+    }
+}
+
+/// A task as served to one downstream miner.
+pub struct DownstreamJob {
+    task: HashTask,
+    /// Upper bytes of every extranonce2 the miner rolls
+    prefix: u64,
+    /// Full size of the pool's extranonce2
+    en2_size: u8,
+    /// Shares already accepted, as (extranonce2, ntime, nonce, version)
+    seen: HashSet<(u64, u32, u32, i32)>,
+}
+
+impl DownstreamJob {
+    pub fn new(task: HashTask) -> Result<Self, WorkError> {
+        let range = match (&task.template.merkle_root, &task.en2_range) {
+            (MerkleRootKind::Computed(_), Some(range)) => range,
+            _ => return Err(WorkError::HeaderOnly),
+        };
+        let prefix = carve(range).ok_or(WorkError::SliceTooSmall {
+            min: range.min,
+            max: range.max,
+        })?;
+        Ok(Self {
+            prefix,
+            en2_size: range.size,
+            task,
+            seen: HashSet::new(),
+        })
+    }
+
+    pub fn task(&self) -> &HashTask {
+        &self.task
+    }
+
+    pub fn into_task(self) -> HashTask {
+        self.task
+    }
+
+    /// The pool's extranonce1, which the miner must be subscribed with.
+    pub fn extranonce1(&self) -> &[u8] {
+        match &self.task.template.merkle_root {
+            MerkleRootKind::Computed(merkle) => &merkle.extranonce1,
+            MerkleRootKind::Fixed(_) => unreachable!("checked in new()"),
+        }
+    }
+
+    /// Version bits the pool lets the miner roll, as a Stratum mask.
+    pub fn version_mask(&self) -> u32 {
+        self.task.template.version.gp_bits_mask().to_version_mask()
+    }
+
+    /// `mining.set_difficulty` params for the task's share target.
+    ///
+    /// Rounded up, so every share the miner finds meets the target.
+    pub fn difficulty(&self) -> Value {
+        let difficulty = Difficulty::from_target(self.task.share_target).as_f64();
+        if difficulty >= 1.0 {
+            json!([difficulty.ceil() as u64])
+        } else {
+            json!([difficulty])
+        }
+    }
+
+    /// `mining.notify` params, announcing the job as `job_id`.
+    pub fn notify_params(&self, job_id: &str, clean_jobs: bool) -> Value {
+        let template = &self.task.template;
+        let MerkleRootKind::Computed(merkle) = &template.merkle_root else {
+            unreachable!("checked in new()");
+        };
+
+        let prefix_len = (self.en2_size - EXTRANONCE2_SIZE) as usize;
+        let mut coinbase2 = self.prefix.to_le_bytes()[..prefix_len].to_vec();
+        coinbase2.extend_from_slice(&merkle.coinbase2);
+
+        // Stratum sends the previous block hash word-swapped, the reverse
+        // of parse_block_hash() in the client
+        let mut prev_hash = template.prev_blockhash.to_byte_array();
+        for word in prev_hash.chunks_mut(4) {
+            word.reverse();
+        }
+
+        let branches: Vec<String> = merkle
+            .merkle_branches
+            .iter()
+            .map(|branch| hex::encode(branch.to_byte_array()))
+            .collect();
+
+        json!([
+            job_id,
+            hex::encode(prev_hash),
+            hex::encode(&merkle.coinbase1),
+            hex::encode(coinbase2),
+            branches,
+            format!("{:08x}", template.version.base().to_consensus() as u32),
+            format!("{:08x}", template.bits.to_consensus()),
+            format!("{:08x}", self.task.ntime),
+            clean_jobs,
+        ])
+    }
+
+    /// Rebuild the header a submission describes and check it.
+    ///
+    /// Returns the share to hand the scheduler if it meets the task's
+    /// share target. `max_ntime_roll` is how far past the task's ntime
+    /// the miner may have rolled.
+    pub fn check(&mut self, submission: &Submission, max_ntime_roll: u32) -> Result<Share, Reject> {
+        let template = &self.task.template;
+
+        let rolled: [u8; EXTRANONCE2_SIZE as usize] = submission
+            .extranonce2
+            .as_slice()
+            .try_into()
+            .map_err(|_| Reject::Invalid("Wrong extranonce2 size".into()))?;
+        let value =
+            (self.prefix << (8 * EXTRANONCE2_SIZE as u32)) | u64::from(u16::from_le_bytes(rolled));
+        let extranonce2 =
+            Extranonce2::new(value, self.en2_size).map_err(|e| Reject::Invalid(e.to_string()))?;
+
+        let version = match submission.version_bits {
+            None => template.version.base(),
+            Some(bits) if bits & !self.version_mask() != 0 => {
+                return Err(Reject::Invalid("Version bits outside mask".into()));
+            }
+            Some(bits) => template
+                .version
+                .apply_gp_bits(&GeneralPurposeBits::from(&bits.to_be_bytes()))
+                .map_err(|e| Reject::Invalid(e.to_string()))?,
+        };
+
+        let rolled_by = submission.ntime.wrapping_sub(self.task.ntime);
+        if submission.ntime < self.task.ntime || rolled_by > max_ntime_roll {
+            return Err(Reject::Invalid("ntime out of range".into()));
+        }
+
+        let merkle_root = template
+            .compute_merkle_root(&extranonce2)
+            .map_err(|e| Reject::Invalid(e.to_string()))?;
+        let hash = BlockHeader {
+            version,
+            prev_blockhash: template.prev_blockhash,
+            merkle_root,
+            time: submission.ntime,
+            bits: template.bits,
+            nonce: submission.nonce,
+        }
+        .block_hash();
+
+        if !self.task.share_target.is_met_by(hash) {
+            return Err(Reject::LowDifficulty);
+        }
+        let key = (
+            value,
+            submission.ntime,
+            submission.nonce,
+            version.to_consensus(),
+        );
+        if !self.seen.insert(key) {
+            return Err(Reject::Duplicate);
+        }
+
+        Ok(Share {
+            nonce: submission.nonce,
+            hash,
+            version,
+            ntime: submission.ntime,
+            extranonce2: Some(extranonce2),
+            expected_work: self.task.share_target.to_work(),
+        })
+    }
+}
+
+/// Find a prefix whose every downstream extranonce2 falls within `range`.
+fn carve(range: &Extranonce2Range) -> Option<u64> {
+    if range.size <= EXTRANONCE2_SIZE {
+        return None;
+    }
+    let block = 1u64 << (8 * EXTRANONCE2_SIZE as u32);
+    let prefix = range.min.div_ceil(block);
+    let last = prefix.checked_mul(block)?.checked_add(block - 1)?;
+    (last <= range.max).then_some(prefix)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Arc;
+
+    use bitcoin::pow::Target;
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::job_source::test_blocks::block_881423;
+    use crate::job_source::{JobTemplate, MerkleRootTemplate, VersionTemplate};
+
+    /// Block 881,423's job, with the given slice of extranonce2 space.
+    pub(crate) fn task(min: u64, max: u64, share_target: Target) -> HashTask {
+        let version = block_881423::VERSION.to_consensus() as u32 & !0x1fff_e000;
+        let template = JobTemplate {
+            id: "881423".into(),
+            prev_blockhash: *block_881423::PREV_BLOCKHASH,
+            version: VersionTemplate::new(
+                bitcoin::block::Version::from_consensus(version as i32),
+                GeneralPurposeBits::full(),
+            )
+            .unwrap(),
+            bits: *block_881423::BITS,
+            share_target,
+            time: block_881423::TIME,
+            merkle_root: MerkleRootKind::Computed(Box::new(MerkleRootTemplate::new(
+                block_881423::coinbase1_bytes().to_vec(),
+                block_881423::extranonce1_bytes().to_vec(),
+                Extranonce2Range::new(4).unwrap(),
+                block_881423::coinbase2_bytes().to_vec(),
+                block_881423::MERKLE_BRANCHES.clone(),
+            ))),
+        };
+        let (share_tx, _) = mpsc::channel(1);
+        HashTask {
+            template: Arc::new(template),
+            en2_range: Some(Extranonce2Range::new_range(min, max, 4).unwrap()),
+            en2: None,
+            share_target,
+            ntime: block_881423::TIME,
+            share_tx: share_tx.into(),
+        }
+    }
+
+    /// The block's own share, as a miner given the block's prefix finds it.
+    pub(crate) fn winning_submission() -> Submission {
+        Submission {
+            job_id: "1".into(),
+            extranonce2: block_881423::extranonce2_bytes()[..2].to_vec(),
+            ntime: block_881423::TIME,
+            nonce: block_881423::NONCE,
+            version_bits: Some(block_881423::VERSION.to_consensus() as u32 & 0x1fff_e000),
+        }
+    }
+
+    #[test]
+    fn prefixes_are_carved_from_within_the_slice() {
+        let range = |min, max| Extranonce2Range::new_range(min, max, 4).unwrap();
+        assert_eq!(carve(&range(0, 0xffff)), Some(0));
+        assert_eq!(carve(&range(1, 0x1ffff)), Some(1));
+        assert_eq!(carve(&range(1, 0x1fffe)), None);
+        assert_eq!(carve(&Extranonce2Range::new(2).unwrap()), None);
+    }
+
+    #[test]
+    fn the_block_rebuilt_from_downstream_work() {
+        let en2 = block_881423::EXTRANONCE2.value();
+        let slice_min = en2 & !0xffff;
+        let mut job = DownstreamJob::new(task(slice_min, slice_min + 0x1ffff, Target::MAX))
+            .expect("slice has room");
+
+        // The miner's coinbase is the block's: the prefix opens coinbase2
+        let params = job.notify_params("1", true);
+        let coinbase2 = hex::decode(params[3].as_str().unwrap()).unwrap();
+        assert_eq!(coinbase2[..2], block_881423::extranonce2_bytes()[2..]);
+        assert_eq!(coinbase2[2..], *block_881423::coinbase2_bytes());
+        assert_eq!(params[5], "20000000");
+        assert_eq!(job.extranonce1(), block_881423::extranonce1_bytes());
+
+        let share = job.check(&winning_submission(), 0).unwrap();
+        assert_eq!(share.hash, *block_881423::BLOCK_HASH);
+        assert_eq!(share.extranonce2, Some(*block_881423::EXTRANONCE2));
+        assert_eq!(share.version, *block_881423::VERSION);
+
+        assert_eq!(
+            job.check(&winning_submission(), 0).unwrap_err(),
+            Reject::Duplicate
+        );
+    }
+
+    #[test]
+    fn bad_shares_are_turned_away() {
+        let en2 = block_881423::EXTRANONCE2.value();
+        let slice_min = en2 & !0xffff;
+        let hard = Difficulty::from(1u64 << 60).to_target();
+        let mut job = DownstreamJob::new(task(slice_min, slice_min + 0xffff, hard)).unwrap();
+
+        let mut submission = winning_submission();
+        submission.nonce ^= 1;
+        assert_eq!(
+            job.check(&submission, 0).unwrap_err(),
+            Reject::LowDifficulty
+        );
+
+        let mut submission = winning_submission();
+        submission.ntime += 1;
+        assert!(matches!(job.check(&submission, 0), Err(Reject::Invalid(_))));
+
+        let mut submission = winning_submission();
+        submission.extranonce2.push(0);
+        assert!(matches!(job.check(&submission, 0), Err(Reject::Invalid(_))));
+
+        let mut submission = winning_submission();
+        submission.version_bits = Some(0x8000_0000);
+        assert!(matches!(job.check(&submission, 0), Err(Reject::Invalid(_))));
+    }
+
+    #[test]
+    fn submissions_parse_from_stratum_params() {
+        let params = json!(["worker", "1f", "220c", "679ac169", "ff05fb02", "0e596000"]);
+        let submission = Submission::from_params(&params).unwrap();
+        assert_eq!(submission.job_id, "1f");
+        assert_eq!(submission.extranonce2, [0x22, 0x0c]);
+        assert_eq!(submission.nonce, 0xff05fb02);
+        assert_eq!(submission.version_bits, Some(0x0e596000));
+
+        let params = json!(["worker", "1f", "220c", "679ac169"]);
+        assert!(Submission::from_params(&params).is_err());
+    }
+}
@@ -30,28 +30,54 @@
 //! This is a work-in-progress. It's currently the main and initial place where
 //! functionality is added, after which the functionality is refactored out to
 //! where it belongs.
+//!
+//! Scheduling decisions can be recorded for later replay; see
+//! [`decision_log`].
+
+pub mod decision_log;
+mod en2_sizing;
+mod job_book;
+mod job_ledger;
+mod share_histogram;
 
 use slotmap::SlotMap;
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
 
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::{StreamExt, StreamMap};
 use tokio_util::sync::CancellationToken;
 
 use crate::api::commands::SchedulerCommand;
-use crate::api_client::types::{MinerState, SourceState};
-use crate::asic::hash_thread::{HashTask, HashThread, HashThreadEvent, Share};
+use crate::api_client::types::{
+    Extranonce2SpareState, FilteringIssue, MinerState, NtimeGuardState, OfflineQueueState,
+    PauseLevel, RemediationState, ShareFiltering, SoloStats, SourceHealthState, SourceState,
+    TaskAssignment, ThreadScheduling,
+};
+use crate::asic::hash_thread::{
+    AssignmentParameters, ChannelPressure, HashTask, HashThread, HashThreadCapabilities,
+    HashThreadEvent, HashThreadStatus, Share, ShareSender,
+};
+use crate::events::{EventBus, PoolEvent, PoolEventKind, Pools, ShareEvent, Shares};
 use crate::job_source::{
-    JobTemplate, MerkleRootKind, Share as SourceShare, SourceCommand, SourceEvent,
+    Extranonce2, Extranonce2Allocator, GeneralPurposeBits, JobTemplate, MerkleRootKind,
+    Share as SourceShare, SourceCommand, SourceEvent, SourceHealth,
 };
+use crate::share_audit::ShareAudit;
+use crate::share_proof::{self, ShareProof};
 use crate::tracing::prelude::*;
 use crate::types::{
-    AlarmStatus, DebouncedAlarm, Difficulty, HashRate, HashrateEstimator, ShareRate, Target,
-    expected_time_to_share_from_target, target_for_share_rate,
+    AlarmStatus, BlockLuck, DebouncedAlarm, Difficulty, HashRate, HashrateEstimator, ShareRate,
+    Target, expected_time_to_share_from_target, target_for_share_rate,
 };
+use decision_log::{Decision, DecisionLog, PreemptReason, SourceScore};
+use job_book::{JobBook, skip_rounds};
+use job_ledger::JobLedger;
+use share_histogram::ShareHistogram;
 
 /// Unique identifier for a job source, assigned by the scheduler.
 type SourceId = slotmap::DefaultKey;
@@ -92,6 +118,19 @@ const MEASUREMENT_SHARE_RATE: ShareRate = ShareRate::from_interval(Duration::fro
 /// giving vardiff a clear signal to converge quickly.
 const FLOOD_CAP_RATE: ShareRate = ShareRate::from_interval(Duration::from_millis(100));
 
+/// Furthest any thread may roll ntime past a task's value, in seconds.
+///
+/// Sources don't say how far ahead they accept timestamps; ten minutes
+/// is well within what pools tolerate. Threads that can't roll as far
+/// keep their own limit.
+pub(crate) const NTIME_ROLL_LIMIT: u32 = 600;
+
+/// Health score lead a standby source needs before the scheduler fails
+/// over to it.
+///
+/// Keeps near-equal sources from trading places on every evaluation.
+const FAILOVER_MARGIN: u8 = 15;
+
 /// Scheduler-side bookkeeping for an active task.
 ///
 /// Each HashTask sent to a thread has a corresponding TaskEntry in the
@@ -107,6 +146,27 @@ struct TaskEntry {
 
     /// Thread this task was assigned to
     thread_id: ThreadId,
+
+    /// Job account in the ledger that this task's shares count towards
+    ledger_id: u64,
+
+    /// Highest extranonce2 value the task is known to have reached
+    en2_reached: Option<u64>,
+}
+
+impl TaskEntry {
+    /// Note a share's extranonce2, returning how many values past the
+    /// highest seen so far it shows were mined.
+    fn reach(&mut self, en2: Option<Extranonce2>) -> u64 {
+        match (en2.map(|en2| en2.value()), &mut self.en2_reached) {
+            (Some(value), Some(reached)) if value > *reached => {
+                let mined = value - *reached;
+                *reached = value;
+                mined
+            }
+            _ => 0,
+        }
+    }
 }
 
 /// Registration message for adding a job source to the scheduler.
@@ -126,6 +186,32 @@ pub struct SourceRegistration {
 
     /// Command sender for this source (SubmitShare, etc.)
     pub command_tx: mpsc::Sender<SourceCommand>,
+
+    /// How the source's jobs share the threads with other sources.
+    pub policy: SourcePolicy,
+}
+
+/// How a source's jobs are spread over the threads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SourcePolicy {
+    /// Competes with the other shared sources for every thread not
+    /// pinned elsewhere; the healthiest one with work is mined and the
+    /// rest stand by.
+    #[default]
+    Shared,
+
+    /// Mined alongside the shared sources on up to `threads` threads of
+    /// its own, such as a solo pool taking a lottery slice of the rig.
+    ///
+    /// At least one thread always stays with the shared sources. Pinned
+    /// threads sit idle while their source has no job.
+    Pinned { threads: usize },
+
+    /// Mined on the shared threads only while no shared source has work,
+    /// to keep the chips warm through an outage rather than letting them
+    /// cool and heat again. Its shares earn nothing and are neither
+    /// submitted nor counted; real work preempts it as soon as it arrives.
+    Backfill,
 }
 
 /// Internal scheduler tracking for a registered source.
@@ -145,6 +231,34 @@ struct SourceEntry {
 
     /// Debounced alarm for high-difficulty warnings.
     difficulty_alarm: DebouncedAlarm,
+
+    /// Observed connection and share behavior, for failover ranking.
+    health: SourceHealth,
+
+    /// Latest reject rate remediation state reported by the source.
+    remediation: RemediationState,
+
+    /// Latest ntime window counts reported by the source.
+    ntime_guard: NtimeGuardState,
+
+    /// Jobs the source coalesced away in bursts, as last reported.
+    coalesced_jobs: u64,
+
+    /// Latest offline share queue counts reported by the source.
+    offline_queue: OfflineQueueState,
+
+    /// Difficulties of the shares found on this source's jobs.
+    share_difficulties: ShareHistogram,
+
+    /// Whether the source competes for the shared threads or has its own.
+    policy: SourcePolicy,
+
+    /// Extranonce2 handed out this pool session, kept across reconnects.
+    job_book: JobBook,
+
+    /// Extranonce2 of the last job left over after sizing its threads'
+    /// slices, reserved in chunks by threads that join while it runs.
+    en2_spare: Option<Extranonce2Allocator>,
 }
 
 /// Whether to update alongside existing work or replace it.
@@ -160,6 +274,223 @@ enum AssignMode {
 struct ThreadEntry {
     thread: Box<dyn HashThread>,
     hashrate: HashrateEstimator,
+
+    /// Work distribution history, published via the API.
+    telemetry: ThreadTelemetry,
+
+    /// Debounced alarm for measured hashrate falling short of expected.
+    degraded_alarm: DebouncedAlarm,
+
+    /// Pinned source this thread mines, or None for the active shared
+    /// source.
+    pinned_to: Option<SourceId>,
+
+    /// Waits on this thread's share channels, shared by all its tasks.
+    share_pressure: Arc<ChannelPressure>,
+
+    /// Share waits seen at the last saturation check.
+    share_waits_seen: u64,
+
+    /// Debounced alarm for shares waiting on full channels.
+    saturated_alarm: DebouncedAlarm,
+}
+
+/// Number of recent assignments kept per thread for telemetry.
+const ASSIGNMENT_HISTORY_LEN: usize = 16;
+
+/// A task assignment, as remembered for telemetry.
+#[derive(Debug)]
+struct AssignmentRecord {
+    source: String,
+    job_id: String,
+    at: Instant,
+    en2_start: Option<u64>,
+    en2_len: Option<u64>,
+    version_bits_rolled: u32,
+}
+
+impl AssignmentRecord {
+    fn new(source: &str, task: &HashTask, version_rolling: GeneralPurposeBits) -> Self {
+        Self {
+            source: source.to_string(),
+            job_id: task.template.id.clone(),
+            at: Instant::now(),
+            en2_start: task.en2_range.as_ref().map(|r| r.min),
+            en2_len: task.en2_range.as_ref().map(|r| r.len()),
+            version_bits_rolled: task
+                .template
+                .version
+                .rolling_mask_for(version_rolling)
+                .count_ones(),
+        }
+    }
+}
+
+/// Per-thread work distribution counters.
+///
+/// On multi-board rigs these show whether work is spread fairly: how
+/// often each thread gets new work, how long it sits without any, and
+/// how much of the submitted shares it contributes.
+#[derive(Debug)]
+struct ThreadTelemetry {
+    registered: Instant,
+    tasks_assigned: u64,
+    recent: VecDeque<AssignmentRecord>,
+
+    /// Start of the current idle period, if the thread has no work.
+    idle_since: Option<Instant>,
+
+    /// Accumulated idle time from completed idle periods.
+    idle_total: Duration,
+
+    shares_found: u64,
+    shares_submitted: u64,
+
+    /// Difficulties of the shares this thread found.
+    share_difficulties: ShareHistogram,
+
+    /// Per-chip hashrate from the thread's latest status update.
+    chip_hashrates: Vec<HashRate>,
+}
+
+impl ThreadTelemetry {
+    fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            registered: now,
+            tasks_assigned: 0,
+            recent: VecDeque::with_capacity(ASSIGNMENT_HISTORY_LEN),
+            idle_since: Some(now),
+            idle_total: Duration::ZERO,
+            shares_found: 0,
+            shares_submitted: 0,
+            share_difficulties: ShareHistogram::new(),
+            chip_hashrates: Vec::new(),
+        }
+    }
+
+    fn record_assignment(&mut self, record: AssignmentRecord) {
+        self.tasks_assigned += 1;
+        if self.recent.len() == ASSIGNMENT_HISTORY_LEN {
+            self.recent.pop_back();
+        }
+        self.recent.push_front(record);
+        self.set_idle(false);
+    }
+
+    fn set_idle(&mut self, idle: bool) {
+        match (idle, self.idle_since) {
+            (true, None) => self.idle_since = Some(Instant::now()),
+            (false, Some(since)) => {
+                self.idle_total += since.elapsed();
+                self.idle_since = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn idle_percent(&self) -> u8 {
+        let lifetime = self.registered.elapsed();
+        if lifetime.is_zero() {
+            return 0;
+        }
+        let idle = self.idle_total + self.idle_since.map_or(Duration::ZERO, |s| s.elapsed());
+        percent(idle.as_secs_f64(), lifetime.as_secs_f64())
+    }
+
+    fn snapshot(
+        &self,
+        name: &str,
+        total_submitted: u64,
+        share_pressure: &ChannelPressure,
+        thread_status: &HashThreadStatus,
+    ) -> ThreadScheduling {
+        ThreadScheduling {
+            name: name.to_string(),
+            registered_secs: self.registered.elapsed().as_secs(),
+            tasks_assigned: self.tasks_assigned,
+            idle_percent: self.idle_percent(),
+            shares_found: self.shares_found,
+            shares_submitted: self.shares_submitted,
+            share_percent: percent(self.shares_submitted as f64, total_submitted as f64),
+            chip_hashrates: self.chip_hashrates.iter().map(|&h| u64::from(h)).collect(),
+            shares_delayed: share_pressure.waits(),
+            share_wait_ms: share_pressure.wait_time().as_millis() as u64,
+            status_updates_coalesced: thread_status.status_updates_coalesced,
+            duplicate_nonces: thread_status.duplicate_nonces,
+            frequency_mismatches: thread_status.frequency_mismatches,
+            share_difficulties: self.share_difficulties.snapshot(),
+            filtering: share_filtering(thread_status),
+            recent_assignments: self
+                .recent
+                .iter()
+                .map(|r| TaskAssignment {
+                    source: r.source.clone(),
+                    job_id: r.job_id.clone(),
+                    age_secs: r.at.elapsed().as_secs(),
+                    en2_start: r.en2_start,
+                    en2_len: r.en2_len,
+                    version_bits_rolled: r.version_bits_rolled,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Build the API view of a source's health.
+fn source_health_state(health: &mut SourceHealth, now: Instant) -> SourceHealthState {
+    SourceHealthState {
+        score: health.score(now),
+        connected: health.is_connected(),
+        uptime_percent: percent(health.uptime_ratio(now), 1.0),
+        recent_disconnects: health.recent_disconnects(now),
+        reject_percent: percent(health.reject_ratio(), 1.0),
+        notify_jitter_percent: percent(health.notify_jitter(), 1.0),
+        latency_ms: health.latency().map(|d| d.as_millis() as u64),
+    }
+}
+
+/// Build the API view of a thread's share filtering stages, flagging
+/// any out of order.
+fn share_filtering(status: &HashThreadStatus) -> ShareFiltering {
+    let share = status.share_target.map(Difficulty::from_target);
+    let pool = status.pool_target.map(Difficulty::from_target);
+    let harder =
+        |a: Option<Difficulty>, b: Option<Difficulty>| a.zip(b).is_some_and(|(a, b)| a > b);
+
+    let mut issues = Vec::new();
+    if harder(status.chip_difficulty, pool) {
+        issues.push(FilteringIssue::ChipHarderThanPool);
+    }
+    if harder(status.chip_difficulty, share) {
+        issues.push(FilteringIssue::ChipHarderThanShareTarget);
+    }
+    if harder(share, pool) {
+        issues.push(FilteringIssue::ShareTargetHarderThanPool);
+    }
+    if status.shares_forwarded > status.chip_shares_found
+        || status.pool_shares_submitted > status.shares_forwarded
+    {
+        issues.push(FilteringIssue::CountsOutOfOrder);
+    }
+
+    ShareFiltering {
+        chip_difficulty: status.chip_difficulty.map(Difficulty::as_u64),
+        share_difficulty: share.map(Difficulty::as_u64),
+        pool_difficulty: pool.map(Difficulty::as_u64),
+        chip_shares_found: status.chip_shares_found,
+        shares_forwarded: status.shares_forwarded,
+        pool_shares_submitted: status.pool_shares_submitted,
+        issues,
+    }
+}
+
+/// Express `part` as an integer percentage of `whole`, clamped to 0--100.
+fn percent(part: f64, whole: f64) -> u8 {
+    if whole <= 0.0 {
+        return 0;
+    }
+    (part / whole * 100.0).round().clamp(0.0, 100.0) as u8
 }
 
 /// Core scheduler state.
@@ -183,8 +514,26 @@ struct Scheduler {
     /// Track thread count for disconnect detection
     last_thread_count: usize,
 
-    /// Mining paused
-    paused: bool,
+    /// How deeply mining is paused, if it is
+    pause: Option<PauseLevel>,
+
+    /// Source whose jobs are being mined; others are on standby.
+    active_source: Option<SourceId>,
+
+    /// Shared source the operator wants mined whenever it has work.
+    preferred_source: Option<SourceId>,
+
+    /// Where decisions are recorded for replay, if anywhere.
+    decision_log: DecisionLog,
+
+    /// Where share trips are stamped, if anywhere.
+    share_audit: ShareAudit,
+
+    /// Shares and extranonce2 accounted per recent job.
+    job_ledger: JobLedger,
+
+    /// Where shares and pool events are published.
+    events: EventBus,
 }
 
 impl Scheduler {
@@ -195,10 +544,46 @@ impl Scheduler {
             tasks: SlotMap::new(),
             stats: MiningStats::default(),
             last_thread_count: 0,
-            paused: false,
+            pause: None,
+            active_source: None,
+            preferred_source: None,
+            decision_log: DecisionLog::disabled(),
+            share_audit: ShareAudit::disabled(),
+            job_ledger: JobLedger::default(),
+            events: EventBus::new(),
         }
     }
 
+    fn with_decision_log(mut self, decision_log: DecisionLog) -> Self {
+        self.decision_log = decision_log;
+        self
+    }
+
+    fn with_share_audit(mut self, share_audit: ShareAudit) -> Self {
+        self.share_audit = share_audit;
+        self
+    }
+
+    fn with_event_bus(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Publish a pool event for `source_id`.
+    fn publish_pool_event(&self, source_id: SourceId, kind: PoolEventKind) {
+        self.events.publish::<Pools>(PoolEvent {
+            source: self.source_name(source_id),
+            kind,
+        });
+    }
+
+    fn source_name(&self, source_id: SourceId) -> String {
+        self.sources
+            .get(source_id)
+            .map(|s| s.name.clone())
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
     /// Aggregate measured hashrate from per-thread estimators.
     ///
     /// Returns the truth: zero if no shares have been recorded yet.
@@ -209,15 +594,17 @@ impl Scheduler {
             .sum()
     }
 
-    /// Aggregate hashrate for operational decisions.
+    /// Aggregate hashrate for operational decisions, over the threads
+    /// pinned to `pin`, or the shared threads for `None`.
     ///
     /// Per thread, uses measured hashrate if the estimator has settled,
     /// otherwise falls back to the static capability estimate. Suitable
     /// for broadcasting to sources and difficulty warnings, where a zero
     /// value at startup would be unhelpful.
-    fn operational_hashrate(&mut self) -> HashRate {
+    fn operational_hashrate(&mut self, pin: Option<SourceId>) -> HashRate {
         self.threads
             .values_mut()
+            .filter(|entry| entry.pinned_to == pin)
             .map(|entry| {
                 entry
                     .hashrate
@@ -227,30 +614,199 @@ impl Scheduler {
             .sum()
     }
 
+    /// Extranonce2 values `thread_id` needs for a job expected to last
+    /// `lifetime`, at its operational hashrate.
+    fn en2_needed(&mut self, thread_id: ThreadId, job: &JobTemplate, lifetime: Duration) -> u64 {
+        let entry = &mut self.threads[thread_id];
+        let hashrate = entry
+            .hashrate
+            .settled_hashrate()
+            .unwrap_or(entry.thread.capabilities().hashrate_estimate);
+        let version_bits = en2_sizing::rolled_version_bits(
+            job.version.gp_bits_mask(),
+            entry.thread.capabilities().version_rolling,
+        );
+        en2_sizing::en2_needed(hashrate, version_bits, lifetime)
+    }
+
+    /// Threads key for a source: itself if pinned, `None` if shared.
+    fn pin_of(&self, source_id: SourceId) -> Option<SourceId> {
+        match self.sources.get(source_id)?.policy {
+            SourcePolicy::Pinned { .. } => Some(source_id),
+            SourcePolicy::Shared | SourcePolicy::Backfill => None,
+        }
+    }
+
+    /// Whether `source_id` is a backfill source.
+    fn is_backfill(&self, source_id: SourceId) -> bool {
+        self.sources
+            .get(source_id)
+            .is_some_and(|s| s.policy == SourcePolicy::Backfill)
+    }
+
+    /// Compare each thread's measured hashrate against its expected rate.
+    ///
+    /// The expected rate is the thread's capability estimate, derived from
+    /// chip model and clock frequency. A thread that stays well below it
+    /// usually has dead cores, a bad clock, or is throttling, none of
+    /// which the thread itself can see. Warns once per episode.
+    fn check_hashrate_sanity(&mut self) {
+        if self.pause.is_some() {
+            return;
+        }
+
+        for entry in self.threads.values_mut() {
+            let expected = entry.thread.capabilities().hashrate_estimate;
+            let measured = entry.hashrate.settled_hashrate();
+            let degraded =
+                entry.thread.status().is_active && is_hashrate_degraded(measured, expected);
+
+            match entry.degraded_alarm.check(degraded) {
+                AlarmStatus::Triggered => {
+                    let measured = measured.unwrap_or_default();
+                    warn!(
+                        thread = %entry.thread.name(),
+                        measured = %measured.to_human_readable(),
+                        expected = %expected.to_human_readable(),
+                        ratio = %format!("{:.2}", hashrate_ratio(measured, expected)),
+                        "Measured hashrate well below expected for frequency"
+                    );
+                }
+                AlarmStatus::Resolved => {
+                    info!(
+                        thread = %entry.thread.name(),
+                        "Measured hashrate back within expected range"
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Warn about threads whose shares keep waiting on full channels.
+    ///
+    /// Shares are never dropped, so a thread reporting more than the
+    /// scheduler can take just falls behind, usually because its chip
+    /// target is set too low. Warns once per episode.
+    fn check_channel_saturation(&mut self) {
+        for entry in self.threads.values_mut() {
+            let waits = entry.share_pressure.waits();
+            let saturated = waits > entry.share_waits_seen;
+            entry.share_waits_seen = waits;
+
+            match entry.saturated_alarm.check(saturated) {
+                AlarmStatus::Triggered => {
+                    warn!(
+                        thread = %entry.thread.name(),
+                        shares_delayed = waits,
+                        waited_ms = entry.share_pressure.wait_time().as_millis() as u64,
+                        "Shares waiting on a full channel; chip target may be too low"
+                    );
+                }
+                AlarmStatus::Resolved => {
+                    info!(
+                        thread = %entry.thread.name(),
+                        "Share channel no longer saturated"
+                    );
+                }
+                _ => {}
+            }
+        }
+    }
+
     /// Build a [`MinerState`] snapshot from current scheduler state.
     ///
     /// The scheduler contributes aggregate stats and source info. Board
     /// and thread details come from the backplane, not the scheduler, so
     /// `boards` is left empty here.
     fn compute_miner_state(&mut self) -> MinerState {
+        let now = Instant::now();
+        let active_source = self.active_source;
+        let preferred_source = self.preferred_source;
+
+        // Measured hashrate and thread count behind each pin
+        let mut pins: HashMap<Option<SourceId>, (HashRate, u32)> = HashMap::new();
+        for entry in self.threads.values_mut() {
+            let (hashrate, threads) = pins.entry(entry.pinned_to).or_default();
+            *hashrate += entry.hashrate.hashrate();
+            *threads += 1;
+        }
+
         MinerState {
             uptime_secs: self.stats.start_time.elapsed().as_secs(),
             hashrate: u64::from(self.measured_hashrate()),
             shares_submitted: self.stats.shares_submitted,
-            paused: self.paused,
+            paused: self.pause.is_some(),
+            pause_level: self.pause,
+            backfilling: active_source.is_some_and(|id| self.is_backfill(id)),
+            // Profile, boards and sensors are filled in by the API server
+            profile: Default::default(),
             boards: vec![],
             sources: self
                 .sources
+                .iter_mut()
+                .map(|(id, s)| {
+                    // Standby sources aren't mined on any thread
+                    let pin = match s.policy {
+                        SourcePolicy::Pinned { .. } => Some(Some(id)),
+                        _ if active_source == Some(id) => Some(None),
+                        SourcePolicy::Shared | SourcePolicy::Backfill => None,
+                    };
+                    let (hashrate, threads) = pin
+                        .and_then(|pin| pins.get(&pin).copied())
+                        .unwrap_or_default();
+                    SourceState {
+                        name: s.name.clone(),
+                        url: s.url.clone(),
+                        difficulty: s
+                            .last_job
+                            .as_ref()
+                            .map(|j| Difficulty::from_target(j.share_target).as_u64()),
+                        active: active_source == Some(id),
+                        pinned: matches!(s.policy, SourcePolicy::Pinned { .. }),
+                        backfill: s.policy == SourcePolicy::Backfill,
+                        preferred: preferred_source == Some(id),
+                        hashrate: u64::from(hashrate),
+                        threads,
+                        health: source_health_state(&mut s.health, now),
+                        remediation: s.remediation.clone(),
+                        ntime_guard: s.ntime_guard.clone(),
+                        coalesced_jobs: s.coalesced_jobs,
+                        offline_queue: s.offline_queue.clone(),
+                        share_difficulties: s.share_difficulties.snapshot(),
+                        en2_spare: s
+                            .en2_spare
+                            .as_ref()
+                            .map(|spare| Extranonce2SpareState {
+                                reserved: spare.reserved(),
+                                remaining: spare.remaining(),
+                            })
+                            .unwrap_or_default(),
+                    }
+                })
+                .collect(),
+            solo: SoloStats {
+                network_difficulty: self.stats.luck.network_difficulty().map(|d| d.as_u64()),
+                effort_percent: self.stats.luck.effort_percent(),
+                expected_blocks: self.stats.luck.expected_blocks(),
+                blocks_found: self.stats.luck.blocks_found(),
+                luck_percent: self.stats.luck.luck_percent(),
+                last_block_secs: self.stats.luck.last_block().map(|t| t.elapsed().as_secs()),
+            },
+            scheduling: self
+                .threads
                 .values()
-                .map(|s| SourceState {
-                    name: s.name.clone(),
-                    url: s.url.clone(),
-                    difficulty: s
-                        .last_job
-                        .as_ref()
-                        .map(|j| Difficulty::from_target(j.share_target).as_u64()),
+                .map(|entry| {
+                    entry.telemetry.snapshot(
+                        entry.thread.name(),
+                        self.stats.shares_submitted,
+                        &entry.share_pressure,
+                        &entry.thread.status(),
+                    )
                 })
                 .collect(),
+            recent_jobs: self.job_ledger.snapshot(now),
+            sensors: vec![],
         }
     }
 
@@ -273,23 +829,93 @@ impl Scheduler {
         source_target.clamp(hardest, easiest)
     }
 
-    /// Collects hashrate command senders from all sources.
+    /// Scheduler target for one thread, given what its hardware reports.
+    ///
+    /// Like [`Self::compute_scheduler_target`], but never easier than the
+    /// difficulty the thread's hardware filters nonces at. Shares can't
+    /// arrive more often than that, so an easier target would credit each
+    /// one with too little work and understate the thread's hashrate.
+    fn thread_share_target(
+        reporting_difficulty: Option<Difficulty>,
+        hashrate: HashRate,
+        source_target: Target,
+    ) -> Target {
+        let target = Self::compute_scheduler_target(hashrate, source_target);
+        match reporting_difficulty {
+            Some(floor) => target.min(floor.to_target()),
+            None => target,
+        }
+    }
+
+    /// Terms offered to a thread at registration.
+    fn assignment_parameters(capabilities: &HashThreadCapabilities) -> AssignmentParameters {
+        AssignmentParameters {
+            max_ntime_roll: capabilities.max_ntime_roll.min(NTIME_ROLL_LIMIT),
+        }
+    }
+
+    /// Which source should be active, given the health scores of the
+    /// sources that have work.
+    ///
+    /// The preferred source wins whenever it is a candidate. Otherwise the
+    /// highest score wins, earlier candidates breaking ties. The active
+    /// source keeps its place unless it's no longer a candidate or the
+    /// best scores at least [`FAILOVER_MARGIN`] more.
+    fn choose_source<K: Copy + PartialEq>(
+        candidates: &[(K, u8)],
+        active: Option<K>,
+        preferred: Option<K>,
+    ) -> Option<K> {
+        if let Some(preferred) = preferred
+            && candidates.iter().any(|&(id, _)| id == preferred)
+        {
+            return Some(preferred);
+        }
+
+        let mut best: Option<(K, u8)> = None;
+        for &(id, score) in candidates {
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((id, score));
+            }
+        }
+        let (best_id, best_score) = best?;
+
+        let current = candidates
+            .iter()
+            .find(|(id, _)| Some(*id) == active)
+            .map(|&(_, score)| score);
+        if current.is_some_and(|score| best_score < score.saturating_add(FAILOVER_MARGIN)) {
+            return active;
+        }
+        Some(best_id)
+    }
+
+    /// Collects each source's command sender with the hashrate its jobs
+    /// are mined at.
+    ///
+    /// Pinned sources get their own threads' hashrate. Shared sources all
+    /// get the shared threads', since any of them may become active.
     ///
     /// Used with `broadcast_hashrate()` to avoid capturing `&self` across
     /// await points (Scheduler contains Box<dyn HashThread> which isn't Sync).
-    fn hashrate_senders(&self) -> Vec<mpsc::Sender<SourceCommand>> {
-        self.sources
-            .values()
-            .map(|s| s.command_tx.clone())
+    fn hashrate_senders(&mut self) -> Vec<(mpsc::Sender<SourceCommand>, HashRate)> {
+        let ids: Vec<SourceId> = self.sources.keys().collect();
+        ids.into_iter()
+            .map(|id| {
+                let hashrate = self.operational_hashrate(self.pin_of(id));
+                (self.sources[id].command_tx.clone(), hashrate)
+            })
             .collect()
     }
 
     /// Remove tasks matching a predicate, closing their share channels.
+    ///
+    /// Returns how many were removed.
     fn remove_tasks_where(
         &mut self,
         share_channels: &mut ShareStream,
         predicate: impl Fn(&TaskEntry) -> bool,
-    ) {
+    ) -> usize {
         let task_ids: Vec<TaskId> = self
             .tasks
             .iter()
@@ -297,10 +923,143 @@ impl Scheduler {
             .map(|(id, _)| id)
             .collect();
 
-        for task_id in task_ids {
-            self.tasks.remove(task_id);
-            share_channels.remove(&task_id);
+        for task_id in &task_ids {
+            self.tasks.remove(*task_id);
+            share_channels.remove(task_id);
+        }
+        self.job_ledger.tasks_removed(&task_ids, Instant::now());
+
+        self.update_idle_threads();
+        task_ids.len()
+    }
+
+    /// Remove a source's tasks, recording why.
+    fn preempt_source_tasks(
+        &mut self,
+        share_channels: &mut ShareStream,
+        source_id: SourceId,
+        reason: PreemptReason,
+    ) {
+        let count = self.remove_tasks_where(share_channels, |e| e.source_id == source_id);
+        let source = Some(self.source_name(source_id));
+        self.decision_log.record(Decision::TasksPreempted {
+            reason,
+            source,
+            count,
+        });
+    }
+
+    /// Mark threads without any live task as idle for telemetry.
+    fn update_idle_threads(&mut self) {
+        let busy: HashSet<ThreadId> = self.tasks.values().map(|t| t.thread_id).collect();
+        for (thread_id, entry) in self.threads.iter_mut() {
+            entry.telemetry.set_idle(!busy.contains(&thread_id));
+        }
+    }
+
+    /// Move threads between pinned sources and the shared sources.
+    ///
+    /// Each pinned source gets up to its quota of threads, in registration
+    /// order, but at least one thread always stays shared. Moved threads
+    /// lose their tasks, and every source whose threads changed has its
+    /// cached job spread over its new set.
+    ///
+    /// Returns whether any thread moved.
+    async fn rebalance_pins(&mut self, share_channels: &mut ShareStream) -> bool {
+        let mut spare = self.threads.len().saturating_sub(1);
+        let quotas: Vec<(SourceId, usize)> = self
+            .sources
+            .iter()
+            .filter_map(|(id, source)| match source.policy {
+                SourcePolicy::Pinned { threads } => {
+                    let quota = threads.min(spare);
+                    spare -= quota;
+                    Some((id, quota))
+                }
+                SourcePolicy::Shared | SourcePolicy::Backfill => None,
+            })
+            .collect();
+
+        // Previous pin of each thread that moved
+        let mut moved: HashMap<ThreadId, Option<SourceId>> = HashMap::new();
+
+        // Release threads beyond a source's quota...
+        let mut held: HashMap<SourceId, usize> = HashMap::new();
+        for (thread_id, entry) in self.threads.iter_mut() {
+            let Some(pin) = entry.pinned_to else {
+                continue;
+            };
+            let count = held.entry(pin).or_default();
+            let quota = quotas
+                .iter()
+                .find(|&&(id, _)| id == pin)
+                .map_or(0, |&(_, quota)| quota);
+            if *count < quota {
+                *count += 1;
+            } else {
+                moved.insert(thread_id, Some(pin));
+                entry.pinned_to = None;
+            }
+        }
+
+        // ...then fill quotas from the shared threads, newest first
+        let mut shared: Vec<ThreadId> = self
+            .threads
+            .iter()
+            .filter(|(_, entry)| entry.pinned_to.is_none())
+            .map(|(id, _)| id)
+            .collect();
+        for &(source_id, quota) in &quotas {
+            while held.get(&source_id).copied().unwrap_or(0) < quota {
+                let Some(thread_id) = shared.pop() else {
+                    break;
+                };
+                moved.entry(thread_id).or_insert(None);
+                self.threads[thread_id].pinned_to = Some(source_id);
+                *held.entry(source_id).or_default() += 1;
+            }
+        }
+
+        moved.retain(|&thread_id, &mut previous| self.threads[thread_id].pinned_to != previous);
+        if moved.is_empty() {
+            return false;
         }
+
+        let mut affected = HashSet::new();
+        for (&thread_id, &previous) in &moved {
+            let pin = self.threads[thread_id].pinned_to;
+            let preempted = self.remove_tasks_where(share_channels, |e| e.thread_id == thread_id);
+            let thread = self.threads[thread_id].thread.name().to_string();
+            let source = pin.map(|id| self.source_name(id));
+            info!(
+                thread = %thread,
+                source = %source.as_deref().unwrap_or("shared"),
+                "Thread pinning changed"
+            );
+            self.decision_log.record(Decision::ThreadPinned {
+                thread,
+                source,
+                preempted,
+            });
+            affected.extend([previous, pin]);
+        }
+
+        for pin in affected {
+            let Some(source_id) = pin.or(self.active_source) else {
+                continue;
+            };
+            let Some(job) = self.sources[source_id].last_job.clone() else {
+                continue;
+            };
+            self.assign_job_to_threads(
+                AssignMode::Replace,
+                source_id,
+                JobTemplate::clone(&job),
+                share_channels,
+            )
+            .await;
+        }
+        true
     }
 
     /// Handle registration of a new job source.
@@ -308,6 +1067,7 @@ impl Scheduler {
         &mut self,
         registration: SourceRegistration,
         source_events: &mut SourceEventStream,
+        share_channels: &mut ShareStream,
     ) {
         let source_id = self.sources.insert(SourceEntry {
             name: registration.name.clone(),
@@ -315,31 +1075,209 @@ impl Scheduler {
             command_tx: registration.command_tx,
             last_job: None,
             difficulty_alarm: DebouncedAlarm::new(HIGH_DIFFICULTY_DEBOUNCE),
+            health: SourceHealth::new(Instant::now()),
+            remediation: RemediationState::default(),
+            ntime_guard: NtimeGuardState::default(),
+            coalesced_jobs: 0,
+            offline_queue: OfflineQueueState::default(),
+            share_difficulties: ShareHistogram::new(),
+            policy: registration.policy,
+            job_book: JobBook::default(),
+            en2_spare: None,
         });
         source_events.insert(source_id, ReceiverStream::new(registration.event_rx));
-        debug!(source_id = ?source_id, name = %registration.name, "Source registered");
+        debug!(source_id = ?source_id, name = %registration.name, policy = ?registration.policy, "Source registered");
+        self.decision_log.record(Decision::SourceAdded {
+            source: registration.name,
+        });
+
+        // A pinned source takes threads from the shared ones
+        if self.rebalance_pins(share_channels).await {
+            let senders = self.hashrate_senders();
+            broadcast_hashrate(senders).await;
+            return;
+        }
 
         // Send current hashrate estimate to the new source
-        let hashrate = self.operational_hashrate();
+        let hashrate = self.operational_hashrate(self.pin_of(source_id));
         let _ = self.sources[source_id]
             .command_tx
             .send(SourceCommand::UpdateHashRate(hashrate))
             .await;
     }
 
-    /// Assign or replace work on all threads from a job template.
-    async fn assign_job_to_threads(
+    /// Handle a new job from a source.
+    ///
+    /// Jobs from the active source and from pinned sources go straight to
+    /// their threads. Jobs from standby sources are only cached, so
+    /// failover can start on fresh work.
+    async fn handle_job(
         &mut self,
         mode: AssignMode,
         source_id: SourceId,
         job_template: JobTemplate,
         share_channels: &mut ShareStream,
     ) {
-        let source_name = self
+        let Some(source) = self.sources.get_mut(source_id) else {
+            return;
+        };
+        source.health.record_job(Instant::now());
+
+        // Work from an earlier pool session is stale; a resumed session
+        // carries on with its extranonce2 progress
+        if let MerkleRootKind::Computed(template) = &job_template.merkle_root
+            && source.job_book.enter_session(&template.extranonce1)
+        {
+            info!(source = %source.name, "Pool session changed, dropping outstanding work");
+            self.preempt_source_tasks(share_channels, source_id, PreemptReason::SessionChanged);
+        }
+        let Some(source) = self.sources.get_mut(source_id) else {
+            return;
+        };
+
+        if let SourcePolicy::Pinned { .. } = source.policy {
+            self.assign_job_to_threads(mode, source_id, job_template, share_channels)
+                .await;
+            return;
+        }
+
+        match self.active_source {
+            Some(active) if active != source_id => {
+                trace!(source = %source.name, job_id = %job_template.id, "Caching job from standby source");
+                source.last_job = Some(Arc::new(job_template));
+                // The preferred source takes over as soon as it has work,
+                // and any real work takes over from backfill
+                let real_work = source.policy == SourcePolicy::Shared;
+                if self.preferred_source == Some(source_id)
+                    || (real_work && self.is_backfill(active))
+                {
+                    self.update_active_source(share_channels).await;
+                }
+            }
+            _ => {
+                if self.active_source.is_none() {
+                    self.active_source = Some(source_id);
+                    self.decision_log.record(Decision::SourceActivated {
+                        source: source.name.clone(),
+                    });
+                }
+                self.assign_job_to_threads(mode, source_id, job_template, share_channels)
+                    .await;
+            }
+        }
+    }
+
+    /// Re-evaluate which source the shared threads should mine.
+    ///
+    /// Ranks shared sources that have work by health score. The active source
+    /// keeps its place unless it has lost its work or another source
+    /// scores at least [`FAILOVER_MARGIN`] higher.
+    async fn update_active_source(&mut self, share_channels: &mut ShareStream) {
+        let now = Instant::now();
+        let candidates: Vec<(SourceId, u8)> = self
             .sources
-            .get(source_id)
-            .map(|s| s.name.clone())
-            .unwrap_or_else(|| "unknown".to_string());
+            .iter_mut()
+            .filter(|(_, source)| source.policy == SourcePolicy::Shared)
+            .filter(|(_, source)| source.last_job.is_some())
+            .map(|(id, source)| (id, source.health.score(now)))
+            .collect();
+
+        let chosen = Self::choose_source(&candidates, self.active_source, self.preferred_source);
+        if chosen == self.active_source {
+            return;
+        }
+        // Already filling in until real work arrives
+        let backfilling = self.active_source.is_some_and(|id| self.is_backfill(id));
+        if chosen.is_none() && backfilling {
+            return;
+        }
+
+        let previous = std::mem::replace(&mut self.active_source, chosen);
+        self.decision_log.record(Decision::SourceSwitched {
+            from: previous.map(|id| self.source_name(id)),
+            to: chosen.map(|id| self.source_name(id)),
+            scores: candidates
+                .iter()
+                .map(|&(id, score)| SourceScore {
+                    source: self.source_name(id),
+                    score,
+                })
+                .collect(),
+        });
+
+        // Nothing to mine; keep the chips warm if there's backfill, else
+        // the next job to arrive picks the source
+        let Some(best_id) = chosen else {
+            self.start_backfill(share_channels).await;
+            return;
+        };
+
+        let score_of = |id| {
+            candidates
+                .iter()
+                .find(|&&(candidate, _)| Some(candidate) == id)
+                .map(|&(_, score)| score)
+        };
+        info!(
+            from = %previous.map_or_else(|| "none".to_string(), |id| self.source_name(id)),
+            to = %self.sources[best_id].name,
+            score = ?score_of(chosen),
+            previous_score = ?score_of(previous),
+            "Switching job source"
+        );
+
+        if let Some(previous) = previous {
+            self.preempt_source_tasks(share_channels, previous, PreemptReason::Failover);
+        }
+        let job = self.sources[best_id]
+            .last_job
+            .clone()
+            .expect("ranked sources have a job");
+        self.assign_job_to_threads(
+            AssignMode::Replace,
+            best_id,
+            JobTemplate::clone(&job),
+            share_channels,
+        )
+        .await;
+    }
+
+    /// Mine the backfill source on the shared threads, if there is one
+    /// with a job.
+    async fn start_backfill(&mut self, share_channels: &mut ShareStream) {
+        let Some((backfill_id, job)) = self
+            .sources
+            .iter()
+            .filter(|(_, source)| source.policy == SourcePolicy::Backfill)
+            .find_map(|(id, source)| Some((id, source.last_job.clone()?)))
+        else {
+            return;
+        };
+
+        self.active_source = Some(backfill_id);
+        self.decision_log.record(Decision::SourceActivated {
+            source: self.source_name(backfill_id),
+        });
+        info!("No source has work, backfilling idle threads with dummy work");
+        self.assign_job_to_threads(
+            AssignMode::Replace,
+            backfill_id,
+            JobTemplate::clone(&job),
+            share_channels,
+        )
+        .await;
+    }
+
+    /// Assign or replace work from a job template on the source's threads:
+    /// its pinned threads, or the shared threads for a shared source.
+    async fn assign_job_to_threads(
+        &mut self,
+        mode: AssignMode,
+        source_id: SourceId,
+        job_template: JobTemplate,
+        share_channels: &mut ShareStream,
+    ) {
+        let source_name = self.source_name(source_id);
 
         // Extract EN2 range (only supported for computed merkle roots)
         let full_en2_range = match &job_template.merkle_root {
@@ -362,14 +1300,26 @@ impl Scheduler {
             source.last_job = Some(template.clone());
         }
 
-        // Skip assignment if no threads registered yet
-        if self.threads.is_empty() {
+        // A paused miner keeps the job for when it resumes
+        if self.pause.is_some() {
+            debug!(source = %source_name, "Mining paused, job cached for resume");
+            return;
+        }
+
+        // Skip assignment if the source has no threads yet
+        let pin = self.pin_of(source_id);
+        let thread_count = self
+            .threads
+            .values()
+            .filter(|entry| entry.pinned_to == pin)
+            .count();
+        if thread_count == 0 {
             debug!(source = %source_name, "No threads yet, job cached for later");
             return;
         }
 
         // Debounced difficulty warning
-        let hashrate = self.operational_hashrate();
+        let hashrate = self.operational_hashrate(pin);
         if let Some(source) = self.sources.get_mut(source_id) {
             let too_high = is_difficulty_too_high(&template, hashrate);
             match source.difficulty_alarm.check(too_high) {
@@ -399,23 +1349,57 @@ impl Scheduler {
 
         // If replacing, invalidate old tasks for this source first
         if matches!(mode, AssignMode::Replace) {
-            self.remove_tasks_where(share_channels, |e| e.source_id == source_id);
+            self.preempt_source_tasks(share_channels, source_id, PreemptReason::Replaced);
         }
 
-        // Split EN2 range among all threads
-        let en2_slices = full_en2_range
-            .split(self.threads.len())
-            .expect("Failed to split EN2 range among threads");
-
-        // Assign work to all threads
-        for ((thread_id, entry), en2_range) in self.threads.iter_mut().zip(en2_slices) {
+        // Threads furthest away first, so the work reaches every chip at
+        // about the same time; local threads keep their order
+        let mut thread_ids: Vec<ThreadId> = self
+            .threads
+            .iter()
+            .filter(|(_, entry)| entry.pinned_to == pin)
+            .map(|(thread_id, _)| thread_id)
+            .collect();
+        thread_ids.sort_by_key(|&thread_id| Reverse(self.threads[thread_id].thread.link_latency()));
+
+        // Size each thread's EN2 slice to what it will search before the
+        // pool's next job, each starting past the values earlier rounds of
+        // this job used. Too small a range to size falls back to an even
+        // split.
+        let lifetime = self.sources[source_id]
+            .health
+            .job_interval()
+            .unwrap_or(en2_sizing::DEFAULT_JOB_LIFETIME);
+        let needs: Vec<u64> = thread_ids
+            .iter()
+            .map(|&thread_id| self.en2_needed(thread_id, &template, lifetime))
+            .collect();
+        let (en2_slices, en2_spare) = en2_sizing::sized_slices(&full_en2_range, &needs)
+            .unwrap_or_else(|| {
+                let even = full_en2_range
+                    .split(thread_count)
+                    .expect("Failed to split EN2 range among threads");
+                (even, None)
+            });
+        let source = &mut self.sources[source_id];
+        source.en2_spare = en2_spare.map(Extranonce2Allocator::new);
+        let round = source.job_book.next_round(&template.id);
+        let en2_slices = en2_slices
+            .into_iter()
+            .map(|slice| skip_rounds(&slice, round));
+        for (thread_id, en2_range) in thread_ids.into_iter().zip(en2_slices) {
+            let entry = &mut self.threads[thread_id];
             let starting_en2 = en2_range.iter().next();
 
             let hashrate = entry
                 .hashrate
                 .settled_hashrate()
                 .unwrap_or(entry.thread.capabilities().hashrate_estimate);
-            let share_target = Self::compute_scheduler_target(hashrate, template.share_target);
+            let share_target = Self::thread_share_target(
+                entry.thread.capabilities().reporting_difficulty,
+                hashrate,
+                template.share_target,
+            );
 
             // Create share channel for this task
             let (share_tx, share_rx) = mpsc::channel(32);
@@ -426,8 +1410,13 @@ impl Scheduler {
                 en2: starting_en2,
                 share_target,
                 ntime: template.time,
-                share_tx,
+                share_tx: ShareSender::new(share_tx, entry.share_pressure.clone()),
             };
+            let record = AssignmentRecord::new(
+                &source_name,
+                &hash_task,
+                entry.thread.capabilities().version_rolling,
+            );
 
             let result = match mode {
                 AssignMode::Update => entry.thread.update_task(hash_task).await,
@@ -437,12 +1426,32 @@ impl Scheduler {
             if let Err(e) = result {
                 error!(thread = %entry.thread.name(), error = %e, "Failed to assign task");
             } else {
+                let now = Instant::now();
+                let ledger_id = self
+                    .job_ledger
+                    .open(source_id, &source_name, &template.id, now);
                 let task_id = self.tasks.insert(TaskEntry {
                     source_id,
                     template: template.clone(),
                     thread_id,
+                    ledger_id,
+                    en2_reached: starting_en2.map(|en2| en2.value()),
                 });
+                self.job_ledger
+                    .mining(thread_id, task_id, ledger_id, share_target, hashrate, now);
                 share_channels.insert(task_id, ReceiverStream::new(share_rx));
+                self.decision_log.record(Decision::JobAssigned {
+                    source: source_name.clone(),
+                    job_id: template.id.clone(),
+                    thread: entry.thread.name().to_string(),
+                    replace: matches!(mode, AssignMode::Replace),
+                    hashrate: hashrate.into(),
+                    source_target: template.share_target,
+                    share_target,
+                    en2_start: record.en2_start,
+                    en2_len: record.en2_len,
+                });
+                entry.telemetry.record_assignment(record);
             }
         }
     }
@@ -459,14 +1468,42 @@ impl Scheduler {
         // Clear cached job so newly-arriving threads don't get stale work
         if let Some(source) = self.sources.get_mut(source_id) {
             source.last_job = None;
+            source.health.record_disconnect(Instant::now());
         }
 
         // Remove tasks for this source (channels close, stale shares fail)
-        self.remove_tasks_where(share_channels, |e| e.source_id == source_id);
+        self.preempt_source_tasks(share_channels, source_id, PreemptReason::ClearJobs);
     }
 
-    /// Handle a share arriving from a task's channel.
+    /// Handle a share arriving from a task's channel, in a span naming the
+    /// share's thread, source, and job.
     async fn handle_share(&mut self, task_id: TaskId, share: Share) {
+        let span = match self.tasks.get(task_id) {
+            Some(task) => info_span!(
+                "share",
+                thread = self
+                    .threads
+                    .get(task.thread_id)
+                    .map(|t| t.thread.name())
+                    .unwrap_or("unknown"),
+                source = self
+                    .sources
+                    .get(task.source_id)
+                    .map(|s| s.name.as_str())
+                    .unwrap_or("unknown"),
+                job_id = %task.template.id,
+            ),
+            None => info_span!("share", ?task_id),
+        };
+        self.process_share(task_id, share).instrument(span).await
+    }
+
+    async fn process_share(&mut self, task_id: TaskId, share: Share) {
+        if let Some(task_entry) = self.tasks.get_mut(task_id) {
+            let en2_mined = task_entry.reach(share.extranonce2);
+            self.job_ledger.found(task_entry.ledger_id, en2_mined);
+        }
+
         // Look up task context for routing
         let Some(task_entry) = self.tasks.get(task_id) else {
             // Task was removed (ReplaceJob/ClearJobs) but share arrived
@@ -480,6 +1517,22 @@ impl Scheduler {
         let hash = share.hash;
         let share_difficulty = Difficulty::from_hash(&hash);
         let threshold = Difficulty::from_target(task_entry.template.share_target);
+        let backfill = self.is_backfill(task_entry.source_id);
+
+        if self.share_audit.is_enabled() && !backfill {
+            self.share_audit.received(
+                hash,
+                &task_entry.template.id,
+                self.threads
+                    .get(task_entry.thread_id)
+                    .map(|t| t.thread.name())
+                    .unwrap_or("unknown"),
+                self.sources
+                    .get(task_entry.source_id)
+                    .map(|s| s.name.as_str())
+                    .unwrap_or("unknown"),
+            );
+        }
 
         debug!(
             source = %self.sources.get(task_entry.source_id).map(|s| s.name.as_str()).unwrap_or("unknown"),
@@ -491,18 +1544,82 @@ impl Scheduler {
             "Share found"
         );
 
-        // Feed share work to per-thread hashrate estimator
+        // Feed share work to per-thread hashrate estimator; the chips hash
+        // just as hard on backfill
         if let Some(entry) = self.threads.get_mut(task_entry.thread_id) {
             entry.hashrate.record(share.expected_work);
         }
 
+        // Backfill only keeps the chips warm; its shares are worth nothing
+        // and stay out of the share counts
+        if backfill {
+            trace!(job_id = %task_entry.template.id, "Backfill share (not submitted)");
+            return;
+        }
+
+        if let Some(entry) = self.threads.get_mut(task_entry.thread_id) {
+            entry.telemetry.shares_found += 1;
+            entry.telemetry.share_difficulties.record(share_difficulty);
+        }
+        if let Some(source) = self.sources.get_mut(task_entry.source_id) {
+            source.share_difficulties.record(share_difficulty);
+        }
+
+        // Every share is work towards a block, whether or not the source
+        // wants it
+        if self.stats.luck.record_at(
+            std::time::Instant::now(),
+            share.expected_work,
+            task_entry.template.target(),
+            hash,
+        ) {
+            info!(
+                job_id = %task_entry.template.id,
+                hash = %hash,
+                blocks_found = self.stats.luck.blocks_found(),
+                "Block found"
+            );
+        }
+
         // Check if share meets source threshold
-        if task_entry.template.share_target.is_met_by(hash) {
+        let meets_threshold = task_entry.template.share_target.is_met_by(hash);
+        self.share_audit.validated(hash, meets_threshold);
+        if self.events.has_subscribers::<Shares>() {
+            self.events.publish::<Shares>(ShareEvent {
+                source: self.source_name(task_entry.source_id),
+                thread: self
+                    .threads
+                    .get(task_entry.thread_id)
+                    .map(|t| t.thread.name().to_string())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                job_id: task_entry.template.id.clone(),
+                difficulty: share_difficulty,
+                met_target: meets_threshold,
+            });
+        }
+        if meets_threshold {
             self.stats.shares_submitted += 1;
+            self.job_ledger.submitted(task_entry.ledger_id);
+            if let Some(entry) = self.threads.get_mut(task_entry.thread_id) {
+                entry.telemetry.shares_submitted += 1;
+            }
 
             // Submit share to originating source
             if let Some(source) = self.sources.get(task_entry.source_id) {
-                let source_share = SourceShare::from((share, task_entry.template.id.clone()));
+                let proofs = share_proof::installed();
+                if proofs.is_enabled()
+                    && let Some(proof) =
+                        ShareProof::for_share(&source.name, &task_entry.template, &share)
+                {
+                    proofs.submitted(hash, proof);
+                }
+
+                let mut source_share = SourceShare::from((share, task_entry.template.id.clone()));
+                source_share.device_id = self
+                    .threads
+                    .get(task_entry.thread_id)
+                    .and_then(|entry| entry.thread.device_id())
+                    .map(String::from);
 
                 if let Err(e) = source
                     .command_tx
@@ -543,6 +1660,9 @@ impl Scheduler {
         match event {
             HashThreadEvent::WorkExhausted { en2_searched } => {
                 info!(thread = %thread_name, en2_searched, "Work exhausted");
+                if let Some(entry) = self.threads.get_mut(thread_id) {
+                    entry.telemetry.set_idle(true);
+                }
                 // TODO: Assign new work to this thread
             }
 
@@ -553,6 +1673,13 @@ impl Scheduler {
                 // TODO: Prepare next work assignment
             }
 
+            HashThreadEvent::GoingOffline => {
+                info!(thread = %thread_name, "Thread going offline");
+                if let Some(entry) = self.threads.get_mut(thread_id) {
+                    entry.telemetry.set_idle(true);
+                }
+            }
+
             HashThreadEvent::StatusUpdate(status) => {
                 trace!(
                     thread = %thread_name,
@@ -560,6 +1687,13 @@ impl Scheduler {
                     active = status.is_active,
                     "Thread status"
                 );
+                if let Some(entry) = self.threads.get_mut(thread_id) {
+                    entry.telemetry.chip_hashrates = status
+                        .chips
+                        .iter()
+                        .map(|chip| chip.hashrate.unwrap_or_default())
+                        .collect();
+                }
             }
         }
     }
@@ -576,17 +1710,48 @@ impl Scheduler {
             .expect("Thread missing event receiver");
 
         let thread_name = thread.name().to_string();
+        let capabilities = thread.capabilities().clone();
+        let params = Self::assignment_parameters(&capabilities);
+        if let Err(e) = thread.negotiate(params.clone()).await {
+            error!(thread = %thread_name, error = %e, "Thread refused assignment parameters");
+            return;
+        }
+        debug!(
+            thread = %thread_name,
+            hashrate = %capabilities.hashrate_estimate.to_human_readable(),
+            version_rolling = format!("{:#06x}", u16::from_be_bytes(*capabilities.version_rolling.as_bytes())),
+            iterates_extranonce2 = capabilities.iterates_extranonce2,
+            reporting_difficulty = ?capabilities.reporting_difficulty.map(|d| d.as_u64()),
+            max_ntime_roll = params.max_ntime_roll,
+            "Thread capabilities negotiated"
+        );
+
         let thread_id = self.threads.insert(ThreadEntry {
             thread,
             hashrate: HashrateEstimator::new(HASHRATE_WINDOW),
+            degraded_alarm: DebouncedAlarm::new(DEGRADED_HASHRATE_DEBOUNCE),
+            telemetry: ThreadTelemetry::new(),
+            pinned_to: None,
+            share_pressure: Arc::default(),
+            share_waits_seen: 0,
+            saturated_alarm: DebouncedAlarm::new(SATURATED_CHANNEL_DEBOUNCE),
         });
         thread_events.insert(thread_id, ReceiverStream::new(event_rx));
         debug!(thread = %thread_name, "Thread registered");
+        self.decision_log.record(Decision::ThreadAdded {
+            thread: thread_name.clone(),
+            hashrate: capabilities.hashrate_estimate.into(),
+            reporting_difficulty: capabilities.reporting_difficulty.map(|d| d.as_u64()),
+            max_ntime_roll: params.max_ntime_roll,
+        });
+
+        // Another thread may free one up for a pinned source, in which
+        // case the moved sources' jobs have already been spread anew
+        let rebalanced = self.rebalance_pins(share_channels).await;
 
         // Broadcast updated hashrate to all sources
-        let hashrate = self.operational_hashrate();
         let senders = self.hashrate_senders();
-        broadcast_hashrate(senders, hashrate).await;
+        broadcast_hashrate(senders).await;
 
         // Reset difficulty alarm since hashrate changed
         for source in self.sources.values_mut() {
@@ -594,45 +1759,86 @@ impl Scheduler {
         }
 
         self.last_thread_count = thread_events.len();
+        if rebalanced {
+            return;
+        }
 
         // Hashrate is constant for a brand-new thread (estimator has no
         // samples yet, so this always falls back to the static estimate).
         // Compute once rather than repeating inside the source loop.
-        let thread_hashrate = {
+        let (thread_hashrate, share_pressure) = {
             let entry = self
                 .threads
                 .get_mut(thread_id)
                 .expect("Just inserted thread");
-            entry
+            let hashrate = entry
                 .hashrate
                 .settled_hashrate()
-                .unwrap_or(entry.thread.capabilities().hashrate_estimate)
+                .unwrap_or(entry.thread.capabilities().hashrate_estimate);
+            (hashrate, entry.share_pressure.clone())
         };
 
-        // Assign cached jobs from all sources to the new thread
-        for (source_id, source) in self.sources.iter() {
+        if self.pause.is_some() {
+            return;
+        }
+
+        // Assign the active source's cached job to the new thread
+        for (source_id, source) in self.sources.iter_mut() {
+            if self.active_source != Some(source_id) {
+                continue;
+            }
             let Some(template) = &source.last_job else {
                 continue;
             };
 
-            // Extract full EN2 range (new thread overlaps with others)
+            // Take a slice of the EN2 the job's threads left over; without
+            // room there, the full range (overlapping the others, but in a
+            // round of its own so it doesn't repeat their work)
             let full_en2_range = match &template.merkle_root {
-                MerkleRootKind::Computed(t) => t.extranonce2_range.clone(),
+                MerkleRootKind::Computed(t) => {
+                    let lifetime = source
+                        .health
+                        .job_interval()
+                        .unwrap_or(en2_sizing::DEFAULT_JOB_LIFETIME);
+                    let version_bits = en2_sizing::rolled_version_bits(
+                        template.version.gp_bits_mask(),
+                        capabilities.version_rolling,
+                    );
+                    let need = en2_sizing::en2_needed(thread_hashrate, version_bits, lifetime);
+                    let from_spare = source
+                        .en2_spare
+                        .as_ref()
+                        .filter(|spare| spare.remaining() >= need)
+                        .and_then(|spare| spare.reserve(need));
+                    match from_spare {
+                        Some(slice) => slice,
+                        None => skip_rounds(
+                            &t.extranonce2_range,
+                            source.job_book.next_round(&template.id),
+                        ),
+                    }
+                }
                 MerkleRootKind::Fixed(_) => continue,
             };
 
-            let share_target =
-                Self::compute_scheduler_target(thread_hashrate, template.share_target);
+            let share_target = Self::thread_share_target(
+                capabilities.reporting_difficulty,
+                thread_hashrate,
+                template.share_target,
+            );
 
+            let starting_en2 = full_en2_range.iter().next();
             let (share_tx, share_rx) = mpsc::channel(32);
             let hash_task = HashTask {
                 template: template.clone(),
                 en2_range: Some(full_en2_range.clone()),
-                en2: full_en2_range.iter().next(),
+                en2: starting_en2,
                 share_target,
                 ntime: template.time,
-                share_tx,
+                share_tx: ShareSender::new(share_tx, share_pressure.clone()),
             };
+            let record =
+                AssignmentRecord::new(&source.name, &hash_task, capabilities.version_rolling);
 
             let entry = self
                 .threads
@@ -641,12 +1847,38 @@ impl Scheduler {
             if let Err(e) = entry.thread.update_task(hash_task).await {
                 error!(thread = %thread_name, error = %e, "Failed to assign cached job");
             } else {
+                let now = Instant::now();
+                let ledger_id = self
+                    .job_ledger
+                    .open(source_id, &source.name, &template.id, now);
                 let task_id = self.tasks.insert(TaskEntry {
                     source_id,
                     template: template.clone(),
                     thread_id,
+                    ledger_id,
+                    en2_reached: starting_en2.map(|en2| en2.value()),
                 });
+                self.job_ledger.mining(
+                    thread_id,
+                    task_id,
+                    ledger_id,
+                    share_target,
+                    thread_hashrate,
+                    now,
+                );
                 share_channels.insert(task_id, ReceiverStream::new(share_rx));
+                self.decision_log.record(Decision::JobAssigned {
+                    source: source.name.clone(),
+                    job_id: template.id.clone(),
+                    thread: thread_name.clone(),
+                    replace: false,
+                    hashrate: thread_hashrate.into(),
+                    source_target: template.share_target,
+                    share_target,
+                    en2_start: record.en2_start,
+                    en2_len: record.en2_len,
+                });
+                entry.telemetry.record_assignment(record);
                 debug!(
                     thread = %thread_name,
                     source = %source.name,
@@ -676,19 +1908,36 @@ impl Scheduler {
 
         // Remove threads that no longer have active event streams
         let active_thread_ids: HashSet<_> = thread_events.keys().collect();
-        self.threads.retain(|id, _| active_thread_ids.contains(&id));
+        let gone: Vec<ThreadId> = self
+            .threads
+            .keys()
+            .filter(|id| !active_thread_ids.contains(id))
+            .collect();
+        for thread_id in gone {
+            let entry = self.threads.remove(thread_id).expect("listed thread");
+            self.decision_log.record(Decision::ThreadRemoved {
+                thread: entry.thread.name().to_string(),
+            });
+        }
 
         // Remove tasks for disconnected threads
-        self.remove_tasks_where(share_channels, |e| {
+        let count = self.remove_tasks_where(share_channels, |e| {
             !active_thread_ids.contains(&e.thread_id)
         });
+        self.decision_log.record(Decision::TasksPreempted {
+            reason: PreemptReason::ThreadGone,
+            source: None,
+            count,
+        });
 
         self.last_thread_count = current_count;
 
+        // Pinned sources may lose threads, or take over departed ones'
+        self.rebalance_pins(share_channels).await;
+
         // Broadcast updated hashrate to all sources
-        let hashrate = self.operational_hashrate();
         let senders = self.hashrate_senders();
-        broadcast_hashrate(senders, hashrate).await;
+        broadcast_hashrate(senders).await;
 
         // Reset difficulty alarm since hashrate changed
         for source in self.sources.values_mut() {
@@ -700,24 +1949,130 @@ impl Scheduler {
     ///
     /// Publishes an updated state snapshot before replying so the API
     /// handler's subsequent `borrow()` sees the new value.
-    fn handle_api_command(
+    async fn handle_api_command(
         &mut self,
         cmd: SchedulerCommand,
         miner_state_tx: &watch::Sender<MinerState>,
+        share_channels: &mut ShareStream,
     ) {
         match cmd {
-            SchedulerCommand::PauseMining { reply } => {
-                self.paused = true;
-                warn!("Mining paused via API (not yet implemented)");
+            SchedulerCommand::PauseMining { level, reply } => {
+                self.pause_mining(level, share_channels).await;
                 let _ = miner_state_tx.send(self.compute_miner_state());
                 let _ = reply.send(Ok(()));
             }
             SchedulerCommand::ResumeMining { reply } => {
-                self.paused = false;
-                warn!("Mining resumed via API (not yet implemented)");
+                self.resume_mining(share_channels).await;
                 let _ = miner_state_tx.send(self.compute_miner_state());
                 let _ = reply.send(Ok(()));
             }
+            SchedulerCommand::PreferSource { source, reply } => {
+                let result = self.prefer_source(source.as_deref(), share_channels).await;
+                let _ = miner_state_tx.send(self.compute_miner_state());
+                let _ = reply.send(result);
+            }
+        }
+    }
+
+    /// Mine the shared source `name` whenever it has work, or hand the
+    /// choice back to failover with `None`.
+    async fn prefer_source(
+        &mut self,
+        name: Option<&str>,
+        share_channels: &mut ShareStream,
+    ) -> anyhow::Result<()> {
+        let preferred = match name {
+            Some(name) => {
+                let (id, source) = self
+                    .sources
+                    .iter()
+                    .find(|(_, source)| source.name == name)
+                    .ok_or_else(|| anyhow::anyhow!("no source named {name}"))?;
+                match source.policy {
+                    SourcePolicy::Shared => {}
+                    SourcePolicy::Pinned { .. } => {
+                        anyhow::bail!("source {name} mines threads of its own")
+                    }
+                    SourcePolicy::Backfill => {
+                        anyhow::bail!("source {name} only fills in for idle threads")
+                    }
+                }
+                Some(id)
+            }
+            None => None,
+        };
+        if preferred == self.preferred_source {
+            return Ok(());
+        }
+
+        self.preferred_source = preferred;
+        self.decision_log.record(Decision::SourcePreferred {
+            source: name.map(String::from),
+        });
+        info!(source = name.unwrap_or("none"), "Preferred source set.");
+        self.update_active_source(share_channels).await;
+        Ok(())
+    }
+
+    /// Pause at `level`, or move an existing pause to it.
+    ///
+    /// Every level stops dispatch; jobs keep arriving and are cached for
+    /// the resume. Deeper levels also take the threads' work away and send
+    /// them idle. Powering the boards down is the backplane's half of
+    /// [`PauseLevel::PowerDown`].
+    async fn pause_mining(&mut self, level: PauseLevel, share_channels: &mut ShareStream) {
+        if self.pause.is_none() {
+            self.decision_log.record(Decision::Paused);
+        }
+        self.pause = Some(level);
+        info!(?level, "Mining paused.");
+
+        if level == PauseLevel::StopDispatch {
+            return;
+        }
+        let count = self.remove_tasks_where(share_channels, |_| true);
+        if count > 0 {
+            self.decision_log.record(Decision::TasksPreempted {
+                reason: PreemptReason::Paused,
+                source: None,
+                count,
+            });
+        }
+        for entry in self.threads.values_mut() {
+            if let Err(e) = entry.thread.go_idle().await {
+                warn!(thread = %entry.thread.name(), error = %e, "Failed to idle thread");
+            }
+        }
+    }
+
+    /// Resume after a pause, giving every source's threads its latest job.
+    async fn resume_mining(&mut self, share_channels: &mut ShareStream) {
+        if self.pause.take().is_none() {
+            return;
+        }
+        self.decision_log.record(Decision::Resumed);
+        info!("Mining resumed.");
+
+        let sources: Vec<SourceId> = self
+            .sources
+            .iter()
+            .filter(|&(id, source)| {
+                matches!(source.policy, SourcePolicy::Pinned { .. })
+                    || self.active_source == Some(id)
+            })
+            .map(|(id, _)| id)
+            .collect();
+        for source_id in sources {
+            let Some(job) = self.sources[source_id].last_job.clone() else {
+                continue;
+            };
+            self.assign_job_to_threads(
+                AssignMode::Replace,
+                source_id,
+                JobTemplate::clone(&job),
+                share_channels,
+            )
+            .await;
         }
     }
 
@@ -748,7 +2103,11 @@ impl Scheduler {
             tokio::select! {
                 // Source registration
                 Some(registration) = source_reg_rx.recv() => {
-                    self.handle_source_registration(registration, &mut source_events).await;
+                    self.handle_source_registration(
+                        registration,
+                        &mut source_events,
+                        &mut share_channels,
+                    ).await;
                 }
 
                 // Source events
@@ -764,7 +2123,11 @@ impl Scheduler {
                                 job_id = %job_template.id,
                                 "UpdateJob received"
                             );
-                            self.assign_job_to_threads(
+                            self.publish_pool_event(source_id, PoolEventKind::NewJob {
+                                job_id: job_template.id.clone(),
+                                clean: false,
+                            });
+                            self.handle_job(
                                 AssignMode::Update,
                                 source_id,
                                 job_template,
@@ -778,7 +2141,11 @@ impl Scheduler {
                                 job_id = %job_template.id,
                                 "ReplaceJob received"
                             );
-                            self.assign_job_to_threads(
+                            self.publish_pool_event(source_id, PoolEventKind::NewJob {
+                                job_id: job_template.id.clone(),
+                                clean: true,
+                            });
+                            self.handle_job(
                                 AssignMode::Replace,
                                 source_id,
                                 job_template,
@@ -787,7 +2154,47 @@ impl Scheduler {
                         }
 
                         SourceEvent::ClearJobs => {
+                            self.publish_pool_event(source_id, PoolEventKind::WorkCleared);
                             self.handle_clear_jobs(source_id, &mut share_channels);
+                            if self.active_source == Some(source_id) {
+                                self.update_active_source(&mut share_channels).await;
+                            }
+                        }
+
+                        SourceEvent::ShareResult { job_id, accepted, latency } => {
+                            if let Some(source) = self.sources.get_mut(source_id) {
+                                source.health.record_share_result(accepted, latency);
+                            }
+                            self.job_ledger.verdict(source_id, &job_id, accepted);
+                            self.publish_pool_event(source_id, if accepted {
+                                PoolEventKind::ShareAccepted
+                            } else {
+                                PoolEventKind::ShareRejected
+                            });
+                        }
+
+                        SourceEvent::Remediation(state) => {
+                            if let Some(source) = self.sources.get_mut(source_id) {
+                                source.remediation = state;
+                            }
+                        }
+
+                        SourceEvent::NtimeGuard(state) => {
+                            if let Some(source) = self.sources.get_mut(source_id) {
+                                source.ntime_guard = state;
+                            }
+                        }
+
+                        SourceEvent::JobsCoalesced(total) => {
+                            if let Some(source) = self.sources.get_mut(source_id) {
+                                source.coalesced_jobs = total;
+                            }
+                        }
+
+                        SourceEvent::OfflineQueue(state) => {
+                            if let Some(source) = self.sources.get_mut(source_id) {
+                                source.offline_queue = state;
+                            }
                         }
                     }
                 }
@@ -819,11 +2226,14 @@ impl Scheduler {
 
                 // API commands
                 Some(cmd) = cmd_rx.recv() => {
-                    self.handle_api_command(cmd, &miner_state_tx);
+                    self.handle_api_command(cmd, &miner_state_tx, &mut share_channels).await;
                 }
 
                 // Periodic state publishing
                 _ = hashrate_interval.tick() => {
+                    self.check_hashrate_sanity();
+                    self.check_channel_saturation();
+                    self.update_active_source(&mut share_channels).await;
                     let _ = miner_state_tx.send(self.compute_miner_state());
                 }
 
@@ -847,12 +2257,12 @@ impl Scheduler {
     }
 }
 
-/// Broadcasts hashrate update to all registered sources.
+/// Broadcasts hashrate updates to all registered sources.
 ///
 /// Takes pre-collected senders to avoid capturing Scheduler across await
 /// points (it contains Box<dyn HashThread> which isn't Sync).
-async fn broadcast_hashrate(senders: Vec<mpsc::Sender<SourceCommand>>, hashrate: HashRate) {
-    for sender in senders {
+async fn broadcast_hashrate(senders: Vec<(mpsc::Sender<SourceCommand>, HashRate)>) {
+    for (sender, hashrate) in senders {
         let _ = sender.send(SourceCommand::UpdateHashRate(hashrate)).await;
     }
 }
@@ -880,17 +2290,73 @@ fn is_difficulty_too_high(job: &JobTemplate, hashrate: HashRate) -> bool {
     time_to_share > HIGH_DIFFICULTY_THRESHOLD
 }
 
+/// Fraction of expected hashrate below which a thread is considered degraded.
+const DEGRADED_HASHRATE_RATIO: f64 = 0.7;
+
+/// How long measured hashrate must stay low before warning.
+///
+/// Share-based measurement is noisy, particularly at low share rates, so
+/// this is long enough to ride out a run of bad luck.
+const DEGRADED_HASHRATE_DEBOUNCE: Duration = Duration::from_secs(600); // 10 minutes
+
+/// How long shares must keep waiting on a thread's channel before warning.
+///
+/// Checked on the 10-second state tick, so this spans a few ticks of
+/// sustained saturation rather than a single burst.
+const SATURATED_CHANNEL_DEBOUNCE: Duration = Duration::from_secs(30);
+
+/// Ratio of measured to expected hashrate.
+fn hashrate_ratio(measured: HashRate, expected: HashRate) -> f64 {
+    f64::from(measured) / f64::from(expected)
+}
+
+/// Check whether a thread's measured hashrate falls short of expected.
+///
+/// An unsettled estimator has too few samples to judge, so it never
+/// counts as degraded.
+fn is_hashrate_degraded(measured: Option<HashRate>, expected: HashRate) -> bool {
+    let Some(measured) = measured else {
+        return false;
+    };
+    if expected.is_zero() {
+        return false;
+    }
+
+    hashrate_ratio(measured, expected) < DEGRADED_HASHRATE_RATIO
+}
+
+/// Channels between the scheduler and the rest of the miner.
+pub struct SchedulerChannels {
+    /// Hash threads as boards bring them up
+    pub threads: mpsc::Receiver<Box<dyn HashThread>>,
+    /// Job sources as they are registered
+    pub sources: mpsc::Receiver<SourceRegistration>,
+    /// Where the scheduler publishes miner state for the API
+    pub miner_state: watch::Sender<MinerState>,
+    /// Commands from the API
+    pub commands: mpsc::Receiver<SchedulerCommand>,
+}
+
 /// Run the scheduler task, receiving hash threads and job sources.
 pub async fn task(
     running: CancellationToken,
-    thread_rx: mpsc::Receiver<Box<dyn HashThread>>,
-    source_reg_rx: mpsc::Receiver<SourceRegistration>,
-    miner_state_tx: watch::Sender<MinerState>,
-    cmd_rx: mpsc::Receiver<SchedulerCommand>,
+    channels: SchedulerChannels,
+    decision_log: DecisionLog,
+    share_audit: ShareAudit,
+    events: EventBus,
 ) {
-    let mut scheduler = Scheduler::new();
+    let SchedulerChannels {
+        threads,
+        sources,
+        miner_state,
+        commands,
+    } = channels;
+    let mut scheduler = Scheduler::new()
+        .with_decision_log(decision_log)
+        .with_share_audit(share_audit)
+        .with_event_bus(events);
     scheduler
-        .run(running, thread_rx, source_reg_rx, miner_state_tx, cmd_rx)
+        .run(running, threads, sources, miner_state, commands)
         .await;
 }
 
@@ -928,6 +2394,7 @@ fn format_duration(secs: u64) -> String {
 struct MiningStats {
     start_time: std::time::Instant,
     shares_submitted: u64,
+    luck: BlockLuck,
 }
 
 impl Default for MiningStats {
@@ -935,6 +2402,7 @@ impl Default for MiningStats {
         Self {
             start_time: std::time::Instant::now(),
             shares_submitted: 0,
+            luck: BlockLuck::new(),
         }
     }
 }
@@ -1008,6 +2476,938 @@ mod tests {
         assert!(result < very_easy, "clamped target should be harder");
     }
 
+    #[test]
+    fn thread_target_respects_hardware_reporting_difficulty() {
+        // At 1 TH/s the flood ceiling is about difficulty 23, but chips
+        // reporting at 64 can't deliver shares that easy
+        let hashrate = HashRate::from_terahashes(1.0);
+        let chip = Some(Difficulty::from(64));
+        let result = Scheduler::thread_share_target(chip, hashrate, Target::MAX);
+        assert_eq!(result, Difficulty::from(64).to_target());
+
+        // A harder source target is left alone
+        let source = Difficulty::from(100).to_target();
+        assert_eq!(
+            Scheduler::thread_share_target(chip, hashrate, source),
+            source
+        );
+
+        // Even the measurement floor gives way to the hardware
+        let slow_chip = Some(Difficulty::from(1024));
+        let result = Scheduler::thread_share_target(slow_chip, hashrate, source);
+        assert_eq!(result, Difficulty::from(1024).to_target());
+
+        // Software threads see every nonce
+        assert_eq!(
+            Scheduler::thread_share_target(None, hashrate, Target::MAX),
+            target_for_share_rate(FLOOD_CAP_RATE, hashrate)
+        );
+    }
+
+    #[test]
+    fn assignment_parameters_cap_ntime_roll() {
+        let unlimited = HashThreadCapabilities {
+            hashrate_estimate: HashRate::from_terahashes(1.0),
+            version_rolling: crate::job_source::GeneralPurposeBits::full(),
+            max_ntime_roll: u32::MAX,
+            iterates_extranonce2: false,
+            reporting_difficulty: None,
+        };
+        assert_eq!(
+            Scheduler::assignment_parameters(&unlimited).max_ntime_roll,
+            NTIME_ROLL_LIMIT
+        );
+
+        let short = HashThreadCapabilities {
+            max_ntime_roll: 30,
+            ..unlimited
+        };
+        assert_eq!(Scheduler::assignment_parameters(&short).max_ntime_roll, 30);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn telemetry_tracks_idle_time() {
+        let mut telemetry = ThreadTelemetry::new();
+
+        // Idle from registration until the first assignment
+        tokio::time::advance(Duration::from_secs(10)).await;
+        telemetry.set_idle(false);
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert_eq!(telemetry.idle_percent(), 25);
+
+        // Idle again: the open period counts too
+        telemetry.set_idle(true);
+        tokio::time::advance(Duration::from_secs(40)).await;
+        assert_eq!(telemetry.idle_percent(), 63);
+    }
+
+    fn test_job(id: &str) -> JobTemplate {
+        use crate::job_source::{
+            Extranonce2Range, GeneralPurposeBits, MerkleRootTemplate, VersionTemplate,
+        };
+        use bitcoin::hashes::Hash;
+
+        JobTemplate {
+            id: id.to_string(),
+            prev_blockhash: bitcoin::BlockHash::all_zeros(),
+            version: VersionTemplate::new(
+                bitcoin::block::Version::from_consensus(0x20000000),
+                GeneralPurposeBits::none(),
+            )
+            .unwrap(),
+            bits: bitcoin::CompactTarget::from_consensus(0x1d00ffff),
+            share_target: Target::MAX,
+            time: 0,
+            merkle_root: MerkleRootKind::Computed(Box::new(MerkleRootTemplate::new(
+                vec![],
+                vec![],
+                Extranonce2Range::new(4).unwrap(),
+                vec![],
+                vec![],
+            ))),
+        }
+    }
+
+    fn test_source(scheduler: &mut Scheduler, name: &str) -> SourceId {
+        test_source_with(scheduler, name, SourcePolicy::Shared)
+    }
+
+    fn test_source_with(scheduler: &mut Scheduler, name: &str, policy: SourcePolicy) -> SourceId {
+        let (command_tx, _command_rx) = mpsc::channel(1);
+        scheduler.sources.insert(SourceEntry {
+            name: name.to_string(),
+            url: None,
+            command_tx,
+            last_job: None,
+            difficulty_alarm: DebouncedAlarm::new(HIGH_DIFFICULTY_DEBOUNCE),
+            health: SourceHealth::new(Instant::now()),
+            remediation: RemediationState::default(),
+            ntime_guard: NtimeGuardState::default(),
+            coalesced_jobs: 0,
+            offline_queue: OfflineQueueState::default(),
+            share_difficulties: ShareHistogram::new(),
+            policy,
+            job_book: JobBook::default(),
+            en2_spare: None,
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failover_demotes_flapping_source() {
+        let mut scheduler = Scheduler::new();
+        let mut share_channels: ShareStream = StreamMap::new();
+        let primary = test_source(&mut scheduler, "primary");
+        let backup = test_source(&mut scheduler, "backup");
+
+        // First source with work becomes active; the other stands by
+        for source in [primary, backup] {
+            scheduler
+                .handle_job(
+                    AssignMode::Replace,
+                    source,
+                    test_job("job"),
+                    &mut share_channels,
+                )
+                .await;
+        }
+        assert_eq!(scheduler.active_source, Some(primary));
+        assert!(scheduler.sources[backup].last_job.is_some());
+
+        // A single disconnect fails over at once: the primary has no work
+        tokio::time::advance(Duration::from_secs(60)).await;
+        scheduler.handle_clear_jobs(primary, &mut share_channels);
+        scheduler.update_active_source(&mut share_channels).await;
+        assert_eq!(scheduler.active_source, Some(backup));
+
+        // The primary returns but has flapped, so it stays on standby
+        tokio::time::advance(Duration::from_secs(5)).await;
+        scheduler
+            .handle_job(
+                AssignMode::Replace,
+                primary,
+                test_job("job"),
+                &mut share_channels,
+            )
+            .await;
+        scheduler.update_active_source(&mut share_channels).await;
+        assert_eq!(scheduler.active_source, Some(backup));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failover_waits_for_clear_lead() {
+        let mut scheduler = Scheduler::new();
+        let mut share_channels: ShareStream = StreamMap::new();
+        let primary = test_source(&mut scheduler, "primary");
+        let backup = test_source(&mut scheduler, "backup");
+
+        for source in [primary, backup] {
+            scheduler
+                .handle_job(
+                    AssignMode::Replace,
+                    source,
+                    test_job("job"),
+                    &mut share_channels,
+                )
+                .await;
+        }
+
+        // A few rejects on the primary cost less than the failover margin
+        for accepted in [true, true, true, false] {
+            scheduler.sources[primary]
+                .health
+                .record_share_result(accepted, Duration::from_millis(100));
+        }
+        tokio::time::advance(Duration::from_secs(10)).await;
+        scheduler.update_active_source(&mut share_channels).await;
+        assert_eq!(scheduler.active_source, Some(primary));
+
+        // Rejecting most shares does not
+        for _ in 0..20 {
+            scheduler.sources[primary]
+                .health
+                .record_share_result(false, Duration::from_millis(100));
+        }
+        scheduler.update_active_source(&mut share_channels).await;
+        assert_eq!(scheduler.active_source, Some(backup));
+    }
+
+    /// Decision log sink the test can read back.
+    #[derive(Clone, Default)]
+    struct SharedLog(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedLog {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failover_decisions_replay_cleanly() {
+        let log = SharedLog::default();
+        let mut scheduler = Scheduler::new().with_decision_log(DecisionLog::to_writer(log.clone()));
+        let mut share_channels: ShareStream = StreamMap::new();
+        let primary = test_source(&mut scheduler, "primary");
+        let backup = test_source(&mut scheduler, "backup");
+
+        for source in [primary, backup] {
+            scheduler
+                .handle_job(
+                    AssignMode::Replace,
+                    source,
+                    test_job("job"),
+                    &mut share_channels,
+                )
+                .await;
+        }
+        tokio::time::advance(Duration::from_secs(60)).await;
+        scheduler.handle_clear_jobs(primary, &mut share_channels);
+        scheduler.update_active_source(&mut share_channels).await;
+        assert_eq!(scheduler.active_source, Some(backup));
+
+        let text = log.0.lock().unwrap().clone();
+        let records = decision_log::read_log(text.as_slice()).unwrap();
+        assert!(records.iter().any(|r| matches!(
+            &r.decision,
+            Decision::SourceSwitched { to: Some(to), .. } if to == "backup"
+        )));
+
+        let report = decision_log::replay(&records);
+        assert!(report.is_clean(), "{:?}", report.divergences);
+        assert_eq!(report.active_source.as_deref(), Some("backup"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn preferred_source_wins_while_it_has_work() {
+        let mut scheduler = Scheduler::new();
+        let mut share_channels: ShareStream = StreamMap::new();
+        let primary = test_source(&mut scheduler, "primary");
+        let backup = test_source(&mut scheduler, "backup");
+
+        for source in [primary, backup] {
+            scheduler
+                .handle_job(
+                    AssignMode::Replace,
+                    source,
+                    test_job("job"),
+                    &mut share_channels,
+                )
+                .await;
+        }
+        assert_eq!(scheduler.active_source, Some(primary));
+
+        // Preferring the standby switches at once, health notwithstanding
+        scheduler
+            .prefer_source(Some("backup"), &mut share_channels)
+            .await
+            .unwrap();
+        assert_eq!(scheduler.active_source, Some(backup));
+
+        // Without work it gives way, and takes over again when work returns
+        scheduler.handle_clear_jobs(backup, &mut share_channels);
+        scheduler.update_active_source(&mut share_channels).await;
+        assert_eq!(scheduler.active_source, Some(primary));
+        scheduler
+            .handle_job(
+                AssignMode::Replace,
+                backup,
+                test_job("job"),
+                &mut share_channels,
+            )
+            .await;
+        assert_eq!(scheduler.active_source, Some(backup));
+
+        assert!(
+            scheduler
+                .prefer_source(Some("nope"), &mut share_channels)
+                .await
+                .is_err()
+        );
+    }
+
+    /// Hash thread that takes any work and never finds a share.
+    struct StubThread {
+        name: String,
+        capabilities: HashThreadCapabilities,
+        link_latency: Option<Duration>,
+        event_rx: Option<mpsc::Receiver<HashThreadEvent>>,
+        _event_tx: mpsc::Sender<HashThreadEvent>,
+    }
+
+    impl StubThread {
+        fn boxed(name: &str) -> Box<dyn HashThread> {
+            Self::remote(name, None)
+        }
+
+        /// A thread whose hardware is `link_latency` away.
+        fn remote(name: &str, link_latency: Option<Duration>) -> Box<dyn HashThread> {
+            let (event_tx, event_rx) = mpsc::channel(1);
+            Box::new(Self {
+                name: name.to_string(),
+                capabilities: HashThreadCapabilities {
+                    hashrate_estimate: HashRate::from_terahashes(1.0),
+                    version_rolling: GeneralPurposeBits::none(),
+                    max_ntime_roll: 0,
+                    iterates_extranonce2: true,
+                    reporting_difficulty: None,
+                },
+                link_latency,
+                event_rx: Some(event_rx),
+                _event_tx: event_tx,
+            })
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HashThread for StubThread {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn capabilities(&self) -> &HashThreadCapabilities {
+            &self.capabilities
+        }
+
+        fn link_latency(&self) -> Option<Duration> {
+            self.link_latency
+        }
+
+        async fn negotiate(
+            &mut self,
+            _params: AssignmentParameters,
+        ) -> Result<(), crate::asic::hash_thread::HashThreadError> {
+            Ok(())
+        }
+
+        async fn update_task(
+            &mut self,
+            _task: HashTask,
+        ) -> Result<Option<HashTask>, crate::asic::hash_thread::HashThreadError> {
+            Ok(None)
+        }
+
+        async fn replace_task(
+            &mut self,
+            _task: HashTask,
+        ) -> Result<Option<HashTask>, crate::asic::hash_thread::HashThreadError> {
+            Ok(None)
+        }
+
+        async fn go_idle(
+            &mut self,
+        ) -> Result<Option<HashTask>, crate::asic::hash_thread::HashThreadError> {
+            Ok(None)
+        }
+
+        fn take_event_receiver(&mut self) -> Option<mpsc::Receiver<HashThreadEvent>> {
+            self.event_rx.take()
+        }
+
+        fn status(&self) -> crate::asic::hash_thread::HashThreadStatus {
+            Default::default()
+        }
+    }
+
+    /// Sources mined on each thread, by thread name.
+    fn sources_by_thread(scheduler: &Scheduler) -> Vec<(String, Vec<String>)> {
+        scheduler
+            .threads
+            .iter()
+            .map(|(thread_id, entry)| {
+                let sources = scheduler
+                    .tasks
+                    .values()
+                    .filter(|task| task.thread_id == thread_id)
+                    .map(|task| scheduler.source_name(task.source_id))
+                    .collect();
+                (entry.thread.name().to_string(), sources)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn work_goes_to_the_furthest_threads_first() {
+        let mut scheduler = Scheduler::new();
+        let mut thread_events: ThreadEventStream = StreamMap::new();
+        let mut share_channels: ShareStream = StreamMap::new();
+        let pool = test_source(&mut scheduler, "pool");
+        for (name, latency_ms) in [("local", None), ("near", Some(2)), ("far", Some(40))] {
+            let latency = latency_ms.map(Duration::from_millis);
+            scheduler
+                .handle_new_thread(
+                    StubThread::remote(name, latency),
+                    &mut thread_events,
+                    &mut share_channels,
+                )
+                .await;
+        }
+
+        scheduler
+            .handle_job(
+                AssignMode::Replace,
+                pool,
+                test_job("1"),
+                &mut share_channels,
+            )
+            .await;
+
+        let order: Vec<&str> = scheduler
+            .tasks
+            .values()
+            .map(|task| scheduler.threads[task.thread_id].thread.name())
+            .collect();
+        assert_eq!(order, ["far", "near", "local"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pause_levels_hold_work_until_resume() {
+        let log = SharedLog::default();
+        let mut scheduler = Scheduler::new().with_decision_log(DecisionLog::to_writer(log.clone()));
+        let mut thread_events: ThreadEventStream = StreamMap::new();
+        let mut share_channels: ShareStream = StreamMap::new();
+        let pool = test_source(&mut scheduler, "pool");
+        scheduler
+            .handle_job(
+                AssignMode::Replace,
+                pool,
+                test_job("1"),
+                &mut share_channels,
+            )
+            .await;
+        for name in ["a", "b"] {
+            scheduler
+                .handle_new_thread(
+                    StubThread::boxed(name),
+                    &mut thread_events,
+                    &mut share_channels,
+                )
+                .await;
+        }
+        let job_ids = |scheduler: &Scheduler| {
+            scheduler
+                .tasks
+                .values()
+                .map(|task| task.template.id.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(job_ids(&scheduler), ["1", "1"]);
+
+        // Stopping dispatch leaves the chips on the work they have
+        scheduler
+            .pause_mining(PauseLevel::StopDispatch, &mut share_channels)
+            .await;
+        scheduler
+            .handle_job(
+                AssignMode::Replace,
+                pool,
+                test_job("2"),
+                &mut share_channels,
+            )
+            .await;
+        assert_eq!(job_ids(&scheduler), ["1", "1"]);
+
+        // Idling the chips takes it away, and new threads get none
+        scheduler
+            .pause_mining(PauseLevel::IdleChips, &mut share_channels)
+            .await;
+        assert!(scheduler.tasks.is_empty());
+        scheduler
+            .handle_new_thread(
+                StubThread::boxed("c"),
+                &mut thread_events,
+                &mut share_channels,
+            )
+            .await;
+        assert!(scheduler.tasks.is_empty());
+        let state = scheduler.compute_miner_state();
+        assert!(state.paused);
+        assert_eq!(state.pause_level, Some(PauseLevel::IdleChips));
+
+        // Resuming hands every thread the latest job
+        scheduler.resume_mining(&mut share_channels).await;
+        assert_eq!(job_ids(&scheduler), ["2", "2", "2"]);
+        assert_eq!(scheduler.compute_miner_state().pause_level, None);
+
+        let text = log.0.lock().unwrap().clone();
+        let records = decision_log::read_log(text.as_slice()).unwrap();
+        let report = decision_log::replay(&records);
+        assert!(report.is_clean(), "{:?}", report.divergences);
+        assert_eq!(report.live_tasks, 3);
+        assert!(!report.paused);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backfill_fills_in_until_real_work() {
+        let log = SharedLog::default();
+        let mut scheduler = Scheduler::new().with_decision_log(DecisionLog::to_writer(log.clone()));
+        let mut thread_events: ThreadEventStream = StreamMap::new();
+        let mut share_channels: ShareStream = StreamMap::new();
+        let pool = test_source(&mut scheduler, "pool");
+        let backfill = test_source_with(&mut scheduler, "backfill", SourcePolicy::Backfill);
+        scheduler
+            .handle_new_thread(
+                StubThread::boxed("a"),
+                &mut thread_events,
+                &mut share_channels,
+            )
+            .await;
+        let mined = |scheduler: &Scheduler| sources_by_thread(scheduler)[0].1.clone();
+
+        // Nothing else has work, so the chips get dummy work
+        scheduler
+            .handle_job(
+                AssignMode::Update,
+                backfill,
+                test_job("dummy"),
+                &mut share_channels,
+            )
+            .await;
+        assert_eq!(mined(&scheduler), ["backfill"]);
+        assert!(scheduler.compute_miner_state().backfilling);
+
+        // Real work preempts it at once
+        scheduler
+            .handle_job(AssignMode::Update, pool, test_job("1"), &mut share_channels)
+            .await;
+        assert_eq!(scheduler.active_source, Some(pool));
+        assert_eq!(mined(&scheduler), ["pool"]);
+        assert!(!scheduler.compute_miner_state().backfilling);
+
+        // A standby backfill job doesn't disturb the pool's work
+        scheduler
+            .handle_job(
+                AssignMode::Update,
+                backfill,
+                test_job("dummy"),
+                &mut share_channels,
+            )
+            .await;
+        assert_eq!(mined(&scheduler), ["pool"]);
+
+        // When the pool drops, backfill takes over again, and stays
+        scheduler.handle_clear_jobs(pool, &mut share_channels);
+        scheduler.update_active_source(&mut share_channels).await;
+        assert_eq!(scheduler.active_source, Some(backfill));
+        scheduler.update_active_source(&mut share_channels).await;
+        assert_eq!(mined(&scheduler), ["backfill"]);
+
+        let state = scheduler.compute_miner_state();
+        let backfill_state = state.sources.iter().find(|s| s.name == "backfill");
+        assert!(backfill_state.is_some_and(|s| s.backfill && s.active));
+        assert!(
+            scheduler
+                .prefer_source(Some("backfill"), &mut share_channels)
+                .await
+                .is_err()
+        );
+
+        // Shares found on dummy work don't count as found
+        use bitcoin::hashes::Hash;
+        let task_id = scheduler.tasks.keys().next().unwrap();
+        scheduler
+            .handle_share(
+                task_id,
+                Share {
+                    nonce: 1,
+                    hash: bitcoin::BlockHash::all_zeros(),
+                    version: bitcoin::block::Version::from_consensus(0x2000_0000),
+                    ntime: 0,
+                    extranonce2: None,
+                    expected_work: Difficulty::from(1u64).to_target().to_work(),
+                },
+            )
+            .await;
+        let thread = scheduler.threads.values().next().unwrap();
+        assert_eq!(thread.telemetry.shares_found, 0);
+        assert_eq!(thread.telemetry.share_difficulties.snapshot().count, 0);
+        let source = scheduler.sources.get(backfill).unwrap();
+        assert_eq!(source.share_difficulties.snapshot().count, 0);
+
+        let text = log.0.lock().unwrap().clone();
+        let records = decision_log::read_log(text.as_slice()).unwrap();
+        let report = decision_log::replay(&records);
+        assert!(report.is_clean(), "{:?}", report.divergences);
+        assert_eq!(report.active_source.as_deref(), Some("backfill"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pinned_source_mines_its_own_threads() {
+        let log = SharedLog::default();
+        let mut scheduler = Scheduler::new().with_decision_log(DecisionLog::to_writer(log.clone()));
+        let mut thread_events: ThreadEventStream = StreamMap::new();
+        let mut share_channels: ShareStream = StreamMap::new();
+        let pool = test_source(&mut scheduler, "pool");
+        let solo = test_source_with(&mut scheduler, "solo", SourcePolicy::Pinned { threads: 1 });
+
+        for source in [pool, solo] {
+            scheduler
+                .handle_job(
+                    AssignMode::Replace,
+                    source,
+                    test_job("job"),
+                    &mut share_channels,
+                )
+                .await;
+        }
+        // Only the shared source competes for activation
+        assert_eq!(scheduler.active_source, Some(pool));
+
+        // The first thread stays with the pool; the next goes solo
+        for name in ["a", "b", "c"] {
+            scheduler
+                .handle_new_thread(
+                    StubThread::boxed(name),
+                    &mut thread_events,
+                    &mut share_channels,
+                )
+                .await;
+        }
+        assert_eq!(
+            sources_by_thread(&scheduler),
+            [
+                ("a".to_string(), vec!["pool".to_string()]),
+                ("b".to_string(), vec!["solo".to_string()]),
+                ("c".to_string(), vec!["pool".to_string()]),
+            ]
+        );
+        let state = scheduler.compute_miner_state();
+        assert_eq!(
+            state.sources.iter().map(|s| s.threads).collect::<Vec<_>>(),
+            [2, 1]
+        );
+        assert!(state.sources[1].pinned);
+
+        // Losing the solo thread pins another in its place
+        let b = scheduler
+            .threads
+            .iter()
+            .find(|(_, entry)| entry.thread.name() == "b")
+            .map(|(id, _)| id)
+            .unwrap();
+        thread_events.remove(&b);
+        scheduler
+            .handle_thread_disconnections(&thread_events, &mut share_channels)
+            .await;
+        assert_eq!(
+            sources_by_thread(&scheduler),
+            [
+                ("a".to_string(), vec!["pool".to_string()]),
+                ("c".to_string(), vec!["solo".to_string()]),
+            ]
+        );
+
+        let text = log.0.lock().unwrap().clone();
+        let records = decision_log::read_log(text.as_slice()).unwrap();
+        let report = decision_log::replay(&records);
+        assert!(report.is_clean(), "{:?}", report.divergences);
+        assert_eq!(report.live_tasks, 2);
+    }
+
+    fn session_job(id: &str, extranonce1: &[u8]) -> JobTemplate {
+        let mut job = test_job(id);
+        if let MerkleRootKind::Computed(template) = &mut job.merkle_root {
+            template.extranonce1 = extranonce1.to_vec();
+        }
+        job
+    }
+
+    /// Starting extranonce2 of each thread's latest task.
+    fn en2_starts(scheduler: &Scheduler) -> Vec<Option<u64>> {
+        scheduler
+            .threads
+            .values()
+            .map(|entry| entry.telemetry.recent.front().and_then(|r| r.en2_start))
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resumed_session_continues_extranonce2() {
+        let mut scheduler = Scheduler::new();
+        let mut thread_events: ThreadEventStream = StreamMap::new();
+        let mut share_channels: ShareStream = StreamMap::new();
+        let pool = test_source(&mut scheduler, "pool");
+        for name in ["a", "b"] {
+            scheduler
+                .handle_new_thread(
+                    StubThread::boxed(name),
+                    &mut thread_events,
+                    &mut share_channels,
+                )
+                .await;
+        }
+
+        scheduler
+            .handle_job(
+                AssignMode::Replace,
+                pool,
+                session_job("1", &[1]),
+                &mut share_channels,
+            )
+            .await;
+        // At 1 TH/s without version rolling, each thread needs 2^17 values
+        // for the default job lifetime
+        assert_eq!(en2_starts(&scheduler), [Some(0), Some(1 << 17)]);
+
+        // The pool resumes the session after a reconnect and resends the
+        // job: threads start past what they already searched
+        scheduler.handle_clear_jobs(pool, &mut share_channels);
+        scheduler.update_active_source(&mut share_channels).await;
+        scheduler
+            .handle_job(
+                AssignMode::Replace,
+                pool,
+                session_job("1", &[1]),
+                &mut share_channels,
+            )
+            .await;
+        assert_eq!(en2_starts(&scheduler), [Some(1), Some((1 << 17) + 1)]);
+
+        // A new session starts over, dropping the old session's work
+        scheduler
+            .handle_job(
+                AssignMode::Update,
+                pool,
+                session_job("1", &[2]),
+                &mut share_channels,
+            )
+            .await;
+        assert_eq!(en2_starts(&scheduler), [Some(0), Some(1 << 17)]);
+        assert_eq!(scheduler.tasks.len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn late_threads_reserve_from_the_spare_extranonce2() {
+        let mut scheduler = Scheduler::new();
+        let mut thread_events: ThreadEventStream = StreamMap::new();
+        let mut share_channels: ShareStream = StreamMap::new();
+        let pool = test_source(&mut scheduler, "pool");
+        scheduler
+            .handle_new_thread(
+                StubThread::boxed("a"),
+                &mut thread_events,
+                &mut share_channels,
+            )
+            .await;
+        scheduler
+            .handle_job(
+                AssignMode::Replace,
+                pool,
+                test_job("1"),
+                &mut share_channels,
+            )
+            .await;
+        let spare = scheduler.compute_miner_state().sources[0].en2_spare.clone();
+        assert_eq!(
+            spare,
+            Extranonce2SpareState {
+                reserved: 0,
+                remaining: (1 << 32) - (1 << 17),
+            }
+        );
+
+        // A thread joining while the job runs takes the next chunk
+        scheduler
+            .handle_new_thread(
+                StubThread::boxed("b"),
+                &mut thread_events,
+                &mut share_channels,
+            )
+            .await;
+        assert_eq!(en2_starts(&scheduler), [Some(0), Some(1 << 17)]);
+        let spare = scheduler.compute_miner_state().sources[0].en2_spare.clone();
+        assert_eq!(
+            spare,
+            Extranonce2SpareState {
+                reserved: 1 << 17,
+                remaining: (1 << 32) - (2 << 17),
+            }
+        );
+    }
+
+    /// A real CPU thread mines through the whole pipeline: the scheduler
+    /// assigns it a job, and what it finds reaches the source as a share
+    /// whose header solves the job.
+    #[cfg(feature = "cpu-miner")]
+    #[tokio::test]
+    async fn cpu_thread_shares_reach_the_source() {
+        use crate::cpu_miner::CpuHashThread;
+
+        let mut scheduler = Scheduler::new();
+        let mut thread_events: ThreadEventStream = StreamMap::new();
+        let mut share_channels: ShareStream = StreamMap::new();
+        let pool = test_source(&mut scheduler, "pool");
+        let (command_tx, mut command_rx) = mpsc::channel(8);
+        scheduler.sources.get_mut(pool).unwrap().command_tx = command_tx;
+
+        // A real coinbase, and a share target easier than the
+        // scheduler's flood cap, so every share the thread finds is one
+        // for the source
+        let mut job = test_job("cpu");
+        if let MerkleRootKind::Computed(template) = &mut job.merkle_root {
+            use crate::job_source::test_blocks::block_881423;
+            template.coinbase1 = block_881423::coinbase1_bytes().to_vec();
+            template.extranonce1 = block_881423::extranonce1_bytes().to_vec();
+            template.coinbase2 = block_881423::coinbase2_bytes().to_vec();
+        }
+        job.share_target = Difficulty::from_f64(1.0 / (1u64 << 24) as f64).to_target();
+        job.time = 0x6500_0000;
+        let template = job.clone();
+        scheduler
+            .handle_job(AssignMode::Replace, pool, job, &mut share_channels)
+            .await;
+        scheduler
+            .handle_new_thread(
+                Box::new(CpuHashThread::new("cpu".into(), 100)),
+                &mut thread_events,
+                &mut share_channels,
+            )
+            .await;
+
+        let (task_id, share) = tokio::time::timeout(Duration::from_secs(60), share_channels.next())
+            .await
+            .expect("share within timeout")
+            .expect("share channel open");
+        scheduler.handle_share(task_id, share).await;
+
+        let share = loop {
+            match command_rx.recv().await {
+                Some(SourceCommand::SubmitShare(share)) => break share,
+                Some(SourceCommand::UpdateHashRate(_)) => continue,
+                None => panic!("share submitted to source"),
+            }
+        };
+        assert_eq!(share.job_id, "cpu");
+        let en2 = share
+            .extranonce2
+            .as_ref()
+            .expect("thread was given extranonce2");
+        let header = bitcoin::block::Header {
+            version: share.version,
+            prev_blockhash: template.prev_blockhash,
+            merkle_root: template.compute_merkle_root(en2).unwrap(),
+            time: share.time,
+            bits: template.bits,
+            nonce: share.nonce,
+        };
+        assert_eq!(header.block_hash(), share.hash);
+        assert!(template.share_target.is_met_by(share.hash));
+    }
+
+    #[test]
+    fn percent_handles_empty_whole() {
+        assert_eq!(percent(5.0, 0.0), 0);
+        assert_eq!(percent(1.0, 3.0), 33);
+        assert_eq!(percent(3.0, 3.0), 100);
+    }
+
+    #[test]
+    fn share_filtering_flags_stages_out_of_order() {
+        let status = HashThreadStatus {
+            chip_difficulty: Some(Difficulty::from(256)),
+            share_target: Some(Difficulty::from(1024).to_target()),
+            pool_target: Some(Difficulty::from(65536).to_target()),
+            chip_shares_found: 400,
+            shares_forwarded: 100,
+            pool_shares_submitted: 2,
+            ..Default::default()
+        };
+        let filtering = share_filtering(&status);
+        assert_eq!(filtering.chip_difficulty, Some(256));
+        assert_eq!(filtering.share_difficulty, Some(1024));
+        assert_eq!(filtering.pool_difficulty, Some(65536));
+        assert!(filtering.issues.is_empty());
+
+        // A pool asking less of each share than the chips report at
+        let status = HashThreadStatus {
+            pool_target: Some(Difficulty::from(128).to_target()),
+            pool_shares_submitted: 200,
+            ..status
+        };
+        assert_eq!(
+            share_filtering(&status).issues,
+            [
+                FilteringIssue::ChipHarderThanPool,
+                FilteringIssue::ShareTargetHarderThanPool,
+                FilteringIssue::CountsOutOfOrder,
+            ]
+        );
+
+        // Idle CPU threads have nothing to compare
+        assert!(
+            share_filtering(&HashThreadStatus::default())
+                .issues
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn hashrate_degraded_below_ratio() {
+        let expected = HashRate::from_terahashes(1.0);
+        assert!(is_hashrate_degraded(
+            Some(HashRate::from_gigahashes(500.0)),
+            expected
+        ));
+        assert!(!is_hashrate_degraded(
+            Some(HashRate::from_gigahashes(900.0)),
+            expected
+        ));
+    }
+
+    #[test]
+    fn hashrate_not_degraded_without_data() {
+        // Unsettled estimator: not enough samples to judge
+        assert!(!is_hashrate_degraded(None, HashRate::from_terahashes(1.0)));
+
+        // No expected rate to compare against
+        assert!(!is_hashrate_degraded(
+            Some(HashRate::from(0)),
+            HashRate::from(0)
+        ));
+    }
+
     #[test]
     fn scheduler_target_clamp_ordering_invariant() {
         // Verify hardest <= easiest in Ord terms for several
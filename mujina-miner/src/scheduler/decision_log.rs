@@ -0,0 +1,727 @@
+//! Record of scheduler decisions, for replaying scheduling bugs.
+//!
+//! When enabled, the scheduler appends one JSON object per line for each
+//! decision it makes: sources and threads coming and going, jobs assigned
+//! to threads, tasks preempted, and failover between sources. Each record
+//! carries the inputs the decision was made from, such as the hashrate
+//! and source target behind a thread's share target, or the health
+//! scores behind a source switch.
+//!
+//! [`replay`] walks a log without any hardware or pool attached. It
+//! rebuilds the scheduler's view of sources, threads and tasks, recomputes
+//! each decision from its recorded inputs with the current scheduling
+//! code, and reports every place where the two disagree. A log captured
+//! on a misbehaving rig can then be replayed as often as needed, and
+//! replays cleanly once the bug is fixed.
+//!
+//! Sources and threads are identified by name, since the scheduler's
+//! internal keys mean nothing outside the process that assigned them.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, LineWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use super::{FAILOVER_MARGIN, Scheduler};
+use crate::tracing::prelude::*;
+use crate::types::{Difficulty, HashRate, Target};
+
+/// Errors from reading a decision log.
+#[derive(Debug, thiserror::Error)]
+pub enum DecisionLogError {
+    #[error("failed to read decision log: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("line {line}: {source}")]
+    Parse {
+        line: usize,
+        source: serde_json::Error,
+    },
+}
+
+/// Why tasks were taken away from threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreemptReason {
+    /// The source replaced its job.
+    Replaced,
+    /// The source cleared its jobs, usually on disconnect.
+    ClearJobs,
+    /// The scheduler switched to another source.
+    Failover,
+    /// The threads holding the tasks went away.
+    ThreadGone,
+    /// The source's pool started a new session, so its work is for a
+    /// stale extranonce1.
+    SessionChanged,
+    /// Mining was paused at a level that sends the chips idle.
+    Paused,
+}
+
+/// Health score of a source at a failover evaluation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceScore {
+    pub source: String,
+    pub score: u8,
+}
+
+/// One scheduler decision and the inputs it was made from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum Decision {
+    SourceAdded {
+        source: String,
+    },
+
+    /// A thread was registered after negotiating its parameters.
+    ThreadAdded {
+        thread: String,
+        /// Capability estimate, in H/s.
+        hashrate: u64,
+        reporting_difficulty: Option<u64>,
+        max_ntime_roll: u32,
+    },
+
+    /// A thread's event stream ended.
+    ThreadRemoved {
+        thread: String,
+    },
+
+    /// A thread was pinned to a source, or returned to the shared sources
+    /// when `source` is absent, losing its tasks.
+    ThreadPinned {
+        thread: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
+        preempted: usize,
+    },
+
+    /// A job went to a thread as a new task.
+    JobAssigned {
+        source: String,
+        job_id: String,
+        thread: String,
+        /// Whether the task replaced the thread's work for this source.
+        replace: bool,
+        /// Hashrate the share target was computed for, in H/s.
+        hashrate: u64,
+        #[serde(with = "target_hex")]
+        source_target: Target,
+        #[serde(with = "target_hex")]
+        share_target: Target,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        en2_start: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        en2_len: Option<u64>,
+    },
+
+    /// Tasks were removed, for one source or for departed threads.
+    TasksPreempted {
+        reason: PreemptReason,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
+        count: usize,
+    },
+
+    /// Nothing was active and the first source with a job took over.
+    SourceActivated {
+        source: String,
+    },
+
+    /// Failover evaluation picked a different source, or none.
+    SourceSwitched {
+        from: Option<String>,
+        to: Option<String>,
+        /// Sources that had work, in scheduler order.
+        scores: Vec<SourceScore>,
+    },
+
+    /// The operator picked a source to mine whenever it has work, or
+    /// handed the choice back to failover when `source` is absent.
+    SourcePreferred {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<String>,
+    },
+
+    Paused,
+    Resumed,
+}
+
+/// A decision with the time it was made.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    /// Milliseconds since the log was opened.
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub decision: Decision,
+}
+
+/// Appends decisions to a JSON-lines log.
+///
+/// A disabled log ignores everything, so the scheduler can record
+/// unconditionally. Write errors are logged once and disable the log
+/// rather than disturbing mining.
+pub struct DecisionLog {
+    out: Option<Box<dyn Write + Send>>,
+    start: Instant,
+}
+
+impl DecisionLog {
+    /// A log that records nothing.
+    pub fn disabled() -> Self {
+        Self {
+            out: None,
+            start: Instant::now(),
+        }
+    }
+
+    /// Log to a file, truncating it.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = File::create(path)?;
+        Ok(Self::to_writer(LineWriter::new(file)))
+    }
+
+    /// Log to any writer.
+    ///
+    /// Records are written whole, one per line; buffering is up to the
+    /// writer.
+    pub fn to_writer(out: impl Write + Send + 'static) -> Self {
+        Self {
+            out: Some(Box::new(out)),
+            start: Instant::now(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.out.is_some()
+    }
+
+    /// Append a decision.
+    pub fn record(&mut self, decision: Decision) {
+        let Some(out) = self.out.as_mut() else {
+            return;
+        };
+
+        let record = Record {
+            at_ms: self.start.elapsed().as_millis() as u64,
+            decision,
+        };
+        let result = serde_json::to_writer(&mut *out, &record)
+            .map_err(io::Error::from)
+            .and_then(|()| out.write_all(b"\n"));
+        if let Err(e) = result {
+            warn!(error = %e, "Failed to write decision log, disabling it");
+            self.out = None;
+        }
+    }
+}
+
+impl fmt::Debug for DecisionLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecisionLog")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+/// Read every record from a decision log.
+pub fn read_log(reader: impl BufRead) -> Result<Vec<Record>, DecisionLogError> {
+    let mut records = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line).map_err(|source| DecisionLogError::Parse {
+            line: index + 1,
+            source,
+        })?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// A recorded decision the replay doesn't agree with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Index of the record in the log, from zero.
+    pub step: usize,
+    pub at_ms: u64,
+    pub message: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} at {} ms: {}", self.step, self.at_ms, self.message)
+    }
+}
+
+/// Outcome of replaying a log.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayReport {
+    pub steps: usize,
+    pub divergences: Vec<Divergence>,
+
+    /// State at the end of the log.
+    pub active_source: Option<String>,
+    pub threads: Vec<String>,
+    pub live_tasks: usize,
+    pub paused: bool,
+}
+
+impl ReplayReport {
+    /// Whether every decision matched the replay.
+    pub fn is_clean(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+/// Thread as the replay knows it.
+#[derive(Debug)]
+struct ReplayThread {
+    name: String,
+    reporting_difficulty: Option<Difficulty>,
+}
+
+/// Task as the replay knows it.
+#[derive(Debug)]
+struct ReplayTask {
+    source: String,
+    thread: String,
+}
+
+/// Replay recorded decisions against the current scheduling logic.
+pub fn replay(records: &[Record]) -> ReplayReport {
+    let mut sources = BTreeSet::new();
+    let mut threads: Vec<ReplayThread> = Vec::new();
+    let mut tasks: Vec<ReplayTask> = Vec::new();
+    // Source each pinned thread mines, by thread
+    let mut pins: HashMap<String, String> = HashMap::new();
+    let mut active: Option<String> = None;
+    let mut preferred: Option<String> = None;
+    let mut paused = false;
+    let mut divergences = Vec::new();
+
+    for (step, record) in records.iter().enumerate() {
+        let mut diverge = |message: String| {
+            divergences.push(Divergence {
+                step,
+                at_ms: record.at_ms,
+                message,
+            })
+        };
+
+        match &record.decision {
+            Decision::SourceAdded { source } => {
+                if !sources.insert(source.clone()) {
+                    diverge(format!("source {source} added twice"));
+                }
+            }
+
+            Decision::ThreadAdded {
+                thread,
+                reporting_difficulty,
+                ..
+            } => {
+                if threads.iter().any(|t| t.name == *thread) {
+                    diverge(format!("thread {thread} added twice"));
+                }
+                threads.push(ReplayThread {
+                    name: thread.clone(),
+                    reporting_difficulty: reporting_difficulty.map(Difficulty::from),
+                });
+            }
+
+            Decision::ThreadRemoved { thread } => {
+                let before = threads.len();
+                threads.retain(|t| t.name != *thread);
+                if threads.len() == before {
+                    diverge(format!("unknown thread {thread} removed"));
+                }
+                pins.remove(thread);
+            }
+
+            Decision::ThreadPinned {
+                thread,
+                source,
+                preempted,
+            } => {
+                if !threads.iter().any(|t| t.name == *thread) {
+                    diverge(format!("unknown thread {thread} pinned"));
+                }
+
+                let before = tasks.len();
+                tasks.retain(|task| task.thread != *thread);
+                let removed = before - tasks.len();
+                if removed != *preempted {
+                    diverge(format!(
+                        "pinning {thread} preempted {preempted} tasks, replay has {removed}"
+                    ));
+                }
+                match source {
+                    Some(source) => pins.insert(thread.clone(), source.clone()),
+                    None => pins.remove(thread),
+                };
+            }
+
+            Decision::JobAssigned {
+                source,
+                job_id,
+                thread,
+                hashrate,
+                source_target,
+                share_target,
+                ..
+            } => {
+                // Pinned threads mine their own source, the rest the active one
+                match pins.get(thread) {
+                    Some(pinned) if pinned != source => diverge(format!(
+                        "job {job_id} from {source} assigned to {thread}, pinned to {pinned}"
+                    )),
+                    None if active.as_ref() != Some(source) => diverge(format!(
+                        "job {job_id} from {source} assigned while {} was active",
+                        active.as_deref().unwrap_or("no source")
+                    )),
+                    _ => {}
+                }
+                let Some(entry) = threads.iter().find(|t| t.name == *thread) else {
+                    diverge(format!("job {job_id} assigned to unknown thread {thread}"));
+                    continue;
+                };
+
+                let expected = Scheduler::thread_share_target(
+                    entry.reporting_difficulty,
+                    HashRate::from(*hashrate),
+                    *source_target,
+                );
+                if expected != *share_target {
+                    diverge(format!(
+                        "job {job_id} on {thread}: share target difficulty {}, replay gives {}",
+                        Difficulty::from_target(*share_target),
+                        Difficulty::from_target(expected)
+                    ));
+                }
+                tasks.push(ReplayTask {
+                    source: source.clone(),
+                    thread: thread.clone(),
+                });
+            }
+
+            Decision::TasksPreempted {
+                reason,
+                source,
+                count,
+            } => {
+                let before = tasks.len();
+                match (reason, source) {
+                    (PreemptReason::ThreadGone, _) => {
+                        tasks.retain(|task| threads.iter().any(|t| t.name == task.thread))
+                    }
+                    (PreemptReason::Paused, _) => tasks.clear(),
+                    (_, Some(source)) => tasks.retain(|task| task.source != *source),
+                    (_, None) => {
+                        diverge(format!("{reason:?} preemption without a source"));
+                        continue;
+                    }
+                }
+                let removed = before - tasks.len();
+                if removed != *count {
+                    diverge(format!(
+                        "{reason:?} preempted {count} tasks, replay has {removed}"
+                    ));
+                }
+            }
+
+            Decision::SourceActivated { source } => {
+                if let Some(current) = &active {
+                    diverge(format!("{source} activated while {current} was active"));
+                }
+                active = Some(source.clone());
+            }
+
+            Decision::SourceSwitched { from, to, scores } => {
+                if *from != active {
+                    diverge(format!(
+                        "switch from {} while {} was active",
+                        from.as_deref().unwrap_or("no source"),
+                        active.as_deref().unwrap_or("no source")
+                    ));
+                }
+                let candidates: Vec<(&str, u8)> = scores
+                    .iter()
+                    .map(|s| (s.source.as_str(), s.score))
+                    .collect();
+                let chosen =
+                    Scheduler::choose_source(&candidates, from.as_deref(), preferred.as_deref());
+                if chosen != to.as_deref() {
+                    diverge(format!(
+                        "switched to {}, replay chooses {} (margin {FAILOVER_MARGIN})",
+                        to.as_deref().unwrap_or("no source"),
+                        chosen.unwrap_or("no source")
+                    ));
+                }
+                active = to.clone();
+            }
+
+            Decision::SourcePreferred { source } => {
+                if let Some(source) = source
+                    && !sources.contains(source)
+                {
+                    diverge(format!("unknown source {source} preferred"));
+                }
+                preferred = source.clone();
+            }
+
+            Decision::Paused => paused = true,
+            Decision::Resumed => paused = false,
+        }
+    }
+
+    ReplayReport {
+        steps: records.len(),
+        divergences,
+        active_source: active,
+        threads: threads.into_iter().map(|t| t.name).collect(),
+        live_tasks: tasks.len(),
+        paused,
+    }
+}
+
+/// Targets as big-endian hex, so they round-trip exactly.
+mod target_hex {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    use crate::types::Target;
+
+    pub fn serialize<S: Serializer>(target: &Target, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(target.to_be_bytes()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Target, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let bytes: [u8; 32] = hex::decode(&text)
+            .map_err(D::Error::custom)?
+            .try_into()
+            .map_err(|_| D::Error::custom("target must be 32 bytes"))?;
+        Ok(Target::from_be_bytes(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(at_ms: u64, decision: Decision) -> Record {
+        Record { at_ms, decision }
+    }
+
+    fn assigned(source: &str, thread: &str, share_target: Target) -> Decision {
+        Decision::JobAssigned {
+            source: source.to_string(),
+            job_id: "1".to_string(),
+            thread: thread.to_string(),
+            replace: true,
+            hashrate: HashRate::from_terahashes(1.0).into(),
+            source_target: Difficulty::from(100).to_target(),
+            share_target,
+            en2_start: Some(0),
+            en2_len: Some(1 << 32),
+        }
+    }
+
+    fn session() -> Vec<Record> {
+        vec![
+            at(
+                0,
+                Decision::SourceAdded {
+                    source: "pool".to_string(),
+                },
+            ),
+            at(
+                5,
+                Decision::ThreadAdded {
+                    thread: "board0".to_string(),
+                    hashrate: HashRate::from_terahashes(1.0).into(),
+                    reporting_difficulty: Some(256),
+                    max_ntime_roll: 600,
+                },
+            ),
+            at(
+                10,
+                Decision::SourceActivated {
+                    source: "pool".to_string(),
+                },
+            ),
+            at(
+                10,
+                Decision::TasksPreempted {
+                    reason: PreemptReason::Replaced,
+                    source: Some("pool".to_string()),
+                    count: 0,
+                },
+            ),
+            // The chip can't report anything easier than 256
+            at(
+                10,
+                assigned("pool", "board0", Difficulty::from(256).to_target()),
+            ),
+        ]
+    }
+
+    #[test]
+    fn records_round_trip_through_json_lines() {
+        let records = session();
+        let mut text = String::new();
+        for record in &records {
+            text.push_str(&serde_json::to_string(record).unwrap());
+            text.push('\n');
+        }
+        assert!(text.contains(r#""decision":"job_assigned""#));
+
+        assert_eq!(read_log(text.as_bytes()).unwrap(), records);
+        assert!(matches!(
+            read_log("{\"at_ms\":1}\n".as_bytes()),
+            Err(DecisionLogError::Parse { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn consistent_log_replays_cleanly() {
+        let report = replay(&session());
+        assert!(report.is_clean(), "{:?}", report.divergences);
+        assert_eq!(report.steps, 5);
+        assert_eq!(report.active_source.as_deref(), Some("pool"));
+        assert_eq!(report.threads, ["board0"]);
+        assert_eq!(report.live_tasks, 1);
+    }
+
+    #[test]
+    fn replay_flags_decisions_the_code_would_not_make() {
+        let mut records = session();
+
+        // A share target easier than the chip reports at
+        records.push(at(
+            20,
+            assigned("pool", "board0", Difficulty::from(100).to_target()),
+        ));
+
+        // Failing over to a source that isn't clearly better
+        records.push(at(
+            30,
+            Decision::SourceSwitched {
+                from: Some("pool".to_string()),
+                to: Some("backup".to_string()),
+                scores: vec![
+                    SourceScore {
+                        source: "pool".to_string(),
+                        score: 90,
+                    },
+                    SourceScore {
+                        source: "backup".to_string(),
+                        score: 95,
+                    },
+                ],
+            },
+        ));
+
+        // Preempting tasks the scheduler shouldn't have had
+        records.push(at(
+            40,
+            Decision::TasksPreempted {
+                reason: PreemptReason::Failover,
+                source: Some("pool".to_string()),
+                count: 5,
+            },
+        ));
+
+        let report = replay(&records);
+        let steps: Vec<usize> = report.divergences.iter().map(|d| d.step).collect();
+        assert_eq!(steps, [5, 6, 7], "{:?}", report.divergences);
+        assert!(
+            report.divergences[1]
+                .message
+                .contains("replay chooses pool")
+        );
+        assert_eq!(report.active_source.as_deref(), Some("backup"));
+    }
+
+    #[test]
+    fn replay_follows_pinned_threads() {
+        let mut records = session();
+        records.push(at(
+            20,
+            Decision::ThreadPinned {
+                thread: "board0".to_string(),
+                source: Some("solo".to_string()),
+                preempted: 1,
+            },
+        ));
+        records.push(at(
+            20,
+            assigned("solo", "board0", Difficulty::from(256).to_target()),
+        ));
+
+        // The active source's jobs no longer go to the pinned thread
+        records.push(at(
+            30,
+            assigned("pool", "board0", Difficulty::from(256).to_target()),
+        ));
+
+        let report = replay(&records);
+        let steps: Vec<usize> = report.divergences.iter().map(|d| d.step).collect();
+        assert_eq!(steps, [7], "{:?}", report.divergences);
+        assert!(report.divergences[0].message.contains("pinned to solo"));
+    }
+
+    #[test]
+    fn replay_follows_preferred_source() {
+        let scores = vec![
+            SourceScore {
+                source: "pool".to_string(),
+                score: 90,
+            },
+            SourceScore {
+                source: "backup".to_string(),
+                score: 50,
+            },
+        ];
+        let mut records = session();
+        records.push(at(
+            20,
+            Decision::SourceAdded {
+                source: "backup".to_string(),
+            },
+        ));
+        records.push(at(
+            30,
+            Decision::SourcePreferred {
+                source: Some("backup".to_string()),
+            },
+        ));
+        // The less healthy source wins because it is preferred
+        records.push(at(
+            30,
+            Decision::SourceSwitched {
+                from: Some("pool".to_string()),
+                to: Some("backup".to_string()),
+                scores,
+            },
+        ));
+        records.push(at(40, Decision::SourcePreferred { source: None }));
+        records.push(at(
+            40,
+            Decision::SourcePreferred {
+                source: Some("nope".to_string()),
+            },
+        ));
+
+        let report = replay(&records);
+        let steps: Vec<usize> = report.divergences.iter().map(|d| d.step).collect();
+        assert_eq!(steps, [9], "{:?}", report.divergences);
+        assert_eq!(report.active_source.as_deref(), Some("backup"));
+    }
+}
@@ -0,0 +1,162 @@
+//! Extranonce2 slices sized to what each thread will use.
+//!
+//! Every extranonce2 value gives a thread `2^32 × 2^version_bits` headers
+//! to hash, and a job only lives until the pool sends the next one. A
+//! thread thus needs about `hashrate × job lifetime / headers per value`
+//! values per job; splitting the whole range evenly hands out far more
+//! than that when the range is large, and too few to the fast threads
+//! when it is small.
+//!
+//! Slices are sized to each thread's need with headroom instead. When the
+//! range has room, the rest stays free for threads that join while the
+//! job runs; when it doesn't, the range is shared in proportion to need.
+//! The job lifetime follows the source's notify cadence, so the sizes
+//! adapt as it changes.
+
+use std::time::Duration;
+
+use crate::job_source::{Extranonce2Range, GeneralPurposeBits};
+use crate::types::HashRate;
+
+/// Job lifetime assumed until a source's notify cadence is known.
+pub(super) const DEFAULT_JOB_LIFETIME: Duration = Duration::from_secs(120);
+
+/// Factor over the predicted need, for jobs that outlive the cadence and
+/// threads that speed up.
+const HEADROOM: f64 = 4.0;
+
+/// Fewest values in a slice.
+///
+/// Reissued rounds of a job start one value further into each slice (see
+/// [`skip_rounds`](super::job_book::skip_rounds)); this leaves them room.
+const MIN_SLICE: u64 = 1024;
+
+/// Version bits a thread rolls for a job: those the job allows that the
+/// thread can roll.
+pub(super) fn rolled_version_bits(job: GeneralPurposeBits, thread: GeneralPurposeBits) -> u32 {
+    (u16::from_be_bytes(*job.as_bytes()) & u16::from_be_bytes(*thread.as_bytes())).count_ones()
+}
+
+/// Extranonce2 values a thread hashing at `hashrate` works through in
+/// `lifetime`, with headroom.
+///
+/// Rounded up to a power of two, so small changes in hashrate or cadence
+/// don't move slice boundaries between rounds of a job.
+pub(super) fn en2_needed(hashrate: HashRate, version_bits: u32, lifetime: Duration) -> u64 {
+    let headers_per_value = 2f64.powi(32 + version_bits as i32);
+    let headers = hashrate.0 as f64 * lifetime.as_secs_f64() * HEADROOM;
+    let values = (headers / headers_per_value).ceil() as u64;
+    values
+        .max(MIN_SLICE)
+        .checked_next_power_of_two()
+        .unwrap_or(u64::MAX)
+}
+
+/// Consecutive slices of `range`, one per entry of `needs`, and what is
+/// left of the range after them.
+///
+/// Each slice holds its need if the range has room for all of them;
+/// otherwise the range is shared in proportion to need. None if the range
+/// has fewer values than there are slices.
+pub(super) fn sized_slices(
+    range: &Extranonce2Range,
+    needs: &[u64],
+) -> Option<(Vec<Extranonce2Range>, Option<Extranonce2Range>)> {
+    let available = range.len();
+    if needs.is_empty() || available < needs.len() as u64 {
+        return None;
+    }
+
+    let total: u128 = needs.iter().map(|&n| u128::from(n.max(1))).sum();
+    let sizes: Vec<u64> = if total <= u128::from(available) {
+        needs.iter().map(|&n| n.max(1)).collect()
+    } else {
+        // Everyone gets at least one value; the rest goes by need
+        let spare = u128::from(available - needs.len() as u64);
+        let mut sizes: Vec<u64> = needs
+            .iter()
+            .map(|&n| 1 + (spare * u128::from(n.max(1)) / total) as u64)
+            .collect();
+        let used: u64 = sizes.iter().sum();
+        *sizes.last_mut().expect("needs is not empty") += available - used;
+        sizes
+    };
+
+    let mut start = range.min;
+    let slices = sizes
+        .iter()
+        .map(|&size| {
+            let slice = Extranonce2Range::new_range(start, start + (size - 1), range.size)
+                .expect("slice within range");
+            start = slice.max.wrapping_add(1);
+            slice
+        })
+        .collect::<Vec<_>>();
+    let end = slices.last().expect("needs is not empty").max;
+    let rest = (end < range.max).then(|| {
+        Extranonce2Range::new_range(end + 1, range.max, range.size).expect("within range")
+    });
+    Some((slices, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn need_follows_hashrate_version_bits_and_lifetime() {
+        let minute = Duration::from_secs(60);
+        // 1 TH/s with all version bits covers a value every ~4.7 minutes
+        assert_eq!(
+            en2_needed(HashRate::from_terahashes(1.0), 16, minute),
+            MIN_SLICE
+        );
+
+        // Without version rolling each value lasts ~4 ms at 1 TH/s
+        let need = en2_needed(HashRate::from_terahashes(1.0), 0, minute);
+        assert_eq!(need, 1 << 16);
+        assert_eq!(
+            en2_needed(HashRate::from_terahashes(1.0), 0, minute * 4),
+            need * 4
+        );
+
+        assert_eq!(
+            rolled_version_bits(
+                GeneralPurposeBits::new([0x1f, 0xff]),
+                GeneralPurposeBits::full()
+            ),
+            13
+        );
+    }
+
+    #[test]
+    fn roomy_range_leaves_the_rest_free() {
+        let range = Extranonce2Range::new(4).unwrap();
+        let (slices, rest) = sized_slices(&range, &[1024, 4096]).unwrap();
+        assert_eq!(slices[0], Extranonce2Range::new_range(0, 1023, 4).unwrap());
+        assert_eq!(
+            slices[1],
+            Extranonce2Range::new_range(1024, 5119, 4).unwrap()
+        );
+        assert_eq!(rest.unwrap().min, 5120);
+
+        let exact = Extranonce2Range::new_range(0, 5119, 4).unwrap();
+        assert_eq!(sized_slices(&exact, &[1024, 4096]).unwrap().1, None);
+    }
+
+    #[test]
+    fn scarce_range_is_shared_by_need() {
+        let range = Extranonce2Range::new(1).unwrap();
+        let (slices, rest) = sized_slices(&range, &[1024, 3072]).unwrap();
+        assert_eq!(rest, None);
+        assert_eq!(slices[0].min, 0);
+        assert_eq!(slices[1].max, 255);
+        assert_eq!(slices[0].len() + slices[1].len(), 256);
+        // The thread needing three times as much gets about three times
+        // as much
+        assert!((63..=65).contains(&slices[0].len()));
+
+        let tiny = Extranonce2Range::new_range(0, 1, 1).unwrap();
+        assert!(sized_slices(&tiny, &[1, 1, 1]).is_none());
+    }
+}
@@ -0,0 +1,111 @@
+//! Extranonce2 progress per source, kept across reconnects.
+//!
+//! A thread mines the extranonce2 value its task starts at, so handing
+//! out the same starting values for a job twice repeats work and yields
+//! duplicate shares. Jobs do get handed out again: a pool resends its
+//! current job after a reconnect, and sources reissue a job when the
+//! share difficulty changes. The book counts how many rounds of each
+//! recent job have gone out, and each round starts that many values into
+//! the threads' extranonce2 slices.
+//!
+//! Progress belongs to a pool session, identified by its extranonce1. A
+//! reconnect that resumes the session carries on where the previous
+//! connection left off; a new session starts over.
+
+use std::collections::VecDeque;
+
+use crate::job_source::Extranonce2Range;
+
+/// Number of recent jobs whose rounds are remembered.
+const JOBS_KEPT: usize = 16;
+
+/// Extranonce2 consumption for one source's pool session.
+#[derive(Debug, Default)]
+pub(super) struct JobBook {
+    /// Extranonce1 of the session the progress belongs to.
+    extranonce1: Option<Vec<u8>>,
+
+    /// Rounds handed out per job, oldest first.
+    rounds: VecDeque<(String, u64)>,
+}
+
+impl JobBook {
+    /// Note the session of a job about to be handed out.
+    ///
+    /// Returns true if it differs from the session of earlier jobs, whose
+    /// progress is then forgotten.
+    pub(super) fn enter_session(&mut self, extranonce1: &[u8]) -> bool {
+        match &self.extranonce1 {
+            Some(current) if current == extranonce1 => false,
+            previous => {
+                let changed = previous.is_some();
+                self.extranonce1 = Some(extranonce1.to_vec());
+                self.rounds.clear();
+                changed
+            }
+        }
+    }
+
+    /// Start another round of `job_id`, returning how many rounds came
+    /// before it this session.
+    pub(super) fn next_round(&mut self, job_id: &str) -> u64 {
+        if let Some((_, rounds)) = self.rounds.iter_mut().find(|(id, _)| id == job_id) {
+            *rounds += 1;
+            return *rounds - 1;
+        }
+        if self.rounds.len() == JOBS_KEPT {
+            self.rounds.pop_front();
+        }
+        self.rounds.push_back((job_id.to_string(), 1));
+        0
+    }
+}
+
+/// The part of `range` a task starts at in the given round.
+///
+/// Rounds past the end of a short range wrap around to its start.
+pub(super) fn skip_rounds(range: &Extranonce2Range, round: u64) -> Extranonce2Range {
+    let offset = round % range.len();
+    Extranonce2Range::new_range(range.min + offset, range.max, range.size)
+        .expect("offset within range")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumed_session_keeps_counting() {
+        let mut book = JobBook::default();
+        assert!(!book.enter_session(&[1, 2, 3, 4]));
+        assert_eq!(book.next_round("a"), 0);
+        assert_eq!(book.next_round("a"), 1);
+        assert_eq!(book.next_round("b"), 0);
+
+        // Same extranonce1 after a reconnect: progress carries on
+        assert!(!book.enter_session(&[1, 2, 3, 4]));
+        assert_eq!(book.next_round("a"), 2);
+
+        // A new session starts over
+        assert!(book.enter_session(&[5, 6, 7, 8]));
+        assert_eq!(book.next_round("a"), 0);
+    }
+
+    #[test]
+    fn forgets_old_jobs() {
+        let mut book = JobBook::default();
+        book.next_round("old");
+        for n in 0..JOBS_KEPT {
+            book.next_round(&format!("job-{n}"));
+        }
+        assert_eq!(book.next_round("old"), 0);
+    }
+
+    #[test]
+    fn rounds_wrap_in_short_ranges() {
+        let range = Extranonce2Range::new_range(10, 12, 1).unwrap();
+        assert_eq!(skip_rounds(&range, 0), range);
+        assert_eq!(skip_rounds(&range, 2).min, 12);
+        assert_eq!(skip_rounds(&range, 4).min, 11);
+    }
+}
@@ -0,0 +1,399 @@
+//! Share accounting per job, for spotting work that went wrong on its way
+//! to the chips.
+//!
+//! A job whose header reaches a chip corrupted, or built from the wrong
+//! coinbase, still gets hashed; the chips just find nothing the host can
+//! verify, or far less than they should. Per-thread counters average that
+//! away over the jobs around it. The ledger keeps, for each recent job,
+//! the shares the threads found on it and how many their hashrate says
+//! they should have found in the time they spent on it, along with what
+//! the pool made of the shares and how far into their extranonce2 slices
+//! the threads got.
+//!
+//! A thread mines only its newest task: an older task's channel stays
+//! open for shares still in flight, but its time on the job ends when the
+//! next task arrives. Once no thread is left on a job, a job that found
+//! implausibly few shares for its expected count is flagged and warned
+//! about. Served at `GET /api/v0/jobs/recent`.
+
+use std::collections::{HashMap, VecDeque};
+
+use tokio::time::Instant;
+
+use super::{SourceId, TaskId, ThreadId};
+use crate::api_client::types::JobAccounting;
+use crate::tracing::prelude::*;
+use crate::types::{HashRate, Target};
+
+/// Number of recent jobs accounted for.
+const JOBS_KEPT: usize = 32;
+
+/// Chance, under the job's expected share count, of finding as few
+/// shares as it did, below which the job is flagged.
+const ANOMALY_P: f64 = 1e-3;
+
+/// Share counts for recent jobs.
+#[derive(Debug, Default)]
+pub(super) struct JobLedger {
+    next_id: u64,
+    /// Oldest first.
+    jobs: VecDeque<JobAccount>,
+    /// Task each thread is mining and since when.
+    sessions: HashMap<ThreadId, Session>,
+}
+
+#[derive(Debug)]
+struct JobAccount {
+    id: u64,
+    source_id: SourceId,
+    source: String,
+    job_id: String,
+    first_assigned: Instant,
+    tasks: u64,
+    /// Threads mining the job now
+    live: usize,
+    shares_found: u64,
+    /// Shares expected over sessions that have ended
+    shares_expected: f64,
+    shares_submitted: u64,
+    shares_accepted: u64,
+    shares_rejected: u64,
+    en2_consumed: u64,
+    /// Whether the job has been warned about
+    flagged: bool,
+}
+
+#[derive(Debug)]
+struct Session {
+    account: u64,
+    task: TaskId,
+    since: Instant,
+    shares_per_sec: f64,
+}
+
+impl Session {
+    fn expected_at(&self, now: Instant) -> f64 {
+        self.shares_per_sec * now.saturating_duration_since(self.since).as_secs_f64()
+    }
+}
+
+impl JobLedger {
+    /// Account for `job_id` from `source`, opening one if it is new.
+    ///
+    /// A job handed out again, as after a difficulty change, is the same
+    /// work and shares the account.
+    pub(super) fn open(
+        &mut self,
+        source_id: SourceId,
+        source: &str,
+        job_id: &str,
+        now: Instant,
+    ) -> u64 {
+        if let Some(account) = self
+            .jobs
+            .iter()
+            .rev()
+            .find(|a| a.source_id == source_id && a.job_id == job_id)
+        {
+            return account.id;
+        }
+        if self.jobs.len() == JOBS_KEPT {
+            self.jobs.pop_front();
+        }
+        self.next_id += 1;
+        self.jobs.push_back(JobAccount {
+            id: self.next_id,
+            source_id,
+            source: source.to_string(),
+            job_id: job_id.to_string(),
+            first_assigned: now,
+            tasks: 0,
+            live: 0,
+            shares_found: 0,
+            shares_expected: 0.0,
+            shares_submitted: 0,
+            shares_accepted: 0,
+            shares_rejected: 0,
+            en2_consumed: 0,
+            flagged: false,
+        });
+        self.next_id
+    }
+
+    /// `thread` started on `task`, from `account`, at `now`, to find
+    /// shares at `share_target` at about `hashrate`, beginning with one
+    /// fresh extranonce2 value.
+    ///
+    /// Ends the thread's time on whatever it mined before.
+    pub(super) fn mining(
+        &mut self,
+        thread: ThreadId,
+        task: TaskId,
+        account: u64,
+        share_target: Target,
+        hashrate: HashRate,
+        now: Instant,
+    ) {
+        if let Some(session) = self.sessions.remove(&thread) {
+            self.end(session, now);
+        }
+        let Some(entry) = self.account_mut(account) else {
+            return;
+        };
+        entry.tasks += 1;
+        entry.live += 1;
+        entry.en2_consumed += 1;
+        self.sessions.insert(
+            thread,
+            Session {
+                account,
+                task,
+                since: now,
+                shares_per_sec: shares_per_sec(share_target, hashrate),
+            },
+        );
+    }
+
+    /// The scheduler dropped `tasks`; threads still mining one stop.
+    pub(super) fn tasks_removed(&mut self, tasks: &[TaskId], now: Instant) {
+        let ended: Vec<ThreadId> = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| tasks.contains(&session.task))
+            .map(|(&thread, _)| thread)
+            .collect();
+        for thread in ended {
+            let session = self.sessions.remove(&thread).expect("listed session");
+            self.end(session, now);
+        }
+    }
+
+    /// A share came in on one of `account`'s tasks, `en2_mined` fresh
+    /// extranonce2 values past the last the task was known to reach.
+    pub(super) fn found(&mut self, account: u64, en2_mined: u64) {
+        if let Some(account) = self.account_mut(account) {
+            account.shares_found += 1;
+            account.en2_consumed += en2_mined;
+        }
+    }
+
+    /// A share on `account` met the job's target and went to the source.
+    pub(super) fn submitted(&mut self, account: u64) {
+        if let Some(account) = self.account_mut(account) {
+            account.shares_submitted += 1;
+        }
+    }
+
+    /// The pool's verdict on a share for `job_id` arrived.
+    pub(super) fn verdict(&mut self, source_id: SourceId, job_id: &str, accepted: bool) {
+        let Some(account) = self
+            .jobs
+            .iter_mut()
+            .rev()
+            .find(|a| a.source_id == source_id && a.job_id == job_id)
+        else {
+            return;
+        };
+        if accepted {
+            account.shares_accepted += 1;
+        } else {
+            account.shares_rejected += 1;
+        }
+    }
+
+    /// Recent jobs as of `now`, newest first.
+    pub(super) fn snapshot(&self, now: Instant) -> Vec<JobAccounting> {
+        self.jobs
+            .iter()
+            .rev()
+            .map(|account| {
+                let live: f64 = self
+                    .sessions
+                    .values()
+                    .filter(|session| session.account == account.id)
+                    .map(|session| session.expected_at(now))
+                    .sum();
+                let shares_expected = account.shares_expected + live;
+                JobAccounting {
+                    source: account.source.clone(),
+                    job_id: account.job_id.clone(),
+                    age_secs: now
+                        .saturating_duration_since(account.first_assigned)
+                        .as_secs(),
+                    tasks: account.tasks,
+                    active: account.live > 0,
+                    shares_found: account.shares_found,
+                    shares_expected,
+                    shares_submitted: account.shares_submitted,
+                    shares_accepted: account.shares_accepted,
+                    shares_rejected: account.shares_rejected,
+                    en2_consumed: account.en2_consumed,
+                    anomalous: account.live == 0
+                        && is_anomalous(account.shares_found, shares_expected),
+                }
+            })
+            .collect()
+    }
+
+    fn account_mut(&mut self, id: u64) -> Option<&mut JobAccount> {
+        self.jobs.iter_mut().rev().find(|a| a.id == id)
+    }
+
+    /// Close a thread's time on a job, judging the job once no thread is
+    /// left on it.
+    fn end(&mut self, session: Session, now: Instant) {
+        let Some(account) = self.account_mut(session.account) else {
+            return;
+        };
+        account.shares_expected += session.expected_at(now);
+        account.live -= 1;
+        if account.live > 0 || account.flagged {
+            return;
+        }
+        if is_anomalous(account.shares_found, account.shares_expected) {
+            account.flagged = true;
+            warn!(
+                source = %account.source,
+                job_id = %account.job_id,
+                found = account.shares_found,
+                expected = %format!("{:.1}", account.shares_expected),
+                "Job found far fewer shares than expected; its work may have \
+                 reached the chips corrupted"
+            );
+        }
+    }
+}
+
+/// Shares per second a thread at `hashrate` finds at `target`.
+fn shares_per_sec(target: Target, hashrate: HashRate) -> f64 {
+    let hashes_per_share = target.difficulty_float() * (u32::MAX as f64 + 1.0);
+    hashrate.0 as f64 / hashes_per_share
+}
+
+/// Whether finding `found` shares is implausible when `expected` were
+/// due, i.e. less likely than [`ANOMALY_P`] under a Poisson count.
+fn is_anomalous(found: u64, expected: f64) -> bool {
+    if found as f64 >= expected {
+        return false;
+    }
+
+    // ln P(X = found), then P(X <= found) as P(X = found) times
+    // 1 + found/λ + found(found-1)/λ² + ..., which converges since
+    // found < λ
+    let ln_factorial: f64 = (1..=found).map(|i| (i as f64).ln()).sum();
+    let ln_pmf = -expected + found as f64 * expected.ln() - ln_factorial;
+    let (mut term, mut sum) = (1.0, 1.0);
+    for i in (1..=found).rev() {
+        term *= i as f64 / expected;
+        sum += term;
+        if term < 1e-12 {
+            break;
+        }
+    }
+    ln_pmf + sum.ln() < ANOMALY_P.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use slotmap::SlotMap;
+
+    use super::*;
+    use crate::types::Difficulty;
+
+    struct Keys {
+        source: SourceId,
+        threads: Vec<ThreadId>,
+        tasks: SlotMap<TaskId, ()>,
+    }
+
+    fn keys(threads: usize) -> Keys {
+        let mut sources = SlotMap::<SourceId, ()>::new();
+        let mut thread_ids = SlotMap::<ThreadId, ()>::new();
+        Keys {
+            source: sources.insert(()),
+            threads: (0..threads).map(|_| thread_ids.insert(())).collect(),
+            tasks: SlotMap::new(),
+        }
+    }
+
+    /// One share a second at difficulty 1.
+    fn one_per_sec() -> (Target, HashRate) {
+        (Difficulty::from(1).to_target(), HashRate(1 << 32))
+    }
+
+    #[test]
+    fn poisson_threshold() {
+        assert!(!is_anomalous(0, 5.0), "e^-5 is not rare enough");
+        assert!(is_anomalous(0, 10.0));
+        assert!(!is_anomalous(85, 100.0));
+        assert!(is_anomalous(60, 100.0));
+        assert!(!is_anomalous(120, 100.0));
+        // Large counts don't underflow
+        assert!(!is_anomalous(9_900, 10_000.0));
+        assert!(is_anomalous(9_000, 10_000.0));
+    }
+
+    #[test]
+    fn an_update_ends_the_previous_tasks_time() {
+        let mut k = keys(1);
+        let mut ledger = JobLedger::default();
+        let (target, hashrate) = one_per_sec();
+        let now = Instant::now();
+
+        let first = ledger.open(k.source, "pool", "a", now);
+        let task = k.tasks.insert(());
+        ledger.mining(k.threads[0], task, first, target, hashrate, now);
+        for _ in 0..10 {
+            ledger.found(first, 0);
+        }
+        let later = now + Duration::from_secs(10);
+        let second = ledger.open(k.source, "pool", "b", later);
+        let task = k.tasks.insert(());
+        ledger.mining(k.threads[0], task, second, target, hashrate, later);
+        ledger.found(second, 2);
+
+        let jobs = ledger.snapshot(later + Duration::from_secs(5));
+        assert_eq!(jobs[0].job_id, "b");
+        assert!(jobs[0].active);
+        assert!((jobs[0].shares_expected - 5.0).abs() < 0.01);
+        assert_eq!(jobs[0].en2_consumed, 3);
+        assert_eq!(jobs[1].job_id, "a");
+        assert!(!jobs[1].active);
+        assert!((jobs[1].shares_expected - 10.0).abs() < 0.01);
+        assert_eq!(jobs[1].shares_found, 10);
+        assert!(!jobs[1].anomalous);
+    }
+
+    #[test]
+    fn a_barren_job_is_flagged_once_every_thread_leaves() {
+        let mut k = keys(2);
+        let mut ledger = JobLedger::default();
+        let (target, hashrate) = one_per_sec();
+        let now = Instant::now();
+
+        let tasks: Vec<TaskId> = (0..2).map(|_| k.tasks.insert(())).collect();
+        let account = ledger.open(k.source, "pool", "a", now);
+        assert_eq!(ledger.open(k.source, "pool", "a", now), account);
+        for (&thread, &task) in k.threads.iter().zip(&tasks) {
+            ledger.mining(thread, task, account, target, hashrate, now);
+        }
+        ledger.found(account, 0);
+        ledger.submitted(account);
+        ledger.verdict(k.source, "a", true);
+
+        let later = now + Duration::from_secs(30);
+        ledger.tasks_removed(&tasks[..1], later);
+        let jobs = ledger.snapshot(later);
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].tasks, 2);
+        assert!(jobs[0].active && !jobs[0].anomalous, "judged once done");
+
+        ledger.tasks_removed(&tasks[1..], later);
+        let jobs = ledger.snapshot(later + Duration::from_secs(60));
+        assert!((jobs[0].shares_expected - 60.0).abs() < 0.01);
+        assert_eq!((jobs[0].shares_submitted, jobs[0].shares_accepted), (1, 1));
+        assert!(jobs[0].anomalous);
+    }
+}
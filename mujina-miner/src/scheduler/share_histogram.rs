@@ -0,0 +1,100 @@
+//! Distribution of the difficulties shares achieve.
+//!
+//! A hash that meets a target of difficulty `d` meets a target of `2d`
+//! half as often, so on a log-2 scale a healthy thread's share counts
+//! halve from one bucket to the next above its share target. Comparing
+//! the shape against that is how to tell luck from a fault: a chip that
+//! reports nonces below the difficulty it was asked for shows up as a
+//! bucket under the target that should be empty.
+
+use crate::api_client::types::{ShareDifficultyBucket, ShareDifficultyHistogram};
+use crate::types::Difficulty;
+
+/// Buckets: one below difficulty 1, then one per power of two up to 2^64.
+const BUCKETS: usize = 65;
+
+/// Share counts in log-2 difficulty buckets.
+#[derive(Debug, Clone)]
+pub(crate) struct ShareHistogram {
+    /// `counts[0]` is below 1; `counts[k]` is `[2^(k-1), 2^k)`.
+    counts: [u64; BUCKETS],
+    sum: f64,
+}
+
+impl ShareHistogram {
+    pub fn new() -> Self {
+        Self {
+            counts: [0; BUCKETS],
+            sum: 0.0,
+        }
+    }
+
+    /// Count a share of `difficulty`.
+    pub fn record(&mut self, difficulty: Difficulty) {
+        let value = difficulty.as_f64();
+        self.counts[bucket_of(value)] += 1;
+        self.sum += value;
+    }
+
+    /// API view: the buckets holding any shares, lowest first.
+    pub fn snapshot(&self) -> ShareDifficultyHistogram {
+        ShareDifficultyHistogram {
+            count: self.counts.iter().sum(),
+            sum: self.sum,
+            buckets: self
+                .counts
+                .iter()
+                .enumerate()
+                .filter(|&(_, &count)| count > 0)
+                .map(|(k, &count)| ShareDifficultyBucket {
+                    min_difficulty: bucket_min(k),
+                    count,
+                })
+                .collect(),
+        }
+    }
+}
+
+fn bucket_of(difficulty: f64) -> usize {
+    if difficulty < 1.0 {
+        return 0;
+    }
+    (difficulty.log2().floor() as usize + 1).min(BUCKETS - 1)
+}
+
+/// Lowest difficulty counted in bucket `k`.
+fn bucket_min(k: usize) -> u64 {
+    match k {
+        0 => 0,
+        k => 1 << (k - 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_by_power_of_two() {
+        let mut histogram = ShareHistogram::new();
+        for difficulty in [0.5, 1.0, 1.5, 2.0, 3.0, 1024.0, 2047.0] {
+            histogram.record(Difficulty::from_f64(difficulty));
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count, 7);
+        let buckets: Vec<(u64, u64)> = snapshot
+            .buckets
+            .iter()
+            .map(|b| (b.min_difficulty, b.count))
+            .collect();
+        assert_eq!(buckets, [(0, 1), (1, 2), (2, 2), (1024, 2)]);
+        assert!((snapshot.sum - 3079.0).abs() < 1.0, "{}", snapshot.sum);
+    }
+
+    #[test]
+    fn huge_difficulties_land_in_the_top_bucket() {
+        assert_eq!(bucket_of(f64::MAX), BUCKETS - 1);
+        assert_eq!(bucket_min(BUCKETS - 1), 1 << 63);
+    }
+}
@@ -0,0 +1,577 @@
+//! Serialized jobs, shares and thread status, for other processes.
+//!
+//! Tools outside the miner---the dissector, a replay source, a fleet
+//! manager---need to read and write the miner's jobs and shares without
+//! linking its internal types, which change freely. The records here are
+//! the stable form: plain fields, hashes and byte strings as hex, numbers
+//! as numbers. Each serialized record carries the [`SCHEMA_VERSION`] it
+//! was written with and a `kind` naming what it holds, so a stream may mix
+//! kinds and a reader can refuse records newer than it understands.
+//!
+//! Fields may be added within a version, with defaults so older records
+//! still read; renaming or removing one, or changing its meaning, needs a
+//! new version.
+//!
+//! Block and merkle hashes are written in the usual reversed display
+//! order, as block explorers and `bitcoin-cli` show them. Targets are
+//! big-endian hex.
+
+use bitcoin::{BlockHash, CompactTarget, TxMerkleNode, block::Version};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api_client::types::ChipNonceReport,
+    asic::{ChipStats, hash_thread::HashThreadStatus},
+    job_source::{
+        Extranonce2, Extranonce2Range, GeneralPurposeBits, JobTemplate, MerkleRootKind,
+        MerkleRootTemplate, Share, VersionTemplate,
+    },
+    types::{Difficulty, HashRate, Target},
+};
+
+/// Version of the record formats written by this build.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Errors reading a record.
+#[derive(Debug, thiserror::Error)]
+pub enum SchemaError {
+    #[error("malformed record: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("schema version {found} is newer than supported version {SCHEMA_VERSION}")]
+    Unsupported { found: u32 },
+
+    #[error("invalid {field}: {reason}")]
+    InvalidField { field: &'static str, reason: String },
+}
+
+fn invalid(field: &'static str) -> impl Fn(String) -> SchemaError {
+    move |reason| SchemaError::InvalidField { field, reason }
+}
+
+/// One serialized record.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Record {
+    Job(JobRecord),
+    Share(ShareRecord),
+    ThreadStatus(ThreadStatusRecord),
+}
+
+/// A record as written, with the schema version alongside its fields.
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    schema: u32,
+    #[serde(flatten)]
+    record: T,
+}
+
+/// Just the version, read before the rest.
+#[derive(Deserialize)]
+struct Header {
+    schema: u32,
+}
+
+impl Record {
+    /// Serialize as one line of JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&Envelope {
+            schema: SCHEMA_VERSION,
+            record: self,
+        })
+        .expect("records serialize")
+    }
+
+    /// Read a record written by this or an earlier schema version.
+    pub fn from_json(text: &str) -> Result<Self, SchemaError> {
+        let Header { schema } = serde_json::from_str(text)?;
+        if schema > SCHEMA_VERSION {
+            return Err(SchemaError::Unsupported { found: schema });
+        }
+        let envelope: Envelope<Record> = serde_json::from_str(text)?;
+        Ok(envelope.record)
+    }
+}
+
+/// A job template.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub id: String,
+    pub prev_blockhash: String,
+    /// Base block version, with no rolled bits
+    pub version: u32,
+    /// Version bits the job may roll (BIP320), as a mask over the version
+    pub version_rolling_mask: u32,
+    pub bits: u32,
+    pub share_target: String,
+    pub time: u32,
+    pub merkle_root: MerkleRootRecord,
+}
+
+/// How a job's merkle root is found.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MerkleRootRecord {
+    Fixed {
+        merkle_root: String,
+    },
+    Computed {
+        coinbase1: String,
+        extranonce1: String,
+        extranonce2_min: u64,
+        extranonce2_max: u64,
+        extranonce2_size: u8,
+        coinbase2: String,
+        merkle_branches: Vec<String>,
+    },
+}
+
+/// A share found for a job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShareRecord {
+    pub job_id: String,
+    pub nonce: u32,
+    pub time: u32,
+    /// Full block version, rolled bits included
+    pub version: u32,
+    /// Extranonce2 value as hex, two digits per byte of its size
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extranonce2: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+    pub hash: String,
+}
+
+/// A hash thread's status.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThreadStatusRecord {
+    /// Hashes per second
+    pub hashrate: u64,
+    pub chip_shares_found: u64,
+    pub pool_shares_submitted: u64,
+    pub hardware_errors: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature_c: Option<f32>,
+    pub is_active: bool,
+    #[serde(default)]
+    pub chips: Vec<ChipStatsRecord>,
+    #[serde(default)]
+    pub nonce_map: Vec<ChipNonceReport>,
+    #[serde(default)]
+    pub status_updates_coalesced: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub duplicate_nonces: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub frequency_mismatches: u64,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub chip_resets: u64,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub quarantined: bool,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub link_errors: u64,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub link_degraded: bool,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub shares_forwarded: u64,
+    /// Difficulty the chips report nonces at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chip_difficulty: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub share_target: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_target: Option<String>,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+fn target_hex(target: Target) -> String {
+    hex::encode(target.to_be_bytes())
+}
+
+/// Read a target written as hex, or None if malformed.
+fn parse_target(text: &str) -> Option<Target> {
+    let bytes: [u8; 32] = hex::decode(text).ok()?.try_into().ok()?;
+    Some(Target::from_be_bytes(bytes))
+}
+
+/// One chip's statistics within a thread status.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChipStatsRecord {
+    pub nonces_found: u64,
+    pub jobs_sent: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_mhz: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature_c: Option<f32>,
+    /// Hashes per second, once the chip's estimate has settled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hashrate: Option<u64>,
+}
+
+fn parse_hex(field: &'static str, text: &str) -> Result<Vec<u8>, SchemaError> {
+    hex::decode(text).map_err(|e| invalid(field)(e.to_string()))
+}
+
+fn parse_hash<T: std::str::FromStr>(field: &'static str, text: &str) -> Result<T, SchemaError>
+where
+    T::Err: std::fmt::Display,
+{
+    text.parse()
+        .map_err(|e: T::Err| invalid(field)(e.to_string()))
+}
+
+/// Read an extranonce2 written as hex, two digits per byte of its size.
+pub(crate) fn parse_extranonce2(text: &str) -> Result<Extranonce2, SchemaError> {
+    let value = u64::from_str_radix(text, 16).map_err(|e| invalid("extranonce2")(e.to_string()))?;
+    let size =
+        u8::try_from(text.len() / 2).map_err(|_| invalid("extranonce2")("too long".into()))?;
+    Extranonce2::new(value, size).map_err(|e| invalid("extranonce2")(e.to_string()))
+}
+
+impl From<&JobTemplate> for JobRecord {
+    fn from(job: &JobTemplate) -> Self {
+        Self {
+            id: job.id.clone(),
+            prev_blockhash: job.prev_blockhash.to_string(),
+            version: job.version.base().to_consensus() as u32,
+            version_rolling_mask: job.version.gp_bits_mask().to_version_mask(),
+            bits: job.bits.to_consensus(),
+            share_target: target_hex(job.share_target),
+            time: job.time,
+            merkle_root: match &job.merkle_root {
+                MerkleRootKind::Fixed(root) => MerkleRootRecord::Fixed {
+                    merkle_root: root.to_string(),
+                },
+                MerkleRootKind::Computed(template) => MerkleRootRecord::Computed {
+                    coinbase1: hex::encode(&template.coinbase1),
+                    extranonce1: hex::encode(&template.extranonce1),
+                    extranonce2_min: template.extranonce2_range.min,
+                    extranonce2_max: template.extranonce2_range.max,
+                    extranonce2_size: template.extranonce2_range.size,
+                    coinbase2: hex::encode(&template.coinbase2),
+                    merkle_branches: template
+                        .merkle_branches
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect(),
+                },
+            },
+        }
+    }
+}
+
+impl TryFrom<&JobRecord> for JobTemplate {
+    type Error = SchemaError;
+
+    fn try_from(record: &JobRecord) -> Result<Self, SchemaError> {
+        let mask = record.version_rolling_mask;
+        if mask & !GeneralPurposeBits::full().to_version_mask() != 0 {
+            return Err(invalid("version_rolling_mask")(format!(
+                "{mask:#010x} has bits outside 13-28"
+            )));
+        }
+        let gp_bits = ((mask >> 13) as u16).to_be_bytes();
+        let version = VersionTemplate::new(
+            Version::from_consensus(record.version as i32),
+            GeneralPurposeBits::new(gp_bits),
+        )
+        .map_err(|e| invalid("version")(e.to_string()))?;
+
+        let share_target: [u8; 32] = parse_hex("share_target", &record.share_target)?
+            .try_into()
+            .map_err(|_| invalid("share_target")("must be 32 bytes".into()))?;
+
+        let merkle_root = match &record.merkle_root {
+            MerkleRootRecord::Fixed { merkle_root } => {
+                MerkleRootKind::Fixed(parse_hash::<TxMerkleNode>("merkle_root", merkle_root)?)
+            }
+            MerkleRootRecord::Computed {
+                coinbase1,
+                extranonce1,
+                extranonce2_min,
+                extranonce2_max,
+                extranonce2_size,
+                coinbase2,
+                merkle_branches,
+            } => MerkleRootKind::Computed(Box::new(MerkleRootTemplate::new(
+                parse_hex("coinbase1", coinbase1)?,
+                parse_hex("extranonce1", extranonce1)?,
+                Extranonce2Range::new_range(*extranonce2_min, *extranonce2_max, *extranonce2_size)
+                    .map_err(|e| invalid("extranonce2")(e.to_string()))?,
+                parse_hex("coinbase2", coinbase2)?,
+                merkle_branches
+                    .iter()
+                    .map(|branch| parse_hash("merkle_branches", branch))
+                    .collect::<Result<_, _>>()?,
+            ))),
+        };
+
+        Ok(Self {
+            id: record.id.clone(),
+            prev_blockhash: parse_hash::<BlockHash>("prev_blockhash", &record.prev_blockhash)?,
+            version,
+            bits: CompactTarget::from_consensus(record.bits),
+            share_target: Target::from_be_bytes(share_target),
+            time: record.time,
+            merkle_root,
+        })
+    }
+}
+
+impl From<&Share> for ShareRecord {
+    fn from(share: &Share) -> Self {
+        Self {
+            job_id: share.job_id.clone(),
+            nonce: share.nonce,
+            time: share.time,
+            version: share.version.to_consensus() as u32,
+            extranonce2: share.extranonce2.map(|en2| en2.to_string()),
+            device_id: share.device_id.clone(),
+            hash: share.hash.to_string(),
+        }
+    }
+}
+
+impl TryFrom<&ShareRecord> for Share {
+    type Error = SchemaError;
+
+    fn try_from(record: &ShareRecord) -> Result<Self, SchemaError> {
+        let extranonce2 = record
+            .extranonce2
+            .as_deref()
+            .map(parse_extranonce2)
+            .transpose()?;
+
+        Ok(Self {
+            job_id: record.job_id.clone(),
+            nonce: record.nonce,
+            time: record.time,
+            version: Version::from_consensus(record.version as i32),
+            extranonce2,
+            device_id: record.device_id.clone(),
+            hash: parse_hash("hash", &record.hash)?,
+        })
+    }
+}
+
+impl From<&HashThreadStatus> for ThreadStatusRecord {
+    fn from(status: &HashThreadStatus) -> Self {
+        Self {
+            hashrate: status.hashrate.0,
+            chip_shares_found: status.chip_shares_found,
+            pool_shares_submitted: status.pool_shares_submitted,
+            hardware_errors: status.hardware_errors,
+            temperature_c: status.temperature_c,
+            is_active: status.is_active,
+            chips: status
+                .chips
+                .iter()
+                .map(|chip| ChipStatsRecord {
+                    nonces_found: chip.nonces_found,
+                    jobs_sent: chip.jobs_sent,
+                    frequency_mhz: chip.frequency_mhz,
+                    temperature_c: chip.temperature_c,
+                    hashrate: chip.hashrate.map(|h| h.0),
+                })
+                .collect(),
+            nonce_map: status.nonce_map.clone(),
+            status_updates_coalesced: status.status_updates_coalesced,
+            duplicate_nonces: status.duplicate_nonces,
+            frequency_mismatches: status.frequency_mismatches,
+            chip_resets: status.chip_resets,
+            quarantined: status.quarantined,
+            link_errors: status.link_errors,
+            link_degraded: status.link_degraded,
+            shares_forwarded: status.shares_forwarded,
+            chip_difficulty: status.chip_difficulty.map(Difficulty::as_u64),
+            share_target: status.share_target.map(target_hex),
+            pool_target: status.pool_target.map(target_hex),
+        }
+    }
+}
+
+impl From<&ThreadStatusRecord> for HashThreadStatus {
+    fn from(record: &ThreadStatusRecord) -> Self {
+        Self {
+            hashrate: HashRate(record.hashrate),
+            chip_shares_found: record.chip_shares_found,
+            pool_shares_submitted: record.pool_shares_submitted,
+            hardware_errors: record.hardware_errors,
+            temperature_c: record.temperature_c,
+            is_active: record.is_active,
+            chips: record
+                .chips
+                .iter()
+                .map(|chip| ChipStats {
+                    nonces_found: chip.nonces_found,
+                    jobs_sent: chip.jobs_sent,
+                    frequency_mhz: chip.frequency_mhz,
+                    temperature_c: chip.temperature_c,
+                    hashrate: chip.hashrate.map(HashRate),
+                })
+                .collect(),
+            nonce_map: record.nonce_map.clone(),
+            status_updates_coalesced: record.status_updates_coalesced,
+            duplicate_nonces: record.duplicate_nonces,
+            frequency_mismatches: record.frequency_mismatches,
+            chip_resets: record.chip_resets,
+            quarantined: record.quarantined,
+            link_errors: record.link_errors,
+            link_degraded: record.link_degraded,
+            shares_forwarded: record.shares_forwarded,
+            chip_difficulty: record.chip_difficulty.map(Difficulty::from),
+            share_target: record.share_target.as_deref().and_then(parse_target),
+            pool_target: record.pool_target.as_deref().and_then(parse_target),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job_source::test_blocks::block_881423;
+
+    fn pool_job() -> JobTemplate {
+        JobTemplate {
+            id: "4a1b".into(),
+            prev_blockhash: *block_881423::PREV_BLOCKHASH,
+            version: VersionTemplate::new(
+                Version::from_consensus(0x2000_0000),
+                GeneralPurposeBits::full(),
+            )
+            .unwrap(),
+            bits: CompactTarget::from_consensus(0x1702_8c61),
+            share_target: crate::types::Difficulty::from(1024).to_target(),
+            time: 0x6839_5e10,
+            merkle_root: MerkleRootKind::Computed(Box::new(MerkleRootTemplate::new(
+                block_881423::coinbase1_bytes().to_vec(),
+                block_881423::extranonce1_bytes().to_vec(),
+                Extranonce2Range::new(4).unwrap(),
+                block_881423::coinbase2_bytes().to_vec(),
+                block_881423::MERKLE_BRANCHES.clone(),
+            ))),
+        }
+    }
+
+    /// Version 1 records, as tools wrote them. They must keep reading.
+    const JOB_V1: &str = r#"{"schema":1,"kind":"job","id":"7","prev_blockhash":"00000000000000000001ad0bbf4ee38df9e1d5a06e14ad2b8a4c7a1d0bc0f2ba","version":536870912,"version_rolling_mask":536862720,"bits":386042977,"share_target":"00000000003fffc0000000000000000000000000000000000000000000000000","time":1748590096,"merkle_root":{"type":"fixed","merkle_root":"4e3f0ac8a7b1c2d3e4f5061728394a5b6c7d8e9fa0b1c2d3e4f5061728394a5b"}}"#;
+    const SHARE_V1: &str = r#"{"schema":1,"kind":"share","job_id":"7","nonce":305419896,"time":1748590097,"version":536928256,"extranonce2":"00000002","hash":"0000000000000a3f9a8d7b6c5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f"}"#;
+    const STATUS_V1: &str = r#"{"schema":1,"kind":"thread_status","hashrate":1200000000000,"chip_shares_found":42,"pool_shares_submitted":3,"hardware_errors":0,"temperature_c":61.5,"is_active":true,"chips":[{"nonces_found":40,"jobs_sent":12,"frequency_mhz":525,"hashrate":1100000000000}],"nonce_map":[],"status_updates_coalesced":0}"#;
+
+    #[test]
+    fn records_round_trip() {
+        let job = pool_job();
+        let record = Record::Job(JobRecord::from(&job));
+        let read = Record::from_json(&record.to_json()).unwrap();
+        assert_eq!(read, record);
+        let Record::Job(ref read) = read else {
+            unreachable!()
+        };
+        let back = JobTemplate::try_from(read).unwrap();
+        assert_eq!(JobRecord::from(&back), JobRecord::from(&job));
+        let en2 = Extranonce2::new(2, 4).unwrap();
+        assert_eq!(
+            back.compute_merkle_root(&en2).unwrap(),
+            job.compute_merkle_root(&en2).unwrap()
+        );
+
+        let share = Share {
+            job_id: job.id.clone(),
+            nonce: 0x1234_5678,
+            time: job.time + 1,
+            version: Version::from_consensus(0x2000_e000),
+            extranonce2: Some(en2),
+            device_id: Some("e2f56f9b".into()),
+            hash: *block_881423::BLOCK_HASH,
+        };
+        let record = Record::Share(ShareRecord::from(&share));
+        let Record::Share(read) = Record::from_json(&record.to_json()).unwrap() else {
+            panic!("share read back as another kind");
+        };
+        let back = Share::try_from(&read).unwrap();
+        assert_eq!(back.extranonce2, share.extranonce2);
+        assert_eq!((back.hash, back.version), (share.hash, share.version));
+        assert_eq!(back.device_id, share.device_id);
+
+        let status = HashThreadStatus {
+            hashrate: HashRate::from_terahashes(1.2),
+            chip_shares_found: 42,
+            shares_forwarded: 12,
+            chip_difficulty: Some(Difficulty::from(256)),
+            share_target: Some(Difficulty::from(1024).to_target()),
+            pool_target: Some(Difficulty::from(65536).to_target()),
+            temperature_c: Some(61.5),
+            is_active: true,
+            chips: vec![ChipStats {
+                nonces_found: 40,
+                frequency_mhz: Some(525),
+                hashrate: Some(HashRate::from_terahashes(1.1)),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let record = Record::ThreadStatus(ThreadStatusRecord::from(&status));
+        let read = Record::from_json(&record.to_json()).unwrap();
+        assert_eq!(read, record);
+        let Record::ThreadStatus(read) = read else {
+            panic!("status read back as another kind");
+        };
+        let back = HashThreadStatus::from(&read);
+        assert_eq!(back.chip_difficulty, status.chip_difficulty);
+        assert_eq!(back.share_target, status.share_target);
+        assert_eq!(back.pool_target, status.pool_target);
+    }
+
+    #[test]
+    fn version_1_records_still_read() {
+        for text in [JOB_V1, SHARE_V1, STATUS_V1] {
+            let record = Record::from_json(text).unwrap();
+            // Written back unchanged, field for field
+            let rewritten: serde_json::Value = serde_json::from_str(&record.to_json()).unwrap();
+            let original: serde_json::Value = serde_json::from_str(text).unwrap();
+            assert_eq!(rewritten, original);
+        }
+
+        let Record::Job(job) = Record::from_json(JOB_V1).unwrap() else {
+            panic!("job read back as another kind");
+        };
+        let job = JobTemplate::try_from(&job).unwrap();
+        assert_eq!(job.version.gp_bits_mask(), GeneralPurposeBits::full());
+        let Record::Share(share) = Record::from_json(SHARE_V1).unwrap() else {
+            panic!("share read back as another kind");
+        };
+        let share = Share::try_from(&share).unwrap();
+        assert_eq!(share.extranonce2, Some(Extranonce2::new(2, 4).unwrap()));
+    }
+
+    #[test]
+    fn newer_and_malformed_records_are_refused() {
+        let newer = JOB_V1.replace(r#""schema":1"#, r#""schema":2"#);
+        assert!(matches!(
+            Record::from_json(&newer),
+            Err(SchemaError::Unsupported { found: 2 })
+        ));
+        assert!(matches!(
+            Record::from_json(r#"{"kind":"job"}"#),
+            Err(SchemaError::Json(_))
+        ));
+
+        let Record::Job(mut job) = Record::from_json(JOB_V1).unwrap() else {
+            panic!("job read back as another kind");
+        };
+        job.share_target.truncate(10);
+        assert!(matches!(
+            JobTemplate::try_from(&job),
+            Err(SchemaError::InvalidField {
+                field: "share_target",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,354 @@
+//! Startup self-check.
+//!
+//! A miner that came up without its boards, or can't reach its pool,
+//! looks much like a healthy one from outside: the process runs and the
+//! API answers. The self-check rates each part the miner depends on --
+//! configuration, the API listener, USB discovery, boards and pools -- as
+//! ready, degraded or failed, so fleet monitoring can tell the cases
+//! apart. The report is logged once the miner has had [`SETTLE_TIME`] to
+//! start, and served, evaluated afresh, at `GET /api/v0/health/detail`.
+
+use std::time::Duration;
+
+use crate::api_client::types::{BoardState, SourceState};
+pub use crate::api_client::types::{ReadinessCheck, ReadinessReport, ReadinessState};
+use crate::config::Config;
+use crate::tracing::prelude::*;
+
+/// Time boards get to initialize and pools to send work before the
+/// startup report is logged.
+pub const SETTLE_TIME: Duration = Duration::from_secs(30);
+
+/// Checks settled once at startup; the rest follow live state.
+#[derive(Debug, Clone)]
+pub struct StartupChecks {
+    pub config: ReadinessCheck,
+    pub api: ReadinessCheck,
+    pub usb: ReadinessCheck,
+}
+
+impl Default for StartupChecks {
+    fn default() -> Self {
+        Self {
+            config: ready("config", "valid"),
+            api: ready("api", "listening"),
+            usb: ready("usb", "discovery disabled"),
+        }
+    }
+}
+
+impl StartupChecks {
+    /// Full report, given the current boards and sources.
+    pub fn report(&self, boards: &[BoardState], sources: &[SourceState]) -> ReadinessReport {
+        report(vec![
+            self.config.clone(),
+            self.api.clone(),
+            self.usb.clone(),
+            check_boards(boards),
+            check_pools(sources),
+        ])
+    }
+}
+
+/// Combine checks into a report rated by the worst of them.
+pub fn report(checks: Vec<ReadinessCheck>) -> ReadinessReport {
+    let state = checks
+        .iter()
+        .map(|c| c.state)
+        .max()
+        .unwrap_or(ReadinessState::Ready);
+    ReadinessReport { state, checks }
+}
+
+/// Log a report, at a level to match its state, with the report itself as
+/// JSON for log scrapers.
+pub fn log_report(report: &ReadinessReport) {
+    let json = serde_json::to_string(report).unwrap_or_default();
+    match report.state {
+        ReadinessState::Ready => info!(report = %json, "Self-check: ready"),
+        ReadinessState::Degraded => warn!(report = %json, "Self-check: degraded"),
+        ReadinessState::Failed => error!(report = %json, "Self-check: failed"),
+    }
+    for check in report
+        .checks
+        .iter()
+        .filter(|c| c.state != ReadinessState::Ready)
+    {
+        warn!(check = %check.name, state = ?check.state, "{}", check.detail);
+    }
+}
+
+fn check(name: &str, state: ReadinessState, detail: impl Into<String>) -> ReadinessCheck {
+    ReadinessCheck {
+        name: name.to_string(),
+        state,
+        detail: detail.into(),
+    }
+}
+
+fn ready(name: &str, detail: impl Into<String>) -> ReadinessCheck {
+    check(name, ReadinessState::Ready, detail)
+}
+
+/// Whether the pool URLs can be connected to.
+///
+/// Anything the config loader rejects never gets this far, so what's left
+/// is what it can't know: URLs the stratum connector won't understand,
+/// and running without a pool at all.
+pub fn check_config(config: &Config) -> ReadinessCheck {
+    let urls = [
+        ("pool.url", &config.pool.url),
+        ("solo.url", &config.solo.url),
+    ];
+    for (key, url) in urls {
+        if let Some(url) = url
+            && let Err(reason) = check_pool_url(url)
+        {
+            return check(
+                "config",
+                ReadinessState::Failed,
+                format!("{key} {url:?}: {reason}"),
+            );
+        }
+    }
+    if config.pool.url.is_none() && config.solo.url.is_none() {
+        return check(
+            "config",
+            ReadinessState::Degraded,
+            "no pool configured, mining dummy work",
+        );
+    }
+    ready("config", "valid")
+}
+
+/// Check that a pool URL is `host:port`, optionally behind a scheme the
+/// stratum connector strips.
+fn check_pool_url(url: &str) -> Result<(), String> {
+    let address = match url.split_once("://") {
+        Some(("stratum+tcp" | "tcp", address)) => address,
+        Some((scheme, _)) => return Err(format!("unsupported scheme {scheme}")),
+        None => url,
+    };
+    match address.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => Ok(()),
+        _ => Err("expected host:port".into()),
+    }
+}
+
+/// Whether the API server is listening, from the result of binding it.
+pub fn check_api(bound: Result<String, String>) -> ReadinessCheck {
+    match bound {
+        Ok(address) => ready("api", format!("listening on {address}")),
+        Err(e) => check("api", ReadinessState::Failed, format!("cannot listen: {e}")),
+    }
+}
+
+/// Whether USB discovery started, and how many supported boards it saw.
+pub fn check_usb(discovery: Result<usize, String>) -> ReadinessCheck {
+    match discovery {
+        Ok(0) => check(
+            "usb",
+            ReadinessState::Degraded,
+            "no supported boards connected",
+        ),
+        Ok(n) => ready("usb", format!("{n} supported boards connected")),
+        Err(e) => check(
+            "usb",
+            ReadinessState::Failed,
+            format!("discovery failed: {e}"),
+        ),
+    }
+}
+
+/// Whether boards have come up and started hash threads.
+pub fn check_boards(boards: &[BoardState]) -> ReadinessCheck {
+    if boards.is_empty() {
+        return check("boards", ReadinessState::Failed, "no boards");
+    }
+    let idle: Vec<&str> = boards
+        .iter()
+        .filter(|b| b.threads.is_empty())
+        .map(|b| b.name.as_str())
+        .collect();
+    if idle.len() == boards.len() {
+        return check(
+            "boards",
+            ReadinessState::Failed,
+            format!("no board is hashing: {}", idle.join(", ")),
+        );
+    }
+    if !idle.is_empty() {
+        return check(
+            "boards",
+            ReadinessState::Degraded,
+            format!("not hashing: {}", idle.join(", ")),
+        );
+    }
+    let quarantined: Vec<&str> = boards
+        .iter()
+        .filter(|b| b.threads.iter().any(|t| t.quarantined))
+        .map(|b| b.name.as_str())
+        .collect();
+    if !quarantined.is_empty() {
+        return check(
+            "boards",
+            ReadinessState::Degraded,
+            format!(
+                "chips resetting repeatedly, held at reduced frequency: {}",
+                quarantined.join(", ")
+            ),
+        );
+    }
+    let noisy: Vec<&str> = boards
+        .iter()
+        .filter(|b| b.threads.iter().any(|t| t.link_degraded))
+        .map(|b| b.name.as_str())
+        .collect();
+    if !noisy.is_empty() {
+        return check(
+            "boards",
+            ReadinessState::Degraded,
+            format!(
+                "chip link errors, running at reduced speed: {}",
+                noisy.join(", ")
+            ),
+        );
+    }
+    ready("boards", format!("{} hashing", boards.len()))
+}
+
+/// Whether the pools have sent work.
+///
+/// Sources without a URL, like the dummy source, always have work.
+pub fn check_pools(sources: &[SourceState]) -> ReadinessCheck {
+    let pools: Vec<&SourceState> = sources.iter().filter(|s| s.url.is_some()).collect();
+    if pools.is_empty() {
+        return if sources.is_empty() {
+            check("pools", ReadinessState::Failed, "no job sources")
+        } else {
+            ready("pools", "no pool configured")
+        };
+    }
+    let unreachable: Vec<&str> = pools
+        .iter()
+        .filter(|s| !s.health.connected)
+        .map(|s| s.name.as_str())
+        .collect();
+    if unreachable.len() == pools.len() {
+        return check(
+            "pools",
+            ReadinessState::Failed,
+            format!("no pool reachable: {}", unreachable.join(", ")),
+        );
+    }
+    if !unreachable.is_empty() {
+        return check(
+            "pools",
+            ReadinessState::Degraded,
+            format!("unreachable: {}", unreachable.join(", ")),
+        );
+    }
+    ready("pools", format!("{} connected", pools.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_client::types::{SourceHealthState, ThreadState};
+
+    fn board(name: &str, threads: usize) -> BoardState {
+        BoardState {
+            name: name.into(),
+            threads: (0..threads)
+                .map(|i| ThreadState {
+                    name: format!("{name}-{i}"),
+                    hashrate: 0,
+                    is_active: true,
+                    nonces: 0,
+                    hardware_errors: 0,
+                    chip_resets: 0,
+                    quarantined: false,
+                    link_errors: 0,
+                    link_degraded: false,
+                    job_dispatch: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn pool(name: &str, connected: bool) -> SourceState {
+        SourceState {
+            name: name.into(),
+            url: Some(format!("stratum+tcp://{name}:3333")),
+            health: SourceHealthState {
+                connected,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn pool_urls_are_checked() {
+        let mut config = Config::default();
+        assert_eq!(check_config(&config).state, ReadinessState::Degraded);
+
+        config.pool.url = Some("stratum+tcp://pool.example:3333".into());
+        assert_eq!(check_config(&config).state, ReadinessState::Ready);
+        config.pool.url = Some("pool.example:3333".into());
+        assert_eq!(check_config(&config).state, ReadinessState::Ready);
+
+        config.solo.url = Some("stratum+ssl://solo.example:443".into());
+        assert_eq!(check_config(&config).state, ReadinessState::Failed);
+        config.solo.url = Some("stratum+tcp://solo.example".into());
+        assert_eq!(check_config(&config).state, ReadinessState::Failed);
+    }
+
+    #[test]
+    fn boards_and_pools_degrade_before_failing() {
+        assert_eq!(check_boards(&[]).state, ReadinessState::Failed);
+        assert_eq!(check_boards(&[board("a", 0)]).state, ReadinessState::Failed);
+        assert_eq!(
+            check_boards(&[board("a", 1), board("b", 0)]).state,
+            ReadinessState::Degraded
+        );
+        assert_eq!(check_boards(&[board("a", 1)]).state, ReadinessState::Ready);
+        let mut flapping = board("a", 2);
+        flapping.threads[1].quarantined = true;
+        assert_eq!(check_boards(&[flapping]).state, ReadinessState::Degraded);
+        let mut noisy = board("a", 1);
+        noisy.threads[0].link_degraded = true;
+        assert_eq!(check_boards(&[noisy]).state, ReadinessState::Degraded);
+
+        assert_eq!(
+            check_pools(&[pool("a", false)]).state,
+            ReadinessState::Failed
+        );
+        assert_eq!(
+            check_pools(&[pool("a", true), pool("b", false)]).state,
+            ReadinessState::Degraded
+        );
+        let dummy = SourceState {
+            name: "dummy".into(),
+            ..Default::default()
+        };
+        assert_eq!(check_pools(&[dummy]).state, ReadinessState::Ready);
+    }
+
+    #[test]
+    fn report_takes_the_worst_state() {
+        let mut checks = StartupChecks::default();
+        let ok = checks.report(&[board("a", 1)], &[pool("a", true)]);
+        assert_eq!(ok.state, ReadinessState::Ready);
+        assert_eq!(ok.checks.len(), 5);
+
+        let sources = [pool("a", true), pool("b", false)];
+        let degraded = checks.report(&[board("a", 1)], &sources);
+        assert_eq!(degraded.state, ReadinessState::Degraded);
+
+        checks.api = check_api(Err("address in use".into()));
+        let failed = checks.report(&[board("a", 1)], &sources);
+        assert_eq!(failed.state, ReadinessState::Failed);
+    }
+}
@@ -0,0 +1,193 @@
+//! Readings pushed in by external systems.
+//!
+//! Not every setup has a room thermometer or a power meter on the board.
+//! Where something else does (a home-automation hub, a smart plug), it can
+//! push its readings through the API, one named sensor at a time. They are
+//! served with the miner state and as metrics, and the room temperature
+//! feeds the thermal controller's ambient compensation (see
+//! [`ThermalConfig::ambient_offset_c`](crate::asic::thermal::ThermalConfig::ambient_offset_c)).
+//!
+//! A reading counts for [`STALE_AFTER`] after it is pushed, so a system
+//! that stops pushing drops out instead of leaving a stale value behind.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::api_client::types::{ExternalSensor, SensorKind};
+
+/// Age at which a pushed reading is no longer used.
+pub const STALE_AFTER: Duration = Duration::from_secs(600);
+
+/// Longest sensor name accepted.
+pub const MAX_NAME_LEN: usize = 64;
+
+/// Sensors tracked at most; pushes for new names beyond this are refused.
+pub const MAX_SENSORS: usize = 32;
+
+/// Errors from pushing a reading.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum SensorError {
+    #[error("sensor names are 1 to {MAX_NAME_LEN} letters, digits, '-', '_' or '.'")]
+    InvalidName,
+
+    #[error("reading {0} out of range for {1:?}")]
+    OutOfRange(f64, SensorKind),
+
+    #[error("too many sensors, at most {MAX_SENSORS}")]
+    TooMany,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Reading {
+    kind: SensorKind,
+    value: f64,
+    at: Instant,
+}
+
+/// Latest reading of each external sensor.
+///
+/// Cheap to clone; clones share the same readings.
+#[derive(Debug, Clone, Default)]
+pub struct ExternalSensors {
+    readings: Arc<Mutex<BTreeMap<String, Reading>>>,
+}
+
+impl ExternalSensors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a reading from the sensor `name`, replacing its last.
+    pub fn push(
+        &self,
+        name: &str,
+        kind: SensorKind,
+        value: f64,
+        now: Instant,
+    ) -> Result<(), SensorError> {
+        let valid_name = !name.is_empty()
+            && name.len() <= MAX_NAME_LEN
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid_name {
+            return Err(SensorError::InvalidName);
+        }
+        let plausible = match kind {
+            SensorKind::AmbientTemperature => (-50.0..=80.0).contains(&value),
+            SensorKind::Power => (0.0..=100_000.0).contains(&value),
+        };
+        if !plausible {
+            return Err(SensorError::OutOfRange(value, kind));
+        }
+
+        let mut readings = self.readings.lock().unwrap();
+        expire(&mut readings, now);
+        if !readings.contains_key(name) && readings.len() >= MAX_SENSORS {
+            return Err(SensorError::TooMany);
+        }
+        readings.insert(
+            name.to_string(),
+            Reading {
+                kind,
+                value,
+                at: now,
+            },
+        );
+        Ok(())
+    }
+
+    /// Every reading still fresh at `now`, by sensor name.
+    pub fn readings(&self, now: Instant) -> Vec<ExternalSensor> {
+        let mut readings = self.readings.lock().unwrap();
+        expire(&mut readings, now);
+        readings
+            .iter()
+            .map(|(name, reading)| ExternalSensor {
+                name: name.clone(),
+                kind: reading.kind,
+                value: reading.value,
+                age_secs: now.duration_since(reading.at).as_secs(),
+            })
+            .collect()
+    }
+
+    /// Room temperature at `now`: the mean of the fresh ambient
+    /// readings, or `None` without any.
+    pub fn ambient_c(&self, now: Instant) -> Option<f32> {
+        let mut readings = self.readings.lock().unwrap();
+        expire(&mut readings, now);
+        let (sum, count) = readings
+            .values()
+            .filter(|r| r.kind == SensorKind::AmbientTemperature)
+            .fold((0.0, 0), |(sum, count), r| (sum + r.value, count + 1));
+        (count > 0).then(|| (sum / count as f64) as f32)
+    }
+}
+
+fn expire(readings: &mut BTreeMap<String, Reading>, now: Instant) {
+    readings.retain(|_, r| now.duration_since(r.at) < STALE_AFTER);
+}
+
+static SHARED: OnceLock<ExternalSensors> = OnceLock::new();
+
+/// The readings the API takes pushes into and the hash threads read.
+pub fn shared() -> &'static ExternalSensors {
+    SHARED.get_or_init(ExternalSensors::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_bad_names_and_implausible_readings() {
+        let sensors = ExternalSensors::new();
+        let now = Instant::now();
+        assert_eq!(
+            sensors.push("", SensorKind::Power, 1.0, now),
+            Err(SensorError::InvalidName)
+        );
+        assert_eq!(
+            sensors.push("room/1", SensorKind::Power, 1.0, now),
+            Err(SensorError::InvalidName)
+        );
+        assert!(matches!(
+            sensors.push("room", SensorKind::AmbientTemperature, 250.0, now),
+            Err(SensorError::OutOfRange(..))
+        ));
+        assert!(matches!(
+            sensors.push("plug", SensorKind::Power, f64::NAN, now),
+            Err(SensorError::OutOfRange(..))
+        ));
+        assert!(sensors.readings(now).is_empty());
+    }
+
+    #[test]
+    fn ambient_is_the_mean_of_fresh_readings() {
+        let sensors = ExternalSensors::new();
+        let now = Instant::now();
+        sensors
+            .push("hall", SensorKind::AmbientTemperature, 20.0, now)
+            .unwrap();
+        sensors.push("plug", SensorKind::Power, 180.0, now).unwrap();
+        let later = now + STALE_AFTER / 2;
+        sensors
+            .push("shed", SensorKind::AmbientTemperature, 30.0, later)
+            .unwrap();
+        assert_eq!(sensors.ambient_c(later), Some(25.0));
+
+        // The hall and plug readings go stale first
+        let stale = now + STALE_AFTER;
+        assert_eq!(sensors.ambient_c(stale), Some(30.0));
+        let readings = sensors.readings(stale);
+        assert_eq!(readings.len(), 1);
+        assert_eq!(readings[0].name, "shed");
+        assert_eq!(readings[0].age_secs, STALE_AFTER.as_secs() / 2);
+
+        assert_eq!(sensors.ambient_c(later + STALE_AFTER), None);
+    }
+}
@@ -0,0 +1,213 @@
+//! Audit trail of recent shares, for debugging share loss.
+//!
+//! A share passes through several hands on its way to the pool: the
+//! scheduler receives it from a hash thread and checks it against the
+//! source's target, the source queues it, sends it, and gets the pool's
+//! verdict. When shares go missing somewhere along the way, the counters
+//! at each end don't say where. When enabled, each of those steps stamps
+//! the share's entry in a [`ShareAudit`], and `GET /api/v0/shares/recent`
+//! lists the entries, so a share that stopped short shows the last stage
+//! it reached.
+//!
+//! Shares are identified by their hash. Only the most recent entries are
+//! kept; stamps for a share that has aged out are ignored.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use bitcoin::BlockHash;
+
+pub use crate::api_client::types::{ShareAuditEntry, ShareOutcome};
+use crate::types::now_ms;
+
+/// Recent share trips, shared by the scheduler, sources, and API.
+///
+/// Clones record into the same log. A disabled log ignores everything.
+#[derive(Clone, Debug, Default)]
+pub struct ShareAudit {
+    inner: Option<Arc<Mutex<Entries>>>,
+}
+
+#[derive(Debug)]
+struct Entries {
+    capacity: usize,
+    /// Shares received so far, numbering each entry.
+    received: u64,
+    /// Oldest first, with their numbers.
+    entries: VecDeque<(BlockHash, u64, ShareAuditEntry)>,
+}
+
+impl ShareAudit {
+    /// Keep the last `capacity` shares; zero disables the log.
+    pub fn new(capacity: usize) -> Self {
+        if capacity == 0 {
+            return Self::disabled();
+        }
+        Self {
+            inner: Some(Arc::new(Mutex::new(Entries {
+                capacity,
+                received: 0,
+                entries: VecDeque::with_capacity(capacity),
+            }))),
+        }
+    }
+
+    /// A log that records nothing.
+    pub fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Start an entry for a share the scheduler received from `thread`.
+    pub fn received(&self, hash: BlockHash, job_id: &str, thread: &str, source: &str) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        let mut inner = inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.entries.len() == inner.capacity {
+            inner.entries.pop_front();
+        }
+        inner.received += 1;
+        let number = inner.received;
+        inner.entries.push_back((
+            hash,
+            number,
+            ShareAuditEntry {
+                hash: hash.to_string(),
+                job_id: job_id.to_string(),
+                thread: thread.to_string(),
+                source: source.to_string(),
+                received_at_ms: now_ms(),
+                ..Default::default()
+            },
+        ));
+    }
+
+    /// The share was checked against the source's target.
+    pub fn validated(&self, hash: BlockHash, met_target: bool) {
+        self.update(hash, |entry| {
+            entry.validated_at_ms = Some(now_ms());
+            if !met_target {
+                entry.outcome = Some(ShareOutcome::BelowTarget);
+            }
+        });
+    }
+
+    /// The source queued the share for submission.
+    pub fn enqueued(&self, hash: BlockHash) {
+        self.update(hash, |entry| entry.enqueued_at_ms = Some(now_ms()));
+    }
+
+    /// The share was sent to the pool.
+    pub fn submitted(&self, hash: BlockHash) {
+        self.update(hash, |entry| entry.submitted_at_ms = Some(now_ms()));
+    }
+
+    /// The pool's verdict on the share arrived.
+    pub fn acked(&self, hash: BlockHash, accepted: bool) {
+        self.update(hash, |entry| {
+            entry.acked_at_ms = Some(now_ms());
+            entry.outcome = Some(if accepted {
+                ShareOutcome::Accepted
+            } else {
+                ShareOutcome::Rejected
+            });
+        });
+    }
+
+    /// Recorded entries, newest first.
+    pub fn recent(&self) -> Vec<ShareAuditEntry> {
+        self.numbered()
+            .into_iter()
+            .map(|(_, entry)| entry)
+            .collect()
+    }
+
+    /// Recorded entries, newest first, each with its number: one more
+    /// than the share received before it, for paging through the log.
+    pub fn numbered(&self) -> Vec<(u64, ShareAuditEntry)> {
+        let Some(inner) = &self.inner else {
+            return Vec::new();
+        };
+        let inner = inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner
+            .entries
+            .iter()
+            .rev()
+            .map(|(_, number, entry)| (*number, entry.clone()))
+            .collect()
+    }
+
+    fn update(&self, hash: BlockHash, f: impl FnOnce(&mut ShareAuditEntry)) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        let mut inner = inner.lock().unwrap_or_else(|e| e.into_inner());
+        // Stamps nearly always land on one of the newest entries
+        if let Some((_, _, entry)) = inner.entries.iter_mut().rev().find(|(h, _, _)| *h == hash) {
+            f(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+
+    fn hash(n: u8) -> BlockHash {
+        BlockHash::from_byte_array([n; 32])
+    }
+
+    #[test]
+    fn records_each_stage_of_a_trip() {
+        let audit = ShareAudit::new(8);
+        audit.received(hash(1), "job-1", "thread-0", "pool");
+        audit.validated(hash(1), true);
+        audit.enqueued(hash(1));
+        audit.submitted(hash(1));
+
+        // Lost after submission: no verdict yet
+        let entry = &audit.recent()[0];
+        assert_eq!(entry.job_id, "job-1");
+        assert!(entry.submitted_at_ms.is_some());
+        assert_eq!(entry.acked_at_ms, None);
+        assert_eq!(entry.outcome, None);
+
+        audit.acked(hash(1), false);
+        let entry = &audit.recent()[0];
+        assert!(entry.acked_at_ms.unwrap() >= entry.received_at_ms);
+        assert_eq!(entry.outcome, Some(ShareOutcome::Rejected));
+
+        audit.received(hash(2), "job-1", "thread-0", "pool");
+        audit.validated(hash(2), false);
+        assert_eq!(audit.recent()[0].outcome, Some(ShareOutcome::BelowTarget));
+        assert_eq!(audit.recent()[0].enqueued_at_ms, None);
+    }
+
+    #[test]
+    fn keeps_only_the_newest_entries() {
+        let audit = ShareAudit::new(2);
+        for n in 1..=3 {
+            audit.received(hash(n), &format!("job-{n}"), "thread-0", "pool");
+        }
+        // An aged-out share's stamps are ignored
+        audit.acked(hash(1), true);
+
+        let jobs: Vec<_> = audit.recent().into_iter().map(|e| e.job_id).collect();
+        assert_eq!(jobs, ["job-3", "job-2"]);
+        let numbers: Vec<_> = audit.numbered().into_iter().map(|(n, _)| n).collect();
+        assert_eq!(numbers, [3, 2]);
+    }
+
+    #[test]
+    fn disabled_log_records_nothing() {
+        let audit = ShareAudit::new(0);
+        assert!(!audit.is_enabled());
+        audit.received(hash(1), "job-1", "thread-0", "pool");
+        assert!(audit.recent().is_empty());
+    }
+}
@@ -0,0 +1,459 @@
+//! Proofs of accepted shares, for auditing a miner from outside.
+//!
+//! When enabled (`daemon.share_proofs`), every share the pool accepts is
+//! appended to a JSON-lines file as the fields of its block header, the
+//! hash the miner claims for it, and the difficulty it was submitted at.
+//! That is everything needed to check the work without trusting the
+//! miner: `mujina-cli verify-shares <file>` hashes each header again and
+//! checks it against the claimed hash and difficulty, so whoever pays
+//! for hosted hashrate can compare the work proven with what the pool
+//! credited.
+//!
+//! The scheduler builds each proof when it hands a share to its source,
+//! since it holds the job the header comes from; the source writes it out
+//! once the pool's verdict is in. Both reach the log through
+//! [`installed`], set up once at startup.
+
+use std::collections::{HashSet, VecDeque};
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, LineWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use bitcoin::block::{Header, Version};
+use bitcoin::{BlockHash, CompactTarget, TxMerkleNode};
+use serde::{Deserialize, Serialize};
+
+use crate::asic::hash_thread::Share;
+use crate::job_source::{JobTemplate, MerkleRootKind};
+use crate::tracing::prelude::*;
+use crate::types::{Difficulty, now_ms};
+
+/// Shares held for a verdict at once; older ones are forgotten.
+const PENDING_LEN: usize = 256;
+
+/// An accepted share, with what it takes to verify it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShareProof {
+    /// When the pool accepted the share, in milliseconds since the Unix
+    /// epoch
+    pub at_ms: u64,
+    pub source: String,
+    pub job_id: String,
+    /// Header fields, as in the block header: version with rolled bits
+    /// applied, the previous block and merkle root as hashes are
+    /// usually shown, the time, the compact target and the nonce
+    pub version: i32,
+    pub prev_hash: String,
+    pub merkle_root: String,
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+    /// Hash of the header, as the miner computed it
+    pub hash: String,
+    /// Difficulty the share was submitted at
+    pub difficulty: Difficulty,
+}
+
+/// Why a proof doesn't verify.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ProofError {
+    #[error("invalid {field} {value:?}")]
+    Field { field: &'static str, value: String },
+
+    #[error("header hashes to {computed}, not the claimed {claimed}")]
+    HashMismatch { claimed: String, computed: String },
+
+    #[error("hash achieves difficulty {achieved}, below the claimed {claimed}")]
+    BelowDifficulty {
+        achieved: Difficulty,
+        claimed: Difficulty,
+    },
+}
+
+impl ShareProof {
+    /// Proof for a share found on `template`, not yet accepted.
+    ///
+    /// None if the merkle root can't be rebuilt, such as for a share
+    /// without the extranonce2 its job needs.
+    pub fn for_share(source: &str, template: &JobTemplate, share: &Share) -> Option<Self> {
+        let merkle_root = match &template.merkle_root {
+            MerkleRootKind::Fixed(root) => *root,
+            MerkleRootKind::Computed(_) => template
+                .compute_merkle_root(share.extranonce2.as_ref()?)
+                .ok()?,
+        };
+        Some(Self {
+            at_ms: 0,
+            source: source.to_string(),
+            job_id: template.id.clone(),
+            version: share.version.to_consensus(),
+            prev_hash: template.prev_blockhash.to_string(),
+            merkle_root: merkle_root.to_string(),
+            time: share.ntime,
+            bits: template.bits.to_consensus(),
+            nonce: share.nonce,
+            hash: share.hash.to_string(),
+            difficulty: Difficulty::from_target(template.share_target),
+        })
+    }
+
+    /// The block header the proof describes.
+    pub fn header(&self) -> Result<Header, ProofError> {
+        fn field<T: std::str::FromStr>(field: &'static str, value: &str) -> Result<T, ProofError> {
+            value.parse().map_err(|_| ProofError::Field {
+                field,
+                value: value.to_string(),
+            })
+        }
+
+        Ok(Header {
+            version: Version::from_consensus(self.version),
+            prev_blockhash: field::<BlockHash>("prev_hash", &self.prev_hash)?,
+            merkle_root: field::<TxMerkleNode>("merkle_root", &self.merkle_root)?,
+            time: self.time,
+            bits: CompactTarget::from_consensus(self.bits),
+            nonce: self.nonce,
+        })
+    }
+
+    /// Hash the header again and check it against the claimed hash and
+    /// difficulty, returning the difficulty the hash achieves.
+    pub fn verify(&self) -> Result<Difficulty, ProofError> {
+        let computed = self.header()?.block_hash();
+        if computed.to_string() != self.hash {
+            return Err(ProofError::HashMismatch {
+                claimed: self.hash.clone(),
+                computed: computed.to_string(),
+            });
+        }
+        let achieved = Difficulty::from_hash(&computed);
+        if achieved < self.difficulty {
+            return Err(ProofError::BelowDifficulty {
+                achieved,
+                claimed: self.difficulty,
+            });
+        }
+        Ok(achieved)
+    }
+}
+
+/// Log of accepted shares, shared by the scheduler and sources.
+///
+/// Clones write to the same log. A disabled log ignores everything.
+/// Write errors are logged once and disable the log rather than
+/// disturbing mining.
+#[derive(Clone, Default)]
+pub struct ShareProofs {
+    inner: Option<Arc<Mutex<Inner>>>,
+}
+
+struct Inner {
+    out: Option<Box<dyn Write + Send>>,
+    /// Proofs of shares awaiting a verdict, oldest first
+    pending: VecDeque<(BlockHash, ShareProof)>,
+}
+
+impl ShareProofs {
+    /// A log that records nothing.
+    pub fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    /// Log to a file, appending to any proofs already in it.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::to_writer(LineWriter::new(file)))
+    }
+
+    /// Log to any writer, one proof per line.
+    pub fn to_writer(out: impl Write + Send + 'static) -> Self {
+        Self {
+            inner: Some(Arc::new(Mutex::new(Inner {
+                out: Some(Box::new(out)),
+                pending: VecDeque::with_capacity(PENDING_LEN),
+            }))),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    /// Hold the proof of a share handed to its source until the pool's
+    /// verdict.
+    pub fn submitted(&self, hash: BlockHash, proof: ShareProof) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        let mut inner = inner.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.pending.len() == PENDING_LEN {
+            inner.pending.pop_front();
+        }
+        inner.pending.push_back((hash, proof));
+    }
+
+    /// The pool accepted the share: write its proof out.
+    pub fn accepted(&self, hash: BlockHash) {
+        self.settle(hash, true);
+    }
+
+    /// The pool rejected the share: forget its proof.
+    pub fn rejected(&self, hash: BlockHash) {
+        self.settle(hash, false);
+    }
+
+    fn settle(&self, hash: BlockHash, accepted: bool) {
+        let Some(inner) = &self.inner else {
+            return;
+        };
+        let mut inner = inner.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(position) = inner.pending.iter().position(|(h, _)| *h == hash) else {
+            return;
+        };
+        let Some((_, mut proof)) = inner.pending.remove(position) else {
+            return;
+        };
+        if !accepted {
+            return;
+        }
+        let Some(out) = inner.out.as_mut() else {
+            return;
+        };
+
+        proof.at_ms = now_ms();
+        let result = serde_json::to_writer(&mut *out, &proof)
+            .map_err(io::Error::from)
+            .and_then(|()| out.write_all(b"\n"));
+        if let Err(e) = result {
+            warn!(error = %e, "Failed to write share proof, disabling share proofs");
+            inner.out = None;
+        }
+    }
+}
+
+impl std::fmt::Debug for ShareProofs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShareProofs")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+static PROOFS: OnceLock<ShareProofs> = OnceLock::new();
+
+/// Install the share proof log the scheduler and sources record into.
+///
+/// Called once by the daemon at startup; later calls are ignored.
+pub fn install(proofs: ShareProofs) {
+    let _ = PROOFS.set(proofs);
+}
+
+/// Share proof log installed at startup, or a disabled one if none was.
+pub fn installed() -> &'static ShareProofs {
+    PROOFS.get_or_init(ShareProofs::disabled)
+}
+
+/// Errors from reading a share proof file.
+#[derive(Debug, thiserror::Error)]
+pub enum ReadError {
+    #[error("failed to read share proofs: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("line {line}: {source}")]
+    Parse {
+        line: usize,
+        source: serde_json::Error,
+    },
+}
+
+/// Read every proof from a share proof file.
+pub fn read(reader: impl BufRead) -> Result<Vec<ShareProof>, ReadError> {
+    let mut proofs = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let proof = serde_json::from_str(&line).map_err(|source| ReadError::Parse {
+            line: index + 1,
+            source,
+        })?;
+        proofs.push(proof);
+    }
+    Ok(proofs)
+}
+
+/// Outcome of verifying a set of proofs.
+#[derive(Debug, Default, PartialEq)]
+pub struct Audit {
+    /// Proofs checked
+    pub shares: usize,
+    /// Proofs that verified, counting each hash once
+    pub verified: usize,
+    /// Sum of the claimed difficulty of the verified shares: the work
+    /// they prove, in difficulty-1 shares
+    pub difficulty_sum: f64,
+    /// First and last acceptance among the verified shares, in ms since
+    /// the Unix epoch
+    pub span_ms: Option<(u64, u64)>,
+    /// Proofs that failed, by their position in the file (from 1)
+    pub failures: Vec<(usize, String)>,
+}
+
+impl Audit {
+    pub fn is_clean(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Verify every proof, flagging any share claimed twice.
+pub fn audit(proofs: &[ShareProof]) -> Audit {
+    let mut audit = Audit {
+        shares: proofs.len(),
+        ..Default::default()
+    };
+    let mut seen = HashSet::new();
+    for (index, proof) in proofs.iter().enumerate() {
+        let number = index + 1;
+        if let Err(e) = proof.verify() {
+            audit.failures.push((number, e.to_string()));
+            continue;
+        }
+        if !seen.insert(&proof.hash) {
+            audit
+                .failures
+                .push((number, format!("share {} claimed again", proof.hash)));
+            continue;
+        }
+        audit.verified += 1;
+        audit.difficulty_sum += proof.difficulty.as_f64();
+        audit.span_ms = Some(match audit.span_ms {
+            Some((first, last)) => (first.min(proof.at_ms), last.max(proof.at_ms)),
+            None => (proof.at_ms, proof.at_ms),
+        });
+    }
+    audit
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::Network;
+    use bitcoin::constants::genesis_block;
+    use bitcoin::hashes::Hash;
+
+    use super::*;
+
+    /// Proof of the genesis block, a share of difficulty 1.
+    fn genesis_proof() -> ShareProof {
+        let header = genesis_block(Network::Bitcoin).header;
+        ShareProof {
+            at_ms: 1_000,
+            source: "pool".into(),
+            job_id: "1".into(),
+            version: header.version.to_consensus(),
+            prev_hash: header.prev_blockhash.to_string(),
+            merkle_root: header.merkle_root.to_string(),
+            time: header.time,
+            bits: header.bits.to_consensus(),
+            nonce: header.nonce,
+            hash: header.block_hash().to_string(),
+            difficulty: Difficulty::from(1),
+        }
+    }
+
+    #[test]
+    fn genuine_share_verifies_and_forgeries_dont() {
+        let proof = genesis_proof();
+        assert!(proof.verify().is_ok());
+
+        let forged = ShareProof {
+            nonce: proof.nonce + 1,
+            ..proof.clone()
+        };
+        assert!(matches!(
+            forged.verify(),
+            Err(ProofError::HashMismatch { .. })
+        ));
+
+        let inflated = ShareProof {
+            difficulty: Difficulty::from(1_000_000),
+            ..proof.clone()
+        };
+        assert!(matches!(
+            inflated.verify(),
+            Err(ProofError::BelowDifficulty { .. })
+        ));
+
+        let garbled = ShareProof {
+            prev_hash: "zz".into(),
+            ..proof
+        };
+        assert!(matches!(
+            garbled.verify(),
+            Err(ProofError::Field {
+                field: "prev_hash",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn only_accepted_shares_are_written() {
+        #[derive(Clone, Default)]
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = Buffer::default();
+        let proofs = ShareProofs::to_writer(buffer.clone());
+        let accepted = genesis_proof();
+        let accepted_hash: BlockHash = accepted.hash.parse().unwrap();
+        let rejected_hash = BlockHash::from_byte_array([7; 32]);
+        proofs.submitted(accepted_hash, accepted.clone());
+        proofs.submitted(rejected_hash, genesis_proof());
+
+        proofs.rejected(rejected_hash);
+        proofs.accepted(accepted_hash);
+        // A second verdict for the same share finds nothing pending
+        proofs.accepted(accepted_hash);
+
+        let written = buffer.0.lock().unwrap().clone();
+        let read = read(written.as_slice()).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].hash, accepted.hash);
+        assert!(read[0].at_ms > 0);
+    }
+
+    #[test]
+    fn audit_counts_work_and_flags_duplicates() {
+        let proof = genesis_proof();
+        let later = ShareProof {
+            at_ms: 5_000,
+            ..proof.clone()
+        };
+        let forged = ShareProof {
+            nonce: 0,
+            ..proof.clone()
+        };
+
+        let audit = audit(&[proof, forged, later]);
+        assert_eq!(audit.shares, 3);
+        assert_eq!(audit.verified, 1);
+        assert_eq!(audit.difficulty_sum, 1.0);
+        assert_eq!(audit.span_ms, Some((1_000, 1_000)));
+        assert_eq!(audit.failures.len(), 2);
+        assert_eq!(audit.failures[0].0, 2);
+        assert!(audit.failures[1].1.contains("claimed again"));
+        assert!(!audit.is_clean());
+    }
+}
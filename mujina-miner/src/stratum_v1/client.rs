@@ -3,12 +3,16 @@
 //! This module contains the main client that manages the connection lifecycle,
 //! protocol state, and event emission.
 
+use std::collections::HashSet;
 use std::time::Duration;
 
 use super::connection::{Connection, Transport};
 use super::error::{StratumError, StratumResult};
-use super::messages::{ClientCommand, ClientEvent, JsonRpcMessage, SubmitParams};
+use super::messages::{ClientCommand, ClientEvent, JsonRpcMessage, SubmitParams, error_reason};
+use super::quirks::PoolQuirks;
+use crate::build_info;
 use tokio::sync::mpsc;
+use tokio::time::{Instant, sleep_until};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, trace, warn};
 
@@ -19,6 +23,10 @@ pub struct PoolConfig {
     pub url: String,
 
     /// Worker username
+    ///
+    /// May contain the [`BOARD_SERIAL_PLACEHOLDER`] template (e.g.,
+    /// `bc1q....{board_serial}`), in which case each board submits under
+    /// its own worker name. See [`PoolConfig::worker_name`].
     pub username: String,
 
     /// Worker password
@@ -34,11 +42,54 @@ impl Default for PoolConfig {
             url: String::new(),
             username: String::new(),
             password: String::new(),
-            user_agent: "mujina-miner/0.1.0-alpha".to_string(),
+            user_agent: build_info::default_user_agent(),
         }
     }
 }
 
+impl PoolConfig {
+    /// Worker name to use for shares found by `device`.
+    ///
+    /// Substitutes [`BOARD_SERIAL_PLACEHOLDER`] in the configured username
+    /// with the device ID. Without a device (or for the initial authorize),
+    /// the placeholder and the separator before it are dropped, so
+    /// `addr.{board_serial}` becomes plain `addr`.
+    pub fn worker_name(&self, device: Option<&str>) -> String {
+        let Some((prefix, suffix)) = self.username.split_once(BOARD_SERIAL_PLACEHOLDER) else {
+            return self.username.clone();
+        };
+        match device {
+            Some(device) => format!("{prefix}{device}{suffix}"),
+            None => {
+                let prefix = prefix.trim_end_matches(['.', '_', '-']);
+                format!("{prefix}{suffix}")
+            }
+        }
+    }
+}
+
+/// How long to wait for an answer to `mining.configure`.
+///
+/// Pools that don't know the extension mostly answer with an error, but
+/// some say nothing at all; the handshake carries on without version
+/// rolling once this has passed.
+const CONFIGURE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the pool may stay silent before the client pings it.
+///
+/// Pools send fresh work every minute or so, so a connection this quiet
+/// is either idle behind a proxy that may drop it, or already dead.
+const IDLE_PING_AFTER: Duration = Duration::from_secs(120);
+
+/// How long after a ping the pool has to say anything at all.
+///
+/// Any message counts, so a pool that answers `mining.ping` with an
+/// unknown-method error, or sends work instead, keeps the connection.
+const PING_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Username template placeholder replaced with the board's ID.
+pub const BOARD_SERIAL_PLACEHOLDER: &str = "{board_serial}";
+
 /// Stratum v1 client.
 ///
 /// Manages connection to a mining pool, handles the protocol lifecycle
@@ -69,6 +120,23 @@ pub struct StratumV1Client {
     /// Initial difficulty to suggest during the handshake (before the main
     /// event loop). Subsequent re-suggestions arrive via `ClientCommand`.
     initial_suggest_difficulty: Option<u64>,
+
+    /// Worker names authorized on this connection.
+    ///
+    /// Templated usernames produce one worker per board; each is
+    /// authorized on first use.
+    authorized_workers: HashSet<String>,
+
+    /// Notifications that arrived before the subscription was answered.
+    ///
+    /// Some pools send the first difficulty and job straight after
+    /// `mining.subscribe`, ahead of its response. They are held back and
+    /// handled after [`ClientEvent::Subscribed`], so consumers never see
+    /// work for a session they don't know the extranonce1 of yet.
+    early_notifications: Vec<(String, serde_json::Value)>,
+
+    /// Quirks of the pool the client works around
+    quirks: PoolQuirks,
 }
 
 /// Protocol state after successful subscription.
@@ -102,6 +170,9 @@ impl StratumV1Client {
             next_id: 1,
             state: None,
             initial_suggest_difficulty: None,
+            authorized_workers: HashSet::new(),
+            early_notifications: Vec::new(),
+            quirks: PoolQuirks::NONE,
         }
     }
 
@@ -121,7 +192,34 @@ impl StratumV1Client {
             next_id: 1,
             state: None,
             initial_suggest_difficulty,
+            authorized_workers: HashSet::new(),
+            early_notifications: Vec::new(),
+            quirks: PoolQuirks::NONE,
+        }
+    }
+
+    /// Work around `quirks` of the pool from the start of the session.
+    ///
+    /// Quirks the client finds on its own are reported with
+    /// [`ClientEvent::QuirksLearned`]; passing them in here on the next
+    /// connection spares the pool, and the handshake, finding them again.
+    pub fn with_quirks(mut self, quirks: PoolQuirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    /// Note quirks found during the session and report the new ones.
+    async fn learn(&mut self, found: PoolQuirks) {
+        let quirks = self.quirks.union(found);
+        if quirks == self.quirks {
+            return;
         }
+        self.quirks = quirks;
+        debug!(pool = %self.config.url, quirks = %found, "Pool quirk found");
+        self.event_tx
+            .send(ClientEvent::QuirksLearned(found))
+            .await
+            .ok();
     }
 
     /// Get next message ID and increment counter.
@@ -160,7 +258,15 @@ impl StratumV1Client {
                 tokio::select! {
                     // Read message from pool
                     result = conn.read_message() => {
-                        let msg = result?.ok_or(StratumError::Disconnected)?;
+                        let msg = match result {
+                            Ok(Some(msg)) => msg,
+                            Ok(None) => return Err(StratumError::Disconnected),
+                            Err(StratumError::InvalidMessage(msg)) => {
+                                warn!(error = %msg, "Received malformed message from pool, ignoring");
+                                continue;
+                            }
+                            Err(e) => return Err(e),
+                        };
 
                         match msg {
                             JsonRpcMessage::Response { id: resp_id, .. } if resp_id == id => {
@@ -176,18 +282,23 @@ impl StratumV1Client {
                                 method,
                                 params,
                             } => {
+                                if self.state.is_none() && method != "client.reconnect" {
+                                    trace!(method = %method, "Holding notification until subscribed");
+                                    self.early_notifications.push((method, params));
+                                    continue;
+                                }
+
                                 // Notification - handle it while waiting for our response
                                 if let Err(e) = self.handle_notification(&method, &params).await {
                                     warn!(error = %e, "Error handling notification during setup");
                                 }
                             }
                             JsonRpcMessage::Request {
-                                id: Some(_),
+                                id: Some(request_id),
                                 method,
                                 ..
                             } => {
-                                // Request with ID from server (very unusual)
-                                warn!(method = %method, "Server sent request during setup");
+                                self.answer_request(conn, request_id, &method).await?;
                             }
                         }
                     }
@@ -203,6 +314,30 @@ impl StratumV1Client {
         .map_err(|_| StratumError::Timeout)?
     }
 
+    /// Answer a request the pool sent us.
+    ///
+    /// The only one pools send in practice is `mining.ping`, which some
+    /// proxies use to check the miner is still there; anything else is
+    /// logged and left unanswered.
+    async fn answer_request(
+        &mut self,
+        conn: &mut dyn Transport,
+        id: u64,
+        method: &str,
+    ) -> StratumResult<()> {
+        if method != "mining.ping" {
+            warn!(method = %method, "Server sent request (not notification)");
+            return Ok(());
+        }
+        trace!("Answering ping from pool");
+        conn.write_message(&JsonRpcMessage::Response {
+            id,
+            result: Some(serde_json::json!("pong")),
+            error: None,
+        })
+        .await
+    }
+
     /// Configure version rolling support.
     ///
     /// Sends `mining.configure` to request version rolling capability.
@@ -217,6 +352,11 @@ impl StratumV1Client {
     ) -> StratumResult<Option<u32>> {
         use serde_json::json;
 
+        if self.quirks.no_configure {
+            debug!("Skipping mining.configure for this pool");
+            return Ok(None);
+        }
+
         // Request GP bits mask (0x1fffe000 = bits 13-28)
         let result = self
             .send_request(
@@ -226,7 +366,7 @@ impl StratumV1Client {
                     ["version-rolling"],
                     {"version-rolling.mask": "1fffe000"}
                 ]),
-                Duration::from_secs(30),
+                CONFIGURE_TIMEOUT,
             )
             .await;
 
@@ -279,6 +419,11 @@ impl StratumV1Client {
             Err(StratumError::Timeout) => {
                 // Pool didn't respond - doesn't support mining.configure
                 debug!("Pool doesn't support mining.configure (timeout)");
+                self.learn(PoolQuirks {
+                    no_configure: true,
+                    ..PoolQuirks::NONE
+                })
+                .await;
                 Ok(None)
             }
             Err(e) => Err(e), // Other errors are fatal
@@ -345,25 +490,25 @@ impl StratumV1Client {
             }
             JsonRpcMessage::Response {
                 error: Some(error), ..
-            } => Err(StratumError::SubscriptionFailed(format!("{:?}", error))),
+            } => Err(StratumError::SubscriptionFailed(error_reason(&error))),
             _ => Err(StratumError::UnexpectedResponse(
                 "Invalid subscribe response".to_string(),
             )),
         }
     }
 
-    /// Authorize with the pool.
+    /// Authorize a worker with the pool.
     ///
-    /// Sends `mining.authorize` with username and password. Uses the message
-    /// router to handle interleaved notifications.
-    async fn authorize(&mut self, conn: &mut dyn Transport) -> StratumResult<()> {
+    /// Sends `mining.authorize` with the worker name and password. Uses the
+    /// message router to handle interleaved notifications.
+    async fn authorize(&mut self, conn: &mut dyn Transport, worker: &str) -> StratumResult<()> {
         use serde_json::json;
 
         let response = self
             .send_request(
                 conn,
                 "mining.authorize",
-                json!([&self.config.username, &self.config.password]),
+                json!([worker, &self.config.password]),
                 Duration::from_secs(30),
             )
             .await?;
@@ -377,6 +522,7 @@ impl StratumV1Client {
             } => {
                 let authorized = result.as_bool().unwrap_or(false);
                 if authorized {
+                    self.authorized_workers.insert(worker.to_string());
                     Ok(())
                 } else {
                     Err(StratumError::AuthorizationFailed(
@@ -386,7 +532,7 @@ impl StratumV1Client {
             }
             JsonRpcMessage::Response {
                 error: Some(error), ..
-            } => Err(StratumError::AuthorizationFailed(format!("{:?}", error))),
+            } => Err(StratumError::AuthorizationFailed(error_reason(&error))),
             _ => Err(StratumError::UnexpectedResponse(
                 "Invalid authorize response".to_string(),
             )),
@@ -413,6 +559,14 @@ impl StratumV1Client {
     ) -> StratumResult<()> {
         use serde_json::json;
 
+        if self.quirks.no_suggest_difficulty {
+            trace!(
+                difficulty,
+                "Pool doesn't take suggest_difficulty, not sending"
+            );
+            return Ok(());
+        }
+
         let result = self
             .send_request(
                 conn,
@@ -423,6 +577,16 @@ impl StratumV1Client {
             .await;
 
         match result {
+            Ok(JsonRpcMessage::Response {
+                error: Some(error), ..
+            }) => {
+                debug!(difficulty, reason = %error_reason(&error), "Pool refused suggest_difficulty");
+                self.learn(PoolQuirks {
+                    no_suggest_difficulty: true,
+                    ..PoolQuirks::NONE
+                })
+                .await;
+            }
             Ok(_) => {
                 trace!(difficulty, "Pool acknowledged suggest_difficulty");
             }
@@ -452,9 +616,21 @@ impl StratumV1Client {
 
         let job_id = params.job_id.clone();
         let nonce = params.nonce;
+        let worker = params.username.clone();
+
+        // Per-board workers are authorized lazily, on their first share.
+        // A failure is not fatal; the pool's verdict on the share itself is
+        // what gets reported.
+        if self.config.username.contains(BOARD_SERIAL_PLACEHOLDER)
+            && !self.authorized_workers.contains(&worker)
+            && let Err(e) = self.authorize(conn, &worker).await
+        {
+            warn!(worker = %worker, error = %e, "Failed to authorize worker");
+        }
 
         // Convert to Stratum JSON format
         let submit_json = params.to_stratum_json();
+        let sent_at = tokio::time::Instant::now();
         let response = self
             .send_request(
                 conn,
@@ -463,6 +639,7 @@ impl StratumV1Client {
                 Duration::from_secs(30),
             )
             .await?;
+        let latency = sent_at.elapsed();
 
         // Parse response and emit appropriate event
         match response {
@@ -475,13 +652,20 @@ impl StratumV1Client {
                 let accepted = result.as_bool().unwrap_or(false);
                 if accepted {
                     self.event_tx
-                        .send(ClientEvent::ShareAccepted { job_id, nonce })
+                        .send(ClientEvent::ShareAccepted {
+                            job_id,
+                            nonce,
+                            worker,
+                            latency,
+                        })
                         .await
                         .map_err(|_| StratumError::Disconnected)?;
                 } else {
                     self.event_tx
                         .send(ClientEvent::ShareRejected {
                             job_id,
+                            worker,
+                            latency,
                             reason: "Pool returned false".to_string(),
                         })
                         .await
@@ -493,19 +677,13 @@ impl StratumV1Client {
                 error: Some(error), ..
             } => {
                 // Pool rejected with error message
-                // Error format: [error_code, "error message", null]
-                let reason = if let Some(arr) = error.as_array() {
-                    arr.get(1)
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("Unknown error")
-                        .to_string()
-                } else {
-                    format!("{:?}", error)
-                };
+                let reason = error_reason(&error);
 
                 self.event_tx
                     .send(ClientEvent::ShareRejected {
                         job_id,
+                        worker,
+                        latency,
                         reason: reason.clone(),
                     })
                     .await
@@ -513,9 +691,20 @@ impl StratumV1Client {
 
                 Ok(false)
             }
-            _ => Err(StratumError::UnexpectedResponse(
-                "Invalid submit response".to_string(),
-            )),
+            _ => {
+                // Both null: no verdict to go on, and no acceptance either
+                self.event_tx
+                    .send(ClientEvent::ShareRejected {
+                        job_id,
+                        worker,
+                        latency,
+                        reason: "Pool returned no result".to_string(),
+                    })
+                    .await
+                    .map_err(|_| StratumError::Disconnected)?;
+
+                Ok(false)
+            }
         }
     }
 
@@ -579,8 +768,18 @@ impl StratumV1Client {
             ));
         }
 
+        // Many pools send difficulty as a float (`512.0`, or below 1 on
+        // test pools). Fractions round up: a share target slightly harder
+        // than the pool's only loses a few shares, an easier one gets them
+        // rejected.
         let difficulty = arr[0]
             .as_u64()
+            .or_else(|| {
+                arr[0]
+                    .as_f64()
+                    .filter(|d| d.is_finite() && *d > 0.0)
+                    .map(|d| d.ceil() as u64)
+            })
             .ok_or_else(|| StratumError::InvalidMessage("difficulty not a number".to_string()))?;
 
         if let Some(state) = &mut self.state {
@@ -680,9 +879,20 @@ impl StratumV1Client {
             .await
             .map_err(|_| StratumError::Disconnected)?;
 
+        for (method, params) in std::mem::take(&mut self.early_notifications) {
+            if let Err(e) = self.handle_notification(&method, &params).await {
+                warn!(error = %e, "Error handling notification sent before subscription");
+            }
+        }
+
         // Authorize
-        self.authorize(&mut conn).await?;
+        let worker = self.config.worker_name(None);
+        self.authorize(&mut conn, &worker).await?;
         debug!("Authorized");
+        self.event_tx
+            .send(ClientEvent::Authorized { worker })
+            .await
+            .map_err(|_| StratumError::Disconnected)?;
 
         // Suggest difficulty after authorize. The source drops jobs
         // until the pool responds with a matching set_difficulty, so
@@ -694,15 +904,32 @@ impl StratumV1Client {
             }
         }
 
+        // When the pool was last heard from, and the ID of the ping sent
+        // since, if it went quiet
+        let mut last_heard = Instant::now();
+        let mut ping: Option<u64> = None;
+
         // Main event loop
         loop {
+            let deadline = match ping {
+                Some(_) => last_heard + IDLE_PING_AFTER + PING_TIMEOUT,
+                None => last_heard + IDLE_PING_AFTER,
+            };
+
             tokio::select! {
                 // Read messages from pool
                 msg = conn.read_message() => {
+                    // Anything at all, even garbage, shows the pool is there
+                    last_heard = Instant::now();
+                    let ping_id = ping.take();
+
                     match msg {
                         Ok(Some(msg)) => {
                             // Handle the message
                             match msg {
+                                JsonRpcMessage::Response { id, .. } if Some(id) == ping_id => {
+                                    trace!("Pool answered ping");
+                                }
                                 JsonRpcMessage::Request { id: None, method, params } => {
                                     // Notification
                                     if let Err(e) = self.handle_notification(&method, &params).await {
@@ -719,9 +946,9 @@ impl StratumV1Client {
                                     // This would be a stray response - log and ignore
                                     debug!(msg_id = %id, "Received unexpected response in main loop");
                                 }
-                                JsonRpcMessage::Request { id: Some(_), method, .. } => {
+                                JsonRpcMessage::Request { id: Some(id), method, .. } => {
                                     // Request with ID from server (unusual, but handle it)
-                                    warn!(method = %method, "Server sent request (not notification)");
+                                    self.answer_request(&mut conn, id, &method).await?;
                                 }
                             }
                         }
@@ -753,8 +980,14 @@ impl StratumV1Client {
                     match cmd {
                         ClientCommand::SubmitShare(params) => {
                             debug!(pool = %self.config.url, job_id = %params.job_id, "Submitting share");
-                            if let Err(e) = self.submit(&mut conn, params).await {
-                                warn!(pool = %self.config.url, error = %e, "Failed to submit share");
+                            match self.submit(&mut conn, params).await {
+                                Ok(_) => {
+                                    last_heard = Instant::now();
+                                    ping = None;
+                                }
+                                Err(e) => {
+                                    warn!(pool = %self.config.url, error = %e, "Failed to submit share");
+                                }
                             }
                             // Acceptance/rejection emitted via ShareAccepted/ShareRejected events
                         }
@@ -767,6 +1000,21 @@ impl StratumV1Client {
                     }
                 }
 
+                // The pool has gone quiet: ping it, or give up on it if
+                // it stayed quiet after the ping
+                _ = sleep_until(deadline) => {
+                    if ping.is_some() {
+                        warn!(pool = %self.config.url, "Pool silent after ping, dropping connection");
+                        self.event_tx.send(ClientEvent::Disconnected).await.ok();
+                        return Err(StratumError::Timeout);
+                    }
+                    let id = self.next_id();
+                    trace!("Pool idle, sending ping");
+                    conn.write_message(&JsonRpcMessage::request(id, "mining.ping", serde_json::json!([])))
+                        .await?;
+                    ping = Some(id);
+                }
+
                 // Shutdown signal
                 _ = self.shutdown.cancelled() => {
                     self.event_tx.send(ClientEvent::Disconnected).await.ok();
@@ -1242,7 +1490,7 @@ mod tests {
         // Verify ShareAccepted event was emitted
         let event = event_rx.try_recv().expect("Expected ShareAccepted event");
         match event {
-            ClientEvent::ShareAccepted { job_id, nonce } => {
+            ClientEvent::ShareAccepted { job_id, nonce, .. } => {
                 assert_eq!(job_id, "job123");
                 assert_eq!(nonce, 0xdeadbeef);
             }
@@ -1250,6 +1498,43 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn refused_suggest_difficulty_is_learned_and_not_resent() {
+        use super::super::connection::MockTransport;
+        use serde_json::json;
+
+        let (mut client, mut event_rx) = test_client();
+        let (mut transport, mut handle) = MockTransport::pair();
+
+        let pool = tokio::spawn(async move {
+            let msg = handle.recv().await;
+            handle.send(JsonRpcMessage::Response {
+                id: msg.id().unwrap(),
+                result: None,
+                error: Some(json!([-3, "Method not found", null])),
+            });
+            handle
+        });
+
+        client
+            .suggest_difficulty(&mut transport, 512)
+            .await
+            .unwrap();
+        let Ok(ClientEvent::QuirksLearned(quirks)) = event_rx.try_recv() else {
+            panic!("expected QuirksLearned");
+        };
+        assert!(quirks.no_suggest_difficulty);
+
+        // The next suggestion never reaches the pool
+        let mut handle = pool.await.unwrap();
+        client
+            .suggest_difficulty(&mut transport, 1024)
+            .await
+            .unwrap();
+        assert!(handle.try_recv().is_none());
+        assert!(event_rx.try_recv().is_err());
+    }
+
     #[tokio::test]
     async fn test_submit_share_rejected_with_error() {
         use super::super::connection::MockTransport;
@@ -1285,7 +1570,7 @@ mod tests {
         // Verify ShareRejected event was emitted with reason
         let event = event_rx.try_recv().expect("Expected ShareRejected event");
         match event {
-            ClientEvent::ShareRejected { job_id, reason } => {
+            ClientEvent::ShareRejected { job_id, reason, .. } => {
                 assert_eq!(job_id, "job456");
                 assert_eq!(reason, "Low difficulty share");
             }
@@ -1329,11 +1614,87 @@ mod tests {
         // Verify ShareRejected event was emitted
         let event = event_rx.try_recv().expect("Expected ShareRejected event");
         match event {
-            ClientEvent::ShareRejected { job_id, reason } => {
+            ClientEvent::ShareRejected { job_id, reason, .. } => {
                 assert_eq!(job_id, "job789");
                 assert_eq!(reason, "Pool returned false");
             }
             _ => panic!("Expected ShareRejected, got {:?}", event),
         }
     }
+
+    #[test]
+    fn worker_name_substitutes_board_serial() {
+        let config = PoolConfig {
+            username: "bc1qaddr.{board_serial}".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(config.worker_name(Some("e2f56f9b")), "bc1qaddr.e2f56f9b");
+        assert_eq!(config.worker_name(None), "bc1qaddr");
+
+        let plain = PoolConfig {
+            username: "bc1qaddr.rig".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(plain.worker_name(Some("e2f56f9b")), "bc1qaddr.rig");
+    }
+
+    #[tokio::test]
+    async fn test_submit_authorizes_templated_worker_once() {
+        use super::super::connection::MockTransport;
+        use serde_json::json;
+
+        let (event_tx, mut event_rx) = mpsc::channel(10);
+        let config = PoolConfig {
+            url: "test:3333".to_string(),
+            username: "test.{board_serial}".to_string(),
+            password: "x".to_string(),
+            user_agent: "test".to_string(),
+        };
+        let mut client = StratumV1Client::new(config, event_tx, CancellationToken::new());
+
+        let (mut transport, mut handle) = MockTransport::pair();
+
+        let pool = tokio::spawn(async move {
+            let mut methods = Vec::new();
+            for _ in 0..3 {
+                let msg = handle.recv().await;
+                if let JsonRpcMessage::Request { method, params, .. } = &msg {
+                    methods.push((method.clone(), params[0].clone()));
+                }
+                handle.send(JsonRpcMessage::Response {
+                    id: msg.id().unwrap(),
+                    result: Some(json!(true)),
+                    error: None,
+                });
+            }
+            methods
+        });
+
+        for nonce in [1, 2] {
+            let params = SubmitParams {
+                username: "test.board1".to_string(),
+                job_id: "job1".to_string(),
+                extranonce2: vec![0x01, 0x02, 0x03, 0x04],
+                ntime: 0x12345678,
+                nonce,
+                version_bits: None,
+            };
+            assert!(client.submit(&mut transport, params).await.unwrap());
+        }
+
+        let methods = pool.await.unwrap();
+        assert_eq!(
+            methods,
+            vec![
+                ("mining.authorize".to_string(), json!("test.board1")),
+                ("mining.submit".to_string(), json!("test.board1")),
+                ("mining.submit".to_string(), json!("test.board1")),
+            ]
+        );
+
+        match event_rx.try_recv().expect("Expected ShareAccepted event") {
+            ClientEvent::ShareAccepted { worker, .. } => assert_eq!(worker, "test.board1"),
+            event => panic!("Expected ShareAccepted, got {:?}", event),
+        }
+    }
 }
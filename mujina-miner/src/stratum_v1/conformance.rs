@@ -0,0 +1,498 @@
+//! Conformance of the client against the ways real pools bend Stratum v1.
+//!
+//! There is no Stratum v1 specification to test against, only a wiki page
+//! and what the popular pools happen to do. This suite scripts a pool at
+//! the line level, so the client's own decoding is exercised, and runs the
+//! full handshake and a pair of share submissions under every combination
+//! of the quirks below. Whatever the pool does, the client must hand its
+//! consumer the same story: version rolling settled, subscribed, then
+//! difficulty and work, then a verdict for each share.
+//!
+//! The quirks, and what the client does about each, are listed in
+//! `STRATUM_QUIRKS.md`.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use super::connection::{Transport, decode_line};
+use super::error::{StratumError, StratumResult};
+use super::messages::{ClientCommand, ClientEvent, JsonRpcMessage, SubmitParams};
+use super::{PoolConfig, PoolQuirks, StratumV1Client};
+
+/// How the pool treats `mining.configure`.
+#[derive(Debug, Clone, Copy)]
+enum Configure {
+    /// Grants version rolling.
+    Grants,
+    /// Answers with an error (unknown method).
+    Refuses,
+    /// Never answers.
+    Ignores,
+}
+
+/// Shape of the error a rejected share comes back with.
+#[derive(Debug, Clone, Copy)]
+enum ErrorShape {
+    /// `[23, "Low difficulty share", null]`
+    Stratum,
+    /// `{"code": 23, "message": "Low difficulty share"}`
+    JsonRpc2,
+    /// `"Low difficulty share"`
+    Bare,
+    /// `result: false` with no error.
+    FalseResult,
+    /// Both `result` and `error` null.
+    Nothing,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Quirks {
+    configure: Configure,
+    /// Difficulty and the first job arrive before the subscribe response.
+    work_before_subscribed: bool,
+    /// Response IDs echoed back as strings.
+    string_ids: bool,
+    /// Difficulty sent as a float.
+    float_difficulty: bool,
+    /// Difficulty and the first job arrive as one JSON-RPC batch.
+    batched: bool,
+    reject: ErrorShape,
+}
+
+fn matrix() -> Vec<Quirks> {
+    let mut cases = Vec::new();
+    for configure in [Configure::Grants, Configure::Refuses, Configure::Ignores] {
+        for work_before_subscribed in [false, true] {
+            for string_ids in [false, true] {
+                for float_difficulty in [false, true] {
+                    for batched in [false, true] {
+                        for reject in [
+                            ErrorShape::Stratum,
+                            ErrorShape::JsonRpc2,
+                            ErrorShape::Bare,
+                            ErrorShape::FalseResult,
+                            ErrorShape::Nothing,
+                        ] {
+                            cases.push(Quirks {
+                                configure,
+                                work_before_subscribed,
+                                string_ids,
+                                float_difficulty,
+                                batched,
+                                reject,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+    cases
+}
+
+/// Client side of a line-level link to a scripted pool.
+struct LineTransport {
+    rx: mpsc::UnboundedReceiver<String>,
+    tx: mpsc::UnboundedSender<String>,
+    pending: VecDeque<StratumResult<JsonRpcMessage>>,
+}
+
+#[async_trait]
+impl Transport for LineTransport {
+    async fn read_message(&mut self) -> StratumResult<Option<JsonRpcMessage>> {
+        loop {
+            if let Some(msg) = self.pending.pop_front() {
+                return msg.map(Some);
+            }
+            match self.rx.recv().await {
+                Some(line) => self.pending.extend(decode_line(&line)),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    async fn write_message(&mut self, msg: &JsonRpcMessage) -> StratumResult<()> {
+        let line = serde_json::to_string(msg)?;
+        self.tx.send(line).map_err(|_| StratumError::Disconnected)
+    }
+}
+
+/// Pool side of the link: reads what the client wrote, writes raw lines.
+struct ScriptedPool {
+    quirks: Quirks,
+    rx: mpsc::UnboundedReceiver<String>,
+    tx: mpsc::UnboundedSender<String>,
+}
+
+impl ScriptedPool {
+    fn link(quirks: Quirks) -> (LineTransport, Self) {
+        let (client_tx, pool_rx) = mpsc::unbounded_channel();
+        let (pool_tx, client_rx) = mpsc::unbounded_channel();
+        let transport = LineTransport {
+            rx: client_rx,
+            tx: client_tx,
+            pending: VecDeque::new(),
+        };
+        let pool = Self {
+            quirks,
+            rx: pool_rx,
+            tx: pool_tx,
+        };
+        (transport, pool)
+    }
+
+    /// Next request from the client, as (id, method).
+    async fn request(&mut self) -> (Value, String) {
+        let line = self.rx.recv().await.expect("client hung up");
+        let msg: Value = serde_json::from_str(&line).unwrap();
+        (
+            msg["id"].clone(),
+            msg["method"].as_str().unwrap().to_string(),
+        )
+    }
+
+    fn send(&self, msg: Value) {
+        self.tx.send(msg.to_string()).ok();
+    }
+
+    fn respond(&self, id: &Value, result: Value, error: Value) {
+        let id = match (self.quirks.string_ids, id) {
+            (true, Value::Number(n)) => json!(n.to_string()),
+            _ => id.clone(),
+        };
+        self.send(json!({"id": id, "result": result, "error": error}));
+    }
+
+    fn send_work(&self) {
+        let difficulty = if self.quirks.float_difficulty {
+            json!(1023.5)
+        } else {
+            json!(1024)
+        };
+        let set_difficulty =
+            json!({"id": null, "method": "mining.set_difficulty", "params": [difficulty]});
+        let notify = json!({"id": null, "method": "mining.notify", "params": [
+            "job1",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "01000000",
+            "ffffffff",
+            [],
+            "20000000",
+            "1d00ffff",
+            "65000000",
+            true,
+        ]});
+        if self.quirks.batched {
+            self.send(json!([set_difficulty, notify]));
+        } else {
+            self.send(set_difficulty);
+            self.send(notify);
+        }
+    }
+
+    async fn run(mut self) {
+        let (id, method) = self.request().await;
+        assert_eq!(method, "mining.configure");
+        match self.quirks.configure {
+            Configure::Grants => self.respond(
+                &id,
+                json!({"version-rolling": true, "version-rolling.mask": "1fffe000"}),
+                Value::Null,
+            ),
+            Configure::Refuses => {
+                self.respond(&id, Value::Null, json!([20, "Unknown method", null]))
+            }
+            Configure::Ignores => {}
+        }
+
+        let (id, method) = self.request().await;
+        assert_eq!(method, "mining.subscribe");
+        if self.quirks.work_before_subscribed {
+            self.send_work();
+        }
+        self.respond(&id, json!([[], "abcd0123", 4]), Value::Null);
+
+        let (id, method) = self.request().await;
+        assert_eq!(method, "mining.authorize");
+        self.respond(&id, json!(true), Value::Null);
+        if !self.quirks.work_before_subscribed {
+            self.send_work();
+        }
+
+        let (id, method) = self.request().await;
+        assert_eq!(method, "mining.submit");
+        self.respond(&id, json!(true), Value::Null);
+
+        let (id, method) = self.request().await;
+        assert_eq!(method, "mining.submit");
+        match self.quirks.reject {
+            ErrorShape::Stratum => {
+                self.respond(&id, Value::Null, json!([23, "Low difficulty share", null]))
+            }
+            ErrorShape::JsonRpc2 => self.respond(
+                &id,
+                Value::Null,
+                json!({"code": 23, "message": "Low difficulty share"}),
+            ),
+            ErrorShape::Bare => self.respond(&id, Value::Null, json!("Low difficulty share")),
+            ErrorShape::FalseResult => self.respond(&id, json!(false), Value::Null),
+            ErrorShape::Nothing => self.respond(&id, Value::Null, Value::Null),
+        }
+
+        // Hold the line open until the client is shut down
+        while self.rx.recv().await.is_some() {}
+    }
+}
+
+fn share(nonce: u32) -> ClientCommand {
+    ClientCommand::SubmitShare(SubmitParams {
+        username: "worker".to_string(),
+        job_id: "job1".to_string(),
+        extranonce2: vec![0; 4],
+        ntime: 0x6500_0000,
+        nonce,
+        version_bits: None,
+    })
+}
+
+/// Run one case, returning the client's events up to the second verdict.
+async fn run_case(quirks: Quirks) -> Vec<ClientEvent> {
+    let (transport, pool) = ScriptedPool::link(quirks);
+    let (event_tx, mut event_rx) = mpsc::channel(32);
+    let (command_tx, command_rx) = mpsc::channel(8);
+    let shutdown = CancellationToken::new();
+    let config = PoolConfig {
+        url: "scripted:3333".to_string(),
+        username: "worker".to_string(),
+        password: "x".to_string(),
+        user_agent: "test".to_string(),
+    };
+    let client =
+        StratumV1Client::with_commands(config, event_tx, command_rx, shutdown.clone(), None);
+
+    tokio::spawn(pool.run());
+    let client = tokio::spawn(client.run_with_transport(transport));
+
+    let mut events = Vec::new();
+    let collect = async {
+        while let Some(event) = event_rx.recv().await {
+            match &event {
+                ClientEvent::NewJob(_) => {
+                    command_tx.send(share(1)).await.unwrap();
+                    command_tx.send(share(2)).await.unwrap();
+                }
+                ClientEvent::ShareRejected { .. } => {
+                    events.push(event);
+                    break;
+                }
+                _ => {}
+            }
+            events.push(event);
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(60), collect)
+        .await
+        .unwrap_or_else(|_| panic!("{quirks:?}: no verdict for both shares"));
+
+    shutdown.cancel();
+    client.await.unwrap().unwrap();
+    events
+}
+
+/// One-word name of an event, for comparing sequences.
+fn kind(event: &ClientEvent) -> &'static str {
+    match event {
+        ClientEvent::VersionRollingConfigured { .. } => "configured",
+        ClientEvent::Subscribed { .. } => "subscribed",
+        ClientEvent::Authorized { .. } => "authorized",
+        ClientEvent::NewJob(_) => "job",
+        ClientEvent::DifficultyChanged(_) => "difficulty",
+        ClientEvent::VersionMaskSet(_) => "mask",
+        ClientEvent::ShareAccepted { .. } => "accepted",
+        ClientEvent::ShareRejected { .. } => "rejected",
+        ClientEvent::QuirksLearned(_) => "quirks",
+        ClientEvent::Disconnected => "disconnected",
+        ClientEvent::Error(_) => "error",
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn client_tells_the_same_story_whatever_the_pool_does() {
+    for quirks in matrix() {
+        let events = run_case(quirks).await;
+        let kinds: Vec<&str> = events
+            .iter()
+            .map(kind)
+            .filter(|&kind| kind != "quirks")
+            .collect();
+
+        // Work never arrives ahead of the session it belongs to
+        let expected: &[&str] = if quirks.work_before_subscribed {
+            &[
+                "configured",
+                "subscribed",
+                "difficulty",
+                "job",
+                "authorized",
+                "accepted",
+                "rejected",
+            ]
+        } else {
+            &[
+                "configured",
+                "subscribed",
+                "authorized",
+                "difficulty",
+                "job",
+                "accepted",
+                "rejected",
+            ]
+        };
+        assert_eq!(kinds, expected, "{quirks:?}");
+
+        let Some(&ClientEvent::VersionRollingConfigured { authorized_mask }) = events
+            .iter()
+            .find(|e| matches!(e, ClientEvent::VersionRollingConfigured { .. }))
+        else {
+            unreachable!();
+        };
+        let expected_mask = matches!(quirks.configure, Configure::Grants).then_some(0x1fffe000);
+        assert_eq!(authorized_mask, expected_mask, "{quirks:?}");
+
+        // A pool that never answers configure is spared it next time
+        let learned = events.iter().find_map(|e| match e {
+            ClientEvent::QuirksLearned(quirks) => Some(*quirks),
+            _ => None,
+        });
+        let expected_learned =
+            matches!(quirks.configure, Configure::Ignores).then_some(PoolQuirks {
+                no_configure: true,
+                ..PoolQuirks::NONE
+            });
+        assert_eq!(learned, expected_learned, "{quirks:?}");
+
+        let Some(ClientEvent::DifficultyChanged(difficulty)) = events
+            .iter()
+            .find(|e| matches!(e, ClientEvent::DifficultyChanged(_)))
+        else {
+            unreachable!();
+        };
+        assert_eq!(*difficulty, 1024, "{quirks:?}");
+
+        let Some(ClientEvent::ShareRejected { reason, .. }) = events.last() else {
+            unreachable!();
+        };
+        let expected_reason = match quirks.reject {
+            ErrorShape::FalseResult => "Pool returned false",
+            ErrorShape::Nothing => "Pool returned no result",
+            _ => "Low difficulty share",
+        };
+        assert_eq!(reason, expected_reason, "{quirks:?}");
+    }
+}
+
+/// A pool with none of the quirks.
+fn plain() -> Quirks {
+    Quirks {
+        configure: Configure::Grants,
+        work_before_subscribed: false,
+        string_ids: false,
+        float_difficulty: false,
+        batched: false,
+        reject: ErrorShape::Stratum,
+    }
+}
+
+/// Take the client through configure, subscribe and authorize.
+async fn handshake(pool: &mut ScriptedPool) {
+    let (id, _) = pool.request().await;
+    pool.respond(
+        &id,
+        json!({"version-rolling": true, "version-rolling.mask": "1fffe000"}),
+        Value::Null,
+    );
+    let (id, _) = pool.request().await;
+    pool.respond(&id, json!([[], "abcd0123", 4]), Value::Null);
+    let (id, method) = pool.request().await;
+    assert_eq!(method, "mining.authorize");
+    pool.respond(&id, json!(true), Value::Null);
+}
+
+#[tokio::test(start_paused = true)]
+async fn quiet_pool_is_pinged_and_dropped_if_it_stays_quiet() {
+    let (transport, mut pool) = ScriptedPool::link(plain());
+    let (event_tx, mut event_rx) = mpsc::channel(32);
+    let client = StratumV1Client::new(PoolConfig::default(), event_tx, CancellationToken::new());
+    let client = tokio::spawn(client.run_with_transport(transport));
+    handshake(&mut pool).await;
+
+    // A pool that doesn't know the ping still shows it's there by
+    // answering with an error
+    let (id, method) = pool.request().await;
+    assert_eq!(method, "mining.ping");
+    pool.respond(&id, Value::Null, json!([20, "Unknown method", null]));
+
+    let (_, method) = pool.request().await;
+    assert_eq!(method, "mining.ping");
+    assert!(matches!(client.await.unwrap(), Err(StratumError::Timeout)));
+
+    let mut last = None;
+    while let Ok(event) = event_rx.try_recv() {
+        last = Some(event);
+    }
+    assert!(matches!(last, Some(ClientEvent::Disconnected)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn ping_from_the_pool_is_answered() {
+    let (transport, mut pool) = ScriptedPool::link(plain());
+    let (event_tx, _event_rx) = mpsc::channel(32);
+    let client = StratumV1Client::new(PoolConfig::default(), event_tx, CancellationToken::new());
+    let client = tokio::spawn(client.run_with_transport(transport));
+    handshake(&mut pool).await;
+
+    pool.send(json!({"id": 7, "method": "mining.ping", "params": []}));
+    let line = pool.rx.recv().await.unwrap();
+    let pong: Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(pong["id"], 7);
+    assert_eq!(pong["result"], "pong");
+    client.abort();
+}
+
+#[tokio::test(start_paused = true)]
+async fn malformed_line_during_handshake_is_skipped() {
+    let (transport, mut pool) = ScriptedPool::link(plain());
+    let (event_tx, mut event_rx) = mpsc::channel(32);
+    let client = StratumV1Client::new(PoolConfig::default(), event_tx, CancellationToken::new());
+    let client = tokio::spawn(client.run_with_transport(transport));
+
+    // An error response with a null ID parses as neither a request nor a
+    // response
+    let (id, _) = pool.request().await;
+    pool.send(json!({"id": null, "result": null, "error": [21, "Stale", null]}));
+    pool.respond(
+        &id,
+        json!({"version-rolling": true, "version-rolling.mask": "1fffe000"}),
+        Value::Null,
+    );
+    let (id, method) = pool.request().await;
+    assert_eq!(method, "mining.subscribe");
+    pool.respond(&id, json!([[], "abcd0123", 4]), Value::Null);
+
+    assert!(matches!(
+        event_rx.recv().await,
+        Some(ClientEvent::VersionRollingConfigured {
+            authorized_mask: Some(0x1fffe000)
+        })
+    ));
+    assert!(matches!(
+        event_rx.recv().await,
+        Some(ClientEvent::Subscribed { .. })
+    ));
+    client.abort();
+}
@@ -4,8 +4,22 @@
 //! wrapper around tokio's TCP stream that handles buffered reading and writing
 //! of complete JSON-RPC messages. The [`Transport`] trait abstracts message
 //! I/O, allowing channel-based mocks for deterministic testing.
+//!
+//! Some proxies and aggregators don't frame one message per line: they
+//! send a JSON-RPC batch (an array of messages) or run several objects
+//! together on one line. [`decode_line`] splits those into their messages,
+//! and the transport hands them out one at a time.
+//!
+//! A pool that goes quiet behind a NAT or a proxy can leave a connection
+//! half-open for hours. TCP keepalive is switched on for every connection
+//! so the kernel notices a dead peer even when neither side has anything
+//! to say; the client adds an application-level ping on top.
+
+use std::collections::VecDeque;
+use std::time::Duration;
 
 use async_trait::async_trait;
+use serde_json::Value;
 
 use super::error::{StratumError, StratumResult};
 use super::messages::JsonRpcMessage;
@@ -14,6 +28,15 @@ use tokio::net::TcpStream;
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tracing::{debug, trace};
 
+/// Idle time before the kernel starts probing a silent connection.
+const KEEPALIVE_IDLE: Duration = Duration::from_secs(60);
+
+/// Time between keepalive probes.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Unanswered probes before the connection is declared dead.
+const KEEPALIVE_PROBES: u32 = 6;
+
 /// Message-level I/O for Stratum protocol.
 ///
 /// Abstracts reading and writing JSON-RPC messages so the client can
@@ -43,6 +66,9 @@ pub struct Connection {
 
     /// Line buffer for reading messages
     line_buf: String,
+
+    /// Messages decoded from a line that held more than one
+    pending: VecDeque<StratumResult<JsonRpcMessage>>,
 }
 
 impl Connection {
@@ -55,6 +81,7 @@ impl Connection {
             reader: BufReader::new(read_half),
             writer: BufWriter::new(write_half),
             line_buf: String::with_capacity(4096),
+            pending: VecDeque::new(),
         }
     }
 
@@ -78,14 +105,34 @@ impl Connection {
 
         debug!("Connected to pool");
 
+        if let Err(e) = enable_keepalive(&stream) {
+            debug!(error = %e, "Failed to enable TCP keepalive");
+        }
+
         Ok(Self::new(stream))
     }
 }
 
+/// Switch on TCP keepalive with probing tighter than the kernel default
+/// of two hours idle.
+pub(crate) fn enable_keepalive(stream: &TcpStream) -> std::io::Result<()> {
+    use rustix::net::sockopt;
+
+    sockopt::set_socket_keepalive(stream, true)?;
+    sockopt::set_tcp_keepidle(stream, KEEPALIVE_IDLE)?;
+    sockopt::set_tcp_keepintvl(stream, KEEPALIVE_INTERVAL)?;
+    sockopt::set_tcp_keepcnt(stream, KEEPALIVE_PROBES)?;
+    Ok(())
+}
+
 #[async_trait]
 impl Transport for Connection {
     async fn read_message(&mut self) -> StratumResult<Option<JsonRpcMessage>> {
         loop {
+            if let Some(msg) = self.pending.pop_front() {
+                return msg.map(Some);
+            }
+
             self.line_buf.clear();
 
             let n = self
@@ -107,11 +154,7 @@ impl Transport for Connection {
 
             trace!(rx = %line, "Received message");
 
-            let msg = serde_json::from_str(line).map_err(|e| {
-                StratumError::InvalidMessage(format!("Failed to parse JSON: {}, line: {}", e, line))
-            })?;
-
-            return Ok(Some(msg));
+            self.pending.extend(decode_line(line));
         }
     }
 
@@ -127,6 +170,35 @@ impl Transport for Connection {
     }
 }
 
+/// Parse one line received from the pool into the messages it holds.
+///
+/// A line normally holds one message, but may hold a batch (a JSON array
+/// of messages) or several messages back to back. Each message decodes on
+/// its own, so one malformed entry in a batch doesn't cost the rest. Text
+/// that isn't JSON at all ends the line with an error.
+pub(super) fn decode_line(line: &str) -> Vec<StratumResult<JsonRpcMessage>> {
+    let invalid = |e: serde_json::Error| {
+        StratumError::InvalidMessage(format!("Failed to parse JSON: {}, line: {}", e, line))
+    };
+
+    let mut messages = Vec::new();
+    for value in serde_json::Deserializer::from_str(line).into_iter::<Value>() {
+        match value {
+            Ok(Value::Array(batch)) => messages.extend(
+                batch
+                    .into_iter()
+                    .map(|msg| serde_json::from_value(msg).map_err(invalid)),
+            ),
+            Ok(msg) => messages.push(serde_json::from_value(msg).map_err(invalid)),
+            Err(e) => {
+                messages.push(Err(invalid(e)));
+                break;
+            }
+        }
+    }
+    messages
+}
+
 /// Forwarding impl so `Box<dyn Transport>` satisfies `impl Transport`.
 ///
 /// `async_trait` doesn't auto-derive this, so spell it out. This lets
@@ -241,6 +313,11 @@ impl MockTransportHandle {
     pub async fn recv(&mut self) -> JsonRpcMessage {
         self.rx.recv().await.expect("transport dropped")
     }
+
+    /// Take a message the client wrote, if there is one waiting.
+    pub fn try_recv(&mut self) -> Option<JsonRpcMessage> {
+        self.rx.try_recv().ok()
+    }
 }
 
 /// Connector that pulls pre-built transports from a channel.
@@ -308,4 +385,54 @@ mod tests {
         assert_eq!(response.id(), Some(1));
         assert_eq!(response.method(), Some("test.method"));
     }
+
+    #[tokio::test]
+    async fn batched_messages_are_read_one_at_a_time() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            socket
+                .write_all(
+                    concat!(
+                        r#"[{"id":null,"method":"mining.set_difficulty","params":[512]},"#,
+                        r#"{"id":1,"result":true,"error":null}]"#,
+                        r#"{"id":2,"result":true,"error":null}"#,
+                        "\n",
+                        r#"{"id":3,"result":false,"error":null}"#,
+                        "\n",
+                    )
+                    .as_bytes(),
+                )
+                .await
+                .unwrap();
+        });
+
+        let mut conn = Connection::connect(&addr.to_string()).await.unwrap();
+        let mut messages = Vec::new();
+        while let Some(msg) = conn.read_message().await.unwrap() {
+            messages.push(msg);
+        }
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0].method(), Some("mining.set_difficulty"));
+        let ids: Vec<_> = messages[1..].iter().map(|msg| msg.id()).collect();
+        assert_eq!(ids, [Some(1), Some(2), Some(3)]);
+    }
+
+    #[test]
+    fn bad_entries_in_a_batch_fail_alone() {
+        let decoded = decode_line(
+            r#"[{"id":1,"result":true,"error":null}, 42, {"id":2,"result":true,"error":null}]"#,
+        );
+        assert_eq!(decoded.len(), 3);
+        assert!(decoded[0].is_ok());
+        assert!(matches!(decoded[1], Err(StratumError::InvalidMessage(_))));
+        assert!(decoded[2].is_ok());
+
+        let decoded = decode_line(r#"{"id":1,"result":true,"error":null} garbage"#);
+        assert_eq!(decoded.len(), 2);
+        assert!(decoded[0].is_ok());
+        assert!(decoded[1].is_err());
+    }
 }
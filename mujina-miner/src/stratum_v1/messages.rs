@@ -4,12 +4,16 @@
 //! serde for JSON serialization. Messages follow the JSON-RPC format with
 //! some Stratum-specific conventions.
 
+use std::time::Duration;
+
 use bitcoin::block::Version;
 use bitcoin::hashes::Hash;
 use bitcoin::{BlockHash, CompactTarget, TxMerkleNode};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
 use serde_json::Value;
 
+use super::quirks::PoolQuirks;
+
 /// Events emitted by the Stratum client.
 ///
 /// These events are sent via channel to the client consumer
@@ -34,6 +38,12 @@ pub enum ClientEvent {
         extranonce2_size: usize,
     },
 
+    /// Pool authorized the connection's worker
+    Authorized {
+        /// Worker name sent in mining.authorize
+        worker: String,
+    },
+
     /// New mining job received from pool
     NewJob(JobNotification),
 
@@ -49,16 +59,32 @@ pub enum ClientEvent {
         job_id: String,
         /// Nonce that was accepted
         nonce: u32,
+        /// Worker name the share was submitted under
+        worker: String,
+        /// Time from submission to the pool's response
+        latency: Duration,
     },
 
     /// Share was rejected by pool
     ShareRejected {
         /// Job ID that was rejected
         job_id: String,
+        /// Worker name the share was submitted under
+        worker: String,
+        /// Time from submission to the pool's response
+        latency: Duration,
         /// Rejection reason from pool
         reason: String,
     },
 
+    /// The pool showed quirks during this session
+    ///
+    /// Carries only the newly found ones. Consumers keep them for the
+    /// next session, see [`StratumV1Client::with_quirks`].
+    ///
+    /// [`StratumV1Client::with_quirks`]: super::StratumV1Client::with_quirks
+    QuirksLearned(PoolQuirks),
+
     /// Disconnected from pool
     Disconnected,
 
@@ -320,12 +346,17 @@ impl SubmitParams {
 /// Standard JSON-RPC libraries expect spec compliance, creating impedance
 /// mismatch. This lightweight custom type (80 lines) fits Stratum's quirks
 /// exactly without adapter layers.
+///
+/// IDs are written as numbers, but read as pools send them: some echo
+/// them back as strings (`"id": "3"`), and some leave a notification's
+/// ID out instead of sending null.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum JsonRpcMessage {
     /// Request or notification from client or server
     Request {
         /// Message ID (null for notifications)
+        #[serde(default, deserialize_with = "deserialize_optional_id")]
         id: Option<u64>,
         /// Method name (e.g., "mining.notify", "mining.subscribe")
         method: String,
@@ -336,6 +367,7 @@ pub enum JsonRpcMessage {
     /// Response to a request
     Response {
         /// Message ID matching the request
+        #[serde(deserialize_with = "deserialize_id")]
         id: u64,
         /// Result value (present on success)
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -387,6 +419,42 @@ impl JsonRpcMessage {
     }
 }
 
+fn deserialize_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    deserialize_optional_id(deserializer)?.ok_or_else(|| D::Error::custom("missing message id"))
+}
+
+fn deserialize_optional_id<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(n)) => n
+            .as_u64()
+            .map(Some)
+            .ok_or_else(|| D::Error::custom("message id not an unsigned integer")),
+        Some(Value::String(s)) => s
+            .parse()
+            .map(Some)
+            .map_err(|_| D::Error::custom("message id not numeric")),
+        Some(_) => Err(D::Error::custom("message id not a number or string")),
+    }
+}
+
+/// Human-readable reason from a response's `error` value.
+///
+/// Stratum errors are `[code, "message", traceback]`, but pools built on
+/// JSON-RPC 2.0 libraries send `{"code": ..., "message": ...}` and a few
+/// send a bare string.
+pub fn error_reason(error: &Value) -> String {
+    let message = match error {
+        Value::Array(fields) => fields.get(1).and_then(Value::as_str),
+        Value::Object(fields) => fields.get("message").and_then(Value::as_str),
+        Value::String(message) => Some(message.as_str()),
+        _ => None,
+    };
+    message.map_or_else(|| error.to_string(), str::to_string)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -43,15 +43,21 @@
 //! ```
 
 mod client;
+#[cfg(test)]
+mod conformance;
 mod connection;
 mod error;
 mod messages;
+mod probe;
+mod quirks;
 
 pub use client::{PoolConfig, StratumV1Client};
+pub(crate) use connection::{Connection, enable_keepalive};
 pub use connection::{Connector, TcpConnector, Transport};
 #[cfg(test)]
 pub(crate) use connection::{MockConnector, MockTransport, MockTransportHandle};
 pub use error::{StratumError, StratumResult};
-#[cfg(test)]
 pub(crate) use messages::JsonRpcMessage;
 pub use messages::{ClientCommand, ClientEvent, JobNotification, SubmitParams};
+pub use probe::{PROBE_DIFFICULTY, PoolProbe, probe};
+pub use quirks::PoolQuirks;
@@ -0,0 +1,265 @@
+//! One-shot pool handshake check.
+//!
+//! Before committing a pool to the config it helps to know the pool
+//! answers and what it offers. [`probe`] connects and goes through the
+//! same handshake as mining (configure, subscribe, authorize, suggest a
+//! difficulty), then waits for the first job. Nothing is hashed or
+//! submitted. `mujina-cli pool-test` prints the result.
+
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use super::client::{PoolConfig, StratumV1Client};
+use super::connection::{Connection, Transport};
+use super::error::{StratumError, StratumResult};
+use super::messages::ClientEvent;
+
+/// Difficulty suggested to the pool during a probe.
+pub const PROBE_DIFFICULTY: u64 = 1024;
+
+/// Longest a probe waits for the handshake and first job.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long after authorizing the pool gets to take up the suggested
+/// difficulty.
+const SUGGEST_WINDOW: Duration = Duration::from_secs(3);
+
+/// What a pool offered during a probe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolProbe {
+    /// Version rolling mask the pool allows, or `None` without version
+    /// rolling
+    pub version_mask: Option<u32>,
+    pub extranonce1: Vec<u8>,
+    pub extranonce2_size: usize,
+    /// Worker name the pool authorized
+    pub worker: String,
+    /// Time from connecting to the first job, or `None` if no job arrived
+    /// in time
+    pub first_job_latency: Option<Duration>,
+    /// Last difficulty the pool set
+    pub difficulty: Option<u64>,
+    /// Whether the pool switched to [`PROBE_DIFFICULTY`] once suggested
+    pub suggestion_accepted: bool,
+}
+
+/// Run the handshake against `config`'s pool and report what it offered.
+///
+/// Fails if the pool can't be reached or refuses the subscription or
+/// worker. A pool that never sends a job still yields a report.
+pub async fn probe(config: PoolConfig) -> StratumResult<PoolProbe> {
+    let started = Instant::now();
+    let conn = Connection::connect(&config.url).await?;
+    probe_with_transport(config, conn, started).await
+}
+
+async fn probe_with_transport(
+    config: PoolConfig,
+    conn: impl Transport + 'static,
+    started: Instant,
+) -> StratumResult<PoolProbe> {
+    let (event_tx, mut event_rx) = mpsc::channel(64);
+    // Kept open so the client idles instead of seeing its commands end
+    let (_command_tx, command_rx) = mpsc::channel(1);
+    let shutdown = CancellationToken::new();
+    let client = StratumV1Client::with_commands(
+        config,
+        event_tx,
+        command_rx,
+        shutdown.clone(),
+        Some(PROBE_DIFFICULTY),
+    );
+    let client_task = tokio::spawn(client.run_with_transport(conn));
+
+    let mut progress = Progress::default();
+    let finished =
+        tokio::time::timeout(PROBE_TIMEOUT, progress.collect(&mut event_rx, started)).await;
+    shutdown.cancel();
+    let client_result = client_task.await.unwrap_or(Err(StratumError::Disconnected));
+
+    match progress.into_probe() {
+        Some(probe) => Ok(probe),
+        None if finished.is_err() => Err(StratumError::Timeout),
+        None => Err(client_result.err().unwrap_or(StratumError::Disconnected)),
+    }
+}
+
+/// Handshake results gathered so far.
+#[derive(Debug, Default)]
+struct Progress {
+    version_mask: Option<u32>,
+    subscription: Option<(Vec<u8>, usize)>,
+    worker: Option<String>,
+    authorized_at: Option<Instant>,
+    first_job_latency: Option<Duration>,
+    difficulty: Option<u64>,
+    suggestion_accepted: bool,
+}
+
+impl Progress {
+    /// Record events until there is nothing left to learn or the client
+    /// stops.
+    async fn collect(&mut self, events: &mut mpsc::Receiver<ClientEvent>, started: Instant) {
+        loop {
+            let done_at = match (self.authorized_at, self.first_job_latency) {
+                (Some(_), Some(_)) if self.suggestion_accepted => return,
+                (Some(at), Some(_)) => Some(at + SUGGEST_WINDOW),
+                _ => None,
+            };
+            let event = match done_at {
+                Some(at) => match tokio::time::timeout_at(at, events.recv()).await {
+                    Ok(event) => event,
+                    Err(_) => return,
+                },
+                None => events.recv().await,
+            };
+            let Some(event) = event else {
+                return;
+            };
+            self.record(event, started);
+        }
+    }
+
+    fn record(&mut self, event: ClientEvent, started: Instant) {
+        match event {
+            ClientEvent::VersionRollingConfigured { authorized_mask } => {
+                self.version_mask = authorized_mask;
+            }
+            ClientEvent::VersionMaskSet(mask) => self.version_mask = Some(mask),
+            ClientEvent::Subscribed {
+                extranonce1,
+                extranonce2_size,
+            } => self.subscription = Some((extranonce1, extranonce2_size)),
+            ClientEvent::Authorized { worker } => {
+                self.worker = Some(worker);
+                self.authorized_at = Some(Instant::now());
+            }
+            ClientEvent::DifficultyChanged(difficulty) => {
+                self.difficulty = Some(difficulty);
+                // The pool's opening difficulty may arrive before the
+                // suggestion goes out; only later changes count
+                if self.authorized_at.is_some() && difficulty == PROBE_DIFFICULTY {
+                    self.suggestion_accepted = true;
+                }
+            }
+            ClientEvent::NewJob(_) => {
+                self.first_job_latency.get_or_insert(started.elapsed());
+            }
+            _ => {}
+        }
+    }
+
+    /// The report, if the pool got as far as authorizing the worker.
+    fn into_probe(self) -> Option<PoolProbe> {
+        let (extranonce1, extranonce2_size) = self.subscription?;
+        Some(PoolProbe {
+            version_mask: self.version_mask,
+            extranonce1,
+            extranonce2_size,
+            worker: self.worker?,
+            first_job_latency: self.first_job_latency,
+            difficulty: self.difficulty,
+            suggestion_accepted: self.suggestion_accepted,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::stratum_v1::{JsonRpcMessage, MockTransport, MockTransportHandle};
+
+    fn config() -> PoolConfig {
+        PoolConfig {
+            url: "stratum+tcp://pool.example.com:3333".into(),
+            username: "bc1qtest.{board_serial}".into(),
+            password: "x".into(),
+            ..Default::default()
+        }
+    }
+
+    fn reply(handle: &MockTransportHandle, id: u64, result: Value) {
+        handle.send(JsonRpcMessage::Response {
+            id,
+            result: Some(result),
+            error: None,
+        });
+    }
+
+    /// Answer the handshake like a pool, authorizing if `authorize`.
+    fn spawn_pool(mut handle: MockTransportHandle, authorize: bool) {
+        tokio::spawn(async move {
+            loop {
+                let JsonRpcMessage::Request {
+                    id: Some(id),
+                    method,
+                    ..
+                } = handle.recv().await
+                else {
+                    continue;
+                };
+                match method.as_str() {
+                    "mining.configure" => reply(
+                        &handle,
+                        id,
+                        json!({"version-rolling": true, "version-rolling.mask": "1fffe000"}),
+                    ),
+                    "mining.subscribe" => reply(&handle, id, json!([[], "abcd1234", 4])),
+                    "mining.authorize" => reply(&handle, id, json!(authorize)),
+                    "mining.suggest_difficulty" => {
+                        handle.send(JsonRpcMessage::notification(
+                            "mining.set_difficulty",
+                            json!([PROBE_DIFFICULTY]),
+                        ));
+                        handle.send(JsonRpcMessage::notification(
+                            "mining.notify",
+                            json!([
+                                "job-1",
+                                "0000000000000000000000000000000000000000000000000000000000000000",
+                                "aa",
+                                "bb",
+                                [],
+                                "20000000",
+                                "1d00ffff",
+                                "5a5a5a5a",
+                                true
+                            ]),
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        });
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reports_what_the_pool_offered() {
+        let (transport, handle) = MockTransport::pair();
+        spawn_pool(handle, true);
+
+        let probe = probe_with_transport(config(), transport, Instant::now())
+            .await
+            .unwrap();
+        assert_eq!(probe.version_mask, Some(0x1fffe000));
+        assert_eq!(probe.extranonce1, [0xab, 0xcd, 0x12, 0x34]);
+        assert_eq!(probe.extranonce2_size, 4);
+        assert_eq!(probe.worker, "bc1qtest");
+        assert!(probe.first_job_latency.is_some());
+        assert_eq!(probe.difficulty, Some(PROBE_DIFFICULTY));
+        assert!(probe.suggestion_accepted);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refused_worker_fails_the_probe() {
+        let (transport, handle) = MockTransport::pair();
+        spawn_pool(handle, false);
+
+        let result = probe_with_transport(config(), transport, Instant::now()).await;
+        assert!(matches!(result, Err(StratumError::AuthorizationFailed(_))));
+    }
+}
@@ -0,0 +1,222 @@
+//! Per-pool protocol quirks.
+//!
+//! Most pool differences are absorbed by the client for every pool alike
+//! (see `STRATUM_QUIRKS.md`). The ones here can't be: working around them
+//! for every pool would cost pools without the quirk something, such as a
+//! request they would have answered or a field they don't expect. So they
+//! are switched on per pool, from three places:
+//!
+//! - a profile for a known pool, matched by host ([`PoolQuirks::for_url`])
+//! - the operator's configuration
+//! - what the client learns during a session, which the job source keeps
+//!   for later sessions with the same pool
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Pools whose quirks are known in advance, by host suffix.
+const KNOWN_POOLS: &[(&str, PoolQuirks)] = &[
+    // Answers mining.suggest_difficulty with error -3 "Method not found"
+    (
+        "ocean.xyz",
+        PoolQuirks {
+            no_suggest_difficulty: true,
+            ..PoolQuirks::NONE
+        },
+    ),
+];
+
+/// Protocol behaviors adjusted for one pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct PoolQuirks {
+    /// Skip `mining.configure`; the pool ignores it and the handshake
+    /// would wait out the configure timeout on every connect.
+    pub no_configure: bool,
+
+    /// Never send `mining.suggest_difficulty`; the pool rejects or
+    /// ignores it.
+    pub no_suggest_difficulty: bool,
+
+    /// Send the `version_bits` submit parameter (as `00000000`) even
+    /// when the pool hasn't granted version rolling.
+    pub version_bits_always: bool,
+}
+
+impl PoolQuirks {
+    /// No quirks.
+    pub const NONE: Self = Self {
+        no_configure: false,
+        no_suggest_difficulty: false,
+        version_bits_always: false,
+    };
+
+    /// Names of the quirks, as used in configuration.
+    pub const NAMES: [&str; 3] = [
+        "no_configure",
+        "no_suggest_difficulty",
+        "version_bits_always",
+    ];
+
+    /// Profile for the pool at `url`, or no quirks for an unknown pool.
+    pub fn for_url(url: &str) -> Self {
+        let host = host_of(url);
+        KNOWN_POOLS
+            .iter()
+            .find(|(suffix, _)| {
+                host == *suffix
+                    || host
+                        .strip_suffix(suffix)
+                        .is_some_and(|rest| rest.ends_with('.'))
+            })
+            .map(|&(_, quirks)| quirks)
+            .unwrap_or_default()
+    }
+
+    /// Quirks set in either.
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            no_configure: self.no_configure || other.no_configure,
+            no_suggest_difficulty: self.no_suggest_difficulty || other.no_suggest_difficulty,
+            version_bits_always: self.version_bits_always || other.version_bits_always,
+        }
+    }
+
+    /// Whether no quirk is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::NONE
+    }
+
+    /// Names of the quirks set.
+    fn names(&self) -> Vec<&'static str> {
+        let flags = [
+            self.no_configure,
+            self.no_suggest_difficulty,
+            self.version_bits_always,
+        ];
+        Self::NAMES
+            .into_iter()
+            .zip(flags)
+            .filter_map(|(name, set)| set.then_some(name))
+            .collect()
+    }
+
+    fn set(&mut self, name: &str) -> Result<(), String> {
+        match name {
+            "no_configure" => self.no_configure = true,
+            "no_suggest_difficulty" => self.no_suggest_difficulty = true,
+            "version_bits_always" => self.version_bits_always = true,
+            _ => {
+                return Err(format!(
+                    "unknown quirk {name:?}, expected one of {}",
+                    Self::NAMES.join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Host part of a pool URL, lowercased.
+fn host_of(url: &str) -> String {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split('/').next().unwrap_or(rest);
+    let authority = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = authority
+        .rsplit_once(':')
+        .map_or(authority, |(host, _)| host);
+    host.to_ascii_lowercase()
+}
+
+impl fmt::Display for PoolQuirks {
+    /// Comma-separated names, or `none`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("none");
+        }
+        f.write_str(&self.names().join(","))
+    }
+}
+
+impl FromStr for PoolQuirks {
+    type Err = String;
+
+    /// Parse a comma-separated list of quirk names; empty or `none` for
+    /// no quirks.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut quirks = Self::NONE;
+        for name in s.split(',').map(str::trim) {
+            if !name.is_empty() && name != "none" {
+                quirks.set(name)?;
+            }
+        }
+        Ok(quirks)
+    }
+}
+
+impl TryFrom<Vec<String>> for PoolQuirks {
+    type Error = String;
+
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        let mut quirks = Self::NONE;
+        for name in &names {
+            quirks.set(name)?;
+        }
+        Ok(quirks)
+    }
+}
+
+impl From<PoolQuirks> for Vec<String> {
+    fn from(quirks: PoolQuirks) -> Self {
+        quirks.names().into_iter().map(String::from).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_pools_match_by_host() {
+        let ocean = PoolQuirks::for_url("stratum+tcp://mine.ocean.xyz:3334");
+        assert!(ocean.no_suggest_difficulty);
+        assert!(!ocean.no_configure);
+        assert_eq!(PoolQuirks::for_url("ocean.xyz:3334"), ocean);
+        assert_eq!(PoolQuirks::for_url("tcp://user@MINE.OCEAN.XYZ"), ocean);
+
+        // Suffix only counts at a label boundary
+        assert!(PoolQuirks::for_url("stratum+tcp://notocean.xyz:3334").is_empty());
+        assert!(PoolQuirks::for_url("stratum+tcp://pool.example.com:3333").is_empty());
+    }
+
+    #[test]
+    fn parses_and_prints_names() {
+        let quirks: PoolQuirks = " version_bits_always, no_configure ".parse().unwrap();
+        assert!(quirks.version_bits_always && quirks.no_configure);
+        assert!(!quirks.no_suggest_difficulty);
+        assert_eq!(quirks.to_string(), "no_configure,version_bits_always");
+
+        assert!("none".parse::<PoolQuirks>().unwrap().is_empty());
+        assert_eq!(PoolQuirks::NONE.to_string(), "none");
+        assert!("no_such_quirk".parse::<PoolQuirks>().is_err());
+    }
+
+    #[test]
+    fn union_keeps_every_quirk() {
+        let a = PoolQuirks {
+            no_configure: true,
+            ..PoolQuirks::NONE
+        };
+        let b = PoolQuirks {
+            version_bits_always: true,
+            ..PoolQuirks::NONE
+        };
+        let both = a.union(b);
+        assert!(both.no_configure && both.version_bits_always);
+        assert!(!both.no_suggest_difficulty);
+    }
+}
@@ -7,19 +7,30 @@
 //! The rest of program the can include `use tracing::prelude::*` for convenient
 //! access to the `trace!()`, `debug!()`, `info!()`, `warn!()`, and `error!()`
 //! macros.
+//!
+//! Long-lived tasks run inside spans naming what they serve (`board`,
+//! `hash_thread`, `source`, `scheduler`), and the scheduler wraps each share
+//! in a `share` span carrying its job ID, so one share can be followed from
+//! the thread that found it to the pool.
+//!
+//! With the `tokio-console` feature, a console-subscriber layer is installed
+//! too, serving task instrumentation to `tokio-console` on its default port.
+//! Tokio only emits that instrumentation when built with
+//! `RUSTFLAGS="--cfg tokio_unstable"`.
 
 use std::{env, fmt};
 use time::OffsetDateTime;
 use tracing::field::{Field, Visit};
 use tracing::{Event, Level, Subscriber};
 use tracing_journald;
+use tracing_subscriber::field::RecordFields;
 use tracing_subscriber::{
     filter::{EnvFilter, LevelFilter},
     fmt::{
-        FmtContext, FormatEvent, FormatFields,
-        format::{DefaultFields, Writer as FmtWriter},
+        FmtContext, FormatEvent, FormatFields, FormattedFields, format::Writer as FmtWriter,
         time::FormatTime,
     },
+    layer::Layer,
     prelude::*,
     registry::LookupSpan,
 };
@@ -32,7 +43,7 @@ use nix::libc;
 
 pub mod prelude {
     #[allow(unused_imports)]
-    pub use tracing::{debug, error, info, trace, warn};
+    pub use tracing::{Instrument, debug, error, info, info_span, trace, warn};
 }
 
 use prelude::*;
@@ -83,12 +94,18 @@ fn stderr_is_journal_stream() -> bool {
 ///
 /// If running under systemd, use journald; otherwise fall
 /// back to stdout.
-pub fn init_journald_or_stdout() {
+///
+/// `log_filter` uses `RUST_LOG` syntax and applies to stdout logging; without
+/// one, info and above are shown.
+pub fn init_journald_or_stdout(log_filter: Option<&str>) {
     #[cfg(target_os = "linux")]
     {
         if stderr_is_journal_stream() {
             if let Ok(layer) = tracing_journald::layer() {
-                tracing_subscriber::registry().with(layer).init();
+                tracing_subscriber::registry()
+                    .with(console_layer())
+                    .with(layer)
+                    .init();
                 return;
             } else {
                 error!("Failed to initialize journald logging, using stdout.");
@@ -96,29 +113,45 @@ pub fn init_journald_or_stdout() {
         }
     }
 
-    use_stdout();
+    use_stdout(log_filter);
 }
 
-// Log to stdout, filtering according to environment variable RUST_LOG,
-// overriding the default level (ERROR) to INFO.
-fn use_stdout() {
+// Log to stdout, filtering according to the configured log filter (RUST_LOG
+// syntax), overriding the default level (ERROR) to INFO.
+fn use_stdout(log_filter: Option<&str>) {
     let env_filter = EnvFilter::builder()
         .with_default_directive(LevelFilter::INFO.into())
-        .with_env_var("RUST_LOG")
-        .from_env_lossy();
+        .parse_lossy(log_filter.unwrap_or_default());
 
+    // Filter per layer, so the filter doesn't hide tokio's own spans from
+    // the console layer
     tracing_subscriber::registry()
-        .with(env_filter)
+        .with(console_layer())
         .with(
             tracing_subscriber::fmt::layer()
                 .with_timer(LocalTimer)
                 .with_target(true)
-                .fmt_fields(DefaultFields::new())
-                .event_format(CustomFormatter),
+                .fmt_fields(SpanFields)
+                .event_format(CustomFormatter)
+                .with_filter(env_filter),
         )
         .init();
 }
 
+// Serve tokio-console, when built with the feature.
+#[cfg(feature = "tokio-console")]
+fn console_layer<S>() -> Option<impl Layer<S>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    Some(console_subscriber::spawn())
+}
+
+#[cfg(not(feature = "tokio-console"))]
+fn console_layer() -> Option<tracing_subscriber::layer::Identity> {
+    None
+}
+
 /// Custom event formatter that strips crate prefix, colors the target,
 /// and displays fields on a second line for readability.
 struct CustomFormatter;
@@ -155,6 +188,24 @@ impl Visit for FieldCollector {
     }
 }
 
+/// Formats span fields as `key=value` pairs separated by commas, matching how
+/// [`CustomFormatter`] writes event fields.
+struct SpanFields;
+
+impl<'w> FormatFields<'w> for SpanFields {
+    fn format_fields<R: RecordFields>(&self, mut writer: FmtWriter<'w>, fields: R) -> fmt::Result {
+        let mut visitor = FieldCollector::new();
+        fields.record(&mut visitor);
+        for (i, (key, value)) in visitor.fields.iter().enumerate() {
+            if i > 0 {
+                write!(writer, ", ")?;
+            }
+            write!(writer, "{}={}", key, value.trim_matches('"'))?;
+        }
+        Ok(())
+    }
+}
+
 impl<S, N> FormatEvent<S, N> for CustomFormatter
 where
     S: Subscriber + for<'a> LookupSpan<'a>,
@@ -162,7 +213,7 @@ where
 {
     fn format_event(
         &self,
-        _ctx: &FmtContext<'_, S, N>,
+        ctx: &FmtContext<'_, S, N>,
         mut writer: FmtWriter<'_>,
         event: &Event<'_>,
     ) -> fmt::Result {
@@ -217,25 +268,32 @@ where
 
         // If there are structured fields, write them on a second line
         // Filter out log.* fields since they're compatibility layer metadata
-        let display_fields: Vec<_> = visitor
+        let mut display_fields: Vec<String> = visitor
             .fields
             .iter()
             .filter(|(k, _)| !k.starts_with("log."))
+            // Strip quotes from string values
+            .map(|(key, value)| format!("{}={}", key, value.trim_matches('"')))
             .collect();
 
+        // Then the fields of enclosing spans (board, thread, share...),
+        // outermost first
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                if let Some(fields) = span.extensions().get::<FormattedFields<N>>()
+                    && !fields.is_empty()
+                {
+                    display_fields.push(fields.to_string());
+                }
+            }
+        }
+
         if !display_fields.is_empty() {
             writeln!(writer)?;
             // Indent to align with module column
             // Timestamp (8 chars) + space + level (5 chars) + space = 15
             write!(writer, "\x1b[90m               ")?; // 15 spaces, bright black (dark gray)
-            for (i, (key, value)) in display_fields.iter().enumerate() {
-                if i > 0 {
-                    write!(writer, ", ")?;
-                }
-                // Strip quotes from string values
-                let clean_value = value.trim_matches('"');
-                write!(writer, "{}={}", key, clean_value)?;
-            }
+            write!(writer, "{}", display_fields.join(", "))?;
             write!(writer, "\x1b[0m")?;
         }
 
@@ -0,0 +1,229 @@
+//! Capture of the bytes crossing a live serial link.
+//!
+//! A [`CaptureTap`] attached to a [`SerialStream`](super::SerialStream)
+//! copies every byte read or written into a CSV file laid out like a
+//! Saleae Logic 2 async serial export, the input format of
+//! `mujina-dissect`. Bytes the host writes go on the CI (command input)
+//! channel and bytes it reads on the RO (response output) channel, each
+//! named with the baud rate at the time, so a capture taken on a running
+//! miner dissects like one taken with a logic analyzer on the same wires.
+//!
+//! Recording never holds up the link. Bytes are handed to a writer thread
+//! through a bounded queue, and dropped and counted when it falls behind.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, SyncSender, TrySendError},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::tracing::prelude::*;
+
+/// Chunks that can wait for the writer before new ones are dropped.
+const QUEUE_LEN: usize = 1024;
+
+/// Column header of a Logic 2 export, as the dissector reads it.
+const HEADER: &str = "name,type,start_time,duration,data,error";
+
+/// Which way bytes crossed the link, as seen from the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Written by the host: the dissector's CI channel
+    Tx,
+    /// Read by the host: the dissector's RO channel
+    Rx,
+}
+
+/// Bytes from one read or write.
+pub(crate) struct Chunk {
+    direction: Direction,
+    baud_rate: u32,
+    /// When the read or write completed
+    at: SystemTime,
+    bytes: Vec<u8>,
+}
+
+/// Tap that records the bytes crossing a serial link.
+///
+/// Clones record into the same file, which is complete once every clone
+/// has been dropped.
+#[derive(Clone)]
+pub struct CaptureTap {
+    queue: SyncSender<Chunk>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl CaptureTap {
+    /// Capture to a file, truncating it.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = File::create(path)?;
+        Self::to_writer(BufWriter::new(file))
+    }
+
+    /// Capture to any writer, from a thread of its own.
+    pub fn to_writer(out: impl Write + Send + 'static) -> io::Result<Self> {
+        let (queue, chunks) = mpsc::sync_channel(QUEUE_LEN);
+        std::thread::Builder::new()
+            .name("serial-capture".to_string())
+            .spawn(move || {
+                if let Err(e) = write_capture(out, chunks) {
+                    warn!(error = %e, "Failed to write serial capture, stopping it");
+                }
+            })?;
+        Ok(Self {
+            queue,
+            dropped: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Tap whose chunks are left in a queue, for tests to write out with
+    /// [`write_capture`] once done.
+    #[cfg(test)]
+    pub(crate) fn queued() -> (Self, mpsc::Receiver<Chunk>) {
+        let (queue, chunks) = mpsc::sync_channel(QUEUE_LEN);
+        let tap = Self {
+            queue,
+            dropped: Arc::new(AtomicU64::new(0)),
+        };
+        (tap, chunks)
+    }
+
+    /// Record bytes that just crossed the link at `baud_rate`.
+    pub fn record(&self, direction: Direction, baud_rate: u32, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        let chunk = Chunk {
+            direction,
+            baud_rate,
+            at: SystemTime::now(),
+            bytes: bytes.to_vec(),
+        };
+        match self.queue.try_send(chunk) {
+            Ok(()) => {}
+            Err(TrySendError::Full(chunk) | TrySendError::Disconnected(chunk)) => {
+                self.dropped
+                    .fetch_add(chunk.bytes.len() as u64, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Bytes left out of the capture because the writer fell behind or
+    /// stopped.
+    pub fn dropped_bytes(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Write chunks as they arrive until every tap is gone.
+pub(crate) fn write_capture(mut out: impl Write, chunks: mpsc::Receiver<Chunk>) -> io::Result<()> {
+    writeln!(out, "{HEADER}")?;
+    while let Ok(chunk) = chunks.recv() {
+        write_chunk(&mut out, &chunk)?;
+        // Catch up on the backlog before paying for a flush
+        for chunk in chunks.try_iter() {
+            write_chunk(&mut out, &chunk)?;
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// Write one row per byte.
+///
+/// Bytes are spaced a character time apart: a write's bytes go out from
+/// when it completed, a read's arrived up to when it completed.
+fn write_chunk(out: &mut impl Write, chunk: &Chunk) -> io::Result<()> {
+    let name = channel_name(chunk.direction, chunk.baud_rate);
+    // Start bit, 8 data bits, stop bit
+    let byte_time = 10.0 / f64::from(chunk.baud_rate.max(1));
+    let at = chunk
+        .at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs_f64();
+    let first = match chunk.direction {
+        Direction::Tx => at,
+        Direction::Rx => at - byte_time * chunk.bytes.len() as f64,
+    };
+
+    for (i, byte) in chunk.bytes.iter().enumerate() {
+        let start = first + byte_time * i as f64;
+        writeln!(out, "{name},data,{start:.9},{byte_time:.9},0x{byte:02X},")?;
+    }
+    Ok(())
+}
+
+/// Logic 2 analyzer name for a channel, e.g. `CI Async Serial 115k`.
+fn channel_name(direction: Direction, baud_rate: u32) -> String {
+    let channel = match direction {
+        Direction::Tx => "CI",
+        Direction::Rx => "RO",
+    };
+    let rate = match baud_rate {
+        b if b % 1_000_000 == 0 => format!("{}M", b / 1_000_000),
+        // 115200 rounds down as the dissector expects
+        b if b >= 100_000 => format!("{}k", b / 1000),
+        b => b.to_string(),
+    };
+    format!("{channel} Async Serial {rate}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_channels_as_the_dissector_does() {
+        assert_eq!(channel_name(Direction::Tx, 115_200), "CI Async Serial 115k");
+        assert_eq!(channel_name(Direction::Rx, 1_000_000), "RO Async Serial 1M");
+        assert_eq!(
+            channel_name(Direction::Tx, 3_125_000),
+            "CI Async Serial 3125k"
+        );
+    }
+
+    #[test]
+    fn records_one_row_per_byte_in_order() {
+        let (tap, chunks) = CaptureTap::queued();
+        tap.record(Direction::Tx, 115_200, &[0x55, 0xaa]);
+        tap.record(Direction::Rx, 1_000_000, &[0x13]);
+        tap.record(Direction::Rx, 1_000_000, &[]);
+        assert_eq!(tap.dropped_bytes(), 0);
+        drop(tap);
+
+        let mut out = Vec::new();
+        write_capture(&mut out, chunks).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let rows: Vec<Vec<&str>> = text.lines().map(|l| l.split(',').collect()).collect();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0].join(","), HEADER);
+        assert_eq!(rows[1][0], "CI Async Serial 115k");
+        assert_eq!(rows[1][4], "0x55");
+        assert_eq!(rows[2][4], "0xAA");
+        assert_eq!(rows[3][0], "RO Async Serial 1M");
+        assert_eq!(rows[3][4], "0x13");
+
+        // A character time apart at the link's baud rate
+        let start = |row: &[&str]| row[2].parse::<f64>().unwrap();
+        assert!((start(&rows[2]) - start(&rows[1]) - 10.0 / 115_200.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn drops_rather_than_blocking_when_full() {
+        let (queue, _chunks) = mpsc::sync_channel(1);
+        let tap = CaptureTap {
+            queue,
+            dropped: Arc::new(AtomicU64::new(0)),
+        };
+        tap.record(Direction::Tx, 115_200, &[0x01]);
+        tap.record(Direction::Tx, 115_200, &[0x02, 0x03]);
+        assert_eq!(tap.dropped_bytes(), 2);
+    }
+}
@@ -0,0 +1,293 @@
+//! Fault injection for serial links, for testing.
+//!
+//! [`FaultyReader`] wraps the read half of a link and corrupts what comes
+//! through it: flipped bits, dropped bytes, and latency spikes where the
+//! link goes quiet for a while. Putting it between a chip (real or
+//! simulated) and the frame codec shows how the host copes with a noisy
+//! cable: whether the decoder resynchronizes, whether bad frames are
+//! counted and rejected rather than turned into bogus nonces, and whether
+//! the hash thread carries on once the line is clean again.
+//!
+//! Faults are drawn from a seeded generator, so a failing run can be
+//! reproduced. Only built for tests or with the `fault-injection` feature.
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncRead, ReadBuf},
+    time::Sleep,
+};
+
+/// Largest chunk pulled from the inner reader per read.
+const CHUNK_LEN: usize = 256;
+
+/// What to inject and how often.
+///
+/// Rates are probabilities in 0.0--1.0. The default injects nothing.
+#[derive(Debug, Clone, Copy)]
+pub struct FaultConfig {
+    /// Probability that any one bit is flipped.
+    pub bit_error_rate: f64,
+
+    /// Probability that any one byte is dropped.
+    pub drop_rate: f64,
+
+    /// Probability that a read stalls for `latency_spike` first.
+    pub latency_spike_rate: f64,
+
+    /// How long a latency spike holds up the read.
+    pub latency_spike: Duration,
+
+    /// Seed for the fault generator.
+    pub seed: u64,
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self {
+            bit_error_rate: 0.0,
+            drop_rate: 0.0,
+            latency_spike_rate: 0.0,
+            latency_spike: Duration::from_millis(500),
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+}
+
+/// Counts of injected faults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    /// Bytes read from the inner reader.
+    pub bytes_read: u64,
+    pub bits_flipped: u64,
+    pub bytes_dropped: u64,
+    pub latency_spikes: u64,
+}
+
+#[derive(Default)]
+struct Shared {
+    disabled: AtomicBool,
+    bytes_read: AtomicU64,
+    bits_flipped: AtomicU64,
+    bytes_dropped: AtomicU64,
+    latency_spikes: AtomicU64,
+}
+
+/// Handle for watching and switching fault injection on a
+/// [`FaultyReader`] after it has been handed off.
+#[derive(Clone)]
+pub struct FaultControl {
+    shared: Arc<Shared>,
+}
+
+impl FaultControl {
+    /// Start or stop injecting faults. Bytes pass through untouched while
+    /// disabled.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.shared.disabled.store(!enabled, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> FaultStats {
+        let s = &self.shared;
+        FaultStats {
+            bytes_read: s.bytes_read.load(Ordering::Relaxed),
+            bits_flipped: s.bits_flipped.load(Ordering::Relaxed),
+            bytes_dropped: s.bytes_dropped.load(Ordering::Relaxed),
+            latency_spikes: s.latency_spikes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Reader that injects faults into the bytes read through it.
+pub struct FaultyReader<R> {
+    inner: R,
+    config: FaultConfig,
+    rng: u64,
+    shared: Arc<Shared>,
+    /// Spike in progress, if any.
+    delay: Option<Pin<Box<Sleep>>>,
+    /// Whether this read has had its chance of a spike.
+    spike_rolled: bool,
+}
+
+impl<R> FaultyReader<R> {
+    pub fn new(inner: R, config: FaultConfig) -> Self {
+        Self {
+            inner,
+            config,
+            // xorshift gets stuck on zero
+            rng: config.seed.max(1),
+            shared: Arc::new(Shared::default()),
+            delay: None,
+            spike_rolled: false,
+        }
+    }
+
+    pub fn control(&self) -> FaultControl {
+        FaultControl {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    /// True with probability `rate`.
+    fn roll(&mut self, rate: f64) -> bool {
+        if rate <= 0.0 {
+            return false;
+        }
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        let sample = (self.rng >> 11) as f64 / (1u64 << 53) as f64;
+        sample < rate
+    }
+
+    fn corrupt(&mut self, byte: u8) -> Option<u8> {
+        if self.roll(self.config.drop_rate) {
+            self.shared.bytes_dropped.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        let mut byte = byte;
+        for bit in 0..8 {
+            if self.roll(self.config.bit_error_rate) {
+                byte ^= 1 << bit;
+                self.shared.bits_flipped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        Some(byte)
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for FaultyReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let enabled = !this.shared.disabled.load(Ordering::Relaxed);
+
+        loop {
+            if enabled && !this.spike_rolled {
+                this.spike_rolled = true;
+                if this.roll(this.config.latency_spike_rate) {
+                    this.shared.latency_spikes.fetch_add(1, Ordering::Relaxed);
+                    this.delay = Some(Box::pin(tokio::time::sleep(this.config.latency_spike)));
+                }
+            }
+            if let Some(delay) = this.delay.as_mut() {
+                ready!(delay.as_mut().poll(cx));
+                this.delay = None;
+            }
+
+            let mut chunk = [0u8; CHUNK_LEN];
+            let mut read = ReadBuf::new(&mut chunk[..buf.remaining().min(CHUNK_LEN)]);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read))?;
+            this.spike_rolled = false;
+
+            let read = read.filled();
+            if read.is_empty() {
+                // End of stream
+                return Poll::Ready(Ok(()));
+            }
+            this.shared
+                .bytes_read
+                .fetch_add(read.len() as u64, Ordering::Relaxed);
+
+            let before = buf.filled().len();
+            for &byte in read {
+                let byte = if enabled {
+                    this.corrupt(byte)
+                } else {
+                    Some(byte)
+                };
+                if let Some(byte) = byte {
+                    buf.put_slice(&[byte]);
+                }
+            }
+
+            // An empty read would look like end of stream, so if every
+            // byte was dropped, read again
+            if buf.filled().len() > before {
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    async fn read_through(config: FaultConfig, data: &[u8]) -> (Vec<u8>, FaultStats) {
+        let (mut tx, rx) = tokio::io::duplex(4096);
+        tx.write_all(data).await.unwrap();
+        drop(tx);
+
+        let mut reader = FaultyReader::new(rx, config);
+        let control = reader.control();
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).await.unwrap();
+        (out, control.stats())
+    }
+
+    #[tokio::test]
+    async fn default_config_passes_bytes_through() {
+        let data: Vec<u8> = (0..=255).collect();
+        let (out, stats) = read_through(FaultConfig::default(), &data).await;
+        assert_eq!(out, data);
+        assert_eq!(
+            stats,
+            FaultStats {
+                bytes_read: 256,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn injects_errors_at_roughly_the_configured_rate() {
+        let data = vec![0u8; 4000];
+        let config = FaultConfig {
+            bit_error_rate: 0.01,
+            drop_rate: 0.05,
+            ..Default::default()
+        };
+        let (out, stats) = read_through(config, &data).await;
+
+        assert_eq!(out.len() as u64, 4000 - stats.bytes_dropped);
+        assert!((100..300).contains(&stats.bytes_dropped), "{stats:?}");
+        // 0.01 of ~30000 bits
+        assert!((200..400).contains(&stats.bits_flipped), "{stats:?}");
+        let ones: u32 = out.iter().map(|b| b.count_ones()).sum();
+        assert_eq!(u64::from(ones), stats.bits_flipped);
+
+        // Same seed, same faults
+        assert_eq!(read_through(config, &data).await.0, out);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn latency_spikes_delay_reads() {
+        let config = FaultConfig {
+            latency_spike_rate: 1.0,
+            latency_spike: Duration::from_secs(2),
+            ..Default::default()
+        };
+        let start = tokio::time::Instant::now();
+        let (out, stats) = read_through(config, b"abc").await;
+        assert_eq!(out, b"abc");
+        assert!(stats.latency_spikes >= 1);
+        assert!(start.elapsed() >= Duration::from_secs(2));
+    }
+}
@@ -5,12 +5,18 @@
 //! implementation provides device discovery and emits transport-specific
 //! events when devices are connected or disconnected.
 
+pub mod capture;
 pub mod cpu;
+#[cfg(any(test, feature = "fault-injection"))]
+pub mod fault;
+pub mod network;
 pub mod serial;
 pub mod usb;
 
 // Re-export transport implementations
+pub use capture::CaptureTap;
 pub use cpu::CpuDeviceInfo;
+pub use network::{LinkLatency, NetworkDeviceInfo, NetworkEndpoint, NetworkTransport};
 pub use serial::{
     Parity, SerialConfig, SerialControl, SerialError, SerialReader, SerialStats, SerialStream,
     SerialWriter,
@@ -25,6 +31,12 @@ pub enum TransportEvent {
 
     /// CPU miner virtual device event
     Cpu(cpu::TransportEvent),
+
+    /// Network-attached board event
+    Network(network::TransportEvent),
+
+    /// Simulated board enabled by configuration
+    Simulated { device_id: String },
 }
 
 /// Common trait for transport discovery (future enhancement).
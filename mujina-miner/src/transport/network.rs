@@ -0,0 +1,380 @@
+//! Network transport for boards reached over TCP.
+//!
+//! A hashboard needn't be plugged into the host: a remote agent can serve
+//! it over the network. Such boards aren't discovered but configured, as
+//! [`NetworkEndpoint`]s naming the kind of board and where it listens.
+//! [`NetworkTransport`] probes each endpoint with a TCP connect every
+//! [`PROBE_INTERVAL`] and reports it connected when it first answers.
+//!
+//! Links drop and come back, so an endpoint that stops answering is
+//! reported [lost](TransportEvent::NetworkDeviceLost) rather than removed.
+//! The backplane keeps the board through a grace period and carries on
+//! with it if the endpoint answers again in time, instead of tearing its
+//! hash threads down for every blip.
+//!
+//! Each probe also times the connect, which takes one round trip. The
+//! smoothed result is kept in the device's [`LinkLatency`], which boards
+//! hand on to their hash threads as a scheduling hint.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::tracing::prelude::*;
+
+/// How often each endpoint is probed.
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long a probe waits for the connect to complete.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Failed probes in a row before an endpoint is reported lost.
+const PROBE_FAILURES_LOST: u32 = 3;
+
+/// How long the backplane keeps a lost board for its link to come back,
+/// unless configured otherwise.
+pub const DEFAULT_RECONNECT_GRACE: Duration = Duration::from_secs(30);
+
+/// Transport events for network-attached boards.
+#[derive(Debug)]
+pub enum TransportEvent {
+    /// An endpoint answered, for the first time or after being lost.
+    NetworkDeviceConnected(NetworkDeviceInfo),
+
+    /// An endpoint stopped answering; it may come back.
+    NetworkDeviceLost { device_id: String },
+}
+
+/// A configured network board: its kind and address, as `kind@host:port`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct NetworkEndpoint {
+    /// Kind of board, matching a registered network board descriptor
+    pub kind: String,
+    /// Host and port the board is served on
+    pub addr: String,
+}
+
+impl NetworkEndpoint {
+    /// Stable identifier for the board behind this endpoint.
+    ///
+    /// Boards use it in their API names, so it is kept URL-friendly.
+    pub fn device_id(&self) -> String {
+        let addr: String = self
+            .addr
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        format!("{}-{}", self.kind, addr)
+    }
+}
+
+impl FromStr for NetworkEndpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, addr) = s
+            .split_once('@')
+            .ok_or_else(|| "expected kind@host:port".to_string())?;
+        if kind.is_empty() {
+            return Err("missing board kind before '@'".into());
+        }
+        match addr.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {}
+            _ => return Err(format!("expected host:port, got {addr:?}")),
+        }
+        Ok(Self {
+            kind: kind.to_string(),
+            addr: addr.to_string(),
+        })
+    }
+}
+
+impl TryFrom<String> for NetworkEndpoint {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<NetworkEndpoint> for String {
+    fn from(endpoint: NetworkEndpoint) -> Self {
+        endpoint.to_string()
+    }
+}
+
+impl fmt::Display for NetworkEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.kind, self.addr)
+    }
+}
+
+/// Information about a network board that answered.
+#[derive(Debug, Clone)]
+pub struct NetworkDeviceInfo {
+    /// Unique identifier, derived from the endpoint
+    pub device_id: String,
+    pub endpoint: NetworkEndpoint,
+    /// Round trip to the board, kept up to date while it's connected
+    pub latency: LinkLatency,
+}
+
+/// Smoothed round-trip time of a link; clones share the measurement.
+///
+/// The transport records a sample with every probe, and a board with a
+/// better measure of its own (a protocol-level ping, say) may record
+/// those too.
+#[derive(Debug, Clone, Default)]
+pub struct LinkLatency {
+    /// Smoothed round trip in microseconds; 0 before the first sample
+    micros: Arc<AtomicU64>,
+}
+
+impl LinkLatency {
+    /// Fold a round-trip sample into the estimate.
+    pub fn record(&self, rtt: Duration) {
+        let sample = (rtt.as_micros() as u64).max(1);
+        let _ = self
+            .micros
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |old| {
+                // One eighth of each new sample, as TCP smooths its RTT
+                Some(if old == 0 {
+                    sample
+                } else {
+                    (old * 7 + sample) / 8
+                })
+            });
+    }
+
+    /// Current estimate, or `None` before the first sample.
+    pub fn get(&self) -> Option<Duration> {
+        match self.micros.load(Ordering::Relaxed) {
+            0 => None,
+            micros => Some(Duration::from_micros(micros)),
+        }
+    }
+}
+
+/// Whether an endpoint is up, from the outcomes of its probes.
+#[derive(Debug, Default)]
+struct LinkMonitor {
+    up: bool,
+    failures: u32,
+}
+
+/// What a probe outcome changed.
+#[derive(Debug, PartialEq, Eq)]
+enum LinkChange {
+    Up,
+    Lost,
+}
+
+impl LinkMonitor {
+    fn observe(&mut self, answered: bool) -> Option<LinkChange> {
+        if answered {
+            self.failures = 0;
+            if self.up {
+                return None;
+            }
+            self.up = true;
+            return Some(LinkChange::Up);
+        }
+
+        self.failures += 1;
+        if self.up && self.failures >= PROBE_FAILURES_LOST {
+            self.up = false;
+            return Some(LinkChange::Lost);
+        }
+        None
+    }
+}
+
+/// Watches configured network boards and reports them to the backplane.
+pub struct NetworkTransport {
+    event_tx: mpsc::Sender<super::TransportEvent>,
+    probe_interval: Duration,
+}
+
+impl NetworkTransport {
+    /// Create a new network transport.
+    pub fn new(event_tx: mpsc::Sender<super::TransportEvent>) -> Self {
+        Self {
+            event_tx,
+            probe_interval: PROBE_INTERVAL,
+        }
+    }
+
+    /// Probe endpoints this often instead of every [`PROBE_INTERVAL`].
+    pub fn with_probe_interval(mut self, interval: Duration) -> Self {
+        self.probe_interval = interval;
+        self
+    }
+
+    /// Start probing each endpoint in a task of its own.
+    ///
+    /// The tasks run until `shutdown` is cancelled.
+    pub fn start_discovery(&self, endpoints: Vec<NetworkEndpoint>, shutdown: CancellationToken) {
+        for endpoint in endpoints {
+            let event_tx = self.event_tx.clone();
+            let shutdown = shutdown.clone();
+            let interval = self.probe_interval;
+            let span = info_span!("network", endpoint = %endpoint);
+            tokio::spawn(
+                async move {
+                    tokio::select! {
+                        _ = watch_endpoint(endpoint, interval, event_tx) => {}
+                        _ = shutdown.cancelled() => {}
+                    }
+                }
+                .instrument(span),
+            );
+        }
+    }
+}
+
+/// Probe one endpoint forever, reporting it as it comes and goes.
+async fn watch_endpoint(
+    endpoint: NetworkEndpoint,
+    interval: Duration,
+    event_tx: mpsc::Sender<super::TransportEvent>,
+) {
+    let info = NetworkDeviceInfo {
+        device_id: endpoint.device_id(),
+        endpoint,
+        latency: LinkLatency::default(),
+    };
+    let mut monitor = LinkMonitor::default();
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        let rtt = probe(&info.endpoint.addr).await;
+        if let Some(rtt) = rtt {
+            info.latency.record(rtt);
+        }
+
+        let event = match monitor.observe(rtt.is_some()) {
+            Some(LinkChange::Up) => {
+                info!(latency = ?info.latency.get(), "Network board answering");
+                TransportEvent::NetworkDeviceConnected(info.clone())
+            }
+            Some(LinkChange::Lost) => {
+                warn!("Network board stopped answering");
+                TransportEvent::NetworkDeviceLost {
+                    device_id: info.device_id.clone(),
+                }
+            }
+            None => continue,
+        };
+        if event_tx
+            .send(super::TransportEvent::Network(event))
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Time a TCP connect to `addr`, or `None` if it fails.
+async fn probe(addr: &str) -> Option<Duration> {
+    let start = Instant::now();
+    match tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect(addr)).await {
+        Ok(Ok(_stream)) => Some(start.elapsed()),
+        Ok(Err(e)) => {
+            debug!(error = %e, "Probe failed");
+            None
+        }
+        Err(_) => {
+            debug!("Probe timed out");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn parses_endpoints() {
+        let endpoint: NetworkEndpoint = "agent@10.0.0.5:4029".parse().unwrap();
+        assert_eq!(endpoint.kind, "agent");
+        assert_eq!(endpoint.addr, "10.0.0.5:4029");
+        assert_eq!(endpoint.to_string(), "agent@10.0.0.5:4029");
+        assert_eq!(endpoint.device_id(), "agent-10-0-0-5-4029");
+
+        assert!("10.0.0.5:4029".parse::<NetworkEndpoint>().is_err());
+        assert!("@10.0.0.5:4029".parse::<NetworkEndpoint>().is_err());
+        assert!("agent@10.0.0.5".parse::<NetworkEndpoint>().is_err());
+        assert!("agent@:4029".parse::<NetworkEndpoint>().is_err());
+    }
+
+    #[test]
+    fn latency_is_smoothed() {
+        let latency = LinkLatency::default();
+        assert_eq!(latency.get(), None);
+        latency.record(Duration::from_millis(8));
+        assert_eq!(latency.get(), Some(Duration::from_millis(8)));
+        latency.record(Duration::from_millis(16));
+        assert_eq!(latency.get(), Some(Duration::from_millis(9)));
+    }
+
+    #[test]
+    fn link_is_lost_only_after_repeated_failures() {
+        let mut monitor = LinkMonitor::default();
+        // Down from the start is not a loss
+        assert_eq!(monitor.observe(false), None);
+        assert_eq!(monitor.observe(true), Some(LinkChange::Up));
+        assert_eq!(monitor.observe(true), None);
+
+        // A blip is ridden out
+        assert_eq!(monitor.observe(false), None);
+        assert_eq!(monitor.observe(true), None);
+
+        for _ in 1..PROBE_FAILURES_LOST {
+            assert_eq!(monitor.observe(false), None);
+        }
+        assert_eq!(monitor.observe(false), Some(LinkChange::Lost));
+        assert_eq!(monitor.observe(false), None);
+        assert_eq!(monitor.observe(true), Some(LinkChange::Up));
+    }
+
+    #[tokio::test]
+    async fn reports_an_answering_endpoint_with_its_latency() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = NetworkEndpoint {
+            kind: "agent".into(),
+            addr: listener.local_addr().unwrap().to_string(),
+        };
+        let (event_tx, mut event_rx) = mpsc::channel(4);
+        let shutdown = CancellationToken::new();
+        NetworkTransport::new(event_tx)
+            .with_probe_interval(Duration::from_millis(10))
+            .start_discovery(vec![endpoint.clone()], shutdown.clone());
+
+        let event = tokio::time::timeout(Duration::from_secs(5), event_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        match event {
+            super::super::TransportEvent::Network(TransportEvent::NetworkDeviceConnected(info)) => {
+                assert_eq!(info.device_id, endpoint.device_id());
+                assert!(info.latency.get().is_some());
+            }
+            other => panic!("unexpected event: {other:?}"),
+        }
+        shutdown.cancel();
+    }
+}
@@ -32,6 +32,8 @@ use rustix::termios::{ControlModes, tcdrain, tcgetattr, tcsetattr};
 use tokio::io::unix::AsyncFd;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
+use super::capture::{CaptureTap, Direction};
+
 /// Parity configuration.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Parity {
@@ -117,6 +119,9 @@ struct SerialInner {
     /// Statistics (lock-free)
     bytes_read: AtomicU64,
     bytes_written: AtomicU64,
+
+    /// Records the bytes crossing the link, if capturing
+    capture: Option<CaptureTap>,
 }
 
 /// Reader half of a split serial stream.
@@ -255,10 +260,20 @@ impl SerialStream {
                 reconfig_lock: RwLock::new(()),
                 bytes_read: AtomicU64::new(0),
                 bytes_written: AtomicU64::new(0),
+                capture: None,
             }),
         })
     }
 
+    /// Record every byte read or written to `tap`.
+    pub fn with_capture(mut self, tap: CaptureTap) -> Self {
+        // Not yet split, so nothing else holds the inner state
+        Arc::get_mut(&mut self.inner)
+            .expect("stream not yet split")
+            .capture = Some(tap);
+        self
+    }
+
     /// Split the stream into reader, writer, and control handles.
     ///
     /// This allows concurrent reading and writing while maintaining the ability
@@ -307,6 +322,7 @@ impl SerialStream {
                 reconfig_lock: RwLock::new(()),
                 bytes_read: AtomicU64::new(0),
                 bytes_written: AtomicU64::new(0),
+                capture: None,
             }),
         })
     }
@@ -329,6 +345,10 @@ impl AsyncRead for SerialReader {
                 let fd_ref = unsafe { BorrowedFd::borrow_raw(fd) };
                 match rustix::io::read(fd_ref, slice) {
                     Ok(n) => {
+                        if let Some(ref tap) = self.inner.capture {
+                            let baud_rate = self.inner.baud_rate.load(Ordering::Acquire);
+                            tap.record(Direction::Rx, baud_rate, &slice[..n]);
+                        }
                         buf.advance(n);
                         if n > 0 {
                             self.inner.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
@@ -380,6 +400,10 @@ impl AsyncWrite for SerialWriter {
                                 .bytes_written
                                 .fetch_add(n as u64, Ordering::Relaxed);
                         }
+                        if let Some(ref tap) = self.inner.capture {
+                            let baud_rate = self.inner.baud_rate.load(Ordering::Acquire);
+                            tap.record(Direction::Tx, baud_rate, &buf[..n]);
+                        }
                         Ok(n)
                     }
                     Err(rustix::io::Errno::AGAIN) => {
@@ -772,6 +796,38 @@ mod tests {
         assert_eq!(control.current_baud_rate(), 123456);
     }
 
+    #[tokio::test]
+    #[cfg_attr(
+        feature = "skip-pty-tests",
+        ignore = "PTY tests skipped via feature flag"
+    )]
+    async fn test_capture_records_both_directions() {
+        use crate::transport::capture::write_capture;
+        use test_support::create_virtual_pair;
+
+        let (stream_a, stream_b) = create_virtual_pair().await.unwrap();
+        let (tap, chunks) = CaptureTap::queued();
+        let (mut reader_a, mut writer_a, control_a) = stream_a.with_capture(tap).split();
+        let (_reader_b, mut writer_b, _control_b) = stream_b.split();
+
+        writer_a.write_all(&[0x55, 0xaa]).await.unwrap();
+        writer_b.write_all(&[0x13]).await.unwrap();
+        let mut buf = [0u8; 1];
+        reader_a.read_exact(&mut buf).await.unwrap();
+
+        // The tap goes with the last handle on the stream
+        drop((reader_a, writer_a, control_a));
+        let mut out = Vec::new();
+        write_capture(&mut out, chunks).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let rows: Vec<&str> = text.lines().skip(1).collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].starts_with("CI Async Serial 115k,data,"));
+        assert!(rows[1].ends_with(",0xAA,"));
+        assert!(rows[2].starts_with("RO Async Serial 115k,data,"));
+        assert!(rows[2].ends_with(",0x13,"));
+    }
+
     #[tokio::test]
     async fn test_stats_tracking() {
         use test_support::create_virtual_pair;
@@ -8,9 +8,19 @@
 //!
 //! - **Linux**: Uses udev for device enumeration and hotplug monitoring
 //! - **macOS**: Stub implementation (IOKit support planned for future)
+//!
+//! ## Rescanning
+//!
+//! Hotplug events don't always reach the miner. In a container the udev
+//! netlink socket usually stays silent, so devices mapped in with
+//! `--device` after startup would never be seen. Discovery therefore also
+//! rescans connected devices every [`RESCAN_INTERVAL`] and reports any
+//! changes the event stream missed.
 
 use crate::{error::Result, tracing::prelude::*};
+use std::collections::HashSet;
 use std::sync::OnceLock;
+use std::time::Duration;
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
@@ -62,6 +72,36 @@ impl UsbDeviceInfo {
             .map_err(|e| crate::error::Error::Other(e.to_string()))
     }
 
+    /// Stable identifier for the board behind this device.
+    ///
+    /// This is the USB serial number when the device reports one. Otherwise
+    /// it is synthesized from the USB topology (bus and port path), which
+    /// stays the same for as long as the board is plugged into the same
+    /// port. Boards use it in their API names, so it is kept URL-friendly.
+    pub fn board_id(&self) -> String {
+        match self.serial_number.as_deref().map(str::trim) {
+            Some(serial) if !serial.is_empty() => serial.to_string(),
+            _ => {
+                let port = self
+                    .device_path
+                    .rsplit('/')
+                    .find(|c| !c.is_empty())
+                    .unwrap_or("unknown");
+                let port: String = port
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                            c
+                        } else {
+                            '-'
+                        }
+                    })
+                    .collect();
+                format!("usb-{port}")
+            }
+        }
+    }
+
     /// Create a UsbDeviceInfo for testing purposes.
     ///
     /// Serial ports are not scanned and will be empty when accessed.
@@ -96,6 +136,54 @@ pub enum TransportEvent {
     UsbDeviceDisconnected { device_path: String },
 }
 
+/// How often discovery rescans connected devices.
+pub const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Device paths already reported as connected.
+///
+/// Hotplug events and rescans both report devices; this keeps each device
+/// from being announced twice and turns rescans into connect and
+/// disconnect events.
+#[derive(Debug, Default)]
+struct KnownDevices {
+    paths: HashSet<String>,
+}
+
+impl KnownDevices {
+    /// Record a connected device; false if it was already known.
+    fn insert(&mut self, device_path: &str) -> bool {
+        self.paths.insert(device_path.to_string())
+    }
+
+    /// Forget a disconnected device; false if it was never known.
+    fn remove(&mut self, device_path: &str) -> bool {
+        self.paths.remove(device_path)
+    }
+
+    /// Reconcile with a fresh enumeration, returning the changes.
+    fn rescan(&mut self, devices: Vec<UsbDeviceInfo>) -> Vec<TransportEvent> {
+        let current: HashSet<String> = devices.iter().map(|d| d.device_path.clone()).collect();
+
+        let mut gone: Vec<String> = self.paths.difference(&current).cloned().collect();
+        gone.sort();
+        let mut events: Vec<TransportEvent> = gone
+            .into_iter()
+            .map(|device_path| {
+                self.paths.remove(&device_path);
+                TransportEvent::UsbDeviceDisconnected { device_path }
+            })
+            .collect();
+
+        events.extend(
+            devices
+                .into_iter()
+                .filter(|d| self.insert(&d.device_path))
+                .map(TransportEvent::UsbDeviceConnected),
+        );
+        events
+    }
+}
+
 /// USB transport discovery.
 pub struct UsbTransport {
     event_tx: mpsc::Sender<super::TransportEvent>,
@@ -155,6 +243,14 @@ impl UsbTransport {
 
         Ok(())
     }
+
+    /// List the USB devices connected right now.
+    ///
+    /// One-shot enumeration without monitoring, for tools that inspect the
+    /// system and exit.
+    pub fn enumerate() -> Result<Vec<UsbDeviceInfo>> {
+        create_discovery()?.enumerate()
+    }
 }
 
 // Platform-specific implementations
@@ -172,6 +268,9 @@ mod macos;
 /// udev's raw C pointers. The synchronous API is used since USB hotplug events
 /// are infrequent and don't benefit from async overhead.
 trait UsbDiscoveryImpl: Send + Sync {
+    /// Enumerate currently connected devices.
+    fn enumerate(&self) -> Result<Vec<UsbDeviceInfo>>;
+
     /// Monitor for USB device add/remove events (blocking).
     ///
     /// This method runs in a dedicated thread and blocks until an error occurs,
@@ -183,8 +282,9 @@ trait UsbDiscoveryImpl: Send + Sync {
     ///
     /// 1. Perform initial enumeration and send Connected events for existing devices
     /// 2. Enter blocking monitoring loop for hotplug events
-    /// 3. Check shutdown token periodically (at least on each event)
-    /// 4. Exit gracefully on shutdown, channel closure, or fatal error
+    /// 3. Rescan every [`RESCAN_INTERVAL`], reporting devices the events missed
+    /// 4. Check shutdown token periodically (at least on each event)
+    /// 5. Exit gracefully on shutdown, channel closure, or fatal error
     ///
     /// # Parameters
     ///
@@ -217,3 +317,66 @@ fn create_discovery() -> Result<Box<dyn UsbDiscoveryImpl>> {
         compile_error!("USB discovery is not implemented for this platform");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(serial: Option<&str>, path: &str) -> UsbDeviceInfo {
+        UsbDeviceInfo::new_for_test(
+            0xc0de,
+            0xcafe,
+            serial.map(String::from),
+            None,
+            None,
+            path.to_string(),
+        )
+    }
+
+    fn paths(events: &[TransportEvent]) -> Vec<String> {
+        events
+            .iter()
+            .map(|e| match e {
+                TransportEvent::UsbDeviceConnected(d) => format!("+{}", d.device_path),
+                TransportEvent::UsbDeviceDisconnected { device_path } => format!("-{device_path}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rescan_reports_only_changes() {
+        let mut known = KnownDevices::default();
+        assert!(known.insert("/sys/usb1/1-1"));
+        assert!(!known.insert("/sys/usb1/1-1"));
+
+        let events = known.rescan(vec![
+            device(None, "/sys/usb1/1-1"),
+            device(None, "/sys/usb1/1-2"),
+        ]);
+        assert_eq!(paths(&events), ["+/sys/usb1/1-2"]);
+
+        let events = known.rescan(vec![device(None, "/sys/usb1/1-2")]);
+        assert_eq!(paths(&events), ["-/sys/usb1/1-1"]);
+        assert!(known.rescan(vec![device(None, "/sys/usb1/1-2")]).is_empty());
+
+        assert!(known.remove("/sys/usb1/1-2"));
+        assert!(!known.remove("/sys/usb1/1-2"));
+    }
+
+    #[test]
+    fn board_id_prefers_serial_number() {
+        let dev = device(Some("e2f56f9b"), "/sys/devices/usb1/1-1/1-1.2");
+        assert_eq!(dev.board_id(), "e2f56f9b");
+    }
+
+    #[test]
+    fn board_id_falls_back_to_usb_port_path() {
+        let a = device(None, "/sys/devices/pci0000:00/0000:00:14.0/usb1/1-1/1-1.2");
+        let b = device(
+            Some("  "),
+            "/sys/devices/pci0000:00/0000:00:14.0/usb1/1-1/1-1.3",
+        );
+        assert_eq!(a.board_id(), "usb-1-1.2");
+        assert_eq!(b.board_id(), "usb-1-1.3");
+    }
+}
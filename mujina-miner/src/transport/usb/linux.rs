@@ -22,6 +22,7 @@
 //! - Extract VID/PID/serial number from device attributes
 //! - Find associated serial port (tty) devices
 //! - Monitor for add/remove events via async udev socket
+//! - Periodically re-enumerate to catch devices whose events never arrived
 //!
 //! ## Serial Port Ordering
 //!
@@ -30,7 +31,7 @@
 //! reconnections. This is critical for boards that expect a specific port for
 //! control vs data communication.
 
-use super::{TransportEvent as UsbEvent, UsbDeviceInfo};
+use super::{KnownDevices, RESCAN_INTERVAL, TransportEvent as UsbEvent, UsbDeviceInfo};
 use crate::{error::Result, tracing::prelude::*, transport::TransportEvent};
 use futures::stream::StreamExt;
 use tokio::sync::mpsc;
@@ -188,9 +189,10 @@ impl LinuxUdevDiscovery {
             serial_ports: std::sync::OnceLock::new(),
         })
     }
+}
 
-    /// Enumerate currently connected USB devices.
-    fn enumerate_devices(&self) -> Result<Vec<UsbDeviceInfo>> {
+impl super::UsbDiscoveryImpl for LinuxUdevDiscovery {
+    fn enumerate(&self) -> Result<Vec<UsbDeviceInfo>> {
         // Create enumerator for USB devices
         let mut enumerator = udev::Enumerator::new().map_err(|e| {
             crate::error::Error::Other(format!("Failed to create enumerator: {}", e))
@@ -225,9 +227,7 @@ impl LinuxUdevDiscovery {
 
         Ok(devices)
     }
-}
 
-impl super::UsbDiscoveryImpl for LinuxUdevDiscovery {
     fn monitor_blocking(
         self: Box<Self>,
         event_tx: mpsc::Sender<crate::transport::TransportEvent>,
@@ -250,8 +250,11 @@ impl super::UsbDiscoveryImpl for LinuxUdevDiscovery {
 
         // Run the async monitoring loop on this thread's runtime
         runtime.block_on(async {
+            let mut known = KnownDevices::default();
+
             // Initial enumeration - send Connected events for existing devices
-            for device_info in self.enumerate_devices()? {
+            for device_info in self.enumerate()? {
+                known.insert(&device_info.device_path);
                 let usb_event = UsbEvent::UsbDeviceConnected(device_info);
                 let transport_event = TransportEvent::Usb(usb_event);
 
@@ -279,8 +282,13 @@ impl super::UsbDiscoveryImpl for LinuxUdevDiscovery {
                 crate::error::Error::Other(format!("Failed to create async socket: {}", e))
             })?;
 
-            // Event loop using tokio::select! to wait on both events and shutdown
-            // This is clean, safe async code with no manual polling required
+            let mut rescan = tokio::time::interval_at(
+                tokio::time::Instant::now() + RESCAN_INTERVAL,
+                RESCAN_INTERVAL,
+            );
+
+            // Event loop using tokio::select! to wait on events, rescans,
+            // and shutdown
             loop {
                 tokio::select! {
                     // Wait for USB hotplug event
@@ -308,6 +316,8 @@ impl super::UsbDiscoveryImpl for LinuxUdevDiscovery {
                                 }
 
                                 match self.build_device_info(&device) {
+                                    // Already picked up by a rescan
+                                    Ok(device_info) if !known.insert(&device_info.device_path) => None,
                                     Ok(device_info) => {
                                         debug!(
                                             vid = %format!("{:04x}", device_info.vid),
@@ -326,9 +336,13 @@ impl super::UsbDiscoveryImpl for LinuxUdevDiscovery {
                             }
 
                             tokio_udev::EventType::Remove => {
-                                device.syspath().to_str().map(|syspath| UsbEvent::UsbDeviceDisconnected {
-                                    device_path: syspath.to_string(),
-                                })
+                                device
+                                    .syspath()
+                                    .to_str()
+                                    .filter(|syspath| known.remove(syspath))
+                                    .map(|syspath| UsbEvent::UsbDeviceDisconnected {
+                                        device_path: syspath.to_string(),
+                                    })
                             }
 
                             // Ignore other event types (change, bind, unbind, etc.)
@@ -345,6 +359,33 @@ impl super::UsbDiscoveryImpl for LinuxUdevDiscovery {
                         }
                     }
 
+                    // Pick up devices whose hotplug events never arrived,
+                    // as in containers without a udev event socket
+                    _ = rescan.tick() => {
+                        let devices = match self.enumerate() {
+                            Ok(devices) => devices,
+                            Err(e) => {
+                                debug!(error = %e, "USB rescan failed");
+                                continue;
+                            }
+                        };
+
+                        for usb_event in known.rescan(devices) {
+                            if let UsbEvent::UsbDeviceConnected(ref device_info) = usb_event {
+                                debug!(
+                                    vid = %format!("{:04x}", device_info.vid),
+                                    pid = %format!("{:04x}", device_info.pid),
+                                    path = %device_info.device_path,
+                                    "USB device found by rescan"
+                                );
+                            }
+                            if event_tx.send(TransportEvent::Usb(usb_event)).await.is_err() {
+                                info!("Event receiver dropped, exiting USB monitor");
+                                return Ok(());
+                            }
+                        }
+                    }
+
                     // Wait for shutdown signal
                     _ = shutdown.cancelled() => {
                         return Ok(());
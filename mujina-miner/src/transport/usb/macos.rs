@@ -11,6 +11,7 @@
 //! - Map IOKit device properties to UsbDeviceInfo
 //! - Handle macOS-specific device paths and serial port naming
 
+use super::UsbDeviceInfo;
 use crate::{
     error::{Error, Result},
     transport::TransportEvent,
@@ -31,11 +32,19 @@ impl MacOsIoKitDiscovery {
 }
 
 impl super::UsbDiscoveryImpl for MacOsIoKitDiscovery {
+    fn enumerate(&self) -> Result<Vec<UsbDeviceInfo>> {
+        Err(Error::Other(
+            "macOS USB enumeration not yet implemented".into(),
+        ))
+    }
+
     fn monitor_blocking(
         self: Box<Self>,
         _event_tx: mpsc::Sender<TransportEvent>,
         _shutdown: CancellationToken,
     ) -> Result<()> {
-        unimplemented!("macOS USB monitoring not yet implemented")
+        Err(Error::Other(
+            "macOS USB monitoring not yet implemented".into(),
+        ))
     }
 }
@@ -0,0 +1,174 @@
+//! Block-finding effort and luck for solo mining.
+//!
+//! A solo miner only gets paid when it finds a block, so the numbers that
+//! matter are how much work has gone in since the last block relative to
+//! network difficulty ("effort"), and how many blocks have been found
+//! compared to how many the work done would predict ("luck").
+//!
+//! Work is accumulated as a fraction of the expected work per block at
+//! the network difficulty in force when it was done, so effort stays
+//! correct across difficulty adjustments. 100% effort means one block's
+//! worth of expected work; it regularly goes well past that, since block
+//! discovery is a Poisson process.
+
+use std::time::Instant;
+
+use bitcoin::pow::Work;
+
+use super::{BlockHash, Difficulty, Target};
+use crate::u256::U256;
+
+/// Tracks work done against network difficulty.
+#[derive(Debug, Default)]
+pub struct BlockLuck {
+    /// Blocks' worth of expected work since the last block found.
+    effort: f64,
+    /// Blocks' worth of expected work since tracking started.
+    expected_blocks: f64,
+    blocks_found: u64,
+    last_block: Option<Instant>,
+    network_target: Option<Target>,
+}
+
+impl BlockLuck {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Account for one share worth `work` on a job with `network_target`.
+    ///
+    /// Returns true if `hash` also meets the network target, i.e. the share
+    /// is a block. Effort resets to zero when it is.
+    pub fn record_at(
+        &mut self,
+        at: Instant,
+        work: Work,
+        network_target: Target,
+        hash: BlockHash,
+    ) -> bool {
+        let block_work = U256::from(network_target.to_work()).to_f64_approx();
+        if block_work > 0.0 {
+            let fraction = U256::from(work).to_f64_approx() / block_work;
+            self.effort += fraction;
+            self.expected_blocks += fraction;
+        }
+        self.network_target = Some(network_target);
+
+        let is_block = network_target.is_met_by(hash);
+        if is_block {
+            self.blocks_found += 1;
+            self.effort = 0.0;
+            self.last_block = Some(at);
+        }
+        is_block
+    }
+
+    /// Network difficulty of the most recent work, if any.
+    pub fn network_difficulty(&self) -> Option<Difficulty> {
+        self.network_target.map(Difficulty::from_target)
+    }
+
+    /// Work since the last block as a percentage of one block's expected
+    /// work.
+    pub fn effort_percent(&self) -> f64 {
+        self.effort * 100.0
+    }
+
+    /// Number of blocks the work done so far would find on average.
+    pub fn expected_blocks(&self) -> f64 {
+        self.expected_blocks
+    }
+
+    pub fn blocks_found(&self) -> u64 {
+        self.blocks_found
+    }
+
+    /// Blocks found relative to blocks expected, as a percentage.
+    ///
+    /// Above 100% is lucky. `None` before any work has been recorded.
+    pub fn luck_percent(&self) -> Option<f64> {
+        (self.expected_blocks > 0.0)
+            .then(|| self.blocks_found as f64 / self.expected_blocks * 100.0)
+    }
+
+    /// When the last block was found.
+    pub fn last_block(&self) -> Option<Instant> {
+        self.last_block
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::hashes::Hash;
+
+    use super::*;
+
+    /// Hash with the given value in its most significant 8 bytes.
+    fn hash(high: u64) -> BlockHash {
+        let mut bytes = [0u8; 32];
+        bytes[24..].copy_from_slice(&high.to_le_bytes());
+        BlockHash::from_byte_array(bytes)
+    }
+
+    fn share_work(difficulty: u64) -> Work {
+        Difficulty::from(difficulty).to_target().to_work()
+    }
+
+    #[test]
+    fn accumulates_effort_against_network_difficulty() {
+        let now = Instant::now();
+        let network = Difficulty::from(1000).to_target();
+        let mut luck = BlockLuck::new();
+        assert_eq!(luck.luck_percent(), None);
+
+        for _ in 0..5 {
+            assert!(!luck.record_at(now, share_work(100), network, hash(u64::MAX)));
+        }
+
+        assert!((luck.effort_percent() - 50.0).abs() < 0.1);
+        assert!((luck.expected_blocks() - 0.5).abs() < 0.001);
+        assert_eq!(luck.luck_percent(), Some(0.0));
+        assert_eq!(luck.network_difficulty().unwrap().as_u64(), 1000);
+    }
+
+    #[test]
+    fn block_resets_effort_but_not_luck() {
+        let now = Instant::now();
+        let network = Difficulty::from(1000).to_target();
+        let mut luck = BlockLuck::new();
+        for _ in 0..3 {
+            luck.record_at(now, share_work(100), network, hash(u64::MAX));
+        }
+
+        // A hash of all zeros meets any target
+        assert!(luck.record_at(now, share_work(100), network, hash(0)));
+        assert_eq!(luck.effort_percent(), 0.0);
+        assert_eq!(luck.blocks_found(), 1);
+        assert_eq!(luck.last_block(), Some(now));
+
+        // One block for 0.4 blocks of work: 250% luck
+        assert!((luck.luck_percent().unwrap() - 250.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn effort_follows_difficulty_changes() {
+        let now = Instant::now();
+        let mut luck = BlockLuck::new();
+        luck.record_at(
+            now,
+            share_work(100),
+            Difficulty::from(1000).to_target(),
+            hash(u64::MAX),
+        );
+        luck.record_at(
+            now,
+            share_work(100),
+            Difficulty::from(500).to_target(),
+            hash(u64::MAX),
+        );
+
+        // 10% of a block at the old difficulty plus 20% at the new one
+        assert!((luck.effort_percent() - 30.0).abs() < 0.1);
+        assert_eq!(luck.network_difficulty().unwrap().as_u64(), 500);
+    }
+}
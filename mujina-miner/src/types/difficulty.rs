@@ -4,8 +4,25 @@ use crate::u256::U256;
 use bitcoin::hash_types::BlockHash;
 use bitcoin::hashes::Hash;
 use bitcoin::pow::Target;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp::Ordering;
 use std::fmt;
+use std::str::FromStr;
+
+use super::si::{self, SiError};
+
+/// Errors from parsing a difficulty string.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum DifficultyParseError {
+    #[error("invalid difficulty {0:?} (expected a positive number, e.g. 1024, 0.5 or 1.5K)")]
+    InvalidNumber(String),
+
+    #[error("unknown difficulty suffix {0:?} (expected K, M, G, T, P or E)")]
+    UnknownSuffix(String),
+
+    #[error("difficulty {0:?} is too large (at most about 18.4E)")]
+    TooLarge(String),
+}
 
 /// Mining difficulty.
 ///
@@ -182,6 +199,100 @@ impl fmt::Display for Difficulty {
     }
 }
 
+impl FromStr for Difficulty {
+    type Err = DifficultyParseError;
+
+    /// Parse an integer ("1024"), a float ("0.001") or an SI-suffixed
+    /// value ("1.5K", "112T"), as produced by `Display`.
+    ///
+    /// Whole values go through `From<u64>` and are exact; fractional
+    /// ones through [`Difficulty::from_f64`]. Values that don't fit in a
+    /// u64 are rejected rather than saturated.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || DifficultyParseError::InvalidNumber(s.to_string());
+
+        if let Ok(whole) = s.parse::<u64>() {
+            return if whole == 0 {
+                Err(invalid())
+            } else {
+                Ok(Self::from(whole))
+            };
+        }
+
+        let value = match si::parse(s, &[]) {
+            Ok(value) if value > 0.0 => value,
+            Ok(_) | Err(SiError::InvalidNumber) => return Err(invalid()),
+            Err(SiError::UnknownPrefix(suffix)) => {
+                return Err(DifficultyParseError::UnknownSuffix(suffix));
+            }
+            Err(SiError::TooLarge) => return Err(DifficultyParseError::TooLarge(s.to_string())),
+        };
+
+        if value.fract() == 0.0 {
+            Ok(Self::from(value as u64))
+        } else {
+            Ok(Self::from_f64(value))
+        }
+    }
+}
+
+/// Serializes as a number (lossy for non-integer or very large values).
+impl Serialize for Difficulty {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = self.as_f64();
+        if value >= 1.0 && value.fract() == 0.0 && value < u64::MAX as f64 {
+            serializer.serialize_u64(value as u64)
+        } else {
+            serializer.serialize_f64(value)
+        }
+    }
+}
+
+/// Deserializes from an integer, a float or a string accepted by
+/// [`FromStr`].
+impl<'de> Deserialize<'de> for Difficulty {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = Difficulty;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a positive difficulty, as a number or a string like \"1.5K\"")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Difficulty, E> {
+                if v == 0 {
+                    return Err(E::invalid_value(serde::de::Unexpected::Unsigned(v), &self));
+                }
+                Ok(Difficulty::from(v))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Difficulty, E> {
+                match u64::try_from(v) {
+                    Ok(v) => self.visit_u64(v),
+                    Err(_) => Err(E::invalid_value(serde::de::Unexpected::Signed(v), &self)),
+                }
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<Difficulty, E> {
+                if v.is_finite() && v > 0.0 {
+                    Ok(Difficulty::from_f64(v))
+                } else {
+                    Err(E::invalid_value(serde::de::Unexpected::Float(v), &self))
+                }
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Difficulty, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -343,4 +454,65 @@ mod tests {
             assert_eq!(diff, recovered, "Round-trip failed for {}", diff_val);
         }
     }
+
+    #[test]
+    fn test_difficulty_from_str() {
+        assert_eq!("1024".parse(), Ok(Difficulty::from(1024)));
+        assert_eq!("1.5K".parse(), Ok(Difficulty::from(1500)));
+        assert_eq!("1.5k".parse(), Ok(Difficulty::from(1500)));
+        assert_eq!(" 112T ".parse(), Ok(Difficulty::from(112_000_000_000_000)));
+        assert_eq!("2 M".parse(), Ok(Difficulty::from(2_000_000)));
+        assert_eq!("0.5".parse(), Ok(Difficulty::from_f64(0.5)));
+
+        assert!(matches!(
+            "0".parse::<Difficulty>(),
+            Err(DifficultyParseError::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            "-3".parse::<Difficulty>(),
+            Err(DifficultyParseError::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            "5X".parse::<Difficulty>(),
+            Err(DifficultyParseError::UnknownSuffix(_))
+        ));
+        assert_eq!(
+            "2E".parse(),
+            Ok(Difficulty::from(2_000_000_000_000_000_000))
+        );
+        assert!(matches!(
+            "100E".parse::<Difficulty>(),
+            Err(DifficultyParseError::TooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn test_difficulty_display_parses_back() {
+        for value in [1u64, 512, 1_500, 65_536_000, 112_000_000_000_000] {
+            let diff = Difficulty::from(value);
+            let parsed: Difficulty = diff.to_string().parse().unwrap();
+            // Display rounds to three significant figures
+            let ratio = parsed.as_f64() / diff.as_f64();
+            assert!((0.99..=1.01).contains(&ratio), "{} -> {}", diff, parsed);
+        }
+    }
+
+    #[test]
+    fn test_difficulty_serde() {
+        let diff = Difficulty::from(1024);
+        assert_eq!(serde_json::to_string(&diff).unwrap(), "1024");
+        assert_eq!(serde_json::from_str::<Difficulty>("1024").unwrap(), diff);
+        assert_eq!(
+            serde_json::from_str::<Difficulty>("\"1.024K\"").unwrap(),
+            diff
+        );
+
+        let easy = Difficulty::from_f64(0.5);
+        let json = serde_json::to_string(&easy).unwrap();
+        assert_eq!(json, "0.5");
+        assert_eq!(serde_json::from_str::<Difficulty>(&json).unwrap(), easy);
+
+        assert!(serde_json::from_str::<Difficulty>("0").is_err());
+        assert!(serde_json::from_str::<Difficulty>("-1").is_err());
+    }
 }
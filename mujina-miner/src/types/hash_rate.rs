@@ -1,9 +1,28 @@
 //! Hashrate measurement type.
 
+use std::fmt;
 use std::iter::Sum;
-use std::ops::Add;
+use std::ops::{Add, AddAssign, Div, Mul, Sub};
+use std::str::FromStr;
 use std::time::Duration;
 
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::si::{self, SiError};
+
+/// Errors from parsing a hashrate string.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum HashRateParseError {
+    #[error("invalid hashrate number in {0:?}")]
+    InvalidNumber(String),
+
+    #[error("unknown hashrate unit {0:?} (expected e.g. H/s, GH/s, TH/s)")]
+    UnknownUnit(String),
+
+    #[error("hashrate {0:?} is too large (at most 18.4 EH/s)")]
+    TooLarge(String),
+}
+
 /// Hashrate measurement.
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub struct HashRate(pub u64); // hashes per second
@@ -24,6 +43,11 @@ impl HashRate {
         Self((th * 1_000_000_000_000.0) as u64)
     }
 
+    /// Create from petahashes per second
+    pub fn from_petahashes(ph: f64) -> Self {
+        Self((ph * 1e15) as u64)
+    }
+
     /// Get value as megahashes per second
     pub fn as_megahashes(&self) -> f64 {
         self.0 as f64 / 1_000_000.0
@@ -39,6 +63,11 @@ impl HashRate {
         self.0 as f64 / 1_000_000_000_000.0
     }
 
+    /// Get value as petahashes per second
+    pub fn as_petahashes(&self) -> f64 {
+        self.0 as f64 / 1e15
+    }
+
     /// Returns true if the hashrate is zero.
     pub fn is_zero(&self) -> bool {
         self.0 == 0
@@ -50,15 +79,35 @@ impl HashRate {
     }
 
     /// Format as human-readable string with appropriate units
+    ///
+    /// Uses the largest SI prefix that keeps the value at least 1, with
+    /// two decimals ("1.50 TH/s"); rates below 1 kH/s are whole hashes.
     pub fn to_human_readable(&self) -> String {
-        if self.0 >= 1_000_000_000_000 {
-            format!("{:.2} TH/s", self.as_terahashes())
-        } else if self.0 >= 1_000_000_000 {
-            format!("{:.2} GH/s", self.as_gigahashes())
-        } else if self.0 >= 1_000_000 {
-            format!("{:.2} MH/s", self.as_megahashes())
-        } else {
-            format!("{} H/s", self.0)
+        match si::PREFIXES
+            .iter()
+            .find(|(_, scale)| self.0 as f64 >= *scale)
+        {
+            Some((prefix, scale)) => format!("{:.2} {}H/s", self.0 as f64 / scale, prefix),
+            None => format!("{} H/s", self.0),
+        }
+    }
+}
+
+impl FromStr for HashRate {
+    type Err = HashRateParseError;
+
+    /// Parse a hashrate such as "500 GH/s", "1.2TH" or "750000".
+    ///
+    /// The unit is optional (plain hashes per second), and case, the
+    /// trailing "/s" and the "H" are all optional: "1.2t" is 1.2 TH/s.
+    /// Rates that don't fit in a u64 are rejected rather than saturated.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match si::parse(s, &["/s", "h"]) {
+            Ok(value) => Ok(Self(value.round() as u64)),
+            Err(SiError::InvalidNumber) => Err(HashRateParseError::InvalidNumber(s.to_string())),
+            Err(SiError::UnknownPrefix(unit)) => Err(HashRateParseError::UnknownUnit(unit)),
+            Err(SiError::TooLarge) => Err(HashRateParseError::TooLarge(s.to_string())),
         }
     }
 }
@@ -89,18 +138,105 @@ impl Add for HashRate {
     }
 }
 
+impl AddAssign for HashRate {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sub for HashRate {
+    type Output = Self;
+
+    /// Difference between two rates, saturating at zero.
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl Mul<f64> for HashRate {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self {
+        Self((self.0 as f64 * rhs) as u64)
+    }
+}
+
+impl Div<f64> for HashRate {
+    type Output = Self;
+
+    fn div(self, rhs: f64) -> Self {
+        Self((self.0 as f64 / rhs) as u64)
+    }
+}
+
+impl Div for HashRate {
+    type Output = f64;
+
+    /// Ratio of two rates (NaN or infinite if `rhs` is zero).
+    fn div(self, rhs: Self) -> f64 {
+        self.0 as f64 / rhs.0 as f64
+    }
+}
+
 impl Sum for HashRate {
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
         iter.fold(HashRate(0), |acc, x| acc + x)
     }
 }
 
-impl std::fmt::Display for HashRate {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl fmt::Display for HashRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.to_human_readable())
     }
 }
 
+/// Serializes as a plain number of hashes per second.
+impl Serialize for HashRate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.0)
+    }
+}
+
+/// Deserializes from a number of hashes per second or a string with
+/// units, as accepted by [`FromStr`].
+impl<'de> Deserialize<'de> for HashRate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = HashRate;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a hashrate in H/s or a string like \"500 GH/s\"")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<HashRate, E> {
+                Ok(HashRate(v))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<HashRate, E> {
+                u64::try_from(v)
+                    .map(HashRate)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(v), &self))
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<HashRate, E> {
+                if v.is_finite() && v >= 0.0 {
+                    Ok(HashRate(v.round() as u64))
+                } else {
+                    Err(E::invalid_value(serde::de::Unexpected::Float(v), &self))
+                }
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<HashRate, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -142,6 +278,93 @@ mod tests {
         assert_eq!(total, HashRate::from_megahashes(600.0));
     }
 
+    #[test]
+    fn human_readable_picks_si_prefix() {
+        assert_eq!(HashRate(999).to_human_readable(), "999 H/s");
+        assert_eq!(HashRate(1_500).to_human_readable(), "1.50 kH/s");
+        assert_eq!(
+            HashRate::from_megahashes(2.0).to_human_readable(),
+            "2.00 MH/s"
+        );
+        assert_eq!(
+            HashRate::from_petahashes(1.25).to_human_readable(),
+            "1.25 PH/s"
+        );
+        assert_eq!(HashRate(u64::MAX).to_human_readable(), "18.45 EH/s");
+    }
+
+    #[test]
+    fn parse_with_and_without_units() {
+        let cases = [
+            ("500 GH/s", HashRate::from_gigahashes(500.0)),
+            ("1.2TH", HashRate::from_terahashes(1.2)),
+            ("1.2 th/s", HashRate::from_terahashes(1.2)),
+            ("750000", HashRate(750_000)),
+            ("42 H/s", HashRate(42)),
+            ("3k", HashRate(3_000)),
+            (" 2 PH/s ", HashRate::from_petahashes(2.0)),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(input.parse::<HashRate>(), Ok(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn parse_rejects_garbage() {
+        assert!(matches!(
+            "".parse::<HashRate>(),
+            Err(HashRateParseError::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            "-1 GH/s".parse::<HashRate>(),
+            Err(HashRateParseError::InvalidNumber(_))
+        ));
+        assert!(matches!(
+            "5 XH/s".parse::<HashRate>(),
+            Err(HashRateParseError::UnknownUnit(_))
+        ));
+        assert!(matches!(
+            "20 EH/s".parse::<HashRate>(),
+            Err(HashRateParseError::TooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn display_round_trips_through_parse() {
+        let rate = HashRate::from_terahashes(1.5);
+        assert_eq!(rate.to_string().parse::<HashRate>(), Ok(rate));
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = HashRate::from_gigahashes(3.0);
+        let b = HashRate::from_gigahashes(1.0);
+        assert_eq!(a - b, HashRate::from_gigahashes(2.0));
+        assert_eq!(b - a, HashRate(0));
+        assert_eq!(b * 2.5, HashRate::from_gigahashes(2.5));
+        assert_eq!(a / 3.0, b);
+        assert_eq!(a / b, 3.0);
+
+        let mut total = a;
+        total += b;
+        assert_eq!(total, HashRate::from_gigahashes(4.0));
+    }
+
+    #[test]
+    fn serde_number_or_string() {
+        assert_eq!(serde_json::to_string(&HashRate(1234)).unwrap(), "1234");
+        assert_eq!(
+            serde_json::from_str::<HashRate>("1234").unwrap(),
+            HashRate(1234)
+        );
+        assert_eq!(
+            serde_json::from_str::<HashRate>("\"500 GH/s\"").unwrap(),
+            HashRate::from_gigahashes(500.0)
+        );
+        assert!(serde_json::from_str::<HashRate>("-5").is_err());
+        assert!(serde_json::from_str::<HashRate>("\"fast\"").is_err());
+    }
+
     #[test]
     fn sum_empty_iterator() {
         let total: HashRate = std::iter::empty().sum();
@@ -5,23 +5,28 @@
 //! mining-specific types.
 
 mod bitcoin_impls;
+mod block_luck;
 mod debounced_alarm;
 mod difficulty;
 mod hash_rate;
 mod hashrate_estimator;
+mod reading;
 mod share_rate;
+mod si;
 
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::u256::U256;
 
 // Re-export frequently used bitcoin types for convenience
 pub use bitcoin::block::Header as BlockHeader;
 pub use bitcoin::{Amount, BlockHash, Network, Target, Transaction, TxOut, Work};
+pub use block_luck::BlockLuck;
 pub use debounced_alarm::{AlarmStatus, DebouncedAlarm};
-pub use difficulty::Difficulty;
-pub use hash_rate::HashRate;
+pub use difficulty::{Difficulty, DifficultyParseError};
+pub use hash_rate::{HashRate, HashRateParseError};
 pub use hashrate_estimator::HashrateEstimator;
+pub use reading::{Reading, ReadingReceiver, ReadingSender, reading_channel};
 pub use share_rate::ShareRate;
 
 /// Calculate expected shares per second at given difficulty and hashrate.
@@ -77,6 +82,14 @@ pub fn target_for_share_rate(rate: ShareRate, hashrate: HashRate) -> Target {
     Target::from(U256::MAX / hashes_per_share)
 }
 
+/// Milliseconds since the Unix epoch, or 0 if the clock reads earlier.
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
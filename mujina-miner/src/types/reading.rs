@@ -0,0 +1,136 @@
+//! Sensor readings that know how old they are.
+//!
+//! Temperature, fan and power readings travel from the task polling the
+//! sensor to the tasks acting on them over watch channels. A watch
+//! channel keeps its last value forever, so when the polling task dies or
+//! hangs, its consumers would go on acting on the last temperature it
+//! read, however long ago. [`reading_channel`] carries each value with
+//! the time it was read, and [`ReadingReceiver::fresh`] only hands out a
+//! value younger than the caller's limit. A consumer left without one
+//! should act as if the worst were true: fans to full, frequency down.
+//!
+//! A channel that has never carried a value counts as stale once the
+//! limit has passed since it was opened, so a sensor task that dies
+//! before its first reading is caught too.
+
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+/// A value together with when it was read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading<T> {
+    pub value: T,
+    pub at: Instant,
+}
+
+impl<T> Reading<T> {
+    /// A value read just now.
+    pub fn now(value: T) -> Self {
+        Self {
+            value,
+            at: Instant::now(),
+        }
+    }
+
+    /// Time since the value was read.
+    pub fn age(&self) -> Duration {
+        self.at.elapsed()
+    }
+}
+
+/// Create a channel for readings of one sensor (or set of sensors).
+pub fn reading_channel<T>() -> (ReadingSender<T>, ReadingReceiver<T>) {
+    let opened = Instant::now();
+    let (tx, rx) = watch::channel(None);
+    (ReadingSender { tx, opened }, ReadingReceiver { rx, opened })
+}
+
+/// Publishes readings, stamping each with the time it was read.
+#[derive(Debug, Clone)]
+pub struct ReadingSender<T> {
+    tx: watch::Sender<Option<Reading<T>>>,
+    opened: Instant,
+}
+
+impl<T> ReadingSender<T> {
+    /// Publish a value read just now.
+    pub fn publish(&self, value: T) {
+        self.tx.send_replace(Some(Reading::now(value)));
+    }
+
+    /// Another receiver for the same readings.
+    pub fn subscribe(&self) -> ReadingReceiver<T> {
+        ReadingReceiver {
+            rx: self.tx.subscribe(),
+            opened: self.opened,
+        }
+    }
+}
+
+/// Receives readings and judges whether they are still fresh.
+#[derive(Debug, Clone)]
+pub struct ReadingReceiver<T> {
+    rx: watch::Receiver<Option<Reading<T>>>,
+    opened: Instant,
+}
+
+impl<T: Clone> ReadingReceiver<T> {
+    /// The latest reading, however old.
+    pub fn latest(&self) -> Option<Reading<T>> {
+        self.rx.borrow().clone()
+    }
+
+    /// The latest value, if it was read within `max_age`.
+    pub fn fresh(&self, max_age: Duration) -> Option<T> {
+        self.latest()
+            .filter(|reading| reading.age() <= max_age)
+            .map(|reading| reading.value)
+    }
+
+    /// Whether nothing has been read within `max_age`, counting from
+    /// when the channel was opened if nothing was ever read.
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        let last = self.rx.borrow().as_ref().map_or(self.opened, |r| r.at);
+        last.elapsed() > max_age
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_AGE: Duration = Duration::from_secs(30);
+
+    #[tokio::test(start_paused = true)]
+    async fn readings_go_stale_once_the_sender_stops() {
+        let (tx, rx) = reading_channel();
+        assert_eq!(rx.fresh(MAX_AGE), None);
+        assert!(!rx.is_stale(MAX_AGE));
+
+        tx.publish(60.0);
+        tokio::time::sleep(Duration::from_secs(20)).await;
+        assert_eq!(rx.fresh(MAX_AGE), Some(60.0));
+        assert!(!rx.is_stale(MAX_AGE));
+
+        // The sensor task is gone; its last reading stays but ages
+        drop(tx);
+        tokio::time::sleep(Duration::from_secs(20)).await;
+        assert_eq!(rx.fresh(MAX_AGE), None);
+        assert!(rx.is_stale(MAX_AGE));
+        assert_eq!(rx.latest().map(|r| r.value), Some(60.0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_channel_never_written_goes_stale() {
+        let (tx, rx) = reading_channel::<f32>();
+        let late = tx.subscribe();
+        tokio::time::sleep(MAX_AGE * 2).await;
+        assert!(rx.is_stale(MAX_AGE));
+        assert!(late.is_stale(MAX_AGE));
+
+        tx.publish(55.0);
+        assert!(!late.is_stale(MAX_AGE));
+    }
+}
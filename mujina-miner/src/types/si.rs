@@ -0,0 +1,90 @@
+//! SI prefixes shared by the hashrate and difficulty types.
+
+/// SI prefixes and their scale, largest first.
+pub(super) const PREFIXES: [(&str, f64); 6] = [
+    ("E", 1e18),
+    ("P", 1e15),
+    ("T", 1e12),
+    ("G", 1e9),
+    ("M", 1e6),
+    ("k", 1e3),
+];
+
+/// Why a number with an SI prefix didn't parse.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum SiError {
+    /// The number is missing, negative or not finite
+    InvalidNumber,
+    /// The text after the number isn't a known prefix and unit
+    UnknownPrefix(String),
+    /// The scaled value doesn't fit in a u64
+    TooLarge,
+}
+
+/// Parse a non-negative number with an optional SI prefix, such as
+/// "1.5k" or "112 T", returning the scaled value.
+///
+/// The prefix is matched in any case and may be followed by any of
+/// `units`, stripped from the end in order and also in any case: with
+/// `["/s", "h"]`, "1.2 TH/s", "1.2th" and "1.2t" all read the same.
+/// Values of 2^64 or more are rejected, so callers can convert the
+/// result to u64 without saturating.
+pub(super) fn parse(s: &str, units: &[&str]) -> Result<f64, SiError> {
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let mut prefix = unit.to_ascii_lowercase();
+    for suffix in units {
+        if let Some(stripped) = prefix.strip_suffix(&suffix.to_ascii_lowercase()) {
+            prefix.truncate(stripped.len());
+        }
+    }
+    let scale = if prefix.is_empty() {
+        1.0
+    } else {
+        PREFIXES
+            .iter()
+            .find(|(p, _)| p.eq_ignore_ascii_case(&prefix))
+            .map(|(_, scale)| *scale)
+            .ok_or_else(|| SiError::UnknownPrefix(unit.to_string()))?
+    };
+
+    let value = number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite() && *v >= 0.0)
+        .ok_or(SiError::InvalidNumber)?
+        * scale;
+    if value >= u64::MAX as f64 {
+        return Err(SiError::TooLarge);
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixes_and_units_in_any_case() {
+        for input in ["1.5k", "1.5K", "1.5 kH/s", "1.5KH", "1.5kh/S"] {
+            assert_eq!(parse(input, &["/s", "h"]), Ok(1_500.0), "{input}");
+        }
+        assert_eq!(parse("2E", &[]), Ok(2e18));
+        assert_eq!(parse("42", &[]), Ok(42.0));
+    }
+
+    #[test]
+    fn rejects_bad_numbers_prefixes_and_overflow() {
+        assert_eq!(parse("", &[]), Err(SiError::InvalidNumber));
+        assert_eq!(parse("-1k", &[]), Err(SiError::InvalidNumber));
+        assert_eq!(
+            parse("1 kH/s", &[]),
+            Err(SiError::UnknownPrefix("kH/s".into()))
+        );
+        assert_eq!(parse("5X", &[]), Err(SiError::UnknownPrefix("X".into())));
+        assert_eq!(parse("18.5E", &[]), Err(SiError::TooLarge));
+        assert!(parse("18.4E", &[]).is_ok());
+    }
+}